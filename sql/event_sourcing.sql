@@ -12,25 +12,42 @@ CREATE TABLE IF NOT EXISTS deciders
     PRIMARY KEY ("decider", "event")
 );
 
-INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'RestaurantCreated');
-INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'RestaurantNotCreated');
-INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'RestaurantMenuChanged');
-INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'RestaurantMenuNotChanged');
-INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'OrderPlaced');
-INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'OrderNotPlaced');
-INSERT INTO deciders ("decider", "event") VALUES ('Order', 'OrderCreated');
-INSERT INTO deciders ("decider", "event") VALUES ('Order', 'OrderPrepared');
-INSERT INTO deciders ("decider", "event") VALUES ('Order', 'OrderNotCreated');
-INSERT INTO deciders ("decider", "event") VALUES ('Order', 'OrderNotPrepared');
+-- The logical event store of the current session/transaction (`fmodel.store_id`), `default` if not set
+-- The stores share the extension install, but keep fully isolated event streams (the same decider id can exist in every store)
+CREATE OR REPLACE FUNCTION fmodel_store_id() RETURNS TEXT AS
+'
+    SELECT COALESCE(NULLIF(current_setting(''fmodel.store_id'', TRUE), ''''), ''default'')
+'
+    LANGUAGE sql
+    STABLE;
 
+-- The owner of the events appended in the current session/transaction (`fmodel.owner`, e.g. the tenant), the current role if not set
+-- The projection rows are owned by the owner of the event that created them (the row-level security of the projections, see `set_projection_row_security`)
+CREATE OR REPLACE FUNCTION fmodel_owner() RETURNS TEXT AS
+'
+    SELECT COALESCE(NULLIF(current_setting(''fmodel.owner'', TRUE), ''''), current_user)
+'
+    LANGUAGE sql
+    STABLE;
+
+-- The tags of the events appended in the current session/transaction (`fmodel.event_tags`, comma-separated, e.g. `vip-customer,promo:summer24`), none if not set
+CREATE OR REPLACE FUNCTION fmodel_event_tags() RETURNS TEXT[] AS
+'
+    SELECT COALESCE(string_to_array(NULLIF(current_setting(''fmodel.event_tags'', TRUE), ''''), '',''), ''{}'')
+'
+    LANGUAGE sql
+    STABLE;
 
 -- Events
+-- The table is partitioned per decider type (LIST partitioning by `decider`): the events of the (few) restaurants are not scanned together with the (many) orders.
+-- The partition (`events_<decider>`) is created automatically, when the decider type is registered in the `deciders` table.
+-- Filter the queries by `decider` (as well) to prune the partitions.
 CREATE TABLE IF NOT EXISTS events
 (
     -- event name/type. Part of a composite foreign key to `deciders`
     "event"       TEXT    NOT NULL,
     -- event ID. This value is used by the next event as it's `previous_id` value to guard against a Lost-EventModel problem / optimistic locking.
-    "event_id"    UUID    NOT NULL UNIQUE,
+    "event_id"    UUID    NOT NULL,
     -- decider name/type. Part of a composite foreign key to `deciders`
     "decider"     TEXT    NOT NULL,
     -- business identifier for the decider
@@ -40,18 +57,330 @@ CREATE TABLE IF NOT EXISTS events
     -- command ID causing this event
     "command_id"  UUID    NULL,
     -- previous event uuid; null for first event; null does not trigger UNIQUE constraint; we defined a function `check_first_event_for_decider`
-    "previous_id" UUID UNIQUE,
+    "previous_id" UUID,
+    -- per-stream sequence number of the event: 1 for the first event of the stream, incremented by 1 for every next event (the version of the stream). AUTOPOPULATES—DO NOT INSERT
+    "sequence_number" BIGINT NOT NULL      DEFAULT 0,
     -- indicator if the event stream for the `decider_id` is final
     "final"       BOOLEAN NOT NULL         DEFAULT FALSE,
+    -- correlation ID of the (top-level) command handling that appended this event, including the saga-derived events. AUTOPOPULATES—DO NOT INSERT
+    "correlation_id" UUID NULL DEFAULT NULLIF(current_setting('fmodel.correlation_id', TRUE), '')::UUID,
+    -- SHA-256 of the canonical (JSONB) event payload; set only if the deduplication (`fmodel.deduplication`) is enabled. AUTOPOPULATES—DO NOT INSERT
+    "payload_hash" BYTEA  NULL,
     -- The timestamp of the event insertion. AUTOPOPULATES—DO NOT INSERT
     "created_at"  TIMESTAMP WITH TIME ZONE DEFAULT NOW() NOT NULL,
     -- ordering sequence/offset for all events in all deciders. AUTOPOPULATES—DO NOT INSERT
-    "offset"      BIGSERIAL PRIMARY KEY,
+    "offset"      BIGSERIAL,
+    -- the id of the transaction that appended the event. The offsets are assigned in the insertion order, not in the commit order: the subscriptions read the events in the (`transaction_id`, `offset`) order instead. AUTOPOPULATES—DO NOT INSERT
+    "transaction_id" XID8 NOT NULL DEFAULT pg_current_xact_id(),
+    -- the logical event store of the event, see `fmodel.store_id`. AUTOPOPULATES—DO NOT INSERT
+    "store_id"    TEXT    NOT NULL         DEFAULT fmodel_store_id(),
+    -- the owner of the event (the role or the tenant, see `fmodel.owner`); `NULL` for the events appended before the owners were introduced. AUTOPOPULATES—DO NOT INSERT
+    "owner"       TEXT    NULL             DEFAULT fmodel_owner(),
+    -- the tags of the event (e.g. `vip-customer`, `promo:summer24`): the tags of the session (`fmodel.event_tags`), and the tags attached by the event repository. AUTOPOPULATES—DO NOT INSERT
+    "tags"        TEXT[]  NOT NULL         DEFAULT fmodel_event_tags(),
+    -- the unique constraints of the partitioned table include the partition key (`decider`); the previous event is always in the same decider
+    -- the global uniqueness (across the partitions) is guaranteed by the assignment: `offset` by the sequence, `event_id` is a random UUID generated by the event repository, and `previous_id` is the `event_id` of the previous event of the same stream
+    PRIMARY KEY ("offset", "decider"),
+    UNIQUE ("event_id", "decider"),
+    UNIQUE ("previous_id", "decider"),
     FOREIGN KEY ("decider", "event") REFERENCES deciders ("decider", "event")
-);
+) PARTITION BY LIST ("decider");
+
+-- Creates the events partition of the decider type (`events_<decider>`), if it does not exist already
+CREATE OR REPLACE FUNCTION create_events_partition(decider_type TEXT) RETURNS VOID AS
+'
+    DECLARE
+        partition_name TEXT := ''events_'' || lower(regexp_replace(decider_type, ''[^a-zA-Z0-9_]'', ''_'', ''g''));
+    BEGIN
+        IF to_regclass(quote_ident(partition_name)) IS NULL THEN
+            EXECUTE format(''CREATE TABLE %I PARTITION OF events FOR VALUES IN (%L)'', partition_name, decider_type);
+            EXECUTE format(''CREATE OR REPLACE RULE ignore_delete_events AS ON DELETE TO %I DO INSTEAD NOTHING'', partition_name);
+            EXECUTE format(''CREATE OR REPLACE RULE ignore_update_events AS ON UPDATE TO %I DO INSTEAD NOTHING'', partition_name);
+        END IF;
+    END;
+'
+    LANGUAGE plpgsql;
+
+-- SIDE EFFECT (trigger): the events partition is created when the decider type first appears
+CREATE OR REPLACE FUNCTION create_decider_events_partition() RETURNS trigger AS
+'
+    BEGIN
+        PERFORM create_events_partition(NEW.decider);
+        RETURN NEW;
+    END;
+'
+    LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS t_create_decider_events_partition ON deciders;
+CREATE TRIGGER t_create_decider_events_partition
+    AFTER INSERT
+    ON deciders
+    FOR EACH ROW
+EXECUTE FUNCTION create_decider_events_partition();
+
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'RestaurantCreated');
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'RestaurantNotCreated');
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'RestaurantMenuChanged');
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'RestaurantMenuNotChanged');
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'OrderPlaced');
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'OrderNotPlaced');
+INSERT INTO deciders ("decider", "event") VALUES ('Order', 'OrderCreated');
+INSERT INTO deciders ("decider", "event") VALUES ('Order', 'OrderPrepared');
+INSERT INTO deciders ("decider", "event") VALUES ('Order', 'OrderNotCreated');
+INSERT INTO deciders ("decider", "event") VALUES ('Order', 'OrderNotPrepared');
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'OrderLineItemsModified');
+INSERT INTO deciders ("decider", "event") VALUES ('Order', 'OrderLineItemsUpdated');
+INSERT INTO deciders ("decider", "event") VALUES ('Order', 'OrderMigrated');
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'PaymentCaptured');
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'PaymentRefunded');
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'RestaurantCapacityChanged');
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'RestaurantOrderClosed');
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'MenuItemMarkedUnavailable');
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'MenuItemMarkedAvailable');
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'RestaurantOpeningHoursChanged');
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'RestaurantOrdersMigrated');
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'RestaurantOrdersReceived');
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'RestaurantPromotionApplied');
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'RestaurantPromotionWithdrawn');
+INSERT INTO deciders ("decider", "event") VALUES ('Promotion', 'PromotionCreated');
+INSERT INTO deciders ("decider", "event") VALUES ('Promotion', 'PromotionExpired');
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'RestaurantOrderCancelled');
+INSERT INTO deciders ("decider", "event") VALUES ('Order', 'OrderCancelled');
+INSERT INTO deciders ("decider", "event") VALUES ('Inventory', 'InventoryRestocked');
+INSERT INTO deciders ("decider", "event") VALUES ('Inventory', 'StockReserved');
+INSERT INTO deciders ("decider", "event") VALUES ('Inventory', 'StockReleased');
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'RestaurantOrderSplit');
+INSERT INTO deciders ("decider", "event") VALUES ('GiftCard', 'GiftCardIssued');
+INSERT INTO deciders ("decider", "event") VALUES ('GiftCard', 'GiftCardRedeemed');
+INSERT INTO deciders ("decider", "event") VALUES ('GiftCard', 'GiftCardRefunded');
+INSERT INTO deciders ("decider", "event") VALUES ('Courier', 'CourierLocationUpdated');
 
 
 CREATE INDEX IF NOT EXISTS decider_index ON events ("decider_id", "offset");
+CREATE UNIQUE INDEX IF NOT EXISTS store_sequence_number_index ON events ("store_id", "decider", "decider_id", "sequence_number");
+CREATE INDEX IF NOT EXISTS correlation_index ON events ("correlation_id") WHERE "correlation_id" IS NOT NULL;
+CREATE INDEX IF NOT EXISTS events_transaction_index ON events ("transaction_id", "offset");
+CREATE INDEX IF NOT EXISTS payload_hash_index ON events ("decider_id", "command_id", "payload_hash") WHERE "payload_hash" IS NOT NULL;
+CREATE INDEX IF NOT EXISTS events_tags_index ON events USING GIN ("tags");
+
+-- Offloaded event payloads (see `fmodel.payload_offload_threshold`): the payloads larger than the threshold are stored here, and the `events` row keeps the reference envelope only (`type`, `identifier`, `final` and `payload_ref`)
+-- Keeps the hot `events` table index-friendly (e.g. the huge menus); the payloads are rehydrated transparently when the events are fetched
+CREATE TABLE IF NOT EXISTS event_payloads
+(
+    -- the event the payload belongs to (`events.event_id`)
+    "event_id" UUID  NOT NULL PRIMARY KEY,
+    -- the event data/payload in JSON format
+    "data"     JSONB NOT NULL
+);
+
+-- The event data/payload, rehydrated from `event_payloads` if it was offloaded
+CREATE OR REPLACE FUNCTION fmodel_event_data(event_id UUID, data JSONB) RETURNS JSONB AS
+'
+    SELECT CASE
+               WHEN data ? ''payload_ref'' THEN (SELECT p.data FROM event_payloads p WHERE p.event_id = $1)
+               ELSE data
+               END
+'
+    LANGUAGE sql
+    STABLE;
+
+-- Registered projections/materialized views, and the mode in which they are updated
+CREATE TABLE IF NOT EXISTS projections
+(
+    -- projection name/type
+    "projection"    TEXT    NOT NULL PRIMARY KEY,
+    -- `sync`: updated by the trigger, in the same transaction in which the events are appended; `async`: updated by the projector (background worker); `statement`: updated by the statement-level trigger, all the events inserted by the statement in one pass
+    "mode"          TEXT    NOT NULL DEFAULT 'sync' CONSTRAINT projections_mode_check CHECK ("mode" IN ('sync', 'async', 'statement')),
+    -- the offset of the last event applied by the projector. Used in the `async` mode only
+    "checkpoint"    BIGINT  NOT NULL DEFAULT 0,
+    -- archive the projection rows of the final streams: the rows are moved to the `<projection>_archive` table, keeping the projection table lean
+    "archive_final" BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+-- Progress of the projection rebuilds. The rebuild is resumable: an unfinished rebuild is resumed by the next `rebuild_all_views` call
+CREATE TABLE IF NOT EXISTS projection_rebuilds
+(
+    -- projection name/type
+    "projection"     TEXT                     NOT NULL PRIMARY KEY REFERENCES projections ("projection"),
+    -- the mode of the projection before the rebuild started. The projection is switched back to this mode when the rebuild is finished
+    "previous_mode"  TEXT                     NOT NULL,
+    -- the offset of the latest event in the event store, when the rebuild started
+    "target_offset"  BIGINT                   NOT NULL,
+    -- the number of events applied to the projection so far
+    "events_applied" BIGINT                   NOT NULL DEFAULT 0,
+    "started_at"     TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    "updated_at"     TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    -- null while the rebuild is in progress
+    "finished_at"    TIMESTAMP WITH TIME ZONE NULL
+);
+
+-- Applied schema migrations (see `fmodel_migrate`). The schema version is the version of the latest applied migration
+CREATE TABLE IF NOT EXISTS fmodel_schema_version
+(
+    "version"     INT                      NOT NULL PRIMARY KEY,
+    "description" TEXT                     NOT NULL,
+    "applied_at"  TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Idempotency keys of the handled commands (see `fmodel.idempotency_key`). A command with the already recorded key is vetoed
+CREATE TABLE IF NOT EXISTS command_idempotency_keys
+(
+    -- the idempotency key, set by the client
+    "idempotency_key" TEXT                     NOT NULL PRIMARY KEY,
+    -- the transaction in which the key was recorded; the commands of the same transaction are allowed
+    "transaction_id"  BIGINT                   NOT NULL DEFAULT txid_current(),
+    "created_at"      TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Saga execution traces (see `fmodel.saga_traces`): a row per saga reaction, for debugging the orchestrations
+CREATE TABLE IF NOT EXISTS saga_traces
+(
+    "id"             BIGSERIAL PRIMARY KEY,
+    -- the correlation id of the (top-level) command handling, see `fmodel.correlation_id`
+    "correlation_id" UUID                     NULL DEFAULT NULLIF(current_setting('fmodel.correlation_id', TRUE), '')::UUID,
+    -- the depth of the orchestration: 0 for the reaction to the events of the (top-level) command, 1 for the reaction to the events of the saga-derived commands, ...
+    "depth"          INT                      NOT NULL,
+    -- the input event the saga reacted to
+    "event"          JSONB                    NOT NULL,
+    -- the commands produced by the saga
+    "commands"       JSONB                    NOT NULL,
+    -- the events resulting from the commands (recursively)
+    "events"         JSONB                    NOT NULL,
+    "created_at"     TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+CREATE INDEX IF NOT EXISTS saga_traces_correlation_index ON saga_traces ("correlation_id") WHERE "correlation_id" IS NOT NULL;
+
+-- Saga rules: the reactions of the saga toggled at runtime (e.g. stop creating the orders from `OrderPlaced` during an incident). The reactions without a rule are enabled
+CREATE TABLE IF NOT EXISTS saga_rules
+(
+    -- the type of the event the saga reacts to, e.g. `OrderPlaced`
+    "event"      TEXT                     NOT NULL,
+    -- the type of the command the saga reacts with, e.g. `CreateOrder`
+    "command"    TEXT                     NOT NULL,
+    "enabled"    BOOLEAN                  NOT NULL DEFAULT TRUE,
+    -- why the reaction was toggled
+    "reason"     TEXT                     NULL,
+    "updated_at" TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY ("event", "command")
+);
+
+-- Saga command ledger: a row per command issued by the saga, so the stuck orchestrations (the failed commands) can be detected (`pending_saga_work`) and re-driven
+CREATE TABLE IF NOT EXISTS saga_commands
+(
+    "id"             BIGSERIAL PRIMARY KEY,
+    -- the correlation id of the (top-level) command handling, see `fmodel.correlation_id`
+    "correlation_id" UUID                     NULL DEFAULT NULLIF(current_setting('fmodel.correlation_id', TRUE), '')::UUID,
+    -- the depth of the orchestration, see `saga_traces.depth`
+    "depth"          INT                      NOT NULL,
+    -- the input event the saga reacted to
+    "event"          JSONB                    NOT NULL,
+    -- the command issued by the saga
+    "command"        JSONB                    NOT NULL,
+    "command_type"   TEXT                     NOT NULL,
+    -- the decider (stream) the command is addressed to
+    "decider_id"     UUID                     NOT NULL,
+    -- `issued`, `succeeded` (the resulting events are appended with the events of the originating command) or `failed` (rejected by the decider, see `error`)
+    "status"         TEXT                     NOT NULL DEFAULT 'issued' CHECK ("status" IN ('issued', 'succeeded', 'failed')),
+    "error"          TEXT                     NULL,
+    -- the number of the handling attempts: the failed commands are re-driven by `retry_saga_command` / `retry_failed_saga_commands`
+    "attempts"       INT                      NOT NULL DEFAULT 0,
+    "created_at"     TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    "updated_at"     TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+CREATE INDEX IF NOT EXISTS saga_commands_pending_index ON saga_commands ("id") WHERE "status" <> 'succeeded';
+
+-- Workflow event streams: the saga of the business transaction, as an event-sourced decider (see `workflow_status`). The stream is keyed by the correlation id
+CREATE TABLE IF NOT EXISTS workflow_events
+(
+    -- the correlation id of the business transaction / the identity of the workflow stream
+    "correlation_id"  UUID                     NOT NULL,
+    -- the version of the workflow stream: 1 for the first event, incremented by 1 for every next event. Guards against the concurrent appends (optimistic locking)
+    "sequence_number" BIGINT                   NOT NULL,
+    -- event name/type, e.g. `WorkflowCommandIssued`
+    "event"           TEXT                     NOT NULL,
+    -- event data in JSON format
+    "data"            JSONB                    NOT NULL,
+    "created_at"      TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY ("correlation_id", "sequence_number")
+);
+
+-- The audit of the administrative correction events: the operator appends the correction event (see `append_correction`), instead of updating the history
+CREATE TABLE IF NOT EXISTS event_corrections
+(
+    -- the correction event, tagged `correction` in the `events` table
+    "event_id"     UUID PRIMARY KEY,
+    "decider_id"   TEXT                     NOT NULL,
+    -- event name/type
+    "event"        TEXT                     NOT NULL,
+    -- why the state was corrected, e.g. the support ticket
+    "reason"       TEXT                     NOT NULL,
+    -- the operator (the session user) who appended the correction
+    "corrected_by" TEXT                     NOT NULL DEFAULT session_user,
+    "created_at"   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- The exchange rates, by the validity start: the order totals are converted to the reference currency (`fmodel.reference_currency`) by the current rate
+CREATE TABLE IF NOT EXISTS exchange_rates
+(
+    -- ISO 4217 currency codes, e.g. `EUR`
+    "from_currency" TEXT                     NOT NULL,
+    "to_currency"   TEXT                     NOT NULL,
+    -- units of `to_currency` per unit of `from_currency`
+    "rate"          NUMERIC(18, 6)           NOT NULL CHECK ("rate" > 0),
+    -- the rate is current from this time, until the next rate of the currency pair
+    "valid_from"    TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY ("from_currency", "to_currency", "valid_from")
+);
+
+-- The outbox of the customer notifications: the intents to notify the customers of the status transitions of their orders (see `write_notifications`), claimed by the relay of the push-notification service (see `claim_notifications`)
+CREATE TABLE IF NOT EXISTS notifications_outbox
+(
+    "id"          BIGSERIAL PRIMARY KEY,
+    -- the target customer (`customer_identifier` of the order)
+    "customer_id" UUID                     NOT NULL,
+    "order_id"    UUID                     NOT NULL,
+    -- `OrderAccepted`, `OrderPrepared`, `OrderCancelled` or `OrderRejected`
+    "template"    TEXT                     NOT NULL,
+    -- the template and its parameters (the order and its restaurant)
+    "payload"     JSONB                    NOT NULL,
+    -- the event the notification reacts to: at most one notification per event
+    "event_id"    UUID                     NOT NULL UNIQUE,
+    "created_at"  TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    -- NULL until the notification is claimed by the relay
+    "relayed_at"  TIMESTAMP WITH TIME ZONE NULL
+);
+CREATE INDEX IF NOT EXISTS notifications_outbox_pending_index ON notifications_outbox ("id") WHERE "relayed_at" IS NULL;
+
+-- The ring buffer of the courier locations (`fmodel.courier_location_policy` = `buffer`): the last `fmodel.courier_location_buffer_size` locations of every courier, written instead of the `CourierLocationUpdated` events (see `courier_track`)
+-- The head of the ring buffer: the sequence of the last location of the courier
+CREATE TABLE IF NOT EXISTS courier_location_heads
+(
+    "store_id"   TEXT   NOT NULL DEFAULT fmodel_store_id(),
+    "courier_id" UUID   NOT NULL,
+    "sequence"   BIGINT NOT NULL,
+    PRIMARY KEY ("store_id", "courier_id")
+);
+-- The slots of the ring buffer (`sequence` modulo the size of the buffer), overwritten by the newer locations
+CREATE TABLE IF NOT EXISTS courier_locations
+(
+    "store_id"    TEXT                     NOT NULL DEFAULT fmodel_store_id(),
+    "courier_id"  UUID                     NOT NULL,
+    "slot"        BIGINT                   NOT NULL,
+    "sequence"    BIGINT                   NOT NULL,
+    -- microdegrees
+    "latitude"    INTEGER                  NOT NULL,
+    "longitude"   INTEGER                  NOT NULL,
+    "recorded_at" TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY ("store_id", "courier_id", "slot")
+);
+
+-- The frozen decider types (see `freeze_decider`): the new commands of the decider type are rejected, e.g. during the migration or the incident
+CREATE TABLE IF NOT EXISTS frozen_deciders
+(
+    "decider"   TEXT PRIMARY KEY,
+    "frozen_at" TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    "frozen_by" TEXT                     NOT NULL DEFAULT current_user
+);
 
 --      ########################
 --      ##### SIDE EFFECTS #####
@@ -71,7 +400,7 @@ CREATE INDEX IF NOT EXISTS decider_index ON events ("decider_id", "offset");
 --CREATE OR REPLACE RULE ignore_update_decider_events AS ON UPDATE TO deciders
 --    DO INSTEAD NOTHING;
 
--- SIDE EFFECT (rule): immutable events - ignore delete
+-- SIDE EFFECT (rule): immutable events - ignore delete (the rules are created on the partitions as well, see `create_events_partition`)
 CREATE OR REPLACE RULE ignore_delete_events AS ON DELETE TO events
     DO INSTEAD NOTHING;
 
@@ -79,6 +408,14 @@ CREATE OR REPLACE RULE ignore_delete_events AS ON DELETE TO events
 CREATE OR REPLACE RULE ignore_update_events AS ON UPDATE TO events
     DO INSTEAD NOTHING;
 
+-- SIDE EFFECT (rule): immutable offloaded event payloads - ignore delete
+CREATE OR REPLACE RULE ignore_delete_event_payloads AS ON DELETE TO event_payloads
+    DO INSTEAD NOTHING;
+
+-- SIDE EFFECT (rule): immutable offloaded event payloads - ignore update
+CREATE OR REPLACE RULE ignore_update_event_payloads AS ON UPDATE TO event_payloads
+    DO INSTEAD NOTHING;
+
 
 -- SIDE EFFECT (trigger): Can only use null previousId for first event in an decider
 CREATE OR REPLACE FUNCTION check_first_event_for_decider() RETURNS trigger AS
@@ -88,7 +425,8 @@ CREATE OR REPLACE FUNCTION check_first_event_for_decider() RETURNS trigger AS
             AND EXISTS(SELECT 1
                        FROM events
                        WHERE NEW.decider_id = decider_id
-                         AND NEW.decider = decider))
+                         AND NEW.decider = decider
+                         AND NEW.store_id = store_id))
         THEN
             RAISE EXCEPTION ''previous_id can only be null for first decider event'';
         END IF;
@@ -113,7 +451,8 @@ CREATE OR REPLACE FUNCTION check_final_event_for_decider() RETURNS trigger AS
                   FROM events
                   WHERE NEW.decider_id = decider_id
                     AND "final" = TRUE
-                    AND NEW.decider = decider)
+                    AND NEW.decider = decider
+                    AND NEW.store_id = store_id)
         THEN
             RAISE EXCEPTION ''last event for this decider stream is already final. the stream is closed, you can not append events to it.'';
         END IF;
@@ -139,7 +478,8 @@ CREATE OR REPLACE FUNCTION check_previous_id_in_same_decider() RETURNS trigger A
                            FROM events
                            WHERE NEW.previous_id = event_id
                              AND NEW.decider_id = decider_id
-                             AND NEW.decider = decider))
+                             AND NEW.decider = decider
+                             AND NEW.store_id = store_id))
         THEN
             RAISE EXCEPTION ''previous_id must be in the same decider'';
         END IF;
@@ -155,3 +495,90 @@ CREATE TRIGGER t_check_previous_id_in_same_decider
     FOR EACH ROW
 EXECUTE FUNCTION check_previous_id_in_same_decider();
 
+
+-- SIDE EFFECT (trigger): the per-stream sequence number follows the sequence number of the previous event (`previous_id`)
+-- The trigger name sorts after the `t_check_*` triggers, so the sequence number is assigned to the validated events only
+CREATE OR REPLACE FUNCTION set_sequence_number() RETURNS trigger AS
+'
+    BEGIN
+        NEW.sequence_number := COALESCE((SELECT sequence_number
+                                         FROM events
+                                         WHERE NEW.previous_id = event_id
+                                           AND NEW.decider = decider), 0) + 1;
+        RETURN NEW;
+    END;
+'
+    LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS t_set_sequence_number ON events;
+CREATE TRIGGER t_set_sequence_number
+    BEFORE INSERT
+    ON events
+    FOR EACH ROW
+EXECUTE FUNCTION set_sequence_number();
+
+
+-- SIDE EFFECT (trigger): deduplication of the events on the payload hash, protecting against double-processing from at-least-once upstream pipelines
+-- `fmodel.deduplication`: `off` (default), `reject` (raise an exception), or `skip` (silently skip the duplicate event)
+-- The duplicate is the event with the same payload, decided by the same command (`command_id`, the id of the command delivered again is the same), in the same decider stream
+-- The payload hash is the SHA-256 of the canonical JSON (`fmodel_canonical_json`), stable across the serializer upgrades; see `verify_canonical_payloads`
+CREATE OR REPLACE FUNCTION check_duplicate_event() RETURNS trigger AS
+'
+    DECLARE
+        mode TEXT := lower(COALESCE(current_setting(''fmodel.deduplication'', TRUE), ''off''));
+    BEGIN
+        IF mode NOT IN (''reject'', ''skip'') THEN
+            RETURN NEW;
+        END IF;
+        NEW.payload_hash := sha256(convert_to(fmodel_canonical_json(NEW.data), ''UTF8''));
+        IF EXISTS(SELECT 1
+                  FROM events
+                  WHERE NEW.decider_id = decider_id
+                    AND NEW.decider = decider
+                    AND NEW.store_id = store_id
+                    AND NEW.command_id IS NOT DISTINCT FROM command_id
+                    AND NEW.payload_hash = payload_hash)
+        THEN
+            IF mode = ''skip'' THEN
+                RETURN NULL;
+            END IF;
+            RAISE EXCEPTION ''duplicate event: the event with the same payload is already appended to the decider stream'';
+        END IF;
+        RETURN NEW;
+    END;
+'
+    LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS t_check_duplicate_event ON events;
+CREATE TRIGGER t_check_duplicate_event
+    BEFORE INSERT
+    ON events
+    FOR EACH ROW
+EXECUTE FUNCTION check_duplicate_event();
+
+-- SIDE EFFECT (trigger): the payloads larger than `fmodel.payload_offload_threshold` (bytes) are offloaded to `event_payloads`, and replaced by the reference envelope
+-- The trigger name sorts after the `t_check_*` triggers: the duplicates are detected on the full payload, and only the validated events are offloaded
+CREATE OR REPLACE FUNCTION offload_event_payload() RETURNS trigger AS
+'
+    DECLARE
+        threshold INT := COALESCE(NULLIF(current_setting(''fmodel.payload_offload_threshold'', TRUE), '''')::INT, 0);
+    BEGIN
+        IF threshold <= 0 OR octet_length(NEW.data::TEXT) <= threshold THEN
+            RETURN NEW;
+        END IF;
+        INSERT INTO event_payloads (event_id, data) VALUES (NEW.event_id, NEW.data);
+        NEW.data := jsonb_build_object(''type'', NEW.data -> ''type'',
+                                       ''identifier'', NEW.data -> ''identifier'',
+                                       ''final'', NEW.data -> ''final'',
+                                       ''payload_ref'', NEW.event_id);
+        RETURN NEW;
+    END;
+'
+    LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS t_offload_event_payload ON events;
+CREATE TRIGGER t_offload_event_payload
+    BEFORE INSERT
+    ON events
+    FOR EACH ROW
+EXECUTE FUNCTION offload_event_payload();
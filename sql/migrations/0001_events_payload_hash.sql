@@ -0,0 +1,34 @@
+-- Deduplication of the events on the payload hash (`fmodel.deduplication`)
+ALTER TABLE events ADD COLUMN IF NOT EXISTS "payload_hash" BYTEA NULL;
+CREATE INDEX IF NOT EXISTS payload_hash_index ON events ("decider_id", "payload_hash") WHERE "payload_hash" IS NOT NULL;
+
+CREATE OR REPLACE FUNCTION check_duplicate_event() RETURNS trigger AS
+'
+    DECLARE
+        mode TEXT := lower(COALESCE(current_setting(''fmodel.deduplication'', TRUE), ''off''));
+    BEGIN
+        IF mode NOT IN (''reject'', ''skip'') THEN
+            RETURN NEW;
+        END IF;
+        NEW.payload_hash := sha256(convert_to(NEW.data::TEXT, ''UTF8''));
+        IF EXISTS(SELECT 1
+                  FROM events
+                  WHERE NEW.decider_id = decider_id
+                    AND NEW.payload_hash = payload_hash)
+        THEN
+            IF mode = ''skip'' THEN
+                RETURN NULL;
+            END IF;
+            RAISE EXCEPTION ''duplicate event: the event with the same payload is already appended to the decider stream'';
+        END IF;
+        RETURN NEW;
+    END;
+'
+    LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS t_check_duplicate_event ON events;
+CREATE TRIGGER t_check_duplicate_event
+    BEFORE INSERT
+    ON events
+    FOR EACH ROW
+EXECUTE FUNCTION check_duplicate_event();
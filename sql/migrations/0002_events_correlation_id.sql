@@ -0,0 +1,3 @@
+-- Correlation ID of the (top-level) command handling that appended the event
+ALTER TABLE events ADD COLUMN IF NOT EXISTS "correlation_id" UUID NULL DEFAULT NULLIF(current_setting('fmodel.correlation_id', TRUE), '')::UUID;
+CREATE INDEX IF NOT EXISTS correlation_index ON events ("correlation_id") WHERE "correlation_id" IS NOT NULL;
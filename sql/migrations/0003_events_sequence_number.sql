@@ -0,0 +1,32 @@
+-- Per-stream sequence number of the events
+ALTER TABLE events ADD COLUMN IF NOT EXISTS "sequence_number" BIGINT NOT NULL DEFAULT 0;
+
+-- Backfill the sequence numbers of the existing events (the events are immutable: the update rule is disabled for the backfill only)
+ALTER TABLE events DISABLE RULE ignore_update_events;
+UPDATE events
+SET sequence_number = numbered.sequence_number
+FROM (SELECT "offset", row_number() OVER (PARTITION BY decider, decider_id ORDER BY "offset") AS sequence_number
+      FROM events) AS numbered
+WHERE events.offset = numbered.offset
+  AND events.sequence_number = 0;
+ALTER TABLE events ENABLE RULE ignore_update_events;
+
+CREATE UNIQUE INDEX IF NOT EXISTS sequence_number_index ON events ("decider", "decider_id", "sequence_number");
+
+CREATE OR REPLACE FUNCTION set_sequence_number() RETURNS trigger AS
+'
+    BEGIN
+        NEW.sequence_number := COALESCE((SELECT sequence_number
+                                         FROM events
+                                         WHERE NEW.previous_id = event_id), 0) + 1;
+        RETURN NEW;
+    END;
+'
+    LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS t_set_sequence_number ON events;
+CREATE TRIGGER t_set_sequence_number
+    BEFORE INSERT
+    ON events
+    FOR EACH ROW
+EXECUTE FUNCTION set_sequence_number();
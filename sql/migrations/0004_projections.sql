@@ -0,0 +1,26 @@
+-- Projections registry (modes, checkpoints, archival), projection rebuilds, and the idempotency keys of the commands
+CREATE TABLE IF NOT EXISTS projections
+(
+    "projection" TEXT   NOT NULL PRIMARY KEY,
+    "mode"       TEXT   NOT NULL DEFAULT 'sync' CHECK ("mode" IN ('sync', 'async')),
+    "checkpoint" BIGINT NOT NULL DEFAULT 0
+);
+ALTER TABLE projections ADD COLUMN IF NOT EXISTS "archive_final" BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE IF NOT EXISTS projection_rebuilds
+(
+    "projection"     TEXT                     NOT NULL PRIMARY KEY REFERENCES projections ("projection"),
+    "previous_mode"  TEXT                     NOT NULL,
+    "target_offset"  BIGINT                   NOT NULL,
+    "events_applied" BIGINT                   NOT NULL DEFAULT 0,
+    "started_at"     TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    "updated_at"     TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    "finished_at"    TIMESTAMP WITH TIME ZONE NULL
+);
+
+CREATE TABLE IF NOT EXISTS command_idempotency_keys
+(
+    "idempotency_key" TEXT                     NOT NULL PRIMARY KEY,
+    "transaction_id"  BIGINT                   NOT NULL DEFAULT txid_current(),
+    "created_at"      TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,16 @@
+-- Position of the last applied event in the materialized views, the archive of the orders, and the registration of the views as projections
+ALTER TABLE restaurants ADD COLUMN IF NOT EXISTS last_event_id UUID;
+ALTER TABLE restaurants ADD COLUMN IF NOT EXISTS last_offset BIGINT;
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS last_event_id UUID;
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS last_offset BIGINT;
+
+CREATE TABLE IF NOT EXISTS orders_archive
+(
+    id            UUID PRIMARY KEY,
+    data          JSONB,
+    last_event_id UUID,
+    last_offset   BIGINT,
+    archived_at   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO projections (projection) VALUES ('restaurants'), ('orders') ON CONFLICT DO NOTHING;
@@ -0,0 +1,173 @@
+-- Partitioning of the `events` table per decider type (LIST partitioning by `decider`)
+-- The existing (not partitioned) table is converted: the events are copied to the partitioned table (preserving the offsets), before the indexes and the triggers are (re)created
+-- DOWNTIME: the copy is not batched; all the events are copied in the transaction of the migration, holding the exclusive lock on the `events` (the commands are blocked until it commits)
+
+CREATE OR REPLACE FUNCTION create_events_partition(decider_type TEXT) RETURNS VOID AS
+'
+    DECLARE
+        partition_name TEXT := ''events_'' || lower(regexp_replace(decider_type, ''[^a-zA-Z0-9_]'', ''_'', ''g''));
+    BEGIN
+        IF to_regclass(quote_ident(partition_name)) IS NULL THEN
+            EXECUTE format(''CREATE TABLE %I PARTITION OF events FOR VALUES IN (%L)'', partition_name, decider_type);
+            EXECUTE format(''CREATE OR REPLACE RULE ignore_delete_events AS ON DELETE TO %I DO INSTEAD NOTHING'', partition_name);
+            EXECUTE format(''CREATE OR REPLACE RULE ignore_update_events AS ON UPDATE TO %I DO INSTEAD NOTHING'', partition_name);
+        END IF;
+    END;
+'
+    LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION create_decider_events_partition() RETURNS trigger AS
+'
+    BEGIN
+        PERFORM create_events_partition(NEW.decider);
+        RETURN NEW;
+    END;
+'
+    LANGUAGE plpgsql;
+
+DO
+'
+    BEGIN
+        IF (SELECT relkind FROM pg_class WHERE oid = ''events''::regclass) = ''p'' THEN
+            RETURN;
+        END IF;
+
+        ALTER TABLE events RENAME TO events_unpartitioned;
+        ALTER SEQUENCE events_offset_seq OWNED BY NONE;
+        ALTER TABLE events_unpartitioned
+            DROP CONSTRAINT events_pkey,
+            DROP CONSTRAINT events_event_id_key,
+            DROP CONSTRAINT events_previous_id_key;
+        DROP INDEX IF EXISTS decider_index, sequence_number_index, correlation_index, payload_hash_index;
+
+        CREATE TABLE events
+        (
+            "event"           TEXT                     NOT NULL,
+            "event_id"        UUID                     NOT NULL,
+            "decider"         TEXT                     NOT NULL,
+            "decider_id"      TEXT                     NOT NULL,
+            "data"            JSONB                    NOT NULL,
+            "command_id"      UUID                     NULL,
+            "previous_id"     UUID,
+            "sequence_number" BIGINT                   NOT NULL DEFAULT 0,
+            "final"           BOOLEAN                  NOT NULL DEFAULT FALSE,
+            "correlation_id"  UUID                     NULL DEFAULT NULLIF(current_setting(''fmodel.correlation_id'', TRUE), '''')::UUID,
+            "payload_hash"    BYTEA                    NULL,
+            "created_at"      TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+            "offset"          BIGINT                   NOT NULL DEFAULT nextval(''events_offset_seq''),
+            PRIMARY KEY ("offset", "decider"),
+            UNIQUE ("event_id", "decider"),
+            UNIQUE ("previous_id", "decider"),
+            FOREIGN KEY ("decider", "event") REFERENCES deciders ("decider", "event")
+        ) PARTITION BY LIST ("decider");
+        ALTER SEQUENCE events_offset_seq OWNED BY events."offset";
+
+        PERFORM create_events_partition(decider_types.decider)
+        FROM (SELECT DISTINCT decider FROM deciders) AS decider_types;
+
+        INSERT INTO events ("event", "event_id", "decider", "decider_id", "data", "command_id", "previous_id",
+                            "sequence_number", "final", "correlation_id", "payload_hash", "created_at", "offset")
+        SELECT "event", "event_id", "decider", "decider_id", "data", "command_id", "previous_id",
+               "sequence_number", "final", "correlation_id", "payload_hash", "created_at", "offset"
+        FROM events_unpartitioned;
+
+        DROP TABLE events_unpartitioned;
+    END;
+';
+
+DROP TRIGGER IF EXISTS t_create_decider_events_partition ON deciders;
+CREATE TRIGGER t_create_decider_events_partition
+    AFTER INSERT
+    ON deciders
+    FOR EACH ROW
+EXECUTE FUNCTION create_decider_events_partition();
+
+CREATE INDEX IF NOT EXISTS decider_index ON events ("decider_id", "offset");
+CREATE UNIQUE INDEX IF NOT EXISTS sequence_number_index ON events ("decider", "decider_id", "sequence_number");
+CREATE INDEX IF NOT EXISTS correlation_index ON events ("correlation_id") WHERE "correlation_id" IS NOT NULL;
+CREATE INDEX IF NOT EXISTS payload_hash_index ON events ("decider_id", "payload_hash") WHERE "payload_hash" IS NOT NULL;
+
+CREATE OR REPLACE RULE ignore_delete_events AS ON DELETE TO events
+    DO INSTEAD NOTHING;
+CREATE OR REPLACE RULE ignore_update_events AS ON UPDATE TO events
+    DO INSTEAD NOTHING;
+
+-- The lookups of the previous event, and of the duplicates, are filtered by `decider` as well (partition pruning)
+CREATE OR REPLACE FUNCTION set_sequence_number() RETURNS trigger AS
+'
+    BEGIN
+        NEW.sequence_number := COALESCE((SELECT sequence_number
+                                         FROM events
+                                         WHERE NEW.previous_id = event_id
+                                           AND NEW.decider = decider), 0) + 1;
+        RETURN NEW;
+    END;
+'
+    LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION check_duplicate_event() RETURNS trigger AS
+'
+    DECLARE
+        mode TEXT := lower(COALESCE(current_setting(''fmodel.deduplication'', TRUE), ''off''));
+    BEGIN
+        IF mode NOT IN (''reject'', ''skip'') THEN
+            RETURN NEW;
+        END IF;
+        NEW.payload_hash := sha256(convert_to(NEW.data::TEXT, ''UTF8''));
+        IF EXISTS(SELECT 1
+                  FROM events
+                  WHERE NEW.decider_id = decider_id
+                    AND NEW.decider = decider
+                    AND NEW.payload_hash = payload_hash)
+        THEN
+            IF mode = ''skip'' THEN
+                RETURN NULL;
+            END IF;
+            RAISE EXCEPTION ''duplicate event: the event with the same payload is already appended to the decider stream'';
+        END IF;
+        RETURN NEW;
+    END;
+'
+    LANGUAGE plpgsql;
+
+-- The triggers of the converted table (in the same order as in the bootstrap schema)
+DROP TRIGGER IF EXISTS t_check_first_event_for_decider ON events;
+CREATE TRIGGER t_check_first_event_for_decider
+    BEFORE INSERT
+    ON events
+    FOR EACH ROW
+EXECUTE FUNCTION check_first_event_for_decider();
+
+DROP TRIGGER IF EXISTS t_check_final_event_for_decider ON events;
+CREATE TRIGGER t_check_final_event_for_decider
+    BEFORE INSERT
+    ON events
+    FOR EACH ROW
+EXECUTE FUNCTION check_final_event_for_decider();
+
+DROP TRIGGER IF EXISTS t_check_previous_id_in_same_decider ON events;
+CREATE TRIGGER t_check_previous_id_in_same_decider
+    BEFORE INSERT
+    ON events
+    FOR EACH ROW
+EXECUTE FUNCTION check_previous_id_in_same_decider();
+
+DROP TRIGGER IF EXISTS t_set_sequence_number ON events;
+CREATE TRIGGER t_set_sequence_number
+    BEFORE INSERT
+    ON events
+    FOR EACH ROW
+EXECUTE FUNCTION set_sequence_number();
+
+DROP TRIGGER IF EXISTS t_check_duplicate_event ON events;
+CREATE TRIGGER t_check_duplicate_event
+    BEFORE INSERT
+    ON events
+    FOR EACH ROW
+EXECUTE FUNCTION check_duplicate_event();
+
+DROP TRIGGER IF EXISTS restaurant_event_handler_trigger ON events;
+CREATE TRIGGER restaurant_event_handler_trigger AFTER INSERT ON events FOR EACH ROW EXECUTE PROCEDURE handle_restaurant_events();
+
+DROP TRIGGER IF EXISTS order_event_handler_trigger ON events;
+CREATE TRIGGER order_event_handler_trigger AFTER INSERT ON events FOR EACH ROW EXECUTE PROCEDURE handle_order_events();
@@ -0,0 +1,23 @@
+-- Restaurant search projection: the searchable text of the restaurants with the full-text search (`tsvector`) GIN index, backfilled from the `restaurants` materialized view
+CREATE TABLE IF NOT EXISTS restaurants_search
+(
+    id         UUID PRIMARY KEY,
+    name       TEXT NOT NULL,
+    cuisine    TEXT NOT NULL,
+    item_names TEXT NOT NULL,
+    document   TSVECTOR GENERATED ALWAYS AS (
+        setweight(to_tsvector('simple', name), 'A') ||
+        setweight(to_tsvector('simple', cuisine), 'B') ||
+        setweight(to_tsvector('simple', item_names), 'C')
+        ) STORED
+);
+
+CREATE INDEX IF NOT EXISTS restaurants_search_document_index ON restaurants_search USING GIN (document);
+
+INSERT INTO restaurants_search (id, name, cuisine, item_names)
+SELECT id,
+       COALESCE(data ->> 'name', ''),
+       COALESCE(data -> 'menu' ->> 'cuisine', ''),
+       COALESCE((SELECT string_agg(item ->> 'name', ' ') FROM jsonb_array_elements(data -> 'menu' -> 'items') AS item), '')
+FROM restaurants
+ON CONFLICT (id) DO NOTHING;
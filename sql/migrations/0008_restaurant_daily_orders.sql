@@ -0,0 +1,17 @@
+-- Restaurant daily orders: the analytic (aggregating) projection of the order counters per restaurant per day, registered as a projection
+-- The existing orders are counted by the projection rebuild (`rebuild_all_views`)
+CREATE TABLE IF NOT EXISTS restaurant_daily_orders
+(
+    restaurant_id UUID   NOT NULL,
+    day           DATE   NOT NULL,
+    orders        BIGINT NOT NULL DEFAULT 0,
+    items         BIGINT NOT NULL DEFAULT 0,
+    revenue       BIGINT NOT NULL DEFAULT 0,
+    last_offset   BIGINT NOT NULL,
+    PRIMARY KEY (restaurant_id, day)
+);
+
+INSERT INTO projections (projection) VALUES ('restaurant_daily_orders') ON CONFLICT DO NOTHING;
+
+DROP TRIGGER IF EXISTS restaurant_daily_orders_event_handler_trigger ON events;
+CREATE TRIGGER restaurant_daily_orders_event_handler_trigger AFTER INSERT ON events FOR EACH ROW EXECUTE PROCEDURE handle_restaurant_daily_orders_events();
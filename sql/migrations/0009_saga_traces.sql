@@ -0,0 +1,12 @@
+-- Saga execution traces: a row per saga reaction (the input event, the produced commands, the resulting events, and the depth of the orchestration)
+CREATE TABLE IF NOT EXISTS saga_traces
+(
+    "id"             BIGSERIAL PRIMARY KEY,
+    "correlation_id" UUID                     NULL DEFAULT NULLIF(current_setting('fmodel.correlation_id', TRUE), '')::UUID,
+    "depth"          INT                      NOT NULL,
+    "event"          JSONB                    NOT NULL,
+    "commands"       JSONB                    NOT NULL,
+    "events"         JSONB                    NOT NULL,
+    "created_at"     TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+CREATE INDEX IF NOT EXISTS saga_traces_correlation_index ON saga_traces ("correlation_id") WHERE "correlation_id" IS NOT NULL;
@@ -0,0 +1,10 @@
+-- Saga rules: the reactions of the saga (event type -> command type) toggled at runtime by the operators
+CREATE TABLE IF NOT EXISTS saga_rules
+(
+    "event"      TEXT                     NOT NULL,
+    "command"    TEXT                     NOT NULL,
+    "enabled"    BOOLEAN                  NOT NULL DEFAULT TRUE,
+    "reason"     TEXT                     NULL,
+    "updated_at" TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY ("event", "command")
+);
@@ -0,0 +1,5 @@
+-- Commit-ordered reads: the id of the transaction that appended the event, so the subscriptions (the projector) read the events in the (transaction id, offset) order, only from the finished transactions
+-- The existing events are in the transaction `0` (ordered by the offset, before all the new events); the constant default does not rewrite the table
+ALTER TABLE events ADD COLUMN IF NOT EXISTS "transaction_id" XID8 NOT NULL DEFAULT '0';
+ALTER TABLE events ALTER COLUMN "transaction_id" SET DEFAULT pg_current_xact_id();
+CREATE INDEX IF NOT EXISTS events_transaction_index ON events ("transaction_id", "offset");
@@ -0,0 +1,94 @@
+-- Logical event stores: the independent bounded contexts share the extension install, but keep fully isolated event streams (see `fmodel.store_id`)
+CREATE OR REPLACE FUNCTION fmodel_store_id() RETURNS TEXT AS
+'
+    SELECT COALESCE(NULLIF(current_setting(''fmodel.store_id'', TRUE), ''''), ''default'')
+'
+    LANGUAGE sql
+    STABLE;
+
+-- The existing events are in the `default` store; the constant default does not rewrite the table
+ALTER TABLE events ADD COLUMN IF NOT EXISTS "store_id" TEXT NOT NULL DEFAULT 'default';
+ALTER TABLE events ALTER COLUMN "store_id" SET DEFAULT fmodel_store_id();
+
+-- The sequence numbers are unique per stream of the store
+CREATE UNIQUE INDEX IF NOT EXISTS store_sequence_number_index ON events ("store_id", "decider", "decider_id", "sequence_number");
+DROP INDEX IF EXISTS sequence_number_index;
+
+-- The stream checks are scoped to the store
+CREATE OR REPLACE FUNCTION check_first_event_for_decider() RETURNS trigger AS
+'
+    BEGIN
+        IF (NEW.previous_id IS NULL
+            AND EXISTS(SELECT 1
+                       FROM events
+                       WHERE NEW.decider_id = decider_id
+                         AND NEW.decider = decider
+                         AND NEW.store_id = store_id))
+        THEN
+            RAISE EXCEPTION ''previous_id can only be null for first decider event'';
+        END IF;
+        RETURN NEW;
+    END;
+'
+    LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION check_final_event_for_decider() RETURNS trigger AS
+'
+    BEGIN
+        IF EXISTS(SELECT 1
+                  FROM events
+                  WHERE NEW.decider_id = decider_id
+                    AND "final" = TRUE
+                    AND NEW.decider = decider
+                    AND NEW.store_id = store_id)
+        THEN
+            RAISE EXCEPTION ''last event for this decider stream is already final. the stream is closed, you can not append events to it.'';
+        END IF;
+        RETURN NEW;
+    END;
+'
+    LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION check_previous_id_in_same_decider() RETURNS trigger AS
+'
+    BEGIN
+        IF (NEW.previous_id IS NOT NULL
+            AND NOT EXISTS(SELECT 1
+                           FROM events
+                           WHERE NEW.previous_id = event_id
+                             AND NEW.decider_id = decider_id
+                             AND NEW.decider = decider
+                             AND NEW.store_id = store_id))
+        THEN
+            RAISE EXCEPTION ''previous_id must be in the same decider'';
+        END IF;
+        RETURN NEW;
+    END;
+'
+    LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION check_duplicate_event() RETURNS trigger AS
+'
+    DECLARE
+        mode TEXT := lower(COALESCE(current_setting(''fmodel.deduplication'', TRUE), ''off''));
+    BEGIN
+        IF mode NOT IN (''reject'', ''skip'') THEN
+            RETURN NEW;
+        END IF;
+        NEW.payload_hash := sha256(convert_to(NEW.data::TEXT, ''UTF8''));
+        IF EXISTS(SELECT 1
+                  FROM events
+                  WHERE NEW.decider_id = decider_id
+                    AND NEW.decider = decider
+                    AND NEW.store_id = store_id
+                    AND NEW.payload_hash = payload_hash)
+        THEN
+            IF mode = ''skip'' THEN
+                RETURN NULL;
+            END IF;
+            RAISE EXCEPTION ''duplicate event: the event with the same payload is already appended to the decider stream'';
+        END IF;
+        RETURN NEW;
+    END;
+'
+    LANGUAGE plpgsql;
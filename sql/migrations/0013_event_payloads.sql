@@ -0,0 +1,55 @@
+-- Large payload offloading: the payloads larger than `fmodel.payload_offload_threshold` are stored in `event_payloads`, and the `events` row keeps the reference envelope only
+CREATE TABLE IF NOT EXISTS event_payloads
+(
+    -- the event the payload belongs to (`events.event_id`)
+    "event_id" UUID  NOT NULL PRIMARY KEY,
+    -- the event data/payload in JSON format
+    "data"     JSONB NOT NULL
+);
+
+-- The event data/payload, rehydrated from `event_payloads` if it was offloaded
+CREATE OR REPLACE FUNCTION fmodel_event_data(event_id UUID, data JSONB) RETURNS JSONB AS
+'
+    SELECT CASE
+               WHEN data ? ''payload_ref'' THEN (SELECT p.data FROM event_payloads p WHERE p.event_id = $1)
+               ELSE data
+               END
+'
+    LANGUAGE sql
+    STABLE;
+
+-- SIDE EFFECT (rule): immutable offloaded event payloads - ignore delete
+CREATE OR REPLACE RULE ignore_delete_event_payloads AS ON DELETE TO event_payloads
+    DO INSTEAD NOTHING;
+
+-- SIDE EFFECT (rule): immutable offloaded event payloads - ignore update
+CREATE OR REPLACE RULE ignore_update_event_payloads AS ON UPDATE TO event_payloads
+    DO INSTEAD NOTHING;
+
+
+-- SIDE EFFECT (trigger): the payloads larger than `fmodel.payload_offload_threshold` (bytes) are offloaded to `event_payloads`, and replaced by the reference envelope
+-- The trigger name sorts after the `t_check_*` triggers: the duplicates are detected on the full payload, and only the validated events are offloaded
+CREATE OR REPLACE FUNCTION offload_event_payload() RETURNS trigger AS
+'
+    DECLARE
+        threshold INT := COALESCE(NULLIF(current_setting(''fmodel.payload_offload_threshold'', TRUE), '''')::INT, 0);
+    BEGIN
+        IF threshold <= 0 OR octet_length(NEW.data::TEXT) <= threshold THEN
+            RETURN NEW;
+        END IF;
+        INSERT INTO event_payloads (event_id, data) VALUES (NEW.event_id, NEW.data);
+        NEW.data := jsonb_build_object(''type'', NEW.data -> ''type'',
+                                       ''identifier'', NEW.data -> ''identifier'',
+                                       ''final'', NEW.data -> ''final'',
+                                       ''payload_ref'', NEW.event_id);
+        RETURN NEW;
+    END;
+'
+    LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS t_offload_event_payload ON events;
+CREATE TRIGGER t_offload_event_payload
+    BEFORE INSERT
+    ON events
+    FOR EACH ROW
+EXECUTE FUNCTION offload_event_payload();
@@ -0,0 +1,15 @@
+-- Restaurant order board: the cross-domain projection of the restaurants with the status of their orders, registered as a projection
+-- The existing restaurants and orders are projected by the projection rebuild (`rebuild_all_views`)
+CREATE TABLE IF NOT EXISTS restaurant_order_board
+(
+    id            UUID PRIMARY KEY,
+    data          JSONB,
+    last_event_id UUID,
+    last_offset   BIGINT
+);
+CREATE INDEX IF NOT EXISTS restaurant_order_board_orders_index ON restaurant_order_board USING GIN ((data -> 'orders') jsonb_path_ops);
+
+INSERT INTO projections (projection) VALUES ('restaurant_order_board') ON CONFLICT DO NOTHING;
+
+DROP TRIGGER IF EXISTS restaurant_order_board_event_handler_trigger ON events;
+CREATE TRIGGER restaurant_order_board_event_handler_trigger AFTER INSERT ON events FOR EACH ROW EXECUTE PROCEDURE handle_restaurant_order_board_events();
@@ -0,0 +1,3 @@
+-- Order modification (before preparation): the restaurant accepts the modified line items (`OrderLineItemsModified`), and the order is updated (`OrderLineItemsUpdated`)
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'OrderLineItemsModified') ON CONFLICT DO NOTHING;
+INSERT INTO deciders ("decider", "event") VALUES ('Order', 'OrderLineItemsUpdated') ON CONFLICT DO NOTHING;
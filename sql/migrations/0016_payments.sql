@@ -0,0 +1,18 @@
+-- Order payments: the split payments (`PaymentCaptured`) and the partial refunds (`PaymentRefunded`) of the orders, on the restaurant stream
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'PaymentCaptured') ON CONFLICT DO NOTHING;
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'PaymentRefunded') ON CONFLICT DO NOTHING;
+
+-- Payments: the aggregating projection of the captured and refunded amounts per order, registered as a projection
+CREATE TABLE IF NOT EXISTS payments
+(
+    order_id      UUID PRIMARY KEY,
+    restaurant_id UUID   NOT NULL,
+    captured      BIGINT NOT NULL DEFAULT 0,
+    refunded      BIGINT NOT NULL DEFAULT 0,
+    last_offset   BIGINT NOT NULL
+);
+
+INSERT INTO projections (projection) VALUES ('payments') ON CONFLICT DO NOTHING;
+
+DROP TRIGGER IF EXISTS payments_event_handler_trigger ON events;
+CREATE TRIGGER payments_event_handler_trigger AFTER INSERT ON events FOR EACH ROW EXECUTE PROCEDURE handle_payments_events();
@@ -0,0 +1,3 @@
+-- Restaurant capacity: the maximum number of the open orders (`RestaurantCapacityChanged`), released when the order is prepared (`RestaurantOrderClosed`)
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'RestaurantCapacityChanged') ON CONFLICT DO NOTHING;
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'RestaurantOrderClosed') ON CONFLICT DO NOTHING;
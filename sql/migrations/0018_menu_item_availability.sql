@@ -0,0 +1,3 @@
+-- Menu item availability: the menu items marked as unavailable (`MenuItemMarkedUnavailable`) can not be ordered until they are marked as available again (`MenuItemMarkedAvailable`)
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'MenuItemMarkedUnavailable') ON CONFLICT DO NOTHING;
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'MenuItemMarkedAvailable') ON CONFLICT DO NOTHING;
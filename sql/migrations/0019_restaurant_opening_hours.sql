@@ -0,0 +1,2 @@
+-- Restaurant opening hours: the orders are placed within the opening hours of the restaurant (`RestaurantOpeningHoursChanged`)
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'RestaurantOpeningHoursChanged') ON CONFLICT DO NOTHING;
@@ -0,0 +1,16 @@
+-- Saga command ledger: a row per command issued by the saga, with its status (issued/succeeded/failed), for detecting and re-driving the stuck orchestrations
+CREATE TABLE IF NOT EXISTS saga_commands
+(
+    "id"             BIGSERIAL PRIMARY KEY,
+    "correlation_id" UUID                     NULL DEFAULT NULLIF(current_setting('fmodel.correlation_id', TRUE), '')::UUID,
+    "depth"          INT                      NOT NULL,
+    "event"          JSONB                    NOT NULL,
+    "command"        JSONB                    NOT NULL,
+    "command_type"   TEXT                     NOT NULL,
+    "decider_id"     UUID                     NOT NULL,
+    "status"         TEXT                     NOT NULL DEFAULT 'issued' CHECK ("status" IN ('issued', 'succeeded', 'failed')),
+    "error"          TEXT                     NULL,
+    "created_at"     TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    "updated_at"     TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+CREATE INDEX IF NOT EXISTS saga_commands_pending_index ON saga_commands ("id") WHERE "status" <> 'succeeded';
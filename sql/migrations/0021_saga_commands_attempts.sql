@@ -0,0 +1,2 @@
+-- Saga command ledger: the number of the handling attempts (the failed commands are re-driven by `retry_saga_command` / `retry_failed_saga_commands`)
+ALTER TABLE saga_commands ADD COLUMN IF NOT EXISTS "attempts" INT NOT NULL DEFAULT 0;
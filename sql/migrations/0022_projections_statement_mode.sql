@@ -0,0 +1,6 @@
+-- Projections: the `statement` mode, the projection is updated by the statement-level trigger (all the events inserted by the statement in one pass, e.g. the bulk imports)
+ALTER TABLE projections DROP CONSTRAINT IF EXISTS projections_mode_check;
+ALTER TABLE projections ADD CONSTRAINT projections_mode_check CHECK ("mode" IN ('sync', 'async', 'statement'));
+
+DROP TRIGGER IF EXISTS events_statement_handler_trigger ON events;
+CREATE TRIGGER events_statement_handler_trigger AFTER INSERT ON events REFERENCING NEW TABLE AS new_events FOR EACH STATEMENT EXECUTE PROCEDURE handle_events_statement();
@@ -0,0 +1,4 @@
+-- Restaurant merge/split: the orders migrated between the restaurant records created by mistake (`RestaurantOrdersMigrated`, `RestaurantOrdersReceived`), and the open orders following them (`OrderMigrated`)
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'RestaurantOrdersMigrated') ON CONFLICT DO NOTHING;
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'RestaurantOrdersReceived') ON CONFLICT DO NOTHING;
+INSERT INTO deciders ("decider", "event") VALUES ('Order', 'OrderMigrated') ON CONFLICT DO NOTHING;
@@ -0,0 +1,27 @@
+-- Events: the payload hash (deduplication) is the SHA-256 of the canonical JSON (`fmodel_canonical_json`), stable across the serializer upgrades
+-- The historical events hashed from the `jsonb` text representation are reported by `verify_canonical_payloads`
+CREATE OR REPLACE FUNCTION check_duplicate_event() RETURNS trigger AS
+'
+    DECLARE
+        mode TEXT := lower(COALESCE(current_setting(''fmodel.deduplication'', TRUE), ''off''));
+    BEGIN
+        IF mode NOT IN (''reject'', ''skip'') THEN
+            RETURN NEW;
+        END IF;
+        NEW.payload_hash := sha256(convert_to(fmodel_canonical_json(NEW.data), ''UTF8''));
+        IF EXISTS(SELECT 1
+                  FROM events
+                  WHERE NEW.decider_id = decider_id
+                    AND NEW.decider = decider
+                    AND NEW.store_id = store_id
+                    AND NEW.payload_hash = payload_hash)
+        THEN
+            IF mode = ''skip'' THEN
+                RETURN NULL;
+            END IF;
+            RAISE EXCEPTION ''duplicate event: the event with the same payload is already appended to the decider stream'';
+        END IF;
+        RETURN NEW;
+    END;
+'
+    LANGUAGE plpgsql;
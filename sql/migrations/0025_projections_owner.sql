@@ -0,0 +1,15 @@
+-- Row-level security of the projections: the owner of the events (the role or the tenant, see `fmodel.owner`), and of the projection rows they create
+CREATE OR REPLACE FUNCTION fmodel_owner() RETURNS TEXT AS
+'
+    SELECT COALESCE(NULLIF(current_setting(''fmodel.owner'', TRUE), ''''), current_user)
+'
+    LANGUAGE sql
+    STABLE;
+
+-- The owner of the existing events (and of the projection rows) is unknown (`NULL`); the column without a default does not rewrite the table
+ALTER TABLE events ADD COLUMN IF NOT EXISTS "owner" TEXT NULL;
+ALTER TABLE events ALTER COLUMN "owner" SET DEFAULT fmodel_owner();
+
+ALTER TABLE restaurants ADD COLUMN IF NOT EXISTS owner TEXT;
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS owner TEXT;
+ALTER TABLE orders_archive ADD COLUMN IF NOT EXISTS owner TEXT;
@@ -0,0 +1,10 @@
+-- Workflow event streams: the saga of the business transaction, as an event-sourced decider (the stream per correlation id), so the long-running workflows are recorded and resumable
+CREATE TABLE IF NOT EXISTS workflow_events
+(
+    "correlation_id"  UUID                     NOT NULL,
+    "sequence_number" BIGINT                   NOT NULL,
+    "event"           TEXT                     NOT NULL,
+    "data"            JSONB                    NOT NULL,
+    "created_at"      TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY ("correlation_id", "sequence_number")
+);
@@ -0,0 +1,11 @@
+-- Event tags (e.g. `vip-customer`, `promo:summer24`), for slicing the event log by the analytics
+CREATE OR REPLACE FUNCTION fmodel_event_tags() RETURNS TEXT[] AS
+'
+    SELECT COALESCE(string_to_array(NULLIF(current_setting(''fmodel.event_tags'', TRUE), ''''), '',''), ''{}'')
+'
+    LANGUAGE sql
+    STABLE;
+
+ALTER TABLE events ADD COLUMN IF NOT EXISTS "tags" TEXT[] NOT NULL DEFAULT '{}';
+ALTER TABLE events ALTER COLUMN "tags" SET DEFAULT fmodel_event_tags();
+CREATE INDEX IF NOT EXISTS events_tags_index ON events USING GIN ("tags");
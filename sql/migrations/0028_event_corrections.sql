@@ -0,0 +1,10 @@
+-- The audit of the administrative correction events (see `append_correction`)
+CREATE TABLE IF NOT EXISTS event_corrections
+(
+    "event_id"     UUID PRIMARY KEY,
+    "decider_id"   TEXT                     NOT NULL,
+    "event"        TEXT                     NOT NULL,
+    "reason"       TEXT                     NOT NULL,
+    "corrected_by" TEXT                     NOT NULL DEFAULT session_user,
+    "created_at"   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,9 @@
+-- The exchange rates, by the validity start: the order totals are converted to the reference currency (`fmodel.reference_currency`) by the current rate
+CREATE TABLE IF NOT EXISTS exchange_rates
+(
+    "from_currency" TEXT                     NOT NULL,
+    "to_currency"   TEXT                     NOT NULL,
+    "rate"          NUMERIC(18, 6)           NOT NULL CHECK ("rate" > 0),
+    "valid_from"    TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY ("from_currency", "to_currency", "valid_from")
+);
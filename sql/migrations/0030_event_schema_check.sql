@@ -0,0 +1,2 @@
+-- The schema check of the inserted events (the `t_check_event_schema` trigger), generated from the required fields of the current events
+SELECT fmodel_create_event_schema_check();
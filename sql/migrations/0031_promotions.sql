@@ -0,0 +1,7 @@
+-- Promotions: the promotions of the restaurants (`PromotionCreated`, `PromotionExpired`), applied to and withdrawn from the restaurants by the saga (`RestaurantPromotionApplied`, `RestaurantPromotionWithdrawn`)
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'RestaurantPromotionApplied') ON CONFLICT DO NOTHING;
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'RestaurantPromotionWithdrawn') ON CONFLICT DO NOTHING;
+INSERT INTO deciders ("decider", "event") VALUES ('Promotion', 'PromotionCreated') ON CONFLICT DO NOTHING;
+INSERT INTO deciders ("decider", "event") VALUES ('Promotion', 'PromotionExpired') ON CONFLICT DO NOTHING;
+-- The schema check of the inserted events (the `t_check_event_schema` trigger), regenerated with the new events
+SELECT fmodel_create_event_schema_check();
@@ -0,0 +1,8 @@
+-- Inventory: the stock of the restaurants (`InventoryRestocked`), reserved by the orders (`StockReserved`) and released when the orders are cancelled (`RestaurantOrderCancelled`, `OrderCancelled`, `StockReleased`)
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'RestaurantOrderCancelled') ON CONFLICT DO NOTHING;
+INSERT INTO deciders ("decider", "event") VALUES ('Order', 'OrderCancelled') ON CONFLICT DO NOTHING;
+INSERT INTO deciders ("decider", "event") VALUES ('Inventory', 'InventoryRestocked') ON CONFLICT DO NOTHING;
+INSERT INTO deciders ("decider", "event") VALUES ('Inventory', 'StockReserved') ON CONFLICT DO NOTHING;
+INSERT INTO deciders ("decider", "event") VALUES ('Inventory', 'StockReleased') ON CONFLICT DO NOTHING;
+-- The schema check of the inserted events (the `t_check_event_schema` trigger), regenerated with the new events
+SELECT fmodel_create_event_schema_check();
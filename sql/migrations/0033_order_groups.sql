@@ -0,0 +1,20 @@
+-- Order groups: the order spanning multiple restaurants is split into the (sub) orders per restaurant (`RestaurantOrderSplit`), linked by the group
+INSERT INTO deciders ("decider", "event") VALUES ('Restaurant', 'RestaurantOrderSplit') ON CONFLICT DO NOTHING;
+-- The schema check of the inserted events (the `t_check_event_schema` trigger), regenerated with the new event
+SELECT fmodel_create_event_schema_check();
+
+-- Order groups: the projection of the (sub) orders of the groups, with the combined status, registered as a projection
+-- The existing orders are not grouped (they were placed at the single restaurant)
+CREATE TABLE IF NOT EXISTS order_groups
+(
+    id            UUID PRIMARY KEY,
+    data          JSONB,
+    last_event_id UUID,
+    last_offset   BIGINT
+);
+CREATE INDEX IF NOT EXISTS order_groups_orders_index ON order_groups USING GIN ((data -> 'orders') jsonb_path_ops);
+
+INSERT INTO projections (projection) VALUES ('order_groups') ON CONFLICT DO NOTHING;
+
+DROP TRIGGER IF EXISTS order_groups_event_handler_trigger ON events;
+CREATE TRIGGER order_groups_event_handler_trigger AFTER INSERT ON events FOR EACH ROW EXECUTE PROCEDURE handle_order_group_events();
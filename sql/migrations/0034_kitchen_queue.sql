@@ -0,0 +1,17 @@
+-- Kitchen queue: the projection of the open orders per restaurant, with the time they were placed, registered as a projection
+-- The existing open orders are projected by the backfill (`call backfill_projection('kitchen_queue', 1000)`)
+CREATE TABLE IF NOT EXISTS kitchen_queue
+(
+    order_id      UUID PRIMARY KEY,
+    restaurant_id UUID        NOT NULL,
+    data          JSONB,
+    placed_at     TIMESTAMPTZ NOT NULL,
+    last_event_id UUID,
+    last_offset   BIGINT
+);
+CREATE INDEX IF NOT EXISTS kitchen_queue_restaurant_index ON kitchen_queue (restaurant_id, placed_at);
+
+INSERT INTO projections (projection) VALUES ('kitchen_queue') ON CONFLICT DO NOTHING;
+
+DROP TRIGGER IF EXISTS kitchen_queue_event_handler_trigger ON events;
+CREATE TRIGGER kitchen_queue_event_handler_trigger AFTER INSERT ON events FOR EACH ROW EXECUTE PROCEDURE handle_kitchen_queue_events();
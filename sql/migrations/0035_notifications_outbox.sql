@@ -0,0 +1,22 @@
+-- Customer notifications: the customer of the order (`customer_identifier` of `PlaceOrder`, `OrderPlaced`, `CreateOrder`, `OrderCreated` and `RestaurantOrderSplit`)
+-- The schema check of the inserted events (the `t_check_event_schema` trigger), regenerated with the new fields
+SELECT fmodel_create_event_schema_check();
+
+-- The outbox of the customer notifications: the intents to notify the customers of the status transitions of their orders (see `write_notifications`), claimed by the relay of the push-notification service (see `claim_notifications`)
+CREATE TABLE IF NOT EXISTS notifications_outbox
+(
+    "id"          BIGSERIAL PRIMARY KEY,
+    -- the target customer (`customer_identifier` of the order)
+    "customer_id" UUID                     NOT NULL,
+    "order_id"    UUID                     NOT NULL,
+    -- `OrderAccepted`, `OrderPrepared`, `OrderCancelled` or `OrderRejected`
+    "template"    TEXT                     NOT NULL,
+    -- the template and its parameters (the order and its restaurant)
+    "payload"     JSONB                    NOT NULL,
+    -- the event the notification reacts to: at most one notification per event
+    "event_id"    UUID                     NOT NULL UNIQUE,
+    "created_at"  TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    -- NULL until the notification is claimed by the relay
+    "relayed_at"  TIMESTAMP WITH TIME ZONE NULL
+);
+CREATE INDEX IF NOT EXISTS notifications_outbox_pending_index ON notifications_outbox ("id") WHERE "relayed_at" IS NULL;
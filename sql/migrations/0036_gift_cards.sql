@@ -0,0 +1,8 @@
+-- Gift cards: the store credit issued (`GiftCardIssued`), redeemed by the orders (`GiftCardRedeemed`) and refunded back to the gift card (`GiftCardRefunded`)
+INSERT INTO deciders ("decider", "event") VALUES ('GiftCard', 'GiftCardIssued') ON CONFLICT DO NOTHING;
+INSERT INTO deciders ("decider", "event") VALUES ('GiftCard', 'GiftCardRedeemed') ON CONFLICT DO NOTHING;
+INSERT INTO deciders ("decider", "event") VALUES ('GiftCard', 'GiftCardRefunded') ON CONFLICT DO NOTHING;
+-- The schema check of the inserted events (the `t_check_event_schema` trigger), regenerated with the new events
+SELECT fmodel_create_event_schema_check();
+-- The `fmodel_event_stream` view, regenerated with the restaurant and the order of the gift card payments
+SELECT fmodel_create_event_stream_view();
@@ -0,0 +1,27 @@
+-- Courier locations: the high-frequency (telemetry) events of the couriers (`CourierLocationUpdated`), downsampled or written to the ring buffer (see `fmodel.courier_location_policy`)
+INSERT INTO deciders ("decider", "event") VALUES ('Courier', 'CourierLocationUpdated') ON CONFLICT DO NOTHING;
+-- The schema check of the inserted events (the `t_check_event_schema` trigger), regenerated with the new event
+SELECT fmodel_create_event_schema_check();
+
+-- The ring buffer of the courier locations (`fmodel.courier_location_policy` = `buffer`): the last `fmodel.courier_location_buffer_size` locations of every courier, written instead of the `CourierLocationUpdated` events (see `courier_track`)
+-- The head of the ring buffer: the sequence of the last location of the courier
+CREATE TABLE IF NOT EXISTS courier_location_heads
+(
+    "store_id"   TEXT   NOT NULL DEFAULT fmodel_store_id(),
+    "courier_id" UUID   NOT NULL,
+    "sequence"   BIGINT NOT NULL,
+    PRIMARY KEY ("store_id", "courier_id")
+);
+-- The slots of the ring buffer (`sequence` modulo the size of the buffer), overwritten by the newer locations
+CREATE TABLE IF NOT EXISTS courier_locations
+(
+    "store_id"    TEXT                     NOT NULL DEFAULT fmodel_store_id(),
+    "courier_id"  UUID                     NOT NULL,
+    "slot"        BIGINT                   NOT NULL,
+    "sequence"    BIGINT                   NOT NULL,
+    -- microdegrees
+    "latitude"    INTEGER                  NOT NULL,
+    "longitude"   INTEGER                  NOT NULL,
+    "recorded_at" TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY ("store_id", "courier_id", "slot")
+);
@@ -0,0 +1,7 @@
+-- The frozen decider types (see `freeze_decider`): the new commands of the decider type are rejected, e.g. during the migration or the incident
+CREATE TABLE IF NOT EXISTS frozen_deciders
+(
+    "decider"   TEXT PRIMARY KEY,
+    "frozen_at" TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    "frozen_by" TEXT                     NOT NULL DEFAULT current_user
+);
@@ -0,0 +1,31 @@
+-- Events: the events decided by the command are saved with the id of the command (`command_id`), and deduplicated by it
+-- The duplicate is the event with the same payload, decided by the same command (`command_id`, the id of the command delivered again is the same), in the same decider stream
+CREATE OR REPLACE FUNCTION check_duplicate_event() RETURNS trigger AS
+'
+    DECLARE
+        mode TEXT := lower(COALESCE(current_setting(''fmodel.deduplication'', TRUE), ''off''));
+    BEGIN
+        IF mode NOT IN (''reject'', ''skip'') THEN
+            RETURN NEW;
+        END IF;
+        NEW.payload_hash := sha256(convert_to(fmodel_canonical_json(NEW.data), ''UTF8''));
+        IF EXISTS(SELECT 1
+                  FROM events
+                  WHERE NEW.decider_id = decider_id
+                    AND NEW.decider = decider
+                    AND NEW.store_id = store_id
+                    AND NEW.command_id IS NOT DISTINCT FROM command_id
+                    AND NEW.payload_hash = payload_hash)
+        THEN
+            IF mode = ''skip'' THEN
+                RETURN NULL;
+            END IF;
+            RAISE EXCEPTION ''duplicate event: the event with the same payload is already appended to the decider stream'';
+        END IF;
+        RETURN NEW;
+    END;
+'
+    LANGUAGE plpgsql;
+
+DROP INDEX IF EXISTS payload_hash_index;
+CREATE INDEX IF NOT EXISTS payload_hash_index ON events ("decider_id", "command_id", "payload_hash") WHERE "payload_hash" IS NOT NULL;
@@ -0,0 +1,41 @@
+-- The projection rows are keyed by the logical event store (`fmodel.store_id`): the events of every store are projected, into the rows of their own store
+-- The existing rows are in the `default` store; the constant default does not rewrite the tables
+ALTER TABLE restaurants ADD COLUMN IF NOT EXISTS store_id TEXT NOT NULL DEFAULT 'default';
+ALTER TABLE restaurants ALTER COLUMN store_id SET DEFAULT fmodel_store_id();
+ALTER TABLE restaurants DROP CONSTRAINT IF EXISTS restaurants_pkey, ADD PRIMARY KEY (store_id, id);
+
+ALTER TABLE restaurants_search ADD COLUMN IF NOT EXISTS store_id TEXT NOT NULL DEFAULT 'default';
+ALTER TABLE restaurants_search ALTER COLUMN store_id SET DEFAULT fmodel_store_id();
+ALTER TABLE restaurants_search DROP CONSTRAINT IF EXISTS restaurants_search_pkey, ADD PRIMARY KEY (store_id, id);
+
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS store_id TEXT NOT NULL DEFAULT 'default';
+ALTER TABLE orders ALTER COLUMN store_id SET DEFAULT fmodel_store_id();
+ALTER TABLE orders DROP CONSTRAINT IF EXISTS orders_pkey, ADD PRIMARY KEY (store_id, id);
+
+ALTER TABLE orders_archive ADD COLUMN IF NOT EXISTS store_id TEXT NOT NULL DEFAULT 'default';
+ALTER TABLE orders_archive ALTER COLUMN store_id SET DEFAULT fmodel_store_id();
+ALTER TABLE orders_archive DROP CONSTRAINT IF EXISTS orders_archive_pkey, ADD PRIMARY KEY (store_id, id);
+
+ALTER TABLE restaurant_daily_orders ADD COLUMN IF NOT EXISTS store_id TEXT NOT NULL DEFAULT 'default';
+ALTER TABLE restaurant_daily_orders ALTER COLUMN store_id SET DEFAULT fmodel_store_id();
+ALTER TABLE restaurant_daily_orders DROP CONSTRAINT IF EXISTS restaurant_daily_orders_pkey, ADD PRIMARY KEY (store_id, restaurant_id, day);
+
+ALTER TABLE restaurant_order_board ADD COLUMN IF NOT EXISTS store_id TEXT NOT NULL DEFAULT 'default';
+ALTER TABLE restaurant_order_board ALTER COLUMN store_id SET DEFAULT fmodel_store_id();
+ALTER TABLE restaurant_order_board DROP CONSTRAINT IF EXISTS restaurant_order_board_pkey, ADD PRIMARY KEY (store_id, id);
+
+ALTER TABLE payments ADD COLUMN IF NOT EXISTS store_id TEXT NOT NULL DEFAULT 'default';
+ALTER TABLE payments ALTER COLUMN store_id SET DEFAULT fmodel_store_id();
+ALTER TABLE payments DROP CONSTRAINT IF EXISTS payments_pkey, ADD PRIMARY KEY (store_id, order_id);
+
+ALTER TABLE order_groups ADD COLUMN IF NOT EXISTS store_id TEXT NOT NULL DEFAULT 'default';
+ALTER TABLE order_groups ALTER COLUMN store_id SET DEFAULT fmodel_store_id();
+ALTER TABLE order_groups DROP CONSTRAINT IF EXISTS order_groups_pkey, ADD PRIMARY KEY (store_id, id);
+
+ALTER TABLE kitchen_queue ADD COLUMN IF NOT EXISTS store_id TEXT NOT NULL DEFAULT 'default';
+ALTER TABLE kitchen_queue ALTER COLUMN store_id SET DEFAULT fmodel_store_id();
+ALTER TABLE kitchen_queue DROP CONSTRAINT IF EXISTS kitchen_queue_pkey, ADD PRIMARY KEY (store_id, order_id);
+DROP INDEX IF EXISTS kitchen_queue_restaurant_index;
+CREATE INDEX IF NOT EXISTS kitchen_queue_restaurant_index ON kitchen_queue (store_id, restaurant_id, placed_at);
+
+-- The events of the other stores appended so far were not projected: rebuild the projections (`rebuild_all_views`) to project them
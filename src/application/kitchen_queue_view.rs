@@ -0,0 +1,12 @@
+use crate::domain::api::OrderEvent;
+use crate::domain::kitchen_queue_view::{KitchenOrder, KitchenQueueView};
+use crate::framework::application::materialized_view::MaterializedView;
+use crate::infrastructure::kitchen_queue_repository::KitchenQueueRepository;
+
+/// A convenient type alias for the kitchen queue materialized view.
+pub type KitchenQueueMaterializedView<'a> = MaterializedView<
+    Option<KitchenOrder>,
+    OrderEvent,
+    KitchenQueueRepository,
+    KitchenQueueView<'a>,
+>;
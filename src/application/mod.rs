@@ -1,3 +1,15 @@
+pub mod kitchen_queue_view;
+pub mod order_group_view;
 pub mod order_materialized_view;
+pub mod order_payments_view;
 pub mod order_restaurant_aggregate;
+#[cfg(feature = "bench")]
+pub mod order_restaurant_bench;
+pub mod order_restaurant_fixtures;
+pub mod order_restaurant_hooks;
+pub mod order_restaurant_middleware;
+pub mod order_restaurant_projector;
+pub mod order_restaurant_validators;
+pub mod restaurant_daily_orders_view;
 pub mod restaurant_materialized_view;
+pub mod restaurant_order_board_view;
@@ -0,0 +1,8 @@
+use crate::domain::api::OrderEvent;
+use crate::domain::order_group_view::{OrderGroupState, OrderGroupView};
+use crate::framework::application::materialized_view::MaterializedView;
+use crate::infrastructure::order_group_repository::OrderGroupRepository;
+
+/// A convenient type alias for the order group materialized view.
+pub type OrderGroupMaterializedView<'a> =
+    MaterializedView<Option<OrderGroupState>, OrderEvent, OrderGroupRepository, OrderGroupView<'a>>;
@@ -0,0 +1,13 @@
+use crate::domain::api::{OrderId, RestaurantEvent, RestaurantId};
+use crate::domain::order_payments_view::OrderPayments;
+use crate::framework::application::aggregating_view::AggregatingView;
+use crate::infrastructure::order_payments_repository::OrderPaymentsRepository;
+
+/// A convenient type alias for the order payments aggregating view.
+pub type OrderPaymentsView<'a> = AggregatingView<
+    'a,
+    RestaurantEvent,
+    (RestaurantId, OrderId),
+    OrderPayments,
+    OrderPaymentsRepository,
+>;
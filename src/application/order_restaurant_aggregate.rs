@@ -1,15 +1,50 @@
-use crate::domain::order_decider::Order;
+use crate::application::order_restaurant_hooks::order_restaurant_hooks;
+use crate::application::order_restaurant_validators::order_restaurant_validators;
 use crate::framework::application::event_sourced_aggregate::EventSourcedOrchestratingAggregate;
+use crate::framework::application::saga_rules::configurable_saga;
+use crate::framework::infrastructure::clock::PostgresClock;
 
-use crate::domain::restaurant_decider::Restaurant;
-use crate::domain::{Command, Event};
+use crate::domain::{
+    order_restaurant_decider, order_restaurant_saga, Command, Event, OrderAndRestaurantState,
+};
+use crate::infrastructure::exchange_rates::PostgresExchangeRates;
 use crate::infrastructure::order_restaurant_event_repository::OrderAndRestaurantEventRepository;
+use crate::infrastructure::stock_levels::PostgresStockLevels;
 
 /// A convenient type alias for the order and restaurant aggregate.
 pub type OrderAndRestaurantAggregate<'a> = EventSourcedOrchestratingAggregate<
     'a,
     Command,
-    (Option<Restaurant>, Option<Order>),
+    OrderAndRestaurantState,
     Event,
     OrderAndRestaurantEventRepository,
 >;
+
+/// The order and restaurant aggregate, combining the decider (with the clock, the stock levels and the exchange rates of the database) and the saga, with the validators run before the events are saved, and the hooks invoked after.
+/// The states of the hot streams are cached in the shared memory, if enabled (`fmodel.state_cache`).
+/// The reactions of the saga can be disabled at runtime (see the `saga_rules` table).
+pub fn order_restaurant_aggregate<'a>() -> OrderAndRestaurantAggregate<'a> {
+    OrderAndRestaurantAggregate::new(
+        OrderAndRestaurantEventRepository::new(),
+        order_restaurant_decider(PostgresClock, PostgresStockLevels, PostgresExchangeRates),
+        configurable_saga(order_restaurant_saga()),
+    )
+    .with_validators(order_restaurant_validators())
+    .with_state_cache()
+    .with_hooks(order_restaurant_hooks())
+}
+
+thread_local! {
+    /// The aggregate of the backend, constructed on the first command (see `cached_order_restaurant_aggregate`).
+    /// The leak is intentional: one aggregate per backend, freed by the operating system when the backend exits, and borrowed as `'static` by the command handlers.
+    /// A `static` `LazyLock` (or `OnceLock`) does not fit: it requires the aggregate to be `Sync`, and the boxed closures of the decider, the saga, the validators and the hooks are not.
+    static AGGREGATE: &'static OrderAndRestaurantAggregate<'static> =
+        Box::leak(Box::new(order_restaurant_aggregate()));
+}
+
+/// The order and restaurant aggregate of the backend: constructed once, lazily, and reused by all the commands handled by the backend.
+/// The aggregate is stateless (the saga rules, the clock and the configuration are read while the command is handled), so the repeated commands skip allocating the decider and the saga (the boxed closures), the validators and the hooks.
+/// The Postgres backend is single-threaded, so the aggregate is constructed once per backend (it lives as long as the backend does).
+pub fn cached_order_restaurant_aggregate() -> &'static OrderAndRestaurantAggregate<'static> {
+    AGGREGATE.with(|aggregate| *aggregate)
+}
@@ -0,0 +1,119 @@
+use crate::application::order_restaurant_aggregate::{
+    cached_order_restaurant_aggregate, order_restaurant_aggregate,
+};
+use crate::domain::api::{
+    ChangeRestaurantMenu, CreateRestaurant, MenuId, MenuItem, MenuItemId, MenuItemName, Money,
+    RestaurantId, RestaurantMenu, RestaurantMenuCuisine, RestaurantName,
+};
+use crate::domain::Command;
+use crate::framework::infrastructure::errors::ErrorMessage;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// The report of the benchmark: the throughput and the latency percentiles of the operations.
+pub struct BenchReport {
+    /// The benchmarked operation
+    pub operation: &'static str,
+    /// The number of the operations
+    pub operations: i64,
+    /// The total duration of the operations, in milliseconds
+    pub total_ms: f64,
+    /// The throughput, in operations per second
+    pub ops_per_sec: f64,
+    /// The latency percentiles and the maximum latency of the operations, in milliseconds
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+/// Appends `n` synthetic events: a new restaurant is created, and its menu is changed `n` times, one command at a time.
+/// Measures the command handling through the event repository (fetch, decide, save), without the middlewares.
+pub fn bench_append(n: i64) -> Result<BenchReport, ErrorMessage> {
+    let aggregate = cached_order_restaurant_aggregate();
+    let restaurant_id = RestaurantId(Uuid::new_v4());
+    aggregate.handle(&Command::CreateRestaurant(CreateRestaurant {
+        identifier: restaurant_id.clone(),
+        name: RestaurantName("Benchmark".to_string()),
+        menu: menu(0),
+    }))?;
+    let mut latencies = Vec::new();
+    let started = Instant::now();
+    for price in 1..=n.max(0) as u64 {
+        let command = Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
+            identifier: restaurant_id.clone(),
+            menu: menu(price),
+        });
+        let handled = Instant::now();
+        aggregate.handle(&command)?;
+        latencies.push(handled.elapsed());
+    }
+    Ok(report("append", latencies, started.elapsed()))
+}
+
+/// Replays the event stream of the decider `iterations` times: the events are fetched and folded into the current state.
+pub fn bench_replay(decider_id: &Uuid, iterations: i64) -> Result<BenchReport, ErrorMessage> {
+    let aggregate = cached_order_restaurant_aggregate();
+    let mut latencies = Vec::new();
+    let started = Instant::now();
+    for _ in 0..iterations.max(0) {
+        let replayed = Instant::now();
+        aggregate.state_at(decider_id, i64::MAX)?;
+        latencies.push(replayed.elapsed());
+    }
+    Ok(report("replay", latencies, started.elapsed()))
+}
+
+/// Constructs the aggregate `iterations` times, as the commands did before the aggregate was cached per backend (see `cached_order_restaurant_aggregate`).
+/// The latencies are the setup cost that the cached aggregate saves on every command.
+pub fn bench_construct(iterations: i64) -> BenchReport {
+    let mut latencies = Vec::new();
+    let started = Instant::now();
+    for _ in 0..iterations.max(0) {
+        let constructed = Instant::now();
+        drop(order_restaurant_aggregate());
+        latencies.push(constructed.elapsed());
+    }
+    report("construct", latencies, started.elapsed())
+}
+
+fn menu(price: u64) -> RestaurantMenu {
+    RestaurantMenu {
+        menu_id: MenuId(Uuid::new_v4()),
+        items: vec![MenuItem {
+            id: MenuItemId(Uuid::new_v4()),
+            name: MenuItemName("supa".to_string()),
+            price: Money(price),
+        }],
+        cuisine: RestaurantMenuCuisine::Other,
+        currency: None,
+    }
+}
+
+fn report(operation: &'static str, mut latencies: Vec<Duration>, total: Duration) -> BenchReport {
+    latencies.sort();
+    let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    let percentile = |p: f64| {
+        latencies
+            .get(((latencies.len() as f64 * p).ceil() as usize).saturating_sub(1))
+            .map(|latency| millis(*latency))
+            .unwrap_or_default()
+    };
+    BenchReport {
+        operation,
+        operations: latencies.len() as i64,
+        total_ms: millis(total),
+        ops_per_sec: if total.is_zero() {
+            0.0
+        } else {
+            latencies.len() as f64 / total.as_secs_f64()
+        },
+        p50_ms: percentile(0.50),
+        p95_ms: percentile(0.95),
+        p99_ms: percentile(0.99),
+        max_ms: latencies
+            .last()
+            .map(|latency| millis(*latency))
+            .unwrap_or_default(),
+    }
+}
@@ -0,0 +1,124 @@
+use crate::domain::api::{
+    CreateRestaurant, MarkOrderAsPrepared, MenuId, MenuItem, MenuItemId, MenuItemName, Money,
+    OrderId, OrderLineItem, OrderLineItemId, OrderLineItemQuantity, PlaceOrder, RestaurantId,
+    RestaurantMenu, RestaurantMenuCuisine, RestaurantName,
+};
+use crate::domain::Command;
+use crate::framework::infrastructure::errors::ErrorMessage;
+use uuid::Uuid;
+
+/// A fixture set: the commands seeding the event store (and the projections, via the event handlers) consistently.
+pub type Fixture = fn() -> Vec<Command>;
+
+/// The restaurant of the `restaurant_with_menu` fixture set.
+pub const RESTAURANT_WITH_MENU_ID: &str = "e48d4d9e-403e-453f-b1ba-328e0ce23737";
+
+/// The restaurant of the `restaurant_with_orders` fixture set.
+pub const RESTAURANT_WITH_ORDERS_ID: &str = "0b1c2d3e-4f5a-4b6c-8d7e-9f0a1b2c3d4e";
+
+/// The restaurant of the `finalized_streams` fixture set.
+pub const FINALIZED_RESTAURANT_ID: &str = "5a6b7c8d-9e0f-4a1b-8c2d-3e4f5a6b7c8d";
+
+/// The (final) order of the `finalized_streams` fixture set.
+pub const FINALIZED_ORDER_ID: &str = "9e8d7c6b-5a4f-4e3d-8c2b-1a0f9e8d7c6b";
+
+/// The number of the orders of the `restaurant_with_orders` fixture set.
+pub const RESTAURANT_ORDERS: u128 = 100;
+
+/// All the embedded fixture sets, by name.
+pub const FIXTURES: [(&str, Fixture); 3] = [
+    ("restaurant_with_menu", restaurant_with_menu),
+    ("restaurant_with_orders", restaurant_with_orders),
+    ("finalized_streams", finalized_streams),
+];
+
+/// Finds the commands of the fixture set.
+pub fn fixture_commands(name: &str) -> Result<Vec<Command>, ErrorMessage> {
+    FIXTURES
+        .iter()
+        .find(|(fixture, _)| *fixture == name)
+        .map(|(_, commands)| commands())
+        .ok_or(ErrorMessage {
+            message: "Unknown fixture: ".to_string() + name,
+            context: None,
+        })
+}
+
+/// The restaurant (`Pljeska`) with the menu of two items (`supa` for 10, `sarma` for 20).
+fn restaurant_with_menu() -> Vec<Command> {
+    vec![create_restaurant(RESTAURANT_WITH_MENU_ID, "Pljeska")]
+}
+
+/// The restaurant with 100 orders (created by the saga), one portion of `supa` each.
+fn restaurant_with_orders() -> Vec<Command> {
+    let mut commands = vec![create_restaurant(RESTAURANT_WITH_ORDERS_ID, "Busy Bistro")];
+    commands.extend((1..=RESTAURANT_ORDERS).map(|order| {
+        place_order(
+            RESTAURANT_WITH_ORDERS_ID,
+            Uuid::from_u128(0x7f3e_0000_0000_4000_8000_0000_0000_0000 + order),
+        )
+    }));
+    commands
+}
+
+/// The restaurant with the order that is prepared: the order stream is final (closed).
+fn finalized_streams() -> Vec<Command> {
+    let order_id = uuid(FINALIZED_ORDER_ID);
+    vec![
+        create_restaurant(FINALIZED_RESTAURANT_ID, "Closing Time"),
+        place_order(FINALIZED_RESTAURANT_ID, order_id),
+        Command::MarkOrderAsPrepared(MarkOrderAsPrepared {
+            identifier: OrderId(order_id),
+        }),
+    ]
+}
+
+fn uuid(id: &str) -> Uuid {
+    Uuid::parse_str(id).expect("valid fixture uuid")
+}
+
+/// The menu item id of both `supa` and `sarma`.
+fn menu_item_id() -> Uuid {
+    uuid("02f09a3f-1624-3b1d-8409-44eff7708210")
+}
+
+fn create_restaurant(id: &str, name: &str) -> Command {
+    Command::CreateRestaurant(CreateRestaurant {
+        identifier: RestaurantId(uuid(id)),
+        name: RestaurantName(name.to_string()),
+        menu: RestaurantMenu {
+            menu_id: MenuId(menu_item_id()),
+            items: vec![
+                MenuItem {
+                    id: MenuItemId(menu_item_id()),
+                    name: MenuItemName("supa".to_string()),
+                    price: Money(10),
+                },
+                MenuItem {
+                    id: MenuItemId(menu_item_id()),
+                    name: MenuItemName("sarma".to_string()),
+                    price: Money(20),
+                },
+            ],
+            cuisine: RestaurantMenuCuisine::Vietnamese,
+            currency: None,
+        },
+    })
+}
+
+fn place_order(restaurant_id: &str, order_id: Uuid) -> Command {
+    Command::PlaceOrder(PlaceOrder {
+        identifier: RestaurantId(uuid(restaurant_id)),
+        order_identifier: OrderId(order_id),
+        line_items: vec![OrderLineItem {
+            id: OrderLineItemId(menu_item_id()),
+            quantity: OrderLineItemQuantity(1),
+            menu_item_id: MenuItemId(menu_item_id()),
+            name: MenuItemName("supa".to_string()),
+            price: None,
+            restaurant_identifier: None,
+        }],
+        group_identifier: None,
+        customer_identifier: None,
+    })
+}
@@ -0,0 +1,79 @@
+use crate::domain::notification_saga::notification_saga;
+use crate::domain::{event_to_order_event, Event};
+use crate::framework::application::hooks::HookRegistry;
+use crate::framework::domain::api::{DeciderType, EventType, Identifier};
+use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::framework::infrastructure::event_repository::EventPosition;
+use crate::framework::infrastructure::projector_wakeup::wake_projector_on_commit;
+use crate::infrastructure::notification_outbox_repository::NotificationOutboxRepository;
+use pgrx::{IntoDatum, PgBuiltInOids, Spi};
+
+/// The channel the saved events are announced on (`LISTEN fmodel_events`).
+pub const EVENTS_CHANNEL: &str = "fmodel_events";
+
+/// The hooks invoked after the events are successfully saved (in this order): notify, wake the projector, record the consistency token, write the customer notifications.
+pub fn order_restaurant_hooks() -> HookRegistry<Event> {
+    HookRegistry::new()
+        .register("notify", notify_events)
+        .register("wake_projector", wake_projector)
+        .register("consistency_token", record_consistency_token)
+        .register("notifications", write_notifications)
+}
+
+/// Announces the saved events on the `fmodel_events` channel, one notification per event: `{"event": ..., "decider": ..., "decider_id": ..., "offset": ...}`.
+/// The notifications are delivered to the listeners when the transaction commits.
+pub fn notify_events(events: &[(Event, EventPosition)]) -> Result<(), ErrorMessage> {
+    for (event, position) in events {
+        let payload = serde_json::json!({
+            "event": event.event_type(),
+            "decider": event.decider_type(),
+            "decider_id": event.identifier(),
+            "offset": position.offset,
+        })
+        .to_string();
+        Spi::run_with_args(
+            "SELECT pg_notify($1, $2)",
+            Some(vec![
+                (PgBuiltInOids::TEXTOID.oid(), EVENTS_CHANNEL.into_datum()),
+                (PgBuiltInOids::TEXTOID.oid(), payload.into_datum()),
+            ]),
+        )
+        .map_err(|err| ErrorMessage::spi("notify the events", None, &err))?;
+    }
+    Ok(())
+}
+
+/// Records the consistency token, the highest offset written by the session so far, in `fmodel.consistency_token` (see `consistency_token()` and `await_projection`).
+/// The setting is session-wide, so the client reads it after the commit; it is reverted if the transaction is rolled back.
+pub fn record_consistency_token(events: &[(Event, EventPosition)]) -> Result<(), ErrorMessage> {
+    let Some(offset) = events.iter().map(|(_, position)| position.offset).max() else {
+        return Ok(());
+    };
+    Spi::run_with_args(
+        "SELECT set_config('fmodel.consistency_token', GREATEST($1, NULLIF(current_setting('fmodel.consistency_token', TRUE), '')::BIGINT)::TEXT, FALSE)",
+        Some(vec![(PgBuiltInOids::INT8OID.oid(), offset.into_datum())]),
+    )
+    .map_err(|err| ErrorMessage::spi("record the consistency token", None, &err))
+}
+
+/// Wakes the projector background worker when the transaction commits, so the `async` projections apply the saved events immediately.
+pub fn wake_projector(_events: &[(Event, EventPosition)]) -> Result<(), ErrorMessage> {
+    wake_projector_on_commit();
+    Ok(())
+}
+
+/// Writes the customer notifications to the outbox (`notifications_outbox`): the reactions of the notification saga to the saved order events (accepted, prepared, cancelled or rejected).
+/// The notifications are written in the transaction of the events, so they are relayed only if the events are committed.
+pub fn write_notifications(events: &[(Event, EventPosition)]) -> Result<(), ErrorMessage> {
+    let saga = notification_saga();
+    let repository = NotificationOutboxRepository::new();
+    for (event, position) in events {
+        let Some(order_event) = event_to_order_event(event) else {
+            continue;
+        };
+        for notification in (saga.react)(&order_event) {
+            repository.save(&notification, position)?;
+        }
+    }
+    Ok(())
+}
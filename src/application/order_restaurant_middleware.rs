@@ -0,0 +1,30 @@
+use crate::domain::Command;
+use crate::framework::application::middleware::{
+    AuthorizationMiddleware, FreezeMiddleware, IdempotencyMiddleware, LoggingMiddleware,
+    MiddlewareChain, RateLimitMiddleware, SchemaCheckMiddleware,
+};
+use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::infrastructure::schema::{EXPECTED_INDEXES, EXPECTED_TABLES};
+
+/// The middleware chain the commands are run through before they are handled (in this order): schema check, authorization, frozen decider check, rate limiting, idempotency, logging.
+pub fn order_restaurant_middleware() -> MiddlewareChain<Command> {
+    MiddlewareChain::new()
+        .with(SchemaCheckMiddleware {
+            tables: &EXPECTED_TABLES,
+            indexes: &EXPECTED_INDEXES,
+        })
+        .with(AuthorizationMiddleware)
+        .with(FreezeMiddleware)
+        .with(RateLimitMiddleware)
+        .with(IdempotencyMiddleware)
+        .with(LoggingMiddleware)
+}
+
+/// Runs all the commands through the middleware chain. The first vetoed command fails the whole command handling.
+pub fn preprocess(commands: &[Command]) -> Result<(), ErrorMessage> {
+    let middleware = order_restaurant_middleware();
+    for command in commands {
+        middleware.run(command)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,314 @@
+use crate::application::kitchen_queue_view::KitchenQueueMaterializedView;
+use crate::application::order_group_view::OrderGroupMaterializedView;
+use crate::application::order_materialized_view::OrderMeterializedView;
+use crate::application::order_payments_view::OrderPaymentsView;
+use crate::application::restaurant_daily_orders_view::RestaurantDailyOrdersView;
+use crate::application::restaurant_materialized_view::RestaurantMeterializedView;
+use crate::application::restaurant_order_board_view::RestaurantOrderBoardMaterializedView;
+use crate::domain::api::RestaurantEvent;
+use crate::domain::kitchen_queue_view::kitchen_queue_view;
+use crate::domain::order_group_view::order_group_view;
+use crate::domain::order_payments_view::order_payments_view;
+use crate::domain::order_view::order_view;
+use crate::domain::restaurant_daily_orders_view::restaurant_daily_orders_view;
+use crate::domain::restaurant_order_board_view::restaurant_order_board_view;
+use crate::domain::restaurant_view::restaurant_view;
+use crate::domain::{event_to_order_event, event_to_restaurant_event, event_to_sum, Event};
+use crate::framework::application::projector::Projector;
+use crate::framework::domain::api::{Identifier, IsFinal};
+use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::framework::infrastructure::event_repository::EventPosition;
+use crate::framework::infrastructure::projection_repository::{
+    ProjectionMode, ProjectionRebuild, ProjectionRepository,
+};
+use crate::infrastructure::kitchen_queue_repository::KitchenQueueRepository;
+use crate::infrastructure::order_group_repository::OrderGroupRepository;
+use crate::infrastructure::order_payments_repository::OrderPaymentsRepository;
+use crate::infrastructure::order_restaurant_projection_repository::OrderAndRestaurantProjectionRepository;
+use crate::infrastructure::order_view_state_repository::OrderViewStateRepository;
+use crate::infrastructure::restaurant_daily_orders_repository::RestaurantDailyOrdersRepository;
+use crate::infrastructure::restaurant_order_board_repository::RestaurantOrderBoardRepository;
+use crate::infrastructure::restaurant_search_repository::RestaurantSearchRepository;
+use crate::infrastructure::restaurant_view_state_repository::RestaurantViewStateRepository;
+use fmodel_rust::Sum;
+use pgrx::notice;
+
+/// A convenient type alias for the order and restaurant projector.
+pub type OrderAndRestaurantProjector = Projector<Event, OrderAndRestaurantProjectionRepository>;
+
+/// A convenient type alias for the event handler of a projection.
+pub type ProjectionHandler = fn(&Event, &EventPosition) -> Result<(), ErrorMessage>;
+
+/// The name of the restaurant projection / materialized view table.
+pub const RESTAURANT_PROJECTION: &str = "restaurants";
+
+/// The name of the order projection / materialized view table.
+pub const ORDER_PROJECTION: &str = "orders";
+
+/// The name of the restaurant daily orders (aggregating) projection table.
+pub const RESTAURANT_DAILY_ORDERS_PROJECTION: &str = "restaurant_daily_orders";
+
+/// The name of the restaurant order board (cross-domain) projection table.
+pub const RESTAURANT_ORDER_BOARD_PROJECTION: &str = "restaurant_order_board";
+
+/// The name of the order payments (aggregating) projection table.
+pub const PAYMENTS_PROJECTION: &str = "payments";
+
+/// The name of the order group projection table.
+pub const ORDER_GROUPS_PROJECTION: &str = "order_groups";
+
+/// The name of the kitchen queue projection table.
+pub const KITCHEN_QUEUE_PROJECTION: &str = "kitchen_queue";
+
+/// All registered projections, together with their event handlers.
+pub const PROJECTIONS: [(&str, ProjectionHandler); 7] = [
+    (RESTAURANT_PROJECTION, project_restaurant_event),
+    (ORDER_PROJECTION, project_order_event),
+    (
+        RESTAURANT_DAILY_ORDERS_PROJECTION,
+        project_restaurant_daily_orders_event,
+    ),
+    (
+        RESTAURANT_ORDER_BOARD_PROJECTION,
+        project_restaurant_order_board_event,
+    ),
+    (PAYMENTS_PROJECTION, project_payments_event),
+    (ORDER_GROUPS_PROJECTION, project_order_group_event),
+    (KITCHEN_QUEUE_PROJECTION, project_kitchen_queue_event),
+];
+
+/// Finds the event handler of the registered projection.
+pub fn projection_handler(projection: &str) -> Result<ProjectionHandler, ErrorMessage> {
+    PROJECTIONS
+        .iter()
+        .find(|(name, _)| *name == projection)
+        .map(|(_, handler)| *handler)
+        .ok_or(ErrorMessage {
+            message: "Unknown projection: ".to_string() + projection,
+            context: None,
+        })
+}
+
+/// Handles the event with the restaurant materialized view, and updates the restaurant search projection (`restaurants_search`) if the name, the menu or the availability of the menu items changed (the merged restaurant is removed from the search). Non-restaurant events are ignored.
+pub fn project_restaurant_event(
+    event: &Event,
+    position: &EventPosition,
+) -> Result<(), ErrorMessage> {
+    match event_to_restaurant_event(event) {
+        // If the event is not a Restaurant event, we do nothing
+        None => Ok(()),
+        // If the event is a Restaurant event, we handle it
+        Some(e) => {
+            let state = RestaurantMeterializedView::new(
+                RestaurantViewStateRepository::new(),
+                restaurant_view(),
+            )
+            .handle(&e, position)?;
+            match (&e, state) {
+                (RestaurantEvent::OrderPlaced(_), _)
+                | (RestaurantEvent::OrderLineItemsModified(_), _)
+                | (RestaurantEvent::PaymentCaptured(_), _)
+                | (RestaurantEvent::PaymentRefunded(_), _)
+                | (RestaurantEvent::CapacityChanged(_), _)
+                | (RestaurantEvent::OrderClosed(_), _)
+                | (RestaurantEvent::OrderCancelled(_), _)
+                | (RestaurantEvent::OrderSplit(_), _)
+                | (RestaurantEvent::OpeningHoursChanged(_), _)
+                | (RestaurantEvent::OrdersReceived(_), _)
+                | (RestaurantEvent::PromotionApplied(_), _)
+                | (RestaurantEvent::PromotionWithdrawn(_), _)
+                | (_, None) => {}
+                (RestaurantEvent::OrdersMigrated(event), _) => {
+                    if event.r#final {
+                        RestaurantSearchRepository::new().delete(&event.identifier)?
+                    }
+                }
+                (_, Some(state)) => RestaurantSearchRepository::new().save(&state)?,
+            }
+            archive_if_final(RESTAURANT_PROJECTION, event)
+        }
+    }
+}
+
+/// Handles the event with the order materialized view. Non-order events are ignored.
+pub fn project_order_event(event: &Event, position: &EventPosition) -> Result<(), ErrorMessage> {
+    match event_to_order_event(event) {
+        // If the event is not an Order event, we do nothing
+        None => Ok(()),
+        // If the event is an Order event, we handle it
+        Some(e) => {
+            OrderMeterializedView::new(OrderViewStateRepository::new(), order_view())
+                .handle(&e, position)?;
+            archive_if_final(ORDER_PROJECTION, event)
+        }
+    }
+}
+
+/// Handles the event with the restaurant daily orders aggregating view. Non-order events are ignored.
+pub fn project_restaurant_daily_orders_event(
+    event: &Event,
+    position: &EventPosition,
+) -> Result<(), ErrorMessage> {
+    match event_to_order_event(event) {
+        // If the event is not an Order event, we do nothing
+        None => Ok(()),
+        // If the event is an Order event, we count it
+        Some(e) => RestaurantDailyOrdersView::new(
+            RestaurantDailyOrdersRepository::new(),
+            restaurant_daily_orders_view(),
+        )
+        .handle(&e, position),
+    }
+}
+
+/// Handles the event with the restaurant order board (cross-domain) materialized view: both the restaurant and the order events are consumed. The promotion and the inventory events are ignored.
+pub fn project_restaurant_order_board_event(
+    event: &Event,
+    position: &EventPosition,
+) -> Result<(), ErrorMessage> {
+    match event_to_sum(event) {
+        // If the event is a Promotion or an Inventory event, we do nothing
+        Sum::Second(_) => Ok(()),
+        // If the event is a Restaurant or an Order event, we handle it
+        Sum::First(e) => RestaurantOrderBoardMaterializedView::new(
+            RestaurantOrderBoardRepository::new(),
+            restaurant_order_board_view(),
+        )
+        .handle(&e, position)
+        .map(|_| ()),
+    }
+}
+
+/// Handles the event with the order payments aggregating view. Non-restaurant events are ignored.
+pub fn project_payments_event(event: &Event, position: &EventPosition) -> Result<(), ErrorMessage> {
+    match event_to_restaurant_event(event) {
+        // If the event is not a Restaurant event, we do nothing
+        None => Ok(()),
+        // If the event is a Restaurant event, we sum the payments
+        Some(e) => OrderPaymentsView::new(OrderPaymentsRepository::new(), order_payments_view())
+            .handle(&e, position),
+    }
+}
+
+/// Handles the event with the order group materialized view: the (sub) orders split across the restaurants, with the combined status. Non-order events are ignored.
+pub fn project_order_group_event(
+    event: &Event,
+    position: &EventPosition,
+) -> Result<(), ErrorMessage> {
+    match event_to_order_event(event) {
+        // If the event is not an Order event, we do nothing
+        None => Ok(()),
+        // If the event is an Order event, we handle it
+        Some(e) => OrderGroupMaterializedView::new(OrderGroupRepository::new(), order_group_view())
+            .handle(&e, position)
+            .map(|_| ()),
+    }
+}
+
+/// Handles the event with the kitchen queue materialized view: the open orders per restaurant, in the order they were placed. Non-order events are ignored.
+pub fn project_kitchen_queue_event(
+    event: &Event,
+    position: &EventPosition,
+) -> Result<(), ErrorMessage> {
+    match event_to_order_event(event) {
+        // If the event is not an Order event, we do nothing
+        None => Ok(()),
+        // If the event is an Order event, we handle it
+        Some(e) => {
+            KitchenQueueMaterializedView::new(KitchenQueueRepository::new(), kitchen_queue_view())
+                .handle(&e, position)
+                .map(|_| ())
+        }
+    }
+}
+
+/// Archives the projection row of the stream, if the event is final and the archival is enabled for the projection.
+fn archive_if_final(projection: &str, event: &Event) -> Result<(), ErrorMessage> {
+    let repository = OrderAndRestaurantProjectionRepository::new();
+    if event.is_final() && repository.fetch_archival(projection)? {
+        repository.archive(projection, &event.identifier())?;
+    }
+    Ok(())
+}
+
+/// Applies the next batch of events to every projection in the `async` mode.
+/// Returns the number of events applied.
+pub fn project_async_projections(batch_size: i64) -> Result<i64, ErrorMessage> {
+    let projector = OrderAndRestaurantProjector::new(OrderAndRestaurantProjectionRepository::new());
+    let mut applied = 0;
+    for (projection, handler) in PROJECTIONS {
+        if projector.mode(projection)? == ProjectionMode::Async {
+            applied += projector.project(projection, batch_size, handler)?;
+        }
+    }
+    Ok(applied)
+}
+
+/// Rebuilds all the projections from scratch, one batch of events (per projection) at a time.
+/// The rebuild is started if there is no unfinished rebuild, otherwise the unfinished rebuild is resumed.
+/// Returns the progress of the rebuild per projection (`None` if there is no rebuild of the projection in progress).
+pub fn rebuild_projections(
+    batch_size: i64,
+) -> Result<Vec<(&'static str, Option<ProjectionRebuild>)>, ErrorMessage> {
+    let projector = OrderAndRestaurantProjector::new(OrderAndRestaurantProjectionRepository::new());
+    let mut unfinished = false;
+    for (projection, _) in PROJECTIONS {
+        unfinished = unfinished || projector.rebuild_progress(projection)?.is_some();
+    }
+    if !unfinished {
+        for (projection, _) in PROJECTIONS {
+            projector.start_rebuild(projection)?;
+        }
+    }
+    let mut results = Vec::new();
+    for (projection, handler) in PROJECTIONS {
+        let progress = projector.rebuild(projection, batch_size, handler)?;
+        match progress {
+            Some(rebuild) if rebuild.finished => notice!(
+                "Rebuilding `{}`: finished, {} events applied",
+                projection,
+                rebuild.events_applied
+            ),
+            Some(rebuild) => notice!(
+                "Rebuilding `{}`: {} events applied, up to offset {}",
+                projection,
+                rebuild.events_applied,
+                rebuild.target_offset
+            ),
+            None => {}
+        }
+        results.push((projection, progress));
+    }
+    Ok(results)
+}
+
+/// Backfills the (newly registered) projection from the historical events, one batch of events at a time: the events are replayed through this projection only.
+/// The backfill is started (the projection is cleared, and updated asynchronously from the first event) if there is no unfinished backfill/rebuild of the projection, otherwise it is resumed from the checkpoint.
+/// Once the projection has caught up with the event store, it is switched back to its live mode (`sync`, `async` or `statement`) in the same transaction.
+/// Returns the progress of the backfill.
+pub fn backfill_projection(
+    projection: &str,
+    batch_size: i64,
+) -> Result<ProjectionRebuild, ErrorMessage> {
+    let handler = projection_handler(projection)?;
+    let projector = OrderAndRestaurantProjector::new(OrderAndRestaurantProjectionRepository::new());
+    if projector.rebuild_progress(projection)?.is_none() {
+        projector.start_rebuild(projection)?;
+    }
+    let rebuild = projector
+        .rebuild(projection, batch_size, handler)?
+        .ok_or(ErrorMessage {
+            message: "Failed to backfill the projection `".to_string()
+                + projection
+                + "`: the backfill was not started",
+            context: None,
+        })?;
+    if rebuild.finished {
+        notice!(
+            "Backfilling `{}`: finished, {} events applied",
+            projection,
+            rebuild.events_applied
+        );
+    }
+    Ok(rebuild)
+}
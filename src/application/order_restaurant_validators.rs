@@ -0,0 +1,110 @@
+use crate::domain::api::{MigratedOrder, OrderLineItem, RestaurantMenu};
+use crate::domain::Event;
+use crate::framework::application::validators::ValidatorRegistry;
+
+/// The validators run before the new events are saved: they catch the decider bugs before the invalid events pollute the event log.
+pub fn order_restaurant_validators() -> ValidatorRegistry<Event> {
+    ValidatorRegistry::new()
+        .register("RestaurantCreated", |event: &Event| match event {
+            Event::RestaurantCreated(evt) => validate_menu(&evt.menu),
+            _ => vec![],
+        })
+        .register("RestaurantMenuChanged", |event: &Event| match event {
+            Event::RestaurantMenuChanged(evt) => validate_menu(&evt.menu),
+            _ => vec![],
+        })
+        .register("OrderPlaced", |event: &Event| match event {
+            Event::OrderPlaced(evt) => validate_line_items(&evt.line_items),
+            _ => vec![],
+        })
+        .register("OrderCreated", |event: &Event| match event {
+            Event::OrderCreated(evt) => validate_line_items(&evt.line_items),
+            _ => vec![],
+        })
+        .register("OrderLineItemsModified", |event: &Event| match event {
+            Event::OrderLineItemsModified(evt) => validate_line_items(&evt.line_items),
+            _ => vec![],
+        })
+        .register("OrderLineItemsUpdated", |event: &Event| match event {
+            Event::OrderLineItemsUpdated(evt) => validate_line_items(&evt.line_items),
+            _ => vec![],
+        })
+        .register("PaymentCaptured", |event: &Event| match event {
+            Event::PaymentCaptured(evt) if evt.amount.0 == 0 => {
+                vec!["the captured amount is zero".to_string()]
+            }
+            _ => vec![],
+        })
+        .register("PaymentRefunded", |event: &Event| match event {
+            Event::PaymentRefunded(evt) if evt.amount.0 == 0 => {
+                vec!["the refunded amount is zero".to_string()]
+            }
+            _ => vec![],
+        })
+        .register("RestaurantOrdersMigrated", |event: &Event| match event {
+            Event::RestaurantOrdersMigrated(evt) => validate_migrated_orders(&evt.orders),
+            _ => vec![],
+        })
+        .register("RestaurantOrdersReceived", |event: &Event| match event {
+            Event::RestaurantOrdersReceived(evt) => validate_migrated_orders(&evt.orders),
+            _ => vec![],
+        })
+}
+
+/// The menu items are named, and listed once.
+fn validate_menu(menu: &RestaurantMenu) -> Vec<String> {
+    let mut violations = Vec::new();
+    for (index, item) in menu.items.iter().enumerate() {
+        if item.name.0.trim().is_empty() {
+            violations
+                .push("the menu item `".to_string() + &item.id.0.to_string() + "` has no name");
+        }
+        if menu.items[..index].iter().any(|other| other.id == item.id) {
+            violations
+                .push("the menu item `".to_string() + &item.id.0.to_string() + "` is duplicated");
+        }
+    }
+    violations
+}
+
+/// The quantities of the line items are positive, and the line items are listed once.
+fn validate_line_items(line_items: &[OrderLineItem]) -> Vec<String> {
+    let mut violations = Vec::new();
+    for (index, item) in line_items.iter().enumerate() {
+        if item.quantity.0 == 0 {
+            violations
+                .push("the line item `".to_string() + &item.id.0.to_string() + "` has no quantity");
+        }
+        if line_items[..index].iter().any(|other| other.id == item.id) {
+            violations
+                .push("the line item `".to_string() + &item.id.0.to_string() + "` is duplicated");
+        }
+    }
+    violations
+}
+
+/// The payments of the migrated orders are consistent: the refunded amount never exceeds the captured amount, nor the captured amount the total.
+fn validate_migrated_orders(orders: &[MigratedOrder]) -> Vec<String> {
+    let mut violations = Vec::new();
+    for order in orders {
+        if order.refunded.0 > order.captured.0 {
+            violations.push(
+                "the order `".to_string()
+                    + &order.order_identifier.0.to_string()
+                    + "` is refunded more than captured",
+            );
+        }
+        if order
+            .total
+            .as_ref()
+            .is_some_and(|total| order.captured.0 > total.0)
+        {
+            violations.push(
+                "the order `".to_string()
+                    + &order.order_identifier.0.to_string()
+                    + "` is captured more than its total",
+            );
+        }
+    }
+    violations
+}
@@ -0,0 +1,13 @@
+use crate::domain::api::{OrderEvent, RestaurantId};
+use crate::domain::restaurant_daily_orders_view::RestaurantDailyOrders;
+use crate::framework::application::aggregating_view::AggregatingView;
+use crate::infrastructure::restaurant_daily_orders_repository::RestaurantDailyOrdersRepository;
+
+/// A convenient type alias for the restaurant daily orders aggregating view.
+pub type RestaurantDailyOrdersView<'a> = AggregatingView<
+    'a,
+    OrderEvent,
+    RestaurantId,
+    RestaurantDailyOrders,
+    RestaurantDailyOrdersRepository,
+>;
@@ -0,0 +1,15 @@
+use crate::domain::api::{OrderEvent, RestaurantEvent};
+use crate::domain::restaurant_order_board_view::{
+    RestaurantOrderBoardState, RestaurantOrderBoardView,
+};
+use crate::framework::application::materialized_view::MaterializedView;
+use crate::infrastructure::restaurant_order_board_repository::RestaurantOrderBoardRepository;
+use fmodel_rust::Sum;
+
+/// A convenient type alias for the restaurant order board (cross-domain) materialized view.
+pub type RestaurantOrderBoardMaterializedView<'a> = MaterializedView<
+    Option<RestaurantOrderBoardState>,
+    Sum<RestaurantEvent, OrderEvent>,
+    RestaurantOrderBoardRepository,
+    RestaurantOrderBoardView<'a>,
+>;
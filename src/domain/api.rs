@@ -1,4 +1,5 @@
 use crate::framework::domain::api::Identifier;
+use crate::framework::domain::clock::LocalTime;
 use pgrx::FromDatum;
 use pgrx::{PostgresEnum, PostgresType};
 use serde::{Deserialize, Serialize};
@@ -38,9 +39,41 @@ pub struct Reason(pub String);
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
 pub struct Money(pub u64);
 
+/// The (ISO 4217) code of the currency, e.g. `EUR`.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Currency(pub String);
+
+/// The exchange rate, scaled by 1 000 000 (e.g. `1 EUR = 1.08 USD` is `1_080_000`), so the events stay exact.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ExchangeRate(pub u64);
+
+impl ExchangeRate {
+    /// The scale of the rate: the rate of the currency to itself.
+    pub const SCALE: u64 = 1_000_000;
+
+    /// Converts the amount, rounded half up.
+    pub fn convert(&self, amount: &Money) -> Money {
+        let scale = u128::from(Self::SCALE);
+        let converted = (u128::from(amount.0) * u128::from(self.0) + scale / 2) / scale;
+        Money(u64::try_from(converted).unwrap_or(u64::MAX))
+    }
+}
+
+/// The amount converted to the (reference) currency, with the exchange rate it was converted by.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct ConvertedMoney {
+    pub amount: Money,
+    pub currency: Currency,
+    pub rate: ExchangeRate,
+}
+
 #[derive(PostgresType, Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
 pub struct MenuId(pub Uuid);
 
+/// The version of the restaurant menu: 1 for the menu the restaurant was created with, incremented by 1 on every menu change.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, Default)]
+pub struct RestaurantMenuVersion(pub u64);
+
 #[derive(PostgresType, Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
 pub struct MenuItemId(pub Uuid);
 
@@ -92,6 +125,50 @@ pub struct RestaurantMenu {
     pub menu_id: MenuId,
     pub items: Vec<MenuItem>,
     pub cuisine: RestaurantMenuCuisine,
+    /// The currency of the prices. The menus without the currency (e.g. created before the currencies were introduced) are not converted
+    #[serde(default)]
+    pub currency: Option<Currency>,
+}
+
+/// The weekly opening hours of a restaurant, in the local time of the (IANA) time zone of the restaurant (e.g. `Europe/Belgrade`)
+#[derive(PostgresType, Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct OpeningHours {
+    pub time_zone: String,
+    pub periods: Vec<OpeningPeriod>,
+}
+
+/// The opening period: the ISO day of the week (1 = Monday, ..., 7 = Sunday), and the local opening and closing times (minutes since midnight).
+/// The period closing at (or before) its opening time closes on the next day (e.g. from 18:00 to 02:00).
+#[derive(PostgresType, Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct OpeningPeriod {
+    pub day_of_week: u32,
+    pub opens_at: u32,
+    pub closes_at: u32,
+}
+
+impl OpeningHours {
+    /// Is the restaurant open at the local time?
+    pub fn is_open_at(&self, time: &LocalTime) -> bool {
+        self.periods.iter().any(|period| {
+            if period.opens_at < period.closes_at {
+                time.day_of_week == period.day_of_week
+                    && (period.opens_at..period.closes_at).contains(&time.minute_of_day)
+            } else {
+                (time.day_of_week == period.day_of_week && time.minute_of_day >= period.opens_at)
+                    || (time.day_of_week == period.day_of_week % 7 + 1
+                        && time.minute_of_day < period.closes_at)
+            }
+        })
+    }
+
+    /// Are the opening periods valid: the days of the week (1 - 7), and the times (0 - 1439)?
+    pub fn is_valid(&self) -> bool {
+        self.periods.iter().all(|period| {
+            (1..=7).contains(&period.day_of_week)
+                && period.opens_at < 24 * 60
+                && period.closes_at < 24 * 60
+        })
+    }
 }
 
 #[derive(PostgresType, Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
@@ -100,6 +177,215 @@ pub struct OrderLineItem {
     pub quantity: OrderLineItemQuantity,
     pub menu_item_id: MenuItemId,
     pub name: MenuItemName,
+    /// The unit price of the menu item, captured from the restaurant menu when the order is placed (frozen, the later menu changes do not alter it).
+    /// `None` for the menu items that are not on the menu, and for the orders placed before the pricing snapshot was introduced
+    #[serde(default)]
+    pub price: Option<Money>,
+    /// The restaurant of the menu item, if the order spans multiple restaurants. `None` for the restaurant the order is placed at
+    #[serde(default)]
+    pub restaurant_identifier: Option<RestaurantId>,
+}
+
+/// The total of the line items: the sum of the (frozen) unit prices multiplied by the quantities. `None` if any of the line items is not priced
+pub fn order_total(line_items: &[OrderLineItem]) -> Option<Money> {
+    line_items
+        .iter()
+        .map(|line_item| {
+            line_item
+                .price
+                .as_ref()
+                .map(|price| price.0 * u64::from(line_item.quantity.0))
+        })
+        .sum::<Option<u64>>()
+        .map(Money)
+}
+
+#[derive(PostgresType, Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct PromotionId(pub Uuid);
+impl fmt::Display for PromotionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Delegate the formatting to the inner Uuid
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(PostgresType, Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct PromotionName(pub String);
+
+/// The discount of the promotion, in percent of the (frozen) unit prices (1 - 100).
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DiscountPercentage(pub u32);
+
+impl DiscountPercentage {
+    /// The discount of the amount, rounded down (in favour of the restaurant).
+    pub fn discount(&self, amount: &Money) -> Money {
+        Money(amount.0 * u64::from(self.0) / 100)
+    }
+}
+
+/// The promotion of the restaurant: the discount of the menu items (all of them, if `menu_item_ids` is empty).
+#[derive(PostgresType, Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct RestaurantPromotion {
+    pub id: PromotionId,
+    pub name: PromotionName,
+    pub percentage: DiscountPercentage,
+    pub menu_item_ids: Vec<MenuItemId>,
+}
+
+impl RestaurantPromotion {
+    /// Does the promotion discount the menu item?
+    pub fn applies_to(&self, menu_item_id: &MenuItemId) -> bool {
+        self.menu_item_ids.is_empty() || self.menu_item_ids.contains(menu_item_id)
+    }
+}
+
+/// The discount of the order line item, by the promotion applied when the order was placed.
+#[derive(PostgresType, Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct AppliedDiscount {
+    pub promotion_id: PromotionId,
+    pub line_item_id: OrderLineItemId,
+    pub amount: Money,
+}
+
+/// The total of the line items, less the discounts. `None` if any of the line items is not priced
+pub fn discounted_total(
+    line_items: &[OrderLineItem],
+    discounts: &[AppliedDiscount],
+) -> Option<Money> {
+    let discount: u64 = discounts.iter().map(|discount| discount.amount.0).sum();
+    order_total(line_items).map(|total| Money(total.0.saturating_sub(discount)))
+}
+
+/// The order migrated between the restaurants (the restaurant records merged, or split): its payments and its capacity move with it.
+#[derive(PostgresType, Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct MigratedOrder {
+    pub order_identifier: OrderId,
+    /// `None` if any of the line items is not priced
+    pub total: Option<Money>,
+    pub captured: Money,
+    pub refunded: Money,
+    /// Is the order open (placed, and not yet closed)?
+    pub open: bool,
+}
+
+#[derive(PostgresType, Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct OrderGroupId(pub Uuid);
+impl fmt::Display for OrderGroupId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Delegate the formatting to the inner Uuid
+        write!(f, "{}", self.0)
+    }
+}
+
+impl OrderGroupId {
+    /// The (sub) order of the group at the restaurant: its identifier derived from the identifiers of the group and the restaurant (a custom, version 8 UUID), so the split is idempotent.
+    pub fn order_of(&self, restaurant_identifier: &RestaurantId) -> OrderId {
+        let mut bytes = *self.0.as_bytes();
+        for (byte, mask) in bytes.iter_mut().zip(restaurant_identifier.0.as_bytes()) {
+            *byte ^= mask;
+        }
+        OrderId(uuid::Builder::from_custom_bytes(bytes).into_uuid())
+    }
+}
+
+#[derive(PostgresType, Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct CustomerId(pub Uuid);
+impl fmt::Display for CustomerId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Delegate the formatting to the inner Uuid
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The (sub) order of the split order: the line items of one restaurant.
+#[derive(PostgresType, Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct SplitOrder {
+    pub restaurant_identifier: RestaurantId,
+    pub order_identifier: OrderId,
+    pub line_items: Vec<OrderLineItem>,
+}
+
+#[derive(PostgresType, Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct InventoryId(pub Uuid);
+impl fmt::Display for InventoryId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Delegate the formatting to the inner Uuid
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The namespace of the inventory identifiers, derived from the restaurant identifiers.
+const INVENTORY_NAMESPACE: Uuid = Uuid::from_u128(0x6f1e_2c3d_4b5a_4968_8776_a5b4_c3d2_e1f0);
+
+impl InventoryId {
+    /// The inventory of the restaurant: one per restaurant, its identifier derived from the identifier of the restaurant (a custom, version 8 UUID), so the saga addresses it by the restaurant of the order.
+    pub fn of(restaurant_identifier: &RestaurantId) -> Self {
+        let mut bytes = *restaurant_identifier.0.as_bytes();
+        for (byte, mask) in bytes.iter_mut().zip(INVENTORY_NAMESPACE.as_bytes()) {
+            *byte ^= mask;
+        }
+        InventoryId(uuid::Builder::from_custom_bytes(bytes).into_uuid())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct StockQuantity(pub u32);
+
+/// The stock of the menu item: the quantity on hand (in the inventory), or the quantity reserved (by an order).
+#[derive(PostgresType, Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct StockItem {
+    pub menu_item_id: MenuItemId,
+    pub quantity: StockQuantity,
+}
+
+impl StockItem {
+    /// The stock ordered by the line items: the quantities per menu item.
+    pub fn of_line_items(line_items: &[OrderLineItem]) -> Vec<StockItem> {
+        let mut items: Vec<StockItem> = Vec::new();
+        for line_item in line_items {
+            match items
+                .iter_mut()
+                .find(|item| item.menu_item_id == line_item.menu_item_id)
+            {
+                Some(item) => item.quantity.0 += line_item.quantity.0,
+                None => items.push(StockItem {
+                    menu_item_id: line_item.menu_item_id.to_owned(),
+                    quantity: StockQuantity(line_item.quantity.0),
+                }),
+            }
+        }
+        items
+    }
+}
+
+#[derive(PostgresType, Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct GiftCardId(pub Uuid);
+impl fmt::Display for GiftCardId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Delegate the formatting to the inner Uuid
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(PostgresType, Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct CourierId(pub Uuid);
+impl fmt::Display for CourierId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Delegate the formatting to the inner Uuid
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The latitude or the longitude, in the millionths of the degree (microdegrees, about 0.1 m), so the locations compare exactly.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Coordinate(pub i32);
+
+impl Coordinate {
+    /// The coordinate of the degrees, rounded to the microdegrees (`None` if the degrees are out of the range of the coordinates).
+    pub fn of_degrees(degrees: f64) -> Option<Coordinate> {
+        let microdegrees = (degrees * 1_000_000.0).round();
+        (microdegrees.abs() <= 180_000_000.0).then_some(Coordinate(microdegrees as i32))
+    }
 }
 
 #[derive(PostgresEnum, Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
@@ -122,6 +408,19 @@ pub enum RestaurantCommand {
     CreateRestaurant(CreateRestaurant),
     ChangeMenu(ChangeRestaurantMenu),
     PlaceOrder(PlaceOrder),
+    ModifyOrderLineItems(ModifyOrderLineItems),
+    CapturePayment(CapturePayment),
+    RefundPayment(RefundPayment),
+    ChangeCapacity(ChangeRestaurantCapacity),
+    CloseOrder(CloseRestaurantOrder),
+    MarkMenuItemUnavailable(MarkMenuItemUnavailable),
+    MarkMenuItemAvailable(MarkMenuItemAvailable),
+    ChangeOpeningHours(ChangeRestaurantOpeningHours),
+    MigrateOrders(MigrateRestaurantOrders),
+    ReceiveOrders(ReceiveRestaurantOrders),
+    ApplyPromotion(ApplyRestaurantPromotion),
+    WithdrawPromotion(WithdrawRestaurantPromotion),
+    CancelOrder(CancelRestaurantOrder),
 }
 /// Intent/Command to create a new restaurant
 #[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
@@ -138,12 +437,118 @@ pub struct ChangeRestaurantMenu {
     pub menu: RestaurantMenu,
 }
 
+/// Intent/Command to change the capacity of a restaurant: the maximum number of the open (placed, and not yet prepared) orders. `None` for no limit
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ChangeRestaurantCapacity {
+    pub identifier: RestaurantId,
+    pub max_concurrent_orders: Option<u32>,
+}
+
+/// Intent/Command to close the order of a restaurant, releasing its capacity (issued by the saga, once the order is prepared)
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct CloseRestaurantOrder {
+    pub identifier: RestaurantId,
+    pub order_identifier: OrderId,
+}
+
+/// Intent/Command to cancel the open order of a restaurant, releasing its capacity and its stock: cancelled by the customer, or `rejected` by the restaurant
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct CancelRestaurantOrder {
+    pub identifier: RestaurantId,
+    pub order_identifier: OrderId,
+    pub rejected: bool,
+}
+
+/// Intent/Command to mark the menu item of a restaurant as unavailable (86'd): it can not be ordered until it is marked as available again
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct MarkMenuItemUnavailable {
+    pub identifier: RestaurantId,
+    pub menu_item_id: MenuItemId,
+}
+
+/// Intent/Command to mark the (unavailable) menu item of a restaurant as available again
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct MarkMenuItemAvailable {
+    pub identifier: RestaurantId,
+    pub menu_item_id: MenuItemId,
+}
+
+/// Intent/Command to change the opening hours of a restaurant. `None` for always open
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ChangeRestaurantOpeningHours {
+    pub identifier: RestaurantId,
+    pub opening_hours: Option<OpeningHours>,
+}
+
+/// Administrative Intent/Command to migrate the orders of a restaurant to another restaurant, when the restaurant records were created by mistake (see `merge_streams` and `split_stream`).
+/// All the orders are migrated if `order_identifiers` is `None`: the restaurant is merged into the target, and retired (its stream is final)
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct MigrateRestaurantOrders {
+    pub identifier: RestaurantId,
+    pub target_identifier: RestaurantId,
+    pub order_identifiers: Option<Vec<OrderId>>,
+}
+
+/// Administrative Intent/Command to receive the orders migrated from another restaurant (see `MigrateRestaurantOrders`)
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ReceiveRestaurantOrders {
+    pub identifier: RestaurantId,
+    pub source_identifier: RestaurantId,
+    pub orders: Vec<MigratedOrder>,
+}
+
+/// Intent/Command to apply the promotion to the orders placed at a restaurant (issued by the saga, once the promotion is created)
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ApplyRestaurantPromotion {
+    pub identifier: RestaurantId,
+    pub promotion: RestaurantPromotion,
+}
+
+/// Intent/Command to withdraw the promotion of a restaurant (issued by the saga, once the promotion expires)
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct WithdrawRestaurantPromotion {
+    pub identifier: RestaurantId,
+    pub promotion_id: PromotionId,
+}
+
 /// Intent/Command to place an order at a restaurant
+/// The order with the line items of multiple restaurants is split into the (sub) orders per restaurant, grouped by the order identifier.
 #[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 pub struct PlaceOrder {
     pub identifier: RestaurantId,
     pub order_identifier: OrderId,
     pub line_items: Vec<OrderLineItem>,
+    /// The group of the (sub) order, placed by the saga splitting the order. `None` for the order placed at the single restaurant
+    #[serde(default)]
+    pub group_identifier: Option<OrderGroupId>,
+    /// The customer who placed the order, notified of its status. `None` for the anonymous orders
+    #[serde(default)]
+    pub customer_identifier: Option<CustomerId>,
+}
+
+/// Intent/Command to modify the line items of an order placed at a restaurant, before the order is prepared.
+/// The line items are validated against the current menu of the restaurant.
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ModifyOrderLineItems {
+    pub identifier: RestaurantId,
+    pub order_identifier: OrderId,
+    pub line_items: Vec<OrderLineItem>,
+}
+
+/// Intent/Command to capture a (partial) payment of an order placed at a restaurant. An order can be paid in several (split) payments, up to its total
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct CapturePayment {
+    pub identifier: RestaurantId,
+    pub order_identifier: OrderId,
+    pub amount: Money,
+}
+
+/// Intent/Command to refund a (partial) payment of an order placed at a restaurant, up to the captured amount
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct RefundPayment {
+    pub identifier: RestaurantId,
+    pub order_identifier: OrderId,
+    pub amount: Money,
 }
 
 // #### ORDER ####
@@ -154,6 +559,9 @@ pub struct PlaceOrder {
 pub enum OrderCommand {
     Create(CreateOrder),
     MarkAsPrepared(MarkOrderAsPrepared),
+    UpdateLineItems(UpdateOrderLineItems),
+    Migrate(MigrateOrder),
+    Cancel(CancelOrder),
 }
 
 /// Intent/Command to create a new order
@@ -162,6 +570,18 @@ pub struct CreateOrder {
     pub identifier: OrderId,
     pub restaurant_identifier: RestaurantId,
     pub line_items: Vec<OrderLineItem>,
+    /// The currency of the (frozen) unit prices, `None` if the menu has no currency
+    #[serde(default)]
+    pub currency: Option<Currency>,
+    /// The discounts of the line items, by the promotions of the restaurant
+    #[serde(default)]
+    pub discounts: Vec<AppliedDiscount>,
+    /// The group of the (sub) order, if the order was split across the restaurants
+    #[serde(default)]
+    pub group_identifier: Option<OrderGroupId>,
+    /// The customer who placed the order, `None` for the anonymous orders
+    #[serde(default)]
+    pub customer_identifier: Option<CustomerId>,
 }
 
 /// Intent/Command to mark an order as prepared
@@ -170,6 +590,144 @@ pub struct MarkOrderAsPrepared {
     pub identifier: OrderId,
 }
 
+/// Intent/Command to update the line items of an order (issued by the saga, once the modification is accepted by the restaurant)
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct UpdateOrderLineItems {
+    pub identifier: OrderId,
+    pub restaurant_identifier: RestaurantId,
+    pub line_items: Vec<OrderLineItem>,
+    /// The discounts of the line items, by the promotions of the restaurant
+    #[serde(default)]
+    pub discounts: Vec<AppliedDiscount>,
+}
+
+/// Intent/Command to migrate an (open) order to the restaurant that received it (issued by the saga, once the order is received by the restaurant)
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct MigrateOrder {
+    pub identifier: OrderId,
+    pub restaurant_identifier: RestaurantId,
+}
+
+/// Intent/Command to cancel an order (issued by the saga, once the order is cancelled at the restaurant): cancelled by the customer, or `rejected` by the restaurant
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct CancelOrder {
+    pub identifier: OrderId,
+    pub rejected: bool,
+}
+
+// #### PROMOTION ####
+
+/// All possible command variants that could be sent to a promotion
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(tag = "type")]
+pub enum PromotionCommand {
+    Create(CreatePromotion),
+    Expire(ExpirePromotion),
+}
+
+/// Intent/Command to create a new promotion of a restaurant: the discount of the menu items (all of them, if `menu_item_ids` is empty)
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct CreatePromotion {
+    pub identifier: PromotionId,
+    pub restaurant_identifier: RestaurantId,
+    pub name: PromotionName,
+    pub percentage: DiscountPercentage,
+    pub menu_item_ids: Vec<MenuItemId>,
+}
+
+/// Intent/Command to expire a promotion: it no longer discounts the orders placed at the restaurant
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ExpirePromotion {
+    pub identifier: PromotionId,
+}
+
+// #### INVENTORY ####
+
+/// All possible command variants that could be sent to an inventory
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(tag = "type")]
+pub enum InventoryCommand {
+    Restock(RestockInventory),
+    Reserve(ReserveStock),
+    Release(ReleaseStock),
+}
+
+/// Intent/Command to restock the inventory of a restaurant: the quantities are added to the stock on hand (the first restock creates the inventory)
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct RestockInventory {
+    pub identifier: InventoryId,
+    pub restaurant_identifier: RestaurantId,
+    pub items: Vec<StockItem>,
+}
+
+/// Intent/Command to reserve the stock of the order (issued by the saga, once the order is placed or its line items are modified): it replaces the previous reservation of the order
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ReserveStock {
+    pub identifier: InventoryId,
+    pub order_identifier: OrderId,
+    pub items: Vec<StockItem>,
+}
+
+/// Intent/Command to release the stock reserved by the order (issued by the saga, once the order is cancelled or rejected)
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ReleaseStock {
+    pub identifier: InventoryId,
+    pub order_identifier: OrderId,
+}
+
+// #### GIFT CARD ####
+
+/// All possible command variants that could be sent to a gift card
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(tag = "type")]
+pub enum GiftCardCommand {
+    Issue(IssueGiftCard),
+    Redeem(RedeemGiftCard),
+    Refund(RefundGiftCard),
+}
+
+/// Intent/Command to issue a new gift card (store credit), with the initial balance
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct IssueGiftCard {
+    pub identifier: GiftCardId,
+    pub amount: Money,
+}
+
+/// Intent/Command to pay (a part of) the order placed at the restaurant with the gift card: the amount is redeemed from the balance, and captured as the payment of the order by the saga
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct RedeemGiftCard {
+    pub identifier: GiftCardId,
+    pub restaurant_identifier: RestaurantId,
+    pub order_identifier: OrderId,
+    pub amount: Money,
+}
+
+/// Intent/Command to refund (a part of) the amount redeemed by the order back to the gift card: the payment of the order is refunded by the saga
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct RefundGiftCard {
+    pub identifier: GiftCardId,
+    pub restaurant_identifier: RestaurantId,
+    pub order_identifier: OrderId,
+    pub amount: Money,
+}
+
+// #### COURIER ####
+
+/// All possible command variants that could be sent to a courier
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(tag = "type")]
+pub enum CourierCommand {
+    UpdateLocation(UpdateCourierLocation),
+}
+
+/// Intent/Command to report the current location of the courier (high-frequency telemetry)
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct UpdateCourierLocation {
+    pub identifier: CourierId,
+    pub latitude: Coordinate,
+    pub longitude: Coordinate,
+}
+
 // ########################################################
 // ######################## EVENTS ########################
 // ########################################################
@@ -183,6 +741,20 @@ pub enum RestaurantEvent {
     Created(RestaurantCreated),
     MenuChanged(RestaurantMenuChanged),
     OrderPlaced(OrderPlaced),
+    OrderLineItemsModified(OrderLineItemsModified),
+    PaymentCaptured(PaymentCaptured),
+    PaymentRefunded(PaymentRefunded),
+    CapacityChanged(RestaurantCapacityChanged),
+    OrderClosed(RestaurantOrderClosed),
+    MenuItemMarkedUnavailable(MenuItemMarkedUnavailable),
+    MenuItemMarkedAvailable(MenuItemMarkedAvailable),
+    OpeningHoursChanged(RestaurantOpeningHoursChanged),
+    OrdersMigrated(RestaurantOrdersMigrated),
+    OrdersReceived(RestaurantOrdersReceived),
+    PromotionApplied(RestaurantPromotionApplied),
+    PromotionWithdrawn(RestaurantPromotionWithdrawn),
+    OrderCancelled(RestaurantOrderCancelled),
+    OrderSplit(RestaurantOrderSplit),
 }
 
 impl Identifier for RestaurantEvent {
@@ -191,6 +763,20 @@ impl Identifier for RestaurantEvent {
             RestaurantEvent::Created(e) => e.identifier.0,
             RestaurantEvent::MenuChanged(e) => e.identifier.0,
             RestaurantEvent::OrderPlaced(e) => e.identifier.0,
+            RestaurantEvent::OrderLineItemsModified(e) => e.identifier.0,
+            RestaurantEvent::PaymentCaptured(e) => e.identifier.0,
+            RestaurantEvent::PaymentRefunded(e) => e.identifier.0,
+            RestaurantEvent::CapacityChanged(e) => e.identifier.0,
+            RestaurantEvent::OrderClosed(e) => e.identifier.0,
+            RestaurantEvent::MenuItemMarkedUnavailable(e) => e.identifier.0,
+            RestaurantEvent::MenuItemMarkedAvailable(e) => e.identifier.0,
+            RestaurantEvent::OpeningHoursChanged(e) => e.identifier.0,
+            RestaurantEvent::OrdersMigrated(e) => e.identifier.0,
+            RestaurantEvent::OrdersReceived(e) => e.identifier.0,
+            RestaurantEvent::PromotionApplied(e) => e.identifier.0,
+            RestaurantEvent::PromotionWithdrawn(e) => e.identifier.0,
+            RestaurantEvent::OrderCancelled(e) => e.identifier.0,
+            RestaurantEvent::OrderSplit(e) => e.identifier.0,
         }
     }
 }
@@ -209,6 +795,104 @@ pub struct RestaurantCreated {
 pub struct RestaurantMenuChanged {
     pub identifier: RestaurantId,
     pub menu: RestaurantMenu,
+    /// The version of the new menu. The events persisted before the menu versioning was introduced default to `0` (unknown)
+    #[serde(default)]
+    pub menu_version: RestaurantMenuVersion,
+    pub r#final: bool,
+}
+
+/// Fact/Event that the capacity of a restaurant was changed
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Clone, Eq)]
+pub struct RestaurantCapacityChanged {
+    pub identifier: RestaurantId,
+    pub max_concurrent_orders: Option<u32>,
+    pub r#final: bool,
+}
+
+/// Fact/Event that the order of a restaurant was closed (prepared): it no longer counts against the capacity of the restaurant
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Clone, Eq)]
+pub struct RestaurantOrderClosed {
+    pub identifier: RestaurantId,
+    pub order_identifier: OrderId,
+    pub r#final: bool,
+}
+
+/// Fact/Event that the open order of a restaurant was cancelled (or `rejected` by the restaurant): it no longer counts against the capacity of the restaurant
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Clone, Eq)]
+pub struct RestaurantOrderCancelled {
+    pub identifier: RestaurantId,
+    pub order_identifier: OrderId,
+    pub rejected: bool,
+    pub r#final: bool,
+}
+
+/// Fact/Event that the order spanning multiple restaurants was split into the (sub) orders per restaurant, grouped by the order identifier (the sub orders are placed by the saga)
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Clone, Eq)]
+pub struct RestaurantOrderSplit {
+    pub identifier: RestaurantId,
+    pub group_identifier: OrderGroupId,
+    pub orders: Vec<SplitOrder>,
+    /// The customer who placed the order, `None` for the anonymous orders. The events persisted before the notifications were introduced default to `None`
+    #[serde(default)]
+    pub customer_identifier: Option<CustomerId>,
+    pub r#final: bool,
+}
+
+/// Fact/Event that the menu item of a restaurant was marked as unavailable (86'd)
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Clone, Eq)]
+pub struct MenuItemMarkedUnavailable {
+    pub identifier: RestaurantId,
+    pub menu_item_id: MenuItemId,
+    pub r#final: bool,
+}
+
+/// Fact/Event that the menu item of a restaurant was marked as available again
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Clone, Eq)]
+pub struct MenuItemMarkedAvailable {
+    pub identifier: RestaurantId,
+    pub menu_item_id: MenuItemId,
+    pub r#final: bool,
+}
+
+/// Fact/Event that the opening hours of a restaurant were changed
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Clone, Eq)]
+pub struct RestaurantOpeningHoursChanged {
+    pub identifier: RestaurantId,
+    pub opening_hours: Option<OpeningHours>,
+    pub r#final: bool,
+}
+
+/// Fact/Event that the orders of a restaurant were migrated to another restaurant (the restaurant records merged, or split). Final if the restaurant was merged into the target (retired)
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Clone, Eq)]
+pub struct RestaurantOrdersMigrated {
+    pub identifier: RestaurantId,
+    pub target_identifier: RestaurantId,
+    pub orders: Vec<MigratedOrder>,
+    pub r#final: bool,
+}
+
+/// Fact/Event that a restaurant received the orders migrated from another restaurant
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Clone, Eq)]
+pub struct RestaurantOrdersReceived {
+    pub identifier: RestaurantId,
+    pub source_identifier: RestaurantId,
+    pub orders: Vec<MigratedOrder>,
+    pub r#final: bool,
+}
+
+/// Fact/Event that the promotion was applied to the orders placed at a restaurant
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Clone, Eq)]
+pub struct RestaurantPromotionApplied {
+    pub identifier: RestaurantId,
+    pub promotion: RestaurantPromotion,
+    pub r#final: bool,
+}
+
+/// Fact/Event that the promotion of a restaurant was withdrawn
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Clone, Eq)]
+pub struct RestaurantPromotionWithdrawn {
+    pub identifier: RestaurantId,
+    pub promotion_id: PromotionId,
     pub r#final: bool,
 }
 
@@ -218,6 +902,48 @@ pub struct OrderPlaced {
     pub identifier: RestaurantId,
     pub order_identifier: OrderId,
     pub line_items: Vec<OrderLineItem>,
+    /// The currency of the (frozen) unit prices: the currency of the menu. The events persisted before the currencies were introduced default to `None`
+    #[serde(default)]
+    pub currency: Option<Currency>,
+    /// The discounts of the line items, by the promotions of the restaurant applied when the order was placed. The events persisted before the promotions were introduced default to none
+    #[serde(default)]
+    pub discounts: Vec<AppliedDiscount>,
+    /// The group of the (sub) order, if the order was split across the restaurants. The events persisted before the order groups were introduced default to `None`
+    #[serde(default)]
+    pub group_identifier: Option<OrderGroupId>,
+    /// The customer who placed the order, `None` for the anonymous orders. The events persisted before the notifications were introduced default to `None`
+    #[serde(default)]
+    pub customer_identifier: Option<CustomerId>,
+    pub r#final: bool,
+}
+
+/// Fact/Event that the line items of an order were modified (validated against the menu, priced, and discounted)
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Clone, Eq)]
+pub struct OrderLineItemsModified {
+    pub identifier: RestaurantId,
+    pub order_identifier: OrderId,
+    pub line_items: Vec<OrderLineItem>,
+    /// The discounts of the modified line items, by the promotions of the restaurant. The events persisted before the promotions were introduced default to none
+    #[serde(default)]
+    pub discounts: Vec<AppliedDiscount>,
+    pub r#final: bool,
+}
+
+/// Fact/Event that a payment of an order was captured
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Clone, Eq)]
+pub struct PaymentCaptured {
+    pub identifier: RestaurantId,
+    pub order_identifier: OrderId,
+    pub amount: Money,
+    pub r#final: bool,
+}
+
+/// Fact/Event that a payment of an order was (partially) refunded
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Clone, Eq)]
+pub struct PaymentRefunded {
+    pub identifier: RestaurantId,
+    pub order_identifier: OrderId,
+    pub amount: Money,
     pub r#final: bool,
 }
 
@@ -229,6 +955,9 @@ pub struct OrderPlaced {
 pub enum OrderEvent {
     Created(OrderCreated),
     Prepared(OrderPrepared),
+    LineItemsUpdated(OrderLineItemsUpdated),
+    Migrated(OrderMigrated),
+    Cancelled(OrderCancelled),
 }
 
 impl Identifier for OrderEvent {
@@ -236,6 +965,9 @@ impl Identifier for OrderEvent {
         match self {
             OrderEvent::Created(e) => e.identifier.0,
             OrderEvent::Prepared(e) => e.identifier.0,
+            OrderEvent::LineItemsUpdated(e) => e.identifier.0,
+            OrderEvent::Migrated(e) => e.identifier.0,
+            OrderEvent::Cancelled(e) => e.identifier.0,
         }
     }
 }
@@ -247,6 +979,19 @@ pub struct OrderCreated {
     pub restaurant_identifier: RestaurantId,
     pub status: OrderStatus,
     pub line_items: Vec<OrderLineItem>,
+    /// The discounts of the line items, by the promotions of the restaurant. The events persisted before the promotions were introduced default to none
+    #[serde(default)]
+    pub discounts: Vec<AppliedDiscount>,
+    /// The total of the order (less the discounts) converted to the reference currency, at the exchange rate of the order time.
+    /// `None` if the order is not priced, or has no currency (and for the events persisted before the currencies were introduced)
+    #[serde(default)]
+    pub reference_total: Option<ConvertedMoney>,
+    /// The group of the (sub) order, if the order was split across the restaurants. The events persisted before the order groups were introduced default to `None`
+    #[serde(default)]
+    pub group_identifier: Option<OrderGroupId>,
+    /// The customer who placed the order, `None` for the anonymous orders. The events persisted before the notifications were introduced default to `None`
+    #[serde(default)]
+    pub customer_identifier: Option<CustomerId>,
     pub r#final: bool,
 }
 
@@ -254,6 +999,200 @@ pub struct OrderCreated {
 #[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Clone, Eq)]
 pub struct OrderPrepared {
     pub identifier: OrderId,
+    /// The restaurant of the order. The events persisted before it was introduced default to `None` (unknown)
+    #[serde(default)]
+    pub restaurant_identifier: Option<RestaurantId>,
+    pub status: OrderStatus,
+    pub r#final: bool,
+}
+
+/// Fact/Event that the line items of an order were updated
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Clone, Eq)]
+pub struct OrderLineItemsUpdated {
+    pub identifier: OrderId,
+    pub restaurant_identifier: RestaurantId,
+    pub line_items: Vec<OrderLineItem>,
+    /// The discounts of the line items, by the promotions of the restaurant. The events persisted before the promotions were introduced default to none
+    #[serde(default)]
+    pub discounts: Vec<AppliedDiscount>,
+    pub r#final: bool,
+}
+
+/// Fact/Event that an order was migrated to another restaurant (the restaurant records merged, or split)
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Clone, Eq)]
+pub struct OrderMigrated {
+    pub identifier: OrderId,
+    pub restaurant_identifier: RestaurantId,
     pub status: OrderStatus,
     pub r#final: bool,
 }
+
+/// Fact/Event that an order was cancelled (`Cancelled`), or rejected by the restaurant (`Rejected`) (final)
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Clone, Eq)]
+pub struct OrderCancelled {
+    pub identifier: OrderId,
+    pub restaurant_identifier: RestaurantId,
+    pub status: OrderStatus,
+    pub r#final: bool,
+}
+
+// #### PROMOTION ####
+
+/// All possible event variants that could be used to update a promotion
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(tag = "type")]
+pub enum PromotionEvent {
+    Created(PromotionCreated),
+    Expired(PromotionExpired),
+}
+
+impl Identifier for PromotionEvent {
+    fn identifier(&self) -> Uuid {
+        match self {
+            PromotionEvent::Created(e) => e.identifier.0,
+            PromotionEvent::Expired(e) => e.identifier.0,
+        }
+    }
+}
+
+/// Fact/Event that a promotion was created
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Clone, Eq)]
+pub struct PromotionCreated {
+    pub identifier: PromotionId,
+    pub restaurant_identifier: RestaurantId,
+    pub name: PromotionName,
+    pub percentage: DiscountPercentage,
+    pub menu_item_ids: Vec<MenuItemId>,
+    pub r#final: bool,
+}
+
+/// Fact/Event that a promotion expired (final)
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Clone, Eq)]
+pub struct PromotionExpired {
+    pub identifier: PromotionId,
+    pub restaurant_identifier: RestaurantId,
+    pub r#final: bool,
+}
+
+// #### INVENTORY ####
+
+/// All possible event variants that could be used to update an inventory
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(tag = "type")]
+pub enum InventoryEvent {
+    Restocked(InventoryRestocked),
+    Reserved(StockReserved),
+    Released(StockReleased),
+}
+
+impl Identifier for InventoryEvent {
+    fn identifier(&self) -> Uuid {
+        match self {
+            InventoryEvent::Restocked(e) => e.identifier.0,
+            InventoryEvent::Reserved(e) => e.identifier.0,
+            InventoryEvent::Released(e) => e.identifier.0,
+        }
+    }
+}
+
+/// Fact/Event that the inventory of a restaurant was restocked
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Clone, Eq)]
+pub struct InventoryRestocked {
+    pub identifier: InventoryId,
+    pub restaurant_identifier: RestaurantId,
+    pub items: Vec<StockItem>,
+    pub r#final: bool,
+}
+
+/// Fact/Event that the stock of the order was reserved: the stock of the menu items tracked by the inventory, replacing the previous reservation of the order
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Clone, Eq)]
+pub struct StockReserved {
+    pub identifier: InventoryId,
+    pub order_identifier: OrderId,
+    pub items: Vec<StockItem>,
+    pub r#final: bool,
+}
+
+/// Fact/Event that the stock reserved by the order was released (back on hand)
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Clone, Eq)]
+pub struct StockReleased {
+    pub identifier: InventoryId,
+    pub order_identifier: OrderId,
+    pub items: Vec<StockItem>,
+    pub r#final: bool,
+}
+
+// #### GIFT CARD ####
+
+/// All possible event variants that could be used to update a gift card
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(tag = "type")]
+pub enum GiftCardEvent {
+    Issued(GiftCardIssued),
+    Redeemed(GiftCardRedeemed),
+    Refunded(GiftCardRefunded),
+}
+
+impl Identifier for GiftCardEvent {
+    fn identifier(&self) -> Uuid {
+        match self {
+            GiftCardEvent::Issued(e) => e.identifier.0,
+            GiftCardEvent::Redeemed(e) => e.identifier.0,
+            GiftCardEvent::Refunded(e) => e.identifier.0,
+        }
+    }
+}
+
+/// Fact/Event that a gift card was issued, with the initial balance
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Clone, Eq)]
+pub struct GiftCardIssued {
+    pub identifier: GiftCardId,
+    pub amount: Money,
+    pub r#final: bool,
+}
+
+/// Fact/Event that the amount was redeemed from the balance of the gift card, to pay the order
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Clone, Eq)]
+pub struct GiftCardRedeemed {
+    pub identifier: GiftCardId,
+    pub restaurant_identifier: RestaurantId,
+    pub order_identifier: OrderId,
+    pub amount: Money,
+    pub r#final: bool,
+}
+
+/// Fact/Event that the amount redeemed by the order was refunded back to the balance of the gift card
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Clone, Eq)]
+pub struct GiftCardRefunded {
+    pub identifier: GiftCardId,
+    pub restaurant_identifier: RestaurantId,
+    pub order_identifier: OrderId,
+    pub amount: Money,
+    pub r#final: bool,
+}
+
+// #### COURIER ####
+
+/// All possible event variants that could be used to update a courier
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(tag = "type")]
+pub enum CourierEvent {
+    LocationUpdated(CourierLocationUpdated),
+}
+
+impl Identifier for CourierEvent {
+    fn identifier(&self) -> Uuid {
+        match self {
+            CourierEvent::LocationUpdated(e) => e.identifier.0,
+        }
+    }
+}
+
+/// Fact/Event that the courier was at the location (downsampled, or kept out of the event log, by the courier location policy)
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq, Clone, Eq)]
+pub struct CourierLocationUpdated {
+    pub identifier: CourierId,
+    pub latitude: Coordinate,
+    pub longitude: Coordinate,
+    pub r#final: bool,
+}
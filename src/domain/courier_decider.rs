@@ -0,0 +1,56 @@
+use fmodel_rust::decider::Decider;
+use pgrx::error;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::api::{
+    Coordinate, CourierCommand, CourierEvent, CourierId, CourierLocationUpdated,
+};
+
+/// The state of the Courier is represented by this struct. It belongs to the Domain layer.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Courier {
+    pub identifier: CourierId,
+    /// The last location of the courier in the event log (the locations kept out of the event log are not folded)
+    pub latitude: Coordinate,
+    pub longitude: Coordinate,
+}
+
+/// A convenient type alias for the Courier decider
+pub type CourierDecider<'a> = Decider<'a, CourierCommand, Option<Courier>, CourierEvent>;
+
+/// Decider is a datatype/struct that represents the main decision-making algorithm. It belongs to the Domain layer.
+/// The courier reports its location in the high frequency: every report is decided independently of the state, so the locations kept out of the event log (see the courier location policy of the event repository) do not change the decisions.
+pub fn courier_decider<'a>() -> CourierDecider<'a> {
+    Decider {
+        // Decide new events based on the current state and the command
+        // Exhaustive pattern matching on the command
+        decide: Box::new(|command, _state| match command {
+            CourierCommand::UpdateLocation(command) => {
+                if command.latitude.0.abs() > 90_000_000 {
+                    error!("Failed to update the courier location. The latitude is out of range!");
+                }
+                if command.longitude.0.abs() > 180_000_000 {
+                    error!("Failed to update the courier location. The longitude is out of range!");
+                }
+                vec![CourierEvent::LocationUpdated(CourierLocationUpdated {
+                    identifier: command.identifier.to_owned(),
+                    latitude: command.latitude,
+                    longitude: command.longitude,
+                    r#final: false,
+                })]
+            }
+        }),
+        // Evolve the state based on the current state and the event
+        // Exhaustive pattern matching on the event
+        evolve: Box::new(|_state, event| match event {
+            CourierEvent::LocationUpdated(event) => Some(Courier {
+                identifier: event.identifier.to_owned(),
+                latitude: event.latitude,
+                longitude: event.longitude,
+            }),
+        }),
+
+        // The initial state of the decider
+        initial_state: Box::new(|| None),
+    }
+}
@@ -0,0 +1,35 @@
+use crate::domain::api::{Currency, ExchangeRate};
+use std::collections::HashMap;
+
+/// The exchange rates, injected into the deciders that convert the money (e.g. the order total, to the reference currency).
+/// The deciders stay pure: the rates are an input of the decision, and the tests can fix them.
+pub trait ExchangeRates: Send + Sync {
+    /// The reference currency: the currency the totals are converted to (e.g. for the reporting).
+    fn reference_currency(&self) -> Currency;
+
+    /// The current rate from the currency to the other currency, or `None` if the rate is unknown.
+    /// The failure of the lookup itself is raised (`error!`), like the rejection of the decider.
+    fn rate(&self, from: &Currency, to: &Currency) -> Option<ExchangeRate>;
+}
+
+/// The fixed exchange rates (to the reference currency), by the currency.
+pub struct FixedExchangeRates {
+    pub reference_currency: Currency,
+    pub rates: HashMap<Currency, ExchangeRate>,
+}
+
+impl ExchangeRates for FixedExchangeRates {
+    fn reference_currency(&self) -> Currency {
+        self.reference_currency.to_owned()
+    }
+
+    fn rate(&self, from: &Currency, to: &Currency) -> Option<ExchangeRate> {
+        if from == to {
+            Some(ExchangeRate(ExchangeRate::SCALE))
+        } else if *to == self.reference_currency {
+            self.rates.get(from).copied()
+        } else {
+            None
+        }
+    }
+}
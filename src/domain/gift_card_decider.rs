@@ -0,0 +1,172 @@
+use fmodel_rust::decider::Decider;
+use pgrx::error;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::api::{
+    GiftCardCommand, GiftCardEvent, GiftCardId, GiftCardIssued, GiftCardRedeemed, GiftCardRefunded,
+    Money, OrderId, RestaurantId,
+};
+
+/// The state of the Gift Card is represented by this struct. It belongs to the Domain layer.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct GiftCard {
+    pub identifier: GiftCardId,
+    /// The balance available to the orders, never negative
+    pub balance: Money,
+    /// The amounts redeemed by the orders, less the amounts refunded back to the gift card
+    pub redemptions: Vec<GiftCardRedemption>,
+}
+
+/// The amount redeemed by the order (less the refunded amount).
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct GiftCardRedemption {
+    pub restaurant_identifier: RestaurantId,
+    pub order_identifier: OrderId,
+    pub amount: Money,
+}
+
+impl GiftCard {
+    /// The amount redeemed by the order (less the refunded amount), or zero if the order did not redeem the gift card.
+    pub fn redeemed(&self, order_identifier: &OrderId) -> u64 {
+        self.redemptions
+            .iter()
+            .find(|redemption| redemption.order_identifier == *order_identifier)
+            .map(|redemption| redemption.amount.0)
+            .unwrap_or_default()
+    }
+}
+
+/// A convenient type alias for the Gift Card decider
+pub type GiftCardDecider<'a> = Decider<'a, GiftCardCommand, Option<GiftCard>, GiftCardEvent>;
+
+/// Decider is a datatype/struct that represents the main decision-making algorithm. It belongs to the Domain layer.
+/// The gift card (store credit) pays the orders: the redeemed amount is captured as the payment of the order, and the refunded amount is refunded from the payments of the order (see the gift card payment saga).
+pub fn gift_card_decider<'a>() -> GiftCardDecider<'a> {
+    Decider {
+        // Decide new events based on the current state and the command
+        // Exhaustive pattern matching on the command
+        decide: Box::new(|command, state| match command {
+            GiftCardCommand::Issue(command) => {
+                if state.is_some() {
+                    error!("Failed to issue the gift card. Gift card already exists!");
+                }
+                if command.amount.0 == 0 {
+                    error!("Failed to issue the gift card. The amount must be positive!");
+                }
+                vec![GiftCardEvent::Issued(GiftCardIssued {
+                    identifier: command.identifier.to_owned(),
+                    amount: command.amount.to_owned(),
+                    r#final: false,
+                })]
+            }
+            // Invariant: the balance never goes negative
+            GiftCardCommand::Redeem(command) => {
+                let Some(state) = state else {
+                    error!("Failed to redeem the gift card. Gift card does not exist!");
+                };
+                if command.amount.0 == 0 {
+                    error!("Failed to redeem the gift card. The amount must be positive!");
+                }
+                if command.amount.0 > state.balance.0 {
+                    error!("Failed to redeem the gift card. Insufficient balance!");
+                }
+                vec![GiftCardEvent::Redeemed(GiftCardRedeemed {
+                    identifier: command.identifier.to_owned(),
+                    restaurant_identifier: command.restaurant_identifier.to_owned(),
+                    order_identifier: command.order_identifier.to_owned(),
+                    amount: command.amount.to_owned(),
+                    r#final: false,
+                })]
+            }
+            // Invariant: only the redeemed amount is refunded, so the refunds never credit the gift card more than it paid
+            GiftCardCommand::Refund(command) => {
+                let Some(state) = state else {
+                    error!("Failed to refund the gift card. Gift card does not exist!");
+                };
+                if command.amount.0 == 0 {
+                    error!("Failed to refund the gift card. The amount must be positive!");
+                }
+                if command.amount.0 > state.redeemed(&command.order_identifier) {
+                    error!("Failed to refund the gift card. The amount exceeds the amount redeemed by the order!");
+                }
+                vec![GiftCardEvent::Refunded(GiftCardRefunded {
+                    identifier: command.identifier.to_owned(),
+                    restaurant_identifier: command.restaurant_identifier.to_owned(),
+                    order_identifier: command.order_identifier.to_owned(),
+                    amount: command.amount.to_owned(),
+                    r#final: false,
+                })]
+            }
+        }),
+        // Evolve the state based on the current state and the event
+        // Exhaustive pattern matching on the event
+        // The invariants are enforced on the evolution as well: the history that overdraws the gift card (e.g. the imported or the corrected events) is rejected, instead of folding into the negative balance
+        evolve: Box::new(|state, event| {
+            match event {
+            GiftCardEvent::Issued(event) => Some(GiftCard {
+                identifier: event.identifier.to_owned(),
+                balance: event.amount.to_owned(),
+                redemptions: vec![],
+            }),
+            GiftCardEvent::Redeemed(event) => state.clone().map(|s| {
+                let Some(balance) = s.balance.0.checked_sub(event.amount.0) else {
+                    error!("Failed to evolve the gift card. The balance can not be negative!");
+                };
+                let redeemed = s.redeemed(&event.order_identifier) + event.amount.0;
+                GiftCard {
+                    balance: Money(balance),
+                    redemptions: with_redemption(
+                        s.redemptions,
+                        &event.restaurant_identifier,
+                        &event.order_identifier,
+                        redeemed,
+                    ),
+                    ..s
+                }
+            }),
+            GiftCardEvent::Refunded(event) => state.clone().map(|s| {
+                let Some(redeemed) = s
+                    .redeemed(&event.order_identifier)
+                    .checked_sub(event.amount.0)
+                else {
+                    error!("Failed to evolve the gift card. The refund exceeds the amount redeemed by the order!");
+                };
+                GiftCard {
+                    balance: Money(s.balance.0 + event.amount.0),
+                    redemptions: with_redemption(
+                        s.redemptions,
+                        &event.restaurant_identifier,
+                        &event.order_identifier,
+                        redeemed,
+                    ),
+                    ..s
+                }
+            }),
+        }
+        }),
+
+        // The initial state of the decider
+        initial_state: Box::new(|| None),
+    }
+}
+
+/// The redemptions, with the amount redeemed by the order replaced (the order is removed once all of its redeemed amount is refunded).
+fn with_redemption(
+    redemptions: Vec<GiftCardRedemption>,
+    restaurant_identifier: &RestaurantId,
+    order_identifier: &OrderId,
+    amount: u64,
+) -> Vec<GiftCardRedemption> {
+    let mut redemptions: Vec<GiftCardRedemption> = redemptions
+        .into_iter()
+        .filter(|redemption| redemption.order_identifier != *order_identifier)
+        .collect();
+    if amount > 0 {
+        redemptions.push(GiftCardRedemption {
+            restaurant_identifier: restaurant_identifier.to_owned(),
+            order_identifier: order_identifier.to_owned(),
+            amount: Money(amount),
+        });
+    }
+    redemptions
+}
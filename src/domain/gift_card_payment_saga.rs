@@ -0,0 +1,31 @@
+use fmodel_rust::saga::Saga;
+
+use crate::domain::api::{CapturePayment, GiftCardEvent, RefundPayment, RestaurantCommand};
+
+/// A convenient type alias for the Gift Card payment choreography saga
+type GiftCardPaymentSaga<'a> = Saga<'a, GiftCardEvent, RestaurantCommand>;
+
+/// The Gift Card payment choreography saga - represents the central point of control deciding what to execute next.
+/// It is a function that takes an event and returns a list of commands.
+/// The amount redeemed from the gift card is captured as the payment of the order, and the amount refunded to the gift card is refunded from the payments of the order.
+pub fn gift_card_payment_saga<'a>() -> GiftCardPaymentSaga<'a> {
+    Saga {
+        react: Box::new(|event| match event {
+            GiftCardEvent::Redeemed(event) => {
+                vec![RestaurantCommand::CapturePayment(CapturePayment {
+                    identifier: event.restaurant_identifier.to_owned(),
+                    order_identifier: event.order_identifier.to_owned(),
+                    amount: event.amount.to_owned(),
+                })]
+            }
+            GiftCardEvent::Refunded(event) => {
+                vec![RestaurantCommand::RefundPayment(RefundPayment {
+                    identifier: event.restaurant_identifier.to_owned(),
+                    order_identifier: event.order_identifier.to_owned(),
+                    amount: event.amount.to_owned(),
+                })]
+            }
+            GiftCardEvent::Issued(..) => vec![],
+        }),
+    }
+}
@@ -0,0 +1,209 @@
+use fmodel_rust::decider::Decider;
+use pgrx::error;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::api::{
+    InventoryCommand, InventoryEvent, InventoryId, InventoryRestocked, OrderId, RestaurantId,
+    StockItem, StockQuantity, StockReleased, StockReserved,
+};
+
+/// The state of the Inventory is represented by this struct. It belongs to the Domain layer.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Inventory {
+    pub identifier: InventoryId,
+    pub restaurant_identifier: RestaurantId,
+    /// The stock on hand, available to the orders: the tracked menu items (the menu items that were never restocked are not tracked)
+    pub stock: Vec<StockItem>,
+    /// The stock reserved by the orders
+    pub reservations: Vec<StockReservation>,
+}
+
+/// The stock reserved by the order.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct StockReservation {
+    pub order_identifier: OrderId,
+    pub items: Vec<StockItem>,
+}
+
+impl Inventory {
+    /// The stock reserved by the order, or no stock if the order has no reservation.
+    fn reserved(&self, order_identifier: &OrderId) -> Vec<StockItem> {
+        self.reservations
+            .iter()
+            .find(|reservation| reservation.order_identifier == *order_identifier)
+            .map(|reservation| reservation.items.to_owned())
+            .unwrap_or_default()
+    }
+
+    /// The stock available to the order: the stock on hand, and the stock already reserved by the order (the new reservation of the order replaces it).
+    pub fn available(&self, order_identifier: &OrderId) -> Vec<StockItem> {
+        add_stock(&self.stock, &self.reserved(order_identifier))
+    }
+}
+
+/// The items of the order exceed the available stock: the tracked menu item is ordered in the larger quantity than available. The menu items that are not tracked are never insufficient.
+pub fn is_insufficient(available: &[StockItem], items: &[StockItem]) -> bool {
+    items
+        .iter()
+        .any(|item| quantity(available, item).is_some_and(|quantity| quantity < item.quantity.0))
+}
+
+/// A convenient type alias for the Inventory decider
+pub type InventoryDecider<'a> = Decider<'a, InventoryCommand, Option<Inventory>, InventoryEvent>;
+
+/// Decider is a datatype/struct that represents the main decision-making algorithm. It belongs to the Domain layer.
+/// The stock is reserved by the orders of the restaurant when they are placed, and released when they are cancelled (see the inventory saga).
+pub fn inventory_decider<'a>() -> InventoryDecider<'a> {
+    Decider {
+        // Decide new events based on the current state and the command
+        // Exhaustive pattern matching on the command
+        decide: Box::new(|command, state| match command {
+            InventoryCommand::Restock(command) => {
+                if command.identifier != InventoryId::of(&command.restaurant_identifier) {
+                    error!("Failed to restock the inventory. Inventory does not belong to the restaurant!");
+                }
+                vec![InventoryEvent::Restocked(InventoryRestocked {
+                    identifier: command.identifier.to_owned(),
+                    restaurant_identifier: command.restaurant_identifier.to_owned(),
+                    items: command.items.to_owned(),
+                    r#final: false,
+                })]
+            }
+            // Invariant: the stock on hand never goes negative. Only the tracked menu items are reserved; the restaurants without the inventory do not track the stock at all
+            InventoryCommand::Reserve(command) => {
+                let Some(state) = state else {
+                    return vec![];
+                };
+                // The previous reservation of the order is replaced: its stock is available to the new one
+                let available = state.available(&command.order_identifier);
+                if is_insufficient(&available, &command.items) {
+                    error!("Failed to reserve the stock. Insufficient stock of the menu item!");
+                }
+                let items: Vec<StockItem> = command
+                    .items
+                    .iter()
+                    .filter(|item| quantity(&available, item).is_some())
+                    .cloned()
+                    .collect();
+                if items.is_empty() && state.reserved(&command.order_identifier).is_empty() {
+                    vec![]
+                } else {
+                    vec![InventoryEvent::Reserved(StockReserved {
+                        identifier: command.identifier.to_owned(),
+                        order_identifier: command.order_identifier.to_owned(),
+                        items,
+                        r#final: false,
+                    })]
+                }
+            }
+            InventoryCommand::Release(command) => {
+                let items = state
+                    .as_ref()
+                    .map(|state| state.reserved(&command.order_identifier))
+                    .unwrap_or_default();
+                if items.is_empty() {
+                    vec![]
+                } else {
+                    vec![InventoryEvent::Released(StockReleased {
+                        identifier: command.identifier.to_owned(),
+                        order_identifier: command.order_identifier.to_owned(),
+                        items,
+                        r#final: false,
+                    })]
+                }
+            }
+        }),
+        // Evolve the state based on the current state and the event
+        // Exhaustive pattern matching on the event
+        evolve: Box::new(|state, event| match event {
+            InventoryEvent::Restocked(event) => Some(match state {
+                Some(s) => Inventory {
+                    stock: add_stock(&s.stock, &event.items),
+                    ..s.clone()
+                },
+                None => Inventory {
+                    identifier: event.identifier.to_owned(),
+                    restaurant_identifier: event.restaurant_identifier.to_owned(),
+                    stock: add_stock(&[], &event.items),
+                    reservations: vec![],
+                },
+            }),
+            InventoryEvent::Reserved(event) => state.clone().map(|s| {
+                let stock = take_stock(
+                    &add_stock(&s.stock, &s.reserved(&event.order_identifier)),
+                    &event.items,
+                );
+                let mut reservations: Vec<StockReservation> = s
+                    .reservations
+                    .into_iter()
+                    .filter(|reservation| reservation.order_identifier != event.order_identifier)
+                    .collect();
+                if !event.items.is_empty() {
+                    reservations.push(StockReservation {
+                        order_identifier: event.order_identifier.to_owned(),
+                        items: event.items.to_owned(),
+                    });
+                }
+                Inventory {
+                    stock,
+                    reservations,
+                    ..s
+                }
+            }),
+            InventoryEvent::Released(event) => state.clone().map(|s| Inventory {
+                stock: add_stock(&s.stock, &event.items),
+                reservations: s
+                    .reservations
+                    .into_iter()
+                    .filter(|reservation| reservation.order_identifier != event.order_identifier)
+                    .collect(),
+                ..s
+            }),
+        }),
+
+        // The initial state of the decider
+        initial_state: Box::new(|| None),
+    }
+}
+
+/// The quantity of the menu item on hand, or `None` if the menu item is not tracked.
+fn quantity(stock: &[StockItem], item: &StockItem) -> Option<u32> {
+    stock
+        .iter()
+        .find(|it| it.menu_item_id == item.menu_item_id)
+        .map(|it| it.quantity.0)
+}
+
+/// The stock, with the items added (the new menu items are tracked from now on).
+fn add_stock(stock: &[StockItem], items: &[StockItem]) -> Vec<StockItem> {
+    let mut stock = stock.to_vec();
+    for item in items {
+        match stock
+            .iter_mut()
+            .find(|it| it.menu_item_id == item.menu_item_id)
+        {
+            Some(it) => it.quantity.0 += item.quantity.0,
+            None => stock.push(item.to_owned()),
+        }
+    }
+    stock
+}
+
+/// The stock, with the (tracked) items taken.
+fn take_stock(stock: &[StockItem], items: &[StockItem]) -> Vec<StockItem> {
+    stock
+        .iter()
+        .map(|it| StockItem {
+            menu_item_id: it.menu_item_id.to_owned(),
+            quantity: StockQuantity(
+                it.quantity.0.saturating_sub(
+                    items
+                        .iter()
+                        .filter(|item| item.menu_item_id == it.menu_item_id)
+                        .map(|item| item.quantity.0)
+                        .sum(),
+                ),
+            ),
+        })
+        .collect()
+}
@@ -0,0 +1,52 @@
+use fmodel_rust::saga::Saga;
+
+use crate::domain::api::{
+    InventoryCommand, InventoryId, ReleaseStock, ReserveStock, RestaurantEvent, StockItem,
+};
+
+/// A convenient type alias for the Inventory choreography saga
+type InventorySaga<'a> = Saga<'a, RestaurantEvent, InventoryCommand>;
+
+/// The Inventory choreography saga - represents the central point of control deciding what to execute next.
+/// It is a function that takes an event and returns a list of commands.
+/// The stock is reserved in the inventory of the restaurant when the order is placed (or modified), and released when the order is cancelled or rejected.
+pub fn inventory_saga<'a>() -> InventorySaga<'a> {
+    Saga {
+        react: Box::new(|event| match event {
+            RestaurantEvent::OrderPlaced(event) => {
+                vec![InventoryCommand::Reserve(ReserveStock {
+                    identifier: InventoryId::of(&event.identifier),
+                    order_identifier: event.order_identifier.to_owned(),
+                    items: StockItem::of_line_items(&event.line_items),
+                })]
+            }
+            RestaurantEvent::OrderLineItemsModified(event) => {
+                vec![InventoryCommand::Reserve(ReserveStock {
+                    identifier: InventoryId::of(&event.identifier),
+                    order_identifier: event.order_identifier.to_owned(),
+                    items: StockItem::of_line_items(&event.line_items),
+                })]
+            }
+            RestaurantEvent::OrderCancelled(event) => {
+                vec![InventoryCommand::Release(ReleaseStock {
+                    identifier: InventoryId::of(&event.identifier),
+                    order_identifier: event.order_identifier.to_owned(),
+                })]
+            }
+            RestaurantEvent::Created(..)
+            | RestaurantEvent::MenuChanged(..)
+            | RestaurantEvent::PaymentCaptured(..)
+            | RestaurantEvent::PaymentRefunded(..)
+            | RestaurantEvent::CapacityChanged(..)
+            | RestaurantEvent::OrderClosed(..)
+            | RestaurantEvent::OpeningHoursChanged(..)
+            | RestaurantEvent::MenuItemMarkedUnavailable(..)
+            | RestaurantEvent::MenuItemMarkedAvailable(..)
+            | RestaurantEvent::OrdersMigrated(..)
+            | RestaurantEvent::OrdersReceived(..)
+            | RestaurantEvent::PromotionApplied(..)
+            | RestaurantEvent::PromotionWithdrawn(..)
+            | RestaurantEvent::OrderSplit(..) => vec![],
+        }),
+    }
+}
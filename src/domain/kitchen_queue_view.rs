@@ -0,0 +1,54 @@
+use fmodel_rust::view::View;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::api::{OrderEvent, OrderId, OrderLineItem, OrderStatus, RestaurantId};
+
+/// The state of the Kitchen Queue View is represented by this struct: the order, as the kitchen of its restaurant prepares it. It belongs to the Domain layer.
+/// The order is in the queue of its restaurant while it is `Created` (open); it leaves the queue once it is prepared, cancelled or rejected.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct KitchenOrder {
+    pub order_identifier: OrderId,
+    pub restaurant_identifier: RestaurantId,
+    pub status: OrderStatus,
+    pub line_items: Vec<OrderLineItem>,
+}
+
+/// A convenient type alias for the Kitchen Queue view
+pub type KitchenQueueView<'a> = View<'a, Option<KitchenOrder>, OrderEvent>;
+
+/// View represents the event handling algorithm. It belongs to the Domain layer.
+pub fn kitchen_queue_view<'a>() -> KitchenQueueView<'a> {
+    View {
+        // Evolve the state based on the current state and the event
+        // Exhaustive pattern matching on the event
+        evolve: Box::new(|state, event| match event {
+            OrderEvent::Created(event) => Some(KitchenOrder {
+                order_identifier: event.identifier.to_owned(),
+                restaurant_identifier: event.restaurant_identifier.to_owned(),
+                status: event.status.to_owned(),
+                line_items: event.line_items.to_owned(),
+            }),
+            OrderEvent::LineItemsUpdated(event) => state.clone().map(|s| KitchenOrder {
+                line_items: event.line_items.to_owned(),
+                ..s
+            }),
+            // The migrated order is moved to the queue of the other restaurant
+            OrderEvent::Migrated(event) => state.clone().map(|s| KitchenOrder {
+                restaurant_identifier: event.restaurant_identifier.to_owned(),
+                status: event.status.to_owned(),
+                ..s
+            }),
+            OrderEvent::Prepared(event) => state.clone().map(|s| KitchenOrder {
+                status: event.status.to_owned(),
+                ..s
+            }),
+            OrderEvent::Cancelled(event) => state.clone().map(|s| KitchenOrder {
+                status: event.status.to_owned(),
+                ..s
+            }),
+        }),
+
+        // The initial state of the view
+        initial_state: Box::new(|| None),
+    }
+}
@@ -1,15 +1,39 @@
 use crate::domain::api::{
-    ChangeRestaurantMenu, CreateOrder, CreateRestaurant, MarkOrderAsPrepared, OrderCommand,
-    PlaceOrder, RestaurantCommand,
+    ApplyRestaurantPromotion, CancelOrder, CancelRestaurantOrder, CapturePayment,
+    ChangeRestaurantCapacity, ChangeRestaurantMenu, ChangeRestaurantOpeningHours,
+    CloseRestaurantOrder, CourierCommand, CreateOrder, CreatePromotion, CreateRestaurant,
+    ExpirePromotion, GiftCardCommand, InventoryCommand, IssueGiftCard, MarkMenuItemAvailable,
+    MarkMenuItemUnavailable, MarkOrderAsPrepared, MigrateOrder, MigrateRestaurantOrders,
+    ModifyOrderLineItems, OrderCommand, PlaceOrder, PromotionCommand, ReceiveRestaurantOrders,
+    RedeemGiftCard, RefundGiftCard, RefundPayment, ReleaseStock, ReserveStock, RestaurantCommand,
+    RestockInventory, UpdateCourierLocation, UpdateOrderLineItems, WithdrawRestaurantPromotion,
 };
+use crate::domain::courier_decider::{courier_decider, Courier};
+use crate::domain::exchange_rates::ExchangeRates;
+use crate::domain::gift_card_decider::{gift_card_decider, GiftCard};
+use crate::domain::gift_card_payment_saga::gift_card_payment_saga;
+use crate::domain::inventory_decider::{inventory_decider, Inventory};
+use crate::domain::inventory_saga::inventory_saga;
 use crate::domain::order_decider::{order_decider, Order};
+use crate::domain::order_group_saga::order_group_saga;
 use crate::domain::order_saga::order_saga;
+use crate::domain::promotion_decider::{promotion_decider, Promotion};
 use crate::domain::restaurant_decider::{restaurant_decider, Restaurant};
+use crate::domain::restaurant_promotion_saga::restaurant_promotion_saga;
 use crate::domain::restaurant_saga::restaurant_saga;
-use crate::framework::domain::api::{DeciderType, EventType, Identifier, IsFinal};
+use crate::domain::stock_levels::StockLevels;
+use crate::framework::domain::api::{CommandType, DeciderType, EventType, Identifier, IsFinal};
+use crate::framework::domain::clock::Clock;
 use api::{
-    OrderCreated, OrderEvent, OrderPlaced, OrderPrepared, RestaurantCreated, RestaurantEvent,
-    RestaurantMenuChanged,
+    CourierEvent, CourierLocationUpdated, GiftCardEvent, GiftCardIssued, GiftCardRedeemed,
+    GiftCardRefunded, InventoryEvent, InventoryRestocked, MenuItemMarkedAvailable,
+    MenuItemMarkedUnavailable, OrderCancelled, OrderCreated, OrderEvent, OrderLineItemsModified,
+    OrderLineItemsUpdated, OrderMigrated, OrderPlaced, OrderPrepared, PaymentCaptured,
+    PaymentRefunded, PromotionCreated, PromotionEvent, PromotionExpired, RestaurantCapacityChanged,
+    RestaurantCreated, RestaurantEvent, RestaurantMenu, RestaurantMenuChanged,
+    RestaurantOpeningHoursChanged, RestaurantOrderCancelled, RestaurantOrderClosed,
+    RestaurantOrderSplit, RestaurantOrdersMigrated, RestaurantOrdersReceived,
+    RestaurantPromotionApplied, RestaurantPromotionWithdrawn, StockReleased, StockReserved,
 };
 use fmodel_rust::decider::Decider;
 use fmodel_rust::saga::Saga;
@@ -19,39 +43,167 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 pub mod api;
+pub mod courier_decider;
+pub mod exchange_rates;
+pub mod gift_card_decider;
+pub mod gift_card_payment_saga;
+pub mod inventory_decider;
+pub mod inventory_saga;
+pub mod kitchen_queue_view;
+pub mod notification_saga;
 pub mod order_decider;
+pub mod order_group_saga;
+pub mod order_group_view;
+pub mod order_payments_view;
 pub mod order_saga;
 pub mod order_view;
+pub mod promotion_decider;
+pub mod restaurant_daily_orders_view;
 pub mod restaurant_decider;
+pub mod restaurant_order_board_view;
+pub mod restaurant_promotion_saga;
 pub mod restaurant_saga;
 pub mod restaurant_view;
+pub mod specifications;
+pub mod stock_levels;
+
+/// A convenient type alias for the state of the combined Decider: the states of the Restaurant, Order, Promotion, Inventory, Gift Card and Courier deciders
+pub type OrderAndRestaurantState = (
+    Option<Restaurant>,
+    Option<Order>,
+    Option<Promotion>,
+    Option<Inventory>,
+    Option<GiftCard>,
+    Option<Courier>,
+);
 
 /// A convenient type alias for the combined Decider
-/// This decider is used to combine the Restaurant and Order deciders into a single decider that can handle both Restaurant and Order commands.
-pub type OrderAndRestaurantDecider<'a> =
-    Decider<'a, Command, (Option<Restaurant>, Option<Order>), Event>;
+/// This decider is used to combine the Restaurant, Order, Promotion, Inventory, Gift Card and Courier deciders into a single decider that can handle the Restaurant, Order, Promotion, Inventory, Gift Card and Courier commands.
+pub type OrderAndRestaurantDecider<'a> = Decider<'a, Command, OrderAndRestaurantState, Event>;
 
 /// A convenient type alias for the combined Saga
 /// This saga is used to combine the Restaurant and Order choreography sagas into a single orchestrating saga that can handle both Restaurant and Order events, and produce Restaurant and Order commands as a result.
 pub type OrderAndRestaurantSaga<'a> = Saga<'a, Event, Command>;
 
-/// Combined Decider, combining the Restaurant and Order deciders into a single decider that can handle both Restaurant and Order commands.
-pub fn order_restaurant_decider<'a>() -> OrderAndRestaurantDecider<'a> {
-    restaurant_decider()
-        .combine(order_decider())
+/// Combined Decider, combining the Restaurant, Order, Promotion, Inventory, Gift Card and Courier deciders into a single decider that can handle the Restaurant, Order, Promotion, Inventory, Gift Card and Courier commands.
+/// The clock and the stock levels are injected into the Restaurant decider (the opening hours, and the stock of the ordered menu items), and the exchange rates into the Order decider (the reference total).
+pub fn order_restaurant_decider<'a>(
+    clock: impl Clock + 'a,
+    stock_levels: impl StockLevels + 'a,
+    exchange_rates: impl ExchangeRates + 'a,
+) -> OrderAndRestaurantDecider<'a> {
+    restaurant_decider(clock, stock_levels)
+        .combine(order_decider(exchange_rates))
+        .combine(
+            promotion_decider().combine(
+                inventory_decider().combine(gift_card_decider().combine(courier_decider())),
+            ),
+        )
+        .map_state(&nest_state, &flatten_state)
         .map_command(&command_to_sum)
         .map_event(&event_to_sum, &sum_to_event)
 }
 
-/// Combined Saga, combining the Restaurant and Order choreography sagas into a single orchestrating saga that can handle both Restaurant and Order events, and produce Restaurant and Order commands as a result.
+/// Combined Saga, merging the Restaurant, Order, Inventory, Order group, Restaurant promotion and Gift Card payment choreography sagas into a single orchestrating saga that can handle the Restaurant, Order, Promotion and Gift Card events, and produce Restaurant, Order and Inventory commands as a result.
+/// Every saga reacts to the events it selects: the Order, the Inventory and the Order group sagas all react to the Restaurant events (e.g. `OrderPlaced` creates the order, and reserves its stock), in this order.
 pub fn order_restaurant_saga<'a>() -> OrderAndRestaurantSaga<'a> {
-    restaurant_saga()
-        .combine(order_saga())
-        .map_action_result(&event_to_sum2)
-        .map_action(&sum_to_command)
+    merge(vec![
+        select(
+            restaurant_saga(),
+            event_to_order_event,
+            restaurant_command_to_command,
+        ),
+        select(
+            order_saga(),
+            event_to_restaurant_event,
+            order_command_to_command,
+        ),
+        select(
+            inventory_saga(),
+            event_to_restaurant_event,
+            inventory_command_to_command,
+        ),
+        select(
+            order_group_saga(),
+            event_to_restaurant_event,
+            restaurant_command_to_command,
+        ),
+        select(
+            restaurant_promotion_saga(),
+            event_to_promotion_event,
+            restaurant_command_to_command,
+        ),
+        select(
+            gift_card_payment_saga(),
+            event_to_gift_card_event,
+            restaurant_command_to_command,
+        ),
+    ])
+}
+
+/// The saga reacting to the events it selects (e.g. the Restaurant events), producing the commands of the combined saga.
+fn select<'a, E: 'a, C: 'a>(
+    saga: Saga<'a, E, C>,
+    event: impl Fn(&Event) -> Option<E> + Send + Sync + 'a,
+    command: impl Fn(&C) -> Command + Send + Sync + 'a,
+) -> OrderAndRestaurantSaga<'a> {
+    Saga {
+        react: Box::new(move |e| match event(e) {
+            Some(e) => (saga.react)(&e).iter().map(&command).collect(),
+            None => vec![],
+        }),
+    }
+}
+
+/// The saga reacting to every event with the commands of all the sagas, in the order of the sagas.
+fn merge(sagas: Vec<OrderAndRestaurantSaga>) -> OrderAndRestaurantSaga {
+    Saga {
+        react: Box::new(move |event| sagas.iter().flat_map(|saga| (saga.react)(event)).collect()),
+    }
+}
+
+/// The state of the combined (nested) deciders, of the flat state.
+#[allow(clippy::type_complexity)]
+fn nest_state(
+    state: &OrderAndRestaurantState,
+) -> (
+    (Option<Restaurant>, Option<Order>),
+    (
+        Option<Promotion>,
+        (Option<Inventory>, (Option<GiftCard>, Option<Courier>)),
+    ),
+) {
+    (
+        (state.0.clone(), state.1.clone()),
+        (
+            state.2.clone(),
+            (state.3.clone(), (state.4.clone(), state.5.clone())),
+        ),
+    )
 }
 
-/// All possible commands in the order&restaurant domains
+/// The flat state, of the state of the combined (nested) deciders.
+#[allow(clippy::type_complexity)]
+fn flatten_state(
+    state: &(
+        (Option<Restaurant>, Option<Order>),
+        (
+            Option<Promotion>,
+            (Option<Inventory>, (Option<GiftCard>, Option<Courier>)),
+        ),
+    ),
+) -> OrderAndRestaurantState {
+    (
+        state.0 .0.clone(),
+        state.0 .1.clone(),
+        state.1 .0.clone(),
+        state.1 .1 .0.clone(),
+        state.1 .1 .1 .0.clone(),
+        state.1 .1 .1 .1.clone(),
+    )
+}
+
+/// All possible commands in the order&restaurant (and promotion, inventory, gift card, courier) domains
 #[derive(PostgresType, Serialize, Deserialize, Debug, Eq, PartialEq)]
 #[serde(tag = "type")]
 pub enum Command {
@@ -60,8 +212,67 @@ pub enum Command {
     PlaceOrder(PlaceOrder),
     CreateOrder(CreateOrder),
     MarkOrderAsPrepared(MarkOrderAsPrepared),
+    ModifyOrderLineItems(ModifyOrderLineItems),
+    UpdateOrderLineItems(UpdateOrderLineItems),
+    CapturePayment(CapturePayment),
+    RefundPayment(RefundPayment),
+    ChangeRestaurantCapacity(ChangeRestaurantCapacity),
+    CloseRestaurantOrder(CloseRestaurantOrder),
+    MarkMenuItemUnavailable(MarkMenuItemUnavailable),
+    MarkMenuItemAvailable(MarkMenuItemAvailable),
+    ChangeRestaurantOpeningHours(ChangeRestaurantOpeningHours),
+    MigrateRestaurantOrders(MigrateRestaurantOrders),
+    ReceiveRestaurantOrders(ReceiveRestaurantOrders),
+    MigrateOrder(MigrateOrder),
+    ApplyRestaurantPromotion(ApplyRestaurantPromotion),
+    WithdrawRestaurantPromotion(WithdrawRestaurantPromotion),
+    CreatePromotion(CreatePromotion),
+    ExpirePromotion(ExpirePromotion),
+    CancelRestaurantOrder(CancelRestaurantOrder),
+    CancelOrder(CancelOrder),
+    RestockInventory(RestockInventory),
+    ReserveStock(ReserveStock),
+    ReleaseStock(ReleaseStock),
+    IssueGiftCard(IssueGiftCard),
+    RedeemGiftCard(RedeemGiftCard),
+    RefundGiftCard(RefundGiftCard),
+    UpdateCourierLocation(UpdateCourierLocation),
 }
 
+/// All the command types (the `type` tags of the commands) supported by this version of the extension.
+pub const COMMAND_TYPES: [&str; 30] = [
+    "CreateRestaurant",
+    "ChangeRestaurantMenu",
+    "PlaceOrder",
+    "CreateOrder",
+    "MarkOrderAsPrepared",
+    "ModifyOrderLineItems",
+    "UpdateOrderLineItems",
+    "CapturePayment",
+    "RefundPayment",
+    "ChangeRestaurantCapacity",
+    "CloseRestaurantOrder",
+    "MarkMenuItemUnavailable",
+    "MarkMenuItemAvailable",
+    "ChangeRestaurantOpeningHours",
+    "MigrateRestaurantOrders",
+    "ReceiveRestaurantOrders",
+    "MigrateOrder",
+    "ApplyRestaurantPromotion",
+    "WithdrawRestaurantPromotion",
+    "CreatePromotion",
+    "ExpirePromotion",
+    "CancelRestaurantOrder",
+    "CancelOrder",
+    "RestockInventory",
+    "ReserveStock",
+    "ReleaseStock",
+    "IssueGiftCard",
+    "RedeemGiftCard",
+    "RefundGiftCard",
+    "UpdateCourierLocation",
+];
+
 /// Implement the Identifier trait for the Command enum
 impl Identifier for Command {
     fn identifier(&self) -> Uuid {
@@ -71,11 +282,112 @@ impl Identifier for Command {
             Command::PlaceOrder(cmd) => cmd.identifier.0,
             Command::CreateOrder(cmd) => cmd.identifier.0,
             Command::MarkOrderAsPrepared(cmd) => cmd.identifier.0,
+            Command::ModifyOrderLineItems(cmd) => cmd.identifier.0,
+            Command::UpdateOrderLineItems(cmd) => cmd.identifier.0,
+            Command::CapturePayment(cmd) => cmd.identifier.0,
+            Command::RefundPayment(cmd) => cmd.identifier.0,
+            Command::ChangeRestaurantCapacity(cmd) => cmd.identifier.0,
+            Command::CloseRestaurantOrder(cmd) => cmd.identifier.0,
+            Command::MarkMenuItemUnavailable(cmd) => cmd.identifier.0,
+            Command::MarkMenuItemAvailable(cmd) => cmd.identifier.0,
+            Command::ChangeRestaurantOpeningHours(cmd) => cmd.identifier.0,
+            Command::MigrateRestaurantOrders(cmd) => cmd.identifier.0,
+            Command::ReceiveRestaurantOrders(cmd) => cmd.identifier.0,
+            Command::MigrateOrder(cmd) => cmd.identifier.0,
+            Command::ApplyRestaurantPromotion(cmd) => cmd.identifier.0,
+            Command::WithdrawRestaurantPromotion(cmd) => cmd.identifier.0,
+            Command::CreatePromotion(cmd) => cmd.identifier.0,
+            Command::ExpirePromotion(cmd) => cmd.identifier.0,
+            Command::CancelRestaurantOrder(cmd) => cmd.identifier.0,
+            Command::CancelOrder(cmd) => cmd.identifier.0,
+            Command::RestockInventory(cmd) => cmd.identifier.0,
+            Command::ReserveStock(cmd) => cmd.identifier.0,
+            Command::ReleaseStock(cmd) => cmd.identifier.0,
+            Command::IssueGiftCard(cmd) => cmd.identifier.0,
+            Command::RedeemGiftCard(cmd) => cmd.identifier.0,
+            Command::RefundGiftCard(cmd) => cmd.identifier.0,
+            Command::UpdateCourierLocation(cmd) => cmd.identifier.0,
+        }
+    }
+}
+
+/// Implement the CommandType trait for the Command enum
+impl CommandType for Command {
+    fn command_type(&self) -> String {
+        match self {
+            Command::CreateRestaurant(_) => "CreateRestaurant".to_string(),
+            Command::ChangeRestaurantMenu(_) => "ChangeRestaurantMenu".to_string(),
+            Command::PlaceOrder(_) => "PlaceOrder".to_string(),
+            Command::CreateOrder(_) => "CreateOrder".to_string(),
+            Command::MarkOrderAsPrepared(_) => "MarkOrderAsPrepared".to_string(),
+            Command::ModifyOrderLineItems(_) => "ModifyOrderLineItems".to_string(),
+            Command::UpdateOrderLineItems(_) => "UpdateOrderLineItems".to_string(),
+            Command::CapturePayment(_) => "CapturePayment".to_string(),
+            Command::RefundPayment(_) => "RefundPayment".to_string(),
+            Command::ChangeRestaurantCapacity(_) => "ChangeRestaurantCapacity".to_string(),
+            Command::CloseRestaurantOrder(_) => "CloseRestaurantOrder".to_string(),
+            Command::MarkMenuItemUnavailable(_) => "MarkMenuItemUnavailable".to_string(),
+            Command::MarkMenuItemAvailable(_) => "MarkMenuItemAvailable".to_string(),
+            Command::ChangeRestaurantOpeningHours(_) => "ChangeRestaurantOpeningHours".to_string(),
+            Command::MigrateRestaurantOrders(_) => "MigrateRestaurantOrders".to_string(),
+            Command::ReceiveRestaurantOrders(_) => "ReceiveRestaurantOrders".to_string(),
+            Command::MigrateOrder(_) => "MigrateOrder".to_string(),
+            Command::ApplyRestaurantPromotion(_) => "ApplyRestaurantPromotion".to_string(),
+            Command::WithdrawRestaurantPromotion(_) => "WithdrawRestaurantPromotion".to_string(),
+            Command::CreatePromotion(_) => "CreatePromotion".to_string(),
+            Command::ExpirePromotion(_) => "ExpirePromotion".to_string(),
+            Command::CancelRestaurantOrder(_) => "CancelRestaurantOrder".to_string(),
+            Command::CancelOrder(_) => "CancelOrder".to_string(),
+            Command::RestockInventory(_) => "RestockInventory".to_string(),
+            Command::ReserveStock(_) => "ReserveStock".to_string(),
+            Command::ReleaseStock(_) => "ReleaseStock".to_string(),
+            Command::IssueGiftCard(_) => "IssueGiftCard".to_string(),
+            Command::RedeemGiftCard(_) => "RedeemGiftCard".to_string(),
+            Command::RefundGiftCard(_) => "RefundGiftCard".to_string(),
+            Command::UpdateCourierLocation(_) => "UpdateCourierLocation".to_string(),
+        }
+    }
+}
+
+/// Implement the DeciderType trait for the Command enum
+impl DeciderType for Command {
+    fn decider_type(&self) -> String {
+        match self {
+            Command::CreateRestaurant(_) => "Restaurant".to_string(),
+            Command::ChangeRestaurantMenu(_) => "Restaurant".to_string(),
+            Command::PlaceOrder(_) => "Restaurant".to_string(),
+            Command::CreateOrder(_) => "Order".to_string(),
+            Command::MarkOrderAsPrepared(_) => "Order".to_string(),
+            Command::ModifyOrderLineItems(_) => "Restaurant".to_string(),
+            Command::UpdateOrderLineItems(_) => "Order".to_string(),
+            Command::CapturePayment(_) => "Restaurant".to_string(),
+            Command::RefundPayment(_) => "Restaurant".to_string(),
+            Command::ChangeRestaurantCapacity(_) => "Restaurant".to_string(),
+            Command::CloseRestaurantOrder(_) => "Restaurant".to_string(),
+            Command::MarkMenuItemUnavailable(_) => "Restaurant".to_string(),
+            Command::MarkMenuItemAvailable(_) => "Restaurant".to_string(),
+            Command::ChangeRestaurantOpeningHours(_) => "Restaurant".to_string(),
+            Command::MigrateRestaurantOrders(_) => "Restaurant".to_string(),
+            Command::ReceiveRestaurantOrders(_) => "Restaurant".to_string(),
+            Command::MigrateOrder(_) => "Order".to_string(),
+            Command::ApplyRestaurantPromotion(_) => "Restaurant".to_string(),
+            Command::WithdrawRestaurantPromotion(_) => "Restaurant".to_string(),
+            Command::CreatePromotion(_) => "Promotion".to_string(),
+            Command::ExpirePromotion(_) => "Promotion".to_string(),
+            Command::CancelRestaurantOrder(_) => "Restaurant".to_string(),
+            Command::CancelOrder(_) => "Order".to_string(),
+            Command::RestockInventory(_) => "Inventory".to_string(),
+            Command::ReserveStock(_) => "Inventory".to_string(),
+            Command::ReleaseStock(_) => "Inventory".to_string(),
+            Command::IssueGiftCard(_) => "GiftCard".to_string(),
+            Command::RedeemGiftCard(_) => "GiftCard".to_string(),
+            Command::RefundGiftCard(_) => "GiftCard".to_string(),
+            Command::UpdateCourierLocation(_) => "Courier".to_string(),
         }
     }
 }
 
-/// All possible events in the order&restaurant domains
+/// All possible events in the order&restaurant (and promotion, inventory, gift card, courier) domains
 #[derive(PostgresType, Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
 #[serde(tag = "type")]
 pub enum Event {
@@ -84,6 +396,32 @@ pub enum Event {
     OrderPlaced(OrderPlaced),
     OrderCreated(OrderCreated),
     OrderPrepared(OrderPrepared),
+    OrderLineItemsModified(OrderLineItemsModified),
+    OrderLineItemsUpdated(OrderLineItemsUpdated),
+    PaymentCaptured(PaymentCaptured),
+    PaymentRefunded(PaymentRefunded),
+    RestaurantCapacityChanged(RestaurantCapacityChanged),
+    RestaurantOrderClosed(RestaurantOrderClosed),
+    MenuItemMarkedUnavailable(MenuItemMarkedUnavailable),
+    MenuItemMarkedAvailable(MenuItemMarkedAvailable),
+    RestaurantOpeningHoursChanged(RestaurantOpeningHoursChanged),
+    RestaurantOrdersMigrated(RestaurantOrdersMigrated),
+    RestaurantOrdersReceived(RestaurantOrdersReceived),
+    OrderMigrated(OrderMigrated),
+    RestaurantPromotionApplied(RestaurantPromotionApplied),
+    RestaurantPromotionWithdrawn(RestaurantPromotionWithdrawn),
+    PromotionCreated(PromotionCreated),
+    PromotionExpired(PromotionExpired),
+    RestaurantOrderCancelled(RestaurantOrderCancelled),
+    OrderCancelled(OrderCancelled),
+    InventoryRestocked(InventoryRestocked),
+    StockReserved(StockReserved),
+    StockReleased(StockReleased),
+    RestaurantOrderSplit(RestaurantOrderSplit),
+    GiftCardIssued(GiftCardIssued),
+    GiftCardRedeemed(GiftCardRedeemed),
+    GiftCardRefunded(GiftCardRefunded),
+    CourierLocationUpdated(CourierLocationUpdated),
 }
 
 /// Implement the Identifier trait for the Event enum
@@ -95,6 +433,32 @@ impl Identifier for Event {
             Event::OrderPlaced(evt) => evt.identifier.0,
             Event::OrderCreated(evt) => evt.identifier.0,
             Event::OrderPrepared(evt) => evt.identifier.0,
+            Event::OrderLineItemsModified(evt) => evt.identifier.0,
+            Event::OrderLineItemsUpdated(evt) => evt.identifier.0,
+            Event::PaymentCaptured(evt) => evt.identifier.0,
+            Event::PaymentRefunded(evt) => evt.identifier.0,
+            Event::RestaurantCapacityChanged(evt) => evt.identifier.0,
+            Event::RestaurantOrderClosed(evt) => evt.identifier.0,
+            Event::MenuItemMarkedUnavailable(evt) => evt.identifier.0,
+            Event::MenuItemMarkedAvailable(evt) => evt.identifier.0,
+            Event::RestaurantOpeningHoursChanged(evt) => evt.identifier.0,
+            Event::RestaurantOrdersMigrated(evt) => evt.identifier.0,
+            Event::RestaurantOrdersReceived(evt) => evt.identifier.0,
+            Event::OrderMigrated(evt) => evt.identifier.0,
+            Event::RestaurantPromotionApplied(evt) => evt.identifier.0,
+            Event::RestaurantPromotionWithdrawn(evt) => evt.identifier.0,
+            Event::PromotionCreated(evt) => evt.identifier.0,
+            Event::PromotionExpired(evt) => evt.identifier.0,
+            Event::RestaurantOrderCancelled(evt) => evt.identifier.0,
+            Event::OrderCancelled(evt) => evt.identifier.0,
+            Event::InventoryRestocked(evt) => evt.identifier.0,
+            Event::StockReserved(evt) => evt.identifier.0,
+            Event::StockReleased(evt) => evt.identifier.0,
+            Event::RestaurantOrderSplit(evt) => evt.identifier.0,
+            Event::GiftCardIssued(evt) => evt.identifier.0,
+            Event::GiftCardRedeemed(evt) => evt.identifier.0,
+            Event::GiftCardRefunded(evt) => evt.identifier.0,
+            Event::CourierLocationUpdated(evt) => evt.identifier.0,
         }
     }
 }
@@ -108,6 +472,32 @@ impl EventType for Event {
             Event::OrderPlaced(_) => "OrderPlaced".to_string(),
             Event::OrderCreated(_) => "OrderCreated".to_string(),
             Event::OrderPrepared(_) => "OrderPrepared".to_string(),
+            Event::OrderLineItemsModified(_) => "OrderLineItemsModified".to_string(),
+            Event::OrderLineItemsUpdated(_) => "OrderLineItemsUpdated".to_string(),
+            Event::PaymentCaptured(_) => "PaymentCaptured".to_string(),
+            Event::PaymentRefunded(_) => "PaymentRefunded".to_string(),
+            Event::RestaurantCapacityChanged(_) => "RestaurantCapacityChanged".to_string(),
+            Event::RestaurantOrderClosed(_) => "RestaurantOrderClosed".to_string(),
+            Event::MenuItemMarkedUnavailable(_) => "MenuItemMarkedUnavailable".to_string(),
+            Event::MenuItemMarkedAvailable(_) => "MenuItemMarkedAvailable".to_string(),
+            Event::RestaurantOpeningHoursChanged(_) => "RestaurantOpeningHoursChanged".to_string(),
+            Event::RestaurantOrdersMigrated(_) => "RestaurantOrdersMigrated".to_string(),
+            Event::RestaurantOrdersReceived(_) => "RestaurantOrdersReceived".to_string(),
+            Event::OrderMigrated(_) => "OrderMigrated".to_string(),
+            Event::RestaurantPromotionApplied(_) => "RestaurantPromotionApplied".to_string(),
+            Event::RestaurantPromotionWithdrawn(_) => "RestaurantPromotionWithdrawn".to_string(),
+            Event::PromotionCreated(_) => "PromotionCreated".to_string(),
+            Event::PromotionExpired(_) => "PromotionExpired".to_string(),
+            Event::RestaurantOrderCancelled(_) => "RestaurantOrderCancelled".to_string(),
+            Event::OrderCancelled(_) => "OrderCancelled".to_string(),
+            Event::InventoryRestocked(_) => "InventoryRestocked".to_string(),
+            Event::StockReserved(_) => "StockReserved".to_string(),
+            Event::StockReleased(_) => "StockReleased".to_string(),
+            Event::RestaurantOrderSplit(_) => "RestaurantOrderSplit".to_string(),
+            Event::GiftCardIssued(_) => "GiftCardIssued".to_string(),
+            Event::GiftCardRedeemed(_) => "GiftCardRedeemed".to_string(),
+            Event::GiftCardRefunded(_) => "GiftCardRefunded".to_string(),
+            Event::CourierLocationUpdated(_) => "CourierLocationUpdated".to_string(),
         }
     }
 }
@@ -121,6 +511,32 @@ impl IsFinal for Event {
             Event::OrderPlaced(evt) => evt.r#final,
             Event::OrderCreated(evt) => evt.r#final,
             Event::OrderPrepared(evt) => evt.r#final,
+            Event::OrderLineItemsModified(evt) => evt.r#final,
+            Event::OrderLineItemsUpdated(evt) => evt.r#final,
+            Event::PaymentCaptured(evt) => evt.r#final,
+            Event::PaymentRefunded(evt) => evt.r#final,
+            Event::RestaurantCapacityChanged(evt) => evt.r#final,
+            Event::RestaurantOrderClosed(evt) => evt.r#final,
+            Event::MenuItemMarkedUnavailable(evt) => evt.r#final,
+            Event::MenuItemMarkedAvailable(evt) => evt.r#final,
+            Event::RestaurantOpeningHoursChanged(evt) => evt.r#final,
+            Event::RestaurantOrdersMigrated(evt) => evt.r#final,
+            Event::RestaurantOrdersReceived(evt) => evt.r#final,
+            Event::OrderMigrated(evt) => evt.r#final,
+            Event::RestaurantPromotionApplied(evt) => evt.r#final,
+            Event::RestaurantPromotionWithdrawn(evt) => evt.r#final,
+            Event::PromotionCreated(evt) => evt.r#final,
+            Event::PromotionExpired(evt) => evt.r#final,
+            Event::RestaurantOrderCancelled(evt) => evt.r#final,
+            Event::OrderCancelled(evt) => evt.r#final,
+            Event::InventoryRestocked(evt) => evt.r#final,
+            Event::StockReserved(evt) => evt.r#final,
+            Event::StockReleased(evt) => evt.r#final,
+            Event::RestaurantOrderSplit(evt) => evt.r#final,
+            Event::GiftCardIssued(evt) => evt.r#final,
+            Event::GiftCardRedeemed(evt) => evt.r#final,
+            Event::GiftCardRefunded(evt) => evt.r#final,
+            Event::CourierLocationUpdated(evt) => evt.r#final,
         }
     }
 }
@@ -134,6 +550,32 @@ impl DeciderType for Event {
             Event::OrderPlaced(_) => "Restaurant".to_string(),
             Event::OrderCreated(_) => "Order".to_string(),
             Event::OrderPrepared(_) => "Order".to_string(),
+            Event::OrderLineItemsModified(_) => "Restaurant".to_string(),
+            Event::OrderLineItemsUpdated(_) => "Order".to_string(),
+            Event::PaymentCaptured(_) => "Restaurant".to_string(),
+            Event::PaymentRefunded(_) => "Restaurant".to_string(),
+            Event::RestaurantCapacityChanged(_) => "Restaurant".to_string(),
+            Event::RestaurantOrderClosed(_) => "Restaurant".to_string(),
+            Event::MenuItemMarkedUnavailable(_) => "Restaurant".to_string(),
+            Event::MenuItemMarkedAvailable(_) => "Restaurant".to_string(),
+            Event::RestaurantOpeningHoursChanged(_) => "Restaurant".to_string(),
+            Event::RestaurantOrdersMigrated(_) => "Restaurant".to_string(),
+            Event::RestaurantOrdersReceived(_) => "Restaurant".to_string(),
+            Event::OrderMigrated(_) => "Order".to_string(),
+            Event::RestaurantPromotionApplied(_) => "Restaurant".to_string(),
+            Event::RestaurantPromotionWithdrawn(_) => "Restaurant".to_string(),
+            Event::PromotionCreated(_) => "Promotion".to_string(),
+            Event::PromotionExpired(_) => "Promotion".to_string(),
+            Event::RestaurantOrderCancelled(_) => "Restaurant".to_string(),
+            Event::OrderCancelled(_) => "Order".to_string(),
+            Event::InventoryRestocked(_) => "Inventory".to_string(),
+            Event::StockReserved(_) => "Inventory".to_string(),
+            Event::StockReleased(_) => "Inventory".to_string(),
+            Event::RestaurantOrderSplit(_) => "Restaurant".to_string(),
+            Event::GiftCardIssued(_) => "GiftCard".to_string(),
+            Event::GiftCardRedeemed(_) => "GiftCard".to_string(),
+            Event::GiftCardRefunded(_) => "GiftCard".to_string(),
+            Event::CourierLocationUpdated(_) => "Courier".to_string(),
         }
     }
 }
@@ -141,62 +583,305 @@ impl DeciderType for Event {
 /// Mapper functions to convert between the `FModel` Sum type and the more appropriate domain specific Command/API type
 /// This is necessary because the `FModel` Sum type is used to combine the Restaurant and Order deciders into a single decider that can handle both Restaurant and Order commands.
 /// We don't want to expose the `FModel` Sum type to the API, so we need to convert between the `FModel` Sum type and the more appropriate Command/API type.
-pub fn command_to_sum(command: &Command) -> Sum<RestaurantCommand, OrderCommand> {
+pub fn command_to_sum(
+    command: &Command,
+) -> Sum<
+    Sum<RestaurantCommand, OrderCommand>,
+    Sum<PromotionCommand, Sum<InventoryCommand, Sum<GiftCardCommand, CourierCommand>>>,
+> {
     match command {
-        Command::CreateRestaurant(c) => {
-            Sum::First(RestaurantCommand::CreateRestaurant(c.to_owned()))
+        Command::CreateRestaurant(c) => Sum::First(Sum::First(
+            RestaurantCommand::CreateRestaurant(c.to_owned()),
+        )),
+        Command::ChangeRestaurantMenu(c) => {
+            Sum::First(Sum::First(RestaurantCommand::ChangeMenu(c.to_owned())))
         }
-        Command::ChangeRestaurantMenu(c) => Sum::First(RestaurantCommand::ChangeMenu(c.to_owned())),
-        Command::PlaceOrder(c) => Sum::First(RestaurantCommand::PlaceOrder(c.to_owned())),
-        Command::CreateOrder(c) => Sum::Second(OrderCommand::Create(c.to_owned())),
-        Command::MarkOrderAsPrepared(c) => Sum::Second(OrderCommand::MarkAsPrepared(c.to_owned())),
+        Command::PlaceOrder(c) => {
+            Sum::First(Sum::First(RestaurantCommand::PlaceOrder(c.to_owned())))
+        }
+        Command::CreateOrder(c) => Sum::First(Sum::Second(OrderCommand::Create(c.to_owned()))),
+        Command::MarkOrderAsPrepared(c) => {
+            Sum::First(Sum::Second(OrderCommand::MarkAsPrepared(c.to_owned())))
+        }
+        Command::ModifyOrderLineItems(c) => Sum::First(Sum::First(
+            RestaurantCommand::ModifyOrderLineItems(c.to_owned()),
+        )),
+        Command::UpdateOrderLineItems(c) => {
+            Sum::First(Sum::Second(OrderCommand::UpdateLineItems(c.to_owned())))
+        }
+        Command::CapturePayment(c) => {
+            Sum::First(Sum::First(RestaurantCommand::CapturePayment(c.to_owned())))
+        }
+        Command::RefundPayment(c) => {
+            Sum::First(Sum::First(RestaurantCommand::RefundPayment(c.to_owned())))
+        }
+        Command::ChangeRestaurantCapacity(c) => {
+            Sum::First(Sum::First(RestaurantCommand::ChangeCapacity(c.to_owned())))
+        }
+        Command::CloseRestaurantOrder(c) => {
+            Sum::First(Sum::First(RestaurantCommand::CloseOrder(c.to_owned())))
+        }
+        Command::MarkMenuItemUnavailable(c) => Sum::First(Sum::First(
+            RestaurantCommand::MarkMenuItemUnavailable(c.to_owned()),
+        )),
+        Command::MarkMenuItemAvailable(c) => Sum::First(Sum::First(
+            RestaurantCommand::MarkMenuItemAvailable(c.to_owned()),
+        )),
+        Command::ChangeRestaurantOpeningHours(c) => Sum::First(Sum::First(
+            RestaurantCommand::ChangeOpeningHours(c.to_owned()),
+        )),
+        Command::MigrateRestaurantOrders(c) => {
+            Sum::First(Sum::First(RestaurantCommand::MigrateOrders(c.to_owned())))
+        }
+        Command::ReceiveRestaurantOrders(c) => {
+            Sum::First(Sum::First(RestaurantCommand::ReceiveOrders(c.to_owned())))
+        }
+        Command::MigrateOrder(c) => Sum::First(Sum::Second(OrderCommand::Migrate(c.to_owned()))),
+        Command::ApplyRestaurantPromotion(c) => {
+            Sum::First(Sum::First(RestaurantCommand::ApplyPromotion(c.to_owned())))
+        }
+        Command::WithdrawRestaurantPromotion(c) => Sum::First(Sum::First(
+            RestaurantCommand::WithdrawPromotion(c.to_owned()),
+        )),
+        Command::CreatePromotion(c) => {
+            Sum::Second(Sum::First(PromotionCommand::Create(c.to_owned())))
+        }
+        Command::ExpirePromotion(c) => {
+            Sum::Second(Sum::First(PromotionCommand::Expire(c.to_owned())))
+        }
+        Command::CancelRestaurantOrder(c) => {
+            Sum::First(Sum::First(RestaurantCommand::CancelOrder(c.to_owned())))
+        }
+        Command::CancelOrder(c) => Sum::First(Sum::Second(OrderCommand::Cancel(c.to_owned()))),
+        Command::RestockInventory(c) => Sum::Second(Sum::Second(Sum::First(
+            InventoryCommand::Restock(c.to_owned()),
+        ))),
+        Command::ReserveStock(c) => Sum::Second(Sum::Second(Sum::First(
+            InventoryCommand::Reserve(c.to_owned()),
+        ))),
+        Command::ReleaseStock(c) => Sum::Second(Sum::Second(Sum::First(
+            InventoryCommand::Release(c.to_owned()),
+        ))),
+        Command::IssueGiftCard(c) => Sum::Second(Sum::Second(Sum::Second(Sum::First(
+            GiftCardCommand::Issue(c.to_owned()),
+        )))),
+        Command::RedeemGiftCard(c) => Sum::Second(Sum::Second(Sum::Second(Sum::First(
+            GiftCardCommand::Redeem(c.to_owned()),
+        )))),
+        Command::RefundGiftCard(c) => Sum::Second(Sum::Second(Sum::Second(Sum::First(
+            GiftCardCommand::Refund(c.to_owned()),
+        )))),
+        Command::UpdateCourierLocation(c) => Sum::Second(Sum::Second(Sum::Second(Sum::Second(
+            CourierCommand::UpdateLocation(c.to_owned()),
+        )))),
     }
 }
 
-pub fn event_to_sum(event: &Event) -> Sum<RestaurantEvent, OrderEvent> {
+pub fn event_to_sum(
+    event: &Event,
+) -> Sum<
+    Sum<RestaurantEvent, OrderEvent>,
+    Sum<PromotionEvent, Sum<InventoryEvent, Sum<GiftCardEvent, CourierEvent>>>,
+> {
     match event {
-        Event::RestaurantCreated(e) => Sum::First(RestaurantEvent::Created(e.to_owned())),
-        Event::RestaurantMenuChanged(e) => Sum::First(RestaurantEvent::MenuChanged(e.to_owned())),
-        Event::OrderPlaced(e) => Sum::First(RestaurantEvent::OrderPlaced(e.to_owned())),
-        Event::OrderCreated(e) => Sum::Second(OrderEvent::Created(e.to_owned())),
-        Event::OrderPrepared(e) => Sum::Second(OrderEvent::Prepared(e.to_owned())),
+        Event::RestaurantCreated(e) => {
+            Sum::First(Sum::First(RestaurantEvent::Created(e.to_owned())))
+        }
+        Event::RestaurantMenuChanged(e) => {
+            Sum::First(Sum::First(RestaurantEvent::MenuChanged(e.to_owned())))
+        }
+        Event::OrderPlaced(e) => Sum::First(Sum::First(RestaurantEvent::OrderPlaced(e.to_owned()))),
+        Event::OrderCreated(e) => Sum::First(Sum::Second(OrderEvent::Created(e.to_owned()))),
+        Event::OrderPrepared(e) => Sum::First(Sum::Second(OrderEvent::Prepared(e.to_owned()))),
+        Event::OrderLineItemsModified(e) => Sum::First(Sum::First(
+            RestaurantEvent::OrderLineItemsModified(e.to_owned()),
+        )),
+        Event::OrderLineItemsUpdated(e) => {
+            Sum::First(Sum::Second(OrderEvent::LineItemsUpdated(e.to_owned())))
+        }
+        Event::PaymentCaptured(e) => {
+            Sum::First(Sum::First(RestaurantEvent::PaymentCaptured(e.to_owned())))
+        }
+        Event::PaymentRefunded(e) => {
+            Sum::First(Sum::First(RestaurantEvent::PaymentRefunded(e.to_owned())))
+        }
+        Event::RestaurantCapacityChanged(e) => {
+            Sum::First(Sum::First(RestaurantEvent::CapacityChanged(e.to_owned())))
+        }
+        Event::RestaurantOrderClosed(e) => {
+            Sum::First(Sum::First(RestaurantEvent::OrderClosed(e.to_owned())))
+        }
+        Event::MenuItemMarkedUnavailable(e) => Sum::First(Sum::First(
+            RestaurantEvent::MenuItemMarkedUnavailable(e.to_owned()),
+        )),
+        Event::MenuItemMarkedAvailable(e) => Sum::First(Sum::First(
+            RestaurantEvent::MenuItemMarkedAvailable(e.to_owned()),
+        )),
+        Event::RestaurantOpeningHoursChanged(e) => Sum::First(Sum::First(
+            RestaurantEvent::OpeningHoursChanged(e.to_owned()),
+        )),
+        Event::RestaurantOrdersMigrated(e) => {
+            Sum::First(Sum::First(RestaurantEvent::OrdersMigrated(e.to_owned())))
+        }
+        Event::RestaurantOrdersReceived(e) => {
+            Sum::First(Sum::First(RestaurantEvent::OrdersReceived(e.to_owned())))
+        }
+        Event::OrderMigrated(e) => Sum::First(Sum::Second(OrderEvent::Migrated(e.to_owned()))),
+        Event::RestaurantPromotionApplied(e) => {
+            Sum::First(Sum::First(RestaurantEvent::PromotionApplied(e.to_owned())))
+        }
+        Event::RestaurantPromotionWithdrawn(e) => Sum::First(Sum::First(
+            RestaurantEvent::PromotionWithdrawn(e.to_owned()),
+        )),
+        Event::PromotionCreated(e) => {
+            Sum::Second(Sum::First(PromotionEvent::Created(e.to_owned())))
+        }
+        Event::PromotionExpired(e) => {
+            Sum::Second(Sum::First(PromotionEvent::Expired(e.to_owned())))
+        }
+        Event::RestaurantOrderCancelled(e) => {
+            Sum::First(Sum::First(RestaurantEvent::OrderCancelled(e.to_owned())))
+        }
+        Event::OrderCancelled(e) => Sum::First(Sum::Second(OrderEvent::Cancelled(e.to_owned()))),
+        Event::InventoryRestocked(e) => Sum::Second(Sum::Second(Sum::First(
+            InventoryEvent::Restocked(e.to_owned()),
+        ))),
+        Event::StockReserved(e) => Sum::Second(Sum::Second(Sum::First(InventoryEvent::Reserved(
+            e.to_owned(),
+        )))),
+        Event::StockReleased(e) => Sum::Second(Sum::Second(Sum::First(InventoryEvent::Released(
+            e.to_owned(),
+        )))),
+        Event::RestaurantOrderSplit(e) => {
+            Sum::First(Sum::First(RestaurantEvent::OrderSplit(e.to_owned())))
+        }
+        Event::GiftCardIssued(e) => Sum::Second(Sum::Second(Sum::Second(Sum::First(
+            GiftCardEvent::Issued(e.to_owned()),
+        )))),
+        Event::GiftCardRedeemed(e) => Sum::Second(Sum::Second(Sum::Second(Sum::First(
+            GiftCardEvent::Redeemed(e.to_owned()),
+        )))),
+        Event::GiftCardRefunded(e) => Sum::Second(Sum::Second(Sum::Second(Sum::First(
+            GiftCardEvent::Refunded(e.to_owned()),
+        )))),
+        Event::CourierLocationUpdated(e) => Sum::Second(Sum::Second(Sum::Second(Sum::Second(
+            CourierEvent::LocationUpdated(e.to_owned()),
+        )))),
     }
 }
 
-pub fn event_to_sum2(event: &Event) -> Sum<OrderEvent, RestaurantEvent> {
-    match event {
-        Event::RestaurantCreated(e) => Sum::Second(RestaurantEvent::Created(e.to_owned())),
-        Event::RestaurantMenuChanged(e) => Sum::Second(RestaurantEvent::MenuChanged(e.to_owned())),
-        Event::OrderPlaced(e) => Sum::Second(RestaurantEvent::OrderPlaced(e.to_owned())),
-        Event::OrderCreated(e) => Sum::First(OrderEvent::Created(e.to_owned())),
-        Event::OrderPrepared(e) => Sum::First(OrderEvent::Prepared(e.to_owned())),
+pub fn restaurant_command_to_command(command: &RestaurantCommand) -> Command {
+    match command {
+        RestaurantCommand::CreateRestaurant(c) => Command::CreateRestaurant(c.to_owned()),
+        RestaurantCommand::ChangeMenu(c) => Command::ChangeRestaurantMenu(c.to_owned()),
+        RestaurantCommand::PlaceOrder(c) => Command::PlaceOrder(c.to_owned()),
+        RestaurantCommand::ModifyOrderLineItems(c) => Command::ModifyOrderLineItems(c.to_owned()),
+        RestaurantCommand::CapturePayment(c) => Command::CapturePayment(c.to_owned()),
+        RestaurantCommand::RefundPayment(c) => Command::RefundPayment(c.to_owned()),
+        RestaurantCommand::ChangeCapacity(c) => Command::ChangeRestaurantCapacity(c.to_owned()),
+        RestaurantCommand::CloseOrder(c) => Command::CloseRestaurantOrder(c.to_owned()),
+        RestaurantCommand::MarkMenuItemUnavailable(c) => {
+            Command::MarkMenuItemUnavailable(c.to_owned())
+        }
+        RestaurantCommand::MarkMenuItemAvailable(c) => Command::MarkMenuItemAvailable(c.to_owned()),
+        RestaurantCommand::ChangeOpeningHours(c) => {
+            Command::ChangeRestaurantOpeningHours(c.to_owned())
+        }
+        RestaurantCommand::MigrateOrders(c) => Command::MigrateRestaurantOrders(c.to_owned()),
+        RestaurantCommand::ReceiveOrders(c) => Command::ReceiveRestaurantOrders(c.to_owned()),
+        RestaurantCommand::ApplyPromotion(c) => Command::ApplyRestaurantPromotion(c.to_owned()),
+        RestaurantCommand::WithdrawPromotion(c) => {
+            Command::WithdrawRestaurantPromotion(c.to_owned())
+        }
+        RestaurantCommand::CancelOrder(c) => Command::CancelRestaurantOrder(c.to_owned()),
     }
 }
 
-pub fn sum_to_command(command: &Sum<OrderCommand, RestaurantCommand>) -> Command {
+pub fn order_command_to_command(command: &OrderCommand) -> Command {
     match command {
-        Sum::Second(c) => match c {
-            RestaurantCommand::CreateRestaurant(c) => Command::CreateRestaurant(c.to_owned()),
-            RestaurantCommand::ChangeMenu(c) => Command::ChangeRestaurantMenu(c.to_owned()),
-            RestaurantCommand::PlaceOrder(c) => Command::PlaceOrder(c.to_owned()),
-        },
-        Sum::First(c) => match c {
-            OrderCommand::Create(c) => Command::CreateOrder(c.to_owned()),
-            OrderCommand::MarkAsPrepared(c) => Command::MarkOrderAsPrepared(c.to_owned()),
-        },
+        OrderCommand::Create(c) => Command::CreateOrder(c.to_owned()),
+        OrderCommand::MarkAsPrepared(c) => Command::MarkOrderAsPrepared(c.to_owned()),
+        OrderCommand::UpdateLineItems(c) => Command::UpdateOrderLineItems(c.to_owned()),
+        OrderCommand::Migrate(c) => Command::MigrateOrder(c.to_owned()),
+        OrderCommand::Cancel(c) => Command::CancelOrder(c.to_owned()),
     }
 }
 
-pub fn sum_to_event(event: &Sum<RestaurantEvent, OrderEvent>) -> Event {
+pub fn inventory_command_to_command(command: &InventoryCommand) -> Command {
+    match command {
+        InventoryCommand::Restock(c) => Command::RestockInventory(c.to_owned()),
+        InventoryCommand::Reserve(c) => Command::ReserveStock(c.to_owned()),
+        InventoryCommand::Release(c) => Command::ReleaseStock(c.to_owned()),
+    }
+}
+
+pub fn gift_card_command_to_command(command: &GiftCardCommand) -> Command {
+    match command {
+        GiftCardCommand::Issue(c) => Command::IssueGiftCard(c.to_owned()),
+        GiftCardCommand::Redeem(c) => Command::RedeemGiftCard(c.to_owned()),
+        GiftCardCommand::Refund(c) => Command::RefundGiftCard(c.to_owned()),
+    }
+}
+
+pub fn sum_to_event(
+    event: &Sum<
+        Sum<RestaurantEvent, OrderEvent>,
+        Sum<PromotionEvent, Sum<InventoryEvent, Sum<GiftCardEvent, CourierEvent>>>,
+    >,
+) -> Event {
     match event {
-        Sum::First(e) => match e {
+        Sum::First(Sum::First(e)) => match e {
             RestaurantEvent::Created(e) => Event::RestaurantCreated(e.to_owned()),
             RestaurantEvent::MenuChanged(e) => Event::RestaurantMenuChanged(e.to_owned()),
             RestaurantEvent::OrderPlaced(e) => Event::OrderPlaced(e.to_owned()),
+            RestaurantEvent::OrderLineItemsModified(e) => {
+                Event::OrderLineItemsModified(e.to_owned())
+            }
+            RestaurantEvent::PaymentCaptured(e) => Event::PaymentCaptured(e.to_owned()),
+            RestaurantEvent::PaymentRefunded(e) => Event::PaymentRefunded(e.to_owned()),
+            RestaurantEvent::CapacityChanged(e) => Event::RestaurantCapacityChanged(e.to_owned()),
+            RestaurantEvent::OrderClosed(e) => Event::RestaurantOrderClosed(e.to_owned()),
+            RestaurantEvent::MenuItemMarkedUnavailable(e) => {
+                Event::MenuItemMarkedUnavailable(e.to_owned())
+            }
+            RestaurantEvent::MenuItemMarkedAvailable(e) => {
+                Event::MenuItemMarkedAvailable(e.to_owned())
+            }
+            RestaurantEvent::OpeningHoursChanged(e) => {
+                Event::RestaurantOpeningHoursChanged(e.to_owned())
+            }
+            RestaurantEvent::OrdersMigrated(e) => Event::RestaurantOrdersMigrated(e.to_owned()),
+            RestaurantEvent::OrdersReceived(e) => Event::RestaurantOrdersReceived(e.to_owned()),
+            RestaurantEvent::PromotionApplied(e) => Event::RestaurantPromotionApplied(e.to_owned()),
+            RestaurantEvent::PromotionWithdrawn(e) => {
+                Event::RestaurantPromotionWithdrawn(e.to_owned())
+            }
+            RestaurantEvent::OrderCancelled(e) => Event::RestaurantOrderCancelled(e.to_owned()),
+            RestaurantEvent::OrderSplit(e) => Event::RestaurantOrderSplit(e.to_owned()),
         },
-        Sum::Second(e) => match e {
+        Sum::First(Sum::Second(e)) => match e {
             OrderEvent::Created(e) => Event::OrderCreated(e.to_owned()),
             OrderEvent::Prepared(e) => Event::OrderPrepared(e.to_owned()),
+            OrderEvent::LineItemsUpdated(e) => Event::OrderLineItemsUpdated(e.to_owned()),
+            OrderEvent::Migrated(e) => Event::OrderMigrated(e.to_owned()),
+            OrderEvent::Cancelled(e) => Event::OrderCancelled(e.to_owned()),
+        },
+        Sum::Second(Sum::First(e)) => match e {
+            PromotionEvent::Created(e) => Event::PromotionCreated(e.to_owned()),
+            PromotionEvent::Expired(e) => Event::PromotionExpired(e.to_owned()),
+        },
+        Sum::Second(Sum::Second(Sum::First(e))) => match e {
+            InventoryEvent::Restocked(e) => Event::InventoryRestocked(e.to_owned()),
+            InventoryEvent::Reserved(e) => Event::StockReserved(e.to_owned()),
+            InventoryEvent::Released(e) => Event::StockReleased(e.to_owned()),
+        },
+        Sum::Second(Sum::Second(Sum::Second(Sum::First(e)))) => match e {
+            GiftCardEvent::Issued(e) => Event::GiftCardIssued(e.to_owned()),
+            GiftCardEvent::Redeemed(e) => Event::GiftCardRedeemed(e.to_owned()),
+            GiftCardEvent::Refunded(e) => Event::GiftCardRefunded(e.to_owned()),
+        },
+        Sum::Second(Sum::Second(Sum::Second(Sum::Second(e)))) => match e {
+            CourierEvent::LocationUpdated(e) => Event::CourierLocationUpdated(e.to_owned()),
         },
     }
 }
@@ -208,6 +893,44 @@ pub fn event_to_restaurant_event(event: &Event) -> Option<RestaurantEvent> {
         Event::OrderPlaced(e) => Some(RestaurantEvent::OrderPlaced(e.to_owned())),
         Event::OrderCreated(_e) => None,
         Event::OrderPrepared(_e) => None,
+        Event::OrderLineItemsModified(e) => {
+            Some(RestaurantEvent::OrderLineItemsModified(e.to_owned()))
+        }
+        Event::OrderLineItemsUpdated(_e) => None,
+        Event::PaymentCaptured(e) => Some(RestaurantEvent::PaymentCaptured(e.to_owned())),
+        Event::PaymentRefunded(e) => Some(RestaurantEvent::PaymentRefunded(e.to_owned())),
+        Event::RestaurantCapacityChanged(e) => Some(RestaurantEvent::CapacityChanged(e.to_owned())),
+        Event::RestaurantOrderClosed(e) => Some(RestaurantEvent::OrderClosed(e.to_owned())),
+        Event::MenuItemMarkedUnavailable(e) => {
+            Some(RestaurantEvent::MenuItemMarkedUnavailable(e.to_owned()))
+        }
+        Event::MenuItemMarkedAvailable(e) => {
+            Some(RestaurantEvent::MenuItemMarkedAvailable(e.to_owned()))
+        }
+        Event::RestaurantOpeningHoursChanged(e) => {
+            Some(RestaurantEvent::OpeningHoursChanged(e.to_owned()))
+        }
+        Event::RestaurantOrdersMigrated(e) => Some(RestaurantEvent::OrdersMigrated(e.to_owned())),
+        Event::RestaurantOrdersReceived(e) => Some(RestaurantEvent::OrdersReceived(e.to_owned())),
+        Event::OrderMigrated(_e) => None,
+        Event::RestaurantPromotionApplied(e) => {
+            Some(RestaurantEvent::PromotionApplied(e.to_owned()))
+        }
+        Event::RestaurantPromotionWithdrawn(e) => {
+            Some(RestaurantEvent::PromotionWithdrawn(e.to_owned()))
+        }
+        Event::PromotionCreated(_e) => None,
+        Event::PromotionExpired(_e) => None,
+        Event::RestaurantOrderCancelled(e) => Some(RestaurantEvent::OrderCancelled(e.to_owned())),
+        Event::OrderCancelled(_e) => None,
+        Event::InventoryRestocked(_e) => None,
+        Event::StockReserved(_e) => None,
+        Event::StockReleased(_e) => None,
+        Event::RestaurantOrderSplit(e) => Some(RestaurantEvent::OrderSplit(e.to_owned())),
+        Event::GiftCardIssued(_e) => None,
+        Event::GiftCardRedeemed(_e) => None,
+        Event::GiftCardRefunded(_e) => None,
+        Event::CourierLocationUpdated(_e) => None,
     }
 }
 
@@ -218,5 +941,69 @@ pub fn event_to_order_event(event: &Event) -> Option<OrderEvent> {
         Event::OrderPlaced(_e) => None,
         Event::OrderCreated(e) => Some(OrderEvent::Created(e.to_owned())),
         Event::OrderPrepared(e) => Some(OrderEvent::Prepared(e.to_owned())),
+        Event::OrderLineItemsModified(_e) => None,
+        Event::OrderLineItemsUpdated(e) => Some(OrderEvent::LineItemsUpdated(e.to_owned())),
+        Event::PaymentCaptured(_e) => None,
+        Event::PaymentRefunded(_e) => None,
+        Event::RestaurantCapacityChanged(_e) => None,
+        Event::RestaurantOrderClosed(_e) => None,
+        Event::MenuItemMarkedUnavailable(_e) => None,
+        Event::MenuItemMarkedAvailable(_e) => None,
+        Event::RestaurantOpeningHoursChanged(_e) => None,
+        Event::RestaurantOrdersMigrated(_e) => None,
+        Event::RestaurantOrdersReceived(_e) => None,
+        Event::OrderMigrated(e) => Some(OrderEvent::Migrated(e.to_owned())),
+        Event::RestaurantPromotionApplied(_e) => None,
+        Event::RestaurantPromotionWithdrawn(_e) => None,
+        Event::PromotionCreated(_e) => None,
+        Event::PromotionExpired(_e) => None,
+        Event::RestaurantOrderCancelled(_e) => None,
+        Event::OrderCancelled(e) => Some(OrderEvent::Cancelled(e.to_owned())),
+        Event::InventoryRestocked(_e) => None,
+        Event::StockReserved(_e) => None,
+        Event::StockReleased(_e) => None,
+        Event::RestaurantOrderSplit(_e) => None,
+        Event::GiftCardIssued(_e) => None,
+        Event::GiftCardRedeemed(_e) => None,
+        Event::GiftCardRefunded(_e) => None,
+        Event::CourierLocationUpdated(_e) => None,
+    }
+}
+
+pub fn event_to_promotion_event(event: &Event) -> Option<PromotionEvent> {
+    match event {
+        Event::PromotionCreated(e) => Some(PromotionEvent::Created(e.to_owned())),
+        Event::PromotionExpired(e) => Some(PromotionEvent::Expired(e.to_owned())),
+        _ => None,
+    }
+}
+
+pub fn event_to_inventory_event(event: &Event) -> Option<InventoryEvent> {
+    match event {
+        Event::InventoryRestocked(e) => Some(InventoryEvent::Restocked(e.to_owned())),
+        Event::StockReserved(e) => Some(InventoryEvent::Reserved(e.to_owned())),
+        Event::StockReleased(e) => Some(InventoryEvent::Released(e.to_owned())),
+        _ => None,
+    }
+}
+
+pub fn event_to_gift_card_event(event: &Event) -> Option<GiftCardEvent> {
+    match event {
+        Event::GiftCardIssued(e) => Some(GiftCardEvent::Issued(e.to_owned())),
+        Event::GiftCardRedeemed(e) => Some(GiftCardEvent::Redeemed(e.to_owned())),
+        Event::GiftCardRefunded(e) => Some(GiftCardEvent::Refunded(e.to_owned())),
+        _ => None,
+    }
+}
+
+/// The tags of the event (e.g. `cuisine:vietnamese`), attached on save, so the analytics can slice the event log by them (see `get_events_by_tag`)
+pub fn event_tags(event: &Event) -> Vec<String> {
+    let cuisine_tag = |menu: &RestaurantMenu| {
+        "cuisine:".to_string() + &format!("{:?}", menu.cuisine).to_lowercase()
+    };
+    match event {
+        Event::RestaurantCreated(e) => vec![cuisine_tag(&e.menu)],
+        Event::RestaurantMenuChanged(e) => vec![cuisine_tag(&e.menu)],
+        _ => Vec::new(),
     }
 }
@@ -0,0 +1,57 @@
+use fmodel_rust::saga::Saga;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::api::{OrderEvent, OrderId, OrderStatus, RestaurantId};
+
+/// The template of the customer notification: the status transition of the order the customer is notified of.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum NotificationTemplate {
+    OrderAccepted,
+    OrderPrepared,
+    OrderCancelled,
+    OrderRejected,
+}
+
+/// The intent to notify the customer of the order. It belongs to the Domain layer.
+/// The notification is rendered by the push-notification service, from the template and the parameters (the order and its restaurant).
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Notification {
+    pub template: NotificationTemplate,
+    pub order_identifier: OrderId,
+    /// The restaurant of the order. `None` for the legacy `OrderPrepared` events (they do not carry the restaurant id)
+    pub restaurant_identifier: Option<RestaurantId>,
+}
+
+/// A convenient type alias for the Notification saga
+type NotificationSaga<'a> = Saga<'a, OrderEvent, Notification>;
+
+/// The Notification saga - reacts to the status transitions of the orders with the intents to notify their customers (instead of the commands).
+/// It is a function that takes an event and returns a list of notifications.
+/// The order is accepted once it is created, and the customer is notified again when it is prepared, cancelled or rejected.
+pub fn notification_saga<'a>() -> NotificationSaga<'a> {
+    Saga {
+        react: Box::new(|event| match event {
+            OrderEvent::Created(event) => vec![Notification {
+                template: NotificationTemplate::OrderAccepted,
+                order_identifier: event.identifier.to_owned(),
+                restaurant_identifier: Some(event.restaurant_identifier.to_owned()),
+            }],
+            OrderEvent::Prepared(event) => vec![Notification {
+                template: NotificationTemplate::OrderPrepared,
+                order_identifier: event.identifier.to_owned(),
+                restaurant_identifier: event.restaurant_identifier.to_owned(),
+            }],
+            OrderEvent::Cancelled(event) => vec![Notification {
+                template: if event.status == OrderStatus::Rejected {
+                    NotificationTemplate::OrderRejected
+                } else {
+                    NotificationTemplate::OrderCancelled
+                },
+                order_identifier: event.identifier.to_owned(),
+                restaurant_identifier: Some(event.restaurant_identifier.to_owned()),
+            }],
+            // The modifications and the migrations of the orders are not notified
+            OrderEvent::LineItemsUpdated(..) | OrderEvent::Migrated(..) => vec![],
+        }),
+    }
+}
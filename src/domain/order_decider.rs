@@ -1,13 +1,16 @@
 use fmodel_rust::decider::Decider;
 use pgrx::error;
+use serde::{Deserialize, Serialize};
 
 use crate::domain::api::{
-    OrderCommand, OrderCreated, OrderEvent, OrderId, OrderLineItem, OrderPrepared, OrderStatus,
+    discounted_total, ConvertedMoney, OrderCancelled, OrderCommand, OrderCreated, OrderEvent,
+    OrderId, OrderLineItem, OrderLineItemsUpdated, OrderMigrated, OrderPrepared, OrderStatus,
     RestaurantId,
 };
+use crate::domain::exchange_rates::ExchangeRates;
 
 /// The state of the Order is represented by this struct. It belongs to the Domain layer.
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Order {
     pub identifier: OrderId,
     pub restaurant_identifier: RestaurantId,
@@ -19,31 +22,47 @@ pub struct Order {
 pub type OrderDecider<'a> = Decider<'a, OrderCommand, Option<Order>, OrderEvent>;
 
 /// Decider is a datatype/struct that represents the main decision-making algorithm. It belongs to the Domain layer.
-pub fn order_decider<'a>() -> OrderDecider<'a> {
+/// The exchange rates (at the order time) are the input of the decision on the reference total.
+pub fn order_decider<'a>(exchange_rates: impl ExchangeRates + 'a) -> OrderDecider<'a> {
     Decider {
         // Decide new events based on the current state and the command
         // Exhaustive pattern matching on the command
-        decide: Box::new(|command, state| match command {
+        decide: Box::new(move |command, state| match command {
             OrderCommand::Create(command) => {
                 if state.is_some() {
                     error!("Failed to create the Order. Order already exists!")
                 } else {
+                    // The total of the priced order less the discounts (in the currency of the menu), converted to the reference currency
+                    let reference_total = command.currency.as_ref().and_then(|currency| {
+                        let total = discounted_total(&command.line_items, &command.discounts)?;
+                        let reference_currency = exchange_rates.reference_currency();
+                        let Some(rate) = exchange_rates.rate(currency, &reference_currency) else {
+                            error!("Failed to create the Order. No exchange rate to the reference currency!");
+                        };
+                        Some(ConvertedMoney {
+                            amount: rate.convert(&total),
+                            currency: reference_currency,
+                            rate,
+                        })
+                    });
                     vec![OrderEvent::Created(OrderCreated {
                         identifier: command.identifier.to_owned(),
                         restaurant_identifier: command.restaurant_identifier.to_owned(),
                         status: OrderStatus::Created,
                         line_items: command.line_items.to_owned(),
+                        discounts: command.discounts.to_owned(),
+                        reference_total,
+                        group_identifier: command.group_identifier.to_owned(),
+                        customer_identifier: command.customer_identifier.to_owned(),
                         r#final: false,
                     })]
                 }
             }
             OrderCommand::MarkAsPrepared(command) => {
-                if state
-                    .clone()
-                    .is_some_and(|s| OrderStatus::Created == s.status)
-                {
+                if let Some(s) = state.clone().filter(|s| OrderStatus::Created == s.status) {
                     vec![OrderEvent::Prepared(OrderPrepared {
                         identifier: command.identifier.to_owned(),
+                        restaurant_identifier: Some(s.restaurant_identifier),
                         status: OrderStatus::Prepared,
                         r#final: true,
                     })]
@@ -51,6 +70,53 @@ pub fn order_decider<'a>() -> OrderDecider<'a> {
                     error!("Failed to mark the order as prepared. Order does not exist or is not in the correct state!");
                 }
             }
+            // The line items can be modified only before the order is prepared, and only by the restaurant of the order
+            OrderCommand::UpdateLineItems(command) => {
+                if state.clone().is_some_and(|s| {
+                    OrderStatus::Created == s.status
+                        && s.restaurant_identifier == command.restaurant_identifier
+                }) {
+                    vec![OrderEvent::LineItemsUpdated(OrderLineItemsUpdated {
+                        identifier: command.identifier.to_owned(),
+                        restaurant_identifier: command.restaurant_identifier.to_owned(),
+                        line_items: command.line_items.to_owned(),
+                        discounts: command.discounts.to_owned(),
+                        r#final: false,
+                    })]
+                } else {
+                    error!("Failed to modify the order. Order does not exist or is not in the correct state!");
+                }
+            }
+            // The order is migrated to the restaurant it was received by (the restaurant records merged, or split)
+            OrderCommand::Migrate(command) => {
+                if let Some(s) = state {
+                    vec![OrderEvent::Migrated(OrderMigrated {
+                        identifier: command.identifier.to_owned(),
+                        restaurant_identifier: command.restaurant_identifier.to_owned(),
+                        status: s.status.to_owned(),
+                        r#final: false,
+                    })]
+                } else {
+                    error!("Failed to migrate the order. Order does not exist!");
+                }
+            }
+            // Only the open (not yet prepared) order can be cancelled; the cancelled order stream is final
+            OrderCommand::Cancel(command) => {
+                if let Some(s) = state.clone().filter(|s| OrderStatus::Created == s.status) {
+                    vec![OrderEvent::Cancelled(OrderCancelled {
+                        identifier: command.identifier.to_owned(),
+                        restaurant_identifier: s.restaurant_identifier,
+                        status: if command.rejected {
+                            OrderStatus::Rejected
+                        } else {
+                            OrderStatus::Cancelled
+                        },
+                        r#final: true,
+                    })]
+                } else {
+                    error!("Failed to cancel the order. Order does not exist or is not in the correct state!");
+                }
+            }
         }),
         // Evolve the state based on the current state and the event
         // Exhaustive pattern matching on the event
@@ -67,6 +133,24 @@ pub fn order_decider<'a>() -> OrderDecider<'a> {
                 status: event.status.to_owned(),
                 line_items: s.line_items,
             }),
+            OrderEvent::LineItemsUpdated(event) => state.clone().map(|s| Order {
+                identifier: event.identifier.to_owned(),
+                restaurant_identifier: s.restaurant_identifier,
+                status: s.status,
+                line_items: event.line_items.to_owned(),
+            }),
+            OrderEvent::Migrated(event) => state.clone().map(|s| Order {
+                identifier: event.identifier.to_owned(),
+                restaurant_identifier: event.restaurant_identifier.to_owned(),
+                status: s.status,
+                line_items: s.line_items,
+            }),
+            OrderEvent::Cancelled(event) => state.clone().map(|s| Order {
+                identifier: event.identifier.to_owned(),
+                restaurant_identifier: s.restaurant_identifier,
+                status: event.status.to_owned(),
+                line_items: s.line_items,
+            }),
         }),
 
         // The initial state of the decider
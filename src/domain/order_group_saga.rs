@@ -0,0 +1,45 @@
+use fmodel_rust::saga::Saga;
+
+use crate::domain::api::{PlaceOrder, RestaurantCommand, RestaurantEvent};
+
+/// A convenient type alias for the Order group choreography saga
+type OrderGroupSaga<'a> = Saga<'a, RestaurantEvent, RestaurantCommand>;
+
+/// The Order group choreography saga - represents the central point of control deciding what to execute next.
+/// It is a function that takes an event and returns a list of commands.
+/// The order spanning multiple restaurants is placed as the (sub) orders at their restaurants, linked by the group; each sub order is then created by the Order saga.
+pub fn order_group_saga<'a>() -> OrderGroupSaga<'a> {
+    Saga {
+        react: Box::new(|event| match event {
+            RestaurantEvent::OrderSplit(event) => event
+                .orders
+                .iter()
+                .map(|order| {
+                    RestaurantCommand::PlaceOrder(PlaceOrder {
+                        identifier: order.restaurant_identifier.to_owned(),
+                        order_identifier: order.order_identifier.to_owned(),
+                        line_items: order.line_items.to_owned(),
+                        group_identifier: Some(event.group_identifier.to_owned()),
+                        customer_identifier: event.customer_identifier.to_owned(),
+                    })
+                })
+                .collect(),
+            RestaurantEvent::Created(..)
+            | RestaurantEvent::MenuChanged(..)
+            | RestaurantEvent::OrderPlaced(..)
+            | RestaurantEvent::OrderLineItemsModified(..)
+            | RestaurantEvent::PaymentCaptured(..)
+            | RestaurantEvent::PaymentRefunded(..)
+            | RestaurantEvent::CapacityChanged(..)
+            | RestaurantEvent::OrderClosed(..)
+            | RestaurantEvent::OpeningHoursChanged(..)
+            | RestaurantEvent::MenuItemMarkedUnavailable(..)
+            | RestaurantEvent::MenuItemMarkedAvailable(..)
+            | RestaurantEvent::OrdersMigrated(..)
+            | RestaurantEvent::OrdersReceived(..)
+            | RestaurantEvent::PromotionApplied(..)
+            | RestaurantEvent::PromotionWithdrawn(..)
+            | RestaurantEvent::OrderCancelled(..) => vec![],
+        }),
+    }
+}
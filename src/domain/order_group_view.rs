@@ -0,0 +1,117 @@
+use fmodel_rust::view::View;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::api::{OrderEvent, OrderGroupId, OrderId, OrderStatus, RestaurantId};
+
+/// The (sub) order of the group.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct OrderGroupEntry {
+    pub order_identifier: OrderId,
+    pub restaurant_identifier: RestaurantId,
+    pub status: OrderStatus,
+}
+
+/// The state of the Order Group View is represented by this struct. It belongs to the Domain layer.
+/// The status of the group combines the statuses of its (sub) orders: `Created` while any of them is created, `Prepared` once all of them are closed and any of them is prepared, otherwise `Cancelled` (or `Rejected`, if all of them are rejected).
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct OrderGroupState {
+    pub identifier: OrderGroupId,
+    pub status: OrderStatus,
+    pub orders: Vec<OrderGroupEntry>,
+}
+
+/// A convenient type alias for the Order Group view
+pub type OrderGroupView<'a> = View<'a, Option<OrderGroupState>, OrderEvent>;
+
+/// View represents the event handling algorithm. It belongs to the Domain layer.
+pub fn order_group_view<'a>() -> OrderGroupView<'a> {
+    View {
+        // Evolve the state based on the current state and the event
+        // Exhaustive pattern matching on the event
+        evolve: Box::new(|state, event| match event {
+            // The orders that are not split across the restaurants are not grouped
+            OrderEvent::Created(event) => match &event.group_identifier {
+                Some(group_identifier) => {
+                    let mut orders = state
+                        .as_ref()
+                        .map(|s| s.orders.to_owned())
+                        .unwrap_or_default();
+                    orders.push(OrderGroupEntry {
+                        order_identifier: event.identifier.to_owned(),
+                        restaurant_identifier: event.restaurant_identifier.to_owned(),
+                        status: event.status.to_owned(),
+                    });
+                    Some(OrderGroupState {
+                        identifier: group_identifier.to_owned(),
+                        status: combined_status(&orders),
+                        orders,
+                    })
+                }
+                None => state.clone(),
+            },
+            OrderEvent::Prepared(event) => {
+                update_order(state, &event.identifier, |order| OrderGroupEntry {
+                    status: event.status.to_owned(),
+                    ..order
+                })
+            }
+            OrderEvent::Cancelled(event) => {
+                update_order(state, &event.identifier, |order| OrderGroupEntry {
+                    status: event.status.to_owned(),
+                    ..order
+                })
+            }
+            OrderEvent::Migrated(event) => {
+                update_order(state, &event.identifier, |order| OrderGroupEntry {
+                    restaurant_identifier: event.restaurant_identifier.to_owned(),
+                    status: event.status.to_owned(),
+                    ..order
+                })
+            }
+            OrderEvent::LineItemsUpdated(..) => state.clone(),
+        }),
+
+        // The initial state of the view
+        initial_state: Box::new(|| None),
+    }
+}
+
+/// Updates the (sub) order of the group, and the combined status of the group.
+fn update_order(
+    state: &Option<OrderGroupState>,
+    order_identifier: &OrderId,
+    update: impl Fn(OrderGroupEntry) -> OrderGroupEntry,
+) -> Option<OrderGroupState> {
+    state.clone().map(|s| {
+        let orders: Vec<OrderGroupEntry> = s
+            .orders
+            .into_iter()
+            .map(|order| {
+                if order.order_identifier == *order_identifier {
+                    update(order)
+                } else {
+                    order
+                }
+            })
+            .collect();
+        OrderGroupState {
+            status: combined_status(&orders),
+            orders,
+            ..s
+        }
+    })
+}
+
+/// The status of the group, combining the statuses of its (sub) orders.
+fn combined_status(orders: &[OrderGroupEntry]) -> OrderStatus {
+    let any = |status: OrderStatus| orders.iter().any(|order| order.status == status);
+    if orders.is_empty() || any(OrderStatus::Created) {
+        OrderStatus::Created
+    } else if any(OrderStatus::Prepared) {
+        OrderStatus::Prepared
+    } else if any(OrderStatus::Cancelled) {
+        OrderStatus::Cancelled
+    } else {
+        OrderStatus::Rejected
+    }
+}
@@ -0,0 +1,54 @@
+use crate::domain::api::{OrderId, RestaurantEvent, RestaurantId};
+use crate::framework::application::aggregating_view::Aggregate;
+
+/// The increment of the payment counters of the order. It belongs to the Domain layer.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct OrderPayments {
+    /// The captured amount (the sum of the split payments)
+    pub captured: i64,
+    /// The refunded amount (the sum of the partial refunds)
+    pub refunded: i64,
+}
+
+/// The aggregation of the payment events into the payment counters of the order (placed at the restaurant). It belongs to the Domain layer.
+pub fn order_payments_view<'a>(
+) -> Aggregate<'a, RestaurantEvent, (RestaurantId, OrderId), OrderPayments> {
+    // Exhaustive pattern matching on the event
+    Box::new(|event| match event {
+        RestaurantEvent::PaymentCaptured(event) => Some((
+            (
+                event.identifier.to_owned(),
+                event.order_identifier.to_owned(),
+            ),
+            OrderPayments {
+                captured: event.amount.0 as i64,
+                refunded: 0,
+            },
+        )),
+        RestaurantEvent::PaymentRefunded(event) => Some((
+            (
+                event.identifier.to_owned(),
+                event.order_identifier.to_owned(),
+            ),
+            OrderPayments {
+                captured: 0,
+                refunded: event.amount.0 as i64,
+            },
+        )),
+        RestaurantEvent::Created(_)
+        | RestaurantEvent::MenuChanged(_)
+        | RestaurantEvent::OrderPlaced(_)
+        | RestaurantEvent::OrderLineItemsModified(_)
+        | RestaurantEvent::CapacityChanged(_)
+        | RestaurantEvent::OrderClosed(_)
+        | RestaurantEvent::OrderCancelled(_)
+        | RestaurantEvent::OrderSplit(_)
+        | RestaurantEvent::OpeningHoursChanged(_)
+        | RestaurantEvent::MenuItemMarkedUnavailable(_)
+        | RestaurantEvent::MenuItemMarkedAvailable(_)
+        | RestaurantEvent::OrdersMigrated(_)
+        | RestaurantEvent::OrdersReceived(_)
+        | RestaurantEvent::PromotionApplied(_)
+        | RestaurantEvent::PromotionWithdrawn(_) => None,
+    })
+}
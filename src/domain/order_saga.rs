@@ -1,6 +1,8 @@
 use fmodel_rust::saga::Saga;
 
-use crate::domain::api::{CreateOrder, OrderCommand, RestaurantEvent};
+use crate::domain::api::{
+    CancelOrder, CreateOrder, MigrateOrder, OrderCommand, RestaurantEvent, UpdateOrderLineItems,
+};
 
 /// A convenient type alias for the Order choreography saga
 type OrderSaga<'a> = Saga<'a, RestaurantEvent, OrderCommand>;
@@ -15,14 +17,78 @@ pub fn order_saga<'a>() -> OrderSaga<'a> {
                     identifier: event.order_identifier.to_owned(),
                     restaurant_identifier: event.identifier.to_owned(),
                     line_items: event.line_items.to_owned(),
+                    currency: event.currency.to_owned(),
+                    discounts: event.discounts.to_owned(),
+                    group_identifier: event.group_identifier.to_owned(),
+                    customer_identifier: event.customer_identifier.to_owned(),
                 })]
             }
+            RestaurantEvent::OrderLineItemsModified(event) => {
+                vec![OrderCommand::UpdateLineItems(UpdateOrderLineItems {
+                    identifier: event.order_identifier.to_owned(),
+                    restaurant_identifier: event.identifier.to_owned(),
+                    line_items: event.line_items.to_owned(),
+                    discounts: event.discounts.to_owned(),
+                })]
+            }
+            // The open orders follow the restaurant they were migrated to; the closed (prepared) order streams are final
+            RestaurantEvent::OrdersReceived(event) => event
+                .orders
+                .iter()
+                .filter(|order| order.open)
+                .map(|order| {
+                    OrderCommand::Migrate(MigrateOrder {
+                        identifier: order.order_identifier.to_owned(),
+                        restaurant_identifier: event.identifier.to_owned(),
+                    })
+                })
+                .collect(),
+            RestaurantEvent::OrderCancelled(event) => {
+                vec![OrderCommand::Cancel(CancelOrder {
+                    identifier: event.order_identifier.to_owned(),
+                    rejected: event.rejected,
+                })]
+            }
+            // The sub orders are created once they are placed at their restaurants
+            RestaurantEvent::OrderSplit(..) => {
+                vec![]
+            }
             RestaurantEvent::Created(..) => {
                 vec![]
             }
             RestaurantEvent::MenuChanged(..) => {
                 vec![]
             }
+            RestaurantEvent::PaymentCaptured(..) => {
+                vec![]
+            }
+            RestaurantEvent::PaymentRefunded(..) => {
+                vec![]
+            }
+            RestaurantEvent::CapacityChanged(..) => {
+                vec![]
+            }
+            RestaurantEvent::OrderClosed(..) => {
+                vec![]
+            }
+            RestaurantEvent::OpeningHoursChanged(..) => {
+                vec![]
+            }
+            RestaurantEvent::MenuItemMarkedUnavailable(..) => {
+                vec![]
+            }
+            RestaurantEvent::MenuItemMarkedAvailable(..) => {
+                vec![]
+            }
+            RestaurantEvent::OrdersMigrated(..) => {
+                vec![]
+            }
+            RestaurantEvent::PromotionApplied(..) => {
+                vec![]
+            }
+            RestaurantEvent::PromotionWithdrawn(..) => {
+                vec![]
+            }
         }),
     }
 }
@@ -1,7 +1,10 @@
 use fmodel_rust::view::View;
 use serde::{Deserialize, Serialize};
 
-use crate::domain::api::{OrderEvent, OrderId, OrderLineItem, OrderStatus, RestaurantId};
+use crate::domain::api::{
+    discounted_total, order_total, AppliedDiscount, Money, OrderEvent, OrderId, OrderLineItem,
+    OrderStatus, RestaurantId,
+};
 
 /// The state of the Order is represented by this struct. It belongs to the Domain layer.
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
@@ -10,6 +13,15 @@ pub struct OrderViewState {
     pub restaurant_identifier: RestaurantId,
     pub status: OrderStatus,
     pub line_items: Vec<OrderLineItem>,
+    /// The total of the order, frozen at the time the order was placed. `None` if any of the line items is not priced
+    #[serde(default)]
+    pub total: Option<Money>,
+    /// The discounts of the line items, by the promotions of the restaurant. The documents projected before the promotions were introduced default to none
+    #[serde(default)]
+    pub discounts: Vec<AppliedDiscount>,
+    /// The total less the discounts: the amount to pay. `None` if any of the line items is not priced
+    #[serde(default)]
+    pub discounted_total: Option<Money>,
 }
 
 /// A convenient type alias for the Order view
@@ -26,13 +38,37 @@ pub fn order_view<'a>() -> OrderView<'a> {
                 restaurant_identifier: event.restaurant_identifier.to_owned(),
                 status: event.status.to_owned(),
                 line_items: event.line_items.to_owned(),
+                total: order_total(&event.line_items),
+                discounts: event.discounts.to_owned(),
+                discounted_total: discounted_total(&event.line_items, &event.discounts),
             }),
 
             OrderEvent::Prepared(event) => state.clone().map(|s| OrderViewState {
                 identifier: event.identifier.to_owned(),
-                restaurant_identifier: s.restaurant_identifier,
                 status: event.status.to_owned(),
-                line_items: s.line_items,
+                ..s
+            }),
+
+            OrderEvent::Cancelled(event) => state.clone().map(|s| OrderViewState {
+                identifier: event.identifier.to_owned(),
+                status: event.status.to_owned(),
+                ..s
+            }),
+
+            // The total is recalculated from the modified line items (priced, and discounted, at the time of the modification)
+            OrderEvent::LineItemsUpdated(event) => state.clone().map(|s| OrderViewState {
+                identifier: event.identifier.to_owned(),
+                line_items: event.line_items.to_owned(),
+                total: order_total(&event.line_items),
+                discounts: event.discounts.to_owned(),
+                discounted_total: discounted_total(&event.line_items, &event.discounts),
+                ..s
+            }),
+
+            OrderEvent::Migrated(event) => state.clone().map(|s| OrderViewState {
+                identifier: event.identifier.to_owned(),
+                restaurant_identifier: event.restaurant_identifier.to_owned(),
+                ..s
             }),
         }),
 
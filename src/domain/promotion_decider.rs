@@ -0,0 +1,76 @@
+use fmodel_rust::decider::Decider;
+use pgrx::error;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::api::{
+    DiscountPercentage, MenuItemId, PromotionCommand, PromotionCreated, PromotionEvent,
+    PromotionExpired, PromotionId, PromotionName, RestaurantId,
+};
+
+/// The state of the Promotion is represented by this struct. It belongs to the Domain layer.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Promotion {
+    pub identifier: PromotionId,
+    pub restaurant_identifier: RestaurantId,
+    pub name: PromotionName,
+    pub percentage: DiscountPercentage,
+    pub menu_item_ids: Vec<MenuItemId>,
+}
+
+/// A convenient type alias for the Promotion decider
+pub type PromotionDecider<'a> = Decider<'a, PromotionCommand, Option<Promotion>, PromotionEvent>;
+
+/// Decider is a datatype/struct that represents the main decision-making algorithm. It belongs to the Domain layer.
+/// The promotion discounts the orders of the restaurant from its creation until it expires (see the restaurant promotion saga).
+pub fn promotion_decider<'a>() -> PromotionDecider<'a> {
+    Decider {
+        // Decide new events based on the current state and the command
+        // Exhaustive pattern matching on the command
+        decide: Box::new(|command, state| match command {
+            PromotionCommand::Create(command) => {
+                if state.is_some() {
+                    error!("Failed to create the Promotion. Promotion already exists!");
+                }
+                // Invariant: the discount is a fraction of the price, it never exceeds the price
+                if !(1..=100).contains(&command.percentage.0) {
+                    error!("Failed to create the Promotion. The discount must be between 1 and 100 percent!");
+                }
+                vec![PromotionEvent::Created(PromotionCreated {
+                    identifier: command.identifier.to_owned(),
+                    restaurant_identifier: command.restaurant_identifier.to_owned(),
+                    name: command.name.to_owned(),
+                    percentage: command.percentage,
+                    menu_item_ids: command.menu_item_ids.to_owned(),
+                    r#final: false,
+                })]
+            }
+            // The expired promotion stream is final: it can not be expired (or re-created) again
+            PromotionCommand::Expire(command) => {
+                if let Some(s) = state {
+                    vec![PromotionEvent::Expired(PromotionExpired {
+                        identifier: command.identifier.to_owned(),
+                        restaurant_identifier: s.restaurant_identifier.to_owned(),
+                        r#final: true,
+                    })]
+                } else {
+                    error!("Failed to expire the Promotion. Promotion does not exist!");
+                }
+            }
+        }),
+        // Evolve the state based on the current state and the event
+        // Exhaustive pattern matching on the event
+        evolve: Box::new(|state, event| match event {
+            PromotionEvent::Created(event) => Some(Promotion {
+                identifier: event.identifier.to_owned(),
+                restaurant_identifier: event.restaurant_identifier.to_owned(),
+                name: event.name.to_owned(),
+                percentage: event.percentage,
+                menu_item_ids: event.menu_item_ids.to_owned(),
+            }),
+            PromotionEvent::Expired(_) => state.clone(),
+        }),
+
+        // The initial state of the decider
+        initial_state: Box::new(|| None),
+    }
+}
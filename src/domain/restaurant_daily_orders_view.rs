@@ -0,0 +1,41 @@
+use crate::domain::api::{discounted_total, OrderEvent, RestaurantId};
+use crate::framework::application::aggregating_view::Aggregate;
+
+/// The increment of the daily order counters of the restaurant. It belongs to the Domain layer.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct RestaurantDailyOrders {
+    /// The number of the orders
+    pub orders: i64,
+    /// The number of the ordered items (the sum of the line item quantities)
+    pub items: i64,
+    /// The revenue (the sum of the order totals less the discounts; the orders with unpriced line items are not included)
+    pub revenue: i64,
+}
+
+/// The aggregation of the order events into the daily order counters of the restaurant. It belongs to the Domain layer.
+/// The day (bucket) is the day in which the event was appended to the event store.
+pub fn restaurant_daily_orders_view<'a>(
+) -> Aggregate<'a, OrderEvent, RestaurantId, RestaurantDailyOrders> {
+    // Exhaustive pattern matching on the event
+    Box::new(|event| match event {
+        OrderEvent::Created(event) => Some((
+            event.restaurant_identifier.to_owned(),
+            RestaurantDailyOrders {
+                orders: 1,
+                items: event
+                    .line_items
+                    .iter()
+                    .map(|line_item| i64::from(line_item.quantity.0))
+                    .sum(),
+                revenue: discounted_total(&event.line_items, &event.discounts)
+                    .map(|total| total.0 as i64)
+                    .unwrap_or_default(),
+            },
+        )),
+        // The orders are counted when they are created (at the restaurant they were created at); the modifications and the migrations are not included
+        OrderEvent::Prepared(_)
+        | OrderEvent::LineItemsUpdated(_)
+        | OrderEvent::Migrated(_)
+        | OrderEvent::Cancelled(_) => None,
+    })
+}
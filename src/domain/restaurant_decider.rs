@@ -1,17 +1,49 @@
+use crate::domain::inventory_decider::is_insufficient;
+use crate::domain::stock_levels::StockLevels;
+use crate::framework::domain::clock::Clock;
 use fmodel_rust::decider::Decider;
 use pgrx::error;
+use serde::{Deserialize, Serialize};
 
 use crate::domain::api::{
-    OrderPlaced, RestaurantCommand, RestaurantCreated, RestaurantEvent, RestaurantId,
-    RestaurantMenu, RestaurantMenuChanged, RestaurantName,
+    discounted_total, AppliedDiscount, MenuItemId, MenuItemMarkedAvailable,
+    MenuItemMarkedUnavailable, MigratedOrder, Money, OpeningHours, OrderGroupId, OrderId,
+    OrderLineItem, OrderLineItemsModified, OrderPlaced, PaymentCaptured, PaymentRefunded,
+    RestaurantCapacityChanged, RestaurantCommand, RestaurantCreated, RestaurantEvent, RestaurantId,
+    RestaurantMenu, RestaurantMenuChanged, RestaurantMenuVersion, RestaurantName,
+    RestaurantOpeningHoursChanged, RestaurantOrderCancelled, RestaurantOrderClosed,
+    RestaurantOrderSplit, RestaurantOrdersMigrated, RestaurantOrdersReceived, RestaurantPromotion,
+    RestaurantPromotionApplied, RestaurantPromotionWithdrawn, SplitOrder, StockItem,
 };
 
 /// The state of the Restaurant is represented by this struct. It belongs to the Domain layer.
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Restaurant {
     identifier: RestaurantId,
     name: RestaurantName,
     menu: RestaurantMenu,
+    menu_version: RestaurantMenuVersion,
+    payments: Vec<OrderPayment>,
+    /// The maximum number of the open orders, or `None` for no limit
+    max_concurrent_orders: Option<u32>,
+    /// The open orders: placed, and not yet closed (prepared)
+    open_orders: Vec<OrderId>,
+    /// The menu items that are unavailable (86'd), and can not be ordered
+    unavailable_items: Vec<MenuItemId>,
+    /// The opening hours, or `None` if the restaurant is always open
+    opening_hours: Option<OpeningHours>,
+    /// The current promotions, discounting the orders when they are placed
+    promotions: Vec<RestaurantPromotion>,
+}
+
+/// The payments of the order placed at the restaurant: the total of the order, and the cumulative captured and refunded amounts.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct OrderPayment {
+    order_identifier: OrderId,
+    /// The total less the discounts. `None` if any of the line items is not priced: such orders can not be paid
+    total: Option<Money>,
+    captured: Money,
+    refunded: Money,
 }
 
 /// A convenient type alias for the Restaurant decider
@@ -19,11 +51,15 @@ pub type RestaurantDecider<'a> =
     Decider<'a, RestaurantCommand, Option<Restaurant>, RestaurantEvent>;
 
 /// Decider is a datatype/struct that represents the main decision-making algorithm. It belongs to the Domain layer.
-pub fn restaurant_decider<'a>() -> RestaurantDecider<'a> {
+/// The clock (the current time) is the input of the decision on the opening hours, and the stock levels (of the inventory of the restaurant) of the decision on the ordered quantities.
+pub fn restaurant_decider<'a>(
+    clock: impl Clock + 'a,
+    stock_levels: impl StockLevels + 'a,
+) -> RestaurantDecider<'a> {
     Decider {
         // Decide new events based on the current state and the command
         // Exhaustive pattern matching on the command
-        decide: Box::new(|command, state| match command {
+        decide: Box::new(move |command, state| match command {
             RestaurantCommand::CreateRestaurant(command) => {
                 if state.is_some() {
                     error!("Failed to create the Restaurant. Restaurant already exists!");
@@ -37,28 +73,402 @@ pub fn restaurant_decider<'a>() -> RestaurantDecider<'a> {
                 }
             }
             RestaurantCommand::ChangeMenu(command) => {
-                if state.is_some() {
+                if let Some(state) = state {
+                    // The menu version is monotonically increasing: every change is the next version of the current menu
                     vec![RestaurantEvent::MenuChanged(RestaurantMenuChanged {
                         identifier: command.identifier.to_owned(),
                         menu: command.menu.to_owned(),
+                        menu_version: RestaurantMenuVersion(state.menu_version.0 + 1),
                         r#final: false,
                     })]
                 } else {
                     error!("Failed to change the menu. Restaurant does not exist!");
                 }
             }
+            // The order spanning multiple restaurants is split into the (sub) orders per restaurant, grouped by the order identifier; the sub orders are placed by the saga
+            RestaurantCommand::PlaceOrder(command)
+                if spans_restaurants(&command.identifier, &command.line_items) =>
+            {
+                if state.is_none() {
+                    error!("Failed to place the order. Restaurant does not exist!");
+                } else if command.group_identifier.is_some() {
+                    error!("Failed to place the order. The line items span multiple restaurants!");
+                } else {
+                    let group_identifier = OrderGroupId(command.order_identifier.0);
+                    vec![RestaurantEvent::OrderSplit(RestaurantOrderSplit {
+                        identifier: command.identifier.to_owned(),
+                        orders: split_line_items(&command.identifier, &command.line_items)
+                            .into_iter()
+                            .map(|(restaurant_identifier, line_items)| SplitOrder {
+                                order_identifier: group_identifier.order_of(&restaurant_identifier),
+                                restaurant_identifier,
+                                line_items,
+                            })
+                            .collect(),
+                        group_identifier,
+                        customer_identifier: command.customer_identifier.to_owned(),
+                        r#final: false,
+                    })]
+                }
+            }
             RestaurantCommand::PlaceOrder(command) => {
-                if state.is_some() {
+                if let Some(state) = state {
+                    // Invariant: the number of the open orders never exceeds the capacity of the restaurant
+                    if state
+                        .max_concurrent_orders
+                        .is_some_and(|max| state.open_orders.len() >= max as usize)
+                    {
+                        error!("Failed to place the order. Restaurant is at capacity!");
+                    }
+                    // Invariant: the orders are placed within the opening hours (in the local time of the restaurant)
+                    if let Some(opening_hours) = &state.opening_hours {
+                        if !clock
+                            .local_time(&opening_hours.time_zone)
+                            .is_some_and(|time| opening_hours.is_open_at(&time))
+                        {
+                            error!("Failed to place the order. Restaurant is closed!");
+                        }
+                    }
+                    // Invariant: the unavailable menu items can not be ordered
+                    if state.has_unavailable_items(&command.line_items) {
+                        error!("Failed to place the order. The menu item is unavailable!");
+                    }
+                    // Invariant: the ordered quantities never exceed the stock of the inventory (the stock is reserved by the saga)
+                    if stock_levels
+                        .available(&command.identifier, &command.order_identifier)
+                        .is_some_and(|available| {
+                            is_insufficient(
+                                &available,
+                                &StockItem::of_line_items(&command.line_items),
+                            )
+                        })
+                    {
+                        error!("Failed to place the order. Insufficient stock of the menu item!");
+                    }
+                    // Snapshot of the unit prices, from the current menu
+                    let line_items: Vec<OrderLineItem> = command
+                        .line_items
+                        .iter()
+                        .map(|line_item| OrderLineItem {
+                            price: state
+                                .menu
+                                .items
+                                .iter()
+                                .find(|item| item.id == line_item.menu_item_id)
+                                .map(|item| item.price.to_owned()),
+                            ..line_item.to_owned()
+                        })
+                        .collect();
                     vec![RestaurantEvent::OrderPlaced(OrderPlaced {
                         identifier: command.identifier.to_owned(),
                         order_identifier: command.order_identifier.to_owned(),
-                        line_items: command.line_items.to_owned(),
+                        discounts: state.discounts(&line_items),
+                        line_items,
+                        currency: state.menu.currency.to_owned(),
+                        group_identifier: command.group_identifier.to_owned(),
+                        customer_identifier: command.customer_identifier.to_owned(),
                         r#final: false,
                     })]
                 } else {
                     error!("Failed to place the order. Restaurant does not exist!");
                 }
             }
+            RestaurantCommand::ModifyOrderLineItems(command) => {
+                if let Some(state) = state {
+                    // Invariant: the (sub) order is placed at the single restaurant
+                    if spans_restaurants(&command.identifier, &command.line_items) {
+                        error!(
+                            "Failed to modify the order. The line items span multiple restaurants!"
+                        );
+                    }
+                    if state.has_unavailable_items(&command.line_items) {
+                        error!("Failed to modify the order. The menu item is unavailable!");
+                    }
+                    if stock_levels
+                        .available(&command.identifier, &command.order_identifier)
+                        .is_some_and(|available| {
+                            is_insufficient(
+                                &available,
+                                &StockItem::of_line_items(&command.line_items),
+                            )
+                        })
+                    {
+                        error!("Failed to modify the order. Insufficient stock of the menu item!");
+                    }
+                    // Every line item must be on the current menu: the unit prices are snapshotted from it, and discounted by the current promotions
+                    let line_items = command
+                        .line_items
+                        .iter()
+                        .map(|line_item| {
+                            state
+                                .menu
+                                .items
+                                .iter()
+                                .find(|item| item.id == line_item.menu_item_id)
+                                .map(|item| OrderLineItem {
+                                    price: Some(item.price.to_owned()),
+                                    ..line_item.to_owned()
+                                })
+                        })
+                        .collect::<Option<Vec<_>>>();
+                    match line_items {
+                        Some(line_items) => {
+                            vec![RestaurantEvent::OrderLineItemsModified(OrderLineItemsModified {
+                                identifier: command.identifier.to_owned(),
+                                order_identifier: command.order_identifier.to_owned(),
+                                discounts: state.discounts(&line_items),
+                                line_items,
+                                r#final: false,
+                            })]
+                        }
+                        None => error!("Failed to modify the order. The menu item is not on the menu of the restaurant!"),
+                    }
+                } else {
+                    error!("Failed to modify the order. Restaurant does not exist!");
+                }
+            }
+            RestaurantCommand::ChangeCapacity(command) => {
+                if state.is_some() {
+                    vec![RestaurantEvent::CapacityChanged(
+                        RestaurantCapacityChanged {
+                            identifier: command.identifier.to_owned(),
+                            max_concurrent_orders: command.max_concurrent_orders,
+                            r#final: false,
+                        },
+                    )]
+                } else {
+                    error!("Failed to change the capacity. Restaurant does not exist!");
+                }
+            }
+            RestaurantCommand::CloseOrder(command) => {
+                if state
+                    .as_ref()
+                    .is_some_and(|state| state.open_orders.contains(&command.order_identifier))
+                {
+                    vec![RestaurantEvent::OrderClosed(RestaurantOrderClosed {
+                        identifier: command.identifier.to_owned(),
+                        order_identifier: command.order_identifier.to_owned(),
+                        r#final: false,
+                    })]
+                } else {
+                    error!("Failed to close the order. Order is not open at the restaurant!");
+                }
+            }
+            // Only the open order can be cancelled (or rejected): the prepared order is closed
+            RestaurantCommand::CancelOrder(command) => {
+                if state
+                    .as_ref()
+                    .is_some_and(|state| state.open_orders.contains(&command.order_identifier))
+                {
+                    vec![RestaurantEvent::OrderCancelled(RestaurantOrderCancelled {
+                        identifier: command.identifier.to_owned(),
+                        order_identifier: command.order_identifier.to_owned(),
+                        rejected: command.rejected,
+                        r#final: false,
+                    })]
+                } else {
+                    error!("Failed to cancel the order. Order is not open at the restaurant!");
+                }
+            }
+            RestaurantCommand::MarkMenuItemUnavailable(command) => {
+                if state
+                    .as_ref()
+                    .is_some_and(|state| state.is_on_menu(&command.menu_item_id))
+                {
+                    vec![RestaurantEvent::MenuItemMarkedUnavailable(
+                        MenuItemMarkedUnavailable {
+                            identifier: command.identifier.to_owned(),
+                            menu_item_id: command.menu_item_id.to_owned(),
+                            r#final: false,
+                        },
+                    )]
+                } else {
+                    error!("Failed to mark the menu item unavailable. The menu item is not on the menu of the restaurant!");
+                }
+            }
+            RestaurantCommand::MarkMenuItemAvailable(command) => {
+                if state
+                    .as_ref()
+                    .is_some_and(|state| state.is_on_menu(&command.menu_item_id))
+                {
+                    vec![RestaurantEvent::MenuItemMarkedAvailable(
+                        MenuItemMarkedAvailable {
+                            identifier: command.identifier.to_owned(),
+                            menu_item_id: command.menu_item_id.to_owned(),
+                            r#final: false,
+                        },
+                    )]
+                } else {
+                    error!("Failed to mark the menu item available. The menu item is not on the menu of the restaurant!");
+                }
+            }
+            RestaurantCommand::ChangeOpeningHours(command) => {
+                if state.is_none() {
+                    error!("Failed to change the opening hours. Restaurant does not exist!");
+                }
+                if let Some(opening_hours) = &command.opening_hours {
+                    if !opening_hours.is_valid() {
+                        error!("Failed to change the opening hours. Invalid opening period!");
+                    }
+                    if clock.local_time(&opening_hours.time_zone).is_none() {
+                        error!("Failed to change the opening hours. Unknown time zone!");
+                    }
+                }
+                vec![RestaurantEvent::OpeningHoursChanged(
+                    RestaurantOpeningHoursChanged {
+                        identifier: command.identifier.to_owned(),
+                        opening_hours: command.opening_hours.to_owned(),
+                        r#final: false,
+                    },
+                )]
+            }
+            // The orders move with their payments: the invariants of the payments hold at the target restaurant
+            RestaurantCommand::MigrateOrders(command) => {
+                let Some(state) = state else {
+                    error!("Failed to migrate the orders. Restaurant does not exist!");
+                };
+                if command.target_identifier == command.identifier {
+                    error!("Failed to migrate the orders. The target is the same restaurant!");
+                }
+                let orders = match &command.order_identifiers {
+                    None => state
+                        .payments
+                        .iter()
+                        .map(|payment| state.to_migrated_order(payment))
+                        .collect(),
+                    Some(order_identifiers) => order_identifiers
+                        .iter()
+                        .map(|order_identifier| {
+                            state
+                                .payments
+                                .iter()
+                                .find(|payment| payment.order_identifier == *order_identifier)
+                                .map(|payment| state.to_migrated_order(payment))
+                        })
+                        .collect::<Option<Vec<_>>>()
+                        .unwrap_or_else(|| {
+                            error!("Failed to migrate the orders. Order was not placed at the restaurant!")
+                        }),
+                };
+                vec![RestaurantEvent::OrdersMigrated(RestaurantOrdersMigrated {
+                    identifier: command.identifier.to_owned(),
+                    target_identifier: command.target_identifier.to_owned(),
+                    orders,
+                    // The merged restaurant is retired
+                    r#final: command.order_identifiers.is_none(),
+                })]
+            }
+            // The received orders do not count against the capacity of the restaurant: the migration is administrative
+            RestaurantCommand::ReceiveOrders(command) => {
+                let Some(state) = state else {
+                    error!("Failed to receive the orders. Restaurant does not exist!");
+                };
+                if command.source_identifier == command.identifier {
+                    error!("Failed to receive the orders. The source is the same restaurant!");
+                }
+                if command.orders.iter().any(|order| {
+                    state
+                        .payments
+                        .iter()
+                        .any(|payment| payment.order_identifier == order.order_identifier)
+                }) {
+                    error!(
+                        "Failed to receive the orders. Order was already placed at the restaurant!"
+                    );
+                }
+                vec![RestaurantEvent::OrdersReceived(RestaurantOrdersReceived {
+                    identifier: command.identifier.to_owned(),
+                    source_identifier: command.source_identifier.to_owned(),
+                    orders: command.orders.to_owned(),
+                    r#final: false,
+                })]
+            }
+            // The promotion is replaced if it is applied already
+            RestaurantCommand::ApplyPromotion(command) => {
+                if state.is_some() {
+                    vec![RestaurantEvent::PromotionApplied(
+                        RestaurantPromotionApplied {
+                            identifier: command.identifier.to_owned(),
+                            promotion: command.promotion.to_owned(),
+                            r#final: false,
+                        },
+                    )]
+                } else {
+                    error!("Failed to apply the promotion. Restaurant does not exist!");
+                }
+            }
+            RestaurantCommand::WithdrawPromotion(command) => {
+                if state.as_ref().is_some_and(|state| {
+                    state
+                        .promotions
+                        .iter()
+                        .any(|promotion| promotion.id == command.promotion_id)
+                }) {
+                    vec![RestaurantEvent::PromotionWithdrawn(
+                        RestaurantPromotionWithdrawn {
+                            identifier: command.identifier.to_owned(),
+                            promotion_id: command.promotion_id.to_owned(),
+                            r#final: false,
+                        },
+                    )]
+                } else {
+                    error!("Failed to withdraw the promotion. Promotion is not applied at the restaurant!");
+                }
+            }
+            // Invariant: the cumulative captured amount never exceeds the total of the order
+            RestaurantCommand::CapturePayment(command) => {
+                let Some(state) = state else {
+                    error!("Failed to capture the payment. Restaurant does not exist!");
+                };
+                let Some(payment) = state
+                    .payments
+                    .iter()
+                    .find(|payment| payment.order_identifier == command.order_identifier)
+                else {
+                    error!(
+                        "Failed to capture the payment. Order was not placed at the restaurant!"
+                    );
+                };
+                match &payment.total {
+                    Some(total)
+                        if command.amount.0 > 0
+                            && payment.captured.0 + command.amount.0 <= total.0 =>
+                    {
+                        vec![RestaurantEvent::PaymentCaptured(PaymentCaptured {
+                            identifier: command.identifier.to_owned(),
+                            order_identifier: command.order_identifier.to_owned(),
+                            amount: command.amount.to_owned(),
+                            r#final: false,
+                        })]
+                    }
+                    _ => error!("Failed to capture the payment. The amount exceeds the unpaid total of the order!"),
+                }
+            }
+            // Invariant: the cumulative refunded amount never exceeds the captured amount
+            RestaurantCommand::RefundPayment(command) => {
+                let Some(state) = state else {
+                    error!("Failed to refund the payment. Restaurant does not exist!");
+                };
+                let Some(payment) = state
+                    .payments
+                    .iter()
+                    .find(|payment| payment.order_identifier == command.order_identifier)
+                else {
+                    error!("Failed to refund the payment. Order was not placed at the restaurant!");
+                };
+                if command.amount.0 > 0
+                    && payment.refunded.0 + command.amount.0 <= payment.captured.0
+                {
+                    vec![RestaurantEvent::PaymentRefunded(PaymentRefunded {
+                        identifier: command.identifier.to_owned(),
+                        order_identifier: command.order_identifier.to_owned(),
+                        amount: command.amount.to_owned(),
+                        r#final: false,
+                    })]
+                } else {
+                    error!("Failed to refund the payment. The amount exceeds the captured amount of the order!");
+                }
+            }
         }),
         // Evolve the state based on the current state and the event
         // Exhaustive pattern matching on the event
@@ -67,18 +477,198 @@ pub fn restaurant_decider<'a>() -> RestaurantDecider<'a> {
                 identifier: event.identifier.to_owned(),
                 name: event.name.to_owned(),
                 menu: event.menu.to_owned(),
+                menu_version: RestaurantMenuVersion(1),
+                payments: vec![],
+                max_concurrent_orders: None,
+                open_orders: vec![],
+                unavailable_items: vec![],
+                opening_hours: None,
+                promotions: vec![],
             }),
 
+            // The versions are assigned by the decider in the stream order, so the legacy (unversioned) events are versioned the same way
             RestaurantEvent::MenuChanged(event) => state.clone().map(|s| Restaurant {
                 identifier: event.identifier.to_owned(),
-                name: s.name,
                 menu: event.menu.to_owned(),
+                menu_version: RestaurantMenuVersion(s.menu_version.0 + 1),
+                ..s
+            }),
+
+            RestaurantEvent::OrderPlaced(event) => state.clone().map(|s| {
+                let mut payments = s.payments;
+                payments.push(OrderPayment {
+                    order_identifier: event.order_identifier.to_owned(),
+                    total: discounted_total(&event.line_items, &event.discounts),
+                    captured: Money(0),
+                    refunded: Money(0),
+                });
+                let mut open_orders = s.open_orders;
+                open_orders.push(event.order_identifier.to_owned());
+                Restaurant {
+                    identifier: event.identifier.to_owned(),
+                    payments,
+                    open_orders,
+                    ..s
+                }
+            }),
+
+            RestaurantEvent::OrderLineItemsModified(event) => state.clone().map(|s| Restaurant {
+                identifier: event.identifier.to_owned(),
+                payments: update_payment(s.payments, &event.order_identifier, |payment| {
+                    OrderPayment {
+                        total: discounted_total(&event.line_items, &event.discounts),
+                        ..payment
+                    }
+                }),
+                ..s
+            }),
+
+            RestaurantEvent::PaymentCaptured(event) => state.clone().map(|s| Restaurant {
+                identifier: event.identifier.to_owned(),
+                payments: update_payment(s.payments, &event.order_identifier, |payment| {
+                    OrderPayment {
+                        captured: Money(payment.captured.0 + event.amount.0),
+                        ..payment
+                    }
+                }),
+                ..s
+            }),
+
+            RestaurantEvent::PaymentRefunded(event) => state.clone().map(|s| Restaurant {
+                identifier: event.identifier.to_owned(),
+                payments: update_payment(s.payments, &event.order_identifier, |payment| {
+                    OrderPayment {
+                        refunded: Money(payment.refunded.0 + event.amount.0),
+                        ..payment
+                    }
+                }),
+                ..s
+            }),
+
+            RestaurantEvent::CapacityChanged(event) => state.clone().map(|s| Restaurant {
+                identifier: event.identifier.to_owned(),
+                max_concurrent_orders: event.max_concurrent_orders,
+                ..s
+            }),
+
+            RestaurantEvent::OrderClosed(event) => state.clone().map(|s| Restaurant {
+                identifier: event.identifier.to_owned(),
+                open_orders: s
+                    .open_orders
+                    .into_iter()
+                    .filter(|order| *order != event.order_identifier)
+                    .collect(),
+                ..s
+            }),
+
+            // The sub orders are placed by the saga
+            RestaurantEvent::OrderSplit(..) => state.clone(),
+
+            RestaurantEvent::OrderCancelled(event) => state.clone().map(|s| Restaurant {
+                identifier: event.identifier.to_owned(),
+                open_orders: s
+                    .open_orders
+                    .into_iter()
+                    .filter(|order| *order != event.order_identifier)
+                    .collect(),
+                ..s
+            }),
+
+            RestaurantEvent::MenuItemMarkedUnavailable(event) => state.clone().map(|s| {
+                let mut unavailable_items = s.unavailable_items;
+                if !unavailable_items.contains(&event.menu_item_id) {
+                    unavailable_items.push(event.menu_item_id.to_owned());
+                }
+                Restaurant {
+                    identifier: event.identifier.to_owned(),
+                    unavailable_items,
+                    ..s
+                }
+            }),
+
+            RestaurantEvent::MenuItemMarkedAvailable(event) => state.clone().map(|s| Restaurant {
+                identifier: event.identifier.to_owned(),
+                unavailable_items: s
+                    .unavailable_items
+                    .into_iter()
+                    .filter(|item| *item != event.menu_item_id)
+                    .collect(),
+                ..s
             }),
 
-            RestaurantEvent::OrderPlaced(event) => state.clone().map(|s| Restaurant {
+            RestaurantEvent::OpeningHoursChanged(event) => state.clone().map(|s| Restaurant {
                 identifier: event.identifier.to_owned(),
-                name: s.name,
-                menu: s.menu,
+                opening_hours: event.opening_hours.to_owned(),
+                ..s
+            }),
+
+            RestaurantEvent::PromotionApplied(event) => state.clone().map(|s| {
+                let mut promotions: Vec<RestaurantPromotion> = s
+                    .promotions
+                    .into_iter()
+                    .filter(|promotion| promotion.id != event.promotion.id)
+                    .collect();
+                promotions.push(event.promotion.to_owned());
+                Restaurant {
+                    identifier: event.identifier.to_owned(),
+                    promotions,
+                    ..s
+                }
+            }),
+
+            RestaurantEvent::PromotionWithdrawn(event) => state.clone().map(|s| Restaurant {
+                identifier: event.identifier.to_owned(),
+                promotions: s
+                    .promotions
+                    .into_iter()
+                    .filter(|promotion| promotion.id != event.promotion_id)
+                    .collect(),
+                ..s
+            }),
+
+            RestaurantEvent::OrdersMigrated(event) => state.clone().map(|s| {
+                let migrated = |order_identifier: &OrderId| {
+                    event
+                        .orders
+                        .iter()
+                        .any(|order| order.order_identifier == *order_identifier)
+                };
+                Restaurant {
+                    identifier: event.identifier.to_owned(),
+                    payments: s
+                        .payments
+                        .into_iter()
+                        .filter(|payment| !migrated(&payment.order_identifier))
+                        .collect(),
+                    open_orders: s
+                        .open_orders
+                        .into_iter()
+                        .filter(|order| !migrated(order))
+                        .collect(),
+                    ..s
+                }
+            }),
+
+            RestaurantEvent::OrdersReceived(event) => state.clone().map(|s| {
+                let mut payments = s.payments;
+                let mut open_orders = s.open_orders;
+                for order in &event.orders {
+                    payments.push(OrderPayment {
+                        order_identifier: order.order_identifier.to_owned(),
+                        total: order.total.to_owned(),
+                        captured: order.captured.to_owned(),
+                        refunded: order.refunded.to_owned(),
+                    });
+                    if order.open {
+                        open_orders.push(order.order_identifier.to_owned());
+                    }
+                }
+                Restaurant {
+                    identifier: event.identifier.to_owned(),
+                    payments,
+                    open_orders,
+                    ..s
+                }
             }),
         }),
 
@@ -86,3 +676,99 @@ pub fn restaurant_decider<'a>() -> RestaurantDecider<'a> {
         initial_state: Box::new(|| None),
     }
 }
+
+impl Restaurant {
+    /// Is the menu item on the current menu of the restaurant?
+    fn is_on_menu(&self, menu_item_id: &MenuItemId) -> bool {
+        self.menu.items.iter().any(|item| item.id == *menu_item_id)
+    }
+
+    /// The order (and its payments) migrated to another restaurant.
+    fn to_migrated_order(&self, payment: &OrderPayment) -> MigratedOrder {
+        MigratedOrder {
+            order_identifier: payment.order_identifier.to_owned(),
+            total: payment.total.to_owned(),
+            captured: payment.captured.to_owned(),
+            refunded: payment.refunded.to_owned(),
+            open: self.open_orders.contains(&payment.order_identifier),
+        }
+    }
+
+    /// The discounts of the (priced) line items: every line item is discounted by the best of the current promotions applying to its menu item.
+    fn discounts(&self, line_items: &[OrderLineItem]) -> Vec<AppliedDiscount> {
+        line_items
+            .iter()
+            .filter_map(|line_item| {
+                let price = line_item.price.as_ref()?;
+                let promotion = self
+                    .promotions
+                    .iter()
+                    .filter(|promotion| promotion.applies_to(&line_item.menu_item_id))
+                    .max_by_key(|promotion| promotion.percentage.0)?;
+                let amount = promotion
+                    .percentage
+                    .discount(&Money(price.0 * u64::from(line_item.quantity.0)));
+                Some(AppliedDiscount {
+                    promotion_id: promotion.id.to_owned(),
+                    line_item_id: line_item.id.to_owned(),
+                    amount,
+                })
+            })
+            .filter(|discount| discount.amount.0 > 0)
+            .collect()
+    }
+
+    /// Does any of the line items order the unavailable menu item?
+    fn has_unavailable_items(&self, line_items: &[OrderLineItem]) -> bool {
+        line_items
+            .iter()
+            .any(|line_item| self.unavailable_items.contains(&line_item.menu_item_id))
+    }
+}
+
+/// Updates the payments of the order, leaving the payments of the other orders intact.
+fn update_payment(
+    payments: Vec<OrderPayment>,
+    order_identifier: &OrderId,
+    update: impl Fn(OrderPayment) -> OrderPayment,
+) -> Vec<OrderPayment> {
+    payments
+        .into_iter()
+        .map(|payment| {
+            if payment.order_identifier == *order_identifier {
+                update(payment)
+            } else {
+                payment
+            }
+        })
+        .collect()
+}
+
+/// The line items span multiple restaurants: any of them belongs to the restaurant other than the one the order is placed at.
+fn spans_restaurants(restaurant_identifier: &RestaurantId, line_items: &[OrderLineItem]) -> bool {
+    line_items.iter().any(|line_item| {
+        line_item
+            .restaurant_identifier
+            .as_ref()
+            .is_some_and(|restaurant| restaurant != restaurant_identifier)
+    })
+}
+
+/// Splits the line items per restaurant (in the order of their first line item). The line items without the restaurant belong to the restaurant the order is placed at.
+fn split_line_items(
+    restaurant_identifier: &RestaurantId,
+    line_items: &[OrderLineItem],
+) -> Vec<(RestaurantId, Vec<OrderLineItem>)> {
+    let mut orders: Vec<(RestaurantId, Vec<OrderLineItem>)> = Vec::new();
+    for line_item in line_items {
+        let restaurant = line_item
+            .restaurant_identifier
+            .as_ref()
+            .unwrap_or(restaurant_identifier);
+        match orders.iter_mut().find(|(r, _)| r == restaurant) {
+            Some((_, items)) => items.push(line_item.to_owned()),
+            None => orders.push((restaurant.to_owned(), vec![line_item.to_owned()])),
+        }
+    }
+    orders
+}
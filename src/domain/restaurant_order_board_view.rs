@@ -0,0 +1,138 @@
+use fmodel_rust::view::View;
+use fmodel_rust::Sum;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::api::{
+    OrderEvent, OrderId, OrderStatus, RestaurantEvent, RestaurantId, RestaurantName,
+};
+
+/// The order on the board of the restaurant.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct RestaurantOrderBoardEntry {
+    pub order_identifier: OrderId,
+    pub status: OrderStatus,
+}
+
+/// The state of the Restaurant Order Board View is represented by this struct. It belongs to the Domain layer.
+/// It is a cross-domain view: the restaurant (`RestaurantEvent`) joined with the status of its orders (`OrderEvent`).
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct RestaurantOrderBoardState {
+    pub identifier: RestaurantId,
+    pub name: RestaurantName,
+    pub orders: Vec<RestaurantOrderBoardEntry>,
+}
+
+/// A convenient type alias for the Restaurant Order Board view, consuming the events of both deciders
+pub type RestaurantOrderBoardView<'a> =
+    View<'a, Option<RestaurantOrderBoardState>, Sum<RestaurantEvent, OrderEvent>>;
+
+/// View represents the event handling algorithm. It belongs to the Domain layer.
+pub fn restaurant_order_board_view<'a>() -> RestaurantOrderBoardView<'a> {
+    View {
+        // Evolve the state based on the current state and the event
+        // Exhaustive pattern matching on the events of both deciders
+        evolve: Box::new(|state, event| match event {
+            Sum::First(RestaurantEvent::Created(event)) => Some(RestaurantOrderBoardState {
+                identifier: event.identifier.to_owned(),
+                name: event.name.to_owned(),
+                orders: vec![],
+            }),
+
+            // The order is on the board once it is created (by the saga)
+            Sum::First(RestaurantEvent::MenuChanged(_))
+            | Sum::First(RestaurantEvent::OrderPlaced(_))
+            | Sum::First(RestaurantEvent::OrderLineItemsModified(_))
+            | Sum::First(RestaurantEvent::PaymentCaptured(_))
+            | Sum::First(RestaurantEvent::PaymentRefunded(_))
+            | Sum::First(RestaurantEvent::CapacityChanged(_))
+            | Sum::First(RestaurantEvent::OrderClosed(_))
+            | Sum::First(RestaurantEvent::OpeningHoursChanged(_))
+            | Sum::First(RestaurantEvent::MenuItemMarkedUnavailable(_))
+            | Sum::First(RestaurantEvent::MenuItemMarkedAvailable(_))
+            | Sum::First(RestaurantEvent::OrdersReceived(_))
+            | Sum::First(RestaurantEvent::PromotionApplied(_))
+            | Sum::First(RestaurantEvent::PromotionWithdrawn(_))
+            | Sum::First(RestaurantEvent::OrderCancelled(_))
+            | Sum::First(RestaurantEvent::OrderSplit(_))
+            | Sum::Second(OrderEvent::LineItemsUpdated(_)) => state.clone(),
+
+            // The open orders move to the board of the restaurant they were migrated to (`OrderMigrated`); the closed ones stay on the board, as prepared
+            Sum::First(RestaurantEvent::OrdersMigrated(event)) => {
+                state.clone().map(|s| RestaurantOrderBoardState {
+                    orders: s
+                        .orders
+                        .into_iter()
+                        .filter(|entry| {
+                            !event.orders.iter().any(|order| {
+                                order.open && order.order_identifier == entry.order_identifier
+                            })
+                        })
+                        .collect(),
+                    ..s
+                })
+            }
+
+            Sum::Second(OrderEvent::Migrated(event)) => state.clone().map(|s| {
+                let mut orders = s.orders;
+                orders.push(RestaurantOrderBoardEntry {
+                    order_identifier: event.identifier.to_owned(),
+                    status: event.status.to_owned(),
+                });
+                RestaurantOrderBoardState { orders, ..s }
+            }),
+
+            Sum::Second(OrderEvent::Created(event)) => state.clone().map(|s| {
+                let mut orders = s.orders;
+                orders.push(RestaurantOrderBoardEntry {
+                    order_identifier: event.identifier.to_owned(),
+                    status: event.status.to_owned(),
+                });
+                RestaurantOrderBoardState { orders, ..s }
+            }),
+
+            Sum::Second(OrderEvent::Prepared(event)) => {
+                state.clone().map(|s| RestaurantOrderBoardState {
+                    orders: s
+                        .orders
+                        .into_iter()
+                        .map(|order| {
+                            if order.order_identifier == event.identifier {
+                                RestaurantOrderBoardEntry {
+                                    status: event.status.to_owned(),
+                                    ..order
+                                }
+                            } else {
+                                order
+                            }
+                        })
+                        .collect(),
+                    ..s
+                })
+            }
+
+            // The cancelled (or rejected) orders stay on the board, with their status
+            Sum::Second(OrderEvent::Cancelled(event)) => {
+                state.clone().map(|s| RestaurantOrderBoardState {
+                    orders: s
+                        .orders
+                        .into_iter()
+                        .map(|order| {
+                            if order.order_identifier == event.identifier {
+                                RestaurantOrderBoardEntry {
+                                    status: event.status.to_owned(),
+                                    ..order
+                                }
+                            } else {
+                                order
+                            }
+                        })
+                        .collect(),
+                    ..s
+                })
+            }
+        }),
+
+        // The initial state of the decider
+        initial_state: Box::new(|| None),
+    }
+}
@@ -0,0 +1,40 @@
+use fmodel_rust::saga::Saga;
+
+use crate::domain::api::{
+    ApplyRestaurantPromotion, PromotionEvent, RestaurantCommand, RestaurantPromotion,
+    WithdrawRestaurantPromotion,
+};
+
+/// A convenient type alias for the Restaurant promotion choreography saga
+type RestaurantPromotionSaga<'a> = Saga<'a, PromotionEvent, RestaurantCommand>;
+
+/// The Restaurant promotion choreography saga - represents the central point of control deciding what to execute next.
+/// It is a function that takes an event and returns a list of commands.
+/// The promotions are applied to (and withdrawn from) the restaurant, so the restaurant decider discounts the orders by the current promotions when they are placed.
+pub fn restaurant_promotion_saga<'a>() -> RestaurantPromotionSaga<'a> {
+    Saga {
+        react: Box::new(|event| match event {
+            PromotionEvent::Created(event) => {
+                vec![RestaurantCommand::ApplyPromotion(
+                    ApplyRestaurantPromotion {
+                        identifier: event.restaurant_identifier.to_owned(),
+                        promotion: RestaurantPromotion {
+                            id: event.identifier.to_owned(),
+                            name: event.name.to_owned(),
+                            percentage: event.percentage,
+                            menu_item_ids: event.menu_item_ids.to_owned(),
+                        },
+                    },
+                )]
+            }
+            PromotionEvent::Expired(event) => {
+                vec![RestaurantCommand::WithdrawPromotion(
+                    WithdrawRestaurantPromotion {
+                        identifier: event.restaurant_identifier.to_owned(),
+                        promotion_id: event.identifier.to_owned(),
+                    },
+                )]
+            }
+        }),
+    }
+}
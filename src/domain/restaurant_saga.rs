@@ -1,20 +1,37 @@
 use fmodel_rust::saga::Saga;
 
-use crate::domain::api::{OrderEvent, RestaurantCommand};
+use crate::domain::api::{CloseRestaurantOrder, OrderEvent, RestaurantCommand};
 
 /// A convenient type alias for the Restaurant choreography saga
 type RestaurantSaga<'a> = Saga<'a, OrderEvent, RestaurantCommand>;
 
 /// The Restaurant choreography saga - represents the central point of control deciding what to execute next.
 /// It is a function that takes an event and returns a list of commands.
-/// This Saga is not doing much ;)
+/// Once the order is prepared, it is closed at the restaurant, releasing the capacity of the restaurant.
 pub fn restaurant_saga<'a>() -> RestaurantSaga<'a> {
     Saga {
         react: Box::new(|_event| match _event {
             OrderEvent::Created(..) => {
                 vec![]
             }
-            OrderEvent::Prepared(..) => {
+            // The orders prepared before the restaurant was recorded in the event can not be closed
+            OrderEvent::Prepared(event) => match &event.restaurant_identifier {
+                Some(restaurant_identifier) => {
+                    vec![RestaurantCommand::CloseOrder(CloseRestaurantOrder {
+                        identifier: restaurant_identifier.to_owned(),
+                        order_identifier: event.identifier.to_owned(),
+                    })]
+                }
+                None => vec![],
+            },
+            OrderEvent::LineItemsUpdated(..) => {
+                vec![]
+            }
+            OrderEvent::Migrated(..) => {
+                vec![]
+            }
+            // The order is cancelled at the restaurant first: the open order is already released
+            OrderEvent::Cancelled(..) => {
                 vec![]
             }
         }),
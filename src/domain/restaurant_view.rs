@@ -2,7 +2,10 @@ use fmodel_rust::view::View;
 use pgrx::PostgresType;
 use serde::{Deserialize, Serialize};
 
-use crate::domain::api::{RestaurantEvent, RestaurantId, RestaurantMenu, RestaurantName};
+use crate::domain::api::{
+    MenuItemId, OpeningHours, RestaurantEvent, RestaurantId, RestaurantMenu, RestaurantName,
+    RestaurantPromotion,
+};
 
 /// The state of the Restaurant View is represented by this struct. It belongs to the Domain layer.
 #[derive(PostgresType, Clone, PartialEq, Debug, Serialize, Deserialize)]
@@ -10,6 +13,18 @@ pub struct RestaurantViewState {
     pub identifier: RestaurantId,
     pub name: RestaurantName,
     pub menu: RestaurantMenu,
+    /// The menu items that are unavailable (86'd). The documents projected before the availability was introduced default to none
+    #[serde(default)]
+    pub unavailable_items: Vec<MenuItemId>,
+    /// The opening hours, or `None` if the restaurant is always open
+    #[serde(default)]
+    pub opening_hours: Option<OpeningHours>,
+    /// The restaurant this (retired) restaurant was merged into, or `None`
+    #[serde(default)]
+    pub merged_into: Option<RestaurantId>,
+    /// The current promotions of the restaurant. The documents projected before the promotions were introduced default to none
+    #[serde(default)]
+    pub promotions: Vec<RestaurantPromotion>,
 }
 
 /// A convenient type alias for the Restaurant view
@@ -25,19 +40,131 @@ pub fn restaurant_view<'a>() -> RestaurantView<'a> {
                 identifier: event.identifier.to_owned(),
                 name: event.name.to_owned(),
                 menu: event.menu.to_owned(),
+                unavailable_items: vec![],
+                opening_hours: None,
+                merged_into: None,
+                promotions: vec![],
             }),
 
             RestaurantEvent::MenuChanged(event) => state.clone().map(|s| RestaurantViewState {
                 identifier: event.identifier.to_owned(),
-                name: s.name,
                 menu: event.menu.to_owned(),
+                ..s
             }),
 
             RestaurantEvent::OrderPlaced(event) => state.clone().map(|s| RestaurantViewState {
                 identifier: event.identifier.to_owned(),
-                name: s.name,
-                menu: s.menu,
+                ..s
             }),
+
+            RestaurantEvent::OrderLineItemsModified(event) => {
+                state.clone().map(|s| RestaurantViewState {
+                    identifier: event.identifier.to_owned(),
+                    ..s
+                })
+            }
+
+            RestaurantEvent::PaymentCaptured(event) => state.clone().map(|s| RestaurantViewState {
+                identifier: event.identifier.to_owned(),
+                ..s
+            }),
+
+            RestaurantEvent::PaymentRefunded(event) => state.clone().map(|s| RestaurantViewState {
+                identifier: event.identifier.to_owned(),
+                ..s
+            }),
+
+            RestaurantEvent::CapacityChanged(event) => state.clone().map(|s| RestaurantViewState {
+                identifier: event.identifier.to_owned(),
+                ..s
+            }),
+
+            RestaurantEvent::OrderClosed(event) => state.clone().map(|s| RestaurantViewState {
+                identifier: event.identifier.to_owned(),
+                ..s
+            }),
+
+            RestaurantEvent::OrderCancelled(event) => state.clone().map(|s| RestaurantViewState {
+                identifier: event.identifier.to_owned(),
+                ..s
+            }),
+
+            RestaurantEvent::OrderSplit(event) => state.clone().map(|s| RestaurantViewState {
+                identifier: event.identifier.to_owned(),
+                ..s
+            }),
+            RestaurantEvent::MenuItemMarkedUnavailable(event) => state.clone().map(|s| {
+                let mut unavailable_items = s.unavailable_items;
+                if !unavailable_items.contains(&event.menu_item_id) {
+                    unavailable_items.push(event.menu_item_id.to_owned());
+                }
+                RestaurantViewState {
+                    identifier: event.identifier.to_owned(),
+                    unavailable_items,
+                    ..s
+                }
+            }),
+
+            RestaurantEvent::MenuItemMarkedAvailable(event) => {
+                state.clone().map(|s| RestaurantViewState {
+                    identifier: event.identifier.to_owned(),
+                    unavailable_items: s
+                        .unavailable_items
+                        .into_iter()
+                        .filter(|item| *item != event.menu_item_id)
+                        .collect(),
+                    ..s
+                })
+            }
+
+            RestaurantEvent::OpeningHoursChanged(event) => {
+                state.clone().map(|s| RestaurantViewState {
+                    identifier: event.identifier.to_owned(),
+                    opening_hours: event.opening_hours.to_owned(),
+                    ..s
+                })
+            }
+
+            RestaurantEvent::OrdersMigrated(event) => state.clone().map(|s| RestaurantViewState {
+                identifier: event.identifier.to_owned(),
+                merged_into: if event.r#final {
+                    Some(event.target_identifier.to_owned())
+                } else {
+                    s.merged_into
+                },
+                ..s
+            }),
+
+            RestaurantEvent::OrdersReceived(event) => state.clone().map(|s| RestaurantViewState {
+                identifier: event.identifier.to_owned(),
+                ..s
+            }),
+
+            RestaurantEvent::PromotionApplied(event) => state.clone().map(|s| {
+                let mut promotions: Vec<RestaurantPromotion> = s
+                    .promotions
+                    .into_iter()
+                    .filter(|promotion| promotion.id != event.promotion.id)
+                    .collect();
+                promotions.push(event.promotion.to_owned());
+                RestaurantViewState {
+                    identifier: event.identifier.to_owned(),
+                    promotions,
+                    ..s
+                }
+            }),
+
+            RestaurantEvent::PromotionWithdrawn(event) => {
+                state.clone().map(|s| RestaurantViewState {
+                    identifier: event.identifier.to_owned(),
+                    promotions: s
+                        .promotions
+                        .into_iter()
+                        .filter(|promotion| promotion.id != event.promotion_id)
+                        .collect(),
+                    ..s
+                })
+            }
         }),
 
         // The initial state of the decider
@@ -0,0 +1,241 @@
+use crate::domain::api::{
+    CapturePayment, ChangeRestaurantMenu, CreateOrder, CreatePromotion, CreateRestaurant,
+    DiscountPercentage, MarkOrderAsPrepared, MenuId, MenuItem, MenuItemId, MenuItemName, Money,
+    OrderCreated, OrderId, OrderLineItem, OrderLineItemId, OrderLineItemQuantity, OrderPlaced,
+    OrderPrepared, OrderStatus, PaymentCaptured, PlaceOrder, PromotionId, PromotionName,
+    RestaurantCapacityChanged, RestaurantCreated, RestaurantId, RestaurantMenu,
+    RestaurantMenuChanged, RestaurantMenuCuisine, RestaurantMenuVersion, RestaurantName,
+};
+use crate::domain::{Command, Event};
+use crate::framework::domain::specification::{DeciderSpecification, Then};
+use uuid::Uuid;
+
+/// The specifications of the combined (order&restaurant) decider, verified by `spec_report` in the deployed extension.
+/// Given the events of the stream, when the command is decided, then the events (or the error) are expected.
+pub fn order_restaurant_specifications() -> Vec<DeciderSpecification<Command, Event>> {
+    vec![
+        DeciderSpecification {
+            name: "Restaurant is created",
+            given: vec![],
+            when: Command::CreateRestaurant(CreateRestaurant {
+                identifier: restaurant_identifier(),
+                name: RestaurantName("Pljeska".to_string()),
+                menu: menu(),
+            }),
+            then: Then::Events(vec![restaurant_created()]),
+        },
+        DeciderSpecification {
+            name: "Restaurant is not created twice",
+            given: vec![restaurant_created()],
+            when: Command::CreateRestaurant(CreateRestaurant {
+                identifier: restaurant_identifier(),
+                name: RestaurantName("Pljeska".to_string()),
+                menu: menu(),
+            }),
+            then: Then::Error("Failed to create the Restaurant. Restaurant already exists!"),
+        },
+        DeciderSpecification {
+            name: "Menu is changed, the next version of the menu",
+            given: vec![restaurant_created()],
+            when: Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
+                identifier: restaurant_identifier(),
+                menu: menu(),
+            }),
+            then: Then::Events(vec![Event::RestaurantMenuChanged(RestaurantMenuChanged {
+                identifier: restaurant_identifier(),
+                menu: menu(),
+                menu_version: RestaurantMenuVersion(2),
+                r#final: false,
+            })]),
+        },
+        DeciderSpecification {
+            name: "Order is placed, priced from the menu",
+            given: vec![restaurant_created()],
+            when: Command::PlaceOrder(PlaceOrder {
+                identifier: restaurant_identifier(),
+                order_identifier: order_identifier(),
+                line_items: line_items(None),
+                group_identifier: None,
+                customer_identifier: None,
+            }),
+            then: Then::Events(vec![order_placed()]),
+        },
+        DeciderSpecification {
+            name: "Order is not placed at the restaurant at capacity",
+            given: vec![
+                restaurant_created(),
+                Event::RestaurantCapacityChanged(RestaurantCapacityChanged {
+                    identifier: restaurant_identifier(),
+                    max_concurrent_orders: Some(1),
+                    r#final: false,
+                }),
+                order_placed(),
+            ],
+            when: Command::PlaceOrder(PlaceOrder {
+                identifier: restaurant_identifier(),
+                order_identifier: OrderId(
+                    Uuid::parse_str("5e0c8b1a-3f2d-4c6e-9a7b-8d1e2f3a4b5c").unwrap(),
+                ),
+                line_items: line_items(None),
+                group_identifier: None,
+                customer_identifier: None,
+            }),
+            then: Then::Error("Failed to place the order. Restaurant is at capacity!"),
+        },
+        DeciderSpecification {
+            name: "Payment is captured, up to the total of the order",
+            given: vec![restaurant_created(), order_placed()],
+            when: Command::CapturePayment(CapturePayment {
+                identifier: restaurant_identifier(),
+                order_identifier: order_identifier(),
+                amount: Money(1000),
+            }),
+            then: Then::Events(vec![Event::PaymentCaptured(PaymentCaptured {
+                identifier: restaurant_identifier(),
+                order_identifier: order_identifier(),
+                amount: Money(1000),
+                r#final: false,
+            })]),
+        },
+        DeciderSpecification {
+            name: "Payment is not captured over the total of the order",
+            given: vec![restaurant_created(), order_placed()],
+            when: Command::CapturePayment(CapturePayment {
+                identifier: restaurant_identifier(),
+                order_identifier: order_identifier(),
+                amount: Money(1001),
+            }),
+            then: Then::Error(
+                "Failed to capture the payment. The amount exceeds the unpaid total of the order!",
+            ),
+        },
+        DeciderSpecification {
+            name: "Order is created",
+            given: vec![],
+            when: Command::CreateOrder(CreateOrder {
+                identifier: order_identifier(),
+                restaurant_identifier: restaurant_identifier(),
+                line_items: line_items(Some(Money(500))),
+                currency: None,
+                discounts: vec![],
+                group_identifier: None,
+                customer_identifier: None,
+            }),
+            then: Then::Events(vec![order_created()]),
+        },
+        DeciderSpecification {
+            name: "Order is prepared, and its stream is final",
+            given: vec![order_created()],
+            when: Command::MarkOrderAsPrepared(MarkOrderAsPrepared {
+                identifier: order_identifier(),
+            }),
+            then: Then::Events(vec![order_prepared()]),
+        },
+        DeciderSpecification {
+            name: "Order is not prepared twice",
+            given: vec![order_created(), order_prepared()],
+            when: Command::MarkOrderAsPrepared(MarkOrderAsPrepared {
+                identifier: order_identifier(),
+            }),
+            then: Then::Error("Failed to mark the order as prepared. Order does not exist or is not in the correct state!"),
+        },
+        DeciderSpecification {
+            name: "Promotion is not created with the discount over 100 percent",
+            given: vec![],
+            when: Command::CreatePromotion(CreatePromotion {
+                identifier: PromotionId(
+                    Uuid::parse_str("d4e5f6a7-b8c9-4d0e-9f1a-3b4c5d6e7f8a").unwrap(),
+                ),
+                restaurant_identifier: restaurant_identifier(),
+                name: PromotionName("Everything must go".to_string()),
+                percentage: DiscountPercentage(101),
+                menu_item_ids: vec![],
+            }),
+            then: Then::Error(
+                "Failed to create the Promotion. The discount must be between 1 and 100 percent!",
+            ),
+        },
+    ]
+}
+
+fn restaurant_identifier() -> RestaurantId {
+    RestaurantId(Uuid::parse_str("8c2a7d4e-1b3f-4a5c-9e6d-7f8a9b0c1d2e").unwrap())
+}
+
+fn order_identifier() -> OrderId {
+    OrderId(Uuid::parse_str("9d3b8e5f-2c4a-4b6d-8f7e-0a1b2c3d4e5f").unwrap())
+}
+
+fn menu_item_id() -> MenuItemId {
+    MenuItemId(Uuid::parse_str("a1b2c3d4-e5f6-4a7b-8c9d-0e1f2a3b4c5d").unwrap())
+}
+
+fn menu() -> RestaurantMenu {
+    RestaurantMenu {
+        menu_id: MenuId(Uuid::parse_str("b2c3d4e5-f6a7-4b8c-9d0e-1f2a3b4c5d6e").unwrap()),
+        items: vec![MenuItem {
+            id: menu_item_id(),
+            name: MenuItemName("Pljeskavica".to_string()),
+            price: Money(500),
+        }],
+        cuisine: RestaurantMenuCuisine::Other,
+        currency: None,
+    }
+}
+
+/// Two portions of the menu item (the total is `1000`, if priced).
+fn line_items(price: Option<Money>) -> Vec<OrderLineItem> {
+    vec![OrderLineItem {
+        id: OrderLineItemId(Uuid::parse_str("c3d4e5f6-a7b8-4c9d-8e0f-2a3b4c5d6e7f").unwrap()),
+        quantity: OrderLineItemQuantity(2),
+        menu_item_id: menu_item_id(),
+        name: MenuItemName("Pljeskavica".to_string()),
+        price,
+        restaurant_identifier: None,
+    }]
+}
+
+fn restaurant_created() -> Event {
+    Event::RestaurantCreated(RestaurantCreated {
+        identifier: restaurant_identifier(),
+        name: RestaurantName("Pljeska".to_string()),
+        menu: menu(),
+        r#final: false,
+    })
+}
+
+fn order_placed() -> Event {
+    Event::OrderPlaced(OrderPlaced {
+        identifier: restaurant_identifier(),
+        order_identifier: order_identifier(),
+        line_items: line_items(Some(Money(500))),
+        currency: None,
+        discounts: vec![],
+        group_identifier: None,
+        customer_identifier: None,
+        r#final: false,
+    })
+}
+
+fn order_created() -> Event {
+    Event::OrderCreated(OrderCreated {
+        identifier: order_identifier(),
+        restaurant_identifier: restaurant_identifier(),
+        status: OrderStatus::Created,
+        line_items: line_items(Some(Money(500))),
+        reference_total: None,
+        discounts: vec![],
+        group_identifier: None,
+        customer_identifier: None,
+        r#final: false,
+    })
+}
+
+fn order_prepared() -> Event {
+    Event::OrderPrepared(OrderPrepared {
+        identifier: order_identifier(),
+        restaurant_identifier: Some(restaurant_identifier()),
+        status: OrderStatus::Prepared,
+        r#final: true,
+    })
+}
@@ -0,0 +1,31 @@
+use crate::domain::api::{OrderId, RestaurantId, StockItem};
+
+/// The stock levels of the restaurants, injected into the deciders that take the stock (e.g. the orders, rejected when the stock of the ordered menu items is insufficient).
+/// The deciders stay pure: the stock is an input of the decision, and the tests can fix it.
+pub trait StockLevels: Send + Sync {
+    /// The stock available to the order at the restaurant (the stock on hand, and the stock already reserved by the order), or `None` if the restaurant does not track its stock (it has no inventory).
+    /// The failure of the lookup itself is raised (`error!`), like the rejection of the decider.
+    fn available(
+        &self,
+        restaurant_identifier: &RestaurantId,
+        order_identifier: &OrderId,
+    ) -> Option<Vec<StockItem>>;
+}
+
+/// The fixed stock levels, by the restaurant (the reservations of the orders are not tracked).
+pub struct FixedStockLevels {
+    pub stock: Vec<(RestaurantId, Vec<StockItem>)>,
+}
+
+impl StockLevels for FixedStockLevels {
+    fn available(
+        &self,
+        restaurant_identifier: &RestaurantId,
+        _order_identifier: &OrderId,
+    ) -> Option<Vec<StockItem>> {
+        self.stock
+            .iter()
+            .find(|(restaurant, _)| restaurant == restaurant_identifier)
+            .map(|(_, items)| items.to_owned())
+    }
+}
@@ -0,0 +1,49 @@
+use crate::framework::infrastructure::aggregating_view_repository::AggregatingViewRepository;
+use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::framework::infrastructure::event_repository::EventPosition;
+use std::marker::PhantomData;
+
+/// The fold of the event into the bucket and the increment of its counters, or `None` if the event is not counted.
+pub type Aggregate<'a, E, K, V> = Box<dyn Fn(&E) -> Option<(K, V)> + 'a>;
+
+/// Aggregating View.
+///
+/// It is folding the events into the (additive) counters of the buckets, instead of the entity documents, to build the analytic read models (e.g. the orders per restaurant per day).
+/// The counters are incremented in place (no state is fetched), so the concurrent events of the same bucket do not conflict.
+/// Unlike the [MaterializedView](crate::framework::application::materialized_view::MaterializedView), the increments are not idempotent: the projection relies on the trigger/projector (checkpoint) to apply every event exactly once, and it is rebuilt from scratch.
+///
+/// Generic parameters:
+///
+/// - `E` - Event
+/// - `K` - Bucket (key)
+/// - `V` - Increment of the counters
+/// - `Repository` - Aggregating view repository
+pub struct AggregatingView<'a, E, K, V, Repository>
+where
+    Repository: AggregatingViewRepository<K, V>,
+{
+    repository: Repository,
+    aggregate: Aggregate<'a, E, K, V>,
+    _marker: PhantomData<(E, K, V)>,
+}
+
+impl<'a, E, K, V, Repository> AggregatingView<'a, E, K, V, Repository>
+where
+    Repository: AggregatingViewRepository<K, V>,
+{
+    /// Creates a new instance of [AggregatingView].
+    pub fn new(repository: Repository, aggregate: Aggregate<'a, E, K, V>) -> Self {
+        AggregatingView {
+            repository,
+            aggregate,
+            _marker: PhantomData,
+        }
+    }
+    /// Handles the event by folding it into the bucket, and incrementing the counters of the bucket in the repository.
+    pub fn handle(&self, event: &E, position: &EventPosition) -> Result<(), ErrorMessage> {
+        match (self.aggregate)(event) {
+            None => Ok(()),
+            Some((bucket, increment)) => self.repository.increment(&bucket, &increment, position),
+        }
+    }
+}
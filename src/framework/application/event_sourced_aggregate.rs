@@ -2,17 +2,35 @@
 // ###################### Regular Aggregate ##########################
 // ###################################################################
 
-use crate::framework::domain::api::{DeciderType, EventType, Identifier, IsFinal};
+use crate::framework::application::hooks::HookRegistry;
+use crate::framework::application::limits::{check_produced_events, check_replayed_events};
+use crate::framework::application::trace::CommandTrace;
+use crate::framework::application::validators::ValidatorRegistry;
+use crate::framework::application::workflow::record_workflow_step;
+use crate::framework::domain::api::{CommandType, DeciderType, EventType, Identifier, IsFinal};
+use crate::framework::domain::workflow::{WorkflowCommand, WorkflowStep};
+use crate::framework::infrastructure::command_stats;
 use crate::framework::infrastructure::errors::ErrorMessage;
 use crate::framework::infrastructure::event_repository::{
-    EventOrchestratingRepository, EventRepository,
+    command_id, EventOrchestratingRepository, EventPosition, EventRepository,
 };
+use crate::framework::infrastructure::frozen_deciders::check_not_frozen;
+use crate::framework::infrastructure::saga_commands::{
+    save_saga_command, update_saga_command_status, SagaCommandStatus,
+};
+use crate::framework::infrastructure::saga_trace::save_saga_trace;
+use crate::framework::infrastructure::state_cache;
 use fmodel_rust::decider::{Decider, EventComputation};
 use fmodel_rust::saga::Saga;
+use json_patch::Patch;
+use pgrx::pg_sys::panic::CaughtError;
+use pgrx::{error, warning, PgTryBuilder};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::fmt::Debug;
 use std::marker::PhantomData;
+use std::panic::AssertUnwindSafe;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
 /// Event sourced aggregate is composed of a repository and a decider.
@@ -22,8 +40,8 @@ pub struct EventSourcedAggregate<C, S, E, Repository, Decider>
 where
     Repository: EventRepository<C, E>,
     Decider: EventComputation<C, S, E>,
-    C: Identifier,
-    E: EventType + Identifier + IsFinal + DeciderType + DeserializeOwned + Serialize,
+    C: Identifier + CommandType + DeciderType,
+    E: Clone + EventType + Identifier + IsFinal + DeciderType + DeserializeOwned + Serialize,
 {
     repository: Repository,
     decider: Decider,
@@ -36,8 +54,8 @@ impl<C, S, E, Repository, Decider> EventComputation<C, S, E>
 where
     Repository: EventRepository<C, E>,
     Decider: EventComputation<C, S, E>,
-    C: Identifier,
-    E: EventType + Identifier + IsFinal + DeciderType + DeserializeOwned + Serialize,
+    C: Identifier + CommandType + DeciderType,
+    E: Clone + EventType + Identifier + IsFinal + DeciderType + DeserializeOwned + Serialize,
 {
     /// Computes new events based on the current events and the command.
     fn compute_new_events(&self, current_events: &[E], command: &C) -> Vec<E> {
@@ -49,8 +67,8 @@ impl<C, S, E, Repository, Decider> EventSourcedAggregate<C, S, E, Repository, De
 where
     Repository: EventRepository<C, E>,
     Decider: EventComputation<C, S, E>,
-    C: Identifier,
-    E: EventType + Identifier + IsFinal + DeciderType + DeserializeOwned + Serialize,
+    C: Identifier + CommandType + DeciderType,
+    E: Clone + EventType + Identifier + IsFinal + DeciderType + DeserializeOwned + Serialize,
 {
     /// Creates a new event sourced aggregate.
     #[allow(dead_code)]
@@ -63,7 +81,8 @@ where
     }
     /// Handles the command and returns the new events.
     #[allow(dead_code)]
-    pub fn handle(&self, command: &C) -> Result<Vec<(E, Uuid)>, ErrorMessage> {
+    pub fn handle(&self, command: &C) -> Result<Vec<(E, EventPosition)>, ErrorMessage> {
+        let started = Instant::now();
         let events: Vec<(E, Uuid)> = self.repository.fetch_events(command)?;
         let mut version: Option<Uuid> = None;
         let mut current_events: Vec<E> = vec![];
@@ -71,8 +90,22 @@ where
             version = Some(ver);
             current_events.push(event);
         }
+        let fetched = Instant::now();
         let new_events = self.compute_new_events(&current_events, command);
-        self.repository.save(&new_events, &version)
+        let decided = Instant::now();
+        let saved_events = self.repository.save(&new_events, &version);
+        CommandTrace {
+            span: "handle",
+            command_type: command.command_type(),
+            decider_id: command.identifier().to_string(),
+            fetched_events: current_events.len(),
+            produced_events: new_events.len(),
+            fetch: fetched - started,
+            decide: decided - fetched,
+            save: decided.elapsed(),
+        }
+        .report();
+        saved_events
     }
 }
 
@@ -85,7 +118,7 @@ where
 pub struct EventSourcedOrchestratingAggregate<'a, C, S, E, Repository>
 where
     Repository: EventOrchestratingRepository<C, E>,
-    C: Identifier,
+    C: Identifier + CommandType + DeciderType + Serialize,
     E: Clone
         + EventType
         + Identifier
@@ -98,15 +131,24 @@ where
     repository: Repository,
     decider: Decider<'a, C, S, E>,
     saga: Saga<'a, E, C>,
+    hooks: HookRegistry<E>,
+    validators: ValidatorRegistry<E>,
+    state_codec: Option<StateCodec<S>>,
     _marker: PhantomData<(C, S, E)>,
 }
 
+/// The (de)serialization of the decider state, for the shared-memory state cache (see `with_state_cache`).
+struct StateCodec<S> {
+    serialize: fn(&S) -> Option<Vec<u8>>,
+    deserialize: fn(&[u8]) -> Option<S>,
+}
+
 /// Implementation of the event computation for the event sourced orchestrating aggregate.
 impl<'a, C, S, E, Repository> EventComputation<C, S, E>
     for EventSourcedOrchestratingAggregate<'a, C, S, E, Repository>
 where
     Repository: EventOrchestratingRepository<C, E>,
-    C: Identifier,
+    C: Identifier + CommandType + DeciderType + Serialize,
     E: Clone
         + EventType
         + Identifier
@@ -117,49 +159,149 @@ where
         + Debug,
 {
     fn compute_new_events(&self, current_events: &[E], command: &C) -> Vec<E> {
-        let current_state: S = current_events
-            .iter()
-            .fold((self.decider.initial_state)(), |state, event| {
-                (self.decider.evolve)(&state, event)
-            });
+        self.compute_new_events_at_depth(current_events, command, 0)
+    }
+}
 
-        // Initial resulting events from the decider's decision.
-        let initial_events = (self.decider.decide)(command, &current_state);
+impl<'a, C, S, E, Repository> EventSourcedOrchestratingAggregate<'a, C, S, E, Repository>
+where
+    Repository: EventOrchestratingRepository<C, E>,
+    C: Identifier + CommandType + DeciderType + Serialize,
+    E: Clone
+        + EventType
+        + Identifier
+        + IsFinal
+        + DeciderType
+        + DeserializeOwned
+        + Serialize
+        + Debug,
+{
+    /// Computes new events based on the current events and the command, at the depth of the saga orchestration (`0` for the top-level command).
+    /// Every saga reaction is traced (see `save_saga_trace`), and every command issued by the saga is recorded in the ledger (see `handle_saga_command`).
+    fn compute_new_events_at_depth(&self, current_events: &[E], command: &C, depth: i32) -> Vec<E> {
+        let current_state: S = self.fold(current_events.iter());
+        self.compute_new_events_from_state(&current_state, command, depth)
+    }
 
-        // Commands to process derived from initial resulting events.
-        let commands_to_process: Vec<C> = initial_events
-            .iter()
-            .flat_map(|event| (self.saga.react)(event))
-            .collect();
+    /// Folds the events into the state, starting from the initial state of the decider.
+    fn fold<'e>(&self, events: impl Iterator<Item = &'e E>) -> S
+    where
+        E: 'e,
+    {
+        events.fold((self.decider.initial_state)(), |state, event| {
+            (self.decider.evolve)(&state, event)
+        })
+    }
+
+    /// Computes new events based on the current state and the command, at the depth of the saga orchestration.
+    fn compute_new_events_from_state(&self, current_state: &S, command: &C, depth: i32) -> Vec<E> {
+        // Initial resulting events from the decider's decision.
+        let initial_events = (self.decider.decide)(command, current_state);
 
         // Collect all events including recursively computed new events.
         let mut all_events = initial_events.clone(); // Start with initial events.
 
-        for command in commands_to_process.iter() {
-            let previous_events = [
-                self.repository
-                    .fetch_events(command)
-                    .unwrap_or_default()
-                    .iter()
-                    .map(|(e, _)| e.clone())
-                    .collect::<Vec<E>>(),
-                initial_events.clone(),
-            ]
-            .concat();
+        for event in initial_events.iter() {
+            // Commands to process derived from the resulting event.
+            let commands_to_process: Vec<C> = (self.saga.react)(event);
+            if commands_to_process.is_empty() {
+                continue;
+            }
+            let mut reaction_events = Vec::new();
+            for command in commands_to_process.iter() {
+                let previous_events = [
+                    self.repository
+                        .fetch_events(command)
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|(e, _)| e.clone())
+                        .collect::<Vec<E>>(),
+                    initial_events.clone(),
+                ]
+                .concat();
 
-            // Recursively compute new events and extend the accumulated events list.
-            let new_events = self.compute_new_events(&previous_events, command);
-            all_events.extend(new_events);
+                // Recursively compute new events and extend the accumulated events list.
+                let new_events = self.handle_saga_command(&previous_events, event, command, depth);
+                reaction_events.extend(new_events);
+            }
+            // Tracing is best-effort: it does not fail the command handling
+            if let Err(err) = save_saga_trace(depth, event, &commands_to_process, &reaction_events)
+            {
+                warning!("fmodel: {}", err.message);
+            }
+            all_events.extend(reaction_events);
         }
 
         all_events
     }
+
+    /// Computes the new events of the command issued by the saga (in reaction to the event), recording the command in the `saga_commands` ledger, and as the step of the workflow (see `workflow_status`).
+    /// The rejection of the saga command (the decider error) does not fail the originating command: the command is recorded as `failed`, so the stuck orchestration can be detected (`pending_saga_work`) and re-driven.
+    /// If the command can not be recorded, the rejection fails the originating command, so the intent is never lost.
+    fn handle_saga_command(
+        &self,
+        previous_events: &[E],
+        event: &E,
+        command: &C,
+        depth: i32,
+    ) -> Vec<E> {
+        let id = match save_saga_command(depth, event, command) {
+            Ok(id) => id,
+            Err(err) => {
+                warning!("fmodel: {}", err.message);
+                return self.compute_new_events_at_depth(previous_events, command, depth + 1);
+            }
+        };
+        record_workflow_step(|identifier| WorkflowCommand::IssueCommand {
+            identifier,
+            step: WorkflowStep {
+                saga_command_id: id,
+                depth,
+                event_type: event.event_type(),
+                command_type: command.command_type(),
+                decider_id: command.identifier(),
+            },
+        });
+        // Only the errors raised by the decider (Rust) are caught; the Postgres errors abort the transaction
+        // The command of the frozen decider type is rejected as well (see `freeze_decider`), so it can be re-driven once the decider type is unfrozen
+        let new_events = PgTryBuilder::new(AssertUnwindSafe(|| {
+            if let Err(err) = check_not_frozen(&command.decider_type(), &command.command_type()) {
+                error!("{}", err);
+            }
+            Ok(self.compute_new_events_at_depth(previous_events, command, depth + 1))
+        }))
+        .catch_others(|err| match err {
+            CaughtError::ErrorReport(report) => Err(report.message().to_string()),
+            err => err.rethrow(),
+        })
+        .execute();
+        let result = match &new_events {
+            Ok(_) => update_saga_command_status(id, SagaCommandStatus::Succeeded, None),
+            Err(error) => {
+                warning!(
+                    "fmodel: the saga command {} failed (see `pending_saga_work`): {}",
+                    command.command_type(),
+                    error
+                );
+                update_saga_command_status(id, SagaCommandStatus::Failed, Some(error))
+            }
+        };
+        if let Err(err) = result {
+            warning!("fmodel: {}", err.message);
+        }
+        record_workflow_step(|identifier| WorkflowCommand::CompleteCommand {
+            identifier,
+            saga_command_id: id,
+            error: new_events.as_ref().err().cloned(),
+        });
+        new_events.unwrap_or_default()
+    }
 }
 
 impl<'a, C, S, E, Repository> EventSourcedOrchestratingAggregate<'a, C, S, E, Repository>
 where
     Repository: EventOrchestratingRepository<C, E>,
-    C: Identifier,
+    C: Identifier + CommandType + DeciderType + Serialize,
     E: Clone
         + EventType
         + Identifier
@@ -179,35 +321,366 @@ where
             repository,
             decider,
             saga,
+            hooks: HookRegistry::new(),
+            validators: ValidatorRegistry::new(),
+            state_codec: None,
             _marker: PhantomData,
         }
     }
-    /// Handles the command and returns the new events that are persisted.
-    pub fn handle(&self, command: &C) -> Result<Vec<(E, Uuid)>, ErrorMessage> {
-        let events: Vec<E> = self
+
+    /// Sets the hooks invoked after the new events are successfully saved.
+    pub fn with_hooks(mut self, hooks: HookRegistry<E>) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Sets the validators run before the new events are saved.
+    pub fn with_validators(mut self, validators: ValidatorRegistry<E>) -> Self {
+        self.validators = validators;
+        self
+    }
+
+    /// Caches the states of the decider streams in the shared memory (if enabled by `fmodel.state_cache`), so the commands skip the replay of the hot streams.
+    /// The state is cached at the version (the id of the last event) of the stream: the append moves the head of the stream, invalidating the cached state.
+    pub fn with_state_cache(mut self) -> Self
+    where
+        S: Serialize + DeserializeOwned,
+    {
+        self.state_codec = Some(StateCodec {
+            serialize: |state| serde_json::to_vec(state).ok(),
+            deserialize: |bytes| serde_json::from_slice(bytes).ok(),
+        });
+        self
+    }
+
+    /// Folds the state of the decider from the events of its stream, up to (and including) the offset.
+    pub fn state_at(&self, decider_id: &Uuid, offset: i64) -> Result<S, ErrorMessage> {
+        Ok(self
             .repository
-            .fetch_events(command)?
+            .fetch_stream_events_until(decider_id, offset)?
+            .iter()
+            .fold((self.decider.initial_state)(), |state, (event, _)| {
+                (self.decider.evolve)(&state, event)
+            }))
+    }
+
+    /// Returns the JSON diff (RFC 6902 patch) of the decider state between the two offsets.
+    /// The states are folded at each bound by replaying the events through the decider.
+    pub fn state_diff(
+        &self,
+        decider_id: &Uuid,
+        from_offset: i64,
+        to_offset: i64,
+    ) -> Result<Patch, ErrorMessage>
+    where
+        S: Serialize,
+    {
+        let to_json = |state: S| {
+            serde_json::to_value(state).map_err(|err| ErrorMessage {
+                message: "Failed to serialize the state: ".to_string() + &err.to_string(),
+                context: None,
+            })
+        };
+        let from = to_json(self.state_at(decider_id, from_offset)?)?;
+        let to = to_json(self.state_at(decider_id, to_offset)?)?;
+        Ok(json_patch::diff(&from, &to))
+    }
+
+    /// Handles the command and returns the new events that are persisted.
+    pub fn handle(&self, command: &C) -> Result<Vec<(E, EventPosition)>, ErrorMessage> {
+        self.handle_checked(command, None)
+    }
+
+    /// Handles the command and returns the ids of the new events that are persisted (lightweight mode).
+    pub fn handle_ids(&self, command: &C) -> Result<Vec<Uuid>, ErrorMessage> {
+        Ok(self
+            .handle(command)?
             .into_iter()
-            .map(|(e, _)| e)
-            .collect();
-        let new_events = self.compute_new_events(&events, command);
-        self.repository.save(&new_events)
+            .map(|(_, position)| position.event_id)
+            .collect())
+    }
+
+    /// Handles the command and returns the new events that are persisted, if the event stream of the command is at the expected version.
+    /// The version is the sequence number of the last event in the stream (`0` for the new stream).
+    pub fn handle_with_expected_version(
+        &self,
+        command: &C,
+        expected_version: i64,
+    ) -> Result<Vec<(E, EventPosition)>, ErrorMessage> {
+        self.handle_checked(command, Some(expected_version))
+    }
+
+    /// Handles the command, and records the time of handling it and the number of the produced events in the command statistics (see `command_stats`).
+    fn handle_checked(
+        &self,
+        command: &C,
+        expected_version: Option<i64>,
+    ) -> Result<Vec<(E, EventPosition)>, ErrorMessage> {
+        let started = Instant::now();
+        // The command rejected by the decider (`error!`) is recorded as failed, and the error is rethrown
+        let handled = PgTryBuilder::new(AssertUnwindSafe(|| {
+            self.handle_versioned(command, expected_version)
+        }))
+        .catch_others(|err| {
+            command_stats::record(&command.command_type(), started.elapsed(), None);
+            err.rethrow()
+        })
+        .execute();
+        command_stats::record(
+            &command.command_type(),
+            started.elapsed(),
+            handled.as_ref().ok().map(Vec::len),
+        );
+        handled
+    }
+
+    /// Handles the command.
+    /// If `expected_version` is set, the version of the command stream is checked against the sequence numbers of the saved events (before the hooks are run).
+    fn handle_versioned(
+        &self,
+        command: &C,
+        expected_version: Option<i64>,
+    ) -> Result<Vec<(E, EventPosition)>, ErrorMessage> {
+        let started = Instant::now();
+        let (state, version, replayed_events) = self.fetch_state(command)?;
+        let fetched = Instant::now();
+        let new_events = self.compute_new_events_from_state(&state, command, 0);
+        check_produced_events(command, new_events.len())?;
+        self.validators.validate(&new_events)?;
+        let decided = Instant::now();
+        let command_id = command_id(command)?;
+        let saved_events = self.repository.save_commanded(
+            &new_events
+                .iter()
+                .map(|event| (event.clone(), Some(command_id)))
+                .collect::<Vec<_>>(),
+        );
+        let saved_events = match (saved_events, expected_version) {
+            (Ok(saved), Some(expected_version)) => self
+                .check_version(command, &saved, expected_version)
+                .map(|_| saved),
+            (saved_events, _) => saved_events,
+        };
+        CommandTrace {
+            span: "handle",
+            command_type: command.command_type(),
+            decider_id: command.identifier().to_string(),
+            fetched_events: replayed_events,
+            produced_events: new_events.len(),
+            fetch: fetched - started,
+            decide: decided - fetched,
+            save: decided.elapsed(),
+        }
+        .report();
+        if let Ok(saved) = &saved_events {
+            self.cache_state(command, state, version, saved);
+            self.hooks.run(saved);
+        }
+        saved_events
+    }
+
+    /// Fetches the current state of the command stream, together with the version of the stream and the number of the replayed events.
+    /// The state cached at the head of the stream is used, if any (see `with_state_cache`); otherwise the events of the stream are replayed.
+    fn fetch_state(&self, command: &C) -> Result<(S, Option<Uuid>, usize), ErrorMessage> {
+        let decider = command.decider_type();
+        let decider_id = command.identifier();
+        if let Some(codec) = self
+            .state_codec
+            .as_ref()
+            .filter(|_| state_cache::is_enabled())
+        {
+            if let Some(version) = self
+                .repository
+                .fetch_decider_stream_version(&decider, &decider_id)?
+            {
+                if let Some(state) = state_cache::get(&decider, &decider_id, &version)
+                    .and_then(|state| (codec.deserialize)(&state))
+                {
+                    return Ok((state, Some(version), 0));
+                }
+            }
+        }
+        let events = self.repository.fetch_events(command)?;
+        check_replayed_events(command, events.len())?;
+        let version = events.last().map(|(_, version)| *version);
+        Ok((
+            self.fold(events.iter().map(|(event, _)| event)),
+            version,
+            events.len(),
+        ))
+    }
+
+    /// Caches the state of the command stream after the new events are saved: the saved events of the stream are folded into the state, at the version of the last of them.
+    fn cache_state(
+        &self,
+        command: &C,
+        state: S,
+        version: Option<Uuid>,
+        saved: &[(E, EventPosition)],
+    ) {
+        let Some(codec) = self
+            .state_codec
+            .as_ref()
+            .filter(|_| state_cache::is_enabled())
+        else {
+            return;
+        };
+        let decider = command.decider_type();
+        let decider_id = command.identifier();
+        let (state, version) = saved
+            .iter()
+            .filter(|(event, _)| {
+                event.identifier() == decider_id && event.decider_type() == decider
+            })
+            .fold((state, version), |(state, _), (event, position)| {
+                (
+                    (self.decider.evolve)(&state, event),
+                    Some(position.event_id),
+                )
+            });
+        if let (Some(version), Some(state)) = (version, (codec.serialize)(&state)) {
+            state_cache::put(&decider, &decider_id, &version, &state);
+        }
+    }
+
+    /// Checks that the event stream of the command was at the expected version, before the new events were saved.
+    /// The sequence numbers are assigned to the saved events by the database, so the check also covers the events appended concurrently.
+    fn check_version(
+        &self,
+        command: &C,
+        saved: &[(E, EventPosition)],
+        expected_version: i64,
+    ) -> Result<(), ErrorMessage> {
+        let version = match saved
+            .iter()
+            .find(|(event, _)| event.identifier() == command.identifier())
+        {
+            Some((_, position)) => position.sequence_number - 1,
+            None => self
+                .repository
+                .fetch_stream_sequence_number(&command.identifier())?
+                .unwrap_or(0),
+        };
+        if version != expected_version {
+            return Err(ErrorMessage {
+                message: "Optimistic locking failure: the event stream `".to_string()
+                    + &command.identifier().to_string()
+                    + "` is at version `"
+                    + &version.to_string()
+                    + "`, expected version `"
+                    + &expected_version.to_string()
+                    + "`",
+                context: None,
+            });
+        }
+        Ok(())
     }
 
     /// Handles the list of commands and returns the new events that are persisted.
     /// This method is useful for processing multiple commands in a single transaction.
     /// Effects/Events of the previous commands are visible to the subsequent commands.
-    pub fn handle_all(&self, commands: &[C]) -> Result<Vec<(E, Uuid)>, ErrorMessage> {
+    pub fn handle_all(&self, commands: &[C]) -> Result<Vec<(E, EventPosition)>, ErrorMessage> {
+        self.handle_all_checked(commands, false)
+    }
+
+    /// Handles the list of commands and returns the ids of the new events that are persisted (lightweight mode).
+    pub fn handle_all_ids(&self, commands: &[C]) -> Result<Vec<Uuid>, ErrorMessage> {
+        Ok(self
+            .handle_all(commands)?
+            .into_iter()
+            .map(|(_, position)| position.event_id)
+            .collect())
+    }
+
+    /// Handles the list of commands, addressed to (possibly) different deciders, atomically.
+    /// The new events are persisted only if none of the event streams (of the commands) has changed since its events were fetched (optimistic locking per stream).
+    /// Returns the new events that are persisted, grouped by the decider (stream) identifier, in the order of the first appearance.
+    pub fn handle_all_atomically(
+        &self,
+        commands: &[C],
+    ) -> Result<Vec<(Uuid, Vec<(E, EventPosition)>)>, ErrorMessage> {
+        let mut grouped: Vec<(Uuid, Vec<(E, EventPosition)>)> = Vec::new();
+        for (event, position) in self.handle_all_checked(commands, true)? {
+            let decider_id = event.identifier();
+            match grouped.iter_mut().find(|(id, _)| *id == decider_id) {
+                Some((_, events)) => events.push((event, position)),
+                None => grouped.push((decider_id, vec![(event, position)])),
+            }
+        }
+        Ok(grouped)
+    }
+
+    /// Handles the list of commands, and records the time of handling them and the numbers of the produced events in the command statistics (see `command_stats`).
+    /// The time of handling the list is shared evenly by its commands.
+    fn handle_all_checked(
+        &self,
+        commands: &[C],
+        check_versions: bool,
+    ) -> Result<Vec<(E, EventPosition)>, ErrorMessage> {
+        let started = Instant::now();
+        let mut produced: Vec<usize> = Vec::new();
+        // The command rejected by the decider (`error!`) is recorded as failed, and the error is rethrown
+        let handled = PgTryBuilder::new(AssertUnwindSafe(|| {
+            self.handle_all_versioned(commands, check_versions, &mut produced)
+        }))
+        .catch_others(|err| {
+            record_all(commands, started.elapsed(), None);
+            err.rethrow()
+        })
+        .execute();
+        record_all(
+            commands,
+            started.elapsed(),
+            handled.as_ref().ok().map(|_| produced.as_slice()),
+        );
+        handled
+    }
+
+    /// Handles the list of commands in a single transaction. The numbers of the events produced by the commands are pushed to `produced`.
+    /// If `check_versions` is set, the command streams are locked (see `lock_stream`) before they are fetched, and their versions are checked right before the new events are saved, and once more by the sequence numbers of the saved events.
+    fn handle_all_versioned(
+        &self,
+        commands: &[C],
+        check_versions: bool,
+        produced: &mut Vec<usize>,
+    ) -> Result<Vec<(E, EventPosition)>, ErrorMessage> {
         let mut all_new_events: Vec<E> = Vec::new();
+        let mut command_ids: Vec<Option<Uuid>> = Vec::new();
+        let mut versions: Vec<(Uuid, Option<Uuid>)> = Vec::new();
+        let mut sequence_numbers: Vec<(Uuid, i64)> = Vec::new();
+        let mut fetched_events_count = 0;
+        let mut fetch = Duration::ZERO;
+        let mut decide = Duration::ZERO;
+
+        // Lock the streams in a fixed order, so the concurrent atomic handlers do not deadlock
+        if check_versions {
+            let mut decider_ids: Vec<Uuid> = commands.iter().map(|c| c.identifier()).collect();
+            decider_ids.sort();
+            decider_ids.dedup();
+            for decider_id in &decider_ids {
+                self.repository.lock_stream(decider_id)?;
+            }
+        }
 
         for command in commands {
             // Fetch events for the current command
-            let fetched_events: Vec<E> = self
-                .repository
-                .fetch_events(command)?
+            let started = Instant::now();
+            let fetched_events_with_versions = self.repository.fetch_events(command)?;
+            // Remember the version of the stream as it was first fetched
+            if !versions.iter().any(|(id, _)| *id == command.identifier()) {
+                versions.push((
+                    command.identifier(),
+                    fetched_events_with_versions.last().map(|(_, v)| *v),
+                ));
+            }
+            let fetched_events: Vec<E> = fetched_events_with_versions
                 .into_iter()
                 .map(|(e, _)| e)
                 .collect();
+            check_replayed_events(command, fetched_events.len())?;
+            fetched_events_count += fetched_events.len();
+            let fetched = Instant::now();
+            fetch += fetched - started;
 
             // Combine all previous new events with fetched events for the current command
             let combined_events: Vec<E> = fetched_events
@@ -217,12 +690,104 @@ where
 
             // Compute new events based on the combined events and the current command
             let new_events = self.compute_new_events(&combined_events, command);
+            check_produced_events(command, new_events.len())?;
+            decide += fetched.elapsed();
 
-            // Accumulate all new events
+            // Accumulate all new events, with the id of the command they were decided by
+            let id = command_id(command)?;
+            command_ids.extend(new_events.iter().map(|_| Some(id)));
+            produced.push(new_events.len());
             all_new_events.extend(new_events);
         }
 
-        // Save all new events at the end
-        self.repository.save(&all_new_events)
+        // Check that none of the streams has changed in the meantime
+        if check_versions {
+            for (decider_id, version) in &versions {
+                let current_version = self.repository.fetch_stream_version(decider_id)?;
+                if current_version != *version {
+                    return Err(ErrorMessage {
+                        message: "Optimistic locking failure: the event stream `".to_string()
+                            + &decider_id.to_string()
+                            + "` has changed concurrently (expected version `"
+                            + &version.map(|v| v.to_string()).unwrap_or_default()
+                            + "`, found `"
+                            + &current_version.map(|v| v.to_string()).unwrap_or_default()
+                            + "`)",
+                        context: None,
+                    });
+                }
+                sequence_numbers.push((
+                    *decider_id,
+                    self.repository
+                        .fetch_stream_sequence_number(decider_id)?
+                        .unwrap_or(0),
+                ));
+            }
+        }
+
+        // Validate and save all new events at the end
+        self.validators.validate(&all_new_events)?;
+        let started = Instant::now();
+        let saved_events = self.repository.save_commanded(
+            &all_new_events
+                .iter()
+                .cloned()
+                .zip(command_ids)
+                .collect::<Vec<_>>(),
+        );
+        CommandTrace {
+            span: "handle_all",
+            command_type: commands
+                .iter()
+                .map(|c| c.command_type())
+                .collect::<Vec<_>>()
+                .join(","),
+            decider_id: commands
+                .iter()
+                .map(|c| c.identifier().to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+            fetched_events: fetched_events_count,
+            produced_events: all_new_events.len(),
+            fetch,
+            decide,
+            save: started.elapsed(),
+        }
+        .report();
+        let saved_events = saved_events?;
+        // The events appended concurrently by the handlers that do not lock the stream (e.g. `handle`) are caught by the sequence numbers assigned on save
+        for (decider_id, sequence_number) in &sequence_numbers {
+            if let Some((_, position)) = saved_events
+                .iter()
+                .find(|(event, _)| event.identifier() == *decider_id)
+            {
+                if position.sequence_number - 1 != *sequence_number {
+                    return Err(ErrorMessage {
+                        message: "Optimistic locking failure: the event stream `".to_string()
+                            + &decider_id.to_string()
+                            + "` has changed concurrently (expected version `"
+                            + &sequence_number.to_string()
+                            + "`, found `"
+                            + &(position.sequence_number - 1).to_string()
+                            + "`)",
+                        context: None,
+                    });
+                }
+            }
+        }
+        self.hooks.run(&saved_events);
+        Ok(saved_events)
+    }
+}
+
+/// Records the handled commands in the command statistics: the time of handling them is shared evenly, and `produced` are the numbers of the events produced by the commands (`None` if the commands failed).
+fn record_all<C: CommandType>(commands: &[C], elapsed: Duration, produced: Option<&[usize]>) {
+    let elapsed = elapsed / commands.len().max(1) as u32;
+    for (index, command) in commands.iter().enumerate() {
+        command_stats::record(
+            &command.command_type(),
+            elapsed,
+            produced.map(|produced| produced.get(index).copied().unwrap_or(0)),
+        );
     }
 }
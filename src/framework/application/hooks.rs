@@ -0,0 +1,40 @@
+use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::framework::infrastructure::event_repository::EventPosition;
+use pgrx::warning;
+
+/// A hook / callback invoked with the events that were successfully saved.
+pub type EventHook<E> = fn(&[(E, EventPosition)]) -> Result<(), ErrorMessage>;
+
+/// A registry of the hooks invoked after the events are successfully saved, within the same backend and transaction (e.g. `NOTIFY`, metrics, outbox rows).
+///
+/// - The hooks are invoked in the order they were registered.
+/// - The hooks are isolated from each other and from the command handling: the error reported by a hook is logged as a warning, and the rest of the hooks are invoked.
+///   A hook must not raise Postgres errors (e.g. by failing SQL), as they abort the whole transaction.
+pub struct HookRegistry<E> {
+    hooks: Vec<(&'static str, EventHook<E>)>,
+}
+
+impl<E> HookRegistry<E> {
+    /// Creates a new, empty, hook registry.
+    pub fn new() -> Self {
+        HookRegistry { hooks: Vec::new() }
+    }
+
+    /// Registers the named hook, to be invoked after the previously registered hooks.
+    pub fn register(mut self, name: &'static str, hook: EventHook<E>) -> Self {
+        self.hooks.push((name, hook));
+        self
+    }
+
+    /// Invokes all the hooks with the saved events. Nothing is invoked if there are no saved events.
+    pub fn run(&self, events: &[(E, EventPosition)]) {
+        if events.is_empty() {
+            return;
+        }
+        for (name, hook) in &self.hooks {
+            if let Err(err) = hook(events) {
+                warning!("fmodel hook `{}` failed: {}", name, err);
+            }
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use crate::framework::domain::api::CommandType;
+use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::framework::infrastructure::guc::{MAX_EVENTS_PER_COMMAND, MAX_REPLAYED_EVENTS};
+
+// Cost guards of the command handling, protecting against the pathological commands (e.g. the saga fan-out) bloating the transaction.
+// The command handling is aborted, before the events are saved, if a limit is exceeded. `0` disables the limit.
+
+/// Fails if the decider and the saga produced more events than `fmodel.max_events_per_command`.
+pub fn check_produced_events<C: CommandType>(
+    command: &C,
+    produced: usize,
+) -> Result<(), ErrorMessage> {
+    check_limit(
+        command,
+        produced,
+        MAX_EVENTS_PER_COMMAND.get(),
+        "produced",
+        "fmodel.max_events_per_command",
+    )
+}
+
+/// Fails if more events than `fmodel.max_replayed_events` were fetched (replayed) to handle the command.
+pub fn check_replayed_events<C: CommandType>(
+    command: &C,
+    replayed: usize,
+) -> Result<(), ErrorMessage> {
+    check_limit(
+        command,
+        replayed,
+        MAX_REPLAYED_EVENTS.get(),
+        "replayed",
+        "fmodel.max_replayed_events",
+    )
+}
+
+fn check_limit<C: CommandType>(
+    command: &C,
+    count: usize,
+    limit: i32,
+    action: &str,
+    guc: &str,
+) -> Result<(), ErrorMessage> {
+    if limit > 0 && count > limit as usize {
+        return Err(ErrorMessage {
+            message: "Command limit exceeded: the command ".to_string()
+                + &command.command_type()
+                + " "
+                + action
+                + " "
+                + &count.to_string()
+                + " events, more than `"
+                + guc
+                + "` ("
+                + &limit.to_string()
+                + ")",
+            context: None,
+        });
+    }
+    Ok(())
+}
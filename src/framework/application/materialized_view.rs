@@ -1,12 +1,19 @@
 use crate::framework::infrastructure::errors::ErrorMessage;
-use crate::framework::infrastructure::view_state_repository::ViewStateRepository;
+use crate::framework::infrastructure::event_repository::EventPosition;
+use crate::framework::infrastructure::guc::{OutOfOrderEvents, OUT_OF_ORDER_EVENTS};
+use crate::framework::infrastructure::view_state_repository::{is_applied, ViewStateRepository};
 use fmodel_rust::view::ViewStateComputation;
+use pgrx::debug1;
 use std::marker::PhantomData;
 
+/// The prefix of the error message of the event applied out of order (`fmodel.out_of_order_events = reject`).
+pub const OUT_OF_ORDER_EVENT_ERROR: &str = "Failed to apply the event out of order";
+
 /// Materialized View.
 ///
 /// It is using a `View` / [ViewStateComputation] to compute new state based on the current state and the event.
 /// It is using a [ViewStateRepository] to fetch the current state and to save the new state.
+/// The event can be the sum of the events of multiple deciders (e.g. `Sum<RestaurantEvent, OrderEvent>`), for the cross-domain views: the repository resolves the view row of every event type.
 ///
 /// Generic parameters:
 ///
@@ -51,9 +58,42 @@ where
         }
     }
     /// Handles the event by fetching the state from the repository, computing new state based on the current state and the event, and saving the new state to the repository.
-    pub fn handle(&self, event: &E) -> Result<S, ErrorMessage> {
+    /// The position of the event is stored alongside the state, so clients can check if the view has caught up with their writes.
+    /// The events that are already applied (at or before the stored position in the commit order, see [is_applied], e.g. replayed by the projector, or retried out of order) are skipped, so the event is never applied twice and the view never regresses.
+    /// The handling of such events is configurable with `fmodel.out_of_order_events` (`skip`, `apply` or `reject`).
+    /// The existing state is saved by patching it with the changes of the event (see [ViewStateRepository::save_patch]).
+    pub fn handle(&self, event: &E, position: &EventPosition) -> Result<S, ErrorMessage> {
         let state = self.repository.fetch_state(event)?;
-        let new_state = self.compute_new_state(state, &[event]);
-        self.repository.save(&new_state)
+        let out_of_order = match self.repository.fetch_applied_offset(event)? {
+            Some(applied) if is_applied(applied, position.offset)? => Some(applied),
+            _ => None,
+        };
+        match (state, out_of_order, OUT_OF_ORDER_EVENTS.get()) {
+            (Some(state), Some(applied), OutOfOrderEvents::Skip) => {
+                debug1!(
+                    "fmodel: skipping the event at offset {}, the view is at offset {}",
+                    position.offset,
+                    applied
+                );
+                Ok(state)
+            }
+            (Some(_), Some(applied), OutOfOrderEvents::Reject) => Err(ErrorMessage {
+                message: OUT_OF_ORDER_EVENT_ERROR.to_string()
+                    + ": the event at offset "
+                    + &position.offset.to_string()
+                    + " is at or before the event of the view (at offset "
+                    + &applied.to_string()
+                    + ") in the commit order",
+                context: None,
+            }),
+            (Some(state), _, _) => {
+                let new_state = self.compute_new_state(Some(state), &[event]);
+                self.repository.save_patch(event, &new_state, position)
+            }
+            (None, _, _) => {
+                let new_state = self.compute_new_state(None, &[event]);
+                self.repository.save(&new_state, position)
+            }
+        }
     }
 }
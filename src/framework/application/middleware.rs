@@ -0,0 +1,197 @@
+use crate::framework::domain::api::{CommandType, DeciderType, Identifier};
+use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::framework::infrastructure::frozen_deciders::check_not_frozen;
+use crate::framework::infrastructure::guc::{COMMAND_ROLE, RATE_LIMIT, RATE_LIMIT_BURST};
+use crate::framework::infrastructure::rate_limiter;
+use crate::framework::infrastructure::schema_check::{check_schema_once, ExpectedTable};
+use pgrx::{debug1, IntoDatum, PgBuiltInOids, Spi};
+use std::collections::BTreeMap;
+
+/// The context of the command handling, passed through the middleware chain.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CommandContext {
+    /// The metadata of the command, enriched by the middlewares (e.g. the user handling the command)
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// Command middleware / pre-processor.
+/// It is invoked before the command is handled, and can enrich the metadata in the context, or veto the command by returning an error.
+pub trait Middleware<C> {
+    fn handle(&self, command: &C, context: &mut CommandContext) -> Result<(), ErrorMessage>;
+}
+
+/// An ordered chain of the command middlewares.
+/// The middlewares are invoked in the order they were added; the first error vetoes the command, and the rest of the chain is not invoked.
+pub struct MiddlewareChain<C> {
+    middlewares: Vec<Box<dyn Middleware<C>>>,
+}
+
+impl<C> MiddlewareChain<C> {
+    /// Creates a new, empty, middleware chain.
+    pub fn new() -> Self {
+        MiddlewareChain {
+            middlewares: Vec::new(),
+        }
+    }
+
+    /// Adds the middleware to the end of the chain.
+    pub fn with(mut self, middleware: impl Middleware<C> + 'static) -> Self {
+        self.middlewares.push(Box::new(middleware));
+        self
+    }
+
+    /// Runs the command through the chain, and returns the (enriched) context.
+    pub fn run(&self, command: &C) -> Result<CommandContext, ErrorMessage> {
+        let mut context = CommandContext::default();
+        for middleware in &self.middlewares {
+            middleware.handle(command, &mut context)?;
+        }
+        Ok(context)
+    }
+}
+
+/// Logs the command (type, identifier and metadata) on the `DEBUG1` level.
+pub struct LoggingMiddleware;
+
+impl<C: Identifier + CommandType> Middleware<C> for LoggingMiddleware {
+    fn handle(&self, command: &C, context: &mut CommandContext) -> Result<(), ErrorMessage> {
+        debug1!(
+            "fmodel: handling command {} for {} {:?}",
+            command.command_type(),
+            command.identifier(),
+            context.metadata
+        );
+        Ok(())
+    }
+}
+
+/// Authorizes the command: the current user must be a member of the role configured by `fmodel.command_role` (if any).
+/// The current user is added to the metadata (`user`).
+pub struct AuthorizationMiddleware;
+
+impl<C: CommandType> Middleware<C> for AuthorizationMiddleware {
+    fn handle(&self, command: &C, context: &mut CommandContext) -> Result<(), ErrorMessage> {
+        let user = Spi::get_one::<String>("SELECT current_user::TEXT")
+            .map_err(|err| ErrorMessage::spi("authorize the command", None, &err))?
+            .unwrap_or_default();
+        if let Some(role) = COMMAND_ROLE
+            .get()
+            .and_then(|role| role.to_str().ok().map(str::to_owned))
+        {
+            let authorized = Spi::get_one_with_args::<bool>(
+                "SELECT pg_has_role(current_user, $1, 'MEMBER')",
+                vec![(PgBuiltInOids::TEXTOID.oid(), role.as_str().into_datum())],
+            )
+            .map_err(|err| ErrorMessage::spi("authorize the command", None, &err))?;
+            if authorized != Some(true) {
+                return Err(ErrorMessage {
+                    message: "Not authorized to handle the command ".to_string()
+                        + &command.command_type()
+                        + ": user `"
+                        + &user
+                        + "` is not a member of the role `"
+                        + &role
+                        + "`",
+                    context: None,
+                });
+            }
+        }
+        context.metadata.insert("user".to_string(), user);
+        Ok(())
+    }
+}
+
+/// Checks (once per backend) that the schema matches the one the repositories assume: the expected tables, columns (and their types) and indexes exist.
+/// The command is vetoed with the hint to run `fmodel_migrate()` if the schema was not migrated (e.g. after the upgrade of the extension binary).
+pub struct SchemaCheckMiddleware {
+    pub tables: &'static [ExpectedTable],
+    pub indexes: &'static [&'static str],
+}
+
+impl<C> Middleware<C> for SchemaCheckMiddleware {
+    fn handle(&self, _command: &C, _context: &mut CommandContext) -> Result<(), ErrorMessage> {
+        check_schema_once(self.tables, self.indexes)
+    }
+}
+
+/// Vetoes the command of the frozen decider type (see `freeze_decider`), e.g. during the migration or the incident.
+pub struct FreezeMiddleware;
+
+impl<C: DeciderType + CommandType> Middleware<C> for FreezeMiddleware {
+    fn handle(&self, command: &C, _context: &mut CommandContext) -> Result<(), ErrorMessage> {
+        check_not_frozen(&command.decider_type(), &command.command_type())
+    }
+}
+
+/// Rate limits the commands per decider stream (token bucket in the shared memory), protecting the hot streams from the command storms.
+/// Up to `fmodel.rate_limit` commands per second are allowed, with bursts of up to `fmodel.rate_limit_burst` commands.
+pub struct RateLimitMiddleware;
+
+impl<C: Identifier + DeciderType + CommandType> Middleware<C> for RateLimitMiddleware {
+    fn handle(&self, command: &C, _context: &mut CommandContext) -> Result<(), ErrorMessage> {
+        let rate = RATE_LIMIT.get();
+        if rate <= 0 {
+            return Ok(());
+        }
+        rate_limiter::acquire(
+            &command.decider_type(),
+            &command.identifier(),
+            rate as f64,
+            RATE_LIMIT_BURST.get() as f64,
+        )
+        .map_err(|retry_after| ErrorMessage {
+            message: "Rate limited: the command ".to_string()
+                + &command.command_type()
+                + " exceeds the rate limit of the "
+                + &command.decider_type()
+                + " stream `"
+                + &command.identifier().to_string()
+                + "` ("
+                + &rate.to_string()
+                + " commands per second). Retry after "
+                + &retry_after.as_millis().max(1).to_string()
+                + " ms",
+            context: None,
+        })
+    }
+}
+
+/// Vetoes the command with the idempotency key (`fmodel.idempotency_key`, set by the client) that was already handled.
+/// The idempotency key is recorded in the `command_idempotency_keys` table, in the same transaction in which the command is handled.
+/// All the commands handled in the transaction that recorded the key (e.g. `handle_all`) are allowed.
+pub struct IdempotencyMiddleware;
+
+impl<C: CommandType> Middleware<C> for IdempotencyMiddleware {
+    fn handle(&self, command: &C, context: &mut CommandContext) -> Result<(), ErrorMessage> {
+        let key = Spi::get_one::<String>(
+            "SELECT NULLIF(current_setting('fmodel.idempotency_key', TRUE), '')",
+        )
+        .map_err(|err| ErrorMessage::spi("fetch the idempotency key", None, &err))?;
+        let Some(key) = key else {
+            return Ok(());
+        };
+        let recorded = Spi::get_one_with_args::<bool>(
+            "WITH recorded AS (INSERT INTO command_idempotency_keys (idempotency_key) VALUES ($1) ON CONFLICT DO NOTHING RETURNING 1)
+             SELECT EXISTS(SELECT 1 FROM recorded)
+                 OR EXISTS(SELECT 1 FROM command_idempotency_keys WHERE idempotency_key = $1 AND transaction_id = txid_current())",
+            vec![(PgBuiltInOids::TEXTOID.oid(), key.as_str().into_datum())],
+        )
+        .map_err(|err| ErrorMessage::spi(
+                "record the idempotency key",
+                Some(command.identifier().to_string()),
+                &err,
+            ))?;
+        if recorded != Some(true) {
+            return Err(ErrorMessage {
+                message: "The command ".to_string()
+                    + &command.command_type()
+                    + " with the idempotency key `"
+                    + &key
+                    + "` was already handled",
+                context: None,
+            });
+        }
+        context.metadata.insert("idempotency_key".to_string(), key);
+        Ok(())
+    }
+}
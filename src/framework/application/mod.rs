@@ -1,2 +1,11 @@
+pub mod aggregating_view;
 pub mod event_sourced_aggregate;
+pub mod hooks;
+pub mod limits;
 pub mod materialized_view;
+pub mod middleware;
+pub mod projector;
+pub mod saga_rules;
+pub mod trace;
+pub mod validators;
+pub mod workflow;
@@ -0,0 +1,206 @@
+use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::framework::infrastructure::event_repository::EventPosition;
+use crate::framework::infrastructure::projection_repository::{
+    ProjectionMode, ProjectionRebuild, ProjectionRepository,
+};
+use crate::framework::infrastructure::store::in_store;
+use pgrx::pg_sys;
+use serde::de::DeserializeOwned;
+use std::marker::PhantomData;
+use std::time::{Duration, Instant};
+
+/// The longest sleep between the checks of the projection checkpoint, while awaiting the projection.
+const MAX_AWAIT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Projector / pull-based event handler.
+///
+/// It is reading the events past the checkpoint of the projection, in batches and in the commit order (see [ProjectionRepository::fetch_events]), and applying them to the projection/materialized view.
+/// The checkpoint is the offset of the last applied event.
+/// The checkpoint is moved forward in the same transaction in which the events are applied, so every event is applied exactly once.
+///
+/// Generic parameters:
+///
+/// - `E` - Event
+/// - `Repository` - Projection repository
+pub struct Projector<E, Repository>
+where
+    Repository: ProjectionRepository<E>,
+    E: DeserializeOwned,
+{
+    repository: Repository,
+    _marker: PhantomData<E>,
+}
+
+impl<E, Repository> Projector<E, Repository>
+where
+    Repository: ProjectionRepository<E>,
+    E: DeserializeOwned,
+{
+    /// Creates a new instance of [Projector].
+    pub fn new(repository: Repository) -> Self {
+        Projector {
+            repository,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the mode in which the projection is updated.
+    pub fn mode(&self, projection: &str) -> Result<ProjectionMode, ErrorMessage> {
+        self.repository.fetch_mode(projection)
+    }
+
+    /// Applies the next batch of events past the checkpoint to the projection, and moves the checkpoint forward.
+    /// Returns the number of events applied.
+    pub fn project<H>(
+        &self,
+        projection: &str,
+        batch_size: i64,
+        handler: H,
+    ) -> Result<i64, ErrorMessage>
+    where
+        H: Fn(&E, &EventPosition) -> Result<(), ErrorMessage>,
+    {
+        self.project_batch(projection, batch_size, handler)
+            .map(|(applied, _)| applied)
+    }
+
+    /// Applies the next batch of events past the checkpoint to the projection, and moves the checkpoint past the last fetched event (the skipped events of unknown types included).
+    /// Returns the number of events applied, and the number of events fetched: the projection has caught up with the event store if the batch is not full.
+    fn project_batch<H>(
+        &self,
+        projection: &str,
+        batch_size: i64,
+        handler: H,
+    ) -> Result<(i64, i64), ErrorMessage>
+    where
+        H: Fn(&E, &EventPosition) -> Result<(), ErrorMessage>,
+    {
+        let checkpoint = self.repository.fetch_checkpoint(projection)?;
+        let batch = self.repository.fetch_events(checkpoint, batch_size)?;
+        // The event is applied to the projection rows of its (logical) store
+        for (event, position, store) in &batch.events {
+            in_store(store, || handler(event, position))?;
+        }
+        if let Some(offset) = batch.last_offset {
+            self.repository.save_checkpoint(projection, offset)?;
+        }
+        Ok((batch.events.len() as i64, batch.fetched))
+    }
+
+    /// Waits until the projection has applied the event at the offset (the consistency token returned to the client), or the timeout elapses: the read-your-writes against the `async` projection.
+    /// The checkpoint is checked with the exponential backoff (up to 100 ms between the checks), waiting on the latch of the backend in between (see [wait_latch]). Returns `false` if the timeout elapsed.
+    /// The `sync`/`statement` projections are updated in the transaction that appended the events, so they have applied every committed event already.
+    pub fn await_offset(
+        &self,
+        projection: &str,
+        offset: i64,
+        timeout: Duration,
+    ) -> Result<bool, ErrorMessage> {
+        let started = Instant::now();
+        let mut backoff = Duration::from_millis(1);
+        loop {
+            if self.mode(projection)? != ProjectionMode::Async
+                || self.repository.fetch_applied(projection, offset)?
+            {
+                return Ok(true);
+            }
+            let elapsed = started.elapsed();
+            if elapsed >= timeout {
+                return Ok(false);
+            }
+            wait_latch(backoff.min(timeout - elapsed));
+            backoff = (backoff * 2).min(MAX_AWAIT_BACKOFF);
+        }
+    }
+
+    /// Switches the mode of the projection.
+    ///
+    /// - `sync`/`statement` -> `async`: the trigger has applied all the events of the finished transactions, so the checkpoint is moved to the last of them in the commit order (see [ProjectionRepository::fetch_horizon_offset]).
+    ///   The events of the transactions still in progress are read by the projector, as their triggers may run after the switch; those already applied by the trigger are skipped by the materialized views (see `fmodel.out_of_order_events`).
+    /// - `async` -> `sync`/`statement`: the projection catches up with the event store first, so the trigger can take over from the latest offset.
+    /// - `sync` <-> `statement`: the (row-level or statement-level) trigger takes over immediately.
+    pub fn switch_mode<H>(
+        &self,
+        projection: &str,
+        mode: ProjectionMode,
+        batch_size: i64,
+        handler: H,
+    ) -> Result<(), ErrorMessage>
+    where
+        H: Fn(&E, &EventPosition) -> Result<(), ErrorMessage>,
+    {
+        match (self.mode(projection)?, mode) {
+            (current, target) if current == target => Ok(()),
+            (_, ProjectionMode::Async) => {
+                let horizon_offset = self.repository.fetch_horizon_offset()?;
+                self.repository.save_mode(projection, mode, horizon_offset)
+            }
+            (ProjectionMode::Async, _) => {
+                while self.project_batch(projection, batch_size, &handler)?.1 > 0 {}
+                let checkpoint = self.repository.fetch_checkpoint(projection)?;
+                self.repository.save_mode(projection, mode, checkpoint)
+            }
+            _ => {
+                let checkpoint = self.repository.fetch_checkpoint(projection)?;
+                self.repository.save_mode(projection, mode, checkpoint)
+            }
+        }
+    }
+
+    /// Returns the progress of the unfinished rebuild of the projection, if any.
+    pub fn rebuild_progress(
+        &self,
+        projection: &str,
+    ) -> Result<Option<ProjectionRebuild>, ErrorMessage> {
+        self.repository.fetch_rebuild(projection)
+    }
+
+    /// Starts the rebuild of the projection from scratch. The projection is cleared, and updated asynchronously until the rebuild is finished.
+    pub fn start_rebuild(&self, projection: &str) -> Result<(), ErrorMessage> {
+        self.repository.start_rebuild(projection)
+    }
+
+    /// Applies the next batch of events to the projection that is being rebuilt.
+    /// Once the projection has caught up with the event store, it is switched back to its previous mode, and the rebuild is finished.
+    /// Returns the progress of the rebuild, or `None` if there is no rebuild in progress.
+    pub fn rebuild<H>(
+        &self,
+        projection: &str,
+        batch_size: i64,
+        handler: H,
+    ) -> Result<Option<ProjectionRebuild>, ErrorMessage>
+    where
+        H: Fn(&E, &EventPosition) -> Result<(), ErrorMessage>,
+    {
+        let Some(rebuild) = self.repository.fetch_rebuild(projection)? else {
+            return Ok(None);
+        };
+        let (applied, fetched) = self.project_batch(projection, batch_size, &handler)?;
+        let events_applied = rebuild.events_applied + applied;
+        let finished = fetched < batch_size;
+        if finished {
+            self.switch_mode(projection, rebuild.previous_mode, batch_size, &handler)?;
+        }
+        self.repository
+            .save_rebuild(projection, events_applied, finished)?;
+        Ok(Some(ProjectionRebuild {
+            events_applied,
+            finished,
+            ..rebuild
+        }))
+    }
+}
+
+/// Waits on the latch of the backend for the duration (at least 1 ms), instead of sleeping: the wait is interrupted by the signals (e.g. the cancel request, handled right after), and the backend exits if the postmaster dies.
+fn wait_latch(timeout: Duration) {
+    unsafe {
+        pg_sys::WaitLatch(
+            pg_sys::MyLatch,
+            (pg_sys::WL_LATCH_SET | pg_sys::WL_TIMEOUT | pg_sys::WL_EXIT_ON_PM_DEATH) as i32,
+            timeout.as_millis().max(1) as _,
+            pg_sys::PG_WAIT_EXTENSION,
+        );
+        pg_sys::ResetLatch(pg_sys::MyLatch);
+    }
+    pgrx::check_for_interrupts!();
+}
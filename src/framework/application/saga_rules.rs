@@ -0,0 +1,28 @@
+use crate::framework::domain::api::{CommandType, EventType};
+use crate::framework::infrastructure::saga_rules::is_reaction_enabled;
+use fmodel_rust::saga::Saga;
+use pgrx::warning;
+
+/// Makes the reactions of the saga configurable at runtime: the commands of the reactions disabled in the `saga_rules` table are dropped.
+/// The reactions without a rule are enabled, so the saga behaves as built-in when the table is empty.
+/// If the rule can not be fetched, the reaction is enabled (and a warning is reported).
+pub fn configurable_saga<'a, E, C>(saga: Saga<'a, E, C>) -> Saga<'a, E, C>
+where
+    E: EventType,
+    C: CommandType,
+{
+    Saga {
+        react: Box::new(move |event| {
+            (saga.react)(event)
+                .into_iter()
+                .filter(|command| {
+                    is_reaction_enabled(&event.event_type(), &command.command_type())
+                        .unwrap_or_else(|err| {
+                            warning!("fmodel: {}", err.message);
+                            true
+                        })
+                })
+                .collect()
+        }),
+    }
+}
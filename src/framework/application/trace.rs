@@ -0,0 +1,50 @@
+use crate::framework::infrastructure::guc::{TraceLevel, TRACE_LEVEL};
+use pgrx::prelude::*;
+use std::time::Duration;
+
+/// A span-like record of the command handling.
+/// It is reported as a `key=value` line, if `fmodel.trace_level` is not `off`, giving visibility into slow streams.
+pub struct CommandTrace {
+    /// The name of the span: `handle` or `handle_all`
+    pub span: &'static str,
+    /// The type(s) of the handled command(s)
+    pub command_type: String,
+    /// The identifier(s) of the decider(s) the command(s) are addressed to
+    pub decider_id: String,
+    /// The number of events fetched from the event store
+    pub fetched_events: usize,
+    /// The number of new events produced by the decider and the saga
+    pub produced_events: usize,
+    /// The duration of fetching the events
+    pub fetch: Duration,
+    /// The duration of computing the new events (decide, including the saga orchestration)
+    pub decide: Duration,
+    /// The duration of saving the new events
+    pub save: Duration,
+}
+
+impl CommandTrace {
+    /// Reports the trace on the level configured by `fmodel.trace_level`.
+    pub fn report(&self) {
+        let level = match TRACE_LEVEL.get() {
+            TraceLevel::Off => return,
+            TraceLevel::Log => PgLogLevel::LOG,
+            TraceLevel::Notice => PgLogLevel::NOTICE,
+        };
+        ereport!(
+            level,
+            PgSqlErrorCode::ERRCODE_SUCCESSFUL_COMPLETION,
+            format!(
+                "fmodel.trace span={} command_type={} decider_id={} fetched_events={} produced_events={} fetch_ms={:.3} decide_ms={:.3} save_ms={:.3}",
+                self.span,
+                self.command_type,
+                self.decider_id,
+                self.fetched_events,
+                self.produced_events,
+                self.fetch.as_secs_f64() * 1000.0,
+                self.decide.as_secs_f64() * 1000.0,
+                self.save.as_secs_f64() * 1000.0,
+            )
+        );
+    }
+}
@@ -0,0 +1,73 @@
+use crate::framework::domain::api::{EventType, Identifier};
+use crate::framework::infrastructure::errors::ErrorMessage;
+
+/// Event validator, run before the new events are saved.
+/// It returns the violations of the event (empty if the event is valid), e.g. the invariants the decider is expected to keep.
+pub trait EventValidator<E> {
+    fn validate(&self, event: &E) -> Vec<String>;
+}
+
+/// Any function of the event returning the violations is an event validator.
+impl<E, F: Fn(&E) -> Vec<String>> EventValidator<E> for F {
+    fn validate(&self, event: &E) -> Vec<String> {
+        self(event)
+    }
+}
+
+/// A registry of the event validators, by the event type.
+///
+/// - The validators of the event type are run for every new event of that type, before the events are saved.
+/// - The invalid events abort the command: nothing is saved, and the error lists all the violations (of all the new events).
+pub struct ValidatorRegistry<E> {
+    validators: Vec<(&'static str, Box<dyn EventValidator<E>>)>,
+}
+
+impl<E: EventType + Identifier> ValidatorRegistry<E> {
+    /// Creates a new, empty, validator registry.
+    pub fn new() -> Self {
+        ValidatorRegistry {
+            validators: Vec::new(),
+        }
+    }
+
+    /// Registers the validator of the event type/name (e.g. `RestaurantCreated`).
+    pub fn register(
+        mut self,
+        event_type: &'static str,
+        validator: impl EventValidator<E> + 'static,
+    ) -> Self {
+        self.validators.push((event_type, Box::new(validator)));
+        self
+    }
+
+    /// Validates the new events. The error lists the violations: `<event type> <decider id>: <violation>`.
+    pub fn validate(&self, events: &[E]) -> Result<(), ErrorMessage> {
+        let mut violations = Vec::new();
+        for event in events {
+            let event_type = event.event_type();
+            for (_, validator) in self
+                .validators
+                .iter()
+                .filter(|(registered, _)| *registered == event_type)
+            {
+                for violation in validator.validate(event) {
+                    violations.push(
+                        event_type.to_owned()
+                            + " "
+                            + &event.identifier().to_string()
+                            + ": "
+                            + &violation,
+                    );
+                }
+            }
+        }
+        if violations.is_empty() {
+            return Ok(());
+        }
+        Err(ErrorMessage {
+            message: "Failed to save the events. The events are invalid: ".to_string()
+                + &violations.join("; "),
+            context: None,
+        })
+    }
+}
@@ -0,0 +1,46 @@
+use crate::framework::domain::api::Identifier;
+use crate::framework::domain::workflow::{workflow_decider, Workflow, WorkflowCommand};
+use crate::framework::infrastructure::correlation::current_correlation_id;
+use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::framework::infrastructure::workflow_repository::{
+    fetch_workflow_events, save_workflow_events,
+};
+use pgrx::warning;
+use uuid::Uuid;
+
+/// Handles the workflow command: the state of the workflow is folded from its event stream, and the decided events are appended to the stream.
+pub fn handle_workflow_command(command: &WorkflowCommand) -> Result<(), ErrorMessage> {
+    let decider = workflow_decider();
+    let correlation_id = command.identifier();
+    let (events, version) = fetch_workflow_events(&correlation_id)?;
+    let state = events
+        .iter()
+        .fold((decider.initial_state)(), |state, event| {
+            (decider.evolve)(&state, event)
+        });
+    let new_events = (decider.decide)(command, &state);
+    save_workflow_events(&correlation_id, version, &new_events)
+}
+
+/// Fetches the state of the workflow (the saga of the business transaction), folded from its event stream. `None` if the saga issued no commands in the business transaction.
+pub fn fetch_workflow(correlation_id: &Uuid) -> Result<Option<Workflow>, ErrorMessage> {
+    let decider = workflow_decider();
+    let (events, _) = fetch_workflow_events(correlation_id)?;
+    Ok(events
+        .iter()
+        .fold((decider.initial_state)(), |state, event| {
+            (decider.evolve)(&state, event)
+        }))
+}
+
+/// Records the step of the workflow, if the command is handled within a correlation (see `with_correlation`).
+/// Recording is best-effort: the failure is reported as a warning, and does not fail the command handling (the saga command ledger is authoritative).
+pub fn record_workflow_step(to_command: impl FnOnce(Uuid) -> WorkflowCommand) {
+    let recorded = current_correlation_id().and_then(|correlation_id| match correlation_id {
+        Some(correlation_id) => handle_workflow_command(&to_command(correlation_id)),
+        None => Ok(()),
+    });
+    if let Err(err) = recorded {
+        warning!("fmodel: {}", err.message);
+    }
+}
@@ -5,6 +5,11 @@ pub trait Identifier {
     fn identifier(&self) -> Uuid;
 }
 
+/// A trait for identifying the type/name of a command
+pub trait CommandType {
+    fn command_type(&self) -> String;
+}
+
 /// A trait for identifying the type/name of an event
 pub trait EventType {
     fn event_type(&self) -> String;
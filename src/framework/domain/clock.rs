@@ -0,0 +1,24 @@
+/// The local (wall clock) time in the time zone.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct LocalTime {
+    /// The ISO day of the week (1 = Monday, ..., 7 = Sunday)
+    pub day_of_week: u32,
+    /// The minutes since midnight (0 - 1439)
+    pub minute_of_day: u32,
+}
+
+/// The clock, injected into the deciders that depend on the current time (e.g. the opening hours).
+/// The deciders stay pure: the time is an input of the decision, and the tests can fix it.
+pub trait Clock: Send + Sync {
+    /// The current local time in the (IANA) time zone, or `None` if the time zone is unknown.
+    fn local_time(&self, time_zone: &str) -> Option<LocalTime>;
+}
+
+/// The clock that is stopped at the local time, in every time zone.
+pub struct FixedClock(pub LocalTime);
+
+impl Clock for FixedClock {
+    fn local_time(&self, _time_zone: &str) -> Option<LocalTime> {
+        Some(self.0)
+    }
+}
@@ -1 +1,4 @@
 pub mod api;
+pub mod clock;
+pub mod specification;
+pub mod workflow;
@@ -0,0 +1,60 @@
+use fmodel_rust::decider::Decider;
+use pgrx::{CaughtError, PgTryBuilder};
+use serde::Serialize;
+use std::panic::AssertUnwindSafe;
+
+/// The expected outcome of the decision: the events, or the error the decision fails with.
+pub enum Then<E> {
+    Events(Vec<E>),
+    Error(&'static str),
+}
+
+/// The given-when-then specification of the decider: given the events (the state is evolved from them), when the command is decided, then the events (or the error) are expected.
+pub struct DeciderSpecification<C, E> {
+    pub name: &'static str,
+    pub given: Vec<E>,
+    pub when: C,
+    pub then: Then<E>,
+}
+
+impl<C, E: Serialize + PartialEq> DeciderSpecification<C, E> {
+    /// Verifies the specification against the decider: `Err` describes the expected and the actual outcome, if they differ.
+    pub fn verify<S>(&self, decider: &Decider<'_, C, S, E>) -> Result<(), String> {
+        let state = self
+            .given
+            .iter()
+            .fold((decider.initial_state)(), |state, event| {
+                (decider.evolve)(&state, event)
+            });
+        // The decision fails with the error (`error!`): it is caught, and compared to the expected one
+        let actual = PgTryBuilder::new(AssertUnwindSafe(|| {
+            Ok((decider.decide)(&self.when, &state))
+        }))
+        .catch_others(|err| match err {
+            CaughtError::ErrorReport(report) => Err(report.message().to_string()),
+            err => err.rethrow(),
+        })
+        .execute();
+        match (&self.then, actual) {
+            (Then::Events(expected), Ok(actual)) if *expected == actual => Ok(()),
+            (Then::Error(expected), Err(actual)) if *expected == actual => Ok(()),
+            (then, actual) => Err("Expected ".to_string()
+                + &describe(match then {
+                    Then::Events(expected) => Ok(expected),
+                    Then::Error(expected) => Err(expected.to_string()),
+                })
+                + ", but got "
+                + &describe(actual.as_ref().map_err(|err| err.to_owned()))),
+        }
+    }
+}
+
+fn describe<E: Serialize>(outcome: Result<&Vec<E>, String>) -> String {
+    match outcome {
+        Ok(events) => {
+            "the events ".to_string()
+                + &serde_json::to_string(events).unwrap_or_else(|err| err.to_string())
+        }
+        Err(error) => "the error: ".to_string() + &error,
+    }
+}
@@ -0,0 +1,198 @@
+use crate::framework::domain::api::{EventType, Identifier};
+use fmodel_rust::decider::Decider;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// The command issued by the saga, in reaction to the event: a step of the workflow.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct WorkflowStep {
+    /// The id of the command in the saga command ledger (`saga_commands.id`)
+    pub saga_command_id: i64,
+    /// The depth of the orchestration
+    pub depth: i32,
+    /// The type of the event the saga reacted to, e.g. `OrderPlaced`
+    pub event_type: String,
+    /// The type of the command the saga reacted with, e.g. `CreateOrder`
+    pub command_type: String,
+    /// The decider (stream) the command is addressed to
+    pub decider_id: Uuid,
+}
+
+/// The commands of the workflow (the saga of the business transaction). The workflow is identified by the correlation id.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum WorkflowCommand {
+    /// Records the command issued by the saga
+    IssueCommand {
+        identifier: Uuid,
+        step: WorkflowStep,
+    },
+    /// Records the outcome of the (issued, or re-driven) command: succeeded, or failed with the error
+    CompleteCommand {
+        identifier: Uuid,
+        saga_command_id: i64,
+        error: Option<String>,
+    },
+}
+
+impl Identifier for WorkflowCommand {
+    fn identifier(&self) -> Uuid {
+        match self {
+            WorkflowCommand::IssueCommand { identifier, .. } => *identifier,
+            WorkflowCommand::CompleteCommand { identifier, .. } => *identifier,
+        }
+    }
+}
+
+/// The events of the workflow (the saga of the business transaction).
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum WorkflowEvent {
+    WorkflowCommandIssued { step: WorkflowStep },
+    WorkflowCommandSucceeded { saga_command_id: i64 },
+    WorkflowCommandFailed { saga_command_id: i64, error: String },
+}
+
+impl EventType for WorkflowEvent {
+    fn event_type(&self) -> String {
+        match self {
+            WorkflowEvent::WorkflowCommandIssued { .. } => "WorkflowCommandIssued".to_string(),
+            WorkflowEvent::WorkflowCommandSucceeded { .. } => {
+                "WorkflowCommandSucceeded".to_string()
+            }
+            WorkflowEvent::WorkflowCommandFailed { .. } => "WorkflowCommandFailed".to_string(),
+        }
+    }
+}
+
+/// The status of the workflow step / the command issued by the saga.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkflowStepStatus {
+    Issued,
+    Succeeded,
+    Failed,
+}
+
+/// The state of the workflow step: the outcome of the last attempt.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct WorkflowStepState {
+    pub step: WorkflowStep,
+    pub status: WorkflowStepStatus,
+    /// The number of the handling attempts (the re-drives included)
+    pub attempts: i32,
+    pub error: Option<String>,
+}
+
+/// The status of the workflow.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkflowStatus {
+    /// A command issued by the saga is not handled yet
+    InProgress,
+    /// A command issued by the saga failed: the workflow does not continue until the command is re-driven (`retry_saga_command`)
+    Stuck,
+    /// All the commands issued by the saga succeeded: the workflow waits for the next event (e.g. the order prepared, hours later)
+    Waiting,
+}
+
+/// The state of the workflow (the saga of the business transaction): the steps, in the order they were issued.
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Workflow {
+    pub steps: Vec<WorkflowStepState>,
+}
+
+impl Workflow {
+    /// The status of the workflow: stuck if any step failed, in progress if any step is not handled yet, waiting otherwise.
+    pub fn status(&self) -> WorkflowStatus {
+        let has = |status| self.steps.iter().any(|step| step.status == status);
+        if has(WorkflowStepStatus::Failed) {
+            WorkflowStatus::Stuck
+        } else if has(WorkflowStepStatus::Issued) {
+            WorkflowStatus::InProgress
+        } else {
+            WorkflowStatus::Waiting
+        }
+    }
+}
+
+/// A convenient type alias for the Workflow decider
+pub type WorkflowDecider<'a> = Decider<'a, WorkflowCommand, Option<Workflow>, WorkflowEvent>;
+
+/// The workflow decider: the saga of the business transaction, as an event-sourced decider, so the reactions are recorded and resumable.
+/// The decisions are idempotent: the step issued already, or succeeded already, is not recorded again. The outcome of the unknown step is ignored.
+pub fn workflow_decider<'a>() -> WorkflowDecider<'a> {
+    Decider {
+        decide: Box::new(|command, state| {
+            let find = |saga_command_id: &i64| {
+                state.as_ref().and_then(|workflow| {
+                    workflow
+                        .steps
+                        .iter()
+                        .find(|step| step.step.saga_command_id == *saga_command_id)
+                })
+            };
+            match command {
+                WorkflowCommand::IssueCommand { step, .. } => {
+                    if find(&step.saga_command_id).is_some() {
+                        vec![]
+                    } else {
+                        vec![WorkflowEvent::WorkflowCommandIssued { step: step.clone() }]
+                    }
+                }
+                WorkflowCommand::CompleteCommand {
+                    saga_command_id,
+                    error,
+                    ..
+                } => match find(saga_command_id) {
+                    Some(step) if step.status != WorkflowStepStatus::Succeeded => match error {
+                        None => vec![WorkflowEvent::WorkflowCommandSucceeded {
+                            saga_command_id: *saga_command_id,
+                        }],
+                        Some(error) => vec![WorkflowEvent::WorkflowCommandFailed {
+                            saga_command_id: *saga_command_id,
+                            error: error.to_owned(),
+                        }],
+                    },
+                    _ => vec![],
+                },
+            }
+        }),
+        evolve: Box::new(|state, event| {
+            let mut workflow = state.clone().unwrap_or_default();
+            let (saga_command_id, status, error) = match event {
+                WorkflowEvent::WorkflowCommandIssued { step } => {
+                    workflow.steps.push(WorkflowStepState {
+                        step: step.clone(),
+                        status: WorkflowStepStatus::Issued,
+                        attempts: 0,
+                        error: None,
+                    });
+                    return Some(workflow);
+                }
+                WorkflowEvent::WorkflowCommandSucceeded { saga_command_id } => {
+                    (saga_command_id, WorkflowStepStatus::Succeeded, None)
+                }
+                WorkflowEvent::WorkflowCommandFailed {
+                    saga_command_id,
+                    error,
+                } => (
+                    saga_command_id,
+                    WorkflowStepStatus::Failed,
+                    Some(error.to_owned()),
+                ),
+            };
+            if let Some(step) = workflow
+                .steps
+                .iter_mut()
+                .find(|step| step.step.saga_command_id == *saga_command_id)
+            {
+                step.status = status;
+                step.attempts += 1;
+                step.error = error;
+            }
+            Some(workflow)
+        }),
+        initial_state: Box::new(|| None),
+    }
+}
@@ -0,0 +1,14 @@
+use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::framework::infrastructure::event_repository::EventPosition;
+
+/// A trait for an aggregating view repository / the analytic read model of the CQRS pattern.
+/// The aggregating view is folding the events into the (additive) counters of the buckets (e.g. the orders per restaurant per day), instead of the entity documents.
+pub trait AggregatingViewRepository<K, V> {
+    /// Adds the increment to the counters of the bucket (creating the bucket, if it does not exist), together with the position of the event.
+    fn increment(
+        &self,
+        bucket: &K,
+        increment: &V,
+        position: &EventPosition,
+    ) -> Result<(), ErrorMessage>;
+}
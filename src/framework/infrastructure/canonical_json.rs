@@ -0,0 +1,77 @@
+use crate::framework::infrastructure::errors::ErrorMessage;
+use pgrx::Spi;
+use serde_json::Value;
+
+/// A convenient type alias for the non-canonical event row: the event id, the decider type, the decider id, the offset and the event type.
+pub type NonCanonicalEvent = (pgrx::Uuid, String, String, i64, String);
+
+/// Serializes the value to the canonical JSON: the object keys sorted (by the code points), and no insignificant whitespace.
+/// The payload hash (deduplication) is the SHA-256 of the canonical JSON, so it does not depend on the text representation of the `serde_json` (or the Postgres `jsonb`) version.
+pub fn to_canonical_json(value: &Value) -> String {
+    let mut json = String::new();
+    write_canonical_json(value, &mut json);
+    json
+}
+
+fn write_canonical_json(value: &Value, json: &mut String) {
+    match value {
+        Value::Object(map) => {
+            // The map is sorted by the `serde_json` defaults only (not with the `preserve_order` feature)
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            json.push('{');
+            for (index, (key, value)) in entries.into_iter().enumerate() {
+                if index > 0 {
+                    json.push(',');
+                }
+                json.push_str(&Value::String(key.to_owned()).to_string());
+                json.push(':');
+                write_canonical_json(value, json);
+            }
+            json.push('}');
+        }
+        Value::Array(values) => {
+            json.push('[');
+            for (index, value) in values.iter().enumerate() {
+                if index > 0 {
+                    json.push(',');
+                }
+                write_canonical_json(value, json);
+            }
+            json.push(']');
+        }
+        // The scalars are written compact, the strings escaped
+        scalar => json.push_str(&scalar.to_string()),
+    }
+}
+
+/// Fetches the events with the payload hash which is not the hash of the canonical JSON of the (rehydrated) payload, in the order of the offsets: the historical events hashed from the legacy `jsonb` text representation.
+/// The deduplication does not detect the duplicates of these events.
+pub fn fetch_non_canonical_events() -> Result<Vec<NonCanonicalEvent>, ErrorMessage> {
+    Spi::connect(|client| {
+        let mut results = Vec::new();
+        let tup_table = client.select(
+            "SELECT event_id, decider, decider_id, \"offset\", event FROM events
+             WHERE payload_hash IS NOT NULL
+               AND payload_hash <> sha256(convert_to(fmodel_canonical_json(fmodel_event_data(event_id, data)), 'UTF8'))
+             ORDER BY \"offset\"",
+            None,
+            None,
+        )?;
+        for row in tup_table {
+            if let (Some(event_id), Some(decider), Some(decider_id), Some(offset), Some(event)) = (
+                row["event_id"].value::<pgrx::Uuid>()?,
+                row["decider"].value::<String>()?,
+                row["decider_id"].value::<String>()?,
+                row["offset"].value::<i64>()?,
+                row["event"].value::<String>()?,
+            ) {
+                results.push((event_id, decider, decider_id, offset, event));
+            }
+        }
+        Ok(results)
+    })
+    .map_err(|err: pgrx::spi::Error| {
+        ErrorMessage::spi("fetch the non-canonical events", None, &err)
+    })
+}
@@ -0,0 +1,25 @@
+use crate::framework::domain::clock::{Clock, LocalTime};
+use pgrx::{IntoDatum, PgBuiltInOids, Spi};
+
+/// The clock of the database: the start time of the current transaction (`now()`), in the time zone.
+/// All the commands handled in the transaction see the same time.
+pub struct PostgresClock;
+
+impl Clock for PostgresClock {
+    fn local_time(&self, time_zone: &str) -> Option<LocalTime> {
+        // The time zone is looked up first: an unknown time zone is not an error of the `AT TIME ZONE` conversion
+        Spi::get_two_with_args::<i32, i32>(
+            "SELECT EXTRACT(ISODOW FROM now() AT TIME ZONE name)::INT,
+                    (EXTRACT(HOUR FROM now() AT TIME ZONE name) * 60 + EXTRACT(MINUTE FROM now() AT TIME ZONE name))::INT
+             FROM pg_timezone_names WHERE name = $1",
+            vec![(PgBuiltInOids::TEXTOID.oid(), time_zone.into_datum())],
+        )
+        .ok()
+        .and_then(|(day_of_week, minute_of_day)| {
+            Some(LocalTime {
+                day_of_week: u32::try_from(day_of_week?).ok()?,
+                minute_of_day: u32::try_from(minute_of_day?).ok()?,
+            })
+        })
+    }
+}
@@ -0,0 +1,178 @@
+use pgrx::lwlock::PgLwLock;
+use pgrx::shmem::{PGRXSharedMemory, PgSharedMemoryInitialization};
+use pgrx::{pg_guard, pg_shmem_init, pg_sys};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// The number of the tracked command types in the shared memory. The command types past it are not tracked.
+const SLOTS: usize = 64;
+
+/// The maximum length (in bytes) of the tracked command type.
+const NAME_SIZE: usize = 64;
+
+/// The statistics of the command handling, by the command type, in the shared memory (shared by all the backends).
+static COMMAND_STATS: PgLwLock<CommandStatsTable> = PgLwLock::new();
+
+/// The indicator if the shared memory is initialized (the extension is loaded via `shared_preload_libraries`).
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// The statistics of the command type, accumulated since the server start (or the last reset).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CommandStats {
+    /// The number of the handled commands
+    pub calls: u64,
+    /// The number of the failed commands (rejected by the decider, or failed otherwise)
+    pub failures: u64,
+    /// The total time of handling the commands, in milliseconds
+    pub total_time_ms: f64,
+    /// The maximum time of handling the command, in milliseconds
+    pub max_time_ms: f64,
+    /// The total number of the events produced by the succeeded commands
+    pub events: u64,
+}
+
+impl CommandStats {
+    /// Records the handled command: the time of handling it, and the number of the produced events (`None` if the command failed).
+    pub fn record(&mut self, elapsed: Duration, events: Option<usize>) {
+        let time_ms = elapsed.as_secs_f64() * 1000.0;
+        self.calls += 1;
+        self.total_time_ms += time_ms;
+        self.max_time_ms = self.max_time_ms.max(time_ms);
+        match events {
+            Some(events) => self.events += events as u64,
+            None => self.failures += 1,
+        }
+    }
+
+    /// The mean time of handling the command, in milliseconds.
+    pub fn mean_time_ms(&self) -> f64 {
+        if self.calls == 0 {
+            0.0
+        } else {
+            self.total_time_ms / self.calls as f64
+        }
+    }
+
+    /// The mean number of the events produced by the succeeded command.
+    pub fn mean_events(&self) -> f64 {
+        let succeeded = self.calls - self.failures;
+        if succeeded == 0 {
+            0.0
+        } else {
+            self.events as f64 / succeeded as f64
+        }
+    }
+
+    /// The share of the failed commands (0 to 1).
+    pub fn failure_rate(&self) -> f64 {
+        if self.calls == 0 {
+            0.0
+        } else {
+            self.failures as f64 / self.calls as f64
+        }
+    }
+}
+
+/// The statistics of a command type.
+#[derive(Clone, Copy)]
+struct CommandStatsSlot {
+    /// The hash of the command type the slot belongs to; `0` for the free slot
+    key: u64,
+    /// The length of the command type
+    len: usize,
+    /// The command type
+    name: [u8; NAME_SIZE],
+    stats: CommandStats,
+}
+
+impl Default for CommandStatsSlot {
+    fn default() -> Self {
+        CommandStatsSlot {
+            key: 0,
+            len: 0,
+            name: [0; NAME_SIZE],
+            stats: CommandStats::default(),
+        }
+    }
+}
+
+/// The fixed-size table of the command statistics.
+#[derive(Clone, Copy)]
+struct CommandStatsTable {
+    slots: [CommandStatsSlot; SLOTS],
+}
+
+impl Default for CommandStatsTable {
+    fn default() -> Self {
+        CommandStatsTable {
+            slots: [CommandStatsSlot::default(); SLOTS],
+        }
+    }
+}
+
+unsafe impl PGRXSharedMemory for CommandStatsTable {}
+
+/// Initializes the command statistics in the shared memory. Must be called from `_PG_init`, while the shared preload libraries are loaded.
+pub fn init() {
+    pg_shmem_init!(COMMAND_STATS);
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Records the handled command of the type: the time of handling it, and the number of the produced events (`None` if the command failed).
+/// The statistics require the shared memory; nothing is recorded if the extension is not loaded via `shared_preload_libraries`.
+pub fn record(command_type: &str, elapsed: Duration, events: Option<usize>) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let name = &command_type.as_bytes()[..command_type.len().min(NAME_SIZE)];
+    let key = to_key(name);
+    let mut table = COMMAND_STATS.exclusive();
+    let Some(slot) = table
+        .slots
+        .iter_mut()
+        .find(|slot| slot.key == key || slot.key == 0)
+    else {
+        return;
+    };
+    if slot.key == 0 {
+        slot.key = key;
+        slot.len = name.len();
+        slot.name[..name.len()].copy_from_slice(name);
+    }
+    slot.stats.record(elapsed, events);
+}
+
+/// Fetches the statistics of all the tracked command types.
+pub fn fetch_command_stats() -> Vec<(String, CommandStats)> {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return Vec::new();
+    }
+    COMMAND_STATS
+        .share()
+        .slots
+        .iter()
+        .filter(|slot| slot.key != 0)
+        .map(|slot| {
+            (
+                String::from_utf8_lossy(&slot.name[..slot.len]).into_owned(),
+                slot.stats,
+            )
+        })
+        .collect()
+}
+
+/// Discards all the statistics.
+pub fn reset() {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    *COMMAND_STATS.exclusive() = CommandStatsTable::default();
+}
+
+/// The hash of the command type; never `0` (the free slot).
+fn to_key(name: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    name.hash(&mut hasher);
+    hasher.finish().max(1)
+}
@@ -0,0 +1,47 @@
+use crate::framework::infrastructure::errors::ErrorMessage;
+use pgrx::{IntoDatum, PgBuiltInOids, Spi};
+use uuid::Uuid;
+
+/// Runs the (top-level) command handling within a new correlation.
+/// Every event appended meanwhile, including the saga-derived events, is stamped with the correlation id (the `events.correlation_id` column defaults to `fmodel.correlation_id`).
+/// Returns the correlation id, together with the result of the command handling.
+pub fn with_correlation<T>(
+    handle: impl FnOnce() -> Result<T, ErrorMessage>,
+) -> Result<(Uuid, T), ErrorMessage> {
+    within_correlation(Uuid::new_v4(), handle)
+}
+
+/// Runs the command handling within the existing correlation, e.g. re-driving the saga command of the business transaction.
+/// Returns the correlation id, together with the result of the command handling.
+pub fn within_correlation<T>(
+    correlation_id: Uuid,
+    handle: impl FnOnce() -> Result<T, ErrorMessage>,
+) -> Result<(Uuid, T), ErrorMessage> {
+    set_correlation_id(&correlation_id.to_string())?;
+    let result = handle();
+    set_correlation_id("")?;
+    result.map(|result| (correlation_id, result))
+}
+
+/// Returns the correlation id of the current command handling (`fmodel.correlation_id`), if any.
+pub fn current_correlation_id() -> Result<Option<Uuid>, ErrorMessage> {
+    Spi::get_one::<pgrx::Uuid>(
+        "SELECT NULLIF(current_setting('fmodel.correlation_id', TRUE), '')::UUID",
+    )
+    .map(|correlation_id| {
+        correlation_id.map(|correlation_id| Uuid::from_bytes(*correlation_id.as_bytes()))
+    })
+    .map_err(|err| ErrorMessage::spi("fetch the correlation id", None, &err))
+}
+
+/// Sets the correlation id (`fmodel.correlation_id`) for the rest of the transaction. An empty string clears it.
+fn set_correlation_id(correlation_id: &str) -> Result<(), ErrorMessage> {
+    Spi::run_with_args(
+        "SELECT set_config('fmodel.correlation_id', $1, TRUE)",
+        Some(vec![(
+            PgBuiltInOids::TEXTOID.oid(),
+            correlation_id.into_datum(),
+        )]),
+    )
+    .map_err(|err| ErrorMessage::spi("set the correlation id", None, &err))
+}
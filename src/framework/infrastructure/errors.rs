@@ -1,5 +1,7 @@
 use pgrx::datum::TryFromDatumError;
+use pgrx::pg_sys::panic::ErrorReport;
 use pgrx::prelude::*;
+use pgrx::spi::{SpiError, SpiErrorCodes};
 use serde::{Deserialize, Serialize};
 use std::error::Error;
 use std::fmt;
@@ -9,25 +11,94 @@ use std::num::TryFromIntError;
 #[derive(Serialize, Deserialize)]
 pub struct ErrorMessage {
     pub message: String,
+    /// The context of the failed SPI operation (the repository errors, see `ErrorMessage::spi`), or `None`
+    pub context: Option<ErrorContext>,
+}
+
+/// The context of the failed SPI operation, for the incident triage: the operation, the identity of the stream, and the SQLSTATE.
+/// Never the query arguments (e.g. the event payloads).
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ErrorContext {
+    /// The operation that failed, e.g. `fetch events`
+    pub operation: String,
+    /// The identity of the stream (the decider id) the operation is scoped to, or `None`
+    pub stream: Option<String>,
+    /// The SQLSTATE of the SPI error
+    pub sql_state: String,
+}
+
+impl ErrorMessage {
+    /// The error of the SPI operation: `Failed to <operation>: <SPI error>`, with the context of the operation.
+    pub fn spi(operation: &str, stream: Option<String>, err: &SpiError) -> Self {
+        ErrorMessage {
+            message: "Failed to ".to_string() + operation + ": " + &err.to_string(),
+            context: Some(ErrorContext {
+                operation: operation.to_string(),
+                stream,
+                sql_state: spi_sql_state(err).to_string(),
+            }),
+        }
+    }
+}
+
+/// The SQLSTATE of the SPI error.
+/// The errors of the executed statements (e.g. the constraint violations) are not SPI errors: they abort the transaction with their own SQLSTATE.
+pub fn spi_sql_state(err: &SpiError) -> &'static str {
+    match err {
+        // `datatype_mismatch`: the column is not of the expected type
+        SpiError::DatumError(_) => "42804",
+        // `invalid_parameter_value`
+        SpiError::PreparedStatementArgumentMismatch { .. }
+        | SpiError::SpiError(SpiErrorCodes::Argument | SpiErrorCodes::Param) => "22023",
+        // `invalid_cursor_name`
+        SpiError::CursorNotFound(_) => "34000",
+        // `undefined_table`
+        SpiError::SpiError(SpiErrorCodes::RelNotFound) => "42P01",
+        // `undefined_column`
+        SpiError::SpiError(SpiErrorCodes::NoAttribute) => "42703",
+        // `undefined_object`
+        SpiError::SpiError(SpiErrorCodes::TypUnknown) => "42704",
+        // `invalid_transaction_state`
+        SpiError::SpiError(SpiErrorCodes::Transaction) => "25000",
+        // `internal_error`
+        _ => "XX000",
+    }
 }
 
 /// Implement Display for ErrorMessage
+/// The context (if any) follows the message, e.g. `Failed to fetch events: ... (stream: <decider id>, SQLSTATE: XX000)`.
 impl fmt::Display for ErrorMessage {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.message)
+        match &self.context {
+            Some(ErrorContext {
+                stream: Some(stream),
+                sql_state,
+                ..
+            }) => write!(
+                f,
+                "{} (stream: {}, SQLSTATE: {})",
+                self.message, stream, sql_state
+            ),
+            Some(ErrorContext { sql_state, .. }) => {
+                write!(f, "{} (SQLSTATE: {})", self.message, sql_state)
+            }
+            None => write!(f, "{}", self.message),
+        }
     }
 }
 
 /// Implement Debug for ErrorMessage
 impl fmt::Debug for ErrorMessage {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "ErrorMessage: {}", self.message)
+        write!(f, "ErrorMessage: {}", self)
     }
 }
 
 /// Implement Error for ErrorMessage
 impl Error for ErrorMessage {}
 
+/// Error of the trigger functions / event handlers.
+/// Every variant is raised with its own SQLSTATE (see `sql_error_code`), so the projection failures can be classified from the logs.
 #[derive(thiserror::Error, Debug)]
 pub enum TriggerError {
     #[error("Null Trigger Tuple found")]
@@ -40,4 +111,41 @@ pub enum TriggerError {
     TryFromInt(#[from] TryFromIntError),
     #[error("Event Handling Error: {0}")]
     EventHandlingError(String),
+    #[error("Event Deserialization Error: {0}")]
+    DeserializationFailed(String),
+    #[error("Repository Save Error: {0}")]
+    RepositorySaveFailed(String),
+    #[error("Stale Event Error: {0}")]
+    StaleEvent(String),
+    #[error("Unknown Event Type Error: {0}")]
+    UnknownEventType(String),
+}
+
+impl TriggerError {
+    /// The SQLSTATE the error is raised with.
+    pub fn sql_error_code(&self) -> PgSqlErrorCode {
+        match self {
+            // `22P02`
+            TriggerError::DeserializationFailed(_) => {
+                PgSqlErrorCode::ERRCODE_INVALID_TEXT_REPRESENTATION
+            }
+            // `09000`
+            TriggerError::RepositorySaveFailed(_) => {
+                PgSqlErrorCode::ERRCODE_TRIGGERED_ACTION_EXCEPTION
+            }
+            // `55000`
+            TriggerError::StaleEvent(_) => PgSqlErrorCode::ERRCODE_OBJECT_NOT_IN_PREREQUISITE_STATE,
+            // `0A000`
+            TriggerError::UnknownEventType(_) => PgSqlErrorCode::ERRCODE_FEATURE_NOT_SUPPORTED,
+            // `22000`
+            _ => PgSqlErrorCode::ERRCODE_DATA_EXCEPTION,
+        }
+    }
+}
+
+/// The trigger error is raised as the Postgres ERROR with the SQLSTATE of the variant.
+impl From<TriggerError> for ErrorReport {
+    fn from(err: TriggerError) -> Self {
+        ErrorReport::new(err.sql_error_code(), err.to_string(), "fmodel trigger")
+    }
 }
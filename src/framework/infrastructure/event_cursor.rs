@@ -0,0 +1,76 @@
+use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::framework::infrastructure::event_repository::EventPosition;
+use pgrx::{IntoDatum, JsonB, PgBuiltInOids, Spi, Uuid};
+use uuid::Uuid as UUID;
+
+// Server-side (SPI) cursors over the event store, ordered by the `offset`.
+// The cursor is kept open (by name) for the duration of the transaction, and every batch is fetched in a separate SPI session, so the memory is bounded by the batch size (not by the size of the event store).
+
+/// Opens the cursor over all the events of the current store (`fmodel.store_id`) after the offset (ordered by the `offset`), and returns the name of the cursor.
+/// The cursor is closed at the end of the transaction, or by `close_event_cursor`.
+pub fn open_event_cursor(after_offset: i64) -> Result<String, ErrorMessage> {
+    Spi::connect(|client| {
+        client
+            .try_open_cursor(
+                r#"SELECT fmodel_event_data(event_id, data) AS data, event_id, "offset", sequence_number FROM events WHERE "offset" > $1 AND store_id = fmodel_store_id() ORDER BY "offset""#,
+                Some(vec![(
+                    PgBuiltInOids::INT8OID.oid(),
+                    after_offset.into_datum(),
+                )]),
+            )
+            .map(|cursor| cursor.detach_into_name())
+    })
+    .map_err(|err| ErrorMessage::spi("open the event cursor", None, &err))
+}
+
+/// Fetches the next batch of (up to `count`) events from the cursor: the raw event data/payload and the position of the event.
+/// The batch is empty when the cursor is exhausted.
+pub fn fetch_event_batch(
+    cursor: &str,
+    count: i64,
+) -> Result<Vec<(JsonB, EventPosition)>, ErrorMessage> {
+    Spi::connect(|client| {
+        let mut cursor = client.find_cursor(cursor).map_err(to_error)?;
+        let tup_table = cursor.fetch(count).map_err(to_error)?;
+        let mut results = Vec::new();
+        for row in tup_table {
+            let data = row["data"].value::<JsonB>().map_err(to_error)?;
+            let event_id = row["event_id"].value::<Uuid>().map_err(to_error)?;
+            let offset = row["offset"].value::<i64>().map_err(to_error)?;
+            let sequence_number = row["sequence_number"].value::<i64>().map_err(to_error)?;
+            match (data, event_id, offset, sequence_number) {
+                (Some(data), Some(event_id), Some(offset), Some(sequence_number)) => {
+                    results.push((
+                        data,
+                        EventPosition {
+                            event_id: UUID::from_bytes(*event_id.as_bytes()),
+                            offset,
+                            sequence_number,
+                        },
+                    ))
+                }
+                _ => {
+                    return Err(ErrorMessage {
+                        message:
+                            "Failed to fetch the event batch: No data/payload or position found"
+                                .to_string(),
+                        context: None,
+                    })
+                }
+            }
+        }
+        // The cursor is kept open for the next batch
+        cursor.detach_into_name();
+        Ok(results)
+    })
+}
+
+/// Closes the cursor (before the end of the transaction).
+pub fn close_event_cursor(cursor: &str) -> Result<(), ErrorMessage> {
+    // The cursor is closed when dropped
+    Spi::connect(|client| client.find_cursor(cursor).map(drop)).map_err(to_error)
+}
+
+fn to_error(err: pgrx::spi::Error) -> ErrorMessage {
+    ErrorMessage::spi("use the event cursor", None, &err)
+}
@@ -1,70 +1,79 @@
 use crate::framework::domain::api::{DeciderType, EventType, Identifier, IsFinal};
 use crate::framework::infrastructure::errors::ErrorMessage;
-use crate::framework::infrastructure::to_payload;
+#[cfg(any(test, feature = "pg_test"))]
+use crate::framework::infrastructure::fault_injection;
+use crate::framework::infrastructure::{to_command_side_event, to_known_event};
+use pgrx::spi::{SpiHeapTupleData, SpiTupleTable};
 use pgrx::{IntoDatum, JsonB, PgBuiltInOids, Spi, Uuid};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::fmt::Debug;
 use uuid::Uuid as UUID;
 
+/// The position of the persisted event in the event store.
+/// Clients can use it to implement "read-your-writes" by comparing it with the version of the view/projection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EventPosition {
+    /// The unique identifier of the event / the version of the event stream after this event was appended
+    pub event_id: UUID,
+    /// The global ordering sequence/offset of the event (for all events in all deciders)
+    pub offset: i64,
+    /// The per-stream sequence number of the event (1 for the first event of the stream) / the version of the event stream after this event was appended
+    pub sequence_number: i64,
+}
+
 /// A trait for event repositories / the command side of the CQRS pattern.
 /// Default implementation includes fetching and saving events.
 pub trait EventRepository<C, E>
 where
-    C: Identifier,
-    E: Identifier + EventType + IsFinal + DeciderType + DeserializeOwned + Serialize,
+    C: Identifier + DeciderType,
+    E: Clone + Identifier + EventType + IsFinal + DeciderType + DeserializeOwned + Serialize,
 {
     /// Fetches current events, based on the command.
     fn fetch_events(&self, command: &C) -> Result<Vec<(E, UUID)>, ErrorMessage> {
-        let query = "SELECT * FROM events WHERE decider_id = $1 ORDER BY events.offset";
+        #[cfg(any(test, feature = "pg_test"))]
+        fault_injection::inject_fetch_fault(&command.identifier().to_string())?;
+        // Filtered by `decider` as well, to prune the `events` partitions
+        let query =
+            "SELECT * FROM events WHERE decider = $2 AND decider_id = $1 AND store_id = fmodel_store_id() ORDER BY events.offset";
         Spi::connect(|client| {
-            let mut results = Vec::new();
             let tup_table = client
                 .select(
                     query,
                     None,
-                    Some(vec![(
-                        PgBuiltInOids::TEXTOID.oid(),
-                        command.identifier().to_string().into_datum(),
-                    )]),
+                    Some(vec![
+                        (
+                            PgBuiltInOids::TEXTOID.oid(),
+                            command.identifier().to_string().into_datum(),
+                        ),
+                        (
+                            PgBuiltInOids::TEXTOID.oid(),
+                            command.decider_type().into_datum(),
+                        ),
+                    ]),
                 )
-                .map_err(|err| ErrorMessage {
-                    message: "Failed to fetch events: ".to_string() + &err.to_string(),
+                .map_err(|err| {
+                    ErrorMessage::spi("fetch events", Some(command.identifier().to_string()), &err)
                 })?;
-            for row in tup_table {
-                let data = row["data"].value::<JsonB>().map_err(|err| ErrorMessage {
-                    message: "Failed to fetch event data/payload (map `data` to `JsonB`): ".to_string() + &err.to_string(),
-                })?.ok_or(ErrorMessage {
-                    message: "Failed to fetch event data/payload (map `data` to `JsonB`): No data/payload found".to_string(),
-                })?;
-                let event_id = row["event_id"]
-                    .value::<Uuid>()
-                    .map_err(|err| ErrorMessage {
-                        message: "Failed to fetch event id (map `event_id` to `Uuid`): "
-                            .to_string()
-                            + &err.to_string(),
-                    })?
-                    .ok_or(ErrorMessage {
-                        message:
-                            "Failed to fetch event id (map `data` to `JsonB`): No event id found"
-                                .to_string(),
-                    })?;
-
-                results.push((to_payload(data)?, UUID::from_bytes(*event_id.as_bytes())));
-            }
-            Ok(results)
+            to_events_with_versions(tup_table, true)
         })
     }
+    /// The tags attached to the event on save (e.g. `vip-customer`, `promo:summer24`), in addition to the tags of the session (`fmodel.event_tags`).
+    /// The post-decide hook: override it to tag the events decided by the decider. No tags by default.
+    fn tags(&self, _event: &E) -> Vec<String> {
+        Vec::new()
+    }
     /// Saves events.
+    /// Only the positions of the saved events are read back (not the payloads): the saved events are the in-memory events.
     fn save(
         &self,
         events: &[E],
         latest_version: &Option<UUID>,
-    ) -> Result<Vec<(E, UUID)>, ErrorMessage> {
+    ) -> Result<Vec<(E, EventPosition)>, ErrorMessage> {
         let query = "
-        INSERT INTO events (event, event_id, decider, decider_id, data, command_id, previous_id, final)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-        RETURNING *";
+        INSERT INTO events (event, event_id, decider, decider_id, data, command_id, previous_id, final, tags)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, ARRAY(SELECT DISTINCT tag FROM unnest($9::TEXT[] || fmodel_event_tags()) AS tag ORDER BY tag))
+        RETURNING event_id, \"offset\", sequence_number";
 
         Spi::connect(|mut client| {
             let mut results = Vec::new();
@@ -74,7 +83,10 @@ where
                     message: "Failed to save event! Failed to serialize event data/payload: "
                         .to_string()
                         + &err.to_string(),
+                    context: None,
                 })?;
+                #[cfg(any(test, feature = "pg_test"))]
+                fault_injection::inject_save_fault(&event.identifier().to_string())?;
                 let event_id: UUID = UUID::new_v4();
                 let tup_table = client
                     .update(
@@ -109,32 +121,18 @@ where
                                     .into_datum(),
                             ),
                             (PgBuiltInOids::BOOLOID.oid(), event.is_final().into_datum()),
+                            (
+                                PgBuiltInOids::TEXTARRAYOID.oid(),
+                                self.tags(event).into_datum(),
+                            ),
                         ]),
                     )
-                    .map_err(|err| ErrorMessage {
-                        message: "Failed to save event: ".to_string() + &err.to_string(),
+                    .map_err(|err| {
+                        ErrorMessage::spi("save event", Some(event.identifier().to_string()), &err)
                     })?;
 
                 for row in tup_table {
-                    let data = row["data"].value::<JsonB>().map_err(|err| ErrorMessage {
-                        message: "Failed to save event data/payload (map `data` to `JsonB`): ".to_string() + &err.to_string(),
-                    })?.ok_or(ErrorMessage {
-                        message: "Failed to save event data/payload (map `data` to `JsonB`): No data/payload found".to_string(),
-                    })?;
-                    let event_id = row["event_id"]
-                        .value::<Uuid>()
-                        .map_err(|err| ErrorMessage {
-                            message: "Failed to save event id (map `event_id` to `Uuid`): "
-                                .to_string()
-                                + &err.to_string(),
-                        })?
-                        .ok_or(ErrorMessage {
-                            message:
-                                "Failed to save event id (map `data` to `JsonB`): No event id found"
-                                    .to_string(),
-                        })?;
-
-                    results.push((to_payload(data)?, UUID::from_bytes(*event_id.as_bytes())));
+                    results.push((event.clone(), to_event_position(&row, &event.identifier())?));
                 }
                 version = Some(event_id);
             }
@@ -147,7 +145,7 @@ where
 /// Default implementation includes fetching events, fetching latest version and saving events.
 pub trait EventOrchestratingRepository<C, E>
 where
-    C: Identifier,
+    C: Identifier + DeciderType,
     E: Clone
         + Identifier
         + EventType
@@ -159,99 +157,274 @@ where
 {
     /// Fetches current events, based on the command.
     fn fetch_events(&self, command: &C) -> Result<Vec<(E, UUID)>, ErrorMessage> {
-        let query = "SELECT * FROM events WHERE decider_id = $1 ORDER BY events.offset";
+        self.fetch_decider_stream_events(&command.decider_type(), &command.identifier())
+    }
+
+    /// Fetches the events of the event stream of the decider of the given type, together with their versions.
+    /// Filtered by `decider` as well, to prune the `events` partitions.
+    /// The events of unknown types are never skipped: the state of the decider is computed from them.
+    fn fetch_decider_stream_events(
+        &self,
+        decider: &str,
+        decider_id: &UUID,
+    ) -> Result<Vec<(E, UUID)>, ErrorMessage> {
+        #[cfg(any(test, feature = "pg_test"))]
+        fault_injection::inject_fetch_fault(&decider_id.to_string())?;
+        let query =
+            "SELECT * FROM events WHERE decider = $2 AND decider_id = $1 AND store_id = fmodel_store_id() ORDER BY events.offset";
         Spi::connect(|client| {
-            let mut results = Vec::new();
             let tup_table = client
                 .select(
                     query,
                     None,
-                    Some(vec![(
-                        PgBuiltInOids::TEXTOID.oid(),
-                        command.identifier().to_string().into_datum(),
-                    )]),
+                    Some(vec![
+                        (
+                            PgBuiltInOids::TEXTOID.oid(),
+                            decider_id.to_string().into_datum(),
+                        ),
+                        (PgBuiltInOids::TEXTOID.oid(), decider.into_datum()),
+                    ]),
                 )
-                .map_err(|err| ErrorMessage {
-                    message: "Failed to fetch events: ".to_string() + &err.to_string(),
+                .map_err(|err| {
+                    ErrorMessage::spi("fetch events", Some(decider_id.to_string()), &err)
                 })?;
-            for row in tup_table {
-                let data = row["data"].value::<JsonB>().map_err(|err| ErrorMessage {
-                    message: "Failed to fetch event data/payload (map `data` to `JsonB`): ".to_string() + &err.to_string(),
-                })?.ok_or(ErrorMessage {
-                    message: "Failed to fetch event data/payload (map `data` to `JsonB`): No data/payload found".to_string(),
-                })?;
-                let event_id = row["event_id"]
-                    .value::<Uuid>()
-                    .map_err(|err| ErrorMessage {
-                        message: "Failed to fetch event id (map `event_id` to `Uuid`): "
-                            .to_string()
-                            + &err.to_string(),
-                    })?
-                    .ok_or(ErrorMessage {
-                        message:
-                            "Failed to fetch event id (map `data` to `JsonB`): No event id found"
-                                .to_string(),
-                    })?;
-                results.push((to_payload(data)?, UUID::from_bytes(*event_id.as_bytes())));
-            }
-            Ok(results)
+            to_events_with_versions(tup_table, true)
         })
     }
 
-    /// Fetches the latest version of the event stream to which the event belongs.
-    fn fetch_latest_version(&self, event: &E) -> Result<Option<UUID>, ErrorMessage> {
+    /// Fetches the events of the event stream of the decider, together with their versions.
+    /// Read-only SPI: it can be used on the hot standbys (read replicas) as well.
+    fn fetch_stream_events(&self, decider_id: &UUID) -> Result<Vec<(E, UUID)>, ErrorMessage> {
+        self.fetch_stream_events_until(decider_id, i64::MAX)
+    }
+
+    /// Fetches the events of the event stream of the decider, up to (and including) the offset, together with their versions.
+    fn fetch_stream_events_until(
+        &self,
+        decider_id: &UUID,
+        offset: i64,
+    ) -> Result<Vec<(E, UUID)>, ErrorMessage> {
         let query =
-            "SELECT * FROM events WHERE decider_id = $1 ORDER BY events.offset DESC LIMIT 1";
+            "SELECT * FROM events WHERE decider_id = $1 AND store_id = fmodel_store_id() AND events.offset <= $2 ORDER BY events.offset";
+        Spi::connect(|client| {
+            let tup_table = client
+                .select(
+                    query,
+                    None,
+                    Some(vec![
+                        (
+                            PgBuiltInOids::TEXTOID.oid(),
+                            decider_id.to_string().into_datum(),
+                        ),
+                        (PgBuiltInOids::INT8OID.oid(), offset.into_datum()),
+                    ]),
+                )
+                .map_err(|err| {
+                    ErrorMessage::spi("fetch events", Some(decider_id.to_string()), &err)
+                })?;
+            to_events_with_versions(tup_table, false)
+        })
+    }
+
+    /// Fetches all the events of the correlation (the business transaction) in the store of the session, in the order they were appended.
+    fn fetch_events_by_correlation(
+        &self,
+        correlation_id: &UUID,
+    ) -> Result<Vec<(E, EventPosition)>, ErrorMessage> {
+        let query = "SELECT * FROM events WHERE correlation_id = $1 AND store_id = fmodel_store_id() ORDER BY events.offset";
         Spi::connect(|client| {
-            let mut results = Vec::new();
             let tup_table = client
                 .select(
                     query,
                     None,
                     Some(vec![(
-                        PgBuiltInOids::TEXTOID.oid(),
-                        event.identifier().to_string().into_datum(),
+                        PgBuiltInOids::UUIDOID.oid(),
+                        Uuid::from_bytes(correlation_id.into_bytes()).into_datum(),
                     )]),
                 )
-                .map_err(|err| ErrorMessage {
-                    message: "Failed to fetch latest event / version: ".to_string()
-                        + &err.to_string(),
-                })?;
-            for row in tup_table {
-                let event_id = row["event_id"]
-                    .value::<Uuid>()
-                    .map_err(|err| ErrorMessage {
-                        message: "Failed to fetch latest event id (map `event_id` to `Uuid`): "
-                            .to_string()
-                            + &err.to_string(),
-                    })?
-                    .ok_or(ErrorMessage {
-                        message:
-                        "Failed to fetch latest event id (map `data` to `JsonB`): No event id found"
-                            .to_string(),
-                    })?;
-                results.push(UUID::from_bytes(*event_id.as_bytes()));
-            }
-            Ok(results.first().cloned())
+                .map_err(|err| ErrorMessage::spi("fetch events", None, &err))?;
+            to_events_with_positions(tup_table)
         })
     }
-    /// Saves events.
-    fn save(&self, events: &[E]) -> Result<Vec<(E, UUID)>, ErrorMessage> {
+
+    /// Fetches the events tagged with the tag, after the offset, in the order they were appended (at most `limit` events).
+    /// Served by the GIN index on the `tags`.
+    fn fetch_events_by_tag(
+        &self,
+        tag: &str,
+        after_offset: i64,
+        limit: i64,
+    ) -> Result<Vec<(E, EventPosition)>, ErrorMessage> {
+        let query = "SELECT * FROM events WHERE tags @> ARRAY[$1] AND store_id = fmodel_store_id() AND events.offset > $2 ORDER BY events.offset LIMIT $3";
+        Spi::connect(|client| {
+            let tup_table = client
+                .select(
+                    query,
+                    None,
+                    Some(vec![
+                        (PgBuiltInOids::TEXTOID.oid(), tag.into_datum()),
+                        (PgBuiltInOids::INT8OID.oid(), after_offset.into_datum()),
+                        (PgBuiltInOids::INT8OID.oid(), limit.into_datum()),
+                    ]),
+                )
+                .map_err(|err| ErrorMessage::spi("fetch events by tag", None, &err))?;
+            to_events_with_positions(tup_table)
+        })
+    }
+
+    /// Fetches the latest version of the event stream to which the event belongs.
+    fn fetch_latest_version(&self, event: &E) -> Result<Option<UUID>, ErrorMessage> {
+        self.fetch_decider_stream_version(&event.decider_type(), &event.identifier())
+    }
+
+    /// Fetches the latest version of the event stream of the decider of the given type (the head of the stream).
+    fn fetch_decider_stream_version(
+        &self,
+        decider: &str,
+        decider_id: &UUID,
+    ) -> Result<Option<UUID>, ErrorMessage> {
+        // Filtered by `decider` as well, to prune the `events` partitions.
+        // Only the `event_id` is selected: index-only scan on the covering `events_stream_index` (see `fmodel_index_advisory`)
+        Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT (SELECT event_id FROM events WHERE decider = $2 AND decider_id = $1 AND store_id = fmodel_store_id() ORDER BY events.offset DESC LIMIT 1)",
+                    Some(1),
+                    Some(vec![
+                        (
+                            PgBuiltInOids::TEXTOID.oid(),
+                            decider_id.to_string().into_datum(),
+                        ),
+                        (PgBuiltInOids::TEXTOID.oid(), decider.into_datum()),
+                    ]),
+                )?
+                .first()
+                .get_one::<Uuid>()
+        })
+        .map(|event_id| event_id.map(|event_id| UUID::from_bytes(*event_id.as_bytes())))
+        .map_err(|err| {
+            ErrorMessage::spi(
+                "fetch latest event / version",
+                Some(decider_id.to_string()),
+                &err,
+            )
+        })
+    }
+
+    /// Fetches the latest version of the event stream of the decider.
+    fn fetch_stream_version(&self, decider_id: &UUID) -> Result<Option<UUID>, ErrorMessage> {
+        // Only the `event_id` is selected: index-only scan (see `fmodel_index_advisory`)
+        Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT (SELECT event_id FROM events WHERE decider_id = $1 AND store_id = fmodel_store_id() ORDER BY events.offset DESC LIMIT 1)",
+                    Some(1),
+                    Some(vec![(
+                        PgBuiltInOids::TEXTOID.oid(),
+                        decider_id.to_string().into_datum(),
+                    )]),
+                )?
+                .first()
+                .get_one::<Uuid>()
+        })
+        .map(|event_id| event_id.map(|event_id| UUID::from_bytes(*event_id.as_bytes())))
+        .map_err(|err| {
+            ErrorMessage::spi(
+                "fetch latest event / version",
+                Some(decider_id.to_string()),
+                &err,
+            )
+        })
+    }
+
+    /// Fetches the sequence number of the last event in the event stream of the decider, or `None` if the stream is empty.
+    fn fetch_stream_sequence_number(&self, decider_id: &UUID) -> Result<Option<i64>, ErrorMessage> {
+        Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT (SELECT sequence_number FROM events WHERE decider_id = $1 AND store_id = fmodel_store_id() ORDER BY events.offset DESC LIMIT 1)",
+                    Some(1),
+                    Some(vec![(
+                        PgBuiltInOids::TEXTOID.oid(),
+                        decider_id.to_string().into_datum(),
+                    )]),
+                )?
+                .first()
+                .get_one::<i64>()
+        })
+        .map_err(|err| {
+            ErrorMessage::spi(
+                "fetch the stream sequence number",
+                Some(decider_id.to_string()),
+                &err,
+            )
+        })
+    }
+
+    /// Locks the event stream of the decider until the end of the transaction (transaction-level advisory lock).
+    /// The concurrent atomic handlers of the stream (see `handle_all_atomically`) wait for the lock, instead of saving on top of each other.
+    fn lock_stream(&self, decider_id: &UUID) -> Result<(), ErrorMessage> {
+        Spi::run_with_args(
+            "SELECT pg_advisory_xact_lock(hashtext('fmodel_streams'), hashtext($1))",
+            Some(vec![(
+                PgBuiltInOids::TEXTOID.oid(),
+                decider_id.to_string().into_datum(),
+            )]),
+        )
+        .map_err(|err| {
+            ErrorMessage::spi("lock the event stream", Some(decider_id.to_string()), &err)
+        })
+    }
+
+    /// The tags attached to the event on save (e.g. `vip-customer`, `promo:summer24`), in addition to the tags of the session (`fmodel.event_tags`).
+    /// The post-decide hook: override it to tag the events decided by the decider. No tags by default.
+    fn tags(&self, _event: &E) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Whether the event is appended to the event log: override it to keep the high-frequency (telemetry) events out of the log, e.g. downsampled, or written to another table.
+    /// The events that are not retained are not saved (and not returned by `save`, so the hooks and the state cache do not see them either). All the events are retained by default.
+    fn retain(&self, _event: &E) -> Result<bool, ErrorMessage> {
+        Ok(true)
+    }
+
+    /// Saves events, that are not decided by the command (e.g. the corrections, or the forked events): every event is its own command (`command_id` is the event id).
+    fn save(&self, events: &[E]) -> Result<Vec<(E, EventPosition)>, ErrorMessage> {
+        let events: Vec<(E, Option<UUID>)> =
+            events.iter().map(|event| (event.clone(), None)).collect();
+        self.save_commanded(&events)
+    }
+
+    /// Saves events, together with the id of the command they were decided by (`command_id`, see `command_id`); the events without the command id are their own commands.
+    /// Only the positions of the saved events are read back (not the payloads): the saved events are the in-memory events.
+    fn save_commanded(
+        &self,
+        events: &[(E, Option<UUID>)],
+    ) -> Result<Vec<(E, EventPosition)>, ErrorMessage> {
         let query = "
-        INSERT INTO events (event, event_id, decider, decider_id, data, command_id, previous_id, final)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-        RETURNING *";
+        INSERT INTO events (event, event_id, decider, decider_id, data, command_id, previous_id, final, tags)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, ARRAY(SELECT DISTINCT tag FROM unnest($9::TEXT[] || fmodel_event_tags()) AS tag ORDER BY tag))
+        RETURNING event_id, \"offset\", sequence_number";
 
         Spi::connect(|mut client| {
             let mut results = Vec::new();
-            for event in events {
+            for (event, command_id) in events {
+                if !self.retain(event)? {
+                    continue;
+                }
                 let data = serde_json::to_value(event).map_err(|err| ErrorMessage {
                     message: "Failed to save event! Failed to serialize event data/payload: "
                         .to_string()
                         + &err.to_string(),
+                    context: None,
                 })?;
+                #[cfg(any(test, feature = "pg_test"))]
+                fault_injection::inject_save_fault(&event.identifier().to_string())?;
+                #[cfg(any(test, feature = "pg_test"))]
+                fault_injection::inject_serialization_failure(&event.identifier().to_string());
                 let version = self.fetch_latest_version(event)?;
                 let event_id: UUID = UUID::new_v4();
+                let command_id = command_id.unwrap_or(event_id);
                 let tup_table = client
                     .update(
                         query,
@@ -276,7 +449,7 @@ where
                             (PgBuiltInOids::JSONBOID.oid(), JsonB(data).into_datum()),
                             (
                                 PgBuiltInOids::UUIDOID.oid(),
-                                event_id.to_string().into_datum(),
+                                command_id.to_string().into_datum(),
                             ),
                             (
                                 PgBuiltInOids::UUIDOID.oid(),
@@ -285,34 +458,186 @@ where
                                     .into_datum(),
                             ),
                             (PgBuiltInOids::BOOLOID.oid(), event.is_final().into_datum()),
+                            (
+                                PgBuiltInOids::TEXTARRAYOID.oid(),
+                                self.tags(event).into_datum(),
+                            ),
                         ]),
                     )
-                    .map_err(|err| ErrorMessage {
-                        message: "Failed to save event: ".to_string() + &err.to_string(),
+                    .map_err(|err| {
+                        ErrorMessage::spi("save event", Some(event.identifier().to_string()), &err)
                     })?;
 
                 for row in tup_table {
-                    let data = row["data"].value::<JsonB>().map_err(|err| ErrorMessage {
-                        message: "Failed to save event data/payload (map `data` to `JsonB`): ".to_string() + &err.to_string(),
-                    })?.ok_or(ErrorMessage {
-                        message: "Failed to save event data/payload (map `data` to `JsonB`): No data/payload found".to_string(),
-                    })?;
-                    let event_id = row["event_id"]
-                        .value::<Uuid>()
-                        .map_err(|err| ErrorMessage {
-                            message: "Failed to save event id (map `event_id` to `Uuid`): "
-                                .to_string()
-                                + &err.to_string(),
-                        })?
-                        .ok_or(ErrorMessage {
-                            message:
-                                "Failed to save event id (map `data` to `JsonB`): No event id found"
-                                    .to_string(),
-                        })?;
-                    results.push((to_payload(data)?, UUID::from_bytes(*event_id.as_bytes())));
+                    results.push((event.clone(), to_event_position(&row, &event.identifier())?));
                 }
             }
             Ok(results)
         })
     }
 }
+
+/// The id of the command: the UUID of the MD5 of its canonical JSON (`fmodel_canonical_json`), so the command delivered again (e.g. by the at-least-once upstream pipeline) has the same id.
+/// The events decided by the command (including the events of the saga reacting to them) are saved with its id (`events.command_id`), and deduplicated by it (see `fmodel.deduplication`).
+pub fn command_id<C: Serialize + Identifier>(command: &C) -> Result<UUID, ErrorMessage> {
+    let data = serde_json::to_value(command).map_err(|err| ErrorMessage {
+        message: "Failed to serialize the command: ".to_string() + &err.to_string(),
+        context: None,
+    })?;
+    Spi::get_one_with_args::<Uuid>(
+        "SELECT md5(fmodel_canonical_json($1))::UUID",
+        vec![(PgBuiltInOids::JSONBOID.oid(), JsonB(data).into_datum())],
+    )
+    .map_err(|err| {
+        ErrorMessage::spi(
+            "compute the command id",
+            Some(command.identifier().to_string()),
+            &err,
+        )
+    })?
+    .map(|command_id| UUID::from_bytes(*command_id.as_bytes()))
+    .ok_or(ErrorMessage {
+        message: "Failed to compute the command id".to_string(),
+        context: None,
+    })
+}
+
+/// Maps the row returned by the insert (`event_id`, `offset` and `sequence_number`) to the position of the saved event.
+fn to_event_position(
+    row: &SpiHeapTupleData<'_>,
+    decider_id: &UUID,
+) -> Result<EventPosition, ErrorMessage> {
+    let to_error = |err: pgrx::spi::Error| {
+        ErrorMessage::spi("save event position", Some(decider_id.to_string()), &err)
+    };
+    match (
+        row["event_id"].value::<Uuid>().map_err(to_error)?,
+        row["offset"].value::<i64>().map_err(to_error)?,
+        row["sequence_number"].value::<i64>().map_err(to_error)?,
+    ) {
+        (Some(event_id), Some(offset), Some(sequence_number)) => Ok(EventPosition {
+            event_id: UUID::from_bytes(*event_id.as_bytes()),
+            offset,
+            sequence_number,
+        }),
+        _ => Err(ErrorMessage {
+            message: "Failed to save event position: No event id, offset or sequence number found"
+                .to_string(),
+            context: None,
+        }),
+    }
+}
+
+/// Maps the fetched rows to the events, together with their versions (event ids).
+/// On the command side (`command_side`), the events of unknown types are never skipped, see `to_command_side_event`.
+fn to_events_with_versions<E: DeserializeOwned>(
+    tup_table: SpiTupleTable<'_>,
+    command_side: bool,
+) -> Result<Vec<(E, UUID)>, ErrorMessage> {
+    let mut results = Vec::new();
+    for row in tup_table {
+        let data = row["data"]
+            .value::<JsonB>()
+            .map_err(|err| {
+                ErrorMessage::spi(
+                    "fetch event data/payload (map `data` to `JsonB`)",
+                    None,
+                    &err,
+                )
+            })?
+            .ok_or(ErrorMessage {
+                message: "Failed to fetch event data/payload (map `data` to `JsonB`): No data/payload found".to_string(),
+                context: None,
+            })?;
+        let event_id = row["event_id"]
+            .value::<Uuid>()
+            .map_err(|err| {
+                ErrorMessage::spi("fetch event id (map `event_id` to `Uuid`)", None, &err)
+            })?
+            .ok_or(ErrorMessage {
+                message: "Failed to fetch event id (map `data` to `JsonB`): No event id found"
+                    .to_string(),
+                context: None,
+            })?;
+
+        let event_id = UUID::from_bytes(*event_id.as_bytes());
+        let event = if command_side {
+            Some(to_command_side_event(data)?)
+        } else {
+            // Events of unknown types are skipped, if configured so
+            to_known_event(data)?
+        };
+        if let Some(event) = event {
+            results.push((event, event_id));
+        }
+    }
+    Ok(results)
+}
+
+/// Maps the fetched rows to the events, together with their positions in the event store.
+fn to_events_with_positions<E: DeserializeOwned>(
+    tup_table: SpiTupleTable<'_>,
+) -> Result<Vec<(E, EventPosition)>, ErrorMessage> {
+    let mut results = Vec::new();
+    for row in tup_table {
+        let data = row["data"]
+            .value::<JsonB>()
+            .map_err(|err| {
+                ErrorMessage::spi(
+                    "fetch event data/payload (map `data` to `JsonB`)",
+                    None,
+                    &err,
+                )
+            })?
+            .ok_or(ErrorMessage {
+                message: "Failed to fetch event data/payload (map `data` to `JsonB`): No data/payload found".to_string(),
+                context: None,
+            })?;
+        let event_id = row["event_id"]
+            .value::<Uuid>()
+            .map_err(|err| {
+                ErrorMessage::spi("fetch event id (map `event_id` to `Uuid`)", None, &err)
+            })?
+            .ok_or(ErrorMessage {
+                message: "Failed to fetch event id (map `event_id` to `Uuid`): No event id found"
+                    .to_string(),
+                context: None,
+            })?;
+        let offset = row["offset"]
+            .value::<i64>()
+            .map_err(|err| {
+                ErrorMessage::spi("fetch event offset (map `offset` to `i64`)", None, &err)
+            })?
+            .ok_or(ErrorMessage {
+                message: "Failed to fetch event offset (map `offset` to `i64`): No offset found"
+                    .to_string(),
+                context: None,
+            })?;
+        let sequence_number = row["sequence_number"]
+            .value::<i64>()
+            .map_err(|err| {
+                ErrorMessage::spi(
+                    "fetch event sequence number (map `sequence_number` to `i64`)",
+                    None,
+                    &err,
+                )
+            })?
+            .ok_or(ErrorMessage {
+                message: "Failed to fetch event sequence number (map `sequence_number` to `i64`): No sequence number found"
+                    .to_string(),
+                context: None,
+            })?;
+        // Events of unknown types are skipped, if configured so
+        if let Some(event) = to_known_event(data)? {
+            results.push((
+                event,
+                EventPosition {
+                    event_id: UUID::from_bytes(*event_id.as_bytes()),
+                    offset,
+                    sequence_number,
+                },
+            ));
+        }
+    }
+    Ok(results)
+}
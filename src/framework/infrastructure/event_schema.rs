@@ -0,0 +1,59 @@
+use crate::framework::infrastructure::errors::ErrorMessage;
+use pgrx::Spi;
+
+/// The schema of the event type, as the deserializer of the events expects it: the top-level fields of the JSONB `data` that must be present.
+pub struct EventSchema {
+    /// The event name/type, e.g. `RestaurantCreated`
+    pub event: String,
+    /// The required (top-level) fields of the payload, besides the `type` tag. The optional fields (`Option`, `#[serde(default)]`) are not listed
+    pub required_fields: Vec<String>,
+}
+
+/// Generates the `check_event_schema` trigger function: the payload `type` must match the `event` column, and the payload must have the required fields of its event type.
+/// The event types without the schema (e.g. of the other deciders) are checked for the `type` only.
+fn check_event_schema_sql(schemas: &[EventSchema]) -> String {
+    let mut sql = r#"CREATE OR REPLACE FUNCTION check_event_schema() RETURNS trigger AS
+$check$
+    DECLARE
+        missing TEXT[];
+    BEGIN
+        IF jsonb_typeof(NEW."data") IS DISTINCT FROM 'object' OR NEW."data" ->> 'type' IS DISTINCT FROM NEW."event" THEN
+            RAISE EXCEPTION 'The payload type (%) does not match the event type (%)', NEW."data" ->> 'type', NEW."event"
+                USING ERRCODE = 'check_violation';
+        END IF;
+        missing := ARRAY(SELECT field FROM unnest(CASE NEW."event""#
+        .to_string();
+    for schema in schemas {
+        let fields = schema
+            .required_fields
+            .iter()
+            .map(|field| "'".to_string() + field + "'")
+            .collect::<Vec<_>>()
+            .join(", ");
+        sql +=
+            &("\n            WHEN '".to_string() + &schema.event + "' THEN ARRAY[" + &fields + "]");
+    }
+    sql += r#"
+            ELSE ARRAY[]::TEXT[] END) AS field WHERE NOT NEW."data" ? field);
+        IF cardinality(missing) > 0 THEN
+            RAISE EXCEPTION 'The % payload is missing the required fields: %', NEW."event", array_to_string(missing, ', ')
+                USING ERRCODE = 'check_violation';
+        END IF;
+        RETURN NEW;
+    END;
+$check$
+    LANGUAGE plpgsql"#;
+    sql
+}
+
+/// (Re)creates the `check_event_schema` trigger function, and the trigger checking every event inserted into the `events` table (by the extension, or by other tooling, e.g. the migrations and the manual fixes).
+/// The trigger name sorts before `t_offload_event_payload`: the full payload is checked, before it is offloaded.
+pub fn create_event_schema_check(schemas: &[EventSchema]) -> Result<(), ErrorMessage> {
+    let to_error = |err| ErrorMessage::spi("create the event schema check", None, &err);
+    Spi::run(&check_event_schema_sql(schemas)).map_err(to_error)?;
+    Spi::run("DROP TRIGGER IF EXISTS t_check_event_schema ON events").map_err(to_error)?;
+    Spi::run(
+        "CREATE TRIGGER t_check_event_schema BEFORE INSERT ON events FOR EACH ROW EXECUTE FUNCTION check_event_schema()",
+    )
+    .map_err(to_error)
+}
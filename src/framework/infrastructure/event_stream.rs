@@ -0,0 +1,42 @@
+use crate::framework::infrastructure::errors::ErrorMessage;
+use pgrx::Spi;
+
+/// A column of the `fmodel_event_stream` view, decoded from the JSONB `data` of the events.
+pub struct EventStreamColumn {
+    /// The name of the column
+    pub name: &'static str,
+    /// The SQL type of the column (the decoded JSON text is cast to it)
+    pub sql_type: &'static str,
+    /// The event types carrying the column, and the (top-level) field of the event payload it is decoded from. `NULL` for the other event types
+    pub fields: &'static [(&'static str, &'static str)],
+}
+
+/// Generates the `fmodel_event_stream` view: the events with the metadata (type, decider, sequence number, final, correlation id, timestamp), and the columns decoded from the payload.
+fn event_stream_view_sql(columns: &[EventStreamColumn]) -> String {
+    let mut sql = r#"CREATE OR REPLACE VIEW fmodel_event_stream AS
+SELECT e."offset",
+       e."event"           AS event_type,
+       e."decider",
+       e."decider_id",
+       e."sequence_number",
+       e."final",
+       e."correlation_id",
+       e."created_at""#
+        .to_string();
+    for column in columns {
+        sql += ",\n       (CASE e.\"event\"";
+        for (event, field) in column.fields {
+            sql += &(" WHEN '".to_string() + event + "' THEN d.\"data\" ->> '" + field + "'");
+        }
+        sql += &(" END)::".to_string() + column.sql_type + " AS \"" + column.name + "\"");
+    }
+    // The offloaded payloads are rehydrated (see `fmodel.payload_offload_threshold`)
+    sql += "\nFROM events e, LATERAL (SELECT fmodel_event_data(e.\"event_id\", e.\"data\") AS \"data\") d";
+    sql
+}
+
+/// (Re)creates the `fmodel_event_stream` view, with the columns decoded from the event payloads.
+pub fn create_event_stream_view(columns: &[EventStreamColumn]) -> Result<(), ErrorMessage> {
+    Spi::run(&event_stream_view_sql(columns))
+        .map_err(|err| ErrorMessage::spi("create the event stream view", None, &err))
+}
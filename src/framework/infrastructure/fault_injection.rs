@@ -0,0 +1,90 @@
+use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::framework::infrastructure::guc::{
+    FAIL_FETCH_PROBABILITY, FAIL_SAVE_AFTER_N, FAIL_SERIALIZATION_N,
+};
+use pgrx::prelude::*;
+use pgrx::{register_xact_callback, PgXactCallbackEvent, Spi};
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+
+/// The number of the events saved in the current transaction (of this backend), for `fmodel.fail_save_after_n`.
+static SAVED: AtomicI32 = AtomicI32::new(0);
+
+/// The indicator if the counter of the saved events is reset when the current transaction ends.
+static RESET_PENDING: AtomicBool = AtomicBool::new(false);
+
+/// The number of the saves failed with the serialization failure (in this backend), and the `fmodel.fail_serialization_n` they were failed for.
+/// The counter survives the rollback of the failed attempt, so the retry of the command (in the new transaction) is not failed again.
+static SERIALIZATION_FAILURES: AtomicI32 = AtomicI32::new(0);
+static SERIALIZATION_FAILURES_OF: AtomicI32 = AtomicI32::new(0);
+
+/// Fails the save of the event once `fmodel.fail_save_after_n` events were saved in the current transaction (`-1` disables the fault).
+/// The events saved before the fault are rolled back with the transaction, so the tests can verify the atomicity of the command handling.
+pub fn inject_save_fault(decider_id: &str) -> Result<(), ErrorMessage> {
+    let fail_after = FAIL_SAVE_AFTER_N.get();
+    if fail_after < 0 {
+        return Ok(());
+    }
+    if !RESET_PENDING.swap(true, Ordering::Relaxed) {
+        register_xact_callback(PgXactCallbackEvent::Commit, || {
+            SAVED.store(0, Ordering::Relaxed);
+            RESET_PENDING.store(false, Ordering::Relaxed);
+        });
+        register_xact_callback(PgXactCallbackEvent::Abort, || {
+            SAVED.store(0, Ordering::Relaxed);
+            RESET_PENDING.store(false, Ordering::Relaxed);
+        });
+    }
+    if SAVED.fetch_add(1, Ordering::Relaxed) < fail_after {
+        return Ok(());
+    }
+    Err(ErrorMessage {
+        message: "Failed to save event (stream: ".to_string()
+            + decider_id
+            + "): injected fault, `fmodel.fail_save_after_n` = "
+            + &fail_after.to_string(),
+        context: None,
+    })
+}
+
+/// Fails the fetch of the events with the probability `fmodel.fail_fetch_probability` (`0` disables the fault).
+/// The fault is drawn by the Postgres `random()`, so the tests can make it deterministic with `setseed`.
+pub fn inject_fetch_fault(decider_id: &str) -> Result<(), ErrorMessage> {
+    let probability = FAIL_FETCH_PROBABILITY.get();
+    if probability <= 0.0 {
+        return Ok(());
+    }
+    let draw = Spi::get_one::<f64>("SELECT random()")
+        .map_err(|err| ErrorMessage::spi("fetch events", Some(decider_id.to_string()), &err))?
+        .unwrap_or_default();
+    if draw >= probability {
+        return Ok(());
+    }
+    Err(ErrorMessage {
+        message: "Failed to fetch events (stream: ".to_string()
+            + decider_id
+            + "): injected fault, `fmodel.fail_fetch_probability` = "
+            + &probability.to_string(),
+        context: None,
+    })
+}
+
+/// Fails the save of the events with the serialization failure (SQLSTATE `40001`), until `fmodel.fail_serialization_n` saves were failed in this session (`0` disables the fault).
+/// Setting it to another number starts over.
+pub fn inject_serialization_failure(decider_id: &str) {
+    let fail_n = FAIL_SERIALIZATION_N.get();
+    if SERIALIZATION_FAILURES_OF.swap(fail_n, Ordering::Relaxed) != fail_n {
+        SERIALIZATION_FAILURES.store(0, Ordering::Relaxed);
+    }
+    if SERIALIZATION_FAILURES.load(Ordering::Relaxed) >= fail_n {
+        return;
+    }
+    SERIALIZATION_FAILURES.fetch_add(1, Ordering::Relaxed);
+    ereport!(
+        PgLogLevel::ERROR,
+        PgSqlErrorCode::ERRCODE_T_R_SERIALIZATION_FAILURE,
+        format!(
+            "Failed to save event (stream: {}): injected serialization failure, `fmodel.fail_serialization_n` = {}",
+            decider_id, fail_n
+        )
+    );
+}
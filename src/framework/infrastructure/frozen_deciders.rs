@@ -0,0 +1,81 @@
+use crate::framework::infrastructure::errors::ErrorMessage;
+use pgrx::{IntoDatum, PgBuiltInOids, Spi};
+
+// The freeze of the decider type and the check of the command are serialized by the (transaction-level) advisory lock of the decider type:
+// the freeze waits for the commands in flight to commit (or roll back), and the commands handled after it see the frozen decider type.
+const LOCK_DECIDER_TYPE: &str =
+    "SELECT pg_advisory_xact_lock(hashtext('fmodel_frozen_deciders'), hashtext($1))";
+const LOCK_DECIDER_TYPE_SHARED: &str =
+    "SELECT pg_advisory_xact_lock_shared(hashtext('fmodel_frozen_deciders'), hashtext($1))";
+
+/// Freezes the decider type (e.g. `Restaurant`): the new commands of the decider type are rejected until it is unfrozen.
+/// Returns `false` if the decider type was frozen already.
+pub fn freeze(decider: &str) -> Result<bool, ErrorMessage> {
+    let known = Spi::get_one_with_args::<bool>(
+        "SELECT EXISTS(SELECT 1 FROM deciders WHERE decider = $1)",
+        vec![(PgBuiltInOids::TEXTOID.oid(), decider.into_datum())],
+    )
+    .map_err(|err| ErrorMessage::spi("freeze the decider", Some(decider.to_string()), &err))?;
+    if known != Some(true) {
+        return Err(ErrorMessage {
+            message: "Failed to freeze the decider: unknown decider type `".to_string()
+                + decider
+                + "`",
+            context: None,
+        });
+    }
+    lock(LOCK_DECIDER_TYPE, decider)?;
+    Spi::get_one_with_args::<bool>(
+        "WITH frozen AS (INSERT INTO frozen_deciders (decider) VALUES ($1) ON CONFLICT DO NOTHING RETURNING 1)
+         SELECT EXISTS(SELECT 1 FROM frozen)",
+        vec![(PgBuiltInOids::TEXTOID.oid(), decider.into_datum())],
+    )
+    .map(|frozen| frozen == Some(true))
+    .map_err(|err| ErrorMessage::spi("freeze the decider", Some(decider.to_string()), &err))
+}
+
+/// Unfreezes the decider type: the commands of the decider type are handled again.
+/// Returns `false` if the decider type was not frozen.
+pub fn unfreeze(decider: &str) -> Result<bool, ErrorMessage> {
+    lock(LOCK_DECIDER_TYPE, decider)?;
+    Spi::get_one_with_args::<bool>(
+        "WITH unfrozen AS (DELETE FROM frozen_deciders WHERE decider = $1 RETURNING 1)
+         SELECT EXISTS(SELECT 1 FROM unfrozen)",
+        vec![(PgBuiltInOids::TEXTOID.oid(), decider.into_datum())],
+    )
+    .map(|unfrozen| unfrozen == Some(true))
+    .map_err(|err| ErrorMessage::spi("unfreeze the decider", Some(decider.to_string()), &err))
+}
+
+/// Rejects the command of the frozen decider type (see `freeze_decider`).
+/// The shared advisory lock of the decider type is held until the end of the transaction, so the decider type is not frozen while the command is handled.
+pub fn check_not_frozen(decider: &str, command_type: &str) -> Result<(), ErrorMessage> {
+    lock(LOCK_DECIDER_TYPE_SHARED, decider)?;
+    // A new statement (and snapshot, in the `READ COMMITTED` transaction): the freeze committed while waiting for the lock is visible
+    let frozen = Spi::get_one_with_args::<bool>(
+        "SELECT EXISTS(SELECT 1 FROM frozen_deciders WHERE decider = $1)",
+        vec![(PgBuiltInOids::TEXTOID.oid(), decider.into_datum())],
+    )
+    .map_err(|err| {
+        ErrorMessage::spi("check the frozen decider", Some(decider.to_string()), &err)
+    })?;
+    if frozen == Some(true) {
+        return Err(ErrorMessage {
+            message: "The command ".to_string()
+                + command_type
+                + " is rejected: the decider `"
+                + decider
+                + "` is frozen (see `unfreeze_decider`)",
+            context: None,
+        });
+    }
+    Ok(())
+}
+
+fn lock(query: &str, decider: &str) -> Result<(), ErrorMessage> {
+    Spi::run_with_args(
+        query,
+        Some(vec![(PgBuiltInOids::TEXTOID.oid(), decider.into_datum())]),
+    )
+    .map_err(|err| ErrorMessage::spi("lock the decider", Some(decider.to_string()), &err))
+}
@@ -0,0 +1,376 @@
+use pgrx::{GucContext, GucFlags, GucRegistry, GucSetting, PostgresGucEnum};
+use std::ffi::CStr;
+
+/// The database the projector background worker connects to.
+pub static PROJECTOR_DATABASE: GucSetting<Option<&'static CStr>> =
+    GucSetting::<Option<&'static CStr>>::new(Some(c"postgres"));
+
+/// The interval (in milliseconds) in which the projector background worker polls for new events.
+pub static PROJECTOR_INTERVAL_MS: GucSetting<i32> = GucSetting::<i32>::new(1000);
+
+/// The maximum number of events the projector applies to a projection in a single transaction.
+pub static PROJECTOR_BATCH_SIZE: GucSetting<i32> = GucSetting::<i32>::new(1000);
+
+/// Skip the events of unknown types/variants while fetching the events, instead of failing (e.g. during a rolling upgrade).
+pub static SKIP_UNKNOWN_EVENTS: GucSetting<bool> = GucSetting::<bool>::new(false);
+
+/// Fail the save of the event once this number of events was saved in the transaction (fault injection, test builds only). `-1` disables the fault.
+#[cfg(any(test, feature = "pg_test"))]
+pub static FAIL_SAVE_AFTER_N: GucSetting<i32> = GucSetting::<i32>::new(-1);
+
+/// The probability the fetch of the events fails (fault injection, test builds only). `0` disables the fault.
+#[cfg(any(test, feature = "pg_test"))]
+pub static FAIL_FETCH_PROBABILITY: GucSetting<f64> = GucSetting::<f64>::new(0.0);
+
+/// Fail this number of the saves with the serialization failure (fault injection, test builds only). `0` disables the fault.
+#[cfg(any(test, feature = "pg_test"))]
+pub static FAIL_SERIALIZATION_N: GucSetting<i32> = GucSetting::<i32>::new(0);
+
+/// Persist a trace row per saga reaction (the `saga_traces` table), for debugging the orchestrations.
+pub static SAGA_TRACES: GucSetting<bool> = GucSetting::<bool>::new(false);
+
+/// Cache the folded states of the decider streams in the shared memory, so the commands skip the replay of the hot streams.
+pub static STATE_CACHE: GucSetting<bool> = GucSetting::<bool>::new(false);
+
+/// The logical event store of the session (read by the `fmodel_store_id()` SQL function). If not set, the `default` store is used.
+pub static STORE_ID: GucSetting<Option<&'static CStr>> =
+    GucSetting::<Option<&'static CStr>>::new(None);
+
+/// The owner of the events appended in the session, e.g. the tenant (read by the `fmodel_owner()` SQL function). If not set, the current role is the owner.
+pub static OWNER: GucSetting<Option<&'static CStr>> =
+    GucSetting::<Option<&'static CStr>>::new(None);
+
+/// The role the users must be members of, to handle the commands. If not set, all users can handle the commands.
+pub static COMMAND_ROLE: GucSetting<Option<&'static CStr>> =
+    GucSetting::<Option<&'static CStr>>::new(None);
+
+/// The maximum rate of the commands per decider stream (commands per second). `0` disables the rate limiting.
+pub static RATE_LIMIT: GucSetting<i32> = GucSetting::<i32>::new(0);
+
+/// The maximum burst of the commands per decider stream (the capacity of the token bucket).
+pub static RATE_LIMIT_BURST: GucSetting<i32> = GucSetting::<i32>::new(10);
+
+/// The maximum number of the events the decider and the saga can produce per command. `0` disables the limit.
+pub static MAX_EVENTS_PER_COMMAND: GucSetting<i32> = GucSetting::<i32>::new(0);
+
+/// The maximum number of the events that can be fetched (replayed) to handle a command. `0` disables the limit.
+pub static MAX_REPLAYED_EVENTS: GucSetting<i32> = GucSetting::<i32>::new(0);
+
+/// The length (the number of the events) of the stream reported as long by `analyze_event_streams`. `0` disables the reporting.
+pub static LONG_STREAM_THRESHOLD: GucSetting<i32> = GucSetting::<i32>::new(1000);
+
+/// The number of the retries of the command handling that failed with the serialization failure (SQLSTATE `40001`), by `handle_with_retry`. `0` disables the retries.
+pub static SERIALIZATION_RETRIES: GucSetting<i32> = GucSetting::<i32>::new(3);
+
+/// The size (in bytes) of the event payloads offloaded to the `event_payloads` table. `0` disables the offloading.
+pub static PAYLOAD_OFFLOAD_THRESHOLD: GucSetting<i32> = GucSetting::<i32>::new(0);
+
+/// The reference currency (ISO 4217 code): the order totals are converted to it, by the rates of the `exchange_rates` table.
+pub static REFERENCE_CURRENCY: GucSetting<Option<&'static CStr>> =
+    GucSetting::<Option<&'static CStr>>::new(Some(c"USD"));
+
+/// The level of the command handling traces.
+#[derive(PostgresGucEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TraceLevel {
+    /// No traces
+    Off,
+    /// Traces are reported on the `LOG` level / to the server log
+    Log,
+    /// Traces are reported on the `NOTICE` level / to the server log and the client
+    Notice,
+}
+
+/// The level of the command handling traces (span-like records with counts and durations).
+pub static TRACE_LEVEL: GucSetting<TraceLevel> = GucSetting::<TraceLevel>::new(TraceLevel::Off);
+
+/// The deduplication of the appended events on the payload hash.
+#[derive(PostgresGucEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Deduplication {
+    /// No deduplication
+    Off,
+    /// Appending a duplicate event fails
+    Reject,
+    /// Duplicate events are silently skipped
+    Skip,
+}
+
+/// The deduplication of the appended events on the payload hash (enforced by the `check_duplicate_event` trigger).
+pub static DEDUPLICATION: GucSetting<Deduplication> =
+    GucSetting::<Deduplication>::new(Deduplication::Off);
+
+/// The handling of the events applied to the view out of order (at or before the offset of the last event applied to the view row, e.g. replayed or retried).
+#[derive(PostgresGucEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OutOfOrderEvents {
+    /// The event is skipped, so the view never regresses (monotonic projections)
+    Skip,
+    /// The event is applied anyway
+    Apply,
+    /// Applying the event fails
+    Reject,
+}
+
+/// The handling of the events applied to the materialized views out of order.
+pub static OUT_OF_ORDER_EVENTS: GucSetting<OutOfOrderEvents> =
+    GucSetting::<OutOfOrderEvents>::new(OutOfOrderEvents::Skip);
+
+/// The policy of the courier locations (the high-frequency telemetry events).
+#[derive(PostgresGucEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CourierLocationPolicy {
+    /// The locations are written to the ring-buffer table (the last locations of every courier), instead of the event log
+    Buffer,
+    /// At most one location per courier is appended to the event log in the interval, the others are dropped
+    Downsample,
+}
+
+/// The policy of the courier locations (`CourierLocationUpdated` events).
+pub static COURIER_LOCATION_POLICY: GucSetting<CourierLocationPolicy> =
+    GucSetting::<CourierLocationPolicy>::new(CourierLocationPolicy::Buffer);
+
+/// The interval (in seconds) in which at most one location per courier is appended to the event log, by the `downsample` policy.
+pub static COURIER_LOCATION_INTERVAL: GucSetting<i32> = GucSetting::<i32>::new(10);
+
+/// The number of the last locations kept per courier in the ring-buffer table, by the `buffer` policy.
+pub static COURIER_LOCATION_BUFFER_SIZE: GucSetting<i32> = GucSetting::<i32>::new(100);
+
+/// Registers the `fmodel.*` configuration parameters (GUCs).
+pub fn init() {
+    GucRegistry::define_string_guc(
+        "fmodel.projector_database",
+        "The database the projector background worker connects to.",
+        "The projector applies events to the projections in the `async` mode. The extension must be installed in this database.",
+        &PROJECTOR_DATABASE,
+        GucContext::Postmaster,
+        GucFlags::default(),
+    );
+    GucRegistry::define_int_guc(
+        "fmodel.projector_interval_ms",
+        "The interval (in milliseconds) in which the projector polls for new events.",
+        "The interval (in milliseconds) in which the projector background worker polls for new events. The worker is woken when the events appended by the `handle*` functions are committed; the poll applies the events appended otherwise.",
+        &PROJECTOR_INTERVAL_MS,
+        10,
+        i32::MAX,
+        GucContext::Sighup,
+        GucFlags::UNIT_MS,
+    );
+    GucRegistry::define_int_guc(
+        "fmodel.projector_batch_size",
+        "The maximum number of events the projector applies in a single transaction.",
+        "The maximum number of events the projector applies to a projection in a single transaction.",
+        &PROJECTOR_BATCH_SIZE,
+        1,
+        i32::MAX,
+        GucContext::Sighup,
+        GucFlags::default(),
+    );
+    GucRegistry::define_bool_guc(
+        "fmodel.skip_unknown_events",
+        "Skip the events of unknown types while fetching the events.",
+        "Events of types unknown to this version of the extension (e.g. during a rolling upgrade) are skipped by the command and event handlers, instead of failing.",
+        &SKIP_UNKNOWN_EVENTS,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+    GucRegistry::define_enum_guc(
+        "fmodel.courier_location_policy",
+        "The policy of the courier locations: `buffer` or `downsample`.",
+        "The courier locations are the high-frequency telemetry events: they are written to the `courier_locations` ring-buffer table instead of the event log, or downsampled to at most one event per courier in the `fmodel.courier_location_interval`.",
+        &COURIER_LOCATION_POLICY,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+    GucRegistry::define_enum_guc(
+        "fmodel.trace_level",
+        "The level of the command handling traces: `off`, `log` or `notice`.",
+        "Command handling traces are span-like records with the command type, decider id, number of fetched/produced events and durations of fetch/decide/save.",
+        &TRACE_LEVEL,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+    GucRegistry::define_bool_guc(
+        "fmodel.saga_traces",
+        "Persist a trace row per saga reaction.",
+        "Every saga reaction (the input event, the produced commands, the resulting events, and the depth of the orchestration) is recorded in the `saga_traces` table, with the correlation id of the command handling.",
+        &SAGA_TRACES,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+    GucRegistry::define_bool_guc(
+        "fmodel.state_cache",
+        "Cache the folded states of the decider streams in the shared memory.",
+        "The command skips the replay of the event stream if the state of the stream is cached at the head (the last event) of the stream. The states are shared by all the backends, and the least recently used state is evicted. Requires the extension to be loaded via `shared_preload_libraries`.",
+        &STATE_CACHE,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+    GucRegistry::define_enum_guc(
+        "fmodel.deduplication",
+        "The deduplication of the appended events on the payload hash: `off`, `reject` or `skip`.",
+        "Appending an event with the same payload (SHA-256 of the canonical JSONB) as an event already in the decider stream is rejected or silently skipped.",
+        &DEDUPLICATION,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+    GucRegistry::define_enum_guc(
+        "fmodel.out_of_order_events",
+        "The handling of the events applied to the materialized views out of order: `skip`, `apply` or `reject`.",
+        "The event at or before the offset of the last event applied to the view row (e.g. replayed or retried) is skipped (the view never regresses), applied anyway, or rejected.",
+        &OUT_OF_ORDER_EVENTS,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+    GucRegistry::define_string_guc(
+        "fmodel.store_id",
+        "The logical event store the events are appended to and read from.",
+        "The stores keep fully isolated event streams in one database (e.g. per bounded context). If not set, the `default` store is used.",
+        &STORE_ID,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+    GucRegistry::define_string_guc(
+        "fmodel.owner",
+        "The owner of the appended events (e.g. the tenant), and of the projection rows they create.",
+        "Keys the row-level security of the projections (see `set_projection_row_security`). Set by the superuser (e.g. per role, with `ALTER ROLE ... SET`), so the users can not impersonate the other owners. If not set, the current role is the owner.",
+        &OWNER,
+        GucContext::Suset,
+        GucFlags::default(),
+    );
+    GucRegistry::define_string_guc(
+        "fmodel.command_role",
+        "The role the users must be members of, to handle the commands.",
+        "Enforced by the authorization middleware. If not set, all users can handle the commands.",
+        &COMMAND_ROLE,
+        GucContext::Suset,
+        GucFlags::default(),
+    );
+    GucRegistry::define_string_guc(
+        "fmodel.reference_currency",
+        "The reference currency (ISO 4217 code) the order totals are converted to.",
+        "The total of the order placed from the menu priced in another currency is converted by the current rate of the `exchange_rates` table, and recorded in `OrderCreated`.",
+        &REFERENCE_CURRENCY,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+    GucRegistry::define_int_guc(
+        "fmodel.rate_limit",
+        "The maximum rate of the commands per decider stream (commands per second). `0` disables the rate limiting.",
+        "Enforced by the rate limiting middleware (token bucket per decider stream, in the shared memory). Requires the extension to be loaded via `shared_preload_libraries`.",
+        &RATE_LIMIT,
+        0,
+        i32::MAX,
+        GucContext::Sighup,
+        GucFlags::default(),
+    );
+    GucRegistry::define_int_guc(
+        "fmodel.rate_limit_burst",
+        "The maximum burst of the commands per decider stream.",
+        "The capacity of the token bucket of the decider stream: the number of the commands that can be handled at once, before the rate limit applies.",
+        &RATE_LIMIT_BURST,
+        1,
+        i32::MAX,
+        GucContext::Sighup,
+        GucFlags::default(),
+    );
+    GucRegistry::define_int_guc(
+        "fmodel.max_events_per_command",
+        "The maximum number of the events produced per command. `0` disables the limit.",
+        "If the decider and the saga produce more events for a command (e.g. the pathological saga fan-out), the command handling is aborted before the events are saved.",
+        &MAX_EVENTS_PER_COMMAND,
+        0,
+        i32::MAX,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+    GucRegistry::define_int_guc(
+        "fmodel.payload_offload_threshold",
+        "The size (in bytes) of the event payloads offloaded to the `event_payloads` table. `0` disables the offloading.",
+        "The `events` row of the offloaded payload keeps the reference envelope only, keeping the hot table index-friendly. The payloads are rehydrated transparently when the events are fetched.",
+        &PAYLOAD_OFFLOAD_THRESHOLD,
+        0,
+        i32::MAX,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+    GucRegistry::define_int_guc(
+        "fmodel.max_replayed_events",
+        "The maximum number of the events replayed per command. `0` disables the limit.",
+        "If more events are fetched (replayed) to handle a command, the command handling is aborted.",
+        &MAX_REPLAYED_EVENTS,
+        0,
+        i32::MAX,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+    GucRegistry::define_int_guc(
+        "fmodel.long_stream_threshold",
+        "The length of the stream reported as long by `analyze_event_streams`. `0` disables the reporting.",
+        "The streams longer than the threshold (there are no snapshots, every command replays the whole stream) are reported as warnings, for the capacity planning.",
+        &LONG_STREAM_THRESHOLD,
+        0,
+        i32::MAX,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+    GucRegistry::define_int_guc(
+        "fmodel.courier_location_interval",
+        "The interval (in seconds) in which at most one location per courier is appended to the event log, by the `downsample` policy.",
+        "The locations reported sooner after the last location of the courier in the event log are dropped.",
+        &COURIER_LOCATION_INTERVAL,
+        0,
+        i32::MAX,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+    GucRegistry::define_int_guc(
+        "fmodel.courier_location_buffer_size",
+        "The number of the last locations kept per courier in the ring-buffer table, by the `buffer` policy.",
+        "The location overwrites the oldest slot of the courier in the `courier_locations` table, so the table does not grow with the reports.",
+        &COURIER_LOCATION_BUFFER_SIZE,
+        1,
+        i32::MAX,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+    GucRegistry::define_int_guc(
+        "fmodel.serialization_retries",
+        "The number of the retries of the command handling that failed with the serialization failure, by `handle_with_retry`. `0` disables the retries.",
+        "Under the `SERIALIZABLE` isolation, the command handling can fail with the serialization failure (SQLSTATE `40001`); it is retried in a new transaction (a new snapshot).",
+        &SERIALIZATION_RETRIES,
+        0,
+        i32::MAX,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+    #[cfg(any(test, feature = "pg_test"))]
+    {
+        GucRegistry::define_int_guc(
+            "fmodel.fail_save_after_n",
+            "Fail the save of the event once this number of events was saved in the transaction. `-1` disables the fault.",
+            "Fault injection (test builds only): the tests verify the rollback, the retries and the dead-lettering of the failed command handling deterministically.",
+            &FAIL_SAVE_AFTER_N,
+            -1,
+            i32::MAX,
+            GucContext::Userset,
+            GucFlags::default(),
+        );
+        GucRegistry::define_float_guc(
+            "fmodel.fail_fetch_probability",
+            "The probability the fetch of the events fails. `0` disables the fault.",
+            "Fault injection (test builds only): the fault is drawn by `random()`, so it is deterministic after `setseed`.",
+            &FAIL_FETCH_PROBABILITY,
+            0.0,
+            1.0,
+            GucContext::Userset,
+            GucFlags::default(),
+        );
+        GucRegistry::define_int_guc(
+            "fmodel.fail_serialization_n",
+            "Fail this number of the saves (in this session) with the serialization failure (SQLSTATE `40001`). `0` disables the fault.",
+            "Fault injection (test builds only): the tests verify the retries of `handle_with_retry` deterministically.",
+            &FAIL_SERIALIZATION_N,
+            0,
+            i32::MAX,
+            GucContext::Userset,
+            GucFlags::default(),
+        );
+    }
+}
@@ -0,0 +1,43 @@
+use crate::framework::infrastructure::errors::ErrorMessage;
+use pgrx::{JsonB, Spi};
+
+/// Fetches the health report of the event store, as a single JSON document:
+///
+/// - `extension_version` - the installed version of the extension
+/// - `latest_offset` - the offset of the latest event in the event store
+/// - `projections` - the mode, checkpoint, lag (the number of unapplied events, in the `async` mode) and the rebuild status of every projection
+/// - `oldest_unapplied_offset` - the offset of the oldest event not yet applied to all the projections, or `null`
+/// - `projector_running` - the liveness of the projector background worker
+/// - `frozen_deciders` - the frozen decider types (see `freeze_decider`)
+pub fn fetch_health() -> Result<JsonB, ErrorMessage> {
+    let query = "
+        WITH latest AS (SELECT COALESCE(MAX(events.offset), 0) AS latest_offset FROM events)
+        SELECT jsonb_build_object(
+            'extension_version', (SELECT extversion FROM pg_extension WHERE extname = 'fmodel_rust_postgres'),
+            'latest_offset', latest.latest_offset,
+            'projections', (SELECT COALESCE(jsonb_agg(jsonb_build_object(
+                                'projection', p.projection,
+                                'mode', p.mode,
+                                'checkpoint', p.checkpoint,
+                                'lag', CASE WHEN p.mode = 'async' THEN latest.latest_offset - p.checkpoint ELSE 0 END,
+                                'rebuilding', r.projection IS NOT NULL AND r.finished_at IS NULL
+                            ) ORDER BY p.projection), '[]'::JSONB)
+                            FROM projections p LEFT JOIN projection_rebuilds r ON r.projection = p.projection),
+            'oldest_unapplied_offset', (SELECT MIN(checkpoint) + 1 FROM projections
+                                        WHERE mode = 'async' AND checkpoint < latest.latest_offset),
+            'projector_running', EXISTS(SELECT 1 FROM pg_stat_activity WHERE backend_type = 'fmodel projector'),
+            'frozen_deciders', (SELECT COALESCE(jsonb_agg(decider ORDER BY decider), '[]'::JSONB) FROM frozen_deciders)
+        )
+        FROM latest";
+    Spi::connect(|client| {
+        client
+            .select(query, Some(1), None)?
+            .first()
+            .get_one::<JsonB>()
+    })
+    .map_err(|err| ErrorMessage::spi("fetch the health report", None, &err))?
+    .ok_or(ErrorMessage {
+        message: "Failed to fetch the health report: No report found".to_string(),
+        context: None,
+    })
+}
@@ -0,0 +1,85 @@
+use crate::framework::infrastructure::errors::ErrorMessage;
+use pgrx::{notice, IntoDatum, PgBuiltInOids, Spi};
+
+/// An index recommended for the event store, on top of the indexes of the bootstrap schema.
+pub struct RecommendedIndex {
+    pub name: &'static str,
+    pub definition: &'static str,
+}
+
+/// The indexes recommended for the (large) event stores. They are not part of the bootstrap schema, as building them on a populated store is expensive.
+pub const RECOMMENDED_INDEXES: [RecommendedIndex; 4] = [
+    // Covering index: the latest version of the stream is fetched by the index-only scan
+    RecommendedIndex {
+        name: "events_stream_index",
+        definition: r#"CREATE INDEX IF NOT EXISTS events_stream_index ON events ("decider", "decider_id", "offset") INCLUDE ("event_id")"#,
+    },
+    // The events of the type, in the order they were appended (e.g. the projections/analytics of a single event type)
+    RecommendedIndex {
+        name: "events_event_index",
+        definition: r#"CREATE INDEX IF NOT EXISTS events_event_index ON events ("event", "offset")"#,
+    },
+    // The final streams (the check of the closed stream on every append, and the archival)
+    RecommendedIndex {
+        name: "events_final_index",
+        definition: r#"CREATE INDEX IF NOT EXISTS events_final_index ON events ("decider", "decider_id") WHERE "final""#,
+    },
+    // Time range queries: the events are appended in the time order, so the BRIN index is tiny
+    RecommendedIndex {
+        name: "events_created_at_brin_index",
+        definition: r#"CREATE INDEX IF NOT EXISTS events_created_at_brin_index ON events USING BRIN ("created_at")"#,
+    },
+];
+
+/// Checks the recommended indexes, and creates the missing ones (if `create_missing`).
+/// Returns the name, the definition and the status (`present`, `created` or `missing`) of every recommended index.
+pub fn advise_indexes(
+    indexes: &[RecommendedIndex],
+    create_missing: bool,
+) -> Result<Vec<(String, String, String)>, ErrorMessage> {
+    let mut results = Vec::new();
+    for index in indexes {
+        let present = Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT to_regclass(quote_ident($1)) IS NOT NULL",
+                    Some(1),
+                    Some(vec![(
+                        PgBuiltInOids::TEXTOID.oid(),
+                        index.name.into_datum(),
+                    )]),
+                )?
+                .first()
+                .get_one::<bool>()
+        })
+        .map_err(|err| {
+            ErrorMessage::spi(
+                &("check the index `".to_string() + index.name + "`"),
+                None,
+                &err,
+            )
+        })?
+        .unwrap_or(false);
+        let status = if present {
+            "present"
+        } else if create_missing {
+            notice!("fmodel: creating the index {}", index.name);
+            Spi::run(index.definition).map_err(|err| {
+                ErrorMessage::spi(
+                    &("create the index `".to_string() + index.name + "`"),
+                    None,
+                    &err,
+                )
+            })?;
+            "created"
+        } else {
+            "missing"
+        };
+        results.push((
+            index.name.to_string(),
+            index.definition.to_string(),
+            status.to_string(),
+        ));
+    }
+    Ok(results)
+}
@@ -0,0 +1,103 @@
+use crate::framework::infrastructure::errors::ErrorMessage;
+use pgrx::{notice, IntoDatum, PgBuiltInOids, Spi};
+
+/// A schema migration: the idempotent SQL (e.g. `ALTER TABLE ... ADD COLUMN IF NOT EXISTS`) evolving the schema to the version.
+pub struct Migration {
+    /// The version of the schema after the migration is applied
+    pub version: i32,
+    /// The description of the migration
+    pub description: &'static str,
+    /// The SQL statements of the migration
+    pub sql: &'static str,
+}
+
+/// Applies the migrations newer than the current schema version (in the order of their versions), and returns the new schema version.
+/// Every applied migration is recorded in the `fmodel_schema_version` table, in the same transaction.
+/// Concurrent runs are serialized by the lock on the `fmodel_schema_version` table.
+pub fn migrate(migrations: &[Migration]) -> Result<i32, ErrorMessage> {
+    Spi::connect(|mut client| {
+        client.update(
+            "CREATE TABLE IF NOT EXISTS fmodel_schema_version
+             (
+                 version     INT                      NOT NULL PRIMARY KEY,
+                 description TEXT                     NOT NULL,
+                 applied_at  TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+             )",
+            None,
+            None,
+        )?;
+        client.update(
+            "LOCK TABLE fmodel_schema_version IN EXCLUSIVE MODE",
+            None,
+            None,
+        )?;
+        let mut version = client
+            .update(
+                "SELECT COALESCE(MAX(version), 0) FROM fmodel_schema_version",
+                Some(1),
+                None,
+            )?
+            .first()
+            .get_one::<i32>()?
+            .unwrap_or_default();
+        let mut pending: Vec<&Migration> =
+            migrations.iter().filter(|m| m.version > version).collect();
+        pending.sort_by_key(|m| m.version);
+        for migration in pending {
+            client.update(migration.sql, None, None)?;
+            client.update(
+                "INSERT INTO fmodel_schema_version (version, description) VALUES ($1, $2)",
+                None,
+                Some(vec![
+                    (PgBuiltInOids::INT4OID.oid(), migration.version.into_datum()),
+                    (
+                        PgBuiltInOids::TEXTOID.oid(),
+                        migration.description.into_datum(),
+                    ),
+                ]),
+            )?;
+            notice!(
+                "fmodel: applied migration {} ({})",
+                migration.version,
+                migration.description
+            );
+            version = migration.version;
+        }
+        Ok(version)
+    })
+    .map_err(|err: pgrx::spi::Error| ErrorMessage::spi("migrate the schema", None, &err))
+}
+
+/// Records the migrations as applied, without running them: the fresh schema (e.g. the provisioned event store) has the latest schema already.
+pub fn record_migrations(migrations: &[Migration]) -> Result<(), ErrorMessage> {
+    for migration in migrations {
+        Spi::run_with_args(
+            "INSERT INTO fmodel_schema_version (version, description) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+            Some(vec![
+                (PgBuiltInOids::INT4OID.oid(), migration.version.into_datum()),
+                (
+                    PgBuiltInOids::TEXTOID.oid(),
+                    migration.description.into_datum(),
+                ),
+            ]),
+        )
+        .map_err(|err| ErrorMessage::spi("record the migrations", None, &err))?;
+    }
+    Ok(())
+}
+
+/// Fetches the current schema version (`0` if no migration is applied).
+pub fn fetch_schema_version() -> Result<i32, ErrorMessage> {
+    Spi::connect(|client| {
+        client
+            .select(
+                "SELECT COALESCE(MAX(version), 0) FROM fmodel_schema_version",
+                Some(1),
+                None,
+            )?
+            .first()
+            .get_one::<i32>()
+    })
+    .map(Option::unwrap_or_default)
+    .map_err(|err| ErrorMessage::spi("fetch the schema version", None, &err))
+}
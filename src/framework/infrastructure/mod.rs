@@ -1,15 +1,209 @@
 use crate::framework::infrastructure::errors::ErrorMessage;
-use pgrx::JsonB;
+use crate::framework::infrastructure::guc::SKIP_UNKNOWN_EVENTS;
+use pgrx::{debug1, IntoDatum, JsonB, PgBuiltInOids, Spi};
 use serde::de::DeserializeOwned;
 
+pub mod aggregating_view_repository;
+pub mod canonical_json;
+pub mod clock;
+pub mod command_stats;
+pub mod correlation;
 pub mod errors;
+pub mod event_cursor;
 pub mod event_repository;
+pub mod event_schema;
+pub mod event_stream;
+#[cfg(any(test, feature = "pg_test"))]
+pub mod fault_injection;
+pub mod frozen_deciders;
+pub mod guc;
+pub mod health;
+pub mod indexes;
+pub mod migrations;
+pub mod projection_repository;
+pub mod projector_wakeup;
+pub mod provisioning;
+pub mod rate_limiter;
+pub mod saga_commands;
+pub mod saga_rules;
+pub mod saga_trace;
+pub mod schema_check;
+pub mod state_cache;
+pub mod storage_report;
+pub mod store;
+pub mod stream_statistics;
+pub mod transition_table;
 pub mod view_state_repository;
+pub mod workflow_repository;
+
+/// Fails unless the current user is a superuser: the administrative operations (e.g. the merge of the event streams) are not guarded by the command authorization.
+pub fn require_superuser(operation: &str) -> Result<(), ErrorMessage> {
+    if unsafe { pgrx::pg_sys::superuser() } {
+        Ok(())
+    } else {
+        Err(ErrorMessage {
+            message: "Failed to ".to_string() + operation + ": permission denied, superuser only",
+            context: None,
+        })
+    }
+}
 
 /// Converts a `JsonB` to the payload type.
 pub fn to_payload<E: DeserializeOwned>(jsonb: JsonB) -> Result<E, ErrorMessage> {
     let value = jsonb.0.clone();
     serde_json::from_value(value).map_err(|err| ErrorMessage {
         message: "Failed to deserialize payload: ".to_string() + &err.to_string(),
+        context: None,
+    })
+}
+
+/// Rehydrates the event data/payload offloaded to the `event_payloads` table (see `fmodel.payload_offload_threshold`).
+/// The offloaded event keeps the reference envelope only (`type`, `identifier`, `final` and `payload_ref`); other payloads are returned as they are.
+pub fn rehydrate_payload(jsonb: JsonB) -> Result<JsonB, ErrorMessage> {
+    let Some(payload_ref) = jsonb.0.get("payload_ref").and_then(|r| r.as_str()) else {
+        return Ok(jsonb);
+    };
+    let event_id = pgrx::Uuid::from_bytes(
+        *uuid::Uuid::parse_str(payload_ref)
+            .map_err(|err| ErrorMessage {
+                message: "Failed to rehydrate the event payload (invalid `payload_ref`): "
+                    .to_string()
+                    + &err.to_string(),
+                context: None,
+            })?
+            .as_bytes(),
+    );
+    Spi::connect(|client| {
+        client
+            .select(
+                "SELECT (SELECT data FROM event_payloads WHERE event_id = $1)",
+                Some(1),
+                Some(vec![(PgBuiltInOids::UUIDOID.oid(), event_id.into_datum())]),
+            )?
+            .first()
+            .get_one::<JsonB>()
+    })
+    .map_err(|err| ErrorMessage::spi("rehydrate the event payload", None, &err))?
+    .ok_or(ErrorMessage {
+        message: "Failed to rehydrate the event payload: no offloaded payload found for the event "
+            .to_string()
+            + payload_ref,
+        context: None,
     })
 }
+
+/// Converts a `JsonB` to the command type, version-tolerantly: the command of the type unknown to this version of the extension (e.g. sent by the newer client, during a mixed-version rollout) is reported with the supported command types and the extension version, instead of the `serde` error.
+pub fn to_command<C: DeserializeOwned>(
+    jsonb: JsonB,
+    command_types: &[&str],
+) -> Result<C, ErrorMessage> {
+    let command_type = jsonb
+        .0
+        .get("type")
+        .and_then(|t| t.as_str())
+        .ok_or(ErrorMessage {
+            message: "Failed to deserialize the command: the `type` is missing".to_string(),
+            context: None,
+        })?;
+    if !command_types.contains(&command_type) {
+        return Err(ErrorMessage {
+            message: "Unsupported command type `".to_string()
+                + command_type
+                + "` (extension version "
+                + env!("CARGO_PKG_VERSION")
+                + "); supported: ["
+                + &command_types.join(", ")
+                + "]",
+            context: None,
+        });
+    }
+    serde_json::from_value(jsonb.0).map_err(|err| ErrorMessage {
+        message: "Failed to deserialize the command: ".to_string() + &err.to_string(),
+        context: None,
+    })
+}
+
+/// An event of the type/variant that is unknown to this version of the extension.
+/// For example, during a rolling upgrade, new event types can exist in the event store before the extension is updated.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnknownEvent {
+    /// The event type/name (the `type` tag of the payload)
+    pub r#type: String,
+    /// The raw event data/payload
+    #[allow(dead_code)]
+    pub payload: serde_json::Value,
+}
+
+/// The event payload, deserialized leniently.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EventPayload<E> {
+    /// The event of the known type/variant
+    Known(E),
+    /// The event of the unknown type/variant
+    Unknown(UnknownEvent),
+}
+
+/// Converts a `JsonB` to the event type, leniently: the events of unknown types/variants are captured as [UnknownEvent] instead of failing.
+/// Other deserialization errors (e.g. the known event type with a malformed payload) are still reported as errors.
+/// The offloaded payloads are rehydrated first.
+pub fn to_event<E: DeserializeOwned>(jsonb: JsonB) -> Result<EventPayload<E>, ErrorMessage> {
+    let value = rehydrate_payload(jsonb)?.0;
+    match serde_json::from_value::<E>(value.clone()) {
+        Ok(event) => Ok(EventPayload::Known(event)),
+        // `serde` reports the unknown tag of the (internally tagged) enum as `unknown variant ...`
+        Err(err) if err.to_string().starts_with("unknown variant") => {
+            Ok(EventPayload::Unknown(UnknownEvent {
+                r#type: value
+                    .get("type")
+                    .and_then(|t| t.as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+                payload: value,
+            }))
+        }
+        Err(err) => Err(ErrorMessage {
+            message: "Failed to deserialize payload: ".to_string() + &err.to_string(),
+            context: None,
+        }),
+    }
+}
+
+/// Converts a `JsonB` to the event type.
+/// The events of unknown types/variants are skipped (`None`) if `fmodel.skip_unknown_events` is enabled, otherwise they are reported as errors.
+pub fn to_known_event<E: DeserializeOwned>(jsonb: JsonB) -> Result<Option<E>, ErrorMessage> {
+    to_known_event_or_skip(jsonb, SKIP_UNKNOWN_EVENTS.get())
+}
+
+/// Converts a `JsonB` to the event type, on the command side (the events the state of the decider is computed from).
+/// The events of unknown types/variants are always reported as errors, regardless of `fmodel.skip_unknown_events`:
+/// deciding on the state computed from the incomplete stream could accept the commands the full stream rejects.
+pub fn to_command_side_event<E: DeserializeOwned>(jsonb: JsonB) -> Result<E, ErrorMessage> {
+    match to_event(jsonb)? {
+        EventPayload::Known(event) => Ok(event),
+        EventPayload::Unknown(event) => Err(ErrorMessage {
+            message: "Failed to deserialize payload: unknown event type `".to_string()
+                + &event.r#type
+                + "`. The events of unknown types are not skipped when handling the commands (`fmodel.skip_unknown_events` applies to the read side only)",
+            context: None,
+        }),
+    }
+}
+
+fn to_known_event_or_skip<E: DeserializeOwned>(
+    jsonb: JsonB,
+    skip_unknown: bool,
+) -> Result<Option<E>, ErrorMessage> {
+    match to_event(jsonb)? {
+        EventPayload::Known(event) => Ok(Some(event)),
+        EventPayload::Unknown(event) if skip_unknown => {
+            debug1!("Skipping the event of unknown type `{}`", event.r#type);
+            Ok(None)
+        }
+        EventPayload::Unknown(event) => Err(ErrorMessage {
+            message: "Failed to deserialize payload: unknown event type `".to_string()
+                + &event.r#type
+                + "`. Enable `fmodel.skip_unknown_events` to skip the events of unknown types",
+            context: None,
+        }),
+    }
+}
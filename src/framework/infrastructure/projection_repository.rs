@@ -0,0 +1,550 @@
+use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::framework::infrastructure::event_repository::EventPosition;
+use crate::framework::infrastructure::to_known_event;
+use pgrx::spi::quote_identifier;
+use pgrx::{IntoDatum, JsonB, PgBuiltInOids, Spi, Uuid};
+use serde::de::DeserializeOwned;
+use std::str::FromStr;
+use uuid::Uuid as UUID;
+
+/// The mode in which the projection/materialized view is updated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProjectionMode {
+    /// The projection is updated by the trigger, in the same transaction in which the events are appended.
+    Sync,
+    /// The projection is updated by the projector (background worker), reading the events past the projection checkpoint.
+    Async,
+    /// The projection is updated by the statement-level trigger, in the same transaction in which the events are appended: all the events inserted by the statement in one pass (e.g. the bulk imports).
+    Statement,
+}
+
+impl ProjectionMode {
+    /// The name of the mode, as stored in the `projections` table.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProjectionMode::Sync => "sync",
+            ProjectionMode::Async => "async",
+            ProjectionMode::Statement => "statement",
+        }
+    }
+}
+
+impl FromStr for ProjectionMode {
+    type Err = ErrorMessage;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode {
+            "sync" => Ok(ProjectionMode::Sync),
+            "async" => Ok(ProjectionMode::Async),
+            "statement" => Ok(ProjectionMode::Statement),
+            _ => Err(ErrorMessage {
+                message: "Unknown projection mode: ".to_string()
+                    + mode
+                    + ". Supported modes are `sync`, `async` and `statement`",
+                context: None,
+            }),
+        }
+    }
+}
+
+/// The batch of events fetched past the checkpoint of the projection (see [ProjectionRepository::fetch_events]).
+#[derive(Debug)]
+pub struct EventBatch<E> {
+    /// The events, with their positions and stores. The events of unknown types are skipped, if configured so (`fmodel.skip_unknown_events`)
+    pub events: Vec<(E, EventPosition, String)>,
+    /// The offset of the last fetched event (skipped or not), or `None` if no event was fetched: the next checkpoint of the projection
+    pub last_offset: Option<i64>,
+    /// The number of the fetched events (skipped or not): the batch is full if it equals the limit
+    pub fetched: i64,
+}
+
+/// The progress of the projection rebuild.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProjectionRebuild {
+    /// The mode of the projection before the rebuild started. The projection is switched back to this mode when the rebuild is finished.
+    pub previous_mode: ProjectionMode,
+    /// The offset of the latest event in the event store, when the rebuild started
+    pub target_offset: i64,
+    /// The number of events applied to the projection so far
+    pub events_applied: i64,
+    /// Indicator if the rebuild is finished / the projection has caught up with the event store
+    pub finished: bool,
+}
+
+/// A trait for projection repositories / the registry of projections, their modes and checkpoints.
+/// The checkpoint is the offset of the last event applied to the projection by the projector.
+/// Default implementation includes fetching/saving the mode and the checkpoint, and fetching the events past the checkpoint.
+pub trait ProjectionRepository<E>
+where
+    E: DeserializeOwned,
+{
+    /// Fetches the mode of the projection.
+    fn fetch_mode(&self, projection: &str) -> Result<ProjectionMode, ErrorMessage> {
+        Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT (SELECT mode FROM projections WHERE projection = $1)",
+                    Some(1),
+                    Some(vec![(
+                        PgBuiltInOids::TEXTOID.oid(),
+                        projection.into_datum(),
+                    )]),
+                )?
+                .first()
+                .get_one::<String>()
+        })
+        .map_err(|err| ErrorMessage::spi("fetch the projection mode", None, &err))?
+        .ok_or(ErrorMessage {
+            message: "Failed to fetch the projection mode: Projection `".to_string()
+                + projection
+                + "` is not registered",
+            context: None,
+        })?
+        .parse()
+    }
+
+    /// Saves the mode and the checkpoint of the projection.
+    fn save_mode(
+        &self,
+        projection: &str,
+        mode: ProjectionMode,
+        checkpoint: i64,
+    ) -> Result<(), ErrorMessage> {
+        Spi::run_with_args(
+            "UPDATE projections SET mode = $2, checkpoint = $3 WHERE projection = $1",
+            Some(vec![
+                (PgBuiltInOids::TEXTOID.oid(), projection.into_datum()),
+                (PgBuiltInOids::TEXTOID.oid(), mode.as_str().into_datum()),
+                (PgBuiltInOids::INT8OID.oid(), checkpoint.into_datum()),
+            ]),
+        )
+        .map_err(|err| ErrorMessage::spi("save the projection mode", None, &err))
+    }
+
+    /// Fetches the checkpoint of the projection, and locks it until the end of the transaction, so only one projector can move it forward.
+    fn fetch_checkpoint(&self, projection: &str) -> Result<i64, ErrorMessage> {
+        Spi::connect(|mut client| {
+            client
+                .update(
+                    "SELECT checkpoint FROM projections WHERE projection = $1 FOR UPDATE",
+                    None,
+                    Some(vec![(
+                        PgBuiltInOids::TEXTOID.oid(),
+                        projection.into_datum(),
+                    )]),
+                )?
+                .first()
+                .get_one::<i64>()
+        })
+        .map_err(|err| ErrorMessage::spi("fetch the projection checkpoint", None, &err))?
+        .ok_or(ErrorMessage {
+            message: "Failed to fetch the projection checkpoint: Projection `".to_string()
+                + projection
+                + "` is not registered",
+            context: None,
+        })
+    }
+
+    /// Fetches the indicator if the projection has applied the event at the offset, i.e. its checkpoint is past the event.
+    /// The events are applied in the (`transaction_id`, `offset`) order (see [ProjectionRepository::fetch_events]), so the checkpoint is compared in that order as well.
+    fn fetch_applied(&self, projection: &str, offset: i64) -> Result<bool, ErrorMessage> {
+        Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT (COALESCE((SELECT transaction_id FROM events WHERE \"offset\" = p.checkpoint LIMIT 1), '0'::XID8), p.checkpoint)
+                         >= (COALESCE((SELECT transaction_id FROM events WHERE \"offset\" = $2 LIMIT 1), '0'::XID8), $2)
+                     FROM projections p WHERE p.projection = $1",
+                    Some(1),
+                    Some(vec![
+                        (PgBuiltInOids::TEXTOID.oid(), projection.into_datum()),
+                        (PgBuiltInOids::INT8OID.oid(), offset.into_datum()),
+                    ]),
+                )?
+                .first()
+                .get_one::<bool>()
+        })
+        .map_err(|err| ErrorMessage::spi("fetch the projection checkpoint", None, &err))?
+        .ok_or(ErrorMessage {
+            message: "Failed to fetch the projection checkpoint: Projection `".to_string()
+                + projection
+                + "` is not registered",
+            context: None,
+        })
+    }
+
+    /// Saves the checkpoint of the projection.
+    fn save_checkpoint(&self, projection: &str, checkpoint: i64) -> Result<(), ErrorMessage> {
+        Spi::run_with_args(
+            "UPDATE projections SET checkpoint = $2 WHERE projection = $1",
+            Some(vec![
+                (PgBuiltInOids::TEXTOID.oid(), projection.into_datum()),
+                (PgBuiltInOids::INT8OID.oid(), checkpoint.into_datum()),
+            ]),
+        )
+        .map_err(|err| ErrorMessage::spi("save the projection checkpoint", None, &err))
+    }
+
+    /// Fetches the indicator if the projection rows of the final streams are archived.
+    fn fetch_archival(&self, projection: &str) -> Result<bool, ErrorMessage> {
+        Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT (SELECT archive_final FROM projections WHERE projection = $1)",
+                    Some(1),
+                    Some(vec![(
+                        PgBuiltInOids::TEXTOID.oid(),
+                        projection.into_datum(),
+                    )]),
+                )?
+                .first()
+                .get_one::<bool>()
+        })
+        .map_err(|err| ErrorMessage::spi("fetch the projection archival", None, &err))?
+        .ok_or(ErrorMessage {
+            message: "Failed to fetch the projection archival: Projection `".to_string()
+                + projection
+                + "` is not registered",
+            context: None,
+        })
+    }
+
+    /// Enables/disables the archival of the projection rows of the final streams.
+    /// The archive table (`<projection>_archive`) must exist to enable the archival.
+    fn save_archival(&self, projection: &str, archive_final: bool) -> Result<(), ErrorMessage> {
+        let archive_table = projection.to_string() + "_archive";
+        if archive_final
+            && Spi::get_one_with_args::<bool>(
+                "SELECT to_regclass(quote_ident($1)) IS NOT NULL",
+                vec![(
+                    PgBuiltInOids::TEXTOID.oid(),
+                    archive_table.as_str().into_datum(),
+                )],
+            )
+            .map_err(|err| ErrorMessage::spi("save the projection archival", None, &err))?
+                != Some(true)
+        {
+            return Err(ErrorMessage {
+                message: "Failed to save the projection archival: Archive table `".to_string()
+                    + &archive_table
+                    + "` does not exist",
+                context: None,
+            });
+        }
+        Spi::run_with_args(
+            "UPDATE projections SET archive_final = $2 WHERE projection = $1",
+            Some(vec![
+                (PgBuiltInOids::TEXTOID.oid(), projection.into_datum()),
+                (PgBuiltInOids::BOOLOID.oid(), archive_final.into_datum()),
+            ]),
+        )
+        .map_err(|err| ErrorMessage::spi("save the projection archival", None, &err))
+    }
+
+    /// Enables/disables the row-level security of the projection table (and of its archive table, if it exists), keyed by the `owner` column of the rows.
+    /// When enabled, the roles see (and write) only the rows of their owner (`fmodel_owner()`); the owners of the tables and the superusers bypass the row-level security.
+    fn save_row_security(&self, projection: &str, enabled: bool) -> Result<(), ErrorMessage> {
+        let has_owner = |table: &str| {
+            Spi::get_one_with_args::<bool>(
+                "SELECT EXISTS(SELECT 1 FROM pg_attribute WHERE attrelid = to_regclass(quote_ident($1)) AND attname = 'owner' AND NOT attisdropped)",
+                vec![(PgBuiltInOids::TEXTOID.oid(), table.into_datum())],
+            )
+            .map(|exists| exists == Some(true))
+            .map_err(|err| {
+                ErrorMessage::spi("save the projection row security", None, &err)
+            })
+        };
+        if !has_owner(projection)? {
+            return Err(ErrorMessage {
+                message: "Failed to save the projection row security: Projection table `"
+                    .to_string()
+                    + projection
+                    + "` has no `owner` column",
+                context: None,
+            });
+        }
+        let archive_table = projection.to_string() + "_archive";
+        let mut tables = vec![projection.to_string()];
+        if has_owner(&archive_table)? {
+            tables.push(archive_table);
+        }
+        for table in tables {
+            let policy = quote_identifier(table.clone() + "_owner");
+            let table = quote_identifier(table);
+            let query = if enabled {
+                "ALTER TABLE ".to_string()
+                    + &table
+                    + " ENABLE ROW LEVEL SECURITY; DROP POLICY IF EXISTS "
+                    + &policy
+                    + " ON "
+                    + &table
+                    + "; CREATE POLICY "
+                    + &policy
+                    + " ON "
+                    + &table
+                    + " USING (owner = fmodel_owner())"
+            } else {
+                "DROP POLICY IF EXISTS ".to_string()
+                    + &policy
+                    + " ON "
+                    + &table
+                    + "; ALTER TABLE "
+                    + &table
+                    + " DISABLE ROW LEVEL SECURITY"
+            };
+            Spi::run(&query)
+                .map_err(|err| ErrorMessage::spi("save the projection row security", None, &err))?;
+        }
+        Ok(())
+    }
+
+    /// Moves the projection row of the (final) stream, in the store of the session, to the archive table (`<projection>_archive`).
+    /// The archive table has the columns of the projection table (`store_id`, `id`, `data`, `last_event_id`, `last_offset` and `owner`), and the `archived_at` timestamp.
+    fn archive(&self, projection: &str, id: &UUID) -> Result<(), ErrorMessage> {
+        let query = "WITH archived AS (DELETE FROM ".to_string()
+            + &quote_identifier(projection)
+            + " WHERE store_id = fmodel_store_id() AND id = $1 RETURNING *) INSERT INTO "
+            + &quote_identifier(projection.to_string() + "_archive")
+            + " (store_id, id, data, last_event_id, last_offset, owner, archived_at)
+             SELECT store_id, id, data, last_event_id, last_offset, owner, NOW() FROM archived
+             ON CONFLICT (store_id, id) DO UPDATE SET data = EXCLUDED.data, last_event_id = EXCLUDED.last_event_id, last_offset = EXCLUDED.last_offset, archived_at = EXCLUDED.archived_at";
+        Spi::run_with_args(
+            &query,
+            Some(vec![(
+                PgBuiltInOids::UUIDOID.oid(),
+                id.to_string().into_datum(),
+            )]),
+        )
+        .map_err(|err| ErrorMessage::spi("archive the projection row", None, &err))
+    }
+
+    /// Fetches the offset of the latest event in the event store.
+    fn fetch_latest_offset(&self) -> Result<i64, ErrorMessage> {
+        Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT COALESCE(MAX(events.offset), 0) FROM events",
+                    Some(1),
+                    None,
+                )?
+                .first()
+                .get_one::<i64>()
+        })
+        .map_err(|err| ErrorMessage::spi("fetch the latest offset", None, &err))
+        .map(|offset| offset.unwrap_or_default())
+    }
+
+    /// Fetches the offset of the last event of the finished transactions in the commit order (see [ProjectionRepository::fetch_events]): the checkpoint the projector can take over from.
+    /// The events of the transactions still in progress (at or past the horizon) are behind this checkpoint in the commit order, so the projector reads them once they are committed.
+    fn fetch_horizon_offset(&self) -> Result<i64, ErrorMessage> {
+        let query = r#"
+            WITH horizon AS (SELECT COALESCE((SELECT xip FROM pg_snapshot_xip(pg_current_snapshot()) AS xip ORDER BY xip LIMIT 1),
+                                             pg_snapshot_xmax(pg_current_snapshot())) AS transaction_id)
+            SELECT COALESCE((SELECT events.offset FROM events, horizon
+                             WHERE events.transaction_id < horizon.transaction_id
+                             ORDER BY events.transaction_id DESC, events.offset DESC
+                             LIMIT 1), 0)"#;
+        Spi::get_one::<i64>(query)
+            .map_err(|err| ErrorMessage::spi("fetch the horizon offset", None, &err))
+            .map(|offset| offset.unwrap_or_default())
+    }
+
+    /// Fetches the next batch of events (of all deciders, and of all the logical stores) past the offset, in the commit order, together with the store of each event.
+    ///
+    /// The offsets are assigned in the insertion order, so the event with the lower offset can be committed later (by the concurrent transaction), and missed by reading the events in the offset order.
+    /// Instead, the events are read in the (`transaction_id`, `offset`) order, past the transaction of the event at the offset, and only from the finished transactions:
+    /// the transaction ids lower than the oldest transaction still in progress (the horizon) are never assigned again, so no event can appear behind the checkpoint.
+    /// The events of the current transaction are read if it is older than all the transactions in progress.
+    /// The event is applied to the projection rows of its store (see `fmodel.store_id`), so the checkpoint is the position in the commit order of the whole event log.
+    /// The skipped events of unknown types are counted in the batch, so the checkpoint moves past them.
+    fn fetch_events(&self, after_offset: i64, limit: i64) -> Result<EventBatch<E>, ErrorMessage> {
+        let query = r#"
+            WITH checkpoint AS (SELECT COALESCE((SELECT transaction_id FROM events WHERE "offset" = $1 LIMIT 1), '0'::XID8) AS transaction_id),
+                 horizon AS (SELECT COALESCE((SELECT xip FROM pg_snapshot_xip(pg_current_snapshot()) AS xip ORDER BY xip LIMIT 1),
+                                             pg_snapshot_xmax(pg_current_snapshot())) AS transaction_id)
+            SELECT events.* FROM events, checkpoint, horizon
+            WHERE (events.transaction_id, events.offset) > (checkpoint.transaction_id, $1)
+              AND events.transaction_id < horizon.transaction_id
+            ORDER BY events.transaction_id, events.offset
+            LIMIT $2"#;
+        Spi::connect(|client| {
+            let mut batch = EventBatch {
+                events: Vec::new(),
+                last_offset: None,
+                fetched: 0,
+            };
+            let tup_table = client
+                .select(
+                    query,
+                    None,
+                    Some(vec![
+                        (PgBuiltInOids::INT8OID.oid(), after_offset.into_datum()),
+                        (PgBuiltInOids::INT8OID.oid(), limit.into_datum()),
+                    ]),
+                )
+                .map_err(|err| ErrorMessage::spi("fetch events", None, &err))?;
+            for row in tup_table {
+                let data = row["data"]
+                    .value::<JsonB>()
+                    .map_err(|err| {
+                        ErrorMessage::spi(
+                            "fetch event data/payload (map `data` to `JsonB`)",
+                            None,
+                            &err,
+                        )
+                    })?
+                    .ok_or(ErrorMessage {
+                        message: "Failed to fetch event data/payload (map `data` to `JsonB`): No data/payload found".to_string(),
+                        context: None,
+                    })?;
+                let event_id = row["event_id"]
+                    .value::<Uuid>()
+                    .map_err(|err| {
+                        ErrorMessage::spi("fetch event id (map `event_id` to `Uuid`)", None, &err)
+                    })?
+                    .ok_or(ErrorMessage {
+                        message:
+                            "Failed to fetch event id (map `event_id` to `Uuid`): No event id found"
+                                .to_string(),
+                        context: None,
+                    })?;
+                let offset = row["offset"]
+                    .value::<i64>()
+                    .map_err(|err| {
+                        ErrorMessage::spi("fetch event offset (map `offset` to `i64`)", None, &err)
+                    })?
+                    .ok_or(ErrorMessage {
+                        message:
+                            "Failed to fetch event offset (map `offset` to `i64`): No offset found"
+                                .to_string(),
+                        context: None,
+                    })?;
+                let sequence_number = row["sequence_number"]
+                    .value::<i64>()
+                    .map_err(|err| {
+                        ErrorMessage::spi(
+                            "fetch event sequence number (map `sequence_number` to `i64`)",
+                            None,
+                            &err,
+                        )
+                    })?
+                    .ok_or(ErrorMessage {
+                        message: "Failed to fetch event sequence number (map `sequence_number` to `i64`): No sequence number found"
+                            .to_string(),
+                        context: None,
+                    })?;
+                let store_id = row["store_id"]
+                    .value::<String>()
+                    .map_err(|err| {
+                        ErrorMessage::spi(
+                            "fetch event store id (map `store_id` to `String`)",
+                            None,
+                            &err,
+                        )
+                    })?
+                    .ok_or(ErrorMessage {
+                        message:
+                            "Failed to fetch event store id (map `store_id` to `String`): No store id found"
+                                .to_string(),
+                        context: None,
+                    })?;
+                batch.last_offset = Some(offset);
+                batch.fetched += 1;
+                // Events of unknown types are skipped, if configured so
+                if let Some(event) = to_known_event(data)? {
+                    batch.events.push((
+                        event,
+                        EventPosition {
+                            event_id: UUID::from_bytes(*event_id.as_bytes()),
+                            offset,
+                            sequence_number,
+                        },
+                        store_id,
+                    ));
+                }
+            }
+            Ok(batch)
+        })
+    }
+
+    /// Starts the rebuild of the projection: the projection table is cleared, and the projection is switched to the `async` mode with the checkpoint reset.
+    /// The name of the projection is the name of the projection table.
+    fn start_rebuild(&self, projection: &str) -> Result<(), ErrorMessage> {
+        let previous_mode = self.fetch_mode(projection)?;
+        let target_offset = self.fetch_latest_offset()?;
+        Spi::run_with_args(
+            "INSERT INTO projection_rebuilds (projection, previous_mode, target_offset) VALUES ($1, $2, $3)
+             ON CONFLICT (projection) DO UPDATE SET previous_mode = $2, target_offset = $3, events_applied = 0, started_at = NOW(), updated_at = NOW(), finished_at = NULL",
+            Some(vec![
+                (PgBuiltInOids::TEXTOID.oid(), projection.into_datum()),
+                (PgBuiltInOids::TEXTOID.oid(), previous_mode.as_str().into_datum()),
+                (PgBuiltInOids::INT8OID.oid(), target_offset.into_datum()),
+            ]),
+        )
+        .and_then(|_| Spi::run(&("DELETE FROM ".to_string() + &quote_identifier(projection))))
+        .map_err(|err| ErrorMessage::spi("start the projection rebuild", None, &err))?;
+        self.save_mode(projection, ProjectionMode::Async, 0)
+    }
+
+    /// Fetches the progress of the unfinished rebuild of the projection, if any.
+    fn fetch_rebuild(&self, projection: &str) -> Result<Option<ProjectionRebuild>, ErrorMessage> {
+        let query = "SELECT previous_mode, target_offset, events_applied FROM projection_rebuilds WHERE projection = $1 AND finished_at IS NULL";
+        Spi::connect(|client| {
+            let mut results = Vec::new();
+            let tup_table = client
+                .select(
+                    query,
+                    None,
+                    Some(vec![(
+                        PgBuiltInOids::TEXTOID.oid(),
+                        projection.into_datum(),
+                    )]),
+                )
+                .map_err(|err| ErrorMessage::spi("fetch the projection rebuild", None, &err))?;
+            for row in tup_table {
+                let (previous_mode, target_offset, events_applied) = (
+                    row["previous_mode"].value::<String>(),
+                    row["target_offset"].value::<i64>(),
+                    row["events_applied"].value::<i64>(),
+                );
+                match (previous_mode, target_offset, events_applied) {
+                    (
+                        Ok(Some(previous_mode)),
+                        Ok(Some(target_offset)),
+                        Ok(Some(events_applied)),
+                    ) => results.push(ProjectionRebuild {
+                        previous_mode: previous_mode.parse()?,
+                        target_offset,
+                        events_applied,
+                        finished: false,
+                    }),
+                    _ => {
+                        return Err(ErrorMessage {
+                            message: "Failed to fetch the projection rebuild: Invalid progress row"
+                                .to_string(),
+                            context: None,
+                        })
+                    }
+                }
+            }
+            Ok(results.into_iter().next())
+        })
+    }
+
+    /// Saves the progress of the projection rebuild.
+    fn save_rebuild(
+        &self,
+        projection: &str,
+        events_applied: i64,
+        finished: bool,
+    ) -> Result<(), ErrorMessage> {
+        Spi::run_with_args(
+            "UPDATE projection_rebuilds SET events_applied = $2, updated_at = NOW(), finished_at = CASE WHEN $3 THEN NOW() END WHERE projection = $1",
+            Some(vec![
+                (PgBuiltInOids::TEXTOID.oid(), projection.into_datum()),
+                (PgBuiltInOids::INT8OID.oid(), events_applied.into_datum()),
+                (PgBuiltInOids::BOOLOID.oid(), finished.into_datum()),
+            ]),
+        )
+        .map_err(|err| ErrorMessage::spi("save the projection rebuild", None, &err))
+    }
+}
@@ -0,0 +1,62 @@
+use pgrx::atomics::PgAtomic;
+use pgrx::shmem::PgSharedMemoryInitialization;
+use pgrx::{pg_guard, pg_shmem_init, pg_sys, register_xact_callback, PgXactCallbackEvent};
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+
+/// The `pgprocno` of the projector background worker, plus one (`0` if the projector is not running), in the shared memory.
+static PROJECTOR: PgAtomic<AtomicI32> = PgAtomic::new();
+
+/// The indicator if the shared memory is initialized (the extension is loaded via `shared_preload_libraries`).
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// The indicator if the projector is woken when the current transaction (of this backend) commits.
+static WAKE_PENDING: AtomicBool = AtomicBool::new(false);
+
+/// Initializes the projector wakeup in the shared memory. Must be called from `_PG_init`, while the shared preload libraries are loaded.
+pub fn init() {
+    pg_shmem_init!(PROJECTOR);
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Registers the current process (the projector background worker) to be woken when the new events are committed.
+pub fn register_projector() {
+    if ENABLED.load(Ordering::Relaxed) {
+        let procno = unsafe { (*pg_sys::MyProc).pgprocno };
+        PROJECTOR.get().store(procno + 1, Ordering::Relaxed);
+    }
+}
+
+/// Unregisters the projector background worker (e.g. when it stops).
+pub fn unregister_projector() {
+    if ENABLED.load(Ordering::Relaxed) {
+        PROJECTOR.get().store(0, Ordering::Relaxed);
+    }
+}
+
+/// Wakes the projector background worker when the current transaction commits, so the `async` projections apply the new events immediately (instead of on the next poll).
+/// The worker is woken once per transaction; nothing happens if the transaction is rolled back.
+pub fn wake_projector_on_commit() {
+    if !ENABLED.load(Ordering::Relaxed) || WAKE_PENDING.swap(true, Ordering::Relaxed) {
+        return;
+    }
+    register_xact_callback(PgXactCallbackEvent::Commit, || {
+        WAKE_PENDING.store(false, Ordering::Relaxed);
+        wake_projector();
+    });
+    register_xact_callback(PgXactCallbackEvent::Abort, || {
+        WAKE_PENDING.store(false, Ordering::Relaxed);
+    });
+}
+
+/// Sets the latch of the projector background worker, if it is running.
+/// The latch of the process that took over the slot of the stopped projector may be set as well: the spurious wakeup is harmless.
+fn wake_projector() {
+    let procno = PROJECTOR.get().load(Ordering::Relaxed) - 1;
+    if procno < 0 {
+        return;
+    }
+    unsafe {
+        let proc = (*pg_sys::ProcGlobal).allProcs.add(procno as usize);
+        pg_sys::SetLatch(&mut (*proc).procLatch);
+    }
+}
@@ -0,0 +1,126 @@
+use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::framework::infrastructure::migrations::{migrate, record_migrations, Migration};
+use pgrx::spi::quote_identifier;
+use pgrx::{IntoDatum, PgBuiltInOids, Spi};
+
+/// Provisions the event store (the bootstrap SQL) into the schema, and into the tablespace (if given), and applies the pending migrations. Returns the schema version.
+///
+/// - The schema is created, if it does not exist. The bootstrap SQL is run only if the schema has no `events` table yet, so the provisioning can be re-run idempotently (applying the new migrations).
+/// - The bootstrap SQL creates the latest schema, so the migrations are recorded as applied (not run) on the fresh event store.
+/// - The tables (and the indexes) are created in the tablespace; the partitions of the `events` created later inherit the tablespace of the partitioned table.
+/// - The projections of the extension (the `projection_tables`, the triggers on the `events` and the `projections` registry) are copied from the event store in the search path, so the events appended to the new store are projected into its own projection tables.
+///
+/// The extension resolves the tables by the search path: set it to the schema (e.g. `ALTER ROLE ... SET search_path`) to use the event store.
+pub fn provision(
+    schema: &str,
+    tablespace: Option<&str>,
+    bootstrap: &str,
+    migrations: &[Migration],
+    projection_tables: &[&str],
+) -> Result<i32, ErrorMessage> {
+    let provisioned = Spi::connect(|mut client| {
+        let schema_arg = || Some(vec![(PgBuiltInOids::TEXTOID.oid(), schema.into_datum())]);
+        // The schema of the event store in the search path (the extension schema), before the search path is changed
+        let source = client
+            .update(
+                "SELECT quote_ident(n.nspname) FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace WHERE c.oid = to_regclass('events')",
+                Some(1),
+                None,
+            )?
+            .first()
+            .get_one::<String>()?;
+        let target = client
+            .update("SELECT quote_ident($1)", Some(1), schema_arg())?
+            .first()
+            .get_one::<String>()?
+            .unwrap_or_default();
+        client.update(
+            &("CREATE SCHEMA IF NOT EXISTS ".to_string() + &target),
+            None,
+            None,
+        )?;
+        // The objects are created in the schema, and into the tablespace, for the rest of the transaction
+        client.update(
+            "SELECT set_config('search_path', quote_ident($1) || ', ' || current_setting('search_path'), TRUE)",
+            None,
+            schema_arg(),
+        )?;
+        if let Some(tablespace) = tablespace {
+            client.update(
+                "SELECT set_config('default_tablespace', $1, TRUE)",
+                None,
+                Some(vec![(PgBuiltInOids::TEXTOID.oid(), tablespace.into_datum())]),
+            )?;
+        }
+        let provisioned = client
+            .update(
+                "SELECT to_regclass(quote_ident($1) || '.events') IS NOT NULL",
+                Some(1),
+                schema_arg(),
+            )?
+            .first()
+            .get_one::<bool>()?
+            .unwrap_or_default();
+        if provisioned {
+            return Ok(true);
+        }
+        client.update(bootstrap, None, None)?;
+        let Some(source) = source.filter(|source| *source != target) else {
+            return Ok(false);
+        };
+        // The projection tables are created in the schema with the columns, the defaults, the constraints and the indexes of the tables they are copied from
+        for table in projection_tables {
+            client.update(
+                &("CREATE TABLE IF NOT EXISTS ".to_string()
+                    + &target
+                    + "."
+                    + &quote_identifier(table)
+                    + " (LIKE "
+                    + &source
+                    + "."
+                    + &quote_identifier(table)
+                    + " INCLUDING ALL)"),
+                None,
+                None,
+            )?;
+        }
+        let triggers = client
+            .update(
+                "SELECT regexp_replace(pg_get_triggerdef(t.oid), ' ON \\S+ ', ' ON ' || quote_ident($1) || '.events ')
+                 FROM pg_trigger t
+                 WHERE t.tgrelid = to_regclass($2) AND NOT t.tgisinternal
+                   AND NOT EXISTS (SELECT 1 FROM pg_trigger p WHERE p.tgrelid = to_regclass(quote_ident($1) || '.events') AND p.tgname = t.tgname)
+                 ORDER BY t.tgname",
+                None,
+                Some(vec![
+                    (PgBuiltInOids::TEXTOID.oid(), schema.into_datum()),
+                    (
+                        PgBuiltInOids::TEXTOID.oid(),
+                        (source.to_owned() + ".events").into_datum(),
+                    ),
+                ]),
+            )?
+            .filter_map(|row| row.get::<String>(1).ok().flatten())
+            .collect::<Vec<String>>();
+        for trigger in triggers {
+            client.update(&trigger, None, None)?;
+        }
+        client.update(
+            &("INSERT INTO ".to_string()
+                + &target
+                + ".projections (projection) SELECT projection FROM "
+                + &source
+                + ".projections ON CONFLICT DO NOTHING"),
+            None,
+            None,
+        )?;
+        Ok(false)
+    })
+    .map_err(|err: pgrx::spi::Error| {
+        ErrorMessage::spi("provision the event store", None, &err)
+    })?;
+    if !provisioned {
+        record_migrations(migrations)?;
+    }
+    migrate(migrations)
+}
@@ -0,0 +1,94 @@
+use pgrx::lwlock::PgLwLock;
+use pgrx::shmem::{PGRXSharedMemory, PgSharedMemoryInitialization};
+use pgrx::{pg_guard, pg_shmem_init, pg_sys};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// The number of the token buckets in the shared memory. The streams are hashed to the buckets; a stream evicts the other stream hashed to the same bucket.
+const BUCKETS: usize = 4096;
+
+/// The token buckets of the decider streams, in the shared memory (shared by all the backends).
+static RATE_LIMITS: PgLwLock<TokenBuckets> = PgLwLock::new();
+
+/// The indicator if the shared memory is initialized (the extension is loaded via `shared_preload_libraries`).
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// The token bucket of a decider stream.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TokenBucket {
+    /// The hash of the decider (stream) the bucket belongs to
+    key: u64,
+    /// The available tokens
+    tokens: f64,
+    /// The timestamp (in microseconds) of the last refill
+    updated_at: i64,
+}
+
+impl TokenBucket {
+    /// Creates a new, full, token bucket.
+    pub fn new(key: u64, now: i64, burst: f64) -> Self {
+        TokenBucket {
+            key,
+            tokens: burst,
+            updated_at: now,
+        }
+    }
+
+    /// Refills the bucket (`rate` tokens per second, up to `burst` tokens), and takes a token.
+    /// Returns the time until the next token is available (retry after), if there is no token available.
+    pub fn take(&mut self, now: i64, rate: f64, burst: f64) -> Result<(), Duration> {
+        let elapsed = (now - self.updated_at).max(0) as f64 / 1_000_000.0;
+        self.tokens = (self.tokens + elapsed * rate).min(burst);
+        self.updated_at = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / rate))
+        }
+    }
+}
+
+/// The fixed-size table of the token buckets.
+#[derive(Clone, Copy)]
+struct TokenBuckets {
+    buckets: [TokenBucket; BUCKETS],
+}
+
+impl Default for TokenBuckets {
+    fn default() -> Self {
+        TokenBuckets {
+            buckets: [TokenBucket::default(); BUCKETS],
+        }
+    }
+}
+
+unsafe impl PGRXSharedMemory for TokenBuckets {}
+
+/// Initializes the token buckets in the shared memory. Must be called from `_PG_init`, while the shared preload libraries are loaded.
+pub fn init() {
+    pg_shmem_init!(RATE_LIMITS);
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Takes a token from the bucket of the decider stream (`rate` tokens per second, with bursts of up to `burst` tokens).
+/// Returns the time until the next token is available (retry after), if the stream is rate limited.
+/// Rate limiting requires the shared memory; it is disabled if the extension is not loaded via `shared_preload_libraries`.
+pub fn acquire(decider: &str, decider_id: &Uuid, rate: f64, burst: f64) -> Result<(), Duration> {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+    let mut hasher = DefaultHasher::new();
+    (decider, decider_id).hash(&mut hasher);
+    let key = hasher.finish();
+    let now = unsafe { pg_sys::GetCurrentTimestamp() };
+
+    let mut buckets = RATE_LIMITS.exclusive();
+    let bucket = &mut buckets.buckets[(key % BUCKETS as u64) as usize];
+    if bucket.key != key {
+        *bucket = TokenBucket::new(key, now, burst);
+    }
+    bucket.take(now, rate, burst)
+}
@@ -0,0 +1,192 @@
+use crate::framework::domain::api::{CommandType, Identifier};
+use crate::framework::infrastructure::errors::ErrorMessage;
+use pgrx::{IntoDatum, JsonB, PgBuiltInOids, Spi, TimestampWithTimeZone};
+use serde::Serialize;
+
+/// A convenient type alias for the saga command row: the id, the correlation id, the depth of the orchestration, the input event, the command, the status, the error, the number of the handling attempts, and the time the command was issued.
+pub type SagaCommandRow = (
+    i64,
+    Option<pgrx::Uuid>,
+    i32,
+    JsonB,
+    JsonB,
+    String,
+    Option<String>,
+    i32,
+    TimestampWithTimeZone,
+);
+
+/// The status of the command issued by the saga.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SagaCommandStatus {
+    /// The command is issued, but not handled (yet)
+    Issued,
+    /// The command is handled, the resulting events are appended with the events of the originating command
+    Succeeded,
+    /// The command is rejected (see the `error`); the orchestration is stuck, until the command is re-driven
+    Failed,
+}
+
+impl SagaCommandStatus {
+    /// The status, as stored in the `saga_commands.status` column.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SagaCommandStatus::Issued => "issued",
+            SagaCommandStatus::Succeeded => "succeeded",
+            SagaCommandStatus::Failed => "failed",
+        }
+    }
+}
+
+/// Records the command issued by the saga (as `issued`), and returns the id of the record.
+/// The record is stamped with the correlation id of the command handling (the `saga_commands.correlation_id` column defaults to `fmodel.correlation_id`).
+pub fn save_saga_command<E: Serialize, C: Serialize + CommandType + Identifier>(
+    depth: i32,
+    event: &E,
+    command: &C,
+) -> Result<i64, ErrorMessage> {
+    let to_json = |value: Result<serde_json::Value, serde_json::Error>| {
+        value.map(JsonB).map_err(|err| ErrorMessage {
+            message: "Failed to serialize the saga command: ".to_string() + &err.to_string(),
+            context: None,
+        })
+    };
+    Spi::get_one_with_args::<i64>(
+        "INSERT INTO saga_commands (depth, event, command, command_type, decider_id, status) VALUES ($1, $2, $3, $4, $5, $6) RETURNING id",
+        vec![
+            (PgBuiltInOids::INT4OID.oid(), depth.into_datum()),
+            (
+                PgBuiltInOids::JSONBOID.oid(),
+                to_json(serde_json::to_value(event))?.into_datum(),
+            ),
+            (
+                PgBuiltInOids::JSONBOID.oid(),
+                to_json(serde_json::to_value(command))?.into_datum(),
+            ),
+            (
+                PgBuiltInOids::TEXTOID.oid(),
+                command.command_type().into_datum(),
+            ),
+            (
+                PgBuiltInOids::UUIDOID.oid(),
+                command.identifier().to_string().into_datum(),
+            ),
+            (
+                PgBuiltInOids::TEXTOID.oid(),
+                SagaCommandStatus::Issued.as_str().into_datum(),
+            ),
+        ],
+    )
+    .map_err(|err| ErrorMessage::spi("save the saga command", None, &err))?
+    .ok_or(ErrorMessage {
+        message: "Failed to save the saga command: no id returned".to_string(),
+        context: None,
+    })
+}
+
+/// Updates the status of the saga command (and the error, if the command failed), counting the handling attempt.
+pub fn update_saga_command_status(
+    id: i64,
+    status: SagaCommandStatus,
+    error: Option<&str>,
+) -> Result<(), ErrorMessage> {
+    Spi::run_with_args(
+        "UPDATE saga_commands SET status = $2, error = $3, attempts = attempts + 1, updated_at = NOW() WHERE id = $1",
+        Some(vec![
+            (PgBuiltInOids::INT8OID.oid(), id.into_datum()),
+            (PgBuiltInOids::TEXTOID.oid(), status.as_str().into_datum()),
+            (PgBuiltInOids::TEXTOID.oid(), error.into_datum()),
+        ]),
+    )
+    .map_err(|err| ErrorMessage::spi("update the saga command", None, &err))
+}
+
+/// Fetches the saga commands that are not succeeded (`issued` or `failed`), the oldest first: the stuck orchestrations to be re-driven.
+pub fn fetch_pending_saga_commands() -> Result<Vec<SagaCommandRow>, ErrorMessage> {
+    // Read-only SPI: the pending work is queried by the `STABLE` function(s)
+    Spi::connect(|client| {
+        let mut results = Vec::new();
+        let tup_table = client.select(
+            "SELECT id, correlation_id, depth, event, command, status, error, attempts, created_at FROM saga_commands WHERE status <> 'succeeded' ORDER BY id",
+            None,
+            None,
+        )?;
+        for row in tup_table {
+            if let (
+                Some(id),
+                Some(depth),
+                Some(event),
+                Some(command),
+                Some(status),
+                Some(attempts),
+                Some(created_at),
+            ) = (
+                row["id"].value::<i64>()?,
+                row["depth"].value::<i32>()?,
+                row["event"].value::<JsonB>()?,
+                row["command"].value::<JsonB>()?,
+                row["status"].value::<String>()?,
+                row["attempts"].value::<i32>()?,
+                row["created_at"].value::<TimestampWithTimeZone>()?,
+            ) {
+                results.push((
+                    id,
+                    row["correlation_id"].value::<pgrx::Uuid>()?,
+                    depth,
+                    event,
+                    command,
+                    status,
+                    row["error"].value::<String>()?,
+                    attempts,
+                    created_at,
+                ));
+            }
+        }
+        Ok(results)
+    })
+    .map_err(|err: pgrx::spi::Error| ErrorMessage::spi("fetch the pending saga work", None, &err))
+}
+
+/// Claims the failed saga command to be re-driven: locks the command row (until the end of the transaction), and returns the command and its correlation id.
+/// Returns `None` if the command does not exist, or is not failed (e.g. it was re-driven already, concurrently), so the command is never handled twice.
+pub fn claim_failed_saga_command(
+    id: i64,
+) -> Result<Option<(JsonB, Option<pgrx::Uuid>)>, ErrorMessage> {
+    Spi::connect(|mut client| {
+        let mut results = Vec::new();
+        let tup_table = client.update(
+            "SELECT command, correlation_id FROM saga_commands WHERE id = $1 AND status = 'failed' FOR UPDATE",
+            Some(1),
+            Some(vec![(PgBuiltInOids::INT8OID.oid(), id.into_datum())]),
+        )?;
+        for row in tup_table {
+            if let Some(command) = row["command"].value::<JsonB>()? {
+                results.push((command, row["correlation_id"].value::<pgrx::Uuid>()?));
+            }
+        }
+        Ok(results.into_iter().next())
+    })
+    .map_err(|err: pgrx::spi::Error| ErrorMessage::spi("claim the saga command", None, &err))
+}
+
+/// Fetches the ids of the failed saga commands, the oldest first, up to the limit.
+/// The commands claimed (locked) by the concurrent re-drives are skipped.
+pub fn fetch_failed_saga_command_ids(limit: i64) -> Result<Vec<i64>, ErrorMessage> {
+    Spi::connect(|mut client| {
+        let mut results = Vec::new();
+        let tup_table = client.update(
+            "SELECT id FROM saga_commands WHERE status = 'failed' ORDER BY id LIMIT $1 FOR UPDATE SKIP LOCKED",
+            None,
+            Some(vec![(PgBuiltInOids::INT8OID.oid(), limit.into_datum())]),
+        )?;
+        for row in tup_table {
+            if let Some(id) = row["id"].value::<i64>()? {
+                results.push(id);
+            }
+        }
+        Ok(results)
+    })
+    .map_err(|err: pgrx::spi::Error| {
+        ErrorMessage::spi("fetch the failed saga commands", None, &err)
+    })
+}
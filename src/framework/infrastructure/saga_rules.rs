@@ -0,0 +1,22 @@
+use crate::framework::infrastructure::errors::ErrorMessage;
+use pgrx::{IntoDatum, PgBuiltInOids, Spi};
+
+/// Checks if the saga reaction (the event type reacting with the command type) is enabled in the `saga_rules` table.
+/// The reactions without a rule are enabled (the built-in behavior of the saga).
+pub fn is_reaction_enabled(event_type: &str, command_type: &str) -> Result<bool, ErrorMessage> {
+    Spi::connect(|client| {
+        client
+            .select(
+                "SELECT NOT EXISTS(SELECT 1 FROM saga_rules WHERE event = $1 AND command = $2 AND NOT enabled)",
+                Some(1),
+                Some(vec![
+                    (PgBuiltInOids::TEXTOID.oid(), event_type.into_datum()),
+                    (PgBuiltInOids::TEXTOID.oid(), command_type.into_datum()),
+                ]),
+            )?
+            .first()
+            .get_one::<bool>()
+    })
+    .map(|enabled| enabled.unwrap_or(true))
+    .map_err(|err| ErrorMessage::spi("fetch the saga rule", None, &err))
+}
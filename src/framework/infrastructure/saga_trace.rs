@@ -0,0 +1,75 @@
+use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::framework::infrastructure::guc::SAGA_TRACES;
+use pgrx::{IntoDatum, JsonB, PgBuiltInOids, Spi, TimestampWithTimeZone};
+use serde::Serialize;
+use uuid::Uuid;
+
+/// A convenient type alias for the saga trace row: the depth of the orchestration, the input event, the produced commands, the resulting events, and the time of the reaction.
+pub type SagaTraceRow = (i32, JsonB, JsonB, JsonB, TimestampWithTimeZone);
+
+/// Saves the trace of the saga reaction, if enabled by `fmodel.saga_traces`.
+/// The trace is stamped with the correlation id of the command handling (the `saga_traces.correlation_id` column defaults to `fmodel.correlation_id`).
+pub fn save_saga_trace<E: Serialize, C: Serialize>(
+    depth: i32,
+    event: &E,
+    commands: &[C],
+    events: &[E],
+) -> Result<(), ErrorMessage> {
+    if !SAGA_TRACES.get() {
+        return Ok(());
+    }
+    let to_json = |value: Result<serde_json::Value, serde_json::Error>| {
+        value.map(JsonB).map_err(|err| ErrorMessage {
+            message: "Failed to serialize the saga trace: ".to_string() + &err.to_string(),
+            context: None,
+        })
+    };
+    Spi::run_with_args(
+        "INSERT INTO saga_traces (depth, event, commands, events) VALUES ($1, $2, $3, $4)",
+        Some(vec![
+            (PgBuiltInOids::INT4OID.oid(), depth.into_datum()),
+            (
+                PgBuiltInOids::JSONBOID.oid(),
+                to_json(serde_json::to_value(event))?.into_datum(),
+            ),
+            (
+                PgBuiltInOids::JSONBOID.oid(),
+                to_json(serde_json::to_value(commands))?.into_datum(),
+            ),
+            (
+                PgBuiltInOids::JSONBOID.oid(),
+                to_json(serde_json::to_value(events))?.into_datum(),
+            ),
+        ]),
+    )
+    .map_err(|err| ErrorMessage::spi("save the saga trace", None, &err))
+}
+
+/// Fetches the traces of the saga reactions of the correlation / business transaction, in the order they were recorded.
+pub fn fetch_saga_trace(correlation_id: &Uuid) -> Result<Vec<SagaTraceRow>, ErrorMessage> {
+    // Read-only SPI: the traces are queried by the `STABLE` function(s)
+    Spi::connect(|client| {
+        let mut results = Vec::new();
+        let tup_table = client.select(
+            "SELECT depth, event, commands, events, created_at FROM saga_traces WHERE correlation_id = $1 ORDER BY id",
+            None,
+            Some(vec![(
+                PgBuiltInOids::UUIDOID.oid(),
+                correlation_id.to_string().into_datum(),
+            )]),
+        )?;
+        for row in tup_table {
+            if let (Some(depth), Some(event), Some(commands), Some(events), Some(created_at)) = (
+                row["depth"].value::<i32>()?,
+                row["event"].value::<JsonB>()?,
+                row["commands"].value::<JsonB>()?,
+                row["events"].value::<JsonB>()?,
+                row["created_at"].value::<TimestampWithTimeZone>()?,
+            ) {
+                results.push((depth, event, commands, events, created_at));
+            }
+        }
+        Ok(results)
+    })
+    .map_err(|err: pgrx::spi::Error| ErrorMessage::spi("fetch the saga trace", None, &err))
+}
@@ -0,0 +1,101 @@
+use crate::framework::infrastructure::errors::ErrorMessage;
+use pgrx::{IntoDatum, PgBuiltInOids, Spi};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// A table the repositories assume: the columns they read and write, with their types (as formatted by `format_type`, e.g. `timestamp with time zone`).
+pub struct ExpectedTable {
+    pub name: &'static str,
+    pub columns: &'static [(&'static str, &'static str)],
+}
+
+/// The indicator if the schema was checked (and matched) by this backend.
+static CHECKED: AtomicBool = AtomicBool::new(false);
+
+/// Checks the schema once per backend (see `check_schema`): the schema does not change under the running backend, except by the upgrade of the extension.
+pub fn check_schema_once(tables: &[ExpectedTable], indexes: &[&str]) -> Result<(), ErrorMessage> {
+    if CHECKED.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+    check_schema(tables, indexes)?;
+    CHECKED.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Checks that the tables (with the columns of the expected types) and the indexes the repositories assume exist, in the search path.
+/// The mismatches are reported at once, with the hint to migrate the schema, rather than failing deep inside the SPI (e.g. with the column not found).
+pub fn check_schema(tables: &[ExpectedTable], indexes: &[&str]) -> Result<(), ErrorMessage> {
+    let mut mismatches = Vec::new();
+    for table in tables {
+        let columns = fetch_columns(table.name)?;
+        if columns.is_empty() {
+            mismatches.push("table `".to_string() + table.name + "` is missing");
+            continue;
+        }
+        for (column, expected) in table.columns {
+            match columns.iter().find(|(name, _)| name == column) {
+                None => mismatches
+                    .push("column `".to_string() + table.name + "." + column + "` is missing"),
+                Some((_, actual)) if actual != expected => mismatches.push(
+                    "column `".to_string()
+                        + table.name
+                        + "."
+                        + column
+                        + "` is `"
+                        + actual
+                        + "`, expected `"
+                        + expected
+                        + "`",
+                ),
+                Some(_) => {}
+            }
+        }
+    }
+    for index in indexes {
+        if !index_exists(index)? {
+            mismatches.push("index `".to_string() + index + "` is missing");
+        }
+    }
+    if mismatches.is_empty() {
+        return Ok(());
+    }
+    Err(ErrorMessage {
+        message: "Extension schema mismatch, run fmodel_migrate(): ".to_string()
+            + &mismatches.join("; "),
+        context: None,
+    })
+}
+
+/// Fetches the columns of the table (resolved by the search path), with their types. Empty if the table does not exist.
+fn fetch_columns(table: &str) -> Result<Vec<(String, String)>, ErrorMessage> {
+    Spi::connect(|client| {
+        let mut columns = Vec::new();
+        let tup_table = client.select(
+            "SELECT attname::TEXT AS name, format_type(atttypid, atttypmod) AS type
+             FROM pg_attribute
+             WHERE attrelid = to_regclass(quote_ident($1)) AND attnum > 0 AND NOT attisdropped",
+            None,
+            Some(vec![(PgBuiltInOids::TEXTOID.oid(), table.into_datum())]),
+        )?;
+        for row in tup_table {
+            if let (Some(name), Some(r#type)) = (
+                row["name"].value::<String>()?,
+                row["type"].value::<String>()?,
+            ) {
+                columns.push((name, r#type));
+            }
+        }
+        Ok(columns)
+    })
+    .map_err(|err: pgrx::spi::Error| {
+        ErrorMessage::spi(&("check the table `".to_string() + table + "`"), None, &err)
+    })
+}
+
+fn index_exists(index: &str) -> Result<bool, ErrorMessage> {
+    Spi::get_one_with_args::<bool>(
+        "SELECT to_regclass(quote_ident($1)) IS NOT NULL",
+        vec![(PgBuiltInOids::TEXTOID.oid(), index.into_datum())],
+    )
+    .map(|exists| exists.unwrap_or_default())
+    .map_err(|err| ErrorMessage::spi(&("check the index `".to_string() + index + "`"), None, &err))
+}
@@ -0,0 +1,131 @@
+use crate::framework::infrastructure::guc::STATE_CACHE;
+use pgrx::lwlock::PgLwLock;
+use pgrx::shmem::{PGRXSharedMemory, PgSharedMemoryInitialization};
+use pgrx::{pg_guard, pg_shmem_init, pg_sys};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::atomic::{AtomicBool, Ordering};
+use uuid::Uuid;
+
+/// The number of the cached states (the slots) in the shared memory. The least recently used state is evicted.
+const SLOTS: usize = 64;
+
+/// The maximum size (in bytes) of the serialized state. The larger states are not cached.
+const STATE_SIZE: usize = 8192;
+
+/// The states of the hot decider streams, in the shared memory (shared by all the backends).
+static STATES: PgLwLock<CachedStates> = PgLwLock::new();
+
+/// The indicator if the shared memory is initialized (the extension is loaded via `shared_preload_libraries`).
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// The folded state of a decider stream, at the version of the stream (the id of its last event).
+#[derive(Clone, Copy)]
+struct CachedState {
+    /// The hash of the decider (stream) the state belongs to
+    key: u64,
+    /// The version of the stream the state is folded at / the id of the last event of the stream
+    version: [u8; 16],
+    /// The tick of the last use, for the eviction of the least recently used state
+    used_at: u64,
+    /// The length of the serialized state
+    len: usize,
+    /// The serialized state (JSON)
+    state: [u8; STATE_SIZE],
+}
+
+impl Default for CachedState {
+    fn default() -> Self {
+        CachedState {
+            key: 0,
+            version: [0; 16],
+            used_at: 0,
+            len: 0,
+            state: [0; STATE_SIZE],
+        }
+    }
+}
+
+/// The fixed-size table of the cached states.
+#[derive(Clone, Copy)]
+struct CachedStates {
+    /// The tick, incremented on every use of the cache
+    tick: u64,
+    slots: [CachedState; SLOTS],
+}
+
+impl Default for CachedStates {
+    fn default() -> Self {
+        CachedStates {
+            tick: 0,
+            slots: [CachedState::default(); SLOTS],
+        }
+    }
+}
+
+unsafe impl PGRXSharedMemory for CachedStates {}
+
+/// Initializes the state cache in the shared memory. Must be called from `_PG_init`, while the shared preload libraries are loaded.
+pub fn init() {
+    pg_shmem_init!(STATES);
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// The indicator if the states are cached: the cache is enabled (`fmodel.state_cache`), and the shared memory is initialized.
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed) && STATE_CACHE.get()
+}
+
+/// Returns the serialized state of the decider stream, if it is cached at the version (the id of the last event of the stream).
+/// The event ids are unique, so the state cached at any other version (e.g. by the transaction rolled back, or before the concurrent append) is never returned.
+pub fn get(decider: &str, decider_id: &Uuid, version: &Uuid) -> Option<Vec<u8>> {
+    if !is_enabled() {
+        return None;
+    }
+    let key = to_key(decider, decider_id);
+    let mut states = STATES.exclusive();
+    states.tick += 1;
+    let tick = states.tick;
+    let slot = states
+        .slots
+        .iter_mut()
+        .find(|slot| slot.key == key && slot.version == *version.as_bytes())?;
+    slot.used_at = tick;
+    Some(slot.state[..slot.len].to_vec())
+}
+
+/// Caches the serialized state of the decider stream, at the version (the id of the last event of the stream).
+/// It replaces the state of the stream cached at the previous version, or evicts the least recently used state.
+pub fn put(decider: &str, decider_id: &Uuid, version: &Uuid, state: &[u8]) {
+    if !is_enabled() || state.len() > STATE_SIZE {
+        return;
+    }
+    let key = to_key(decider, decider_id);
+    let mut states = STATES.exclusive();
+    states.tick += 1;
+    let tick = states.tick;
+    let index = states
+        .slots
+        .iter()
+        .position(|slot| slot.key == key)
+        .or_else(|| {
+            states
+                .slots
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, slot)| slot.used_at)
+                .map(|(index, _)| index)
+        })
+        .unwrap_or_default();
+    let slot = &mut states.slots[index];
+    slot.key = key;
+    slot.version = *version.as_bytes();
+    slot.used_at = tick;
+    slot.len = state.len();
+    slot.state[..state.len()].copy_from_slice(state);
+}
+
+fn to_key(decider: &str, decider_id: &Uuid) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (decider, decider_id).hash(&mut hasher);
+    hasher.finish()
+}
@@ -0,0 +1,129 @@
+use crate::framework::infrastructure::errors::ErrorMessage;
+use pgrx::Spi;
+
+/// The share of the dead tuples above which the table is reported as bloated (`VACUUM` is recommended).
+const BLOAT_RATIO: f64 = 0.2;
+
+/// The share of the dead tuples above which the table is reported as heavily bloated (the rewrite, e.g. `pg_repack`, is recommended).
+const HEAVY_BLOAT_RATIO: f64 = 0.4;
+
+/// The number of the dead tuples below which the bloat is not reported (the small tables).
+const MIN_DEAD_TUPLES: i64 = 1000;
+
+/// The storage statistics of the extension table (or the partition of the extension table).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StorageStats {
+    pub relation: String,
+    /// The partitioned table, if the relation is its partition (e.g. `events` of `events_restaurant`)
+    pub parent: Option<String>,
+    /// The size of the table (the main fork), in bytes
+    pub table_bytes: i64,
+    /// The size of all the indexes of the table, in bytes
+    pub index_bytes: i64,
+    /// The size of the TOAST table (the large values, e.g. the event payloads), in bytes
+    pub toast_bytes: i64,
+    /// The estimated number of the live tuples
+    pub live_tuples: i64,
+    /// The estimated number of the dead tuples (not vacuumed yet)
+    pub dead_tuples: i64,
+    /// The share of the live tuples of the partition in all the live tuples of its partitioned table
+    pub partition_share: Option<f64>,
+    /// The indicator if the table was ever analyzed (manually, or by the autovacuum)
+    pub analyzed: bool,
+}
+
+impl StorageStats {
+    /// The share of the dead tuples (0 to 1).
+    pub fn dead_ratio(&self) -> f64 {
+        let tuples = self.live_tuples + self.dead_tuples;
+        if tuples == 0 {
+            0.0
+        } else {
+            self.dead_tuples as f64 / tuples as f64
+        }
+    }
+
+    /// The recommendations for the table: the bloat, the stale planner statistics and the TOASTed payloads.
+    pub fn recommendations(&self) -> Vec<String> {
+        let mut recommendations = Vec::new();
+        let bloat = (self.dead_ratio() * 100.0).round().to_string() + "% bloat";
+        if self.dead_tuples >= MIN_DEAD_TUPLES && self.dead_ratio() >= HEAVY_BLOAT_RATIO {
+            recommendations.push(
+                bloat
+                    + "; consider pg_repack (or VACUUM FULL, in the maintenance window) of `"
+                    + &self.relation
+                    + "`",
+            );
+        } else if self.dead_tuples >= MIN_DEAD_TUPLES && self.dead_ratio() >= BLOAT_RATIO {
+            recommendations.push(
+                bloat
+                    + "; run VACUUM of `"
+                    + &self.relation
+                    + "`, or tune its autovacuum (autovacuum_vacuum_scale_factor)",
+            );
+        }
+        if !self.analyzed && self.live_tuples > 0 {
+            recommendations
+                .push("never analyzed; run ANALYZE of `".to_string() + &self.relation + "`");
+        }
+        if self.parent.as_deref() == Some("events") && self.toast_bytes > self.table_bytes {
+            recommendations.push(
+                "the event payloads are mostly TOASTed; consider offloading them (fmodel.payload_offload_threshold)"
+                    .to_string(),
+            );
+        }
+        recommendations
+    }
+}
+
+/// Fetches the storage statistics of all the extension tables (the members of the extension), and of their partitions (e.g. `events_restaurant`).
+/// The tuple counts are the estimates of the statistics collector.
+pub fn fetch_storage_stats() -> Result<Vec<StorageStats>, ErrorMessage> {
+    let query = "
+        WITH members AS (
+            SELECT c.oid
+            FROM pg_depend d JOIN pg_class c ON c.oid = d.objid
+            WHERE d.classid = 'pg_class'::REGCLASS AND d.refclassid = 'pg_extension'::REGCLASS AND d.deptype = 'e'
+              AND d.refobjid = (SELECT oid FROM pg_extension WHERE extname = 'fmodel_rust_postgres')
+              AND c.relkind IN ('r', 'p')
+        ),
+        relations AS (
+            SELECT oid, NULL::OID AS parent FROM members
+            UNION
+            SELECT i.inhrelid, i.inhparent FROM pg_inherits i JOIN members m ON m.oid = i.inhparent
+        ),
+        stats AS (
+            SELECT r.oid, r.parent,
+                   pg_relation_size(r.oid) AS table_bytes,
+                   pg_indexes_size(r.oid) AS index_bytes,
+                   COALESCE(pg_total_relation_size(NULLIF(c.reltoastrelid, 0)), 0) AS toast_bytes,
+                   COALESCE(s.n_live_tup, 0) AS live_tuples,
+                   COALESCE(s.n_dead_tup, 0) AS dead_tuples,
+                   COALESCE(s.last_analyze, s.last_autoanalyze) IS NOT NULL AS analyzed
+            FROM relations r JOIN pg_class c ON c.oid = r.oid LEFT JOIN pg_stat_all_tables s ON s.relid = r.oid
+        )
+        SELECT oid::REGCLASS::TEXT AS relation, parent::REGCLASS::TEXT AS parent, table_bytes, index_bytes, toast_bytes, live_tuples, dead_tuples, analyzed,
+               CASE WHEN parent IS NOT NULL
+                    THEN live_tuples::FLOAT8 / NULLIF(sum(live_tuples) OVER (PARTITION BY parent), 0)
+               END AS partition_share
+        FROM stats
+        ORDER BY table_bytes + index_bytes + toast_bytes DESC, relation";
+    Spi::connect(|client| {
+        let mut results = Vec::new();
+        for row in client.select(query, None, None)? {
+            results.push(StorageStats {
+                relation: row["relation"].value::<String>()?.unwrap_or_default(),
+                parent: row["parent"].value::<String>()?,
+                table_bytes: row["table_bytes"].value::<i64>()?.unwrap_or_default(),
+                index_bytes: row["index_bytes"].value::<i64>()?.unwrap_or_default(),
+                toast_bytes: row["toast_bytes"].value::<i64>()?.unwrap_or_default(),
+                live_tuples: row["live_tuples"].value::<i64>()?.unwrap_or_default(),
+                dead_tuples: row["dead_tuples"].value::<i64>()?.unwrap_or_default(),
+                partition_share: row["partition_share"].value::<f64>()?,
+                analyzed: row["analyzed"].value::<bool>()?.unwrap_or_default(),
+            });
+        }
+        Ok(results)
+    })
+    .map_err(|err: pgrx::spi::Error| ErrorMessage::spi("fetch the storage report", None, &err))
+}
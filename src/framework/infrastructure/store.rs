@@ -0,0 +1,52 @@
+use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::framework::infrastructure::guc::STORE_ID;
+use pgrx::{IntoDatum, PgBuiltInOids, Spi};
+
+/// Runs the command handling in the logical event store (`fmodel.store_id`), if given; otherwise in the store of the session.
+/// The events are appended to and fetched from the store; the previous store of the session is restored afterwards.
+pub fn with_store<T>(
+    store: Option<&str>,
+    handle: impl FnOnce() -> Result<T, ErrorMessage>,
+) -> Result<T, ErrorMessage> {
+    let Some(store) = store else {
+        return handle();
+    };
+    let previous = Spi::get_one::<String>("SELECT current_setting('fmodel.store_id', TRUE)")
+        .map_err(|err| ErrorMessage::spi("fetch the store id", None, &err))?
+        .unwrap_or_default();
+    set_store_id(store)?;
+    let result = handle();
+    set_store_id(&previous)?;
+    result
+}
+
+/// Runs the handling (e.g. applying the event to the projections) in the logical event store of the event.
+/// The store of the session is switched only if it is another store, so the events of the session store are handled without the extra round trips.
+pub fn in_store<T>(
+    store: &str,
+    handle: impl FnOnce() -> Result<T, ErrorMessage>,
+) -> Result<T, ErrorMessage> {
+    if store == current_store_id() {
+        handle()
+    } else {
+        with_store(Some(store), handle)
+    }
+}
+
+/// The logical event store of the session (`fmodel.store_id`), the `default` store if not set. The same as the `fmodel_store_id()` SQL function.
+pub fn current_store_id() -> String {
+    STORE_ID
+        .get()
+        .and_then(|store| store.to_str().ok().map(str::to_owned))
+        .filter(|store| !store.is_empty())
+        .unwrap_or_else(|| "default".to_string())
+}
+
+/// Sets the store id (`fmodel.store_id`) for the rest of the transaction. An empty string selects the `default` store.
+fn set_store_id(store: &str) -> Result<(), ErrorMessage> {
+    Spi::run_with_args(
+        "SELECT set_config('fmodel.store_id', $1, TRUE)",
+        Some(vec![(PgBuiltInOids::TEXTOID.oid(), store.into_datum())]),
+    )
+    .map_err(|err| ErrorMessage::spi("set the store id", None, &err))
+}
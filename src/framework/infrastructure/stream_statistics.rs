@@ -0,0 +1,71 @@
+use crate::framework::infrastructure::errors::ErrorMessage;
+use pgrx::{IntoDatum, PgBuiltInOids, Spi};
+
+/// A convenient type alias for the stream length histogram row: the decider type, the bucket (the lower and upper bound of the stream length, by the order of magnitude), the number of the streams, and the length of the longest stream in the bucket.
+pub type StreamLengthBucket = (String, i64, i64, i64, i64);
+
+/// A convenient type alias for the long stream row: the decider type, the decider id, and the length of the stream.
+pub type LongStream = (String, String, i64);
+
+/// Refreshes the planner statistics of the `events` table (and all its partitions).
+pub fn analyze_events() -> Result<(), ErrorMessage> {
+    Spi::run("ANALYZE events").map_err(|err| ErrorMessage::spi("analyze the events", None, &err))
+}
+
+/// Fetches the histogram of the stream lengths per decider type, in the buckets by the order of magnitude (1-9, 10-99, 100-999, ...).
+/// The length of the stream is the `sequence_number` of its last event.
+pub fn fetch_stream_length_histogram() -> Result<Vec<StreamLengthBucket>, ErrorMessage> {
+    Spi::connect(|client| {
+        let mut results = Vec::new();
+        let tup_table = client.select(
+            "WITH streams AS (SELECT decider, max(sequence_number) AS length FROM events GROUP BY store_id, decider, decider_id),
+                  buckets AS (SELECT decider, length, power(10, floor(log(length)))::BIGINT AS from_length FROM streams)
+             SELECT decider, from_length, from_length * 10 - 1 AS to_length, count(*) AS streams, max(length) AS max_length
+             FROM buckets GROUP BY decider, from_length ORDER BY decider, from_length",
+            None,
+            None,
+        )?;
+        for row in tup_table {
+            if let (Some(decider), Some(from_length), Some(to_length), Some(streams), Some(max_length)) = (
+                row["decider"].value::<String>()?,
+                row["from_length"].value::<i64>()?,
+                row["to_length"].value::<i64>()?,
+                row["streams"].value::<i64>()?,
+                row["max_length"].value::<i64>()?,
+            ) {
+                results.push((decider, from_length, to_length, streams, max_length));
+            }
+        }
+        Ok(results)
+    })
+    .map_err(|err: pgrx::spi::Error| {
+        ErrorMessage::spi("fetch the stream length histogram", None, &err)
+    })
+}
+
+/// Fetches the streams longer than the threshold, the longest first, up to the limit.
+pub fn fetch_long_streams(threshold: i64, limit: i64) -> Result<Vec<LongStream>, ErrorMessage> {
+    Spi::connect(|client| {
+        let mut results = Vec::new();
+        let tup_table = client.select(
+            "SELECT decider, decider_id, max(sequence_number) AS length FROM events GROUP BY store_id, decider, decider_id
+             HAVING max(sequence_number) > $1 ORDER BY length DESC LIMIT $2",
+            None,
+            Some(vec![
+                (PgBuiltInOids::INT8OID.oid(), threshold.into_datum()),
+                (PgBuiltInOids::INT8OID.oid(), limit.into_datum()),
+            ]),
+        )?;
+        for row in tup_table {
+            if let (Some(decider), Some(decider_id), Some(length)) = (
+                row["decider"].value::<String>()?,
+                row["decider_id"].value::<String>()?,
+                row["length"].value::<i64>()?,
+            ) {
+                results.push((decider, decider_id, length));
+            }
+        }
+        Ok(results)
+    })
+    .map_err(|err: pgrx::spi::Error| ErrorMessage::spi("fetch the long streams", None, &err))
+}
@@ -0,0 +1,61 @@
+use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::framework::infrastructure::event_repository::EventPosition;
+use pgrx::spi::quote_identifier;
+use pgrx::{pg_sys, JsonB, PgTrigger, Spi};
+
+/// Fetches the events inserted by the statement, from the new transition table of the statement-level trigger (`REFERENCING NEW TABLE AS ...`): the event data/payload, the position and the (logical) store of the event, in the order of the offsets.
+pub fn fetch_inserted_events(
+    trigger: &PgTrigger<'_>,
+) -> Result<Vec<(JsonB, EventPosition, String)>, ErrorMessage> {
+    let table = trigger
+        .new_transition_table_name()
+        .map_err(|err| ErrorMessage {
+            message: "Failed to fetch the inserted events: ".to_string() + &err.to_string(),
+            context: None,
+        })?
+        .ok_or(ErrorMessage {
+            message: "Failed to fetch the inserted events: the trigger has no new transition table"
+                .to_string(),
+            context: None,
+        })?;
+    let query = "SELECT event_id, \"offset\", sequence_number, data, store_id FROM ".to_string()
+        + &quote_identifier(table)
+        + " ORDER BY \"offset\"";
+    Spi::connect(|client| {
+        // The transition tables are visible to the queries of the SPI connection once the trigger data is registered.
+        // If the registration fails, the query fails (the transition table does not exist).
+        unsafe {
+            pg_sys::SPI_register_trigger_data(
+                trigger.trigger_data() as *const pg_sys::TriggerData as *mut pg_sys::TriggerData
+            )
+        };
+        let mut results = Vec::new();
+        for row in client.select(&query, None, None)? {
+            if let (
+                Some(event_id),
+                Some(offset),
+                Some(sequence_number),
+                Some(data),
+                Some(store_id),
+            ) = (
+                row["event_id"].value::<pgrx::Uuid>()?,
+                row["offset"].value::<i64>()?,
+                row["sequence_number"].value::<i64>()?,
+                row["data"].value::<JsonB>()?,
+                row["store_id"].value::<String>()?,
+            ) {
+                results.push((
+                    data,
+                    EventPosition {
+                        event_id: uuid::Uuid::from_bytes(*event_id.as_bytes()),
+                        offset,
+                        sequence_number,
+                    },
+                    store_id,
+                ));
+            }
+        }
+        Ok(results)
+    })
+    .map_err(|err: pgrx::spi::Error| ErrorMessage::spi("fetch the inserted events", None, &err))
+}
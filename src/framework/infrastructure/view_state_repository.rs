@@ -1,9 +1,156 @@
 use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::framework::infrastructure::event_repository::EventPosition;
+use pgrx::{IntoDatum, JsonB, PgBuiltInOids, Spi};
+use serde_json::Value;
+use uuid::Uuid as UUID;
 
 /// A trait for a view state repository / the query side of the CQRS pattern.
 pub trait ViewStateRepository<E, S> {
     /// Fetches current state, based on the event.
     fn fetch_state(&self, event: &E) -> Result<Option<S>, ErrorMessage>;
-    /// Saves the new state.
-    fn save(&self, state: &S) -> Result<S, ErrorMessage>;
+    /// Fetches the offset of the last event applied to the state (view row) the event belongs to, if any.
+    fn fetch_applied_offset(&self, event: &E) -> Result<Option<i64>, ErrorMessage>;
+    /// Saves the new state, together with the position of the last applied event.
+    fn save(&self, state: &S, position: &EventPosition) -> Result<S, ErrorMessage>;
+    /// Saves the new state of the existing view row, by patching the stored document with the changes computed from the event, instead of rewriting the whole document.
+    /// The default implementation rewrites the whole document (`save`).
+    fn save_patch(
+        &self,
+        _event: &E,
+        state: &S,
+        position: &EventPosition,
+    ) -> Result<S, ErrorMessage> {
+        self.save(state, position)
+    }
+}
+
+/// Applies the JSON merge patch (the top-level fields of the `patch` object) to the `data` document of the view row, together with the position of the last applied event.
+/// The document is not rewritten if the patch is empty (only the position is updated), so the TOASTed document is not churned.
+/// Returns `false` if the view row does not exist in the store of the session (nothing is patched).
+pub fn patch_view_state(
+    table: &str,
+    id: &UUID,
+    patch: Value,
+    position: &EventPosition,
+) -> Result<bool, ErrorMessage> {
+    let query = "UPDATE ".to_string()
+        + table
+        + " SET data = CASE WHEN $2 = '{}'::JSONB THEN data ELSE data || $2 END, last_event_id = $3, last_offset = $4 WHERE store_id = fmodel_store_id() AND id = $1 RETURNING TRUE";
+    Spi::connect(|mut client| {
+        client
+            .update(
+                query.as_str(),
+                None,
+                Some(vec![
+                    (PgBuiltInOids::UUIDOID.oid(), id.to_string().into_datum()),
+                    (PgBuiltInOids::JSONBOID.oid(), JsonB(patch).into_datum()),
+                    (
+                        PgBuiltInOids::UUIDOID.oid(),
+                        pgrx::Uuid::from_bytes(position.event_id.into_bytes()).into_datum(),
+                    ),
+                    (PgBuiltInOids::INT8OID.oid(), position.offset.into_datum()),
+                ]),
+            )?
+            .first()
+            .get_one::<bool>()
+    })
+    .map(|patched| patched.unwrap_or(false))
+    .map_err(|err| {
+        ErrorMessage::spi(
+            &("patch the view state (".to_string() + table + ")"),
+            Some(id.to_string()),
+            &err,
+        )
+    })
+}
+
+/// Checks if the event at the offset is already applied to the view row at the applied offset: the event is at or before the applied one in the commit order.
+/// The events are applied in the (`transaction_id`, `offset`) order (see `ProjectionRepository::fetch_events`), so the event with the lower offset, committed by the later transaction, is applied later.
+/// The offsets are compared if either event is not in the event store (e.g. the replayed event).
+pub fn is_applied(applied_offset: i64, offset: i64) -> Result<bool, ErrorMessage> {
+    Spi::get_one_with_args::<bool>(
+        r#"SELECT COALESCE(
+               (SELECT (applied.transaction_id, applied."offset") >= (event.transaction_id, event."offset")
+                FROM events applied, events event WHERE applied."offset" = $1 AND event."offset" = $2),
+               $1 >= $2)"#,
+        vec![
+            (PgBuiltInOids::INT8OID.oid(), applied_offset.into_datum()),
+            (PgBuiltInOids::INT8OID.oid(), offset.into_datum()),
+        ],
+    )
+    .map(|applied| applied.unwrap_or(false))
+    .map_err(|err| ErrorMessage::spi("check the applied event", None, &err))
+}
+
+/// Upcaster of the view state documents: converts the stored (older) JSONB document to the current schema of the view state (e.g. adds the new field with the default/derived value).
+/// The documents already in the current schema are returned unchanged.
+pub type ViewStateUpcaster = fn(Value) -> Value;
+
+/// Rewrites the rows of the view table eagerly, with the documents upcasted to the current schema of the view state.
+/// The rows (of all the stores) are read in batches (by the `store_id` and the `id`); only the rows whose document changed are rewritten, the position of the last applied event is kept.
+/// Returns the number of the rewritten rows.
+pub fn migrate_view_rows(
+    table: &str,
+    upcaster: ViewStateUpcaster,
+    batch_size: i64,
+) -> Result<i64, ErrorMessage> {
+    let to_error = |err: pgrx::spi::Error| {
+        ErrorMessage::spi(
+            &("migrate the view rows (".to_string() + table + ")"),
+            None,
+            &err,
+        )
+    };
+    let select = "SELECT store_id, id, data FROM ".to_string()
+        + table
+        + " WHERE (store_id, id) > (COALESCE($1, ''), COALESCE($2, '00000000-0000-0000-0000-000000000000'::UUID)) ORDER BY store_id, id LIMIT $3";
+    let update = "UPDATE ".to_string() + table + " SET data = $3 WHERE store_id = $1 AND id = $2";
+    let mut after: (Option<String>, Option<pgrx::Uuid>) = (None, None);
+    let mut migrated = 0;
+    loop {
+        let rows = Spi::connect(|client| {
+            let mut rows = Vec::new();
+            for row in client.select(
+                select.as_str(),
+                None,
+                Some(vec![
+                    (PgBuiltInOids::TEXTOID.oid(), after.0.clone().into_datum()),
+                    (PgBuiltInOids::UUIDOID.oid(), after.1.into_datum()),
+                    (PgBuiltInOids::INT8OID.oid(), batch_size.into_datum()),
+                ]),
+            )? {
+                if let (Some(store_id), Some(id), data) = (
+                    row["store_id"].value::<String>()?,
+                    row["id"].value::<pgrx::Uuid>()?,
+                    row["data"].value::<JsonB>()?,
+                ) {
+                    rows.push((store_id, id, data));
+                }
+            }
+            Ok(rows)
+        })
+        .map_err(to_error)?;
+        let Some((last_store_id, last_id, _)) = rows.last() else {
+            return Ok(migrated);
+        };
+        after = (Some(last_store_id.clone()), Some(*last_id));
+        for (store_id, id, data) in rows {
+            let Some(JsonB(data)) = data else {
+                continue;
+            };
+            let upcasted = upcaster(data.clone());
+            if upcasted != data {
+                Spi::run_with_args(
+                    update.as_str(),
+                    Some(vec![
+                        (PgBuiltInOids::TEXTOID.oid(), store_id.into_datum()),
+                        (PgBuiltInOids::UUIDOID.oid(), id.into_datum()),
+                        (PgBuiltInOids::JSONBOID.oid(), JsonB(upcasted).into_datum()),
+                    ]),
+                )
+                .map_err(to_error)?;
+                migrated += 1;
+            }
+        }
+    }
 }
@@ -0,0 +1,84 @@
+use crate::framework::domain::api::EventType;
+use crate::framework::domain::workflow::WorkflowEvent;
+use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::framework::infrastructure::to_payload;
+use pgrx::{IntoDatum, JsonB, PgBuiltInOids, Spi};
+use uuid::Uuid;
+
+/// Fetches the events of the workflow stream (keyed by the correlation id), together with the version of the stream (the sequence number of the last event, `0` if the stream is empty).
+pub fn fetch_workflow_events(
+    correlation_id: &Uuid,
+) -> Result<(Vec<WorkflowEvent>, i64), ErrorMessage> {
+    // Read-only SPI: the workflow status is queried by the `STABLE` function(s)
+    let rows = Spi::connect(|client| {
+        let mut rows = Vec::new();
+        let tup_table = client.select(
+            "SELECT data, sequence_number FROM workflow_events WHERE correlation_id = $1 ORDER BY sequence_number",
+            None,
+            Some(vec![(
+                PgBuiltInOids::UUIDOID.oid(),
+                correlation_id.to_string().into_datum(),
+            )]),
+        )?;
+        for row in tup_table {
+            if let (Some(data), Some(sequence_number)) = (
+                row["data"].value::<JsonB>()?,
+                row["sequence_number"].value::<i64>()?,
+            ) {
+                rows.push((data, sequence_number));
+            }
+        }
+        Ok(rows)
+    })
+    .map_err(|err: pgrx::spi::Error| {
+        ErrorMessage::spi(
+            "fetch the workflow events",
+            Some(correlation_id.to_string()),
+            &err,
+        )
+    })?;
+    let version = rows.last().map(|(_, version)| *version).unwrap_or_default();
+    let events = rows
+        .into_iter()
+        .map(|(data, _)| to_payload(data))
+        .collect::<Result<Vec<WorkflowEvent>, ErrorMessage>>()?;
+    Ok((events, version))
+}
+
+/// Appends the events to the workflow stream, after the version the decision was made at.
+/// The concurrent append (at the same version) fails on the primary key, so the workflow decisions are never based on the stale state.
+pub fn save_workflow_events(
+    correlation_id: &Uuid,
+    version: i64,
+    events: &[WorkflowEvent],
+) -> Result<(), ErrorMessage> {
+    for (index, event) in events.iter().enumerate() {
+        let data = serde_json::to_value(event).map_err(|err| ErrorMessage {
+            message: "Failed to serialize the workflow event: ".to_string() + &err.to_string(),
+            context: None,
+        })?;
+        Spi::run_with_args(
+            "INSERT INTO workflow_events (correlation_id, sequence_number, event, data) VALUES ($1, $2, $3, $4)",
+            Some(vec![
+                (
+                    PgBuiltInOids::UUIDOID.oid(),
+                    correlation_id.to_string().into_datum(),
+                ),
+                (
+                    PgBuiltInOids::INT8OID.oid(),
+                    (version + index as i64 + 1).into_datum(),
+                ),
+                (PgBuiltInOids::TEXTOID.oid(), event.event_type().into_datum()),
+                (PgBuiltInOids::JSONBOID.oid(), JsonB(data).into_datum()),
+            ]),
+        )
+        .map_err(|err| {
+            ErrorMessage::spi(
+                "save the workflow event",
+                Some(correlation_id.to_string()),
+                &err,
+            )
+        })?;
+    }
+    Ok(())
+}
@@ -0,0 +1,536 @@
+use serde_json::{json, Value};
+
+/// The namespace of the Avro schemas.
+const NAMESPACE: &str = "com.fraktalio.restaurant";
+
+// Avro schemas of the events, for the schema registry.
+// They mirror the JSON (serde) representation of the events: the newtypes are flattened to their inner values, the identifiers are `uuid` strings, and the enums are represented by their variant names.
+
+/// Avro schemas of all the event types, by the event type/name.
+pub fn event_avro_schemas() -> Vec<(&'static str, Value)> {
+    vec![
+        (
+            "RestaurantCreated",
+            event_schema(
+                "RestaurantCreated",
+                vec![
+                    field("identifier", uuid()),
+                    field("name", json!("string")),
+                    field("menu", restaurant_menu()),
+                ],
+            ),
+        ),
+        (
+            "RestaurantMenuChanged",
+            event_schema(
+                "RestaurantMenuChanged",
+                vec![
+                    field("identifier", uuid()),
+                    field("menu", restaurant_menu()),
+                    field("menu_version", json!("long")),
+                ],
+            ),
+        ),
+        (
+            "OrderPlaced",
+            event_schema(
+                "OrderPlaced",
+                vec![
+                    field("identifier", uuid()),
+                    field("order_identifier", uuid()),
+                    field("line_items", order_line_items()),
+                    json!({"name": "currency", "type": ["null", "string"], "default": null}),
+                    json!({"name": "discounts", "type": applied_discounts(), "default": []}),
+                    json!({"name": "group_identifier", "type": ["null", uuid()], "default": null}),
+                    json!({"name": "customer_identifier", "type": ["null", uuid()], "default": null}),
+                ],
+            ),
+        ),
+        (
+            "OrderCreated",
+            event_schema(
+                "OrderCreated",
+                vec![
+                    field("identifier", uuid()),
+                    field("restaurant_identifier", uuid()),
+                    field("status", order_status()),
+                    field("line_items", order_line_items()),
+                    json!({"name": "reference_total", "type": ["null", converted_money()], "default": null}),
+                    json!({"name": "discounts", "type": applied_discounts(), "default": []}),
+                    json!({"name": "group_identifier", "type": ["null", uuid()], "default": null}),
+                    json!({"name": "customer_identifier", "type": ["null", uuid()], "default": null}),
+                ],
+            ),
+        ),
+        (
+            "OrderPrepared",
+            event_schema(
+                "OrderPrepared",
+                vec![
+                    field("identifier", uuid()),
+                    json!({"name": "restaurant_identifier", "type": ["null", uuid()], "default": null}),
+                    field("status", order_status()),
+                ],
+            ),
+        ),
+        (
+            "OrderLineItemsModified",
+            event_schema(
+                "OrderLineItemsModified",
+                vec![
+                    field("identifier", uuid()),
+                    field("order_identifier", uuid()),
+                    field("line_items", order_line_items()),
+                    json!({"name": "discounts", "type": applied_discounts(), "default": []}),
+                ],
+            ),
+        ),
+        (
+            "OrderLineItemsUpdated",
+            event_schema(
+                "OrderLineItemsUpdated",
+                vec![
+                    field("identifier", uuid()),
+                    field("restaurant_identifier", uuid()),
+                    field("line_items", order_line_items()),
+                    json!({"name": "discounts", "type": applied_discounts(), "default": []}),
+                ],
+            ),
+        ),
+        (
+            "PaymentCaptured",
+            event_schema(
+                "PaymentCaptured",
+                vec![
+                    field("identifier", uuid()),
+                    field("order_identifier", uuid()),
+                    field("amount", json!("long")),
+                ],
+            ),
+        ),
+        (
+            "PaymentRefunded",
+            event_schema(
+                "PaymentRefunded",
+                vec![
+                    field("identifier", uuid()),
+                    field("order_identifier", uuid()),
+                    field("amount", json!("long")),
+                ],
+            ),
+        ),
+        (
+            "RestaurantCapacityChanged",
+            event_schema(
+                "RestaurantCapacityChanged",
+                vec![
+                    field("identifier", uuid()),
+                    json!({"name": "max_concurrent_orders", "type": ["null", "long"], "default": null}),
+                ],
+            ),
+        ),
+        (
+            "RestaurantOrderClosed",
+            event_schema(
+                "RestaurantOrderClosed",
+                vec![
+                    field("identifier", uuid()),
+                    field("order_identifier", uuid()),
+                ],
+            ),
+        ),
+        (
+            "MenuItemMarkedUnavailable",
+            event_schema(
+                "MenuItemMarkedUnavailable",
+                vec![field("identifier", uuid()), field("menu_item_id", uuid())],
+            ),
+        ),
+        (
+            "MenuItemMarkedAvailable",
+            event_schema(
+                "MenuItemMarkedAvailable",
+                vec![field("identifier", uuid()), field("menu_item_id", uuid())],
+            ),
+        ),
+        (
+            "RestaurantOpeningHoursChanged",
+            event_schema(
+                "RestaurantOpeningHoursChanged",
+                vec![
+                    field("identifier", uuid()),
+                    json!({"name": "opening_hours", "type": ["null", opening_hours()], "default": null}),
+                ],
+            ),
+        ),
+        (
+            "RestaurantOrdersMigrated",
+            event_schema(
+                "RestaurantOrdersMigrated",
+                vec![
+                    field("identifier", uuid()),
+                    field("target_identifier", uuid()),
+                    field("orders", migrated_orders()),
+                ],
+            ),
+        ),
+        (
+            "RestaurantOrdersReceived",
+            event_schema(
+                "RestaurantOrdersReceived",
+                vec![
+                    field("identifier", uuid()),
+                    field("source_identifier", uuid()),
+                    field("orders", migrated_orders()),
+                ],
+            ),
+        ),
+        (
+            "OrderMigrated",
+            event_schema(
+                "OrderMigrated",
+                vec![
+                    field("identifier", uuid()),
+                    field("restaurant_identifier", uuid()),
+                    field("status", order_status()),
+                ],
+            ),
+        ),
+        (
+            "RestaurantPromotionApplied",
+            event_schema(
+                "RestaurantPromotionApplied",
+                vec![
+                    field("identifier", uuid()),
+                    field("promotion", restaurant_promotion()),
+                ],
+            ),
+        ),
+        (
+            "RestaurantPromotionWithdrawn",
+            event_schema(
+                "RestaurantPromotionWithdrawn",
+                vec![field("identifier", uuid()), field("promotion_id", uuid())],
+            ),
+        ),
+        (
+            "PromotionCreated",
+            event_schema(
+                "PromotionCreated",
+                vec![
+                    field("identifier", uuid()),
+                    field("restaurant_identifier", uuid()),
+                    field("name", json!("string")),
+                    field("percentage", json!("int")),
+                    field("menu_item_ids", json!({"type": "array", "items": uuid()})),
+                ],
+            ),
+        ),
+        (
+            "PromotionExpired",
+            event_schema(
+                "PromotionExpired",
+                vec![
+                    field("identifier", uuid()),
+                    field("restaurant_identifier", uuid()),
+                ],
+            ),
+        ),
+        (
+            "RestaurantOrderCancelled",
+            event_schema(
+                "RestaurantOrderCancelled",
+                vec![
+                    field("identifier", uuid()),
+                    field("order_identifier", uuid()),
+                    field("rejected", json!("boolean")),
+                ],
+            ),
+        ),
+        (
+            "OrderCancelled",
+            event_schema(
+                "OrderCancelled",
+                vec![
+                    field("identifier", uuid()),
+                    field("restaurant_identifier", uuid()),
+                    field("status", order_status()),
+                ],
+            ),
+        ),
+        (
+            "InventoryRestocked",
+            event_schema(
+                "InventoryRestocked",
+                vec![
+                    field("identifier", uuid()),
+                    field("restaurant_identifier", uuid()),
+                    field("items", stock_items()),
+                ],
+            ),
+        ),
+        (
+            "StockReserved",
+            event_schema(
+                "StockReserved",
+                vec![
+                    field("identifier", uuid()),
+                    field("order_identifier", uuid()),
+                    field("items", stock_items()),
+                ],
+            ),
+        ),
+        (
+            "StockReleased",
+            event_schema(
+                "StockReleased",
+                vec![
+                    field("identifier", uuid()),
+                    field("order_identifier", uuid()),
+                    field("items", stock_items()),
+                ],
+            ),
+        ),
+        (
+            "RestaurantOrderSplit",
+            event_schema(
+                "RestaurantOrderSplit",
+                vec![
+                    field("identifier", uuid()),
+                    field("group_identifier", uuid()),
+                    field("orders", split_orders()),
+                    json!({"name": "customer_identifier", "type": ["null", uuid()], "default": null}),
+                ],
+            ),
+        ),
+        (
+            "GiftCardIssued",
+            event_schema(
+                "GiftCardIssued",
+                vec![field("identifier", uuid()), field("amount", json!("long"))],
+            ),
+        ),
+        (
+            "GiftCardRedeemed",
+            event_schema(
+                "GiftCardRedeemed",
+                vec![
+                    field("identifier", uuid()),
+                    field("restaurant_identifier", uuid()),
+                    field("order_identifier", uuid()),
+                    field("amount", json!("long")),
+                ],
+            ),
+        ),
+        (
+            "GiftCardRefunded",
+            event_schema(
+                "GiftCardRefunded",
+                vec![
+                    field("identifier", uuid()),
+                    field("restaurant_identifier", uuid()),
+                    field("order_identifier", uuid()),
+                    field("amount", json!("long")),
+                ],
+            ),
+        ),
+        (
+            "CourierLocationUpdated",
+            event_schema(
+                "CourierLocationUpdated",
+                vec![
+                    field("identifier", uuid()),
+                    field("latitude", json!("int")),
+                    field("longitude", json!("int")),
+                ],
+            ),
+        ),
+    ]
+}
+
+/// The event record: the `type` tag, the event fields, and the `final` indicator.
+fn event_schema(name: &str, fields: Vec<Value>) -> Value {
+    let mut all_fields = vec![field("type", json!("string"))];
+    all_fields.extend(fields);
+    all_fields.push(field("final", json!("boolean")));
+    json!({
+        "type": "record",
+        "name": name,
+        "namespace": NAMESPACE,
+        "fields": all_fields,
+    })
+}
+
+fn field(name: &str, schema: Value) -> Value {
+    json!({"name": name, "type": schema})
+}
+
+fn uuid() -> Value {
+    json!({"type": "string", "logicalType": "uuid"})
+}
+
+fn restaurant_menu() -> Value {
+    json!({
+        "type": "record",
+        "name": "RestaurantMenu",
+        "fields": [
+            field("menu_id", uuid()),
+            field("items", json!({
+                "type": "array",
+                "items": {
+                    "type": "record",
+                    "name": "MenuItem",
+                    "fields": [
+                        field("id", uuid()),
+                        field("name", json!("string")),
+                        field("price", json!("long")),
+                    ],
+                },
+            })),
+            field("cuisine", json!({
+                "type": "enum",
+                "name": "RestaurantMenuCuisine",
+                "symbols": [
+                    "Italian", "Indian", "Chinese", "Japanese", "American", "Mexican", "French", "Thai",
+                    "Vietnamese", "Greek", "Korean", "Spanish", "Lebanese", "Turkish", "Ethiopian",
+                    "Moroccan", "Egyptian", "Brazilian", "Polish", "German", "British", "Irish", "Other",
+                ],
+            })),
+            json!({"name": "currency", "type": ["null", "string"], "default": null}),
+        ],
+    })
+}
+
+fn converted_money() -> Value {
+    json!({
+        "type": "record",
+        "name": "ConvertedMoney",
+        "fields": [
+            field("amount", json!("long")),
+            field("currency", json!("string")),
+            field("rate", json!("long")),
+        ],
+    })
+}
+
+fn opening_hours() -> Value {
+    json!({
+        "type": "record",
+        "name": "OpeningHours",
+        "fields": [
+            field("time_zone", json!("string")),
+            field("periods", json!({
+                "type": "array",
+                "items": {
+                    "type": "record",
+                    "name": "OpeningPeriod",
+                    "fields": [
+                        field("day_of_week", json!("int")),
+                        field("opens_at", json!("int")),
+                        field("closes_at", json!("int")),
+                    ],
+                },
+            })),
+        ],
+    })
+}
+
+fn order_line_items() -> Value {
+    json!({
+        "type": "array",
+        "items": {
+            "type": "record",
+            "name": "OrderLineItem",
+            "fields": [
+                field("id", uuid()),
+                field("quantity", json!("long")),
+                field("menu_item_id", uuid()),
+                field("name", json!("string")),
+                json!({"name": "price", "type": ["null", "long"], "default": null}),
+                json!({"name": "restaurant_identifier", "type": ["null", uuid()], "default": null}),
+            ],
+        },
+    })
+}
+
+fn applied_discounts() -> Value {
+    json!({
+        "type": "array",
+        "items": {
+            "type": "record",
+            "name": "AppliedDiscount",
+            "fields": [
+                field("promotion_id", uuid()),
+                field("line_item_id", uuid()),
+                field("amount", json!("long")),
+            ],
+        },
+    })
+}
+
+fn restaurant_promotion() -> Value {
+    json!({
+        "type": "record",
+        "name": "RestaurantPromotion",
+        "fields": [
+            field("id", uuid()),
+            field("name", json!("string")),
+            field("percentage", json!("int")),
+            field("menu_item_ids", json!({"type": "array", "items": uuid()})),
+        ],
+    })
+}
+
+fn stock_items() -> Value {
+    json!({
+        "type": "array",
+        "items": {
+            "type": "record",
+            "name": "StockItem",
+            "fields": [
+                field("menu_item_id", uuid()),
+                field("quantity", json!("long")),
+            ],
+        },
+    })
+}
+
+fn split_orders() -> Value {
+    json!({
+        "type": "array",
+        "items": {
+            "type": "record",
+            "name": "SplitOrder",
+            "fields": [
+                field("restaurant_identifier", uuid()),
+                field("order_identifier", uuid()),
+                field("line_items", order_line_items()),
+            ],
+        },
+    })
+}
+
+fn migrated_orders() -> Value {
+    json!({
+        "type": "array",
+        "items": {
+            "type": "record",
+            "name": "MigratedOrder",
+            "fields": [
+                field("order_identifier", uuid()),
+                json!({"name": "total", "type": ["null", "long"], "default": null}),
+                field("captured", json!("long")),
+                field("refunded", json!("long")),
+                field("open", json!("boolean")),
+            ],
+        },
+    })
+}
+
+fn order_status() -> Value {
+    json!({
+        "type": "enum",
+        "name": "OrderStatus",
+        "symbols": ["Created", "Prepared", "Cancelled", "Rejected"],
+    })
+}
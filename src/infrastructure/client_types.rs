@@ -0,0 +1,1056 @@
+use serde_json::{json, Map, Value};
+
+// Client type definitions of the commands, the events and the view states, for the front-end/client teams.
+// They mirror the JSON (serde) representation: the newtypes are flattened to their inner values, the identifiers are `uuid` strings, the enums are represented by their variant names, and the commands/events are tagged by their `type`.
+
+/// The JSON Schema (draft 2020-12) of the client types: `Command`, `Event`, `RestaurantViewState` and `OrderViewState` (and the types they refer to) are defined in `$defs`.
+pub fn client_json_schema() -> Value {
+    let mut defs = Map::new();
+    for (name, schema) in value_objects()
+        .into_iter()
+        .chain(commands())
+        .chain(events())
+        .chain(view_states())
+    {
+        defs.insert(name.to_string(), schema);
+    }
+    defs.insert(
+        "Command".to_string(),
+        json!({"oneOf": commands().iter().map(|(name, _)| reference(name)).collect::<Vec<_>>()}),
+    );
+    defs.insert(
+        "Event".to_string(),
+        json!({"oneOf": events().iter().map(|(name, _)| reference(name)).collect::<Vec<_>>()}),
+    );
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "$id": "urn:fraktalio:restaurant:client-types",
+        "$defs": defs,
+    })
+}
+
+/// The TypeScript definitions of the client types, rendered from the JSON Schema (`client_json_schema`): one exported interface/type per definition.
+pub fn client_typescript() -> String {
+    let schema = client_json_schema();
+    let mut typescript = "// Generated by `generate_client_types()`. Do not edit.\n".to_string();
+    if let Some(defs) = schema["$defs"].as_object() {
+        for (name, def) in defs {
+            typescript.push('\n');
+            match def["properties"].as_object() {
+                Some(properties) => {
+                    typescript.push_str(&("export interface ".to_string() + name + " {\n"));
+                    for (property, property_schema) in properties {
+                        let optional = !def["required"]
+                            .as_array()
+                            .is_some_and(|required| required.contains(&json!(property)));
+                        typescript.push_str(
+                            &("  ".to_string()
+                                + property
+                                + if optional { "?: " } else { ": " }
+                                + &typescript_type(property_schema)
+                                + ";\n"),
+                        );
+                    }
+                    typescript.push_str("}\n");
+                }
+                None => typescript.push_str(
+                    &("export type ".to_string() + name + " = " + &typescript_type(def) + ";\n"),
+                ),
+            }
+        }
+    }
+    typescript
+}
+
+/// The TypeScript type of the (inline) schema.
+fn typescript_type(schema: &Value) -> String {
+    if let Some(reference) = schema["$ref"].as_str() {
+        return reference.trim_start_matches("#/$defs/").to_string();
+    }
+    if let Some(constant) = schema.get("const") {
+        return constant.to_string();
+    }
+    if let Some(variants) = schema["enum"].as_array() {
+        return union(variants.iter().map(|variant| variant.to_string()));
+    }
+    if let Some(schemas) = schema["oneOf"].as_array().or(schema["anyOf"].as_array()) {
+        return union(schemas.iter().map(typescript_type));
+    }
+    match schema["type"].as_str() {
+        Some("string") => "string".to_string(),
+        Some("integer") | Some("number") => "number".to_string(),
+        Some("boolean") => "boolean".to_string(),
+        Some("null") => "null".to_string(),
+        Some("array") => "Array<".to_string() + &typescript_type(&schema["items"]) + ">",
+        _ => "unknown".to_string(),
+    }
+}
+
+fn union(types: impl Iterator<Item = String>) -> String {
+    types.collect::<Vec<_>>().join(" | ")
+}
+
+fn value_objects() -> Vec<(&'static str, Value)> {
+    vec![
+        ("RestaurantId", uuid()),
+        ("RestaurantName", json!({"type": "string"})),
+        ("OrderId", uuid()),
+        ("OrderGroupId", uuid()),
+        ("CustomerId", uuid()),
+        ("Money", unsigned()),
+        ("Currency", json!({"type": "string"})),
+        ("ExchangeRate", unsigned()),
+        (
+            "ConvertedMoney",
+            object(vec![
+                ("amount", reference("Money")),
+                ("currency", reference("Currency")),
+                ("rate", reference("ExchangeRate")),
+            ]),
+        ),
+        ("MenuId", uuid()),
+        ("RestaurantMenuVersion", unsigned()),
+        ("MenuItemId", uuid()),
+        ("MenuItemName", json!({"type": "string"})),
+        ("OrderLineItemId", uuid()),
+        ("OrderLineItemQuantity", unsigned()),
+        (
+            "MenuItem",
+            object(vec![
+                ("id", reference("MenuItemId")),
+                ("name", reference("MenuItemName")),
+                ("price", reference("Money")),
+            ]),
+        ),
+        (
+            "RestaurantMenuCuisine",
+            json!({"type": "string", "enum": [
+                "Italian", "Indian", "Chinese", "Japanese", "American", "Mexican", "French", "Thai",
+                "Vietnamese", "Greek", "Korean", "Spanish", "Lebanese", "Turkish", "Ethiopian",
+                "Moroccan", "Egyptian", "Brazilian", "Polish", "German", "British", "Irish", "Other",
+            ]}),
+        ),
+        (
+            "RestaurantMenu",
+            with_defaults(
+                object(vec![
+                    ("menu_id", reference("MenuId")),
+                    ("items", array(reference("MenuItem"))),
+                    ("cuisine", reference("RestaurantMenuCuisine")),
+                    ("currency", nullable(reference("Currency"))),
+                ]),
+                &["currency"],
+            ),
+        ),
+        (
+            "OpeningHours",
+            object(vec![
+                ("time_zone", json!({"type": "string"})),
+                ("periods", array(reference("OpeningPeriod"))),
+            ]),
+        ),
+        (
+            "OpeningPeriod",
+            object(vec![
+                ("day_of_week", unsigned()),
+                ("opens_at", unsigned()),
+                ("closes_at", unsigned()),
+            ]),
+        ),
+        (
+            "OrderLineItem",
+            with_defaults(
+                object(vec![
+                    ("id", reference("OrderLineItemId")),
+                    ("quantity", reference("OrderLineItemQuantity")),
+                    ("menu_item_id", reference("MenuItemId")),
+                    ("name", reference("MenuItemName")),
+                    ("price", nullable(reference("Money"))),
+                    ("restaurant_identifier", nullable(reference("RestaurantId"))),
+                ]),
+                &["price", "restaurant_identifier"],
+            ),
+        ),
+        (
+            "SplitOrder",
+            object(vec![
+                ("restaurant_identifier", reference("RestaurantId")),
+                ("order_identifier", reference("OrderId")),
+                ("line_items", array(reference("OrderLineItem"))),
+            ]),
+        ),
+        (
+            "MigratedOrder",
+            object(vec![
+                ("order_identifier", reference("OrderId")),
+                ("total", nullable(reference("Money"))),
+                ("captured", reference("Money")),
+                ("refunded", reference("Money")),
+                ("open", json!({"type": "boolean"})),
+            ]),
+        ),
+        (
+            "OrderStatus",
+            json!({"type": "string", "enum": ["Created", "Prepared", "Cancelled", "Rejected"]}),
+        ),
+        ("PromotionId", uuid()),
+        ("PromotionName", json!({"type": "string"})),
+        (
+            "DiscountPercentage",
+            json!({"type": "integer", "minimum": 1, "maximum": 100}),
+        ),
+        (
+            "RestaurantPromotion",
+            object(vec![
+                ("id", reference("PromotionId")),
+                ("name", reference("PromotionName")),
+                ("percentage", reference("DiscountPercentage")),
+                ("menu_item_ids", array(reference("MenuItemId"))),
+            ]),
+        ),
+        (
+            "AppliedDiscount",
+            object(vec![
+                ("promotion_id", reference("PromotionId")),
+                ("line_item_id", reference("OrderLineItemId")),
+                ("amount", reference("Money")),
+            ]),
+        ),
+        ("InventoryId", uuid()),
+        ("StockQuantity", unsigned()),
+        (
+            "StockItem",
+            object(vec![
+                ("menu_item_id", reference("MenuItemId")),
+                ("quantity", reference("StockQuantity")),
+            ]),
+        ),
+        ("GiftCardId", uuid()),
+        ("CourierId", uuid()),
+        (
+            "Coordinate",
+            json!({"type": "integer", "minimum": -180000000, "maximum": 180000000}),
+        ),
+    ]
+}
+
+fn commands() -> Vec<(&'static str, Value)> {
+    vec![
+        (
+            "CreateRestaurant",
+            tagged(
+                "CreateRestaurant",
+                vec![
+                    ("identifier", reference("RestaurantId")),
+                    ("name", reference("RestaurantName")),
+                    ("menu", reference("RestaurantMenu")),
+                ],
+            ),
+        ),
+        (
+            "ChangeRestaurantMenu",
+            tagged(
+                "ChangeRestaurantMenu",
+                vec![
+                    ("identifier", reference("RestaurantId")),
+                    ("menu", reference("RestaurantMenu")),
+                ],
+            ),
+        ),
+        (
+            "PlaceOrder",
+            with_defaults(
+                tagged(
+                    "PlaceOrder",
+                    vec![
+                        ("identifier", reference("RestaurantId")),
+                        ("order_identifier", reference("OrderId")),
+                        ("line_items", array(reference("OrderLineItem"))),
+                        ("group_identifier", nullable(reference("OrderGroupId"))),
+                        ("customer_identifier", nullable(reference("CustomerId"))),
+                    ],
+                ),
+                &["group_identifier", "customer_identifier"],
+            ),
+        ),
+        (
+            "CreateOrder",
+            with_defaults(
+                tagged(
+                    "CreateOrder",
+                    vec![
+                        ("identifier", reference("OrderId")),
+                        ("restaurant_identifier", reference("RestaurantId")),
+                        ("line_items", array(reference("OrderLineItem"))),
+                        ("currency", nullable(reference("Currency"))),
+                        ("discounts", array(reference("AppliedDiscount"))),
+                        ("group_identifier", nullable(reference("OrderGroupId"))),
+                        ("customer_identifier", nullable(reference("CustomerId"))),
+                    ],
+                ),
+                &[
+                    "currency",
+                    "discounts",
+                    "group_identifier",
+                    "customer_identifier",
+                ],
+            ),
+        ),
+        (
+            "MarkOrderAsPrepared",
+            tagged(
+                "MarkOrderAsPrepared",
+                vec![("identifier", reference("OrderId"))],
+            ),
+        ),
+        (
+            "ModifyOrderLineItems",
+            tagged(
+                "ModifyOrderLineItems",
+                vec![
+                    ("identifier", reference("RestaurantId")),
+                    ("order_identifier", reference("OrderId")),
+                    ("line_items", array(reference("OrderLineItem"))),
+                ],
+            ),
+        ),
+        (
+            "UpdateOrderLineItems",
+            with_defaults(
+                tagged(
+                    "UpdateOrderLineItems",
+                    vec![
+                        ("identifier", reference("OrderId")),
+                        ("restaurant_identifier", reference("RestaurantId")),
+                        ("line_items", array(reference("OrderLineItem"))),
+                        ("discounts", array(reference("AppliedDiscount"))),
+                    ],
+                ),
+                &["discounts"],
+            ),
+        ),
+        (
+            "CapturePayment",
+            tagged(
+                "CapturePayment",
+                vec![
+                    ("identifier", reference("RestaurantId")),
+                    ("order_identifier", reference("OrderId")),
+                    ("amount", reference("Money")),
+                ],
+            ),
+        ),
+        (
+            "RefundPayment",
+            tagged(
+                "RefundPayment",
+                vec![
+                    ("identifier", reference("RestaurantId")),
+                    ("order_identifier", reference("OrderId")),
+                    ("amount", reference("Money")),
+                ],
+            ),
+        ),
+        (
+            "ChangeRestaurantCapacity",
+            tagged(
+                "ChangeRestaurantCapacity",
+                vec![
+                    ("identifier", reference("RestaurantId")),
+                    ("max_concurrent_orders", nullable(unsigned())),
+                ],
+            ),
+        ),
+        (
+            "CloseRestaurantOrder",
+            tagged(
+                "CloseRestaurantOrder",
+                vec![
+                    ("identifier", reference("RestaurantId")),
+                    ("order_identifier", reference("OrderId")),
+                ],
+            ),
+        ),
+        (
+            "MarkMenuItemUnavailable",
+            tagged(
+                "MarkMenuItemUnavailable",
+                vec![
+                    ("identifier", reference("RestaurantId")),
+                    ("menu_item_id", reference("MenuItemId")),
+                ],
+            ),
+        ),
+        (
+            "MarkMenuItemAvailable",
+            tagged(
+                "MarkMenuItemAvailable",
+                vec![
+                    ("identifier", reference("RestaurantId")),
+                    ("menu_item_id", reference("MenuItemId")),
+                ],
+            ),
+        ),
+        (
+            "ChangeRestaurantOpeningHours",
+            tagged(
+                "ChangeRestaurantOpeningHours",
+                vec![
+                    ("identifier", reference("RestaurantId")),
+                    ("opening_hours", nullable(reference("OpeningHours"))),
+                ],
+            ),
+        ),
+        (
+            "MigrateRestaurantOrders",
+            tagged(
+                "MigrateRestaurantOrders",
+                vec![
+                    ("identifier", reference("RestaurantId")),
+                    ("target_identifier", reference("RestaurantId")),
+                    ("order_identifiers", nullable(array(reference("OrderId")))),
+                ],
+            ),
+        ),
+        (
+            "ReceiveRestaurantOrders",
+            tagged(
+                "ReceiveRestaurantOrders",
+                vec![
+                    ("identifier", reference("RestaurantId")),
+                    ("source_identifier", reference("RestaurantId")),
+                    ("orders", array(reference("MigratedOrder"))),
+                ],
+            ),
+        ),
+        (
+            "MigrateOrder",
+            tagged(
+                "MigrateOrder",
+                vec![
+                    ("identifier", reference("OrderId")),
+                    ("restaurant_identifier", reference("RestaurantId")),
+                ],
+            ),
+        ),
+        (
+            "ApplyRestaurantPromotion",
+            tagged(
+                "ApplyRestaurantPromotion",
+                vec![
+                    ("identifier", reference("RestaurantId")),
+                    ("promotion", reference("RestaurantPromotion")),
+                ],
+            ),
+        ),
+        (
+            "WithdrawRestaurantPromotion",
+            tagged(
+                "WithdrawRestaurantPromotion",
+                vec![
+                    ("identifier", reference("RestaurantId")),
+                    ("promotion_id", reference("PromotionId")),
+                ],
+            ),
+        ),
+        (
+            "CreatePromotion",
+            tagged(
+                "CreatePromotion",
+                vec![
+                    ("identifier", reference("PromotionId")),
+                    ("restaurant_identifier", reference("RestaurantId")),
+                    ("name", reference("PromotionName")),
+                    ("percentage", reference("DiscountPercentage")),
+                    ("menu_item_ids", array(reference("MenuItemId"))),
+                ],
+            ),
+        ),
+        (
+            "ExpirePromotion",
+            tagged(
+                "ExpirePromotion",
+                vec![("identifier", reference("PromotionId"))],
+            ),
+        ),
+        (
+            "CancelRestaurantOrder",
+            tagged(
+                "CancelRestaurantOrder",
+                vec![
+                    ("identifier", reference("RestaurantId")),
+                    ("order_identifier", reference("OrderId")),
+                    ("rejected", json!({"type": "boolean"})),
+                ],
+            ),
+        ),
+        (
+            "CancelOrder",
+            tagged(
+                "CancelOrder",
+                vec![
+                    ("identifier", reference("OrderId")),
+                    ("rejected", json!({"type": "boolean"})),
+                ],
+            ),
+        ),
+        (
+            "RestockInventory",
+            tagged(
+                "RestockInventory",
+                vec![
+                    ("identifier", reference("InventoryId")),
+                    ("restaurant_identifier", reference("RestaurantId")),
+                    ("items", array(reference("StockItem"))),
+                ],
+            ),
+        ),
+        (
+            "ReserveStock",
+            tagged(
+                "ReserveStock",
+                vec![
+                    ("identifier", reference("InventoryId")),
+                    ("order_identifier", reference("OrderId")),
+                    ("items", array(reference("StockItem"))),
+                ],
+            ),
+        ),
+        (
+            "ReleaseStock",
+            tagged(
+                "ReleaseStock",
+                vec![
+                    ("identifier", reference("InventoryId")),
+                    ("order_identifier", reference("OrderId")),
+                ],
+            ),
+        ),
+        (
+            "IssueGiftCard",
+            tagged(
+                "IssueGiftCard",
+                vec![
+                    ("identifier", reference("GiftCardId")),
+                    ("amount", reference("Money")),
+                ],
+            ),
+        ),
+        (
+            "RedeemGiftCard",
+            tagged(
+                "RedeemGiftCard",
+                vec![
+                    ("identifier", reference("GiftCardId")),
+                    ("restaurant_identifier", reference("RestaurantId")),
+                    ("order_identifier", reference("OrderId")),
+                    ("amount", reference("Money")),
+                ],
+            ),
+        ),
+        (
+            "RefundGiftCard",
+            tagged(
+                "RefundGiftCard",
+                vec![
+                    ("identifier", reference("GiftCardId")),
+                    ("restaurant_identifier", reference("RestaurantId")),
+                    ("order_identifier", reference("OrderId")),
+                    ("amount", reference("Money")),
+                ],
+            ),
+        ),
+        (
+            "UpdateCourierLocation",
+            tagged(
+                "UpdateCourierLocation",
+                vec![
+                    ("identifier", reference("CourierId")),
+                    ("latitude", reference("Coordinate")),
+                    ("longitude", reference("Coordinate")),
+                ],
+            ),
+        ),
+    ]
+}
+
+fn events() -> Vec<(&'static str, Value)> {
+    vec![
+        (
+            "RestaurantCreated",
+            event(
+                "RestaurantCreated",
+                vec![
+                    ("identifier", reference("RestaurantId")),
+                    ("name", reference("RestaurantName")),
+                    ("menu", reference("RestaurantMenu")),
+                ],
+            ),
+        ),
+        (
+            "RestaurantMenuChanged",
+            with_defaults(
+                event(
+                    "RestaurantMenuChanged",
+                    vec![
+                        ("identifier", reference("RestaurantId")),
+                        ("menu", reference("RestaurantMenu")),
+                        ("menu_version", reference("RestaurantMenuVersion")),
+                    ],
+                ),
+                &["menu_version"],
+            ),
+        ),
+        (
+            "OrderPlaced",
+            with_defaults(
+                event(
+                    "OrderPlaced",
+                    vec![
+                        ("identifier", reference("RestaurantId")),
+                        ("order_identifier", reference("OrderId")),
+                        ("line_items", array(reference("OrderLineItem"))),
+                        ("currency", nullable(reference("Currency"))),
+                        ("discounts", array(reference("AppliedDiscount"))),
+                        ("group_identifier", nullable(reference("OrderGroupId"))),
+                        ("customer_identifier", nullable(reference("CustomerId"))),
+                    ],
+                ),
+                &[
+                    "currency",
+                    "discounts",
+                    "group_identifier",
+                    "customer_identifier",
+                ],
+            ),
+        ),
+        (
+            "OrderCreated",
+            with_defaults(
+                event(
+                    "OrderCreated",
+                    vec![
+                        ("identifier", reference("OrderId")),
+                        ("restaurant_identifier", reference("RestaurantId")),
+                        ("status", reference("OrderStatus")),
+                        ("line_items", array(reference("OrderLineItem"))),
+                        ("reference_total", nullable(reference("ConvertedMoney"))),
+                        ("discounts", array(reference("AppliedDiscount"))),
+                        ("group_identifier", nullable(reference("OrderGroupId"))),
+                        ("customer_identifier", nullable(reference("CustomerId"))),
+                    ],
+                ),
+                &[
+                    "reference_total",
+                    "discounts",
+                    "group_identifier",
+                    "customer_identifier",
+                ],
+            ),
+        ),
+        (
+            "OrderPrepared",
+            with_defaults(
+                event(
+                    "OrderPrepared",
+                    vec![
+                        ("identifier", reference("OrderId")),
+                        ("restaurant_identifier", nullable(reference("RestaurantId"))),
+                        ("status", reference("OrderStatus")),
+                    ],
+                ),
+                &["restaurant_identifier"],
+            ),
+        ),
+        (
+            "OrderLineItemsModified",
+            with_defaults(
+                event(
+                    "OrderLineItemsModified",
+                    vec![
+                        ("identifier", reference("RestaurantId")),
+                        ("order_identifier", reference("OrderId")),
+                        ("line_items", array(reference("OrderLineItem"))),
+                        ("discounts", array(reference("AppliedDiscount"))),
+                    ],
+                ),
+                &["discounts"],
+            ),
+        ),
+        (
+            "OrderLineItemsUpdated",
+            with_defaults(
+                event(
+                    "OrderLineItemsUpdated",
+                    vec![
+                        ("identifier", reference("OrderId")),
+                        ("restaurant_identifier", reference("RestaurantId")),
+                        ("line_items", array(reference("OrderLineItem"))),
+                        ("discounts", array(reference("AppliedDiscount"))),
+                    ],
+                ),
+                &["discounts"],
+            ),
+        ),
+        (
+            "PaymentCaptured",
+            event(
+                "PaymentCaptured",
+                vec![
+                    ("identifier", reference("RestaurantId")),
+                    ("order_identifier", reference("OrderId")),
+                    ("amount", reference("Money")),
+                ],
+            ),
+        ),
+        (
+            "PaymentRefunded",
+            event(
+                "PaymentRefunded",
+                vec![
+                    ("identifier", reference("RestaurantId")),
+                    ("order_identifier", reference("OrderId")),
+                    ("amount", reference("Money")),
+                ],
+            ),
+        ),
+        (
+            "RestaurantCapacityChanged",
+            event(
+                "RestaurantCapacityChanged",
+                vec![
+                    ("identifier", reference("RestaurantId")),
+                    ("max_concurrent_orders", nullable(unsigned())),
+                ],
+            ),
+        ),
+        (
+            "RestaurantOrderClosed",
+            event(
+                "RestaurantOrderClosed",
+                vec![
+                    ("identifier", reference("RestaurantId")),
+                    ("order_identifier", reference("OrderId")),
+                ],
+            ),
+        ),
+        (
+            "MenuItemMarkedUnavailable",
+            event(
+                "MenuItemMarkedUnavailable",
+                vec![
+                    ("identifier", reference("RestaurantId")),
+                    ("menu_item_id", reference("MenuItemId")),
+                ],
+            ),
+        ),
+        (
+            "MenuItemMarkedAvailable",
+            event(
+                "MenuItemMarkedAvailable",
+                vec![
+                    ("identifier", reference("RestaurantId")),
+                    ("menu_item_id", reference("MenuItemId")),
+                ],
+            ),
+        ),
+        (
+            "RestaurantOpeningHoursChanged",
+            event(
+                "RestaurantOpeningHoursChanged",
+                vec![
+                    ("identifier", reference("RestaurantId")),
+                    ("opening_hours", nullable(reference("OpeningHours"))),
+                ],
+            ),
+        ),
+        (
+            "RestaurantOrdersMigrated",
+            event(
+                "RestaurantOrdersMigrated",
+                vec![
+                    ("identifier", reference("RestaurantId")),
+                    ("target_identifier", reference("RestaurantId")),
+                    ("orders", array(reference("MigratedOrder"))),
+                ],
+            ),
+        ),
+        (
+            "RestaurantOrdersReceived",
+            event(
+                "RestaurantOrdersReceived",
+                vec![
+                    ("identifier", reference("RestaurantId")),
+                    ("source_identifier", reference("RestaurantId")),
+                    ("orders", array(reference("MigratedOrder"))),
+                ],
+            ),
+        ),
+        (
+            "OrderMigrated",
+            event(
+                "OrderMigrated",
+                vec![
+                    ("identifier", reference("OrderId")),
+                    ("restaurant_identifier", reference("RestaurantId")),
+                    ("status", reference("OrderStatus")),
+                ],
+            ),
+        ),
+        (
+            "RestaurantPromotionApplied",
+            event(
+                "RestaurantPromotionApplied",
+                vec![
+                    ("identifier", reference("RestaurantId")),
+                    ("promotion", reference("RestaurantPromotion")),
+                ],
+            ),
+        ),
+        (
+            "RestaurantPromotionWithdrawn",
+            event(
+                "RestaurantPromotionWithdrawn",
+                vec![
+                    ("identifier", reference("RestaurantId")),
+                    ("promotion_id", reference("PromotionId")),
+                ],
+            ),
+        ),
+        (
+            "PromotionCreated",
+            event(
+                "PromotionCreated",
+                vec![
+                    ("identifier", reference("PromotionId")),
+                    ("restaurant_identifier", reference("RestaurantId")),
+                    ("name", reference("PromotionName")),
+                    ("percentage", reference("DiscountPercentage")),
+                    ("menu_item_ids", array(reference("MenuItemId"))),
+                ],
+            ),
+        ),
+        (
+            "PromotionExpired",
+            event(
+                "PromotionExpired",
+                vec![
+                    ("identifier", reference("PromotionId")),
+                    ("restaurant_identifier", reference("RestaurantId")),
+                ],
+            ),
+        ),
+        (
+            "RestaurantOrderCancelled",
+            event(
+                "RestaurantOrderCancelled",
+                vec![
+                    ("identifier", reference("RestaurantId")),
+                    ("order_identifier", reference("OrderId")),
+                    ("rejected", json!({"type": "boolean"})),
+                ],
+            ),
+        ),
+        (
+            "OrderCancelled",
+            event(
+                "OrderCancelled",
+                vec![
+                    ("identifier", reference("OrderId")),
+                    ("restaurant_identifier", reference("RestaurantId")),
+                    ("status", reference("OrderStatus")),
+                ],
+            ),
+        ),
+        (
+            "InventoryRestocked",
+            event(
+                "InventoryRestocked",
+                vec![
+                    ("identifier", reference("InventoryId")),
+                    ("restaurant_identifier", reference("RestaurantId")),
+                    ("items", array(reference("StockItem"))),
+                ],
+            ),
+        ),
+        (
+            "StockReserved",
+            event(
+                "StockReserved",
+                vec![
+                    ("identifier", reference("InventoryId")),
+                    ("order_identifier", reference("OrderId")),
+                    ("items", array(reference("StockItem"))),
+                ],
+            ),
+        ),
+        (
+            "StockReleased",
+            event(
+                "StockReleased",
+                vec![
+                    ("identifier", reference("InventoryId")),
+                    ("order_identifier", reference("OrderId")),
+                    ("items", array(reference("StockItem"))),
+                ],
+            ),
+        ),
+        (
+            "RestaurantOrderSplit",
+            with_defaults(
+                event(
+                    "RestaurantOrderSplit",
+                    vec![
+                        ("identifier", reference("RestaurantId")),
+                        ("group_identifier", reference("OrderGroupId")),
+                        ("orders", array(reference("SplitOrder"))),
+                        ("customer_identifier", nullable(reference("CustomerId"))),
+                    ],
+                ),
+                &["customer_identifier"],
+            ),
+        ),
+        (
+            "GiftCardIssued",
+            event(
+                "GiftCardIssued",
+                vec![
+                    ("identifier", reference("GiftCardId")),
+                    ("amount", reference("Money")),
+                ],
+            ),
+        ),
+        (
+            "GiftCardRedeemed",
+            event(
+                "GiftCardRedeemed",
+                vec![
+                    ("identifier", reference("GiftCardId")),
+                    ("restaurant_identifier", reference("RestaurantId")),
+                    ("order_identifier", reference("OrderId")),
+                    ("amount", reference("Money")),
+                ],
+            ),
+        ),
+        (
+            "GiftCardRefunded",
+            event(
+                "GiftCardRefunded",
+                vec![
+                    ("identifier", reference("GiftCardId")),
+                    ("restaurant_identifier", reference("RestaurantId")),
+                    ("order_identifier", reference("OrderId")),
+                    ("amount", reference("Money")),
+                ],
+            ),
+        ),
+        (
+            "CourierLocationUpdated",
+            event(
+                "CourierLocationUpdated",
+                vec![
+                    ("identifier", reference("CourierId")),
+                    ("latitude", reference("Coordinate")),
+                    ("longitude", reference("Coordinate")),
+                ],
+            ),
+        ),
+    ]
+}
+
+fn view_states() -> Vec<(&'static str, Value)> {
+    vec![
+        (
+            "RestaurantViewState",
+            with_defaults(
+                object(vec![
+                    ("identifier", reference("RestaurantId")),
+                    ("name", reference("RestaurantName")),
+                    ("menu", reference("RestaurantMenu")),
+                    ("unavailable_items", array(reference("MenuItemId"))),
+                    ("opening_hours", nullable(reference("OpeningHours"))),
+                    ("merged_into", nullable(reference("RestaurantId"))),
+                    ("promotions", array(reference("RestaurantPromotion"))),
+                ]),
+                &[
+                    "unavailable_items",
+                    "opening_hours",
+                    "merged_into",
+                    "promotions",
+                ],
+            ),
+        ),
+        (
+            "OrderViewState",
+            with_defaults(
+                object(vec![
+                    ("identifier", reference("OrderId")),
+                    ("restaurant_identifier", reference("RestaurantId")),
+                    ("status", reference("OrderStatus")),
+                    ("line_items", array(reference("OrderLineItem"))),
+                    ("total", nullable(reference("Money"))),
+                    ("discounts", array(reference("AppliedDiscount"))),
+                    ("discounted_total", nullable(reference("Money"))),
+                ]),
+                &["total", "discounts", "discounted_total"],
+            ),
+        ),
+    ]
+}
+
+/// The object: all the properties are required (see `with_defaults`), and no other properties are allowed.
+fn object(properties: Vec<(&str, Value)>) -> Value {
+    let required: Vec<&str> = properties.iter().map(|(name, _)| *name).collect();
+    let properties: Map<String, Value> = properties
+        .into_iter()
+        .map(|(name, schema)| (name.to_string(), schema))
+        .collect();
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false,
+    })
+}
+
+/// The properties with the (serde) defaults are not required: they are missing in the payloads persisted before they were introduced.
+fn with_defaults(mut schema: Value, defaults: &[&str]) -> Value {
+    if let Some(required) = schema["required"].as_array_mut() {
+        required.retain(|name| !defaults.iter().any(|default| name == default));
+    }
+    schema
+}
+
+/// The command, tagged by its `type`.
+fn tagged(name: &str, fields: Vec<(&str, Value)>) -> Value {
+    let mut all_fields = vec![("type", json!({"const": name}))];
+    all_fields.extend(fields);
+    object(all_fields)
+}
+
+/// The event, tagged by its `type`, with the `final` indicator.
+fn event(name: &str, fields: Vec<(&str, Value)>) -> Value {
+    let mut all_fields = fields;
+    all_fields.push(("final", json!({"type": "boolean"})));
+    tagged(name, all_fields)
+}
+
+fn reference(name: &str) -> Value {
+    json!({"$ref": "#/$defs/".to_string() + name})
+}
+
+fn array(items: Value) -> Value {
+    json!({"type": "array", "items": items})
+}
+
+fn nullable(schema: Value) -> Value {
+    json!({"anyOf": [schema, {"type": "null"}]})
+}
+
+fn uuid() -> Value {
+    json!({"type": "string", "format": "uuid"})
+}
+
+fn unsigned() -> Value {
+    json!({"type": "integer", "minimum": 0})
+}
@@ -0,0 +1,95 @@
+use crate::domain::Command;
+use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::framework::infrastructure::to_payload;
+use pgrx::{IntoDatum, JsonB, PgBuiltInOids, Spi};
+
+/// A repository of the command batch, queued incrementally and executed at once.
+/// The batch is stored in a temporary table, which is dropped at the end of the transaction.
+pub struct CommandBatchRepository {}
+
+impl CommandBatchRepository {
+    /// Creates a new command batch repository.
+    pub fn new() -> Self {
+        CommandBatchRepository {}
+    }
+
+    /// Begins a new (empty) batch. The previous batch of the transaction, if any, is discarded.
+    pub fn begin(&self) -> Result<(), ErrorMessage> {
+        Spi::run(
+            "DROP TABLE IF EXISTS pg_temp.fmodel_command_batch;
+             CREATE TEMP TABLE fmodel_command_batch (
+                 position BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+                 command JSONB NOT NULL
+             ) ON COMMIT DROP",
+        )
+        .map_err(|err| ErrorMessage::spi("begin the command batch", None, &err))
+    }
+
+    /// Queues the command to the batch, and returns the number of the queued commands.
+    pub fn queue(&self, command: &Command) -> Result<i64, ErrorMessage> {
+        self.ensure_begun()?;
+        let data = serde_json::to_value(command).map_err(|err| ErrorMessage {
+            message: "Failed to serialize the command: ".to_string() + &err.to_string(),
+            context: None,
+        })?;
+        Spi::run_with_args(
+            "INSERT INTO pg_temp.fmodel_command_batch (command) VALUES ($1)",
+            Some(vec![(
+                PgBuiltInOids::JSONBOID.oid(),
+                JsonB(data).into_datum(),
+            )]),
+        )
+        .and_then(|_| Spi::get_one::<i64>("SELECT COUNT(*) FROM pg_temp.fmodel_command_batch"))
+        .map(|count| count.unwrap_or_default())
+        .map_err(|err| ErrorMessage::spi("queue the command", None, &err))
+    }
+
+    /// Fetches the queued commands, in the order they were queued, and ends the batch.
+    pub fn take(&self) -> Result<Vec<Command>, ErrorMessage> {
+        self.ensure_begun()?;
+        let commands = Spi::connect(|client| {
+            let mut results = Vec::new();
+            let tup_table = client
+                .select(
+                    "SELECT command FROM pg_temp.fmodel_command_batch ORDER BY position",
+                    None,
+                    None,
+                )
+                .map_err(|err| ErrorMessage::spi("fetch the command batch", None, &err))?;
+            for row in tup_table {
+                let data = row["command"]
+                    .value::<JsonB>()
+                    .map_err(|err| {
+                        ErrorMessage::spi(
+                            "fetch the command (map `command` to `JsonB`)",
+                            None,
+                            &err,
+                        )
+                    })?
+                    .ok_or(ErrorMessage {
+                        message: "Failed to fetch the command (map `command` to `JsonB`): No command found".to_string(),
+                        context: None,
+                    })?;
+                results.push(to_payload::<Command>(data)?);
+            }
+            Ok(results)
+        })?;
+        Spi::run("DROP TABLE pg_temp.fmodel_command_batch")
+            .map_err(|err| ErrorMessage::spi("end the command batch", None, &err))?;
+        Ok(commands)
+    }
+
+    /// Fails if there is no batch begun in the current transaction.
+    fn ensure_begun(&self) -> Result<(), ErrorMessage> {
+        match Spi::get_one::<bool>(
+            "SELECT to_regclass('pg_temp.fmodel_command_batch') IS NOT NULL",
+        ) {
+            Ok(Some(true)) => Ok(()),
+            Ok(_) => Err(ErrorMessage {
+                message: "No command batch in progress. Call `fmodel_begin_batch()` first (in the same transaction)".to_string(),
+                context: None,
+            }),
+            Err(err) => Err(ErrorMessage::spi("fetch the command batch", None, &err)),
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use crate::domain::api::{CourierId, CourierLocationUpdated};
+use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::framework::infrastructure::guc::{
+    CourierLocationPolicy, COURIER_LOCATION_BUFFER_SIZE, COURIER_LOCATION_INTERVAL,
+    COURIER_LOCATION_POLICY,
+};
+use pgrx::{IntoDatum, PgBuiltInOids, Spi, TimestampWithTimeZone, Uuid};
+
+/// The location of the courier in the ring-buffer table: the latitude and the longitude (in degrees), and the time it was reported.
+pub type CourierLocationRow = (f64, f64, TimestampWithTimeZone);
+
+/// Repository of the courier locations: the policy of the high-frequency telemetry events (`fmodel.courier_location_policy`), and the ring-buffer table of the last locations of every courier (`courier_locations`).
+pub struct CourierLocationRepository {}
+
+impl CourierLocationRepository {
+    /// Create a new CourierLocationRepository
+    pub fn new() -> Self {
+        CourierLocationRepository {}
+    }
+
+    /// Whether the location is appended to the event log, by the policy:
+    /// - `buffer`: never, the location is written to the ring-buffer table instead
+    /// - `downsample`: if no location of the courier was appended to the event log in the interval (`fmodel.courier_location_interval`), otherwise the location is dropped
+    pub fn retain(&self, event: &CourierLocationUpdated) -> Result<bool, ErrorMessage> {
+        match COURIER_LOCATION_POLICY.get() {
+            CourierLocationPolicy::Buffer => self.save(event).map(|_| false),
+            CourierLocationPolicy::Downsample => self.is_due(&event.identifier),
+        }
+    }
+
+    /// Writes the location to the ring buffer of the courier: it overwrites the oldest of the last `fmodel.courier_location_buffer_size` locations, so the table does not grow with the reports.
+    /// The head of the ring buffer (`courier_location_heads`) is locked by the update, so the concurrent reports of the courier take the distinct slots.
+    fn save(&self, event: &CourierLocationUpdated) -> Result<(), ErrorMessage> {
+        Spi::run_with_args(
+            r#"WITH head AS (
+                   INSERT INTO courier_location_heads (courier_id, sequence) VALUES ($1, 1)
+                   ON CONFLICT (store_id, courier_id) DO UPDATE SET sequence = courier_location_heads.sequence + 1
+                   RETURNING sequence
+               )
+               INSERT INTO courier_locations (courier_id, slot, sequence, latitude, longitude)
+               SELECT $1, sequence % $4, sequence, $2, $3 FROM head
+               ON CONFLICT (store_id, courier_id, slot) DO UPDATE
+               SET sequence = EXCLUDED.sequence, latitude = EXCLUDED.latitude, longitude = EXCLUDED.longitude, recorded_at = EXCLUDED.recorded_at"#,
+            Some(vec![
+                (
+                    PgBuiltInOids::UUIDOID.oid(),
+                    Uuid::from_bytes(event.identifier.0.into_bytes()).into_datum(),
+                ),
+                (PgBuiltInOids::INT4OID.oid(), event.latitude.0.into_datum()),
+                (PgBuiltInOids::INT4OID.oid(), event.longitude.0.into_datum()),
+                (
+                    PgBuiltInOids::INT4OID.oid(),
+                    COURIER_LOCATION_BUFFER_SIZE.get().into_datum(),
+                ),
+            ]),
+        )
+        .map_err(|err| {
+            ErrorMessage::spi(
+                "write the courier location to the ring buffer",
+                Some(event.identifier.to_string()),
+                &err,
+            )
+        })
+    }
+
+    /// No location of the courier was appended to the event log in the interval (`fmodel.courier_location_interval`).
+    fn is_due(&self, courier_identifier: &CourierId) -> Result<bool, ErrorMessage> {
+        Spi::get_one_with_args::<bool>(
+            r#"SELECT NOT EXISTS (
+                   SELECT 1 FROM events
+                   WHERE decider = 'Courier' AND decider_id = $1 AND store_id = fmodel_store_id()
+                     AND created_at > now() - make_interval(secs => $2)
+               )"#,
+            vec![
+                (
+                    PgBuiltInOids::TEXTOID.oid(),
+                    courier_identifier.0.to_string().into_datum(),
+                ),
+                (
+                    PgBuiltInOids::INT4OID.oid(),
+                    COURIER_LOCATION_INTERVAL.get().into_datum(),
+                ),
+            ],
+        )
+        .map(|due| due.unwrap_or(true))
+        .map_err(|err| {
+            ErrorMessage::spi(
+                "check the courier location interval",
+                Some(courier_identifier.to_string()),
+                &err,
+            )
+        })
+    }
+
+    /// Fetches (up to `limit`) the last locations of the courier from the ring buffer, the most recent first.
+    pub fn fetch_track(
+        &self,
+        courier_identifier: &CourierId,
+        limit: i64,
+    ) -> Result<Vec<CourierLocationRow>, ErrorMessage> {
+        Spi::connect(|client| {
+            let mut results = Vec::new();
+            let tup_table = client.select(
+                "SELECT latitude / 1000000.0::FLOAT8 AS latitude, longitude / 1000000.0::FLOAT8 AS longitude, recorded_at
+                 FROM courier_locations
+                 WHERE store_id = fmodel_store_id() AND courier_id = $1
+                 ORDER BY sequence DESC LIMIT $2",
+                None,
+                Some(vec![
+                    (
+                        PgBuiltInOids::UUIDOID.oid(),
+                        Uuid::from_bytes(courier_identifier.0.into_bytes()).into_datum(),
+                    ),
+                    (PgBuiltInOids::INT8OID.oid(), limit.into_datum()),
+                ]),
+            )?;
+            for row in tup_table {
+                if let (Some(latitude), Some(longitude), Some(recorded_at)) = (
+                    row["latitude"].value::<f64>()?,
+                    row["longitude"].value::<f64>()?,
+                    row["recorded_at"].value::<TimestampWithTimeZone>()?,
+                ) {
+                    results.push((latitude, longitude, recorded_at));
+                }
+            }
+            Ok(results)
+        })
+        .map_err(|err: pgrx::spi::Error| {
+            ErrorMessage::spi(
+                "fetch the courier track",
+                Some(courier_identifier.to_string()),
+                &err,
+            )
+        })
+    }
+}
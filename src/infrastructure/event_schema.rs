@@ -0,0 +1,26 @@
+use crate::framework::infrastructure::event_schema::EventSchema;
+use crate::infrastructure::client_types::client_json_schema;
+
+/// The required fields of the restaurant and order events, for the `check_event_schema` trigger.
+/// Derived from the JSON Schema of the events (`client_types`), which mirrors what the serde (JSON) deserializer of the events expects: the fields with the (serde) defaults are not required.
+pub fn event_schemas() -> Vec<EventSchema> {
+    let schema = client_json_schema();
+    schema["$defs"]["Event"]["oneOf"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|reference| reference["$ref"].as_str())
+        .map(|reference| reference.trim_start_matches("#/$defs/"))
+        .map(|event| EventSchema {
+            event: event.to_string(),
+            required_fields: schema["$defs"][event]["required"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|field| field.as_str())
+                .filter(|field| *field != "type")
+                .map(|field| field.to_string())
+                .collect(),
+        })
+        .collect()
+}
@@ -0,0 +1,75 @@
+use crate::framework::infrastructure::event_stream::EventStreamColumn;
+
+/// The commonly filtered columns of the restaurant and order events, decoded from the serde (JSON) representation of the events, for the `fmodel_event_stream` view.
+/// Keep it in sync with the events (`crate::domain::api`).
+pub const EVENT_STREAM_COLUMNS: [EventStreamColumn; 4] = [
+    EventStreamColumn {
+        name: "restaurant_id",
+        sql_type: "UUID",
+        fields: &[
+            ("RestaurantCreated", "identifier"),
+            ("RestaurantMenuChanged", "identifier"),
+            ("OrderPlaced", "identifier"),
+            ("OrderLineItemsModified", "identifier"),
+            ("PaymentCaptured", "identifier"),
+            ("PaymentRefunded", "identifier"),
+            ("RestaurantCapacityChanged", "identifier"),
+            ("RestaurantOrderClosed", "identifier"),
+            ("MenuItemMarkedUnavailable", "identifier"),
+            ("MenuItemMarkedAvailable", "identifier"),
+            ("RestaurantOpeningHoursChanged", "identifier"),
+            ("RestaurantOrdersMigrated", "identifier"),
+            ("RestaurantOrdersReceived", "identifier"),
+            ("OrderCreated", "restaurant_identifier"),
+            ("OrderLineItemsUpdated", "restaurant_identifier"),
+            ("OrderPrepared", "restaurant_identifier"),
+            ("OrderMigrated", "restaurant_identifier"),
+            ("RestaurantPromotionApplied", "identifier"),
+            ("RestaurantPromotionWithdrawn", "identifier"),
+            ("PromotionCreated", "restaurant_identifier"),
+            ("PromotionExpired", "restaurant_identifier"),
+            ("RestaurantOrderCancelled", "identifier"),
+            ("OrderCancelled", "restaurant_identifier"),
+            ("InventoryRestocked", "restaurant_identifier"),
+            ("RestaurantOrderSplit", "identifier"),
+            ("GiftCardRedeemed", "restaurant_identifier"),
+            ("GiftCardRefunded", "restaurant_identifier"),
+        ],
+    },
+    EventStreamColumn {
+        name: "order_id",
+        sql_type: "UUID",
+        fields: &[
+            ("OrderPlaced", "order_identifier"),
+            ("OrderLineItemsModified", "order_identifier"),
+            ("PaymentCaptured", "order_identifier"),
+            ("PaymentRefunded", "order_identifier"),
+            ("RestaurantOrderClosed", "order_identifier"),
+            ("OrderCreated", "identifier"),
+            ("OrderPrepared", "identifier"),
+            ("OrderLineItemsUpdated", "identifier"),
+            ("OrderMigrated", "identifier"),
+            ("RestaurantOrderCancelled", "order_identifier"),
+            ("OrderCancelled", "identifier"),
+            ("StockReserved", "order_identifier"),
+            ("StockReleased", "order_identifier"),
+            ("GiftCardRedeemed", "order_identifier"),
+            ("GiftCardRefunded", "order_identifier"),
+        ],
+    },
+    EventStreamColumn {
+        name: "order_status",
+        sql_type: "TEXT",
+        fields: &[
+            ("OrderCreated", "status"),
+            ("OrderPrepared", "status"),
+            ("OrderMigrated", "status"),
+            ("OrderCancelled", "status"),
+        ],
+    },
+    EventStreamColumn {
+        name: "menu_version",
+        sql_type: "BIGINT",
+        fields: &[("RestaurantMenuChanged", "menu_version")],
+    },
+];
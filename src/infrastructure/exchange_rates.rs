@@ -0,0 +1,43 @@
+use crate::domain::api::{Currency, ExchangeRate};
+use crate::domain::exchange_rates::ExchangeRates;
+use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::framework::infrastructure::guc::REFERENCE_CURRENCY;
+use pgrx::{error, IntoDatum, PgBuiltInOids, Spi};
+
+/// The exchange rates of the database: the current rates of the `exchange_rates` table (valid at the start of the current transaction, `now()`), and the reference currency of the session (`fmodel.reference_currency`).
+/// The failed lookup aborts the command handling with the SPI error: it is not reported as the unknown rate.
+pub struct PostgresExchangeRates;
+
+impl ExchangeRates for PostgresExchangeRates {
+    fn reference_currency(&self) -> Currency {
+        Currency(
+            REFERENCE_CURRENCY
+                .get()
+                .and_then(|currency| currency.to_str().ok().map(str::to_owned))
+                .unwrap_or_else(|| "USD".to_string()),
+        )
+    }
+
+    fn rate(&self, from: &Currency, to: &Currency) -> Option<ExchangeRate> {
+        if from == to {
+            return Some(ExchangeRate(ExchangeRate::SCALE));
+        }
+        Spi::get_one_with_args::<i64>(
+            "SELECT round(rate * 1000000)::BIGINT FROM exchange_rates
+             WHERE from_currency = $1 AND to_currency = $2 AND valid_from <= now()
+             ORDER BY valid_from DESC LIMIT 1",
+            vec![
+                (PgBuiltInOids::TEXTOID.oid(), from.0.as_str().into_datum()),
+                (PgBuiltInOids::TEXTOID.oid(), to.0.as_str().into_datum()),
+            ],
+        )
+        .unwrap_or_else(|err| {
+            error!(
+                "{}",
+                ErrorMessage::spi("fetch the exchange rate", None, &err)
+            )
+        })
+        .and_then(|rate| u64::try_from(rate).ok())
+        .map(ExchangeRate)
+    }
+}
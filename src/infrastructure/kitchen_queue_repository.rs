@@ -0,0 +1,170 @@
+use crate::domain::api::{OrderEvent, OrderStatus, RestaurantId};
+use crate::domain::kitchen_queue_view::KitchenOrder;
+use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::framework::infrastructure::event_repository::EventPosition;
+use crate::framework::infrastructure::to_payload;
+use crate::framework::infrastructure::view_state_repository::ViewStateRepository;
+use pgrx::{Interval, IntoDatum, JsonB, PgBuiltInOids, Spi, TimestampWithTimeZone, Uuid};
+
+/// The row of the kitchen queue: the order id, the line items, the placement time, and the time elapsed since the placement.
+pub type KitchenQueueRow = (Uuid, JsonB, TimestampWithTimeZone, Interval);
+
+/// Repository of the kitchen queue view: a row per open order, with the time the order was placed (the time its `OrderCreated` event was appended to the event store).
+/// The order is removed from the queue once it is prepared, cancelled or rejected.
+pub struct KitchenQueueRepository {}
+
+impl KitchenQueueRepository {
+    /// Create a new KitchenQueueRepository
+    pub fn new() -> Self {
+        KitchenQueueRepository {}
+    }
+
+    /// Fetches (up to `limit`) open orders of the restaurant, the longest waiting first.
+    pub fn fetch_next_orders(
+        &self,
+        restaurant_id: &RestaurantId,
+        limit: i64,
+    ) -> Result<Vec<KitchenQueueRow>, ErrorMessage> {
+        // Read-only SPI: the queue is read by the `STABLE` function(s)
+        Spi::connect(|client| {
+            let mut results = Vec::new();
+            let tup_table = client.select(
+                "SELECT order_id, data->'line_items' AS line_items, placed_at, now() - placed_at AS elapsed
+                 FROM kitchen_queue
+                 WHERE store_id = fmodel_store_id() AND restaurant_id = $1
+                 ORDER BY placed_at, last_offset
+                 LIMIT $2",
+                None,
+                Some(vec![
+                    (
+                        PgBuiltInOids::UUIDOID.oid(),
+                        restaurant_id.0.to_string().into_datum(),
+                    ),
+                    (PgBuiltInOids::INT8OID.oid(), limit.into_datum()),
+                ]),
+            )?;
+            for row in tup_table {
+                if let (Some(order_id), Some(line_items), Some(placed_at), Some(elapsed)) = (
+                    row["order_id"].value::<Uuid>()?,
+                    row["line_items"].value::<JsonB>()?,
+                    row["placed_at"].value::<TimestampWithTimeZone>()?,
+                    row["elapsed"].value::<Interval>()?,
+                ) {
+                    results.push((order_id, line_items, placed_at, elapsed));
+                }
+            }
+            Ok(results)
+        })
+        .map_err(|err: pgrx::spi::Error| {
+            ErrorMessage::spi(
+                "fetch the kitchen queue",
+                Some(restaurant_id.0.to_string()),
+                &err,
+            )
+        })
+    }
+}
+
+/// The order id of the event.
+fn order_id(event: &OrderEvent) -> Uuid {
+    let identifier = match event {
+        OrderEvent::Created(event) => &event.identifier,
+        OrderEvent::Prepared(event) => &event.identifier,
+        OrderEvent::LineItemsUpdated(event) => &event.identifier,
+        OrderEvent::Migrated(event) => &event.identifier,
+        OrderEvent::Cancelled(event) => &event.identifier,
+    };
+    Uuid::from_bytes(identifier.0.into_bytes())
+}
+
+/// Implementation of the view state repository for the kitchen queue `view` state.
+impl ViewStateRepository<OrderEvent, Option<KitchenOrder>> for KitchenQueueRepository {
+    /// Fetches current state, based on the event.
+    /// The orders that left the queue have no state: their later events are ignored.
+    fn fetch_state(
+        &self,
+        event: &OrderEvent,
+    ) -> Result<Option<Option<KitchenOrder>>, ErrorMessage> {
+        Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT (SELECT data FROM kitchen_queue WHERE store_id = fmodel_store_id() AND order_id = $1)",
+                    Some(1),
+                    Some(vec![(
+                        PgBuiltInOids::UUIDOID.oid(),
+                        order_id(event).into_datum(),
+                    )]),
+                )?
+                .first()
+                .get_one::<JsonB>()
+        })
+        .map_err(|err| ErrorMessage::spi("fetch the kitchen order", None, &err))?
+        .map(to_payload::<KitchenOrder>)
+        .transpose()
+        .map(Some)
+    }
+    /// Fetches the offset of the last event applied to the view row of the event.
+    fn fetch_applied_offset(&self, event: &OrderEvent) -> Result<Option<i64>, ErrorMessage> {
+        Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT (SELECT last_offset FROM kitchen_queue WHERE store_id = fmodel_store_id() AND order_id = $1)",
+                    Some(1),
+                    Some(vec![(
+                        PgBuiltInOids::UUIDOID.oid(),
+                        order_id(event).into_datum(),
+                    )]),
+                )?
+                .first()
+                .get_one::<i64>()
+        })
+        .map_err(|err| ErrorMessage::spi("fetch the kitchen order version", None, &err))
+    }
+    /// Saves the new state: the open order is (re)queued, keeping its placement time; the closed order is removed from the queue.
+    fn save(
+        &self,
+        state: &Option<KitchenOrder>,
+        position: &EventPosition,
+    ) -> Result<Option<KitchenOrder>, ErrorMessage> {
+        let Some(order) = state else {
+            return Ok(None);
+        };
+        let id = Uuid::from_bytes(order.order_identifier.0.into_bytes());
+        if order.status != OrderStatus::Created {
+            Spi::run_with_args(
+                "DELETE FROM kitchen_queue WHERE store_id = fmodel_store_id() AND order_id = $1",
+                Some(vec![(PgBuiltInOids::UUIDOID.oid(), id.into_datum())]),
+            )
+            .map_err(|err| ErrorMessage::spi("remove the kitchen order", None, &err))?;
+            return Ok(state.clone());
+        }
+        let data = serde_json::to_value(order).map_err(|err| ErrorMessage {
+            message: "Failed to serialize the kitchen order: ".to_string() + &err.to_string(),
+            context: None,
+        })?;
+        // The placement time is the time the event (`OrderCreated`) was appended to the event store, so the rebuilt queue keeps it
+        Spi::run_with_args(
+            r#"INSERT INTO kitchen_queue (order_id, restaurant_id, data, placed_at, last_event_id, last_offset)
+               SELECT $1, $2, $3, created_at, $4, $5 FROM events WHERE "offset" = $5
+               ON CONFLICT (store_id, order_id) DO UPDATE SET restaurant_id = EXCLUDED.restaurant_id,
+                                                    data = EXCLUDED.data,
+                                                    last_event_id = EXCLUDED.last_event_id,
+                                                    last_offset = EXCLUDED.last_offset"#,
+            Some(vec![
+                (PgBuiltInOids::UUIDOID.oid(), id.into_datum()),
+                (
+                    PgBuiltInOids::UUIDOID.oid(),
+                    Uuid::from_bytes(order.restaurant_identifier.0.into_bytes()).into_datum(),
+                ),
+                (PgBuiltInOids::JSONBOID.oid(), JsonB(data).into_datum()),
+                (
+                    PgBuiltInOids::UUIDOID.oid(),
+                    Uuid::from_bytes(position.event_id.into_bytes()).into_datum(),
+                ),
+                (PgBuiltInOids::INT8OID.oid(), position.offset.into_datum()),
+            ]),
+        )
+        .map_err(|err| ErrorMessage::spi("save the kitchen order", None, &err))?;
+        Ok(state.clone())
+    }
+}
@@ -0,0 +1,207 @@
+use crate::framework::infrastructure::migrations::Migration;
+
+/// The schema migrations of the extension, evolving the event store and the materialized views of the populated (production) stores across the extension upgrades.
+/// The fresh install creates the latest schema; the migrations are idempotent, so they are recorded as applied without changing it.
+/// Migrations are append-only: never change an applied migration, add a new one instead.
+pub const MIGRATIONS: [Migration; 40] = [
+    Migration {
+        version: 1,
+        description: "events: payload hash (deduplication)",
+        sql: include_str!("../../sql/migrations/0001_events_payload_hash.sql"),
+    },
+    Migration {
+        version: 2,
+        description: "events: correlation id",
+        sql: include_str!("../../sql/migrations/0002_events_correlation_id.sql"),
+    },
+    Migration {
+        version: 3,
+        description: "events: per-stream sequence number",
+        sql: include_str!("../../sql/migrations/0003_events_sequence_number.sql"),
+    },
+    Migration {
+        version: 4,
+        description: "projections registry, rebuilds and command idempotency keys",
+        sql: include_str!("../../sql/migrations/0004_projections.sql"),
+    },
+    Migration {
+        version: 5,
+        description: "materialized views: position of the last applied event, orders archive",
+        sql: include_str!("../../sql/migrations/0005_view_positions.sql"),
+    },
+    Migration {
+        version: 6,
+        description: "events: partitioning per decider type",
+        sql: include_str!("../../sql/migrations/0006_events_partitioning.sql"),
+    },
+    Migration {
+        version: 7,
+        description: "restaurant search projection",
+        sql: include_str!("../../sql/migrations/0007_restaurants_search.sql"),
+    },
+    Migration {
+        version: 8,
+        description: "restaurant daily orders (aggregating) projection",
+        sql: include_str!("../../sql/migrations/0008_restaurant_daily_orders.sql"),
+    },
+    Migration {
+        version: 9,
+        description: "saga execution traces",
+        sql: include_str!("../../sql/migrations/0009_saga_traces.sql"),
+    },
+    Migration {
+        version: 10,
+        description: "saga rules",
+        sql: include_str!("../../sql/migrations/0010_saga_rules.sql"),
+    },
+    Migration {
+        version: 11,
+        description: "events: transaction id (commit-ordered reads)",
+        sql: include_str!("../../sql/migrations/0011_events_transaction_id.sql"),
+    },
+    Migration {
+        version: 12,
+        description: "events: store id (logical event stores)",
+        sql: include_str!("../../sql/migrations/0012_events_store_id.sql"),
+    },
+    Migration {
+        version: 13,
+        description: "large event payload offloading",
+        sql: include_str!("../../sql/migrations/0013_event_payloads.sql"),
+    },
+    Migration {
+        version: 14,
+        description: "restaurant order board (cross-domain) projection",
+        sql: include_str!("../../sql/migrations/0014_restaurant_order_board.sql"),
+    },
+    Migration {
+        version: 15,
+        description: "order line items modification events",
+        sql: include_str!("../../sql/migrations/0015_order_line_items_modified.sql"),
+    },
+    Migration {
+        version: 16,
+        description: "order payments and refunds, payments (aggregating) projection",
+        sql: include_str!("../../sql/migrations/0016_payments.sql"),
+    },
+    Migration {
+        version: 17,
+        description: "restaurant capacity events",
+        sql: include_str!("../../sql/migrations/0017_restaurant_capacity.sql"),
+    },
+    Migration {
+        version: 18,
+        description: "menu item availability events",
+        sql: include_str!("../../sql/migrations/0018_menu_item_availability.sql"),
+    },
+    Migration {
+        version: 19,
+        description: "restaurant opening hours event",
+        sql: include_str!("../../sql/migrations/0019_restaurant_opening_hours.sql"),
+    },
+    Migration {
+        version: 20,
+        description: "saga command ledger",
+        sql: include_str!("../../sql/migrations/0020_saga_commands.sql"),
+    },
+    Migration {
+        version: 21,
+        description: "saga command ledger: handling attempts",
+        sql: include_str!("../../sql/migrations/0021_saga_commands_attempts.sql"),
+    },
+    Migration {
+        version: 22,
+        description: "projections: statement mode (statement-level trigger)",
+        sql: include_str!("../../sql/migrations/0022_projections_statement_mode.sql"),
+    },
+    Migration {
+        version: 23,
+        description: "deciders: restaurant orders migration (merge/split) events",
+        sql: include_str!("../../sql/migrations/0023_restaurant_orders_migration.sql"),
+    },
+    Migration {
+        version: 24,
+        description: "events: payload hash of the canonical JSON",
+        sql: include_str!("../../sql/migrations/0024_events_canonical_payload_hash.sql"),
+    },
+    Migration {
+        version: 25,
+        description: "events and projections: owner (row-level security)",
+        sql: include_str!("../../sql/migrations/0025_projections_owner.sql"),
+    },
+    Migration {
+        version: 26,
+        description: "workflow event streams (event-sourced saga state)",
+        sql: include_str!("../../sql/migrations/0026_workflow_events.sql"),
+    },
+    Migration {
+        version: 27,
+        description: "events: tags",
+        sql: include_str!("../../sql/migrations/0027_events_tags.sql"),
+    },
+    Migration {
+        version: 28,
+        description: "event corrections",
+        sql: include_str!("../../sql/migrations/0028_event_corrections.sql"),
+    },
+    Migration {
+        version: 29,
+        description: "exchange rates",
+        sql: include_str!("../../sql/migrations/0029_exchange_rates.sql"),
+    },
+    Migration {
+        version: 30,
+        description: "events: schema check",
+        sql: include_str!("../../sql/migrations/0030_event_schema_check.sql"),
+    },
+    Migration {
+        version: 31,
+        description: "promotions",
+        sql: include_str!("../../sql/migrations/0031_promotions.sql"),
+    },
+    Migration {
+        version: 32,
+        description: "inventory",
+        sql: include_str!("../../sql/migrations/0032_inventory.sql"),
+    },
+    Migration {
+        version: 33,
+        description: "order groups",
+        sql: include_str!("../../sql/migrations/0033_order_groups.sql"),
+    },
+    Migration {
+        version: 34,
+        description: "kitchen queue",
+        sql: include_str!("../../sql/migrations/0034_kitchen_queue.sql"),
+    },
+    Migration {
+        version: 35,
+        description: "notifications outbox",
+        sql: include_str!("../../sql/migrations/0035_notifications_outbox.sql"),
+    },
+    Migration {
+        version: 36,
+        description: "gift cards",
+        sql: include_str!("../../sql/migrations/0036_gift_cards.sql"),
+    },
+    Migration {
+        version: 37,
+        description: "courier locations",
+        sql: include_str!("../../sql/migrations/0037_courier_locations.sql"),
+    },
+    Migration {
+        version: 38,
+        description: "frozen deciders",
+        sql: include_str!("../../sql/migrations/0038_frozen_deciders.sql"),
+    },
+    Migration {
+        version: 39,
+        description: "events command id deduplication",
+        sql: include_str!("../../sql/migrations/0039_events_command_id_deduplication.sql"),
+    },
+    Migration {
+        version: 40,
+        description: "projections store id",
+        sql: include_str!("../../sql/migrations/0040_projections_store_id.sql"),
+    },
+];
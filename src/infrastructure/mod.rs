@@ -1,3 +1,24 @@
+pub mod avro;
+pub mod client_types;
+pub mod command_batch_repository;
+pub mod courier_location_repository;
+pub mod event_schema;
+pub mod event_stream;
+pub mod exchange_rates;
+pub mod kitchen_queue_repository;
+pub mod migrations;
+pub mod notification_outbox_repository;
+pub mod order_group_repository;
+pub mod order_payments_repository;
 pub mod order_restaurant_event_repository;
+pub mod order_restaurant_projection_repository;
 pub mod order_view_state_repository;
+#[cfg(feature = "protobuf")]
+pub mod protobuf;
+pub mod restaurant_daily_orders_repository;
+pub mod restaurant_order_board_repository;
+pub mod restaurant_search_repository;
 pub mod restaurant_view_state_repository;
+pub mod schema;
+pub mod stock_levels;
+pub mod view_state_upcasters;
@@ -0,0 +1,89 @@
+use crate::domain::notification_saga::Notification;
+use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::framework::infrastructure::event_repository::EventPosition;
+use pgrx::{IntoDatum, JsonB, PgBuiltInOids, Spi, TimestampWithTimeZone, Uuid};
+
+/// The row of the notifications outbox: the id (the order of the notifications), the target customer, the template, the templated payload, and the time the notification was written.
+pub type NotificationRow = (i64, Uuid, String, JsonB, TimestampWithTimeZone);
+
+/// Repository of the notifications outbox (`notifications_outbox`): the intents to notify the customers, written in the transaction of the events they react to, and claimed by the relay.
+pub struct NotificationOutboxRepository {}
+
+impl NotificationOutboxRepository {
+    /// Create a new NotificationOutboxRepository
+    pub fn new() -> Self {
+        NotificationOutboxRepository {}
+    }
+
+    /// Writes the notification, in reaction to the event at the position, to the outbox.
+    /// The target customer is the customer of the order (`customer_identifier` of its `OrderCreated` event); the notifications of the anonymous orders are not written.
+    /// At most one notification is written per event, so the notification is not duplicated if the event is handled again.
+    pub fn save(
+        &self,
+        notification: &Notification,
+        position: &EventPosition,
+    ) -> Result<(), ErrorMessage> {
+        let payload = serde_json::to_value(notification).map_err(|err| ErrorMessage {
+            message: "Failed to serialize the notification: ".to_string() + &err.to_string(),
+            context: None,
+        })?;
+        let template = payload["template"].as_str().unwrap_or_default().to_string();
+        Spi::run_with_args(
+            r#"INSERT INTO notifications_outbox (customer_id, order_id, template, payload, event_id)
+               SELECT (data->>'customer_identifier')::UUID, decider_id::UUID, $2, $3, $4
+               FROM events
+               WHERE decider = 'Order' AND decider_id = $1 AND "event" = 'OrderCreated' AND data->>'customer_identifier' IS NOT NULL
+               ON CONFLICT (event_id) DO NOTHING"#,
+            Some(vec![
+                (
+                    PgBuiltInOids::TEXTOID.oid(),
+                    notification.order_identifier.0.to_string().into_datum(),
+                ),
+                (PgBuiltInOids::TEXTOID.oid(), template.into_datum()),
+                (PgBuiltInOids::JSONBOID.oid(), JsonB(payload).into_datum()),
+                (
+                    PgBuiltInOids::UUIDOID.oid(),
+                    Uuid::from_bytes(position.event_id.into_bytes()).into_datum(),
+                ),
+            ]),
+        )
+        .map_err(|err| {
+            ErrorMessage::spi(
+                "write the notification to the outbox",
+                Some(notification.order_identifier.0.to_string()),
+                &err,
+            )
+        })
+    }
+
+    /// Claims (up to `limit`) notifications that were not relayed yet, in the order they were written: they are marked as relayed, and returned.
+    /// The claimed notifications are locked (`SKIP LOCKED`), so the concurrent relays claim the distinct notifications; the claim is undone if the transaction of the relay is rolled back.
+    pub fn claim(&self, limit: i64) -> Result<Vec<NotificationRow>, ErrorMessage> {
+        Spi::connect(|mut client| {
+            let mut results = Vec::new();
+            let tup_table = client.update(
+                "WITH claimed AS (
+                     UPDATE notifications_outbox SET relayed_at = now()
+                     WHERE id IN (SELECT id FROM notifications_outbox WHERE relayed_at IS NULL ORDER BY id LIMIT $1 FOR UPDATE SKIP LOCKED)
+                     RETURNING id, customer_id, template, payload, created_at
+                 )
+                 SELECT id, customer_id, template, payload, created_at FROM claimed ORDER BY id",
+                None,
+                Some(vec![(PgBuiltInOids::INT8OID.oid(), limit.into_datum())]),
+            )?;
+            for row in tup_table {
+                if let (Some(id), Some(customer_id), Some(template), Some(payload), Some(created_at)) = (
+                    row["id"].value::<i64>()?,
+                    row["customer_id"].value::<Uuid>()?,
+                    row["template"].value::<String>()?,
+                    row["payload"].value::<JsonB>()?,
+                    row["created_at"].value::<TimestampWithTimeZone>()?,
+                ) {
+                    results.push((id, customer_id, template, payload, created_at));
+                }
+            }
+            Ok(results)
+        })
+        .map_err(|err: pgrx::spi::Error| ErrorMessage::spi("claim the notifications", None, &err))
+    }
+}
@@ -0,0 +1,129 @@
+use crate::domain::api::OrderEvent;
+use crate::domain::order_group_view::OrderGroupState;
+use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::framework::infrastructure::event_repository::EventPosition;
+use crate::framework::infrastructure::to_payload;
+use crate::framework::infrastructure::view_state_repository::ViewStateRepository;
+use pgrx::{IntoDatum, JsonB, PgBuiltInOids, Spi};
+use serde_json::json;
+use uuid::Uuid;
+
+/// Repository of the order group view: a row per group of the (sub) orders split across the restaurants, with the combined status.
+pub struct OrderGroupRepository {}
+
+impl OrderGroupRepository {
+    /// Create a new OrderGroupRepository
+    pub fn new() -> Self {
+        OrderGroupRepository {}
+    }
+
+    /// Resolves the group the event belongs to.
+    /// `OrderCreated` carries the group id (`None` for the orders that are not grouped); the later events of the order carry the order id only, so their group is looked up by the order.
+    fn group_id(&self, event: &OrderEvent) -> Result<Option<Uuid>, ErrorMessage> {
+        let order_identifier = match event {
+            OrderEvent::Created(event) => {
+                return Ok(event.group_identifier.as_ref().map(|group| group.0))
+            }
+            OrderEvent::Prepared(event) => &event.identifier,
+            OrderEvent::LineItemsUpdated(event) => &event.identifier,
+            OrderEvent::Migrated(event) => &event.identifier,
+            OrderEvent::Cancelled(event) => &event.identifier,
+        };
+        Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT (SELECT id FROM order_groups WHERE store_id = fmodel_store_id() AND data->'orders' @> $1 LIMIT 1)",
+                    Some(1),
+                    Some(vec![(
+                        PgBuiltInOids::JSONBOID.oid(),
+                        JsonB(json!([{ "order_identifier": order_identifier }])).into_datum(),
+                    )]),
+                )?
+                .first()
+                .get_one::<pgrx::Uuid>()
+        })
+        .map(|id| id.map(|id| Uuid::from_bytes(*id.as_bytes())))
+        .map_err(|err| ErrorMessage::spi("fetch the order group of the order", None, &err))
+    }
+}
+
+/// Implementation of the view state repository for the order group `view` state.
+impl ViewStateRepository<OrderEvent, Option<OrderGroupState>> for OrderGroupRepository {
+    /// Fetches current state, based on the event.
+    fn fetch_state(
+        &self,
+        event: &OrderEvent,
+    ) -> Result<Option<Option<OrderGroupState>>, ErrorMessage> {
+        let Some(id) = self.group_id(event)? else {
+            return Ok(Some(None));
+        };
+        Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT (SELECT data FROM order_groups WHERE store_id = fmodel_store_id() AND id = $1)",
+                    Some(1),
+                    Some(vec![(
+                        PgBuiltInOids::UUIDOID.oid(),
+                        pgrx::Uuid::from_bytes(id.into_bytes()).into_datum(),
+                    )]),
+                )?
+                .first()
+                .get_one::<JsonB>()
+        })
+        .map_err(|err| ErrorMessage::spi("fetch the order group", None, &err))?
+        .map(to_payload::<OrderGroupState>)
+        .transpose()
+        .map(Some)
+    }
+    /// Fetches the offset of the last event applied to the view row of the event.
+    fn fetch_applied_offset(&self, event: &OrderEvent) -> Result<Option<i64>, ErrorMessage> {
+        let Some(id) = self.group_id(event)? else {
+            return Ok(None);
+        };
+        Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT (SELECT last_offset FROM order_groups WHERE store_id = fmodel_store_id() AND id = $1)",
+                    Some(1),
+                    Some(vec![(
+                        PgBuiltInOids::UUIDOID.oid(),
+                        pgrx::Uuid::from_bytes(id.into_bytes()).into_datum(),
+                    )]),
+                )?
+                .first()
+                .get_one::<i64>()
+        })
+        .map_err(|err| ErrorMessage::spi("fetch the order group version", None, &err))
+    }
+    /// Saves the new state. The orders that are not grouped are ignored.
+    fn save(
+        &self,
+        state: &Option<OrderGroupState>,
+        position: &EventPosition,
+    ) -> Result<Option<OrderGroupState>, ErrorMessage> {
+        let Some(group) = state else {
+            return Ok(None);
+        };
+        let data = serde_json::to_value(group).map_err(|err| ErrorMessage {
+            message: "Failed to serialize the order group: ".to_string() + &err.to_string(),
+            context: None,
+        })?;
+        Spi::run_with_args(
+            "INSERT INTO order_groups (id, data, last_event_id, last_offset) VALUES ($1, $2, $3, $4) ON CONFLICT (store_id, id) DO UPDATE SET data = $2, last_event_id = $3, last_offset = $4",
+            Some(vec![
+                (
+                    PgBuiltInOids::UUIDOID.oid(),
+                    pgrx::Uuid::from_bytes(group.identifier.0.into_bytes()).into_datum(),
+                ),
+                (PgBuiltInOids::JSONBOID.oid(), JsonB(data).into_datum()),
+                (
+                    PgBuiltInOids::UUIDOID.oid(),
+                    pgrx::Uuid::from_bytes(position.event_id.into_bytes()).into_datum(),
+                ),
+                (PgBuiltInOids::INT8OID.oid(), position.offset.into_datum()),
+            ]),
+        )
+        .map_err(|err| ErrorMessage::spi("save the order group", None, &err))?;
+        Ok(state.clone())
+    }
+}
@@ -0,0 +1,47 @@
+use crate::domain::api::{OrderId, RestaurantId};
+use crate::domain::order_payments_view::OrderPayments;
+use crate::framework::infrastructure::aggregating_view_repository::AggregatingViewRepository;
+use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::framework::infrastructure::event_repository::EventPosition;
+use pgrx::{IntoDatum, PgBuiltInOids, Spi};
+
+/// OrderPaymentsRepository struct
+/// The `payments` projection: the captured and refunded amounts per order.
+pub struct OrderPaymentsRepository {}
+
+/// OrderPaymentsRepository - struct implementation
+impl OrderPaymentsRepository {
+    /// Create a new OrderPaymentsRepository
+    pub fn new() -> Self {
+        OrderPaymentsRepository {}
+    }
+}
+
+/// Implementation of the aggregating view repository for the payments of the order.
+impl AggregatingViewRepository<(RestaurantId, OrderId), OrderPayments> for OrderPaymentsRepository {
+    /// Adds the increment to the captured/refunded amounts of the order.
+    /// The order is attributed to the restaurant of the latest payment (the order migrated to another restaurant is remapped on its next payment).
+    fn increment(
+        &self,
+        bucket: &(RestaurantId, OrderId),
+        increment: &OrderPayments,
+        position: &EventPosition,
+    ) -> Result<(), ErrorMessage> {
+        let (restaurant_id, order_id) = bucket;
+        Spi::run_with_args(
+            r#"INSERT INTO payments (order_id, restaurant_id, captured, refunded, last_offset) VALUES ($1, $2, $3, $4, $5)
+               ON CONFLICT (store_id, order_id) DO UPDATE SET restaurant_id = EXCLUDED.restaurant_id,
+                                                    captured = payments.captured + EXCLUDED.captured,
+                                                    refunded = payments.refunded + EXCLUDED.refunded,
+                                                    last_offset = GREATEST(payments.last_offset, EXCLUDED.last_offset)"#,
+            Some(vec![
+                (PgBuiltInOids::UUIDOID.oid(), order_id.0.to_string().into_datum()),
+                (PgBuiltInOids::UUIDOID.oid(), restaurant_id.0.to_string().into_datum()),
+                (PgBuiltInOids::INT8OID.oid(), increment.captured.into_datum()),
+                (PgBuiltInOids::INT8OID.oid(), increment.refunded.into_datum()),
+                (PgBuiltInOids::INT8OID.oid(), position.offset.into_datum()),
+            ]),
+        )
+        .map_err(|err| ErrorMessage::spi("save the order payments", Some(order_id.0.to_string()), &err))
+    }
+}
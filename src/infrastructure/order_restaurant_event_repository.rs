@@ -1,16 +1,297 @@
-use crate::domain::{Command, Event};
-use crate::framework::infrastructure::event_repository::EventOrchestratingRepository;
+use crate::domain::{event_tags, Command, Event};
+use crate::framework::domain::api::{EventType, Identifier};
+use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::framework::infrastructure::event_repository::{
+    EventOrchestratingRepository, EventPosition,
+};
+#[cfg(feature = "protobuf")]
+use crate::framework::infrastructure::to_payload;
+use crate::infrastructure::courier_location_repository::CourierLocationRepository;
+use pgrx::datum::TimestampWithTimeZone;
+use pgrx::{IntoDatum, JsonB, PgBuiltInOids, Spi, Uuid};
+
+/// A historical restaurant menu: the menu version, the menu, and the period in which the menu was effective (the end is `None` for the current menu).
+pub type MenuHistoryEntry = (
+    i64,
+    JsonB,
+    TimestampWithTimeZone,
+    Option<TimestampWithTimeZone>,
+);
 
 /// An event repository for the restaurant and order domain(s).
 pub struct OrderAndRestaurantEventRepository {}
 
 /// Implementation of the event orchestrating repository for the restaurant and order domain(s).
-/// We use default implementation from the trait. How cool is that?
-impl EventOrchestratingRepository<Command, Event> for OrderAndRestaurantEventRepository {}
+/// We use default implementation from the trait, only the events are tagged by the domain (e.g. `cuisine:vietnamese`), and the courier locations are retained by the policy (`fmodel.courier_location_policy`).
+impl EventOrchestratingRepository<Command, Event> for OrderAndRestaurantEventRepository {
+    fn tags(&self, event: &Event) -> Vec<String> {
+        event_tags(event)
+    }
+
+    fn retain(&self, event: &Event) -> Result<bool, ErrorMessage> {
+        match event {
+            Event::CourierLocationUpdated(event) => CourierLocationRepository::new().retain(event),
+            _ => Ok(true),
+        }
+    }
+}
+
+/// The event repository appending the administrative correction events: tagged `correction`, in addition to the tags of the domain.
+struct CorrectionEventRepository {}
+
+impl EventOrchestratingRepository<Command, Event> for CorrectionEventRepository {
+    fn tags(&self, event: &Event) -> Vec<String> {
+        let mut tags = event_tags(event);
+        tags.push("correction".to_string());
+        tags
+    }
+}
 
 impl OrderAndRestaurantEventRepository {
     /// Creates a new restaurant and order event repository.
     pub fn new() -> Self {
         OrderAndRestaurantEventRepository {}
     }
+
+    /// Fetches the event by its `event_id`.
+    #[cfg(feature = "protobuf")]
+    pub fn fetch_event(&self, event_id: Uuid) -> Result<Option<Event>, ErrorMessage> {
+        // Read-only SPI: the event is fetched by the `STABLE` function(s)
+        Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT (SELECT fmodel_event_data(event_id, data) FROM events WHERE event_id = $1)",
+                    Some(1),
+                    Some(vec![(PgBuiltInOids::UUIDOID.oid(), event_id.into_datum())]),
+                )?
+                .first()
+                .get_one::<JsonB>()
+        })
+        .map_err(|err| ErrorMessage::spi("fetch the event", None, &err))?
+        .map(to_payload)
+        .transpose()
+    }
+
+    /// Forks the event stream of the decider: copies the events of the stream, up to (and including) the offset, under the new identity (`new_decider_id`).
+    /// The hypothetical commands can then be handled against the fork, without touching the original stream.
+    pub fn fork_stream(
+        &self,
+        decider_id: Uuid,
+        new_decider_id: Uuid,
+        up_to_offset: i64,
+    ) -> Result<Vec<(Event, EventPosition)>, ErrorMessage> {
+        let decider_id = uuid::Uuid::from_bytes(*decider_id.as_bytes());
+        let new_decider_id = uuid::Uuid::from_bytes(*new_decider_id.as_bytes());
+        if self.fetch_stream_version(&new_decider_id)?.is_some() {
+            return Err(ErrorMessage {
+                message: "Failed to fork the stream: the stream `".to_string()
+                    + &new_decider_id.to_string()
+                    + "` already exists",
+                context: None,
+            });
+        }
+        // The `identifier` of the event is the identifier of its (decider) stream
+        let events = self
+            .fetch_stream_events_until(&decider_id, up_to_offset)?
+            .into_iter()
+            .map(|(event, _)| {
+                let mut data = serde_json::to_value(event).map_err(|err| ErrorMessage {
+                    message: "Failed to fork the stream: ".to_string() + &err.to_string(),
+                    context: None,
+                })?;
+                data["identifier"] = serde_json::Value::String(new_decider_id.to_string());
+                serde_json::from_value::<Event>(data).map_err(|err| ErrorMessage {
+                    message: "Failed to fork the stream: ".to_string() + &err.to_string(),
+                    context: None,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if events.is_empty() {
+            return Err(ErrorMessage {
+                message: "Failed to fork the stream: the stream `".to_string()
+                    + &decider_id.to_string()
+                    + "` has no events up to the offset "
+                    + &up_to_offset.to_string(),
+                context: None,
+            });
+        }
+        self.save(&events)
+    }
+
+    /// Fetches the envelopes of the events of the stream, in the order they were appended: the metadata of the event (`event_id`, `event`, `decider`, `offset`, `sequence_number`, `final`, `correlation_id`, `created_at`) and its (rehydrated) payload (`data`).
+    pub fn fetch_stream_envelopes(&self, decider_id: Uuid) -> Result<JsonB, ErrorMessage> {
+        // Read-only SPI: the envelopes are fetched by the `STABLE` function(s)
+        Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT COALESCE(jsonb_agg(jsonb_build_object(
+                                'event_id', event_id,
+                                'event', event,
+                                'decider', decider,
+                                'offset', \"offset\",
+                                'sequence_number', sequence_number,
+                                'final', final,
+                                'correlation_id', correlation_id,
+                                'created_at', created_at,
+                                'data', fmodel_event_data(event_id, data)
+                            ) ORDER BY \"offset\"), '[]'::JSONB)
+                     FROM events WHERE decider_id = $1 AND store_id = fmodel_store_id()",
+                    Some(1),
+                    Some(vec![(
+                        PgBuiltInOids::TEXTOID.oid(),
+                        decider_id.to_string().into_datum(),
+                    )]),
+                )?
+                .first()
+                .get_one::<JsonB>()
+        })
+        .map_err(|err| ErrorMessage::spi("export the stream", Some(decider_id.to_string()), &err))?
+        .ok_or(ErrorMessage {
+            message: "Failed to export the stream: no events found".to_string(),
+            context: None,
+        })
+    }
+
+    /// Imports the events (e.g. exported by `fetch_stream_envelopes` from another database) as the new event stream of the decider.
+    /// The events are appended as new (new `event_id`, `offset` and `created_at`), in the given order; the stream must not exist yet.
+    pub fn import_stream(
+        &self,
+        decider_id: Uuid,
+        events: Vec<Event>,
+    ) -> Result<Vec<(Event, EventPosition)>, ErrorMessage> {
+        let decider_id = uuid::Uuid::from_bytes(*decider_id.as_bytes());
+        if events.is_empty() {
+            return Err(ErrorMessage {
+                message: "Failed to import the stream: the stream has no events".to_string(),
+                context: None,
+            });
+        }
+        // The `identifier` of the event is the identifier of its (decider) stream
+        if let Some(event) = events.iter().find(|event| event.identifier() != decider_id) {
+            return Err(ErrorMessage {
+                message: "Failed to import the stream: the event of the stream `".to_string()
+                    + &event.identifier().to_string()
+                    + "` does not belong to the stream `"
+                    + &decider_id.to_string()
+                    + "`",
+                context: None,
+            });
+        }
+        if self.fetch_stream_version(&decider_id)?.is_some() {
+            return Err(ErrorMessage {
+                message: "Failed to import the stream: the stream `".to_string()
+                    + &decider_id.to_string()
+                    + "` already exists",
+                context: None,
+            });
+        }
+        self.save(&events)
+    }
+
+    /// Appends the administrative correction event to the existing stream of the decider (the `identifier` of the event), and records the reason and the operator in the `event_corrections` audit.
+    /// The correction is appended as any other event (the optimistic locking, the projections), so fixing the bad state never requires updating the history.
+    pub fn append_correction(
+        &self,
+        event: Event,
+        reason: &str,
+    ) -> Result<(Event, EventPosition), ErrorMessage> {
+        let decider_id = event.identifier();
+        if self.fetch_stream_version(&decider_id)?.is_none() {
+            return Err(ErrorMessage {
+                message: "Failed to append the correction: the stream `".to_string()
+                    + &decider_id.to_string()
+                    + "` does not exist",
+                context: None,
+            });
+        }
+        let saved = CorrectionEventRepository {}
+            .save(&[event])?
+            .pop()
+            .ok_or(ErrorMessage {
+                message: "Failed to append the correction: No event saved".to_string(),
+                context: None,
+            })?;
+        Spi::run_with_args(
+            "INSERT INTO event_corrections (event_id, decider_id, event, reason) VALUES ($1, $2, $3, $4)",
+            Some(vec![
+                (
+                    PgBuiltInOids::UUIDOID.oid(),
+                    Uuid::from_bytes(saved.1.event_id.into_bytes()).into_datum(),
+                ),
+                (
+                    PgBuiltInOids::TEXTOID.oid(),
+                    decider_id.to_string().into_datum(),
+                ),
+                (
+                    PgBuiltInOids::TEXTOID.oid(),
+                    saved.0.event_type().into_datum(),
+                ),
+                (PgBuiltInOids::TEXTOID.oid(), reason.into_datum()),
+            ]),
+        )
+        .map_err(|err| {
+            ErrorMessage::spi(
+                "audit the correction",
+                Some(decider_id.to_string()),
+                &err,
+            )
+        })?;
+        Ok(saved)
+    }
+
+    /// Fetches every historical menu of the restaurant from the event log, in the order of the menu versions.
+    /// A menu is effective from the creation of its event, until the creation of the next menu event.
+    /// The events persisted before the menu versioning was introduced are versioned by their position in the stream, as the decider does.
+    pub fn fetch_menu_history(
+        &self,
+        restaurant_id: Uuid,
+    ) -> Result<Vec<MenuHistoryEntry>, ErrorMessage> {
+        // Read-only SPI: the history is fetched by the `STABLE` function(s)
+        Spi::connect(|client| {
+            let mut results = Vec::new();
+            let tup_table = client
+                .select(
+                    "SELECT COALESCE(NULLIF(payload.data->>'menu_version', '0')::BIGINT, row_number() OVER w) AS menu_version,
+                            payload.data->'menu' AS menu,
+                            created_at AS effective_from,
+                            lead(created_at) OVER w AS effective_to
+                     FROM events, LATERAL (SELECT fmodel_event_data(events.event_id, events.data) AS data) AS payload
+                     WHERE decider = 'Restaurant' AND decider_id = $1 AND store_id = fmodel_store_id() AND event IN ('RestaurantCreated', 'RestaurantMenuChanged')
+                     WINDOW w AS (ORDER BY \"offset\")
+                     ORDER BY \"offset\"",
+                    None,
+                    Some(vec![(
+                        PgBuiltInOids::TEXTOID.oid(),
+                        restaurant_id.to_string().into_datum(),
+                    )]),
+                )
+                .map_err(to_error)?;
+            for row in tup_table {
+                let menu_version = row["menu_version"].value::<i64>().map_err(to_error)?;
+                let menu = row["menu"].value::<JsonB>().map_err(to_error)?;
+                let effective_from = row["effective_from"]
+                    .value::<TimestampWithTimeZone>()
+                    .map_err(to_error)?;
+                let effective_to = row["effective_to"]
+                    .value::<TimestampWithTimeZone>()
+                    .map_err(to_error)?;
+                match (menu_version, menu, effective_from) {
+                    (Some(menu_version), Some(menu), Some(effective_from)) => {
+                        results.push((menu_version, menu, effective_from, effective_to))
+                    }
+                    _ => {
+                        return Err(ErrorMessage {
+                            message: "Failed to fetch the menu history: No menu found".to_string(),
+                            context: None,
+                        })
+                    }
+                }
+            }
+            Ok(results)
+        })
+    }
+}
+
+fn to_error(err: pgrx::spi::Error) -> ErrorMessage {
+    ErrorMessage::spi("fetch the menu history", None, &err)
 }
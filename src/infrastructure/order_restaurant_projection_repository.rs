@@ -0,0 +1,16 @@
+use crate::domain::Event;
+use crate::framework::infrastructure::projection_repository::ProjectionRepository;
+
+/// A projection repository for the restaurant and order domain(s).
+pub struct OrderAndRestaurantProjectionRepository {}
+
+/// Implementation of the projection repository for the restaurant and order domain(s).
+/// We use default implementation from the trait.
+impl ProjectionRepository<Event> for OrderAndRestaurantProjectionRepository {}
+
+impl OrderAndRestaurantProjectionRepository {
+    /// Creates a new restaurant and order projection repository.
+    pub fn new() -> Self {
+        OrderAndRestaurantProjectionRepository {}
+    }
+}
@@ -2,9 +2,15 @@ use crate::domain::api::OrderEvent;
 use crate::domain::order_view::OrderViewState;
 use crate::framework::domain::api::Identifier;
 use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::framework::infrastructure::event_repository::EventPosition;
 use crate::framework::infrastructure::to_payload;
-use crate::framework::infrastructure::view_state_repository::ViewStateRepository;
+use crate::framework::infrastructure::view_state_repository::{
+    patch_view_state, ViewStateRepository,
+};
+use crate::infrastructure::view_state_upcasters::upcast_view_state;
 use pgrx::{IntoDatum, JsonB, PgBuiltInOids, Spi};
+use serde_json::json;
+use uuid::Uuid;
 
 /// OrderViewStateRepository struct
 /// View state repository is always very specific to the domain. There is no default implementation in the `ViewStateRepository` trait.
@@ -16,6 +22,25 @@ impl OrderViewStateRepository {
     pub fn new() -> Self {
         OrderViewStateRepository {}
     }
+
+    /// Fetches the offset of the last event applied to the order view/projection.
+    pub fn fetch_version(&self, id: &Uuid) -> Result<Option<i64>, ErrorMessage> {
+        // Read-only SPI: the version is queried by the `STABLE` function(s)
+        Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT (SELECT last_offset FROM orders WHERE store_id = fmodel_store_id() AND id = $1)",
+                    Some(1),
+                    Some(vec![(
+                        PgBuiltInOids::UUIDOID.oid(),
+                        id.to_string().into_datum(),
+                    )]),
+                )?
+                .first()
+                .get_one::<i64>()
+        })
+        .map_err(|err| ErrorMessage::spi("fetch the order version", Some(id.to_string()), &err))
+    }
 }
 
 /// Implementation of the view state repository for the order `view` state.
@@ -25,7 +50,7 @@ impl ViewStateRepository<OrderEvent, Option<OrderViewState>> for OrderViewStateR
         &self,
         event: &OrderEvent,
     ) -> Result<Option<Option<OrderViewState>>, ErrorMessage> {
-        let query = "SELECT data FROM orders WHERE id = $1";
+        let query = "SELECT data FROM orders WHERE store_id = fmodel_store_id() AND id = $1";
         Spi::connect(|client| {
             let mut results = Vec::new();
             let tup_table = client
@@ -37,34 +62,59 @@ impl ViewStateRepository<OrderEvent, Option<OrderViewState>> for OrderViewStateR
                         event.identifier().to_string().into_datum(),
                     )]),
                 )
-                .map_err(|err| ErrorMessage {
-                    message: "Failed to fetch the order: ".to_string() + &err.to_string(),
+                .map_err(|err| {
+                    ErrorMessage::spi(
+                        "fetch the order",
+                        Some(event.identifier().to_string()),
+                        &err,
+                    )
                 })?;
             for row in tup_table {
-                let data = row["data"].value::<JsonB>().map_err(|err| ErrorMessage {
-                    message: "Failed to fetch the order/payload (map `data` to `JsonB`): ".to_string() + &err.to_string(),
-                })?.ok_or(ErrorMessage {
-                    message: "Failed to fetch order data/payload (map `data` to `JsonB`): No data/payload found".to_string(),
-                })?;
+                let data = row["data"]
+                    .value::<JsonB>()
+                    .map_err(|err| {
+                        ErrorMessage::spi(
+                            "fetch the order/payload (map `data` to `JsonB`)",
+                            None,
+                            &err,
+                        )
+                    })?
+                    .ok_or(ErrorMessage {
+                        message: "Failed to fetch order data/payload (map `data` to `JsonB`): No data/payload found".to_string(),
+                        context: None,
+                    })?;
 
-                results.push(to_payload::<OrderViewState>(data)?);
+                results.push(to_payload::<OrderViewState>(upcast_view_state(
+                    "orders", data,
+                ))?);
             }
             Ok(Some(results.into_iter().last()))
         })
     }
+    /// Fetches the offset of the last event applied to the view row of the event.
+    fn fetch_applied_offset(&self, event: &OrderEvent) -> Result<Option<i64>, ErrorMessage> {
+        self.fetch_version(&event.identifier())
+    }
     /// Saves the new state.
-    fn save(&self, state: &Option<OrderViewState>) -> Result<Option<OrderViewState>, ErrorMessage> {
+    /// The new row is owned by the owner of the event that created it (see `fmodel.owner`); the owner of the existing row is kept.
+    fn save(
+        &self,
+        state: &Option<OrderViewState>,
+        position: &EventPosition,
+    ) -> Result<Option<OrderViewState>, ErrorMessage> {
         let state = state.as_ref().ok_or(ErrorMessage {
             message: "Failed to save the order: state is empty".to_string(),
+            context: None,
         })?;
         let data = serde_json::to_value(state).map_err(|err| ErrorMessage {
             message: "Failed to serialize the order: ".to_string() + &err.to_string(),
+            context: None,
         })?;
 
         Spi::connect(|mut client| {
             client
                 .update(
-                    "INSERT INTO orders (id, data) VALUES ($1, $2) ON CONFLICT (id) DO UPDATE SET data = $2 RETURNING data",
+                    "INSERT INTO orders (id, data, last_event_id, last_offset, owner) VALUES ($1, $2, $3, $4, (SELECT owner FROM events WHERE event_id = $3 AND decider = 'Order')) ON CONFLICT (store_id, id) DO UPDATE SET data = $2, last_event_id = $3, last_offset = $4 RETURNING data",
                     None,
                     Some(vec![
                         (
@@ -75,15 +125,45 @@ impl ViewStateRepository<OrderEvent, Option<OrderViewState>> for OrderViewStateR
                             PgBuiltInOids::JSONBOID.oid(),
                             JsonB(data).into_datum(),
                         ),
+                        (
+                            PgBuiltInOids::UUIDOID.oid(),
+                            pgrx::Uuid::from_bytes(position.event_id.into_bytes()).into_datum(),
+                        ),
+                        (
+                            PgBuiltInOids::INT8OID.oid(),
+                            position.offset.into_datum(),
+                        ),
                     ]),
                 )?
                 .first()
                 .get_one::<JsonB>().map(|o|{ o.map( |it| to_payload(it).unwrap() )})
         })
             .map(Some)
-        .map_err(|err| ErrorMessage {
-            message: "Failed to save the order: ".to_string() + &err.to_string(),
-        })
+        .map_err(|err| ErrorMessage::spi("save the order", Some(state.identifier.to_string()), &err))
             .map(|state| state.unwrap())
     }
+    /// Saves the new state, by patching the stored document with the changes of the event (JSON merge patch).
+    fn save_patch(
+        &self,
+        event: &OrderEvent,
+        state: &Option<OrderViewState>,
+        position: &EventPosition,
+    ) -> Result<Option<OrderViewState>, ErrorMessage> {
+        let patch = match event {
+            // The line items and the total are replaced: the whole state is saved
+            OrderEvent::Created(_) | OrderEvent::LineItemsUpdated(_) => {
+                return self.save(state, position)
+            }
+            OrderEvent::Prepared(event) => json!({ "status": event.status }),
+            OrderEvent::Cancelled(event) => json!({ "status": event.status }),
+            OrderEvent::Migrated(event) => {
+                json!({ "restaurant_identifier": event.restaurant_identifier })
+            }
+        };
+        if patch_view_state("orders", &event.identifier(), patch, position)? {
+            Ok(state.clone())
+        } else {
+            self.save(state, position)
+        }
+    }
 }
@@ -0,0 +1,2133 @@
+use crate::domain::api::{
+    AppliedDiscount, ApplyRestaurantPromotion, CancelOrder, CancelRestaurantOrder, CapturePayment,
+    ChangeRestaurantCapacity, ChangeRestaurantMenu, ChangeRestaurantOpeningHours,
+    CloseRestaurantOrder, ConvertedMoney, Coordinate, CourierId, CourierLocationUpdated,
+    CreateOrder, CreatePromotion, CreateRestaurant, Currency, CustomerId, DiscountPercentage,
+    ExchangeRate, ExpirePromotion, GiftCardId, GiftCardIssued, GiftCardRedeemed, GiftCardRefunded,
+    InventoryId, InventoryRestocked, IssueGiftCard, MarkMenuItemAvailable, MarkMenuItemUnavailable,
+    MarkOrderAsPrepared, MenuId, MenuItem, MenuItemId, MenuItemMarkedAvailable,
+    MenuItemMarkedUnavailable, MenuItemName, MigrateOrder, MigrateRestaurantOrders, MigratedOrder,
+    ModifyOrderLineItems, Money, OpeningHours, OpeningPeriod, OrderCancelled, OrderCreated,
+    OrderGroupId, OrderId, OrderLineItem, OrderLineItemId, OrderLineItemQuantity,
+    OrderLineItemsModified, OrderLineItemsUpdated, OrderMigrated, OrderPlaced, OrderPrepared,
+    OrderStatus, PaymentCaptured, PaymentRefunded, PlaceOrder, PromotionCreated, PromotionExpired,
+    PromotionId, PromotionName, ReceiveRestaurantOrders, RedeemGiftCard, RefundGiftCard,
+    RefundPayment, ReleaseStock, ReserveStock, RestaurantCapacityChanged, RestaurantCreated,
+    RestaurantId, RestaurantMenu, RestaurantMenuChanged, RestaurantMenuCuisine,
+    RestaurantMenuVersion, RestaurantName, RestaurantOpeningHoursChanged, RestaurantOrderCancelled,
+    RestaurantOrderClosed, RestaurantOrderSplit, RestaurantOrdersMigrated,
+    RestaurantOrdersReceived, RestaurantPromotion, RestaurantPromotionApplied,
+    RestaurantPromotionWithdrawn, RestockInventory, SplitOrder, StockItem, StockQuantity,
+    StockReleased, StockReserved, UpdateCourierLocation, UpdateOrderLineItems,
+    WithdrawRestaurantPromotion,
+};
+use crate::domain::{Command, Event};
+use crate::framework::infrastructure::errors::ErrorMessage;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use uuid::Uuid;
+
+// Protobuf messages (wire format) of the commands and events.
+// They mirror the domain API: identifiers are encoded as (hyphenated) strings, and enums (cuisine, order status) by their names.
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MenuItemMessage {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(string, tag = "2")]
+    pub name: String,
+    #[prost(uint64, tag = "3")]
+    pub price: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RestaurantMenuMessage {
+    #[prost(string, tag = "1")]
+    pub menu_id: String,
+    #[prost(message, repeated, tag = "2")]
+    pub items: Vec<MenuItemMessage>,
+    #[prost(string, tag = "3")]
+    pub cuisine: String,
+    #[prost(string, optional, tag = "4")]
+    pub currency: Option<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ConvertedMoneyMessage {
+    #[prost(uint64, tag = "1")]
+    pub amount: u64,
+    #[prost(string, tag = "2")]
+    pub currency: String,
+    #[prost(uint64, tag = "3")]
+    pub rate: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct OpeningPeriodMessage {
+    #[prost(uint32, tag = "1")]
+    pub day_of_week: u32,
+    #[prost(uint32, tag = "2")]
+    pub opens_at: u32,
+    #[prost(uint32, tag = "3")]
+    pub closes_at: u32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct OpeningHoursMessage {
+    #[prost(string, tag = "1")]
+    pub time_zone: String,
+    #[prost(message, repeated, tag = "2")]
+    pub periods: Vec<OpeningPeriodMessage>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct OrderLineItemMessage {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(uint32, tag = "2")]
+    pub quantity: u32,
+    #[prost(string, tag = "3")]
+    pub menu_item_id: String,
+    #[prost(string, tag = "4")]
+    pub name: String,
+    #[prost(uint64, optional, tag = "5")]
+    pub price: Option<u64>,
+    #[prost(string, optional, tag = "6")]
+    pub restaurant_identifier: Option<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct AppliedDiscountMessage {
+    #[prost(string, tag = "1")]
+    pub promotion_id: String,
+    #[prost(string, tag = "2")]
+    pub line_item_id: String,
+    #[prost(uint64, tag = "3")]
+    pub amount: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SplitOrderMessage {
+    #[prost(string, tag = "1")]
+    pub restaurant_identifier: String,
+    #[prost(string, tag = "2")]
+    pub order_identifier: String,
+    #[prost(message, repeated, tag = "3")]
+    pub line_items: Vec<OrderLineItemMessage>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StockItemMessage {
+    #[prost(string, tag = "1")]
+    pub menu_item_id: String,
+    #[prost(uint32, tag = "2")]
+    pub quantity: u32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RestaurantPromotionMessage {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(string, tag = "2")]
+    pub name: String,
+    #[prost(uint32, tag = "3")]
+    pub percentage: u32,
+    #[prost(string, repeated, tag = "4")]
+    pub menu_item_ids: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MigratedOrderMessage {
+    #[prost(string, tag = "1")]
+    pub order_identifier: String,
+    #[prost(uint64, optional, tag = "2")]
+    pub total: Option<u64>,
+    #[prost(uint64, tag = "3")]
+    pub captured: u64,
+    #[prost(uint64, tag = "4")]
+    pub refunded: u64,
+    #[prost(bool, tag = "5")]
+    pub open: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CreateRestaurantMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub name: String,
+    #[prost(message, optional, tag = "3")]
+    pub menu: Option<RestaurantMenuMessage>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ChangeRestaurantMenuMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(message, optional, tag = "2")]
+    pub menu: Option<RestaurantMenuMessage>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct PlaceOrderMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub order_identifier: String,
+    #[prost(message, repeated, tag = "3")]
+    pub line_items: Vec<OrderLineItemMessage>,
+    #[prost(string, optional, tag = "4")]
+    pub group_identifier: Option<String>,
+    #[prost(string, optional, tag = "5")]
+    pub customer_identifier: Option<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CreateOrderMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub restaurant_identifier: String,
+    #[prost(message, repeated, tag = "3")]
+    pub line_items: Vec<OrderLineItemMessage>,
+    #[prost(string, optional, tag = "4")]
+    pub currency: Option<String>,
+    #[prost(message, repeated, tag = "5")]
+    pub discounts: Vec<AppliedDiscountMessage>,
+    #[prost(string, optional, tag = "6")]
+    pub group_identifier: Option<String>,
+    #[prost(string, optional, tag = "7")]
+    pub customer_identifier: Option<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MarkOrderAsPreparedMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ModifyOrderLineItemsMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub order_identifier: String,
+    #[prost(message, repeated, tag = "3")]
+    pub line_items: Vec<OrderLineItemMessage>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct UpdateOrderLineItemsMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub restaurant_identifier: String,
+    #[prost(message, repeated, tag = "3")]
+    pub line_items: Vec<OrderLineItemMessage>,
+    #[prost(message, repeated, tag = "4")]
+    pub discounts: Vec<AppliedDiscountMessage>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ChangeRestaurantCapacityMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(uint32, optional, tag = "2")]
+    pub max_concurrent_orders: Option<u32>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CloseRestaurantOrderMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub order_identifier: String,
+}
+
+/// The payment command (`CapturePayment`, `RefundPayment`).
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct PaymentMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub order_identifier: String,
+    #[prost(uint64, tag = "3")]
+    pub amount: u64,
+}
+
+/// The menu item availability command (`MarkMenuItemUnavailable`, `MarkMenuItemAvailable`).
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MenuItemAvailabilityMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub menu_item_id: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ChangeRestaurantOpeningHoursMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(message, optional, tag = "2")]
+    pub opening_hours: Option<OpeningHoursMessage>,
+}
+
+/// The orders migration command. `all_orders` for all the orders of the restaurant (the merge), otherwise the `order_identifiers` (the split).
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MigrateRestaurantOrdersMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub target_identifier: String,
+    #[prost(string, repeated, tag = "3")]
+    pub order_identifiers: Vec<String>,
+    #[prost(bool, tag = "4")]
+    pub all_orders: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ReceiveRestaurantOrdersMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub source_identifier: String,
+    #[prost(message, repeated, tag = "3")]
+    pub orders: Vec<MigratedOrderMessage>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MigrateOrderMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub restaurant_identifier: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ApplyRestaurantPromotionMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(message, optional, tag = "2")]
+    pub promotion: Option<RestaurantPromotionMessage>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct WithdrawRestaurantPromotionMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub promotion_id: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CreatePromotionMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub restaurant_identifier: String,
+    #[prost(string, tag = "3")]
+    pub name: String,
+    #[prost(uint32, tag = "4")]
+    pub percentage: u32,
+    #[prost(string, repeated, tag = "5")]
+    pub menu_item_ids: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ExpirePromotionMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CancelRestaurantOrderMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub order_identifier: String,
+    #[prost(bool, tag = "3")]
+    pub rejected: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CancelOrderMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(bool, tag = "2")]
+    pub rejected: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RestockInventoryMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub restaurant_identifier: String,
+    #[prost(message, repeated, tag = "3")]
+    pub items: Vec<StockItemMessage>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ReserveStockMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub order_identifier: String,
+    #[prost(message, repeated, tag = "3")]
+    pub items: Vec<StockItemMessage>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ReleaseStockMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub order_identifier: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct IssueGiftCardMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(uint64, tag = "2")]
+    pub amount: u64,
+}
+
+/// The gift card payment command (`RedeemGiftCard`, `RefundGiftCard`).
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GiftCardPaymentMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub restaurant_identifier: String,
+    #[prost(string, tag = "3")]
+    pub order_identifier: String,
+    #[prost(uint64, tag = "4")]
+    pub amount: u64,
+}
+
+/// The courier location command: the coordinates are in microdegrees.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct UpdateCourierLocationMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(sint32, tag = "2")]
+    pub latitude: i32,
+    #[prost(sint32, tag = "3")]
+    pub longitude: i32,
+}
+
+/// The command envelope.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CommandMessage {
+    #[prost(
+        oneof = "CommandKind",
+        tags = "1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30"
+    )]
+    pub command: Option<CommandKind>,
+}
+
+#[derive(Clone, PartialEq, prost::Oneof)]
+pub enum CommandKind {
+    #[prost(message, tag = "1")]
+    CreateRestaurant(CreateRestaurantMessage),
+    #[prost(message, tag = "2")]
+    ChangeRestaurantMenu(ChangeRestaurantMenuMessage),
+    #[prost(message, tag = "3")]
+    PlaceOrder(PlaceOrderMessage),
+    #[prost(message, tag = "4")]
+    CreateOrder(CreateOrderMessage),
+    #[prost(message, tag = "5")]
+    MarkOrderAsPrepared(MarkOrderAsPreparedMessage),
+    #[prost(message, tag = "6")]
+    ModifyOrderLineItems(ModifyOrderLineItemsMessage),
+    #[prost(message, tag = "7")]
+    UpdateOrderLineItems(UpdateOrderLineItemsMessage),
+    #[prost(message, tag = "8")]
+    CapturePayment(PaymentMessage),
+    #[prost(message, tag = "9")]
+    RefundPayment(PaymentMessage),
+    #[prost(message, tag = "10")]
+    ChangeRestaurantCapacity(ChangeRestaurantCapacityMessage),
+    #[prost(message, tag = "11")]
+    CloseRestaurantOrder(CloseRestaurantOrderMessage),
+    #[prost(message, tag = "12")]
+    MarkMenuItemUnavailable(MenuItemAvailabilityMessage),
+    #[prost(message, tag = "13")]
+    MarkMenuItemAvailable(MenuItemAvailabilityMessage),
+    #[prost(message, tag = "14")]
+    ChangeRestaurantOpeningHours(ChangeRestaurantOpeningHoursMessage),
+    #[prost(message, tag = "15")]
+    MigrateRestaurantOrders(MigrateRestaurantOrdersMessage),
+    #[prost(message, tag = "16")]
+    ReceiveRestaurantOrders(ReceiveRestaurantOrdersMessage),
+    #[prost(message, tag = "17")]
+    MigrateOrder(MigrateOrderMessage),
+    #[prost(message, tag = "18")]
+    ApplyRestaurantPromotion(ApplyRestaurantPromotionMessage),
+    #[prost(message, tag = "19")]
+    WithdrawRestaurantPromotion(WithdrawRestaurantPromotionMessage),
+    #[prost(message, tag = "20")]
+    CreatePromotion(CreatePromotionMessage),
+    #[prost(message, tag = "21")]
+    ExpirePromotion(ExpirePromotionMessage),
+    #[prost(message, tag = "22")]
+    CancelRestaurantOrder(CancelRestaurantOrderMessage),
+    #[prost(message, tag = "23")]
+    CancelOrder(CancelOrderMessage),
+    #[prost(message, tag = "24")]
+    RestockInventory(RestockInventoryMessage),
+    #[prost(message, tag = "25")]
+    ReserveStock(ReserveStockMessage),
+    #[prost(message, tag = "26")]
+    ReleaseStock(ReleaseStockMessage),
+    #[prost(message, tag = "27")]
+    IssueGiftCard(IssueGiftCardMessage),
+    #[prost(message, tag = "28")]
+    RedeemGiftCard(GiftCardPaymentMessage),
+    #[prost(message, tag = "29")]
+    RefundGiftCard(GiftCardPaymentMessage),
+    #[prost(message, tag = "30")]
+    UpdateCourierLocation(UpdateCourierLocationMessage),
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RestaurantCreatedMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub name: String,
+    #[prost(message, optional, tag = "3")]
+    pub menu: Option<RestaurantMenuMessage>,
+    #[prost(bool, tag = "4")]
+    pub r#final: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RestaurantMenuChangedMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(message, optional, tag = "2")]
+    pub menu: Option<RestaurantMenuMessage>,
+    #[prost(bool, tag = "3")]
+    pub r#final: bool,
+    #[prost(uint64, tag = "4")]
+    pub menu_version: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct OrderPlacedMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub order_identifier: String,
+    #[prost(message, repeated, tag = "3")]
+    pub line_items: Vec<OrderLineItemMessage>,
+    #[prost(bool, tag = "4")]
+    pub r#final: bool,
+    #[prost(string, optional, tag = "5")]
+    pub currency: Option<String>,
+    #[prost(message, repeated, tag = "6")]
+    pub discounts: Vec<AppliedDiscountMessage>,
+    #[prost(string, optional, tag = "7")]
+    pub group_identifier: Option<String>,
+    #[prost(string, optional, tag = "8")]
+    pub customer_identifier: Option<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct OrderCreatedMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub restaurant_identifier: String,
+    #[prost(string, tag = "3")]
+    pub status: String,
+    #[prost(message, repeated, tag = "4")]
+    pub line_items: Vec<OrderLineItemMessage>,
+    #[prost(bool, tag = "5")]
+    pub r#final: bool,
+    #[prost(message, optional, tag = "6")]
+    pub reference_total: Option<ConvertedMoneyMessage>,
+    #[prost(message, repeated, tag = "7")]
+    pub discounts: Vec<AppliedDiscountMessage>,
+    #[prost(string, optional, tag = "8")]
+    pub group_identifier: Option<String>,
+    #[prost(string, optional, tag = "9")]
+    pub customer_identifier: Option<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct OrderPreparedMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub status: String,
+    #[prost(bool, tag = "3")]
+    pub r#final: bool,
+    #[prost(string, optional, tag = "4")]
+    pub restaurant_identifier: Option<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct OrderLineItemsModifiedMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub order_identifier: String,
+    #[prost(message, repeated, tag = "3")]
+    pub line_items: Vec<OrderLineItemMessage>,
+    #[prost(bool, tag = "4")]
+    pub r#final: bool,
+    #[prost(message, repeated, tag = "5")]
+    pub discounts: Vec<AppliedDiscountMessage>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct OrderLineItemsUpdatedMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub restaurant_identifier: String,
+    #[prost(message, repeated, tag = "3")]
+    pub line_items: Vec<OrderLineItemMessage>,
+    #[prost(bool, tag = "4")]
+    pub r#final: bool,
+    #[prost(message, repeated, tag = "5")]
+    pub discounts: Vec<AppliedDiscountMessage>,
+}
+
+/// The payment event (`PaymentCaptured`, `PaymentRefunded`).
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct PaymentEventMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub order_identifier: String,
+    #[prost(uint64, tag = "3")]
+    pub amount: u64,
+    #[prost(bool, tag = "4")]
+    pub r#final: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RestaurantCapacityChangedMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(uint32, optional, tag = "2")]
+    pub max_concurrent_orders: Option<u32>,
+    #[prost(bool, tag = "3")]
+    pub r#final: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RestaurantOrderClosedMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub order_identifier: String,
+    #[prost(bool, tag = "3")]
+    pub r#final: bool,
+}
+
+/// The menu item availability event (`MenuItemMarkedUnavailable`, `MenuItemMarkedAvailable`).
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MenuItemAvailabilityEventMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub menu_item_id: String,
+    #[prost(bool, tag = "3")]
+    pub r#final: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RestaurantOpeningHoursChangedMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(message, optional, tag = "2")]
+    pub opening_hours: Option<OpeningHoursMessage>,
+    #[prost(bool, tag = "3")]
+    pub r#final: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RestaurantOrdersMigratedMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub target_identifier: String,
+    #[prost(message, repeated, tag = "3")]
+    pub orders: Vec<MigratedOrderMessage>,
+    #[prost(bool, tag = "4")]
+    pub r#final: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RestaurantOrdersReceivedMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub source_identifier: String,
+    #[prost(message, repeated, tag = "3")]
+    pub orders: Vec<MigratedOrderMessage>,
+    #[prost(bool, tag = "4")]
+    pub r#final: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct OrderMigratedMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub restaurant_identifier: String,
+    #[prost(string, tag = "3")]
+    pub status: String,
+    #[prost(bool, tag = "4")]
+    pub r#final: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RestaurantPromotionAppliedMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(message, optional, tag = "2")]
+    pub promotion: Option<RestaurantPromotionMessage>,
+    #[prost(bool, tag = "3")]
+    pub r#final: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RestaurantPromotionWithdrawnMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub promotion_id: String,
+    #[prost(bool, tag = "3")]
+    pub r#final: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct PromotionCreatedMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub restaurant_identifier: String,
+    #[prost(string, tag = "3")]
+    pub name: String,
+    #[prost(uint32, tag = "4")]
+    pub percentage: u32,
+    #[prost(string, repeated, tag = "5")]
+    pub menu_item_ids: Vec<String>,
+    #[prost(bool, tag = "6")]
+    pub r#final: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct PromotionExpiredMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub restaurant_identifier: String,
+    #[prost(bool, tag = "3")]
+    pub r#final: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RestaurantOrderCancelledMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub order_identifier: String,
+    #[prost(bool, tag = "3")]
+    pub rejected: bool,
+    #[prost(bool, tag = "4")]
+    pub r#final: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct OrderCancelledMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub restaurant_identifier: String,
+    #[prost(string, tag = "3")]
+    pub status: String,
+    #[prost(bool, tag = "4")]
+    pub r#final: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct InventoryRestockedMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub restaurant_identifier: String,
+    #[prost(message, repeated, tag = "3")]
+    pub items: Vec<StockItemMessage>,
+    #[prost(bool, tag = "4")]
+    pub r#final: bool,
+}
+
+/// The stock reserved (`StockReserved`), or released (`StockReleased`), of the order.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StockReservationEventMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub order_identifier: String,
+    #[prost(message, repeated, tag = "3")]
+    pub items: Vec<StockItemMessage>,
+    #[prost(bool, tag = "4")]
+    pub r#final: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RestaurantOrderSplitMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub group_identifier: String,
+    #[prost(message, repeated, tag = "3")]
+    pub orders: Vec<SplitOrderMessage>,
+    #[prost(bool, tag = "4")]
+    pub r#final: bool,
+    #[prost(string, optional, tag = "5")]
+    pub customer_identifier: Option<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GiftCardIssuedMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(uint64, tag = "2")]
+    pub amount: u64,
+    #[prost(bool, tag = "3")]
+    pub r#final: bool,
+}
+
+/// The gift card payment event (`GiftCardRedeemed`, `GiftCardRefunded`).
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GiftCardPaymentEventMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, tag = "2")]
+    pub restaurant_identifier: String,
+    #[prost(string, tag = "3")]
+    pub order_identifier: String,
+    #[prost(uint64, tag = "4")]
+    pub amount: u64,
+    #[prost(bool, tag = "5")]
+    pub r#final: bool,
+}
+
+/// The courier location event: the coordinates are in microdegrees.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CourierLocationUpdatedMessage {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(sint32, tag = "2")]
+    pub latitude: i32,
+    #[prost(sint32, tag = "3")]
+    pub longitude: i32,
+    #[prost(bool, tag = "4")]
+    pub r#final: bool,
+}
+
+/// The event envelope.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct EventMessage {
+    #[prost(
+        oneof = "EventKind",
+        tags = "1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31"
+    )]
+    pub event: Option<EventKind>,
+}
+
+#[derive(Clone, PartialEq, prost::Oneof)]
+pub enum EventKind {
+    #[prost(message, tag = "1")]
+    RestaurantCreated(RestaurantCreatedMessage),
+    #[prost(message, tag = "2")]
+    RestaurantMenuChanged(RestaurantMenuChangedMessage),
+    #[prost(message, tag = "3")]
+    OrderPlaced(OrderPlacedMessage),
+    #[prost(message, tag = "4")]
+    OrderCreated(OrderCreatedMessage),
+    #[prost(message, tag = "5")]
+    OrderPrepared(OrderPreparedMessage),
+    #[prost(message, tag = "6")]
+    OrderLineItemsModified(OrderLineItemsModifiedMessage),
+    #[prost(message, tag = "7")]
+    OrderLineItemsUpdated(OrderLineItemsUpdatedMessage),
+    #[prost(message, tag = "8")]
+    PaymentCaptured(PaymentEventMessage),
+    #[prost(message, tag = "9")]
+    PaymentRefunded(PaymentEventMessage),
+    #[prost(message, tag = "10")]
+    RestaurantCapacityChanged(RestaurantCapacityChangedMessage),
+    #[prost(message, tag = "11")]
+    RestaurantOrderClosed(RestaurantOrderClosedMessage),
+    #[prost(message, tag = "12")]
+    MenuItemMarkedUnavailable(MenuItemAvailabilityEventMessage),
+    #[prost(message, tag = "13")]
+    MenuItemMarkedAvailable(MenuItemAvailabilityEventMessage),
+    #[prost(message, tag = "14")]
+    RestaurantOpeningHoursChanged(RestaurantOpeningHoursChangedMessage),
+    #[prost(message, tag = "15")]
+    RestaurantOrdersMigrated(RestaurantOrdersMigratedMessage),
+    #[prost(message, tag = "16")]
+    RestaurantOrdersReceived(RestaurantOrdersReceivedMessage),
+    #[prost(message, tag = "17")]
+    OrderMigrated(OrderMigratedMessage),
+    #[prost(message, tag = "18")]
+    RestaurantPromotionApplied(RestaurantPromotionAppliedMessage),
+    #[prost(message, tag = "19")]
+    RestaurantPromotionWithdrawn(RestaurantPromotionWithdrawnMessage),
+    #[prost(message, tag = "20")]
+    PromotionCreated(PromotionCreatedMessage),
+    #[prost(message, tag = "21")]
+    PromotionExpired(PromotionExpiredMessage),
+    #[prost(message, tag = "22")]
+    RestaurantOrderCancelled(RestaurantOrderCancelledMessage),
+    #[prost(message, tag = "23")]
+    OrderCancelled(OrderCancelledMessage),
+    #[prost(message, tag = "24")]
+    InventoryRestocked(InventoryRestockedMessage),
+    #[prost(message, tag = "25")]
+    StockReserved(StockReservationEventMessage),
+    #[prost(message, tag = "26")]
+    StockReleased(StockReservationEventMessage),
+    #[prost(message, tag = "27")]
+    RestaurantOrderSplit(RestaurantOrderSplitMessage),
+    #[prost(message, tag = "28")]
+    GiftCardIssued(GiftCardIssuedMessage),
+    #[prost(message, tag = "29")]
+    GiftCardRedeemed(GiftCardPaymentEventMessage),
+    #[prost(message, tag = "30")]
+    GiftCardRefunded(GiftCardPaymentEventMessage),
+    #[prost(message, tag = "31")]
+    CourierLocationUpdated(CourierLocationUpdatedMessage),
+}
+
+/// Encodes the event to the protobuf bytes.
+pub fn encode_event(event: &Event) -> Vec<u8> {
+    prost::Message::encode_to_vec(&EventMessage::from(event))
+}
+
+/// Decodes the command from the protobuf bytes.
+pub fn decode_command(bytes: &[u8]) -> Result<Command, ErrorMessage> {
+    let message: CommandMessage = prost::Message::decode(bytes).map_err(|err| ErrorMessage {
+        message: "Failed to decode the protobuf command: ".to_string() + &err.to_string(),
+        context: None,
+    })?;
+    Command::try_from(message)
+}
+
+/// The name of the enum variant (e.g. cuisine, order status), as serialized by `serde`.
+fn to_name<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// The enum variant (e.g. cuisine, order status) of the name, as deserialized by `serde`.
+fn from_name<T: DeserializeOwned>(name: &str) -> Result<T, ErrorMessage> {
+    serde_json::from_value(serde_json::Value::String(name.to_string())).map_err(|err| {
+        ErrorMessage {
+            message: "Failed to decode the protobuf message: ".to_string() + &err.to_string(),
+            context: None,
+        }
+    })
+}
+
+fn to_uuid(value: &str) -> Result<Uuid, ErrorMessage> {
+    Uuid::parse_str(value).map_err(|err| ErrorMessage {
+        message: "Failed to decode the protobuf message: invalid identifier `".to_string()
+            + value
+            + "`: "
+            + &err.to_string(),
+        context: None,
+    })
+}
+
+impl From<&RestaurantMenu> for RestaurantMenuMessage {
+    fn from(menu: &RestaurantMenu) -> Self {
+        RestaurantMenuMessage {
+            menu_id: menu.menu_id.0.to_string(),
+            items: menu
+                .items
+                .iter()
+                .map(|item| MenuItemMessage {
+                    id: item.id.0.to_string(),
+                    name: item.name.0.clone(),
+                    price: item.price.0,
+                })
+                .collect(),
+            cuisine: to_name(&menu.cuisine),
+            currency: menu.currency.as_ref().map(|currency| currency.0.clone()),
+        }
+    }
+}
+
+impl TryFrom<Option<RestaurantMenuMessage>> for RestaurantMenu {
+    type Error = ErrorMessage;
+
+    fn try_from(menu: Option<RestaurantMenuMessage>) -> Result<Self, Self::Error> {
+        let menu = menu.ok_or(ErrorMessage {
+            message: "Failed to decode the protobuf message: menu is missing".to_string(),
+            context: None,
+        })?;
+        Ok(RestaurantMenu {
+            menu_id: MenuId(to_uuid(&menu.menu_id)?),
+            items: menu
+                .items
+                .into_iter()
+                .map(|item| {
+                    Ok(MenuItem {
+                        id: MenuItemId(to_uuid(&item.id)?),
+                        name: MenuItemName(item.name),
+                        price: Money(item.price),
+                    })
+                })
+                .collect::<Result<Vec<_>, ErrorMessage>>()?,
+            cuisine: from_name::<RestaurantMenuCuisine>(&menu.cuisine)?,
+            currency: menu.currency.map(Currency),
+        })
+    }
+}
+
+impl From<&OpeningHours> for OpeningHoursMessage {
+    fn from(opening_hours: &OpeningHours) -> Self {
+        OpeningHoursMessage {
+            time_zone: opening_hours.time_zone.clone(),
+            periods: opening_hours
+                .periods
+                .iter()
+                .map(|period| OpeningPeriodMessage {
+                    day_of_week: period.day_of_week,
+                    opens_at: period.opens_at,
+                    closes_at: period.closes_at,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<OpeningHoursMessage> for OpeningHours {
+    fn from(opening_hours: OpeningHoursMessage) -> Self {
+        OpeningHours {
+            time_zone: opening_hours.time_zone,
+            periods: opening_hours
+                .periods
+                .into_iter()
+                .map(|period| OpeningPeriod {
+                    day_of_week: period.day_of_week,
+                    opens_at: period.opens_at,
+                    closes_at: period.closes_at,
+                })
+                .collect(),
+        }
+    }
+}
+
+fn to_line_item_messages(line_items: &[OrderLineItem]) -> Vec<OrderLineItemMessage> {
+    line_items
+        .iter()
+        .map(|item| OrderLineItemMessage {
+            id: item.id.0.to_string(),
+            quantity: item.quantity.0,
+            menu_item_id: item.menu_item_id.0.to_string(),
+            name: item.name.0.clone(),
+            price: item.price.as_ref().map(|price| price.0),
+            restaurant_identifier: item
+                .restaurant_identifier
+                .as_ref()
+                .map(|restaurant_identifier| restaurant_identifier.0.to_string()),
+        })
+        .collect()
+}
+
+fn from_line_item_messages(
+    line_items: Vec<OrderLineItemMessage>,
+) -> Result<Vec<OrderLineItem>, ErrorMessage> {
+    line_items
+        .into_iter()
+        .map(|item| {
+            Ok(OrderLineItem {
+                id: OrderLineItemId(to_uuid(&item.id)?),
+                quantity: OrderLineItemQuantity(item.quantity),
+                menu_item_id: MenuItemId(to_uuid(&item.menu_item_id)?),
+                name: MenuItemName(item.name),
+                price: item.price.map(Money),
+                restaurant_identifier: item
+                    .restaurant_identifier
+                    .map(|restaurant_identifier| to_uuid(&restaurant_identifier).map(RestaurantId))
+                    .transpose()?,
+            })
+        })
+        .collect()
+}
+
+fn to_group_identifier(group_identifier: &Option<OrderGroupId>) -> Option<String> {
+    group_identifier
+        .as_ref()
+        .map(|group_identifier| group_identifier.0.to_string())
+}
+
+fn from_group_identifier(
+    group_identifier: Option<String>,
+) -> Result<Option<OrderGroupId>, ErrorMessage> {
+    group_identifier
+        .map(|group_identifier| to_uuid(&group_identifier).map(OrderGroupId))
+        .transpose()
+}
+
+fn to_customer_identifier(customer_identifier: &Option<CustomerId>) -> Option<String> {
+    customer_identifier
+        .as_ref()
+        .map(|customer_identifier| customer_identifier.0.to_string())
+}
+
+fn from_customer_identifier(
+    customer_identifier: Option<String>,
+) -> Result<Option<CustomerId>, ErrorMessage> {
+    customer_identifier
+        .map(|customer_identifier| to_uuid(&customer_identifier).map(CustomerId))
+        .transpose()
+}
+
+fn to_discount_messages(discounts: &[AppliedDiscount]) -> Vec<AppliedDiscountMessage> {
+    discounts
+        .iter()
+        .map(|discount| AppliedDiscountMessage {
+            promotion_id: discount.promotion_id.0.to_string(),
+            line_item_id: discount.line_item_id.0.to_string(),
+            amount: discount.amount.0,
+        })
+        .collect()
+}
+
+fn from_discount_messages(
+    discounts: Vec<AppliedDiscountMessage>,
+) -> Result<Vec<AppliedDiscount>, ErrorMessage> {
+    discounts
+        .into_iter()
+        .map(|discount| {
+            Ok(AppliedDiscount {
+                promotion_id: PromotionId(to_uuid(&discount.promotion_id)?),
+                line_item_id: OrderLineItemId(to_uuid(&discount.line_item_id)?),
+                amount: Money(discount.amount),
+            })
+        })
+        .collect()
+}
+
+fn to_menu_item_ids(menu_item_ids: &[MenuItemId]) -> Vec<String> {
+    menu_item_ids
+        .iter()
+        .map(|menu_item_id| menu_item_id.0.to_string())
+        .collect()
+}
+
+fn from_menu_item_ids(menu_item_ids: Vec<String>) -> Result<Vec<MenuItemId>, ErrorMessage> {
+    menu_item_ids
+        .iter()
+        .map(|menu_item_id| to_uuid(menu_item_id).map(MenuItemId))
+        .collect()
+}
+
+fn to_stock_item_messages(items: &[StockItem]) -> Vec<StockItemMessage> {
+    items
+        .iter()
+        .map(|item| StockItemMessage {
+            menu_item_id: item.menu_item_id.0.to_string(),
+            quantity: item.quantity.0,
+        })
+        .collect()
+}
+
+fn from_stock_item_messages(items: Vec<StockItemMessage>) -> Result<Vec<StockItem>, ErrorMessage> {
+    items
+        .into_iter()
+        .map(|item| {
+            Ok(StockItem {
+                menu_item_id: MenuItemId(to_uuid(&item.menu_item_id)?),
+                quantity: StockQuantity(item.quantity),
+            })
+        })
+        .collect()
+}
+
+impl From<&RestaurantPromotion> for RestaurantPromotionMessage {
+    fn from(promotion: &RestaurantPromotion) -> Self {
+        RestaurantPromotionMessage {
+            id: promotion.id.0.to_string(),
+            name: promotion.name.0.clone(),
+            percentage: promotion.percentage.0,
+            menu_item_ids: to_menu_item_ids(&promotion.menu_item_ids),
+        }
+    }
+}
+
+impl TryFrom<Option<RestaurantPromotionMessage>> for RestaurantPromotion {
+    type Error = ErrorMessage;
+
+    fn try_from(promotion: Option<RestaurantPromotionMessage>) -> Result<Self, Self::Error> {
+        let promotion = promotion.ok_or(ErrorMessage {
+            message: "Failed to decode the protobuf message: promotion is missing".to_string(),
+            context: None,
+        })?;
+        Ok(RestaurantPromotion {
+            id: PromotionId(to_uuid(&promotion.id)?),
+            name: PromotionName(promotion.name),
+            percentage: DiscountPercentage(promotion.percentage),
+            menu_item_ids: from_menu_item_ids(promotion.menu_item_ids)?,
+        })
+    }
+}
+
+fn to_migrated_order_messages(orders: &[MigratedOrder]) -> Vec<MigratedOrderMessage> {
+    orders
+        .iter()
+        .map(|order| MigratedOrderMessage {
+            order_identifier: order.order_identifier.0.to_string(),
+            total: order.total.as_ref().map(|total| total.0),
+            captured: order.captured.0,
+            refunded: order.refunded.0,
+            open: order.open,
+        })
+        .collect()
+}
+
+fn from_migrated_order_messages(
+    orders: Vec<MigratedOrderMessage>,
+) -> Result<Vec<MigratedOrder>, ErrorMessage> {
+    orders
+        .into_iter()
+        .map(|order| {
+            Ok(MigratedOrder {
+                order_identifier: OrderId(to_uuid(&order.order_identifier)?),
+                total: order.total.map(Money),
+                captured: Money(order.captured),
+                refunded: Money(order.refunded),
+                open: order.open,
+            })
+        })
+        .collect()
+}
+
+impl From<&Event> for EventMessage {
+    fn from(event: &Event) -> Self {
+        let event = match event {
+            Event::RestaurantCreated(e) => EventKind::RestaurantCreated(RestaurantCreatedMessage {
+                identifier: e.identifier.0.to_string(),
+                name: e.name.0.clone(),
+                menu: Some((&e.menu).into()),
+                r#final: e.r#final,
+            }),
+            Event::RestaurantMenuChanged(e) => {
+                EventKind::RestaurantMenuChanged(RestaurantMenuChangedMessage {
+                    identifier: e.identifier.0.to_string(),
+                    menu: Some((&e.menu).into()),
+                    r#final: e.r#final,
+                    menu_version: e.menu_version.0,
+                })
+            }
+            Event::OrderPlaced(e) => EventKind::OrderPlaced(OrderPlacedMessage {
+                identifier: e.identifier.0.to_string(),
+                order_identifier: e.order_identifier.0.to_string(),
+                line_items: to_line_item_messages(&e.line_items),
+                r#final: e.r#final,
+                currency: e.currency.as_ref().map(|currency| currency.0.clone()),
+                discounts: to_discount_messages(&e.discounts),
+                group_identifier: to_group_identifier(&e.group_identifier),
+                customer_identifier: to_customer_identifier(&e.customer_identifier),
+            }),
+            Event::OrderCreated(e) => EventKind::OrderCreated(OrderCreatedMessage {
+                identifier: e.identifier.0.to_string(),
+                restaurant_identifier: e.restaurant_identifier.0.to_string(),
+                status: to_name(&e.status),
+                line_items: to_line_item_messages(&e.line_items),
+                r#final: e.r#final,
+                reference_total: e
+                    .reference_total
+                    .as_ref()
+                    .map(|total| ConvertedMoneyMessage {
+                        amount: total.amount.0,
+                        currency: total.currency.0.clone(),
+                        rate: total.rate.0,
+                    }),
+                discounts: to_discount_messages(&e.discounts),
+                group_identifier: to_group_identifier(&e.group_identifier),
+                customer_identifier: to_customer_identifier(&e.customer_identifier),
+            }),
+            Event::OrderPrepared(e) => EventKind::OrderPrepared(OrderPreparedMessage {
+                identifier: e.identifier.0.to_string(),
+                status: to_name(&e.status),
+                r#final: e.r#final,
+                restaurant_identifier: e
+                    .restaurant_identifier
+                    .as_ref()
+                    .map(|restaurant_identifier| restaurant_identifier.0.to_string()),
+            }),
+            Event::OrderLineItemsModified(e) => {
+                EventKind::OrderLineItemsModified(OrderLineItemsModifiedMessage {
+                    identifier: e.identifier.0.to_string(),
+                    order_identifier: e.order_identifier.0.to_string(),
+                    line_items: to_line_item_messages(&e.line_items),
+                    r#final: e.r#final,
+                    discounts: to_discount_messages(&e.discounts),
+                })
+            }
+            Event::OrderLineItemsUpdated(e) => {
+                EventKind::OrderLineItemsUpdated(OrderLineItemsUpdatedMessage {
+                    identifier: e.identifier.0.to_string(),
+                    restaurant_identifier: e.restaurant_identifier.0.to_string(),
+                    line_items: to_line_item_messages(&e.line_items),
+                    r#final: e.r#final,
+                    discounts: to_discount_messages(&e.discounts),
+                })
+            }
+            Event::PaymentCaptured(e) => EventKind::PaymentCaptured(PaymentEventMessage {
+                identifier: e.identifier.0.to_string(),
+                order_identifier: e.order_identifier.0.to_string(),
+                amount: e.amount.0,
+                r#final: e.r#final,
+            }),
+            Event::PaymentRefunded(e) => EventKind::PaymentRefunded(PaymentEventMessage {
+                identifier: e.identifier.0.to_string(),
+                order_identifier: e.order_identifier.0.to_string(),
+                amount: e.amount.0,
+                r#final: e.r#final,
+            }),
+            Event::RestaurantCapacityChanged(e) => {
+                EventKind::RestaurantCapacityChanged(RestaurantCapacityChangedMessage {
+                    identifier: e.identifier.0.to_string(),
+                    max_concurrent_orders: e.max_concurrent_orders,
+                    r#final: e.r#final,
+                })
+            }
+            Event::RestaurantOrderClosed(e) => {
+                EventKind::RestaurantOrderClosed(RestaurantOrderClosedMessage {
+                    identifier: e.identifier.0.to_string(),
+                    order_identifier: e.order_identifier.0.to_string(),
+                    r#final: e.r#final,
+                })
+            }
+            Event::MenuItemMarkedUnavailable(e) => {
+                EventKind::MenuItemMarkedUnavailable(MenuItemAvailabilityEventMessage {
+                    identifier: e.identifier.0.to_string(),
+                    menu_item_id: e.menu_item_id.0.to_string(),
+                    r#final: e.r#final,
+                })
+            }
+            Event::MenuItemMarkedAvailable(e) => {
+                EventKind::MenuItemMarkedAvailable(MenuItemAvailabilityEventMessage {
+                    identifier: e.identifier.0.to_string(),
+                    menu_item_id: e.menu_item_id.0.to_string(),
+                    r#final: e.r#final,
+                })
+            }
+            Event::RestaurantOpeningHoursChanged(e) => {
+                EventKind::RestaurantOpeningHoursChanged(RestaurantOpeningHoursChangedMessage {
+                    identifier: e.identifier.0.to_string(),
+                    opening_hours: e.opening_hours.as_ref().map(Into::into),
+                    r#final: e.r#final,
+                })
+            }
+            Event::RestaurantOrdersMigrated(e) => {
+                EventKind::RestaurantOrdersMigrated(RestaurantOrdersMigratedMessage {
+                    identifier: e.identifier.0.to_string(),
+                    target_identifier: e.target_identifier.0.to_string(),
+                    orders: to_migrated_order_messages(&e.orders),
+                    r#final: e.r#final,
+                })
+            }
+            Event::RestaurantOrdersReceived(e) => {
+                EventKind::RestaurantOrdersReceived(RestaurantOrdersReceivedMessage {
+                    identifier: e.identifier.0.to_string(),
+                    source_identifier: e.source_identifier.0.to_string(),
+                    orders: to_migrated_order_messages(&e.orders),
+                    r#final: e.r#final,
+                })
+            }
+            Event::OrderMigrated(e) => EventKind::OrderMigrated(OrderMigratedMessage {
+                identifier: e.identifier.0.to_string(),
+                restaurant_identifier: e.restaurant_identifier.0.to_string(),
+                status: to_name(&e.status),
+                r#final: e.r#final,
+            }),
+            Event::RestaurantPromotionApplied(e) => {
+                EventKind::RestaurantPromotionApplied(RestaurantPromotionAppliedMessage {
+                    identifier: e.identifier.0.to_string(),
+                    promotion: Some((&e.promotion).into()),
+                    r#final: e.r#final,
+                })
+            }
+            Event::RestaurantPromotionWithdrawn(e) => {
+                EventKind::RestaurantPromotionWithdrawn(RestaurantPromotionWithdrawnMessage {
+                    identifier: e.identifier.0.to_string(),
+                    promotion_id: e.promotion_id.0.to_string(),
+                    r#final: e.r#final,
+                })
+            }
+            Event::PromotionCreated(e) => EventKind::PromotionCreated(PromotionCreatedMessage {
+                identifier: e.identifier.0.to_string(),
+                restaurant_identifier: e.restaurant_identifier.0.to_string(),
+                name: e.name.0.clone(),
+                percentage: e.percentage.0,
+                menu_item_ids: to_menu_item_ids(&e.menu_item_ids),
+                r#final: e.r#final,
+            }),
+            Event::PromotionExpired(e) => EventKind::PromotionExpired(PromotionExpiredMessage {
+                identifier: e.identifier.0.to_string(),
+                restaurant_identifier: e.restaurant_identifier.0.to_string(),
+                r#final: e.r#final,
+            }),
+            Event::RestaurantOrderCancelled(e) => {
+                EventKind::RestaurantOrderCancelled(RestaurantOrderCancelledMessage {
+                    identifier: e.identifier.0.to_string(),
+                    order_identifier: e.order_identifier.0.to_string(),
+                    rejected: e.rejected,
+                    r#final: e.r#final,
+                })
+            }
+            Event::OrderCancelled(e) => EventKind::OrderCancelled(OrderCancelledMessage {
+                identifier: e.identifier.0.to_string(),
+                restaurant_identifier: e.restaurant_identifier.0.to_string(),
+                status: to_name(&e.status),
+                r#final: e.r#final,
+            }),
+            Event::InventoryRestocked(e) => {
+                EventKind::InventoryRestocked(InventoryRestockedMessage {
+                    identifier: e.identifier.0.to_string(),
+                    restaurant_identifier: e.restaurant_identifier.0.to_string(),
+                    items: to_stock_item_messages(&e.items),
+                    r#final: e.r#final,
+                })
+            }
+            Event::StockReserved(e) => EventKind::StockReserved(StockReservationEventMessage {
+                identifier: e.identifier.0.to_string(),
+                order_identifier: e.order_identifier.0.to_string(),
+                items: to_stock_item_messages(&e.items),
+                r#final: e.r#final,
+            }),
+            Event::StockReleased(e) => EventKind::StockReleased(StockReservationEventMessage {
+                identifier: e.identifier.0.to_string(),
+                order_identifier: e.order_identifier.0.to_string(),
+                items: to_stock_item_messages(&e.items),
+                r#final: e.r#final,
+            }),
+            Event::RestaurantOrderSplit(e) => {
+                EventKind::RestaurantOrderSplit(RestaurantOrderSplitMessage {
+                    identifier: e.identifier.0.to_string(),
+                    group_identifier: e.group_identifier.0.to_string(),
+                    orders: e
+                        .orders
+                        .iter()
+                        .map(|order| SplitOrderMessage {
+                            restaurant_identifier: order.restaurant_identifier.0.to_string(),
+                            order_identifier: order.order_identifier.0.to_string(),
+                            line_items: to_line_item_messages(&order.line_items),
+                        })
+                        .collect(),
+                    r#final: e.r#final,
+                    customer_identifier: to_customer_identifier(&e.customer_identifier),
+                })
+            }
+            Event::GiftCardIssued(e) => EventKind::GiftCardIssued(GiftCardIssuedMessage {
+                identifier: e.identifier.0.to_string(),
+                amount: e.amount.0,
+                r#final: e.r#final,
+            }),
+            Event::GiftCardRedeemed(e) => {
+                EventKind::GiftCardRedeemed(GiftCardPaymentEventMessage {
+                    identifier: e.identifier.0.to_string(),
+                    restaurant_identifier: e.restaurant_identifier.0.to_string(),
+                    order_identifier: e.order_identifier.0.to_string(),
+                    amount: e.amount.0,
+                    r#final: e.r#final,
+                })
+            }
+            Event::GiftCardRefunded(e) => {
+                EventKind::GiftCardRefunded(GiftCardPaymentEventMessage {
+                    identifier: e.identifier.0.to_string(),
+                    restaurant_identifier: e.restaurant_identifier.0.to_string(),
+                    order_identifier: e.order_identifier.0.to_string(),
+                    amount: e.amount.0,
+                    r#final: e.r#final,
+                })
+            }
+            Event::CourierLocationUpdated(e) => {
+                EventKind::CourierLocationUpdated(CourierLocationUpdatedMessage {
+                    identifier: e.identifier.0.to_string(),
+                    latitude: e.latitude.0,
+                    longitude: e.longitude.0,
+                    r#final: e.r#final,
+                })
+            }
+        };
+        EventMessage { event: Some(event) }
+    }
+}
+
+impl TryFrom<EventMessage> for Event {
+    type Error = ErrorMessage;
+
+    fn try_from(message: EventMessage) -> Result<Self, Self::Error> {
+        match message.event {
+            Some(EventKind::RestaurantCreated(e)) => {
+                Ok(Event::RestaurantCreated(RestaurantCreated {
+                    identifier: RestaurantId(to_uuid(&e.identifier)?),
+                    name: RestaurantName(e.name),
+                    menu: e.menu.try_into()?,
+                    r#final: e.r#final,
+                }))
+            }
+            Some(EventKind::RestaurantMenuChanged(e)) => {
+                Ok(Event::RestaurantMenuChanged(RestaurantMenuChanged {
+                    identifier: RestaurantId(to_uuid(&e.identifier)?),
+                    menu: e.menu.try_into()?,
+                    menu_version: RestaurantMenuVersion(e.menu_version),
+                    r#final: e.r#final,
+                }))
+            }
+            Some(EventKind::OrderPlaced(e)) => Ok(Event::OrderPlaced(OrderPlaced {
+                identifier: RestaurantId(to_uuid(&e.identifier)?),
+                order_identifier: OrderId(to_uuid(&e.order_identifier)?),
+                line_items: from_line_item_messages(e.line_items)?,
+                currency: e.currency.map(Currency),
+                discounts: from_discount_messages(e.discounts)?,
+                group_identifier: from_group_identifier(e.group_identifier)?,
+                customer_identifier: from_customer_identifier(e.customer_identifier)?,
+                r#final: e.r#final,
+            })),
+            Some(EventKind::OrderCreated(e)) => Ok(Event::OrderCreated(OrderCreated {
+                identifier: OrderId(to_uuid(&e.identifier)?),
+                restaurant_identifier: RestaurantId(to_uuid(&e.restaurant_identifier)?),
+                status: from_name::<OrderStatus>(&e.status)?,
+                line_items: from_line_item_messages(e.line_items)?,
+                reference_total: e.reference_total.map(|total| ConvertedMoney {
+                    amount: Money(total.amount),
+                    currency: Currency(total.currency),
+                    rate: ExchangeRate(total.rate),
+                }),
+                discounts: from_discount_messages(e.discounts)?,
+                group_identifier: from_group_identifier(e.group_identifier)?,
+                customer_identifier: from_customer_identifier(e.customer_identifier)?,
+                r#final: e.r#final,
+            })),
+            Some(EventKind::OrderPrepared(e)) => Ok(Event::OrderPrepared(OrderPrepared {
+                identifier: OrderId(to_uuid(&e.identifier)?),
+                restaurant_identifier: e
+                    .restaurant_identifier
+                    .map(|restaurant_identifier| to_uuid(&restaurant_identifier).map(RestaurantId))
+                    .transpose()?,
+                status: from_name::<OrderStatus>(&e.status)?,
+                r#final: e.r#final,
+            })),
+            Some(EventKind::OrderLineItemsModified(e)) => {
+                Ok(Event::OrderLineItemsModified(OrderLineItemsModified {
+                    identifier: RestaurantId(to_uuid(&e.identifier)?),
+                    order_identifier: OrderId(to_uuid(&e.order_identifier)?),
+                    line_items: from_line_item_messages(e.line_items)?,
+                    discounts: from_discount_messages(e.discounts)?,
+                    r#final: e.r#final,
+                }))
+            }
+            Some(EventKind::OrderLineItemsUpdated(e)) => {
+                Ok(Event::OrderLineItemsUpdated(OrderLineItemsUpdated {
+                    identifier: OrderId(to_uuid(&e.identifier)?),
+                    restaurant_identifier: RestaurantId(to_uuid(&e.restaurant_identifier)?),
+                    line_items: from_line_item_messages(e.line_items)?,
+                    discounts: from_discount_messages(e.discounts)?,
+                    r#final: e.r#final,
+                }))
+            }
+            Some(EventKind::PaymentCaptured(e)) => Ok(Event::PaymentCaptured(PaymentCaptured {
+                identifier: RestaurantId(to_uuid(&e.identifier)?),
+                order_identifier: OrderId(to_uuid(&e.order_identifier)?),
+                amount: Money(e.amount),
+                r#final: e.r#final,
+            })),
+            Some(EventKind::PaymentRefunded(e)) => Ok(Event::PaymentRefunded(PaymentRefunded {
+                identifier: RestaurantId(to_uuid(&e.identifier)?),
+                order_identifier: OrderId(to_uuid(&e.order_identifier)?),
+                amount: Money(e.amount),
+                r#final: e.r#final,
+            })),
+            Some(EventKind::RestaurantCapacityChanged(e)) => Ok(Event::RestaurantCapacityChanged(
+                RestaurantCapacityChanged {
+                    identifier: RestaurantId(to_uuid(&e.identifier)?),
+                    max_concurrent_orders: e.max_concurrent_orders,
+                    r#final: e.r#final,
+                },
+            )),
+            Some(EventKind::RestaurantOrderClosed(e)) => {
+                Ok(Event::RestaurantOrderClosed(RestaurantOrderClosed {
+                    identifier: RestaurantId(to_uuid(&e.identifier)?),
+                    order_identifier: OrderId(to_uuid(&e.order_identifier)?),
+                    r#final: e.r#final,
+                }))
+            }
+            Some(EventKind::MenuItemMarkedUnavailable(e)) => Ok(Event::MenuItemMarkedUnavailable(
+                MenuItemMarkedUnavailable {
+                    identifier: RestaurantId(to_uuid(&e.identifier)?),
+                    menu_item_id: MenuItemId(to_uuid(&e.menu_item_id)?),
+                    r#final: e.r#final,
+                },
+            )),
+            Some(EventKind::MenuItemMarkedAvailable(e)) => {
+                Ok(Event::MenuItemMarkedAvailable(MenuItemMarkedAvailable {
+                    identifier: RestaurantId(to_uuid(&e.identifier)?),
+                    menu_item_id: MenuItemId(to_uuid(&e.menu_item_id)?),
+                    r#final: e.r#final,
+                }))
+            }
+            Some(EventKind::RestaurantOpeningHoursChanged(e)) => Ok(
+                Event::RestaurantOpeningHoursChanged(RestaurantOpeningHoursChanged {
+                    identifier: RestaurantId(to_uuid(&e.identifier)?),
+                    opening_hours: e.opening_hours.map(Into::into),
+                    r#final: e.r#final,
+                }),
+            ),
+            Some(EventKind::RestaurantOrdersMigrated(e)) => {
+                Ok(Event::RestaurantOrdersMigrated(RestaurantOrdersMigrated {
+                    identifier: RestaurantId(to_uuid(&e.identifier)?),
+                    target_identifier: RestaurantId(to_uuid(&e.target_identifier)?),
+                    orders: from_migrated_order_messages(e.orders)?,
+                    r#final: e.r#final,
+                }))
+            }
+            Some(EventKind::RestaurantOrdersReceived(e)) => {
+                Ok(Event::RestaurantOrdersReceived(RestaurantOrdersReceived {
+                    identifier: RestaurantId(to_uuid(&e.identifier)?),
+                    source_identifier: RestaurantId(to_uuid(&e.source_identifier)?),
+                    orders: from_migrated_order_messages(e.orders)?,
+                    r#final: e.r#final,
+                }))
+            }
+            Some(EventKind::OrderMigrated(e)) => Ok(Event::OrderMigrated(OrderMigrated {
+                identifier: OrderId(to_uuid(&e.identifier)?),
+                restaurant_identifier: RestaurantId(to_uuid(&e.restaurant_identifier)?),
+                status: from_name::<OrderStatus>(&e.status)?,
+                r#final: e.r#final,
+            })),
+            Some(EventKind::RestaurantPromotionApplied(e)) => Ok(
+                Event::RestaurantPromotionApplied(RestaurantPromotionApplied {
+                    identifier: RestaurantId(to_uuid(&e.identifier)?),
+                    promotion: e.promotion.try_into()?,
+                    r#final: e.r#final,
+                }),
+            ),
+            Some(EventKind::RestaurantPromotionWithdrawn(e)) => Ok(
+                Event::RestaurantPromotionWithdrawn(RestaurantPromotionWithdrawn {
+                    identifier: RestaurantId(to_uuid(&e.identifier)?),
+                    promotion_id: PromotionId(to_uuid(&e.promotion_id)?),
+                    r#final: e.r#final,
+                }),
+            ),
+            Some(EventKind::PromotionCreated(e)) => Ok(Event::PromotionCreated(PromotionCreated {
+                identifier: PromotionId(to_uuid(&e.identifier)?),
+                restaurant_identifier: RestaurantId(to_uuid(&e.restaurant_identifier)?),
+                name: PromotionName(e.name),
+                percentage: DiscountPercentage(e.percentage),
+                menu_item_ids: from_menu_item_ids(e.menu_item_ids)?,
+                r#final: e.r#final,
+            })),
+            Some(EventKind::PromotionExpired(e)) => Ok(Event::PromotionExpired(PromotionExpired {
+                identifier: PromotionId(to_uuid(&e.identifier)?),
+                restaurant_identifier: RestaurantId(to_uuid(&e.restaurant_identifier)?),
+                r#final: e.r#final,
+            })),
+            Some(EventKind::RestaurantOrderCancelled(e)) => {
+                Ok(Event::RestaurantOrderCancelled(RestaurantOrderCancelled {
+                    identifier: RestaurantId(to_uuid(&e.identifier)?),
+                    order_identifier: OrderId(to_uuid(&e.order_identifier)?),
+                    rejected: e.rejected,
+                    r#final: e.r#final,
+                }))
+            }
+            Some(EventKind::OrderCancelled(e)) => Ok(Event::OrderCancelled(OrderCancelled {
+                identifier: OrderId(to_uuid(&e.identifier)?),
+                restaurant_identifier: RestaurantId(to_uuid(&e.restaurant_identifier)?),
+                status: from_name::<OrderStatus>(&e.status)?,
+                r#final: e.r#final,
+            })),
+            Some(EventKind::InventoryRestocked(e)) => {
+                Ok(Event::InventoryRestocked(InventoryRestocked {
+                    identifier: InventoryId(to_uuid(&e.identifier)?),
+                    restaurant_identifier: RestaurantId(to_uuid(&e.restaurant_identifier)?),
+                    items: from_stock_item_messages(e.items)?,
+                    r#final: e.r#final,
+                }))
+            }
+            Some(EventKind::StockReserved(e)) => Ok(Event::StockReserved(StockReserved {
+                identifier: InventoryId(to_uuid(&e.identifier)?),
+                order_identifier: OrderId(to_uuid(&e.order_identifier)?),
+                items: from_stock_item_messages(e.items)?,
+                r#final: e.r#final,
+            })),
+            Some(EventKind::StockReleased(e)) => Ok(Event::StockReleased(StockReleased {
+                identifier: InventoryId(to_uuid(&e.identifier)?),
+                order_identifier: OrderId(to_uuid(&e.order_identifier)?),
+                items: from_stock_item_messages(e.items)?,
+                r#final: e.r#final,
+            })),
+            Some(EventKind::RestaurantOrderSplit(e)) => {
+                Ok(Event::RestaurantOrderSplit(RestaurantOrderSplit {
+                    identifier: RestaurantId(to_uuid(&e.identifier)?),
+                    group_identifier: OrderGroupId(to_uuid(&e.group_identifier)?),
+                    orders: e
+                        .orders
+                        .into_iter()
+                        .map(|order| {
+                            Ok(SplitOrder {
+                                restaurant_identifier: RestaurantId(to_uuid(
+                                    &order.restaurant_identifier,
+                                )?),
+                                order_identifier: OrderId(to_uuid(&order.order_identifier)?),
+                                line_items: from_line_item_messages(order.line_items)?,
+                            })
+                        })
+                        .collect::<Result<Vec<SplitOrder>, ErrorMessage>>()?,
+                    customer_identifier: from_customer_identifier(e.customer_identifier)?,
+                    r#final: e.r#final,
+                }))
+            }
+            Some(EventKind::GiftCardIssued(e)) => Ok(Event::GiftCardIssued(GiftCardIssued {
+                identifier: GiftCardId(to_uuid(&e.identifier)?),
+                amount: Money(e.amount),
+                r#final: e.r#final,
+            })),
+            Some(EventKind::GiftCardRedeemed(e)) => Ok(Event::GiftCardRedeemed(GiftCardRedeemed {
+                identifier: GiftCardId(to_uuid(&e.identifier)?),
+                restaurant_identifier: RestaurantId(to_uuid(&e.restaurant_identifier)?),
+                order_identifier: OrderId(to_uuid(&e.order_identifier)?),
+                amount: Money(e.amount),
+                r#final: e.r#final,
+            })),
+            Some(EventKind::GiftCardRefunded(e)) => Ok(Event::GiftCardRefunded(GiftCardRefunded {
+                identifier: GiftCardId(to_uuid(&e.identifier)?),
+                restaurant_identifier: RestaurantId(to_uuid(&e.restaurant_identifier)?),
+                order_identifier: OrderId(to_uuid(&e.order_identifier)?),
+                amount: Money(e.amount),
+                r#final: e.r#final,
+            })),
+            Some(EventKind::CourierLocationUpdated(e)) => {
+                Ok(Event::CourierLocationUpdated(CourierLocationUpdated {
+                    identifier: CourierId(to_uuid(&e.identifier)?),
+                    latitude: Coordinate(e.latitude),
+                    longitude: Coordinate(e.longitude),
+                    r#final: e.r#final,
+                }))
+            }
+            None => Err(ErrorMessage {
+                message: "Failed to decode the protobuf event: event is missing".to_string(),
+                context: None,
+            }),
+        }
+    }
+}
+
+impl From<&Command> for CommandMessage {
+    fn from(command: &Command) -> Self {
+        let command = match command {
+            Command::CreateRestaurant(c) => {
+                CommandKind::CreateRestaurant(CreateRestaurantMessage {
+                    identifier: c.identifier.0.to_string(),
+                    name: c.name.0.clone(),
+                    menu: Some((&c.menu).into()),
+                })
+            }
+            Command::ChangeRestaurantMenu(c) => {
+                CommandKind::ChangeRestaurantMenu(ChangeRestaurantMenuMessage {
+                    identifier: c.identifier.0.to_string(),
+                    menu: Some((&c.menu).into()),
+                })
+            }
+            Command::PlaceOrder(c) => CommandKind::PlaceOrder(PlaceOrderMessage {
+                identifier: c.identifier.0.to_string(),
+                order_identifier: c.order_identifier.0.to_string(),
+                line_items: to_line_item_messages(&c.line_items),
+                group_identifier: to_group_identifier(&c.group_identifier),
+                customer_identifier: to_customer_identifier(&c.customer_identifier),
+            }),
+            Command::CreateOrder(c) => CommandKind::CreateOrder(CreateOrderMessage {
+                identifier: c.identifier.0.to_string(),
+                restaurant_identifier: c.restaurant_identifier.0.to_string(),
+                line_items: to_line_item_messages(&c.line_items),
+                currency: c.currency.as_ref().map(|currency| currency.0.clone()),
+                discounts: to_discount_messages(&c.discounts),
+                group_identifier: to_group_identifier(&c.group_identifier),
+                customer_identifier: to_customer_identifier(&c.customer_identifier),
+            }),
+            Command::MarkOrderAsPrepared(c) => {
+                CommandKind::MarkOrderAsPrepared(MarkOrderAsPreparedMessage {
+                    identifier: c.identifier.0.to_string(),
+                })
+            }
+            Command::ModifyOrderLineItems(c) => {
+                CommandKind::ModifyOrderLineItems(ModifyOrderLineItemsMessage {
+                    identifier: c.identifier.0.to_string(),
+                    order_identifier: c.order_identifier.0.to_string(),
+                    line_items: to_line_item_messages(&c.line_items),
+                })
+            }
+            Command::UpdateOrderLineItems(c) => {
+                CommandKind::UpdateOrderLineItems(UpdateOrderLineItemsMessage {
+                    identifier: c.identifier.0.to_string(),
+                    restaurant_identifier: c.restaurant_identifier.0.to_string(),
+                    line_items: to_line_item_messages(&c.line_items),
+                    discounts: to_discount_messages(&c.discounts),
+                })
+            }
+            Command::CapturePayment(c) => CommandKind::CapturePayment(PaymentMessage {
+                identifier: c.identifier.0.to_string(),
+                order_identifier: c.order_identifier.0.to_string(),
+                amount: c.amount.0,
+            }),
+            Command::RefundPayment(c) => CommandKind::RefundPayment(PaymentMessage {
+                identifier: c.identifier.0.to_string(),
+                order_identifier: c.order_identifier.0.to_string(),
+                amount: c.amount.0,
+            }),
+            Command::ChangeRestaurantCapacity(c) => {
+                CommandKind::ChangeRestaurantCapacity(ChangeRestaurantCapacityMessage {
+                    identifier: c.identifier.0.to_string(),
+                    max_concurrent_orders: c.max_concurrent_orders,
+                })
+            }
+            Command::CloseRestaurantOrder(c) => {
+                CommandKind::CloseRestaurantOrder(CloseRestaurantOrderMessage {
+                    identifier: c.identifier.0.to_string(),
+                    order_identifier: c.order_identifier.0.to_string(),
+                })
+            }
+            Command::MarkMenuItemUnavailable(c) => {
+                CommandKind::MarkMenuItemUnavailable(MenuItemAvailabilityMessage {
+                    identifier: c.identifier.0.to_string(),
+                    menu_item_id: c.menu_item_id.0.to_string(),
+                })
+            }
+            Command::MarkMenuItemAvailable(c) => {
+                CommandKind::MarkMenuItemAvailable(MenuItemAvailabilityMessage {
+                    identifier: c.identifier.0.to_string(),
+                    menu_item_id: c.menu_item_id.0.to_string(),
+                })
+            }
+            Command::ChangeRestaurantOpeningHours(c) => {
+                CommandKind::ChangeRestaurantOpeningHours(ChangeRestaurantOpeningHoursMessage {
+                    identifier: c.identifier.0.to_string(),
+                    opening_hours: c.opening_hours.as_ref().map(Into::into),
+                })
+            }
+            Command::MigrateRestaurantOrders(c) => {
+                CommandKind::MigrateRestaurantOrders(MigrateRestaurantOrdersMessage {
+                    identifier: c.identifier.0.to_string(),
+                    target_identifier: c.target_identifier.0.to_string(),
+                    order_identifiers: c
+                        .order_identifiers
+                        .iter()
+                        .flatten()
+                        .map(|order_identifier| order_identifier.0.to_string())
+                        .collect(),
+                    all_orders: c.order_identifiers.is_none(),
+                })
+            }
+            Command::ReceiveRestaurantOrders(c) => {
+                CommandKind::ReceiveRestaurantOrders(ReceiveRestaurantOrdersMessage {
+                    identifier: c.identifier.0.to_string(),
+                    source_identifier: c.source_identifier.0.to_string(),
+                    orders: to_migrated_order_messages(&c.orders),
+                })
+            }
+            Command::MigrateOrder(c) => CommandKind::MigrateOrder(MigrateOrderMessage {
+                identifier: c.identifier.0.to_string(),
+                restaurant_identifier: c.restaurant_identifier.0.to_string(),
+            }),
+            Command::ApplyRestaurantPromotion(c) => {
+                CommandKind::ApplyRestaurantPromotion(ApplyRestaurantPromotionMessage {
+                    identifier: c.identifier.0.to_string(),
+                    promotion: Some((&c.promotion).into()),
+                })
+            }
+            Command::WithdrawRestaurantPromotion(c) => {
+                CommandKind::WithdrawRestaurantPromotion(WithdrawRestaurantPromotionMessage {
+                    identifier: c.identifier.0.to_string(),
+                    promotion_id: c.promotion_id.0.to_string(),
+                })
+            }
+            Command::CreatePromotion(c) => CommandKind::CreatePromotion(CreatePromotionMessage {
+                identifier: c.identifier.0.to_string(),
+                restaurant_identifier: c.restaurant_identifier.0.to_string(),
+                name: c.name.0.clone(),
+                percentage: c.percentage.0,
+                menu_item_ids: to_menu_item_ids(&c.menu_item_ids),
+            }),
+            Command::ExpirePromotion(c) => CommandKind::ExpirePromotion(ExpirePromotionMessage {
+                identifier: c.identifier.0.to_string(),
+            }),
+            Command::CancelRestaurantOrder(c) => {
+                CommandKind::CancelRestaurantOrder(CancelRestaurantOrderMessage {
+                    identifier: c.identifier.0.to_string(),
+                    order_identifier: c.order_identifier.0.to_string(),
+                    rejected: c.rejected,
+                })
+            }
+            Command::CancelOrder(c) => CommandKind::CancelOrder(CancelOrderMessage {
+                identifier: c.identifier.0.to_string(),
+                rejected: c.rejected,
+            }),
+            Command::RestockInventory(c) => {
+                CommandKind::RestockInventory(RestockInventoryMessage {
+                    identifier: c.identifier.0.to_string(),
+                    restaurant_identifier: c.restaurant_identifier.0.to_string(),
+                    items: to_stock_item_messages(&c.items),
+                })
+            }
+            Command::ReserveStock(c) => CommandKind::ReserveStock(ReserveStockMessage {
+                identifier: c.identifier.0.to_string(),
+                order_identifier: c.order_identifier.0.to_string(),
+                items: to_stock_item_messages(&c.items),
+            }),
+            Command::ReleaseStock(c) => CommandKind::ReleaseStock(ReleaseStockMessage {
+                identifier: c.identifier.0.to_string(),
+                order_identifier: c.order_identifier.0.to_string(),
+            }),
+            Command::IssueGiftCard(c) => CommandKind::IssueGiftCard(IssueGiftCardMessage {
+                identifier: c.identifier.0.to_string(),
+                amount: c.amount.0,
+            }),
+            Command::RedeemGiftCard(c) => CommandKind::RedeemGiftCard(GiftCardPaymentMessage {
+                identifier: c.identifier.0.to_string(),
+                restaurant_identifier: c.restaurant_identifier.0.to_string(),
+                order_identifier: c.order_identifier.0.to_string(),
+                amount: c.amount.0,
+            }),
+            Command::RefundGiftCard(c) => CommandKind::RefundGiftCard(GiftCardPaymentMessage {
+                identifier: c.identifier.0.to_string(),
+                restaurant_identifier: c.restaurant_identifier.0.to_string(),
+                order_identifier: c.order_identifier.0.to_string(),
+                amount: c.amount.0,
+            }),
+            Command::UpdateCourierLocation(c) => {
+                CommandKind::UpdateCourierLocation(UpdateCourierLocationMessage {
+                    identifier: c.identifier.0.to_string(),
+                    latitude: c.latitude.0,
+                    longitude: c.longitude.0,
+                })
+            }
+        };
+        CommandMessage {
+            command: Some(command),
+        }
+    }
+}
+
+impl TryFrom<CommandMessage> for Command {
+    type Error = ErrorMessage;
+
+    fn try_from(message: CommandMessage) -> Result<Self, Self::Error> {
+        match message.command {
+            Some(CommandKind::CreateRestaurant(c)) => {
+                Ok(Command::CreateRestaurant(CreateRestaurant {
+                    identifier: RestaurantId(to_uuid(&c.identifier)?),
+                    name: RestaurantName(c.name),
+                    menu: c.menu.try_into()?,
+                }))
+            }
+            Some(CommandKind::ChangeRestaurantMenu(c)) => {
+                Ok(Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
+                    identifier: RestaurantId(to_uuid(&c.identifier)?),
+                    menu: c.menu.try_into()?,
+                }))
+            }
+            Some(CommandKind::PlaceOrder(c)) => Ok(Command::PlaceOrder(PlaceOrder {
+                identifier: RestaurantId(to_uuid(&c.identifier)?),
+                order_identifier: OrderId(to_uuid(&c.order_identifier)?),
+                line_items: from_line_item_messages(c.line_items)?,
+                group_identifier: from_group_identifier(c.group_identifier)?,
+                customer_identifier: from_customer_identifier(c.customer_identifier)?,
+            })),
+            Some(CommandKind::CreateOrder(c)) => Ok(Command::CreateOrder(CreateOrder {
+                identifier: OrderId(to_uuid(&c.identifier)?),
+                restaurant_identifier: RestaurantId(to_uuid(&c.restaurant_identifier)?),
+                line_items: from_line_item_messages(c.line_items)?,
+                currency: c.currency.map(Currency),
+                discounts: from_discount_messages(c.discounts)?,
+                group_identifier: from_group_identifier(c.group_identifier)?,
+                customer_identifier: from_customer_identifier(c.customer_identifier)?,
+            })),
+            Some(CommandKind::MarkOrderAsPrepared(c)) => {
+                Ok(Command::MarkOrderAsPrepared(MarkOrderAsPrepared {
+                    identifier: OrderId(to_uuid(&c.identifier)?),
+                }))
+            }
+            Some(CommandKind::ModifyOrderLineItems(c)) => {
+                Ok(Command::ModifyOrderLineItems(ModifyOrderLineItems {
+                    identifier: RestaurantId(to_uuid(&c.identifier)?),
+                    order_identifier: OrderId(to_uuid(&c.order_identifier)?),
+                    line_items: from_line_item_messages(c.line_items)?,
+                }))
+            }
+            Some(CommandKind::UpdateOrderLineItems(c)) => {
+                Ok(Command::UpdateOrderLineItems(UpdateOrderLineItems {
+                    identifier: OrderId(to_uuid(&c.identifier)?),
+                    restaurant_identifier: RestaurantId(to_uuid(&c.restaurant_identifier)?),
+                    line_items: from_line_item_messages(c.line_items)?,
+                    discounts: from_discount_messages(c.discounts)?,
+                }))
+            }
+            Some(CommandKind::CapturePayment(c)) => Ok(Command::CapturePayment(CapturePayment {
+                identifier: RestaurantId(to_uuid(&c.identifier)?),
+                order_identifier: OrderId(to_uuid(&c.order_identifier)?),
+                amount: Money(c.amount),
+            })),
+            Some(CommandKind::RefundPayment(c)) => Ok(Command::RefundPayment(RefundPayment {
+                identifier: RestaurantId(to_uuid(&c.identifier)?),
+                order_identifier: OrderId(to_uuid(&c.order_identifier)?),
+                amount: Money(c.amount),
+            })),
+            Some(CommandKind::ChangeRestaurantCapacity(c)) => Ok(
+                Command::ChangeRestaurantCapacity(ChangeRestaurantCapacity {
+                    identifier: RestaurantId(to_uuid(&c.identifier)?),
+                    max_concurrent_orders: c.max_concurrent_orders,
+                }),
+            ),
+            Some(CommandKind::CloseRestaurantOrder(c)) => {
+                Ok(Command::CloseRestaurantOrder(CloseRestaurantOrder {
+                    identifier: RestaurantId(to_uuid(&c.identifier)?),
+                    order_identifier: OrderId(to_uuid(&c.order_identifier)?),
+                }))
+            }
+            Some(CommandKind::MarkMenuItemUnavailable(c)) => {
+                Ok(Command::MarkMenuItemUnavailable(MarkMenuItemUnavailable {
+                    identifier: RestaurantId(to_uuid(&c.identifier)?),
+                    menu_item_id: MenuItemId(to_uuid(&c.menu_item_id)?),
+                }))
+            }
+            Some(CommandKind::MarkMenuItemAvailable(c)) => {
+                Ok(Command::MarkMenuItemAvailable(MarkMenuItemAvailable {
+                    identifier: RestaurantId(to_uuid(&c.identifier)?),
+                    menu_item_id: MenuItemId(to_uuid(&c.menu_item_id)?),
+                }))
+            }
+            Some(CommandKind::ChangeRestaurantOpeningHours(c)) => Ok(
+                Command::ChangeRestaurantOpeningHours(ChangeRestaurantOpeningHours {
+                    identifier: RestaurantId(to_uuid(&c.identifier)?),
+                    opening_hours: c.opening_hours.map(Into::into),
+                }),
+            ),
+            Some(CommandKind::MigrateRestaurantOrders(c)) => {
+                Ok(Command::MigrateRestaurantOrders(MigrateRestaurantOrders {
+                    identifier: RestaurantId(to_uuid(&c.identifier)?),
+                    target_identifier: RestaurantId(to_uuid(&c.target_identifier)?),
+                    order_identifiers: if c.all_orders {
+                        None
+                    } else {
+                        Some(
+                            c.order_identifiers
+                                .iter()
+                                .map(|order_identifier| to_uuid(order_identifier).map(OrderId))
+                                .collect::<Result<Vec<_>, ErrorMessage>>()?,
+                        )
+                    },
+                }))
+            }
+            Some(CommandKind::ReceiveRestaurantOrders(c)) => {
+                Ok(Command::ReceiveRestaurantOrders(ReceiveRestaurantOrders {
+                    identifier: RestaurantId(to_uuid(&c.identifier)?),
+                    source_identifier: RestaurantId(to_uuid(&c.source_identifier)?),
+                    orders: from_migrated_order_messages(c.orders)?,
+                }))
+            }
+            Some(CommandKind::MigrateOrder(c)) => Ok(Command::MigrateOrder(MigrateOrder {
+                identifier: OrderId(to_uuid(&c.identifier)?),
+                restaurant_identifier: RestaurantId(to_uuid(&c.restaurant_identifier)?),
+            })),
+            Some(CommandKind::ApplyRestaurantPromotion(c)) => Ok(
+                Command::ApplyRestaurantPromotion(ApplyRestaurantPromotion {
+                    identifier: RestaurantId(to_uuid(&c.identifier)?),
+                    promotion: c.promotion.try_into()?,
+                }),
+            ),
+            Some(CommandKind::WithdrawRestaurantPromotion(c)) => Ok(
+                Command::WithdrawRestaurantPromotion(WithdrawRestaurantPromotion {
+                    identifier: RestaurantId(to_uuid(&c.identifier)?),
+                    promotion_id: PromotionId(to_uuid(&c.promotion_id)?),
+                }),
+            ),
+            Some(CommandKind::CreatePromotion(c)) => {
+                Ok(Command::CreatePromotion(CreatePromotion {
+                    identifier: PromotionId(to_uuid(&c.identifier)?),
+                    restaurant_identifier: RestaurantId(to_uuid(&c.restaurant_identifier)?),
+                    name: PromotionName(c.name),
+                    percentage: DiscountPercentage(c.percentage),
+                    menu_item_ids: from_menu_item_ids(c.menu_item_ids)?,
+                }))
+            }
+            Some(CommandKind::ExpirePromotion(c)) => {
+                Ok(Command::ExpirePromotion(ExpirePromotion {
+                    identifier: PromotionId(to_uuid(&c.identifier)?),
+                }))
+            }
+            Some(CommandKind::CancelRestaurantOrder(c)) => {
+                Ok(Command::CancelRestaurantOrder(CancelRestaurantOrder {
+                    identifier: RestaurantId(to_uuid(&c.identifier)?),
+                    order_identifier: OrderId(to_uuid(&c.order_identifier)?),
+                    rejected: c.rejected,
+                }))
+            }
+            Some(CommandKind::CancelOrder(c)) => Ok(Command::CancelOrder(CancelOrder {
+                identifier: OrderId(to_uuid(&c.identifier)?),
+                rejected: c.rejected,
+            })),
+            Some(CommandKind::RestockInventory(c)) => {
+                Ok(Command::RestockInventory(RestockInventory {
+                    identifier: InventoryId(to_uuid(&c.identifier)?),
+                    restaurant_identifier: RestaurantId(to_uuid(&c.restaurant_identifier)?),
+                    items: from_stock_item_messages(c.items)?,
+                }))
+            }
+            Some(CommandKind::ReserveStock(c)) => Ok(Command::ReserveStock(ReserveStock {
+                identifier: InventoryId(to_uuid(&c.identifier)?),
+                order_identifier: OrderId(to_uuid(&c.order_identifier)?),
+                items: from_stock_item_messages(c.items)?,
+            })),
+            Some(CommandKind::ReleaseStock(c)) => Ok(Command::ReleaseStock(ReleaseStock {
+                identifier: InventoryId(to_uuid(&c.identifier)?),
+                order_identifier: OrderId(to_uuid(&c.order_identifier)?),
+            })),
+            Some(CommandKind::IssueGiftCard(c)) => Ok(Command::IssueGiftCard(IssueGiftCard {
+                identifier: GiftCardId(to_uuid(&c.identifier)?),
+                amount: Money(c.amount),
+            })),
+            Some(CommandKind::RedeemGiftCard(c)) => Ok(Command::RedeemGiftCard(RedeemGiftCard {
+                identifier: GiftCardId(to_uuid(&c.identifier)?),
+                restaurant_identifier: RestaurantId(to_uuid(&c.restaurant_identifier)?),
+                order_identifier: OrderId(to_uuid(&c.order_identifier)?),
+                amount: Money(c.amount),
+            })),
+            Some(CommandKind::RefundGiftCard(c)) => Ok(Command::RefundGiftCard(RefundGiftCard {
+                identifier: GiftCardId(to_uuid(&c.identifier)?),
+                restaurant_identifier: RestaurantId(to_uuid(&c.restaurant_identifier)?),
+                order_identifier: OrderId(to_uuid(&c.order_identifier)?),
+                amount: Money(c.amount),
+            })),
+            Some(CommandKind::UpdateCourierLocation(c)) => {
+                Ok(Command::UpdateCourierLocation(UpdateCourierLocation {
+                    identifier: CourierId(to_uuid(&c.identifier)?),
+                    latitude: Coordinate(c.latitude),
+                    longitude: Coordinate(c.longitude),
+                }))
+            }
+            None => Err(ErrorMessage {
+                message: "Failed to decode the protobuf command: command is missing".to_string(),
+                context: None,
+            }),
+        }
+    }
+}
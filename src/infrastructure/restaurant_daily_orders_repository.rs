@@ -0,0 +1,48 @@
+use crate::domain::api::RestaurantId;
+use crate::domain::restaurant_daily_orders_view::RestaurantDailyOrders;
+use crate::framework::infrastructure::aggregating_view_repository::AggregatingViewRepository;
+use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::framework::infrastructure::event_repository::EventPosition;
+use pgrx::{IntoDatum, PgBuiltInOids, Spi};
+
+/// RestaurantDailyOrdersRepository struct
+/// The `restaurant_daily_orders` projection: the order counters per restaurant per day (UTC).
+pub struct RestaurantDailyOrdersRepository {}
+
+/// RestaurantDailyOrdersRepository - struct implementation
+impl RestaurantDailyOrdersRepository {
+    /// Create a new RestaurantDailyOrdersRepository
+    pub fn new() -> Self {
+        RestaurantDailyOrdersRepository {}
+    }
+}
+
+/// Implementation of the aggregating view repository for the daily orders of the restaurant.
+impl AggregatingViewRepository<RestaurantId, RestaurantDailyOrders>
+    for RestaurantDailyOrdersRepository
+{
+    /// Adds the increment to the counters of the restaurant, in the day in which the event was appended to the event store.
+    fn increment(
+        &self,
+        bucket: &RestaurantId,
+        increment: &RestaurantDailyOrders,
+        position: &EventPosition,
+    ) -> Result<(), ErrorMessage> {
+        Spi::run_with_args(
+            r#"INSERT INTO restaurant_daily_orders (restaurant_id, day, orders, items, revenue, last_offset)
+               SELECT $1, (created_at AT TIME ZONE 'UTC')::DATE, $2, $3, $4, $5 FROM events WHERE "offset" = $5
+               ON CONFLICT (store_id, restaurant_id, day) DO UPDATE SET orders = restaurant_daily_orders.orders + EXCLUDED.orders,
+                                                              items = restaurant_daily_orders.items + EXCLUDED.items,
+                                                              revenue = restaurant_daily_orders.revenue + EXCLUDED.revenue,
+                                                              last_offset = GREATEST(restaurant_daily_orders.last_offset, EXCLUDED.last_offset)"#,
+            Some(vec![
+                (PgBuiltInOids::UUIDOID.oid(), bucket.0.to_string().into_datum()),
+                (PgBuiltInOids::INT8OID.oid(), increment.orders.into_datum()),
+                (PgBuiltInOids::INT8OID.oid(), increment.items.into_datum()),
+                (PgBuiltInOids::INT8OID.oid(), increment.revenue.into_datum()),
+                (PgBuiltInOids::INT8OID.oid(), position.offset.into_datum()),
+            ]),
+        )
+        .map_err(|err| ErrorMessage::spi("save the restaurant daily orders", None, &err))
+    }
+}
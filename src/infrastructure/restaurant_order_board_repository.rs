@@ -0,0 +1,146 @@
+use crate::domain::api::{OrderEvent, OrderPrepared, RestaurantEvent};
+use crate::domain::restaurant_order_board_view::RestaurantOrderBoardState;
+use crate::framework::domain::api::Identifier;
+use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::framework::infrastructure::event_repository::EventPosition;
+use crate::framework::infrastructure::to_payload;
+use crate::framework::infrastructure::view_state_repository::ViewStateRepository;
+use fmodel_rust::Sum;
+use pgrx::{IntoDatum, JsonB, PgBuiltInOids, Spi};
+use serde_json::json;
+use uuid::Uuid;
+
+/// Repository of the restaurant order board (cross-domain) view: a row per restaurant, with the status of its orders.
+pub struct RestaurantOrderBoardRepository {}
+
+impl RestaurantOrderBoardRepository {
+    /// Create a new RestaurantOrderBoardRepository
+    pub fn new() -> Self {
+        RestaurantOrderBoardRepository {}
+    }
+
+    /// Resolves the board (restaurant) the event belongs to.
+    /// The restaurant events, `OrderCreated`, `OrderLineItemsUpdated`, `OrderMigrated` and `OrderCancelled` carry the restaurant id; the legacy `OrderPrepared` events carry the order id only, so their board is looked up by the order.
+    fn board_id(
+        &self,
+        event: &Sum<RestaurantEvent, OrderEvent>,
+    ) -> Result<Option<Uuid>, ErrorMessage> {
+        match event {
+            Sum::First(event) => Ok(Some(event.identifier())),
+            Sum::Second(OrderEvent::Created(event)) => Ok(Some(event.restaurant_identifier.0)),
+            Sum::Second(OrderEvent::LineItemsUpdated(event)) => {
+                Ok(Some(event.restaurant_identifier.0))
+            }
+            Sum::Second(OrderEvent::Migrated(event)) => Ok(Some(event.restaurant_identifier.0)),
+            Sum::Second(OrderEvent::Cancelled(event)) => Ok(Some(event.restaurant_identifier.0)),
+            Sum::Second(OrderEvent::Prepared(OrderPrepared {
+                restaurant_identifier: Some(restaurant_identifier),
+                ..
+            })) => Ok(Some(restaurant_identifier.0)),
+            Sum::Second(OrderEvent::Prepared(event)) => Spi::connect(|client| {
+                client
+                    .select(
+                        "SELECT (SELECT id FROM restaurant_order_board WHERE store_id = fmodel_store_id() AND data->'orders' @> $1 LIMIT 1)",
+                        Some(1),
+                        Some(vec![(
+                            PgBuiltInOids::JSONBOID.oid(),
+                            JsonB(json!([{ "order_identifier": event.identifier }])).into_datum(),
+                        )]),
+                    )?
+                    .first()
+                    .get_one::<pgrx::Uuid>()
+            })
+            .map(|id| id.map(|id| Uuid::from_bytes(*id.as_bytes())))
+            .map_err(|err| {
+                ErrorMessage::spi("fetch the restaurant order board of the order", None, &err)
+            }),
+        }
+    }
+}
+
+/// Implementation of the view state repository for the restaurant order board `view` state.
+impl ViewStateRepository<Sum<RestaurantEvent, OrderEvent>, Option<RestaurantOrderBoardState>>
+    for RestaurantOrderBoardRepository
+{
+    /// Fetches current state, based on the event.
+    fn fetch_state(
+        &self,
+        event: &Sum<RestaurantEvent, OrderEvent>,
+    ) -> Result<Option<Option<RestaurantOrderBoardState>>, ErrorMessage> {
+        let Some(id) = self.board_id(event)? else {
+            return Ok(Some(None));
+        };
+        Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT (SELECT data FROM restaurant_order_board WHERE store_id = fmodel_store_id() AND id = $1)",
+                    Some(1),
+                    Some(vec![(
+                        PgBuiltInOids::UUIDOID.oid(),
+                        pgrx::Uuid::from_bytes(id.into_bytes()).into_datum(),
+                    )]),
+                )?
+                .first()
+                .get_one::<JsonB>()
+        })
+        .map_err(|err| ErrorMessage::spi("fetch the restaurant order board", None, &err))?
+        .map(to_payload::<RestaurantOrderBoardState>)
+        .transpose()
+        .map(Some)
+    }
+    /// Fetches the offset of the last event applied to the view row of the event.
+    fn fetch_applied_offset(
+        &self,
+        event: &Sum<RestaurantEvent, OrderEvent>,
+    ) -> Result<Option<i64>, ErrorMessage> {
+        let Some(id) = self.board_id(event)? else {
+            return Ok(None);
+        };
+        Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT (SELECT last_offset FROM restaurant_order_board WHERE store_id = fmodel_store_id() AND id = $1)",
+                    Some(1),
+                    Some(vec![(
+                        PgBuiltInOids::UUIDOID.oid(),
+                        pgrx::Uuid::from_bytes(id.into_bytes()).into_datum(),
+                    )]),
+                )?
+                .first()
+                .get_one::<i64>()
+        })
+        .map_err(|err| ErrorMessage::spi("fetch the restaurant order board version", None, &err))
+    }
+    /// Saves the new state. The orders of the restaurants that are not on the board (yet) are ignored.
+    fn save(
+        &self,
+        state: &Option<RestaurantOrderBoardState>,
+        position: &EventPosition,
+    ) -> Result<Option<RestaurantOrderBoardState>, ErrorMessage> {
+        let Some(board) = state else {
+            return Ok(None);
+        };
+        let data = serde_json::to_value(board).map_err(|err| ErrorMessage {
+            message: "Failed to serialize the restaurant order board: ".to_string()
+                + &err.to_string(),
+            context: None,
+        })?;
+        Spi::run_with_args(
+            "INSERT INTO restaurant_order_board (id, data, last_event_id, last_offset) VALUES ($1, $2, $3, $4) ON CONFLICT (store_id, id) DO UPDATE SET data = $2, last_event_id = $3, last_offset = $4",
+            Some(vec![
+                (
+                    PgBuiltInOids::UUIDOID.oid(),
+                    pgrx::Uuid::from_bytes(board.identifier.0.into_bytes()).into_datum(),
+                ),
+                (PgBuiltInOids::JSONBOID.oid(), JsonB(data).into_datum()),
+                (
+                    PgBuiltInOids::UUIDOID.oid(),
+                    pgrx::Uuid::from_bytes(position.event_id.into_bytes()).into_datum(),
+                ),
+                (PgBuiltInOids::INT8OID.oid(), position.offset.into_datum()),
+            ]),
+        )
+        .map_err(|err| ErrorMessage::spi("save the restaurant order board", None, &err))?;
+        Ok(state.clone())
+    }
+}
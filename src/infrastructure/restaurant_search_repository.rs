@@ -0,0 +1,131 @@
+use crate::domain::api::RestaurantId;
+use crate::domain::restaurant_view::RestaurantViewState;
+use crate::framework::infrastructure::errors::ErrorMessage;
+use pgrx::{IntoDatum, PgBuiltInOids, Spi, Uuid};
+
+/// A convenient type alias for the restaurant search result: the restaurant id, name, cuisine and the rank of the match.
+pub type RestaurantSearchResult = (Uuid, String, String, f32);
+
+/// RestaurantSearchRepository struct
+/// The `restaurants_search` projection: the searchable text (name, cuisine and the available menu item names) of the restaurants, with the full-text search (`tsvector`) GIN index.
+pub struct RestaurantSearchRepository {}
+
+/// RestaurantSearchRepository - struct implementation
+impl RestaurantSearchRepository {
+    /// Create a new RestaurantSearchRepository
+    pub fn new() -> Self {
+        RestaurantSearchRepository {}
+    }
+
+    /// Saves the searchable text of the restaurant (the search document is generated by the database).
+    /// The unavailable (86'd) menu items are not searchable: the restaurant is not found by the dishes it can not serve.
+    pub fn save(&self, state: &RestaurantViewState) -> Result<(), ErrorMessage> {
+        let cuisine = serde_json::to_value(&state.menu.cuisine)
+            .ok()
+            .and_then(|cuisine| cuisine.as_str().map(str::to_owned))
+            .unwrap_or_default();
+        let item_names = state
+            .menu
+            .items
+            .iter()
+            .filter(|item| !state.unavailable_items.contains(&item.id))
+            .map(|item| item.name.0.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+        Spi::run_with_args(
+            "INSERT INTO restaurants_search (id, name, cuisine, item_names) VALUES ($1, $2, $3, $4) ON CONFLICT (store_id, id) DO UPDATE SET name = $2, cuisine = $3, item_names = $4",
+            Some(vec![
+                (
+                    PgBuiltInOids::UUIDOID.oid(),
+                    state.identifier.0.to_string().into_datum(),
+                ),
+                (PgBuiltInOids::TEXTOID.oid(), state.name.0.as_str().into_datum()),
+                (PgBuiltInOids::TEXTOID.oid(), cuisine.into_datum()),
+                (PgBuiltInOids::TEXTOID.oid(), item_names.into_datum()),
+            ]),
+        )
+        .map_err(|err| ErrorMessage::spi(
+                "save the restaurant search document",
+                Some(state.identifier.0.to_string()),
+                &err,
+            ))
+    }
+
+    /// Deletes the searchable text of the restaurant: the retired (merged) restaurant is not found anymore.
+    pub fn delete(&self, identifier: &RestaurantId) -> Result<(), ErrorMessage> {
+        Spi::run_with_args(
+            "DELETE FROM restaurants_search WHERE store_id = fmodel_store_id() AND id = $1",
+            Some(vec![(
+                PgBuiltInOids::UUIDOID.oid(),
+                identifier.0.to_string().into_datum(),
+            )]),
+        )
+        .map_err(|err| {
+            ErrorMessage::spi(
+                "delete the restaurant search document",
+                Some(identifier.0.to_string()),
+                &err,
+            )
+        })
+    }
+
+    /// Searches the restaurants by the words of the query (prefixes of the words in the name, cuisine or the menu item names), ordered by the rank of the match.
+    pub fn search(
+        &self,
+        query: &str,
+        max_results: i64,
+    ) -> Result<Vec<RestaurantSearchResult>, ErrorMessage> {
+        let Some(ts_query) = to_prefix_ts_query(query) else {
+            return Ok(Vec::new());
+        };
+        // Read-only SPI: the restaurants are searched by the `STABLE` function(s)
+        Spi::connect(|client| {
+            let mut results = Vec::new();
+            let tup_table = client.select(
+                "SELECT id, name, cuisine, ts_rank(document, to_tsquery('simple', $1)) AS rank
+                 FROM restaurants_search
+                 WHERE store_id = fmodel_store_id() AND document @@ to_tsquery('simple', $1)
+                 ORDER BY rank DESC, name
+                 LIMIT $2",
+                None,
+                Some(vec![
+                    (PgBuiltInOids::TEXTOID.oid(), ts_query.into_datum()),
+                    (PgBuiltInOids::INT8OID.oid(), max_results.into_datum()),
+                ]),
+            )?;
+            for row in tup_table {
+                if let (Some(id), Some(name), Some(cuisine), Some(rank)) = (
+                    row["id"].value::<Uuid>()?,
+                    row["name"].value::<String>()?,
+                    row["cuisine"].value::<String>()?,
+                    row["rank"].value::<f32>()?,
+                ) {
+                    results.push((id, name, cuisine, rank));
+                }
+            }
+            Ok(results)
+        })
+        .map_err(|err: pgrx::spi::Error| ErrorMessage::spi("search the restaurants", None, &err))
+    }
+}
+
+/// The `tsquery` matching all the words of the query as prefixes (e.g. `sar:* & viet:*`), or `None` if the query has no words.
+/// Only the alphanumeric characters of the words are kept, so the query can not break the `tsquery` syntax.
+fn to_prefix_ts_query(query: &str) -> Option<String> {
+    let words = query
+        .split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(|c| c.is_alphanumeric())
+                .collect::<String>()
+                .to_lowercase()
+        })
+        .filter(|word| !word.is_empty())
+        .map(|word| word + ":*")
+        .collect::<Vec<_>>();
+    if words.is_empty() {
+        None
+    } else {
+        Some(words.join(" & "))
+    }
+}
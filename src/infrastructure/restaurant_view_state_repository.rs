@@ -2,9 +2,15 @@ use crate::domain::api::RestaurantEvent;
 use crate::domain::restaurant_view::RestaurantViewState;
 use crate::framework::domain::api::Identifier;
 use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::framework::infrastructure::event_repository::EventPosition;
 use crate::framework::infrastructure::to_payload;
-use crate::framework::infrastructure::view_state_repository::ViewStateRepository;
+use crate::framework::infrastructure::view_state_repository::{
+    patch_view_state, ViewStateRepository,
+};
+use crate::infrastructure::view_state_upcasters::upcast_view_state;
 use pgrx::{IntoDatum, JsonB, PgBuiltInOids, Spi};
+use serde_json::json;
+use uuid::Uuid;
 
 /// RestaurantViewStateRepository struct
 /// View state repository is always very specific to the domain. There is no default implementation in the `ViewStateRepository` trait.
@@ -16,6 +22,27 @@ impl RestaurantViewStateRepository {
     pub fn new() -> Self {
         RestaurantViewStateRepository {}
     }
+
+    /// Fetches the offset of the last event applied to the restaurant view/projection.
+    pub fn fetch_version(&self, id: &Uuid) -> Result<Option<i64>, ErrorMessage> {
+        // Read-only SPI: the version is queried by the `STABLE` function(s)
+        Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT (SELECT last_offset FROM restaurants WHERE store_id = fmodel_store_id() AND id = $1)",
+                    Some(1),
+                    Some(vec![(
+                        PgBuiltInOids::UUIDOID.oid(),
+                        id.to_string().into_datum(),
+                    )]),
+                )?
+                .first()
+                .get_one::<i64>()
+        })
+        .map_err(|err| {
+            ErrorMessage::spi("fetch the restaurant version", Some(id.to_string()), &err)
+        })
+    }
 }
 
 /// Implementation of the view state repository for the restaurant `view` state.
@@ -27,7 +54,7 @@ impl ViewStateRepository<RestaurantEvent, Option<RestaurantViewState>>
         &self,
         event: &RestaurantEvent,
     ) -> Result<Option<Option<RestaurantViewState>>, ErrorMessage> {
-        let query = "SELECT data FROM restaurants WHERE id = $1";
+        let query = "SELECT data FROM restaurants WHERE store_id = fmodel_store_id() AND id = $1";
         Spi::connect(|client| {
             let mut results = Vec::new();
             let tup_table = client
@@ -39,37 +66,60 @@ impl ViewStateRepository<RestaurantEvent, Option<RestaurantViewState>>
                         event.identifier().to_string().into_datum(),
                     )]),
                 )
-                .map_err(|err| ErrorMessage {
-                    message: "Failed to fetch the restaurant: ".to_string() + &err.to_string(),
+                .map_err(|err| {
+                    ErrorMessage::spi(
+                        "fetch the restaurant",
+                        Some(event.identifier().to_string()),
+                        &err,
+                    )
                 })?;
             for row in tup_table {
-                let data = row["data"].value::<JsonB>().map_err(|err| ErrorMessage {
-                    message: "Failed to fetch the restaurant/payload (map `data` to `JsonB`): ".to_string() + &err.to_string(),
-                })?.ok_or(ErrorMessage {
-                    message: "Failed to fetch restaurant data/payload (map `data` to `JsonB`): No data/payload found".to_string(),
-                })?;
+                let data = row["data"]
+                    .value::<JsonB>()
+                    .map_err(|err| {
+                        ErrorMessage::spi(
+                            "fetch the restaurant/payload (map `data` to `JsonB`)",
+                            None,
+                            &err,
+                        )
+                    })?
+                    .ok_or(ErrorMessage {
+                        message: "Failed to fetch restaurant data/payload (map `data` to `JsonB`): No data/payload found".to_string(),
+                        context: None,
+                    })?;
 
-                results.push(to_payload::<RestaurantViewState>(data)?);
+                results.push(to_payload::<RestaurantViewState>(upcast_view_state(
+                    "restaurants",
+                    data,
+                ))?);
             }
             Ok(Some(results.into_iter().last()))
         })
     }
+    /// Fetches the offset of the last event applied to the view row of the event.
+    fn fetch_applied_offset(&self, event: &RestaurantEvent) -> Result<Option<i64>, ErrorMessage> {
+        self.fetch_version(&event.identifier())
+    }
     /// Saves the new state.
+    /// The new row is owned by the owner of the event that created it (see `fmodel.owner`); the owner of the existing row is kept.
     fn save(
         &self,
         state: &Option<RestaurantViewState>,
+        position: &EventPosition,
     ) -> Result<Option<RestaurantViewState>, ErrorMessage> {
         let state = state.as_ref().ok_or(ErrorMessage {
             message: "Failed to save the restaurant: state is empty".to_string(),
+            context: None,
         })?;
         let data = serde_json::to_value(state).map_err(|err| ErrorMessage {
             message: "Failed to serialize the restaurant: ".to_string() + &err.to_string(),
+            context: None,
         })?;
 
         Spi::connect(|mut client| {
             client
                 .update(
-                    "INSERT INTO restaurants (id, data) VALUES ($1, $2) ON CONFLICT (id) DO UPDATE SET data = $2 RETURNING data",
+                    "INSERT INTO restaurants (id, data, last_event_id, last_offset, owner) VALUES ($1, $2, $3, $4, (SELECT owner FROM events WHERE event_id = $3 AND decider = 'Restaurant')) ON CONFLICT (store_id, id) DO UPDATE SET data = $2, last_event_id = $3, last_offset = $4 RETURNING data",
                     None,
                     Some(vec![
                         (
@@ -80,15 +130,61 @@ impl ViewStateRepository<RestaurantEvent, Option<RestaurantViewState>>
                             PgBuiltInOids::JSONBOID.oid(),
                             JsonB(data).into_datum(),
                         ),
+                        (
+                            PgBuiltInOids::UUIDOID.oid(),
+                            pgrx::Uuid::from_bytes(position.event_id.into_bytes()).into_datum(),
+                        ),
+                        (
+                            PgBuiltInOids::INT8OID.oid(),
+                            position.offset.into_datum(),
+                        ),
                     ]),
                 )?
                 .first()
                 .get_one::<JsonB>().map(|o|{ o.map( |it| to_payload(it).unwrap() )})
         })
             .map(Some)
-        .map_err(|err| ErrorMessage {
-            message: "Failed to save the restaurant: ".to_string() + &err.to_string(),
-        })
+        .map_err(|err| ErrorMessage::spi("save the restaurant", Some(state.identifier.to_string()), &err))
             .map(|state| state.unwrap())
     }
+    /// Saves the new state, by patching the stored document with the changes of the event (JSON merge patch).
+    fn save_patch(
+        &self,
+        event: &RestaurantEvent,
+        state: &Option<RestaurantViewState>,
+        position: &EventPosition,
+    ) -> Result<Option<RestaurantViewState>, ErrorMessage> {
+        let patch = match event {
+            RestaurantEvent::Created(_) => return self.save(state, position),
+            RestaurantEvent::MenuChanged(event) => json!({ "menu": event.menu }),
+            // The view state is not changed, only the position
+            RestaurantEvent::OrderPlaced(_)
+            | RestaurantEvent::OrderLineItemsModified(_)
+            | RestaurantEvent::PaymentCaptured(_)
+            | RestaurantEvent::PaymentRefunded(_)
+            | RestaurantEvent::CapacityChanged(_)
+            | RestaurantEvent::OrderClosed(_)
+            | RestaurantEvent::OrderCancelled(_)
+            | RestaurantEvent::OrderSplit(_)
+            | RestaurantEvent::OrdersReceived(_) => json!({}),
+            RestaurantEvent::OpeningHoursChanged(event) => {
+                json!({ "opening_hours": event.opening_hours })
+            }
+            RestaurantEvent::OrdersMigrated(_) => {
+                json!({ "merged_into": state.as_ref().map(|state| &state.merged_into) })
+            }
+            RestaurantEvent::MenuItemMarkedUnavailable(_)
+            | RestaurantEvent::MenuItemMarkedAvailable(_) => {
+                json!({ "unavailable_items": state.as_ref().map(|state| &state.unavailable_items) })
+            }
+            RestaurantEvent::PromotionApplied(_) | RestaurantEvent::PromotionWithdrawn(_) => {
+                json!({ "promotions": state.as_ref().map(|state| &state.promotions) })
+            }
+        };
+        if patch_view_state("restaurants", &event.identifier(), patch, position)? {
+            Ok(state.clone())
+        } else {
+            self.save(state, position)
+        }
+    }
 }
@@ -0,0 +1,95 @@
+use crate::framework::infrastructure::schema_check::ExpectedTable;
+
+/// The tables (and their columns) the repositories of the extension read and write. Checked before the first command is handled (see `SchemaCheckMiddleware`).
+pub const EXPECTED_TABLES: [ExpectedTable; 7] = [
+    ExpectedTable {
+        name: "events",
+        columns: &[
+            ("event", "text"),
+            ("event_id", "uuid"),
+            ("decider", "text"),
+            ("decider_id", "text"),
+            ("data", "jsonb"),
+            ("command_id", "uuid"),
+            ("previous_id", "uuid"),
+            ("sequence_number", "bigint"),
+            ("final", "boolean"),
+            ("correlation_id", "uuid"),
+            ("payload_hash", "bytea"),
+            ("created_at", "timestamp with time zone"),
+            ("offset", "bigint"),
+            ("transaction_id", "xid8"),
+            ("store_id", "text"),
+            ("owner", "text"),
+            ("tags", "text[]"),
+        ],
+    },
+    ExpectedTable {
+        name: "deciders",
+        columns: &[("decider", "text"), ("event", "text")],
+    },
+    ExpectedTable {
+        name: "projections",
+        columns: &[
+            ("projection", "text"),
+            ("mode", "text"),
+            ("checkpoint", "bigint"),
+            ("archive_final", "boolean"),
+        ],
+    },
+    ExpectedTable {
+        name: "restaurants",
+        columns: &[
+            ("id", "uuid"),
+            ("data", "jsonb"),
+            ("last_event_id", "uuid"),
+            ("last_offset", "bigint"),
+            ("owner", "text"),
+            ("store_id", "text"),
+        ],
+    },
+    ExpectedTable {
+        name: "orders",
+        columns: &[
+            ("id", "uuid"),
+            ("data", "jsonb"),
+            ("last_event_id", "uuid"),
+            ("last_offset", "bigint"),
+            ("owner", "text"),
+            ("store_id", "text"),
+        ],
+    },
+    ExpectedTable {
+        name: "fmodel_schema_version",
+        columns: &[("version", "integer"), ("description", "text")],
+    },
+    ExpectedTable {
+        name: "frozen_deciders",
+        columns: &[
+            ("decider", "text"),
+            ("frozen_at", "timestamp with time zone"),
+            ("frozen_by", "text"),
+        ],
+    },
+];
+
+/// The tables of the projections (the materialized views, and their search and archive tables), copied into the event store provisioned by `fmodel_init`.
+pub const PROJECTION_TABLES: [&str; 9] = [
+    "restaurants",
+    "restaurants_search",
+    "orders",
+    "orders_archive",
+    "restaurant_daily_orders",
+    "restaurant_order_board",
+    "payments",
+    "order_groups",
+    "kitchen_queue",
+];
+
+/// The indexes of the `events` the event streams are fetched (and the sequence numbers are guarded) by.
+pub const EXPECTED_INDEXES: [&str; 4] = [
+    "decider_index",
+    "store_sequence_number_index",
+    "correlation_index",
+    "events_transaction_index",
+];
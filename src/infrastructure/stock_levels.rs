@@ -0,0 +1,31 @@
+use crate::domain::api::{InventoryId, OrderId, RestaurantId, StockItem};
+use crate::domain::event_to_inventory_event;
+use crate::domain::inventory_decider::inventory_decider;
+use crate::domain::stock_levels::StockLevels;
+use crate::framework::infrastructure::event_repository::EventOrchestratingRepository;
+use crate::infrastructure::order_restaurant_event_repository::OrderAndRestaurantEventRepository;
+use pgrx::error;
+
+/// The stock levels of the database: the state of the inventory of the restaurant, folded from its event stream (the `Inventory` decider).
+/// The failed lookup aborts the command handling with the SPI error: it is not reported as the untracked stock.
+pub struct PostgresStockLevels;
+
+impl StockLevels for PostgresStockLevels {
+    fn available(
+        &self,
+        restaurant_identifier: &RestaurantId,
+        order_identifier: &OrderId,
+    ) -> Option<Vec<StockItem>> {
+        let events = OrderAndRestaurantEventRepository::new()
+            .fetch_decider_stream_events("Inventory", &InventoryId::of(restaurant_identifier).0)
+            .unwrap_or_else(|err| error!("{}", err));
+        let decider = inventory_decider();
+        events
+            .iter()
+            .filter_map(|(event, _)| event_to_inventory_event(event))
+            .fold((decider.initial_state)(), |state, event| {
+                (decider.evolve)(&state, &event)
+            })
+            .map(|inventory| inventory.available(order_identifier))
+    }
+}
@@ -0,0 +1,63 @@
+use crate::framework::infrastructure::errors::ErrorMessage;
+use crate::framework::infrastructure::view_state_repository::ViewStateUpcaster;
+use pgrx::JsonB;
+use serde_json::{json, Value};
+
+/// The view state upcasters, by the view (projection) table.
+/// Register the upcaster when the view state (`RestaurantViewState`, `OrderViewState`) evolves, so the stored documents are read in the current schema, and can be rewritten eagerly with `migrate_view_rows`.
+pub const VIEW_STATE_UPCASTERS: [(&str, ViewStateUpcaster); 2] = [
+    ("restaurants", upcast_restaurant_view_state),
+    ("orders", upcast_order_view_state),
+];
+
+/// Finds the view state upcaster of the view table.
+pub fn view_state_upcaster(table: &str) -> Result<ViewStateUpcaster, ErrorMessage> {
+    VIEW_STATE_UPCASTERS
+        .iter()
+        .find(|(name, _)| *name == table)
+        .map(|(_, upcaster)| *upcaster)
+        .ok_or(ErrorMessage {
+            message: "No view state upcaster registered for the view: ".to_string() + table,
+            context: None,
+        })
+}
+
+/// Upcasts the stored document of the view table to the current schema of the view state. The documents of the views without the upcaster are returned unchanged.
+pub fn upcast_view_state(table: &str, data: JsonB) -> JsonB {
+    match view_state_upcaster(table) {
+        Ok(upcaster) => JsonB(upcaster(data.0)),
+        Err(_) => data,
+    }
+}
+
+/// The restaurant documents stored before the cuisine was introduced: the menu is of the `Other` cuisine.
+fn upcast_restaurant_view_state(mut data: Value) -> Value {
+    if let Some(menu) = data.get_mut("menu").and_then(Value::as_object_mut) {
+        menu.entry("cuisine").or_insert(json!("Other"));
+    }
+    data
+}
+
+/// The order documents stored before the total was introduced: the total is derived from the (frozen) prices of the line items, or `null` if any of the line items is not priced.
+fn upcast_order_view_state(mut data: Value) -> Value {
+    let Some(order) = data.as_object_mut() else {
+        return data;
+    };
+    if !order.contains_key("total") {
+        let total = order
+            .get("line_items")
+            .and_then(Value::as_array)
+            .and_then(|line_items| {
+                line_items
+                    .iter()
+                    .map(|line_item| {
+                        let price = line_item.get("price").and_then(Value::as_u64)?;
+                        let quantity = line_item.get("quantity").and_then(Value::as_u64)?;
+                        Some(price * quantity)
+                    })
+                    .sum::<Option<u64>>()
+            });
+        order.insert("total".to_string(), json!(total));
+    }
+    data
+}
@@ -1,19 +1,64 @@
-use crate::application::order_materialized_view::OrderMeterializedView;
-use crate::application::order_restaurant_aggregate::OrderAndRestaurantAggregate;
-use crate::application::restaurant_materialized_view::RestaurantMeterializedView;
-use crate::domain::order_view::order_view;
-use crate::domain::restaurant_view::restaurant_view;
-use crate::domain::{
-    event_to_order_event, event_to_restaurant_event, order_restaurant_decider,
-    order_restaurant_saga, Command, Event,
+use crate::application::order_restaurant_aggregate::cached_order_restaurant_aggregate;
+use crate::application::order_restaurant_fixtures::fixture_commands;
+use crate::application::order_restaurant_middleware::preprocess;
+use crate::application::order_restaurant_projector::{
+    backfill_projection, project_async_projections, projection_handler, rebuild_projections,
+    OrderAndRestaurantProjector, ProjectionHandler, KITCHEN_QUEUE_PROJECTION,
+    ORDER_GROUPS_PROJECTION, ORDER_PROJECTION, PAYMENTS_PROJECTION, PROJECTIONS,
+    RESTAURANT_DAILY_ORDERS_PROJECTION, RESTAURANT_ORDER_BOARD_PROJECTION, RESTAURANT_PROJECTION,
 };
+use crate::application::order_restaurant_validators::order_restaurant_validators;
+use crate::domain::api::{
+    CancelRestaurantOrder, CapturePayment, ChangeRestaurantCapacity, ChangeRestaurantMenu,
+    ChangeRestaurantOpeningHours, Coordinate, CourierId, CreateRestaurant, CustomerId, GiftCardId,
+    InventoryId, IssueGiftCard, MarkMenuItemAvailable, MarkMenuItemUnavailable,
+    MarkOrderAsPrepared, MenuItemId, MigrateRestaurantOrders, ModifyOrderLineItems, Money, OrderId,
+    PlaceOrder, ReceiveRestaurantOrders, RedeemGiftCard, RefundGiftCard, RefundPayment,
+    RestaurantId, RestaurantName, RestockInventory, UpdateCourierLocation,
+};
+use crate::domain::{order_restaurant_decider, Command, Event, COMMAND_TYPES};
+use crate::framework::application::materialized_view::OUT_OF_ORDER_EVENT_ERROR;
+use crate::framework::application::workflow::{fetch_workflow, handle_workflow_command};
+use crate::framework::domain::api::{DeciderType, Identifier};
+use crate::framework::domain::workflow::WorkflowCommand;
+use crate::framework::infrastructure::clock::PostgresClock;
+use crate::framework::infrastructure::correlation::{with_correlation, within_correlation};
 use crate::framework::infrastructure::errors::{ErrorMessage, TriggerError};
-use crate::framework::infrastructure::to_payload;
+use crate::framework::infrastructure::event_repository::EventOrchestratingRepository;
+use crate::framework::infrastructure::event_repository::EventPosition;
+use crate::framework::infrastructure::guc::{
+    LONG_STREAM_THRESHOLD, PROJECTOR_BATCH_SIZE, PROJECTOR_DATABASE, PROJECTOR_INTERVAL_MS,
+    SKIP_UNKNOWN_EVENTS,
+};
+use crate::framework::infrastructure::projection_repository::{
+    ProjectionMode, ProjectionRepository,
+};
+use crate::framework::infrastructure::saga_commands::{
+    claim_failed_saga_command, fetch_failed_saga_command_ids, update_saga_command_status,
+    SagaCommandStatus,
+};
+use crate::framework::infrastructure::store::{in_store, with_store};
+use crate::framework::infrastructure::transition_table::fetch_inserted_events;
+use crate::framework::infrastructure::{to_command, to_event, to_payload, EventPayload};
+use crate::infrastructure::command_batch_repository::CommandBatchRepository;
+use crate::infrastructure::courier_location_repository::CourierLocationRepository;
+use crate::infrastructure::exchange_rates::PostgresExchangeRates;
+use crate::infrastructure::kitchen_queue_repository::KitchenQueueRepository;
+use crate::infrastructure::notification_outbox_repository::NotificationOutboxRepository;
 use crate::infrastructure::order_restaurant_event_repository::OrderAndRestaurantEventRepository;
+use crate::infrastructure::order_restaurant_projection_repository::OrderAndRestaurantProjectionRepository;
 use crate::infrastructure::order_view_state_repository::OrderViewStateRepository;
+use crate::infrastructure::restaurant_search_repository::RestaurantSearchRepository;
 use crate::infrastructure::restaurant_view_state_repository::RestaurantViewStateRepository;
+use crate::infrastructure::stock_levels::PostgresStockLevels;
+use crate::infrastructure::view_state_upcasters::view_state_upcaster;
+use pgrx::bgworkers::{BackgroundWorker, BackgroundWorkerBuilder, SignalWakeFlags};
+use pgrx::pg_sys::panic::{CaughtError, ErrorReport};
 use pgrx::prelude::*;
-use pgrx::JsonB;
+use pgrx::{JsonB, Uuid};
+use std::collections::HashMap;
+use std::panic::AssertUnwindSafe;
+use std::time::Duration;
 
 mod application;
 mod domain;
@@ -22,6 +67,67 @@ mod infrastructure;
 
 pg_module_magic!();
 
+/// Extension initialization: registers the configuration parameters, and the projector background worker and the shared memory of the rate limiter, the state cache, the command statistics and the projector wakeup (if the extension is loaded via `shared_preload_libraries`).
+#[pg_guard]
+pub extern "C" fn _PG_init() {
+    framework::infrastructure::guc::init();
+    if unsafe { pg_sys::process_shared_preload_libraries_in_progress } {
+        framework::infrastructure::rate_limiter::init();
+        framework::infrastructure::state_cache::init();
+        framework::infrastructure::command_stats::init();
+        framework::infrastructure::projector_wakeup::init();
+        BackgroundWorkerBuilder::new("fmodel projector")
+            .set_function("projector_main")
+            .set_library("fmodel_rust_postgres")
+            .enable_spi_access()
+            .load();
+    }
+}
+
+/// Projector background worker. It applies the events to all the projections in the `async` mode.
+/// The worker is woken when the transaction appending the events (via the `handle*` functions) commits, so the `async` projections follow the writes in near-real-time.
+/// It catches up from the checkpoints (the offsets of the projections) on startup, and polls every `fmodel.projector_interval_ms` for the events appended otherwise (e.g. imported).
+#[pg_guard]
+#[no_mangle]
+pub extern "C" fn projector_main(_arg: pg_sys::Datum) {
+    BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+    let database = PROJECTOR_DATABASE
+        .get()
+        .and_then(|db| db.to_str().ok().map(str::to_owned));
+    BackgroundWorker::connect_worker_to_spi(database.as_deref(), None);
+    framework::infrastructure::projector_wakeup::register_projector();
+    log!("fmodel projector started");
+
+    project_async_projections_in_worker();
+    while BackgroundWorker::wait_latch(Some(Duration::from_millis(
+        PROJECTOR_INTERVAL_MS.get() as u64
+    ))) {
+        if BackgroundWorker::sighup_received() {
+            unsafe { pg_sys::ProcessConfigFile(pg_sys::GucContext::PGC_SIGHUP) };
+        }
+        project_async_projections_in_worker();
+    }
+    framework::infrastructure::projector_wakeup::unregister_projector();
+    log!("fmodel projector stopped");
+}
+
+/// Applies the new events to the `async` projections, in the transaction of the projector background worker.
+fn project_async_projections_in_worker() {
+    BackgroundWorker::transaction(|| {
+        // The extension might not be created (yet) in the database
+        if Spi::get_one::<bool>("SELECT to_regclass('projections') IS NOT NULL")
+            .ok()
+            .flatten()
+            != Some(true)
+        {
+            return;
+        }
+        if let Err(err) = project_async_projections(PROJECTOR_BATCH_SIZE.get() as i64) {
+            warning!("fmodel projector failed: {}", err);
+        }
+    });
+}
+
 // Declare SQL (from a file) to be included in generated extension script.
 // Defines the `event_sourcing` table(s) and indexes.
 extension_sql_file!(
@@ -32,17 +138,388 @@ extension_sql_file!(
 
 /// Command handler for the whole domain / orders and restaurants combined.
 /// It handles a single command and returns a list of events that were generated and persisted.
+/// The command is run through the middleware chain (authorization, idempotency, logging) first.
+/// All the events (including the saga-derived ones) are stamped with the same correlation id.
+/// The command is handled in the logical event `store`, if given; otherwise in the store of the session (`fmodel.store_id`).
 #[pg_extern]
-fn handle(command: Command) -> Result<Vec<Event>, ErrorMessage> {
-    let repository = OrderAndRestaurantEventRepository::new();
-    let aggregate = OrderAndRestaurantAggregate::new(
-        repository,
-        order_restaurant_decider(),
-        order_restaurant_saga(),
-    );
-    aggregate
-        .handle(&command)
-        .map(|res| res.into_iter().map(|(e, _)| e.clone()).collect())
+fn handle(
+    command: Command,
+    store: default!(Option<&str>, "NULL"),
+) -> Result<Vec<Event>, ErrorMessage> {
+    preprocess(std::slice::from_ref(&command))?;
+    let aggregate = cached_order_restaurant_aggregate();
+    with_store(store, || with_correlation(|| aggregate.handle(&command)))
+        .map(|(_, res)| res.into_iter().map(|(e, _)| e.clone()).collect())
+}
+
+// Command handler retrying the serialization failures (SQLSTATE `40001`, e.g. under `default_transaction_isolation = serializable`), up to `fmodel.serialization_retries` times.
+// The transaction of the failed attempt is rolled back, and the command is handled again in a new transaction: the retry within the same transaction would read the same snapshot, and fail again.
+// Therefore it is a procedure, committing the handled command, and it must be called outside of the transaction block (`CALL handle_with_retry(...)`); the handled events are returned in `events`.
+// The serialization failure detected at the commit is not retried (the commit can not be run in the block catching it): it is raised to the client, which retries the whole `CALL`.
+extension_sql!(
+    r#"
+    CREATE OR REPLACE PROCEDURE handle_with_retry(command Command, INOUT events Event[] DEFAULT NULL, store TEXT DEFAULT NULL)
+        LANGUAGE plpgsql AS
+    '
+        DECLARE
+            -- The setting is registered when the library is loaded: not yet, if the procedure is the first call of the session (without `shared_preload_libraries`)
+            retries INT := COALESCE(current_setting(''fmodel.serialization_retries'', TRUE), ''3'')::INT;
+            attempt INT := 0;
+            handled BOOLEAN;
+        BEGIN
+            LOOP
+                handled := TRUE;
+                BEGIN
+                    events := handle(command, store);
+                EXCEPTION WHEN serialization_failure THEN
+                    IF attempt >= retries THEN
+                        RAISE;
+                    END IF;
+                    handled := FALSE;
+                END;
+                IF handled THEN
+                    COMMIT;
+                    RETURN;
+                END IF;
+                ROLLBACK;
+                attempt := attempt + 1;
+                -- Back off (with jitter), so the conflicting transactions do not collide again
+                PERFORM pg_sleep(random() * 0.01 * attempt);
+            END LOOP;
+        END;
+    ';
+    "#,
+    name = "handle_with_retry",
+    requires = [handle]
+);
+
+/// Command handler for the high-throughput ingestion (lightweight mode).
+/// It works like `handle`, but returns only the ids of the events that were generated and persisted: the events are not serialized back to the client.
+#[pg_extern]
+fn handle_ids(
+    command: Command,
+    store: default!(Option<&str>, "NULL"),
+) -> Result<Vec<Uuid>, ErrorMessage> {
+    preprocess(std::slice::from_ref(&command))?;
+    let aggregate = cached_order_restaurant_aggregate();
+    with_store(store, || {
+        with_correlation(|| aggregate.handle_ids(&command))
+    })
+    .map(|(_, ids)| {
+        ids.into_iter()
+            .map(|id| Uuid::from_bytes(id.into_bytes()))
+            .collect()
+    })
+}
+
+/// Command handler for the command as JSONB (e.g. sent by the client that is versioned independently of the extension).
+/// The command of the type unknown to this version of the extension is rejected with the supported command types and the extension version, easing the mixed-version rollouts.
+#[pg_extern]
+fn handle_json(
+    command: JsonB,
+    store: default!(Option<&str>, "NULL"),
+) -> Result<Vec<Event>, ErrorMessage> {
+    handle(to_command(command, &COMMAND_TYPES)?, store)
+}
+
+// SQL-friendly command handlers: thin wrappers constructing the typed command, and delegating to `handle`.
+// The nested structures (menu, line items) are passed as JSONB, in the same format as in the JSON command.
+
+/// Creates the restaurant with the menu (JSONB: `{"menu_id": ..., "items": [{"id": ..., "name": ..., "price": ...}], "cuisine": ...}`).
+#[pg_extern]
+fn create_restaurant(id: Uuid, name: &str, menu: JsonB) -> Result<Vec<Event>, ErrorMessage> {
+    handle(
+        Command::CreateRestaurant(CreateRestaurant {
+            identifier: RestaurantId(uuid::Uuid::from_bytes(*id.as_bytes())),
+            name: RestaurantName(name.to_string()),
+            menu: to_payload(menu)?,
+        }),
+        None,
+    )
+}
+
+/// Changes the menu of the restaurant (JSONB, see `create_restaurant`).
+#[pg_extern]
+fn change_restaurant_menu(id: Uuid, menu: JsonB) -> Result<Vec<Event>, ErrorMessage> {
+    handle(
+        Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
+            identifier: RestaurantId(uuid::Uuid::from_bytes(*id.as_bytes())),
+            menu: to_payload(menu)?,
+        }),
+        None,
+    )
+}
+
+/// Places the order at the restaurant (JSONB line items: `[{"id": ..., "quantity": ..., "menu_item_id": ..., "name": ...}]`), on behalf of the customer (notified of the status of the order), if any.
+/// The order itself is created by the saga.
+#[pg_extern]
+fn place_order(
+    restaurant_id: Uuid,
+    order_id: Uuid,
+    line_items: JsonB,
+    customer_id: default!(Option<Uuid>, "NULL"),
+) -> Result<Vec<Event>, ErrorMessage> {
+    handle(
+        Command::PlaceOrder(PlaceOrder {
+            identifier: RestaurantId(uuid::Uuid::from_bytes(*restaurant_id.as_bytes())),
+            order_identifier: OrderId(uuid::Uuid::from_bytes(*order_id.as_bytes())),
+            line_items: to_payload(line_items)?,
+            group_identifier: None,
+            customer_identifier: customer_id
+                .map(|customer_id| CustomerId(uuid::Uuid::from_bytes(*customer_id.as_bytes()))),
+        }),
+        None,
+    )
+}
+
+/// Modifies the line items of the order placed at the restaurant (JSONB line items, as in `place_order`), before the order is prepared.
+/// The line items are validated against (and priced from) the current menu of the restaurant; the order itself is updated by the saga.
+#[pg_extern]
+fn modify_order(
+    restaurant_id: Uuid,
+    order_id: Uuid,
+    line_items: JsonB,
+) -> Result<Vec<Event>, ErrorMessage> {
+    handle(
+        Command::ModifyOrderLineItems(ModifyOrderLineItems {
+            identifier: RestaurantId(uuid::Uuid::from_bytes(*restaurant_id.as_bytes())),
+            order_identifier: OrderId(uuid::Uuid::from_bytes(*order_id.as_bytes())),
+            line_items: to_payload(line_items)?,
+        }),
+        None,
+    )
+}
+
+/// Captures a (split) payment of the order placed at the restaurant. The cumulative captured amount can not exceed the total of the order.
+#[pg_extern]
+fn capture_payment(
+    restaurant_id: Uuid,
+    order_id: Uuid,
+    amount: i64,
+) -> Result<Vec<Event>, ErrorMessage> {
+    handle(
+        Command::CapturePayment(CapturePayment {
+            identifier: RestaurantId(uuid::Uuid::from_bytes(*restaurant_id.as_bytes())),
+            order_identifier: OrderId(uuid::Uuid::from_bytes(*order_id.as_bytes())),
+            amount: Money(u64::try_from(amount).map_err(|_| ErrorMessage {
+                message: "Invalid amount: ".to_string() + &amount.to_string(),
+                context: None,
+            })?),
+        }),
+        None,
+    )
+}
+
+/// Refunds (partially) the payments of the order placed at the restaurant. The cumulative refunded amount can not exceed the captured amount.
+#[pg_extern]
+fn refund_payment(
+    restaurant_id: Uuid,
+    order_id: Uuid,
+    amount: i64,
+) -> Result<Vec<Event>, ErrorMessage> {
+    handle(
+        Command::RefundPayment(RefundPayment {
+            identifier: RestaurantId(uuid::Uuid::from_bytes(*restaurant_id.as_bytes())),
+            order_identifier: OrderId(uuid::Uuid::from_bytes(*order_id.as_bytes())),
+            amount: Money(u64::try_from(amount).map_err(|_| ErrorMessage {
+                message: "Invalid amount: ".to_string() + &amount.to_string(),
+                context: None,
+            })?),
+        }),
+        None,
+    )
+}
+
+/// Changes the maximum number of the concurrent (open, not yet prepared) orders of the restaurant. `NULL` removes the limit.
+#[pg_extern]
+fn change_restaurant_capacity(
+    restaurant_id: Uuid,
+    max_concurrent_orders: Option<i32>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    handle(
+        Command::ChangeRestaurantCapacity(ChangeRestaurantCapacity {
+            identifier: RestaurantId(uuid::Uuid::from_bytes(*restaurant_id.as_bytes())),
+            max_concurrent_orders: max_concurrent_orders
+                .map(|max| {
+                    u32::try_from(max).map_err(|_| ErrorMessage {
+                        message: "Invalid capacity: ".to_string() + &max.to_string(),
+                        context: None,
+                    })
+                })
+                .transpose()?,
+        }),
+        None,
+    )
+}
+
+/// Marks the menu item of the restaurant as unavailable (86'd): it can not be ordered until it is marked as available again.
+#[pg_extern]
+fn mark_menu_item_unavailable(
+    restaurant_id: Uuid,
+    menu_item_id: Uuid,
+) -> Result<Vec<Event>, ErrorMessage> {
+    handle(
+        Command::MarkMenuItemUnavailable(MarkMenuItemUnavailable {
+            identifier: RestaurantId(uuid::Uuid::from_bytes(*restaurant_id.as_bytes())),
+            menu_item_id: MenuItemId(uuid::Uuid::from_bytes(*menu_item_id.as_bytes())),
+        }),
+        None,
+    )
+}
+
+/// Marks the (unavailable) menu item of the restaurant as available again.
+#[pg_extern]
+fn mark_menu_item_available(
+    restaurant_id: Uuid,
+    menu_item_id: Uuid,
+) -> Result<Vec<Event>, ErrorMessage> {
+    handle(
+        Command::MarkMenuItemAvailable(MarkMenuItemAvailable {
+            identifier: RestaurantId(uuid::Uuid::from_bytes(*restaurant_id.as_bytes())),
+            menu_item_id: MenuItemId(uuid::Uuid::from_bytes(*menu_item_id.as_bytes())),
+        }),
+        None,
+    )
+}
+
+/// Changes the opening hours of the restaurant (JSONB: `{"time_zone": "Europe/Belgrade", "periods": [{"day_of_week": 1, "opens_at": 540, "closes_at": 1020}]}`, the ISO days of the week and the minutes since midnight). `NULL` for always open.
+#[pg_extern]
+fn change_restaurant_opening_hours(
+    restaurant_id: Uuid,
+    opening_hours: default!(Option<JsonB>, "NULL"),
+) -> Result<Vec<Event>, ErrorMessage> {
+    handle(
+        Command::ChangeRestaurantOpeningHours(ChangeRestaurantOpeningHours {
+            identifier: RestaurantId(uuid::Uuid::from_bytes(*restaurant_id.as_bytes())),
+            opening_hours: opening_hours.map(to_payload).transpose()?,
+        }),
+        None,
+    )
+}
+
+/// Cancels the open order of the restaurant (or rejects it, if `rejected`). The order is cancelled, and its reserved stock released, by the sagas.
+#[pg_extern]
+fn cancel_restaurant_order(
+    restaurant_id: Uuid,
+    order_id: Uuid,
+    rejected: default!(bool, false),
+) -> Result<Vec<Event>, ErrorMessage> {
+    handle(
+        Command::CancelRestaurantOrder(CancelRestaurantOrder {
+            identifier: RestaurantId(uuid::Uuid::from_bytes(*restaurant_id.as_bytes())),
+            order_identifier: OrderId(uuid::Uuid::from_bytes(*order_id.as_bytes())),
+            rejected,
+        }),
+        None,
+    )
+}
+
+/// Restocks the inventory of the restaurant (JSONB items: `[{"menu_item_id": ..., "quantity": ...}]`). The inventory is addressed by the restaurant.
+#[pg_extern]
+fn restock_inventory(restaurant_id: Uuid, items: JsonB) -> Result<Vec<Event>, ErrorMessage> {
+    let restaurant_identifier = RestaurantId(uuid::Uuid::from_bytes(*restaurant_id.as_bytes()));
+    handle(
+        Command::RestockInventory(RestockInventory {
+            identifier: InventoryId::of(&restaurant_identifier),
+            restaurant_identifier,
+            items: to_payload(items)?,
+        }),
+        None,
+    )
+}
+
+/// Issues the gift card (store credit) with the amount.
+#[pg_extern]
+fn issue_gift_card(gift_card_id: Uuid, amount: i64) -> Result<Vec<Event>, ErrorMessage> {
+    handle(
+        Command::IssueGiftCard(IssueGiftCard {
+            identifier: GiftCardId(uuid::Uuid::from_bytes(*gift_card_id.as_bytes())),
+            amount: Money(u64::try_from(amount).map_err(|_| ErrorMessage {
+                message: "Invalid amount: ".to_string() + &amount.to_string(),
+                context: None,
+            })?),
+        }),
+        None,
+    )
+}
+
+/// Redeems the gift card to pay the order placed at the restaurant: the amount can not exceed the balance, and is captured as the payment of the order.
+#[pg_extern]
+fn redeem_gift_card(
+    gift_card_id: Uuid,
+    restaurant_id: Uuid,
+    order_id: Uuid,
+    amount: i64,
+) -> Result<Vec<Event>, ErrorMessage> {
+    handle(
+        Command::RedeemGiftCard(RedeemGiftCard {
+            identifier: GiftCardId(uuid::Uuid::from_bytes(*gift_card_id.as_bytes())),
+            restaurant_identifier: RestaurantId(uuid::Uuid::from_bytes(*restaurant_id.as_bytes())),
+            order_identifier: OrderId(uuid::Uuid::from_bytes(*order_id.as_bytes())),
+            amount: Money(u64::try_from(amount).map_err(|_| ErrorMessage {
+                message: "Invalid amount: ".to_string() + &amount.to_string(),
+                context: None,
+            })?),
+        }),
+        None,
+    )
+}
+
+/// Refunds the payment of the order back to the gift card: the amount can not exceed the amount redeemed by the order, and is refunded from the payments of the order.
+#[pg_extern]
+fn refund_gift_card(
+    gift_card_id: Uuid,
+    restaurant_id: Uuid,
+    order_id: Uuid,
+    amount: i64,
+) -> Result<Vec<Event>, ErrorMessage> {
+    handle(
+        Command::RefundGiftCard(RefundGiftCard {
+            identifier: GiftCardId(uuid::Uuid::from_bytes(*gift_card_id.as_bytes())),
+            restaurant_identifier: RestaurantId(uuid::Uuid::from_bytes(*restaurant_id.as_bytes())),
+            order_identifier: OrderId(uuid::Uuid::from_bytes(*order_id.as_bytes())),
+            amount: Money(u64::try_from(amount).map_err(|_| ErrorMessage {
+                message: "Invalid amount: ".to_string() + &amount.to_string(),
+                context: None,
+            })?),
+        }),
+        None,
+    )
+}
+
+/// Updates the location (in degrees) of the courier.
+/// The location is appended to the event log, or not, by the policy (`fmodel.courier_location_policy`): only the appended `CourierLocationUpdated` events are returned.
+#[pg_extern]
+fn update_courier_location(
+    courier_id: Uuid,
+    latitude: f64,
+    longitude: f64,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let coordinate = |degrees: f64| {
+        Coordinate::of_degrees(degrees).ok_or_else(|| ErrorMessage {
+            message: "Invalid coordinate: ".to_string() + &degrees.to_string(),
+            context: None,
+        })
+    };
+    handle(
+        Command::UpdateCourierLocation(UpdateCourierLocation {
+            identifier: CourierId(uuid::Uuid::from_bytes(*courier_id.as_bytes())),
+            latitude: coordinate(latitude)?,
+            longitude: coordinate(longitude)?,
+        }),
+        None,
+    )
+}
+
+/// Marks the order as prepared.
+#[pg_extern]
+fn mark_order_prepared(id: Uuid) -> Result<Vec<Event>, ErrorMessage> {
+    handle(
+        Command::MarkOrderAsPrepared(MarkOrderAsPrepared {
+            identifier: OrderId(uuid::Uuid::from_bytes(*id.as_bytes())),
+        }),
+        None,
+    )
 }
 
 /// Compound command handler for the domain / orders and restaurants combined
@@ -51,410 +528,6284 @@ fn handle(command: Command) -> Result<Vec<Event>, ErrorMessage> {
 /// If any of the commands fail, the transaction is rolled back, and no events are persisted.
 /// This is useful when you need to ensure that all commands are executed or none.
 #[pg_extern]
-fn handle_all(commands: Vec<Command>) -> Result<Vec<Event>, ErrorMessage> {
-    let repository = OrderAndRestaurantEventRepository::new();
-    let aggregate = OrderAndRestaurantAggregate::new(
-        repository,
-        order_restaurant_decider(),
-        order_restaurant_saga(),
-    );
-    aggregate
-        .handle_all(&commands)
-        .map(|res| res.into_iter().map(|(e, _)| e.clone()).collect())
+fn handle_all(
+    commands: Vec<Command>,
+    store: default!(Option<&str>, "NULL"),
+) -> Result<Vec<Event>, ErrorMessage> {
+    preprocess(&commands)?;
+    let aggregate = cached_order_restaurant_aggregate();
+    with_store(store, || {
+        with_correlation(|| aggregate.handle_all(&commands))
+    })
+    .map(|(_, res)| res.into_iter().map(|(e, _)| e.clone()).collect())
 }
 
-/// Event handler for Restaurant events / Trigger function that handles restaurant related events and updates the materialized view/table.
-#[pg_trigger]
-fn handle_restaurant_events<'a>(
-    trigger: &'a PgTrigger<'a>,
-) -> Result<Option<PgHeapTuple<'a, impl WhoAllocated>>, TriggerError> {
-    let new = trigger
-        .new()
-        .ok_or(TriggerError::NullTriggerTuple)?
-        .into_owned();
-    let event: JsonB = new
-        .get_by_name::<JsonB>("data")?
-        .ok_or(TriggerError::NullTriggerTuple)?;
-    let materialized_view =
-        RestaurantMeterializedView::new(RestaurantViewStateRepository::new(), restaurant_view());
-
-    match event_to_restaurant_event(
-        &to_payload::<Event>(event)
-            .map_err(|err| TriggerError::EventHandlingError(err.to_string()))?,
-    ) {
-        // If the event is not a Restaurant event, we do nothing
-        None => return Ok(Some(new)),
-        // If the event is a Restaurant event, we handle it
-        Some(e) => {
-            materialized_view
-                .handle(&e)
-                .map_err(|err| TriggerError::EventHandlingError(err.message))?;
-        }
-    }
-    Ok(Some(new))
+/// Compound command handler for the high-throughput ingestion (lightweight mode).
+/// It works like `handle_all`, but returns only the ids of the events that were generated and persisted.
+#[pg_extern]
+fn handle_all_ids(
+    commands: Vec<Command>,
+    store: default!(Option<&str>, "NULL"),
+) -> Result<Vec<Uuid>, ErrorMessage> {
+    preprocess(&commands)?;
+    let aggregate = cached_order_restaurant_aggregate();
+    with_store(store, || {
+        with_correlation(|| aggregate.handle_all_ids(&commands))
+    })
+    .map(|(_, ids)| {
+        ids.into_iter()
+            .map(|id| Uuid::from_bytes(id.into_bytes()))
+            .collect()
+    })
 }
 
-// Materialized view / Table for the Restaurant query side model
-// This table is updated by the trigger function / event handler `handle_restaurant_events`
-extension_sql!(
-    r#"
-    CREATE TABLE IF NOT EXISTS restaurants (
-                                           id UUID PRIMARY KEY,
-                                           data JSONB
-    );
+/// Loads the embedded fixture set (`restaurant_with_menu`, `restaurant_with_orders` or `finalized_streams`), for the integration tests and the demos.
+/// The commands of the fixture are handled in a single transaction (see `handle_all`), so the events and the projections are seeded consistently.
+/// Returns the number of the appended events.
+#[pg_extern]
+fn load_fixture(name: &str) -> Result<i64, ErrorMessage> {
+    handle_all(fixture_commands(name)?, None).map(|events| events.len() as i64)
+}
 
-    CREATE TRIGGER restaurant_event_handler_trigger AFTER INSERT ON events FOR EACH ROW EXECUTE PROCEDURE handle_restaurant_events();
-    "#,
-    name = "restaurant_event_handler_trigger",
-    requires = [handle_restaurant_events]
-);
+/// Atomic command handler for the commands addressed to different deciders (e.g. create restaurant A and restaurant B).
+/// It works like `handle_all`, but the events are persisted only if every event stream (of the commands) is still at the version its events were fetched at.
+/// If any of the optimistic checks fails, the transaction is rolled back, and no events are persisted.
+/// Returns the persisted events, grouped by the decider (stream) identifier.
+#[pg_extern]
+fn handle_all_atomically(
+    commands: Vec<Command>,
+    store: default!(Option<&str>, "NULL"),
+) -> Result<
+    TableIterator<'static, (name!(decider_id, Uuid), name!(events, Vec<Event>))>,
+    ErrorMessage,
+> {
+    preprocess(&commands)?;
+    let aggregate = cached_order_restaurant_aggregate();
+    with_store(store, || {
+        with_correlation(|| aggregate.handle_all_atomically(&commands))
+    })
+    .map(|(_, res)| {
+        TableIterator::new(res.into_iter().map(|(decider_id, events)| {
+            (
+                Uuid::from_bytes(decider_id.into_bytes()),
+                events.into_iter().map(|(e, _)| e).collect(),
+            )
+        }))
+    })
+}
 
-/// Event handler for Order events / Trigger function that handles order related events and updates the materialized view/table.
-#[pg_trigger]
-fn handle_order_events<'a>(
-    trigger: &'a PgTrigger<'a>,
-) -> Result<Option<PgHeapTuple<'a, impl WhoAllocated>>, TriggerError> {
-    let new = trigger
-        .new()
-        .ok_or(TriggerError::NullTriggerTuple)?
-        .into_owned();
-    let event: JsonB = new
-        .get_by_name::<JsonB>("data")?
-        .ok_or(TriggerError::NullTriggerTuple)?;
-    let materialized_view =
-        OrderMeterializedView::new(OrderViewStateRepository::new(), order_view());
-
-    match event_to_order_event(
-        &to_payload::<Event>(event)
-            .map_err(|err| TriggerError::EventHandlingError(err.to_string()))?,
-    ) {
-        // If the event is not a Restaurant event, we do nothing
-        None => return Ok(Some(new)),
-        // If the event is a Restaurant event, we handle it
-        Some(e) => {
-            materialized_view
-                .handle(&e)
-                .map_err(|err| TriggerError::EventHandlingError(err.message))?;
-        }
-    }
-    Ok(Some(new))
+/// Encodes the event (by its `event_id`) to protobuf, or returns NULL if there is no such event.
+/// Available with the `protobuf` feature.
+#[cfg(feature = "protobuf")]
+#[pg_extern(stable, parallel_safe)]
+fn event_to_protobuf(event_id: Uuid) -> Result<Option<Vec<u8>>, ErrorMessage> {
+    OrderAndRestaurantEventRepository::new()
+        .fetch_event(event_id)
+        .map(|event| event.as_ref().map(infrastructure::protobuf::encode_event))
 }
 
-// Materialized view / Table for the Order query side model
-// This table is updated by the trigger function / event handler `handle_order_events`
-extension_sql!(
-    r#"
-    CREATE TABLE IF NOT EXISTS orders (
-                                           id UUID PRIMARY KEY,
-                                           data JSONB
-    );
+/// Command handler accepting the protobuf encoded command, and returning the protobuf encoded events that were generated and persisted.
+/// Available with the `protobuf` feature.
+#[cfg(feature = "protobuf")]
+#[pg_extern]
+fn handle_protobuf(command: &[u8]) -> Result<SetOfIterator<'static, Vec<u8>>, ErrorMessage> {
+    let command = infrastructure::protobuf::decode_command(command)?;
+    handle(command, None).map(|events| {
+        SetOfIterator::new(
+            events
+                .iter()
+                .map(infrastructure::protobuf::encode_event)
+                .collect::<Vec<_>>(),
+        )
+    })
+}
 
-    CREATE TRIGGER order_event_handler_trigger AFTER INSERT ON events FOR EACH ROW EXECUTE PROCEDURE handle_order_events();
-    "#,
-    name = "order_event_handler_trigger",
-    requires = [handle_order_events]
-);
+/// The row of the benchmark report: the operation, the number of operations, the total duration, the throughput (operations per second) and the latencies (in milliseconds).
+#[cfg(feature = "bench")]
+type BenchRow = TableIterator<
+    'static,
+    (
+        name!(operation, String),
+        name!(operations, i64),
+        name!(total_ms, f64),
+        name!(ops_per_sec, f64),
+        name!(p50_ms, f64),
+        name!(p95_ms, f64),
+        name!(p99_ms, f64),
+        name!(max_ms, f64),
+    ),
+>;
 
-#[cfg(any(test, feature = "pg_test"))]
-#[pg_schema]
-mod tests {
-    // Test data: RestaurantCreated
-    extension_sql!(
-        r#"
-    INSERT INTO events (event, event_id, decider, decider_id, data, command_id, previous_id, final)
-    VALUES ('RestaurantCreated', '5f8bdf95-c95b-4e4b-8535-d2ac4663bea9', 'Restaurant', 'e48d4d9e-403e-453f-b1ba-328e0ce23737', '{"type": "RestaurantCreated","identifier": "e48d4d9e-403e-453f-b1ba-328e0ce23737", "name": "Pljeska", "menu": {"menu_id": "02f09a3f-1624-3b1d-8409-44eff7708210", "items": [{"id": "02f09a3f-1624-3b1d-8409-44eff7708210","name": "supa","price": 10},{"id": "02f09a3f-1624-3b1d-8409-44eff7708210","name": "sarma","price": 20 }],"cuisine": "Vietnamese"}, "final": false }', 'e48d4d9e-403e-453f-b1ba-328e0ce23737', NULL, FALSE);
-    "#,
-        name = "data_insert",
-        requires = [
-            "restaurant_event_handler_trigger",
-            "order_event_handler_trigger"
-        ]
-    );
-    use crate::domain::api::{
-        ChangeRestaurantMenu, CreateRestaurant, OrderCreated, OrderLineItem, OrderPlaced,
-        PlaceOrder, RestaurantCreated, RestaurantMenuChanged,
-    };
-    use crate::domain::api::{
-        MenuId, MenuItem, MenuItemId, MenuItemName, Money, OrderId, OrderLineItemId,
-        OrderLineItemQuantity, OrderStatus, RestaurantId, RestaurantMenu, RestaurantMenuCuisine,
-        RestaurantName,
-    };
-    use crate::domain::{Command, Event};
-    use pgrx::prelude::*;
-    use uuid::Uuid;
+#[cfg(feature = "bench")]
+fn bench_row(report: application::order_restaurant_bench::BenchReport) -> BenchRow {
+    TableIterator::once((
+        report.operation.to_string(),
+        report.operations,
+        report.total_ms,
+        report.ops_per_sec,
+        report.p50_ms,
+        report.p95_ms,
+        report.p99_ms,
+        report.max_ms,
+    ))
+}
 
-    #[pg_test]
-    fn create_restaurant_test() {
-        let restaurant_identifier =
-            RestaurantId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708208").unwrap());
-        let restaurant_name = RestaurantName("Test Restaurant".to_string());
-        let menu_item_id =
-            MenuItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
-        let menu_id = MenuId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
-        let menu_items = vec![MenuItem {
-            id: menu_item_id,
-            name: MenuItemName("Item 1".to_string()),
-            price: Money(100u64),
-        }];
+/// Benchmarks the command handling: appends `n` synthetic events (to a new restaurant) and reports the throughput and the latencies.
+/// The events are persisted; run it in a transaction and roll it back to discard them.
+/// Available with the `bench` feature.
+#[cfg(feature = "bench")]
+#[pg_extern]
+fn bench_append(n: i32) -> Result<BenchRow, ErrorMessage> {
+    application::order_restaurant_bench::bench_append(n as i64).map(bench_row)
+}
 
-        let create_restaurant_command = Command::CreateRestaurant(CreateRestaurant {
-            identifier: restaurant_identifier.clone(),
-            name: restaurant_name.clone(),
-            menu: RestaurantMenu {
-                menu_id: menu_id.clone(),
-                items: menu_items.clone(),
-                cuisine: RestaurantMenuCuisine::Vietnamese,
-            },
-        });
+/// Benchmarks the event stream replay: replays the events of the decider `iterations` times and reports the throughput and the latencies.
+/// Available with the `bench` feature.
+#[cfg(feature = "bench")]
+#[pg_extern(stable)]
+fn bench_replay(
+    decider_id: Uuid,
+    iterations: default!(i32, 100),
+) -> Result<BenchRow, ErrorMessage> {
+    application::order_restaurant_bench::bench_replay(
+        &uuid::Uuid::from_bytes(*decider_id.as_bytes()),
+        iterations as i64,
+    )
+    .map(bench_row)
+}
 
-        let restaurant_created_event = Event::RestaurantCreated(RestaurantCreated {
-            identifier: restaurant_identifier.clone(),
-            name: restaurant_name.clone(),
-            menu: RestaurantMenu {
-                menu_id: menu_id.clone(),
-                items: menu_items.clone(),
-                cuisine: RestaurantMenuCuisine::Vietnamese,
-            },
-            r#final: false,
-        });
+/// Benchmarks the construction of the aggregate (the decider, the saga, the validators and the hooks): constructs it `iterations` times and reports the throughput and the latencies.
+/// The commands reuse the aggregate of the backend, so this is the setup cost saved on every command.
+/// Available with the `bench` feature.
+#[cfg(feature = "bench")]
+#[pg_extern]
+fn bench_construct(iterations: default!(i32, 1000)) -> BenchRow {
+    bench_row(application::order_restaurant_bench::bench_construct(
+        iterations as i64,
+    ))
+}
 
-        assert_eq!(
-            Some(restaurant_created_event.clone()),
-            crate::handle(create_restaurant_command)
-                .unwrap()
-                .into_iter()
-                .next()
-        );
-    }
+/// Returns the Avro schemas of all the event types (the event catalog), e.g. to register them in the schema registry.
+#[pg_extern(immutable, parallel_safe)]
+fn event_avro_schemas() -> TableIterator<'static, (name!(event_type, String), name!(schema, JsonB))>
+{
+    TableIterator::new(
+        infrastructure::avro::event_avro_schemas()
+            .into_iter()
+            .map(|(event_type, schema)| (event_type.to_string(), JsonB(schema))),
+    )
+}
 
-    #[pg_test(error = "Failed to create the Restaurant. Restaurant already exists!")]
-    fn create_restaurant_error_test() {
-        let restaurant_identifier =
-            RestaurantId(Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap());
-        let restaurant_name = RestaurantName("Test Restaurant".to_string());
-        let menu_item_id =
-            MenuItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
-        let menu_id = MenuId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
-        let menu_items = vec![MenuItem {
-            id: menu_item_id,
-            name: MenuItemName("Item 1".to_string()),
-            price: Money(100u64),
-        }];
+/// Returns the client type definitions of the commands, the events and the view states (mirroring their JSON representation), e.g. to generate the front-end types in the build.
+/// The `format` is `typescript` (the TypeScript definitions) or `json_schema` (the JSON Schema, draft 2020-12).
+#[pg_extern(immutable, parallel_safe)]
+fn generate_client_types(format: default!(&str, "'typescript'")) -> Result<String, ErrorMessage> {
+    match format {
+        "typescript" => Ok(infrastructure::client_types::client_typescript()),
+        "json_schema" => Ok(serde_json::to_string_pretty(
+            &infrastructure::client_types::client_json_schema(),
+        )
+        .map_err(|err| ErrorMessage {
+            message: "Failed to serialize the client types: ".to_string() + &err.to_string(),
+            context: None,
+        })?),
+        _ => Err(ErrorMessage {
+            message: "Unsupported client types format `".to_string()
+                + format
+                + "`, expected `typescript` or `json_schema`",
+            context: None,
+        }),
+    }
+}
 
-        let create_restaurant_command = Command::CreateRestaurant(CreateRestaurant {
-            identifier: restaurant_identifier.clone(),
-            name: restaurant_name.clone(),
-            menu: RestaurantMenu {
-                menu_id: menu_id.clone(),
-                items: menu_items.clone(),
-                cuisine: RestaurantMenuCuisine::Vietnamese,
-            },
-        });
+/// Begins a new batch of commands, scoped to the current transaction.
+/// Commands are queued with `fmodel_queue`, and executed at once, with the `handle_all` semantics, with `fmodel_execute_batch`.
+#[pg_extern]
+fn fmodel_begin_batch() -> Result<(), ErrorMessage> {
+    CommandBatchRepository::new().begin()
+}
 
-        let _ = crate::handle(create_restaurant_command);
-    }
+/// Queues the command to the current batch, and returns the number of the queued commands.
+#[pg_extern]
+fn fmodel_queue(command: Command) -> Result<i64, ErrorMessage> {
+    CommandBatchRepository::new().queue(&command)
+}
 
-    #[pg_test]
-    fn change_menu_test() {
-        let restaurant_identifier =
-            RestaurantId(Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap());
-        let menu_item_id =
-            MenuItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
-        let menu_id = MenuId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
-        let menu_items = vec![MenuItem {
-            id: menu_item_id,
-            name: MenuItemName("Item 1".to_string()),
-            price: Money(100u64),
-        }];
+/// Executes the queued commands with the `handle_all` semantics (all or nothing), ends the batch, and returns the events that were generated and persisted.
+#[pg_extern]
+fn fmodel_execute_batch() -> Result<Vec<Event>, ErrorMessage> {
+    let commands = CommandBatchRepository::new().take()?;
+    handle_all(commands, None)
+}
 
-        let change_restaurant_menu = Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
-            identifier: restaurant_identifier.clone(),
-            menu: RestaurantMenu {
-                menu_id: menu_id.clone(),
-                items: menu_items.clone(),
-                cuisine: RestaurantMenuCuisine::Vietnamese,
-            },
-        });
+/// Command handler for the whole domain / orders and restaurants combined.
+/// It handles a single command and returns a list of events that were generated and persisted, together with their `event_id`, `offset`, `sequence_number` and `correlation_id`.
+/// Clients can compare the returned offsets with `restaurant_view_version`/`order_view_version` to implement "read-your-writes".
+#[pg_extern]
+fn handle_with_offsets(
+    command: Command,
+    store: default!(Option<&str>, "NULL"),
+) -> Result<
+    TableIterator<
+        'static,
+        (
+            name!(event, Event),
+            name!(event_id, Uuid),
+            name!(offset, i64),
+            name!(sequence_number, i64),
+            name!(correlation_id, Uuid),
+        ),
+    >,
+    ErrorMessage,
+> {
+    preprocess(std::slice::from_ref(&command))?;
+    let aggregate = cached_order_restaurant_aggregate();
+    with_store(store, || with_correlation(|| aggregate.handle(&command)))
+        .map(|(correlation_id, res)| TableIterator::new(to_event_rows(correlation_id, res)))
+}
+
+/// Compound command handler for the domain / orders and restaurants combined.
+/// It handles a list of commands in a single transaction and returns a list of events that were generated and persisted, together with their `event_id`, `offset`, `sequence_number` and `correlation_id`.
+#[pg_extern]
+fn handle_all_with_offsets(
+    commands: Vec<Command>,
+    store: default!(Option<&str>, "NULL"),
+) -> Result<
+    TableIterator<
+        'static,
+        (
+            name!(event, Event),
+            name!(event_id, Uuid),
+            name!(offset, i64),
+            name!(sequence_number, i64),
+            name!(correlation_id, Uuid),
+        ),
+    >,
+    ErrorMessage,
+> {
+    preprocess(&commands)?;
+    let aggregate = cached_order_restaurant_aggregate();
+    with_store(store, || {
+        with_correlation(|| aggregate.handle_all(&commands))
+    })
+    .map(|(correlation_id, res)| TableIterator::new(to_event_rows(correlation_id, res)))
+}
+
+/// Command handler with the optimistic check of the command stream version, for the clients caching the stream.
+/// The command is handled only if its event stream is at the expected version (the `sequence_number` of its last event; `0` for the new stream).
+#[pg_extern]
+fn handle_with_expected_version(
+    command: Command,
+    expected_version: i64,
+    store: default!(Option<&str>, "NULL"),
+) -> Result<
+    TableIterator<
+        'static,
+        (
+            name!(event, Event),
+            name!(event_id, Uuid),
+            name!(offset, i64),
+            name!(sequence_number, i64),
+            name!(correlation_id, Uuid),
+        ),
+    >,
+    ErrorMessage,
+> {
+    preprocess(std::slice::from_ref(&command))?;
+    let aggregate = cached_order_restaurant_aggregate();
+    with_store(store, || {
+        with_correlation(|| aggregate.handle_with_expected_version(&command, expected_version))
+    })
+    .map(|(correlation_id, res)| TableIterator::new(to_event_rows(correlation_id, res)))
+}
+
+/// Rows of the persisted events: the event, its position, and the correlation id.
+fn to_event_rows(
+    correlation_id: uuid::Uuid,
+    events: Vec<(Event, EventPosition)>,
+) -> impl Iterator<Item = (Event, Uuid, i64, i64, Uuid)> {
+    let correlation_id = Uuid::from_bytes(correlation_id.into_bytes());
+    events.into_iter().map(move |(e, position)| {
+        (
+            e,
+            Uuid::from_bytes(position.event_id.into_bytes()),
+            position.offset,
+            position.sequence_number,
+            correlation_id,
+        )
+    })
+}
+
+/// Returns the health report of the event store (a single JSONB document), for the monitoring: the extension version, the latest offset, the projection lag and the rebuild status, the oldest unapplied offset, the liveness of the projector background worker, and the frozen decider types.
+#[pg_extern(stable)]
+fn fmodel_health() -> Result<JsonB, ErrorMessage> {
+    framework::infrastructure::health::fetch_health()
+}
+
+/// Freezes the decider type (e.g. `Restaurant`), superuser only: the new commands of the decider type are rejected, including the commands issued by the saga (recorded as `failed`, see `pending_saga_work`), until it is unfrozen.
+/// Use it during the migrations or the incidents. The freeze waits for the commands of the decider type in flight; returns `false` if the decider type was frozen already.
+#[pg_extern]
+fn freeze_decider(decider: &str) -> Result<bool, ErrorMessage> {
+    framework::infrastructure::require_superuser("freeze the decider")?;
+    framework::infrastructure::frozen_deciders::freeze(decider)
+}
+
+/// Unfreezes the decider type (see `freeze_decider`), superuser only: the commands of the decider type are handled again. Returns `false` if the decider type was not frozen.
+#[pg_extern]
+fn unfreeze_decider(decider: &str) -> Result<bool, ErrorMessage> {
+    framework::infrastructure::require_superuser("unfreeze the decider")?;
+    framework::infrastructure::frozen_deciders::unfreeze(decider)
+}
+
+/// Returns the statistics of the command handling, by the command type, since the server start (or `fmodel_command_stats_reset`): the number of the handled commands, the failure rate, the mean and the maximum time of handling the command (in milliseconds), and the mean number of the produced events.
+/// Spot the commands that are slow or failing without the external APM. The statistics are kept in the shared memory, for the commands handled one by one (not `handle_all`); empty if the extension is not loaded via `shared_preload_libraries`.
+#[pg_extern]
+fn fmodel_command_stats() -> TableIterator<
+    'static,
+    (
+        name!(command_type, String),
+        name!(calls, i64),
+        name!(failures, i64),
+        name!(failure_rate, f64),
+        name!(mean_time_ms, f64),
+        name!(max_time_ms, f64),
+        name!(mean_events, f64),
+    ),
+> {
+    TableIterator::new(
+        framework::infrastructure::command_stats::fetch_command_stats()
+            .into_iter()
+            .map(|(command_type, stats)| {
+                (
+                    command_type,
+                    stats.calls as i64,
+                    stats.failures as i64,
+                    stats.failure_rate(),
+                    stats.mean_time_ms(),
+                    stats.max_time_ms,
+                    stats.mean_events(),
+                )
+            }),
+    )
+}
+
+/// Discards the statistics of the command handling (see `fmodel_command_stats`).
+#[pg_extern]
+fn fmodel_command_stats_reset() {
+    framework::infrastructure::command_stats::reset()
+}
+
+/// Runs the embedded specifications of the domain (the given-when-then cases of the order&restaurant decider, see `domain/specifications.rs`), and returns a row per specification: passed or not, and the expected and the actual outcome if not.
+/// Verifies the domain logic of the deployed extension, e.g. after the upgrade; nothing is persisted.
+#[pg_extern(stable)]
+fn spec_report() -> TableIterator<
+    'static,
+    (
+        name!(specification, String),
+        name!(passed, bool),
+        name!(details, Option<String>),
+    ),
+> {
+    let decider =
+        order_restaurant_decider(PostgresClock, PostgresStockLevels, PostgresExchangeRates);
+    TableIterator::new(
+        domain::specifications::order_restaurant_specifications()
+            .into_iter()
+            .map(|specification| match specification.verify(&decider) {
+                Ok(()) => (specification.name.to_string(), true, None),
+                Err(details) => (specification.name.to_string(), false, Some(details)),
+            })
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Analyzes the events (refreshes the planner statistics of the `events` table and its partitions), and returns the histogram of the stream lengths per decider type, in the buckets by the order of magnitude: the input for the capacity planning of the snapshots.
+/// Every command replays the whole stream of the decider, so the streams longer than `fmodel.long_stream_threshold` (the longest first, up to 100 of them) are reported as warnings.
+#[pg_extern]
+fn analyze_event_streams() -> Result<
+    TableIterator<
+        'static,
+        (
+            name!(decider, String),
+            name!(from_length, i64),
+            name!(to_length, i64),
+            name!(streams, i64),
+            name!(max_length, i64),
+        ),
+    >,
+    ErrorMessage,
+> {
+    use framework::infrastructure::stream_statistics::{
+        analyze_events, fetch_long_streams, fetch_stream_length_histogram,
+    };
+    analyze_events()?;
+    let threshold = LONG_STREAM_THRESHOLD.get();
+    if threshold > 0 {
+        for (decider, decider_id, length) in fetch_long_streams(threshold as i64, 100)? {
+            warning!(
+                "The {} stream {} has {} events (the threshold is {}), and no snapshot: every command replays the whole stream",
+                decider,
+                decider_id,
+                length,
+                threshold
+            );
+        }
+    }
+    fetch_stream_length_histogram().map(TableIterator::new)
+}
+
+/// Returns the canonical JSON of the event data/payload: the object keys sorted, and no insignificant whitespace.
+/// The payload hash (deduplication) is the SHA-256 of the canonical JSON, stable across the serializer upgrades.
+#[pg_extern(immutable, parallel_safe)]
+fn fmodel_canonical_json(data: JsonB) -> String {
+    framework::infrastructure::canonical_json::to_canonical_json(&data.0)
+}
+
+/// Verifies the payload hashes of the events, and reports the events hashed from the non-canonical JSON (the legacy `jsonb` text representation), in the order of the offsets.
+/// The deduplication does not detect the duplicates of the reported events.
+#[pg_extern(stable, parallel_safe)]
+fn verify_canonical_payloads() -> Result<
+    TableIterator<
+        'static,
+        (
+            name!(event_id, Uuid),
+            name!(decider, String),
+            name!(decider_id, String),
+            name!(offset, i64),
+            name!(event, String),
+        ),
+    >,
+    ErrorMessage,
+> {
+    framework::infrastructure::canonical_json::fetch_non_canonical_events().map(TableIterator::new)
+}
+
+/// Returns the version of the event stream of the decider: the `sequence_number` of its last event, or NULL if the stream is empty.
+#[pg_extern(stable, parallel_safe)]
+fn stream_version(decider_id: Uuid) -> Result<Option<i64>, ErrorMessage> {
+    OrderAndRestaurantEventRepository::new()
+        .fetch_stream_sequence_number(&uuid::Uuid::from_bytes(*decider_id.as_bytes()))
+}
+
+/// Returns the events of the event stream of the decider, in the order they were appended.
+/// Read-only: it can be called on the hot standbys (read replicas) as well.
+#[pg_extern(stable, parallel_safe)]
+fn get_events(decider_id: Uuid) -> Result<Vec<Event>, ErrorMessage> {
+    OrderAndRestaurantEventRepository::new()
+        .fetch_stream_events(&uuid::Uuid::from_bytes(*decider_id.as_bytes()))
+        .map(|res| res.into_iter().map(|(e, _)| e).collect())
+}
+
+/// Returns the JSON diff (RFC 6902 patch) of what changed in the state of the decider between the two offsets, e.g. for the support and audit reviews.
+/// The states are folded at each bound by replaying the events of the stream through the decider.
+/// Volatile: the events are replayed through the aggregate of the backend, initialized on the first use.
+#[pg_extern]
+fn state_diff(decider_id: Uuid, from_offset: i64, to_offset: i64) -> Result<JsonB, ErrorMessage> {
+    let aggregate = cached_order_restaurant_aggregate();
+    let patch = aggregate.state_diff(
+        &uuid::Uuid::from_bytes(*decider_id.as_bytes()),
+        from_offset,
+        to_offset,
+    )?;
+    serde_json::to_value(patch)
+        .map(JsonB)
+        .map_err(|err| ErrorMessage {
+            message: "Failed to serialize the state diff: ".to_string() + &err.to_string(),
+            context: None,
+        })
+}
+
+/// Decides the command against the hypothetical state of its decider (the restaurant, the order, the promotion, the inventory, the gift card or the courier, as exported by `export_stream`; `NULL` for the decider not created yet), e.g. for the "can I do X?" affordances of the UI.
+/// Returns the hypothetical events, or the rejection (the error of the decider) if the command is rejected in that state.
+/// Sandboxed: the event streams are not loaded (only the stock levels of the restaurant are read from its inventory), and nothing is persisted.
+#[pg_extern(stable)]
+fn decide_against_state(
+    command: Command,
+    state: default!(Option<JsonB>, "NULL"),
+) -> Result<
+    TableIterator<'static, (name!(events, Vec<Event>), name!(rejection, Option<String>))>,
+    ErrorMessage,
+> {
+    let state = state
+        .map(|state| state.0)
+        .unwrap_or(serde_json::Value::Null);
+    let to_error = |err: serde_json::Error| ErrorMessage {
+        message: "Failed to deserialize the state of the `".to_string()
+            + &command.decider_type()
+            + "`: "
+            + &err.to_string(),
+        context: None,
+    };
+    let state = match command.decider_type().as_str() {
+        "Restaurant" => (
+            serde_json::from_value(state).map_err(to_error)?,
+            None,
+            None,
+            None,
+            None,
+            None,
+        ),
+        "Promotion" => (
+            None,
+            None,
+            serde_json::from_value(state).map_err(to_error)?,
+            None,
+            None,
+            None,
+        ),
+        "Inventory" => (
+            None,
+            None,
+            None,
+            serde_json::from_value(state).map_err(to_error)?,
+            None,
+            None,
+        ),
+        "GiftCard" => (
+            None,
+            None,
+            None,
+            None,
+            serde_json::from_value(state).map_err(to_error)?,
+            None,
+        ),
+        "Courier" => (
+            None,
+            None,
+            None,
+            None,
+            None,
+            serde_json::from_value(state).map_err(to_error)?,
+        ),
+        _ => (
+            None,
+            serde_json::from_value(state).map_err(to_error)?,
+            None,
+            None,
+            None,
+            None,
+        ),
+    };
+    let decider =
+        order_restaurant_decider(PostgresClock, PostgresStockLevels, PostgresExchangeRates);
+    // The decision fails with the error (`error!`): it is caught, and returned as the rejection
+    let decision = PgTryBuilder::new(AssertUnwindSafe(|| Ok((decider.decide)(&command, &state))))
+        .catch_others(|err| match err {
+            CaughtError::ErrorReport(report) => Err(report.message().to_string()),
+            err => err.rethrow(),
+        })
+        .execute();
+    Ok(TableIterator::once(match decision {
+        Ok(events) => (events, None),
+        Err(rejection) => (Vec::new(), Some(rejection)),
+    }))
+}
+
+/// Returns all the events of the correlation / business transaction (e.g. the order placed at the restaurant, and the order created by the saga), in the order they were appended.
+#[pg_extern(stable, parallel_safe)]
+fn get_events_by_correlation(correlation_id: Uuid) -> Result<Vec<Event>, ErrorMessage> {
+    OrderAndRestaurantEventRepository::new()
+        .fetch_events_by_correlation(&uuid::Uuid::from_bytes(*correlation_id.as_bytes()))
+        .map(|res| res.into_iter().map(|(e, _)| e).collect())
+}
+
+/// Returns (up to `limit`) events tagged with the tag (e.g. `promo:summer24`, `cuisine:vietnamese`), after the offset, in the order they were appended.
+/// Page through the tagged events by passing the offset of the last returned event as `after_offset`.
+#[pg_extern(stable, parallel_safe)]
+fn get_events_by_tag(
+    tag: &str,
+    after_offset: default!(i64, 0),
+    limit: default!(i64, 100),
+) -> Result<
+    TableIterator<
+        'static,
+        (
+            name!(event, Event),
+            name!(event_id, Uuid),
+            name!(offset, i64),
+            name!(sequence_number, i64),
+        ),
+    >,
+    ErrorMessage,
+> {
+    OrderAndRestaurantEventRepository::new()
+        .fetch_events_by_tag(tag, after_offset, limit)
+        .map(|res| {
+            TableIterator::new(res.into_iter().map(|(event, position)| {
+                (
+                    event,
+                    Uuid::from_bytes(position.event_id.into_bytes()),
+                    position.offset,
+                    position.sequence_number,
+                )
+            }))
+        })
+}
+
+/// Returns the traces of the saga reactions of the correlation / business transaction (recorded if `fmodel.saga_traces` is enabled): the depth of the orchestration, the input event, the produced commands and the resulting events.
+#[pg_extern(stable, parallel_safe)]
+fn get_saga_trace(
+    correlation_id: Uuid,
+) -> Result<
+    TableIterator<
+        'static,
+        (
+            name!(depth, i32),
+            name!(event, JsonB),
+            name!(commands, JsonB),
+            name!(events, JsonB),
+            name!(created_at, TimestampWithTimeZone),
+        ),
+    >,
+    ErrorMessage,
+> {
+    framework::infrastructure::saga_trace::fetch_saga_trace(&uuid::Uuid::from_bytes(
+        *correlation_id.as_bytes(),
+    ))
+    .map(TableIterator::new)
+}
+
+/// Returns the status of the long-running workflow (the saga of the business transaction, e.g. order → preparation), folded from its event stream: `in_progress`, `stuck` (a saga command failed, see `retry_saga_command`) or `waiting` (for the next event), and the steps (the commands issued by the saga, with their outcomes).
+/// Empty if the saga issued no commands in the business transaction.
+#[pg_extern(stable, parallel_safe)]
+fn workflow_status(
+    correlation_id: Uuid,
+) -> Result<TableIterator<'static, (name!(status, String), name!(steps, JsonB))>, ErrorMessage> {
+    let workflow = fetch_workflow(&uuid::Uuid::from_bytes(*correlation_id.as_bytes()))?;
+    let mut rows = Vec::new();
+    if let Some(workflow) = workflow {
+        let to_json = |value: Result<serde_json::Value, serde_json::Error>| {
+            value.map_err(|err| ErrorMessage {
+                message: "Failed to serialize the workflow: ".to_string() + &err.to_string(),
+                context: None,
+            })
+        };
+        let status = to_json(serde_json::to_value(workflow.status()))?;
+        rows.push((
+            status.as_str().unwrap_or_default().to_string(),
+            JsonB(to_json(serde_json::to_value(&workflow.steps))?),
+        ));
+    }
+    Ok(TableIterator::new(rows))
+}
+
+/// Returns the pending saga work: the commands issued by the saga that are not succeeded (`failed`, or `issued`), the oldest first.
+/// The failed command (e.g. the order creation rejected) does not fail the originating command; detect the stuck orchestrations here, and re-drive them.
+#[pg_extern(stable, parallel_safe)]
+fn pending_saga_work() -> Result<
+    TableIterator<
+        'static,
+        (
+            name!(id, i64),
+            name!(correlation_id, Option<Uuid>),
+            name!(depth, i32),
+            name!(event, JsonB),
+            name!(command, JsonB),
+            name!(status, String),
+            name!(error, Option<String>),
+            name!(attempts, i32),
+            name!(created_at, TimestampWithTimeZone),
+        ),
+    >,
+    ErrorMessage,
+> {
+    framework::infrastructure::saga_commands::fetch_pending_saga_commands().map(TableIterator::new)
+}
+
+/// Re-drives the failed saga command (see `pending_saga_work`), e.g. after the transient failure: the command is handled again, through the aggregate, within the correlation of the original business transaction.
+/// The command is claimed (locked) first, so it is never handled twice: the command that is not failed (e.g. re-driven already, concurrently) is rejected.
+/// Returns the command id, the new status, and the error (if the command is rejected again).
+#[pg_extern]
+fn retry_saga_command(
+    id: i64,
+) -> Result<
+    TableIterator<
+        'static,
+        (
+            name!(id, i64),
+            name!(status, String),
+            name!(error, Option<String>),
+        ),
+    >,
+    ErrorMessage,
+> {
+    let retried = redrive_saga_command(id)?.ok_or(ErrorMessage {
+        message: "Failed to retry the saga command `".to_string()
+            + &id.to_string()
+            + "`: the command does not exist or is not failed",
+        context: None,
+    })?;
+    Ok(TableIterator::new(vec![retried]))
+}
+
+/// Re-drives the failed saga commands (up to the limit), the oldest first, automating the recovery after the transient failures.
+/// The commands claimed by the concurrent re-drives are skipped. The command that is rejected again stays `failed`, and does not fail the re-drive of the other commands.
+/// Returns the command ids, the new statuses, and the errors.
+#[pg_extern]
+fn retry_failed_saga_commands(
+    limit: i32,
+) -> Result<
+    TableIterator<
+        'static,
+        (
+            name!(id, i64),
+            name!(status, String),
+            name!(error, Option<String>),
+        ),
+    >,
+    ErrorMessage,
+> {
+    let mut retried = Vec::new();
+    for id in fetch_failed_saga_command_ids(limit as i64)? {
+        retried.extend(redrive_saga_command(id)?);
+    }
+    Ok(TableIterator::new(retried))
+}
+
+/// Claims the failed saga command, and handles it through the aggregate (the middleware chain first).
+/// The rejection of the command (the decider error) is recorded in the ledger; other errors fail the re-drive.
+/// Returns `None` if the command does not exist, or is not failed.
+fn redrive_saga_command(id: i64) -> Result<Option<(i64, String, Option<String>)>, ErrorMessage> {
+    let Some((command, correlation_id)) = claim_failed_saga_command(id)? else {
+        return Ok(None);
+    };
+    let command = to_payload::<Command>(command)?;
+    preprocess(std::slice::from_ref(&command))?;
+    let aggregate = cached_order_restaurant_aggregate();
+    let correlation_id = correlation_id
+        .map(|correlation_id| uuid::Uuid::from_bytes(*correlation_id.as_bytes()))
+        .unwrap_or_else(uuid::Uuid::new_v4);
+    let (_, handled) = within_correlation(correlation_id, || {
+        // Only the errors raised by the decider (Rust) are caught; the Postgres errors abort the transaction
+        PgTryBuilder::new(AssertUnwindSafe(|| aggregate.handle(&command).map(Ok)))
+            .catch_others(|err| match err {
+                CaughtError::ErrorReport(report) => Ok(Err(report.message().to_string())),
+                err => err.rethrow(),
+            })
+            .execute()
+    })?;
+    let (status, error) = match handled {
+        Ok(_) => (SagaCommandStatus::Succeeded, None),
+        Err(error) => (SagaCommandStatus::Failed, Some(error)),
+    };
+    update_saga_command_status(id, status, error.as_deref())?;
+    handle_workflow_command(&WorkflowCommand::CompleteCommand {
+        identifier: correlation_id,
+        saga_command_id: id,
+        error: error.clone(),
+    })?;
+    Ok(Some((id, status.as_str().to_string(), error)))
+}
+
+/// Forks the event stream of the decider for the what-if analysis: copies the events of the stream, up to (and including) the offset, under the new identity.
+/// Handle the hypothetical commands against the fork (`new_decider_id`); the original stream is not touched.
+#[pg_extern]
+fn fork_stream(
+    decider_id: Uuid,
+    new_decider_id: Uuid,
+    up_to_offset: i64,
+) -> Result<Vec<Event>, ErrorMessage> {
+    OrderAndRestaurantEventRepository::new()
+        .fork_stream(decider_id, new_decider_id, up_to_offset)
+        .map(|res| res.into_iter().map(|(e, _)| e).collect())
+}
+
+/// Merges the restaurant (the record created by mistake) into the target restaurant, superuser only.
+/// The administrative `RestaurantOrdersMigrated` event retires the source restaurant (its stream is final), and the target restaurant receives all its orders, with their payments (`RestaurantOrdersReceived`).
+/// The open orders follow the target restaurant (`OrderMigrated`, issued by the saga), and the projections are remapped by these events; the history of both restaurants is kept as it was.
+#[pg_extern]
+fn merge_streams(source: Uuid, target: Uuid) -> Result<Vec<Event>, ErrorMessage> {
+    migrate_restaurant_orders("merge the streams", source, target, None)
+}
+
+/// Splits the orders (placed at the restaurant by mistake) off to the target restaurant (create it first), superuser only.
+/// The orders are migrated as by `merge_streams`, but the source restaurant is not retired.
+#[pg_extern]
+fn split_stream(
+    source: Uuid,
+    target: Uuid,
+    order_ids: Vec<Uuid>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    migrate_restaurant_orders("split the stream", source, target, Some(order_ids))
+}
+
+/// Appends the administrative correction event (JSONB, any known event type) to the existing stream of the decider, superuser only, so fixing the bad state never requires updating the history.
+/// The payload is validated against the event schema (and the event validators); the event is tagged `correction`, and the reason and the operator are recorded in the `event_corrections` audit.
+/// The projections are updated by the correction as by any other event; the saga does not react to it.
+#[pg_extern]
+fn append_correction(decider_id: Uuid, event: JsonB, reason: &str) -> Result<Event, ErrorMessage> {
+    framework::infrastructure::require_superuser("append the correction")?;
+    if reason.trim().is_empty() {
+        return Err(ErrorMessage {
+            message: "Failed to append the correction: the reason is required".to_string(),
+            context: None,
+        });
+    }
+    let event: Event = to_payload(event)?;
+    if event.identifier().as_bytes() != decider_id.as_bytes() {
+        return Err(ErrorMessage {
+            message: "Failed to append the correction: the event of the stream `".to_string()
+                + &event.identifier().to_string()
+                + "` does not belong to the stream `"
+                + &decider_id.to_string()
+                + "`",
+            context: None,
+        });
+    }
+    order_restaurant_validators().validate(std::slice::from_ref(&event))?;
+    OrderAndRestaurantEventRepository::new()
+        .append_correction(event, reason)
+        .map(|(event, _)| event)
+}
+
+/// Migrates the orders (all of them, if `None`) of the source restaurant to the target restaurant, in the same transaction: the source restaurant migrates the orders out, and the target restaurant receives them.
+fn migrate_restaurant_orders(
+    operation: &str,
+    source: Uuid,
+    target: Uuid,
+    order_ids: Option<Vec<Uuid>>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    framework::infrastructure::require_superuser(operation)?;
+    let source = RestaurantId(uuid::Uuid::from_bytes(*source.as_bytes()));
+    let target = RestaurantId(uuid::Uuid::from_bytes(*target.as_bytes()));
+    let mut events = handle(
+        Command::MigrateRestaurantOrders(MigrateRestaurantOrders {
+            identifier: source.clone(),
+            target_identifier: target.clone(),
+            order_identifiers: order_ids.map(|order_ids| {
+                order_ids
+                    .iter()
+                    .map(|order_id| OrderId(uuid::Uuid::from_bytes(*order_id.as_bytes())))
+                    .collect()
+            }),
+        }),
+        None,
+    )?;
+    let orders = events
+        .iter()
+        .find_map(|event| match event {
+            Event::RestaurantOrdersMigrated(event) => Some(event.orders.clone()),
+            _ => None,
+        })
+        .unwrap_or_default();
+    events.extend(handle(
+        Command::ReceiveRestaurantOrders(ReceiveRestaurantOrders {
+            identifier: target,
+            source_identifier: source,
+            orders,
+        }),
+        None,
+    )?);
+    Ok(events)
+}
+
+/// Exports the event stream of the decider as a single JSONB document, e.g. for attaching to the support tickets: the envelopes of the events (the metadata and the payload), in the order they were appended, and the folded (final) state of the decider.
+/// Import the document into another database (e.g. the local development) with `import_stream`.
+/// Volatile: the state is folded by the aggregate of the backend, initialized on the first use.
+#[pg_extern]
+fn export_stream(decider_id: Uuid) -> Result<JsonB, ErrorMessage> {
+    let events = OrderAndRestaurantEventRepository::new().fetch_stream_envelopes(decider_id)?;
+    let state = match cached_order_restaurant_aggregate()
+        .state_at(&uuid::Uuid::from_bytes(*decider_id.as_bytes()), i64::MAX)?
+    {
+        (Some(restaurant), _, _, _, _, _) => serde_json::to_value(restaurant),
+        (_, Some(order), _, _, _, _) => serde_json::to_value(order),
+        (_, _, Some(promotion), _, _, _) => serde_json::to_value(promotion),
+        (_, _, _, Some(inventory), _, _) => serde_json::to_value(inventory),
+        (_, _, _, _, Some(gift_card), _) => serde_json::to_value(gift_card),
+        (_, _, _, _, _, Some(courier)) => serde_json::to_value(courier),
+        (None, None, None, None, None, None) => Ok(serde_json::Value::Null),
+    }
+    .map_err(|err| ErrorMessage {
+        message: "Failed to serialize the state: ".to_string() + &err.to_string(),
+        context: None,
+    })?;
+    Ok(JsonB(serde_json::json!({
+        "decider_id": decider_id.to_string(),
+        "events": events.0,
+        "state": state,
+    })))
+}
+
+/// Imports the event stream exported by `export_stream` (the `data` of the event envelopes), as the new event stream of the decider. The stream must not exist yet.
+/// The events are appended as new (new `event_id`, `offset`, and `created_at`); the projections are updated as for any other appended event.
+/// The identifiers are remapped on the fly by the identity map (`{"<old uuid>": "<new uuid>", ...}`), e.g. to import the production streams into the staging without colliding with the existing data, and without leaking the real identifiers.
+#[pg_extern]
+fn import_stream(
+    document: JsonB,
+    identity_map: default!(Option<JsonB>, "NULL"),
+) -> Result<Vec<Event>, ErrorMessage> {
+    let mut document = document;
+    if let Some(identity_map) = identity_map {
+        remap_identities(&mut document.0, &to_identity_map(identity_map)?);
+    }
+    let decider_id = document
+        .0
+        .get("decider_id")
+        .and_then(|decider_id| decider_id.as_str())
+        .and_then(|decider_id| uuid::Uuid::parse_str(decider_id).ok())
+        .ok_or(ErrorMessage {
+            message: "Failed to import the stream: `decider_id` is missing or invalid".to_string(),
+            context: None,
+        })?;
+    let events = document
+        .0
+        .get("events")
+        .and_then(|events| events.as_array())
+        .ok_or(ErrorMessage {
+            message: "Failed to import the stream: `events` is missing".to_string(),
+            context: None,
+        })?
+        .iter()
+        .map(|envelope| {
+            serde_json::from_value::<Event>(envelope["data"].clone()).map_err(|err| ErrorMessage {
+                message: "Failed to import the stream: ".to_string() + &err.to_string(),
+                context: None,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    OrderAndRestaurantEventRepository::new()
+        .import_stream(Uuid::from_bytes(*decider_id.as_bytes()), events)
+        .map(|res| res.into_iter().map(|(e, _)| e).collect())
+}
+
+/// Parses the identity map (`{"<old uuid>": "<new uuid>", ...}`) of the `import_stream`.
+fn to_identity_map(identity_map: JsonB) -> Result<HashMap<uuid::Uuid, String>, ErrorMessage> {
+    let invalid = || ErrorMessage {
+        message:
+            "Failed to import the stream: the identity map must map the old UUIDs to the new UUIDs"
+                .to_string(),
+        context: None,
+    };
+    identity_map
+        .0
+        .as_object()
+        .ok_or_else(invalid)?
+        .iter()
+        .map(|(old, new)| {
+            let old = uuid::Uuid::parse_str(old).map_err(|_| invalid())?;
+            let new = new
+                .as_str()
+                .and_then(|new| uuid::Uuid::parse_str(new).ok())
+                .ok_or_else(invalid)?;
+            Ok((old, new.to_string()))
+        })
+        .collect()
+}
+
+/// Replaces (recursively) every string value that is the old identifier in the identity map with the new identifier.
+fn remap_identities(value: &mut serde_json::Value, identity_map: &HashMap<uuid::Uuid, String>) {
+    match value {
+        serde_json::Value::String(string) => {
+            if let Some(new) = uuid::Uuid::parse_str(string)
+                .ok()
+                .and_then(|old| identity_map.get(&old))
+            {
+                *string = new.to_owned();
+            }
+        }
+        serde_json::Value::Array(values) => values
+            .iter_mut()
+            .for_each(|value| remap_identities(value, identity_map)),
+        serde_json::Value::Object(values) => values
+            .values_mut()
+            .for_each(|value| remap_identities(value, identity_map)),
+        _ => {}
+    }
+}
+
+/// Opens the server-side cursor over the events after the offset (ordered by the `offset`), and returns the name of the cursor.
+/// Stream the (very large) event store in bounded memory with `fetch_event_batch`, in the same transaction.
+#[pg_extern]
+fn open_event_cursor(after_offset: default!(i64, 0)) -> Result<String, ErrorMessage> {
+    framework::infrastructure::event_cursor::open_event_cursor(after_offset)
+}
+
+/// Fetches the next batch of (up to `n`) events from the cursor. The batch is empty when the cursor is exhausted.
+#[pg_extern]
+fn fetch_event_batch(
+    cursor: &str,
+    n: default!(i64, 1000),
+) -> Result<
+    TableIterator<
+        'static,
+        (
+            name!(event, JsonB),
+            name!(event_id, Uuid),
+            name!(offset, i64),
+            name!(sequence_number, i64),
+        ),
+    >,
+    ErrorMessage,
+> {
+    framework::infrastructure::event_cursor::fetch_event_batch(cursor, n).map(|res| {
+        TableIterator::new(res.into_iter().map(|(event, position)| {
+            (
+                event,
+                Uuid::from_bytes(position.event_id.into_bytes()),
+                position.offset,
+                position.sequence_number,
+            )
+        }))
+    })
+}
+
+/// Closes the event cursor (it is closed at the end of the transaction, otherwise).
+#[pg_extern]
+fn close_event_cursor(cursor: &str) -> Result<(), ErrorMessage> {
+    framework::infrastructure::event_cursor::close_event_cursor(cursor)
+}
+
+/// Returns every historical menu of the restaurant (e.g. for the pricing audits), with the period in which the menu was effective (`effective_to` is NULL for the current menu).
+#[pg_extern(stable, parallel_safe)]
+fn menu_history(
+    restaurant_id: Uuid,
+) -> Result<
+    TableIterator<
+        'static,
+        (
+            name!(menu_version, i64),
+            name!(menu, JsonB),
+            name!(effective_from, TimestampWithTimeZone),
+            name!(effective_to, Option<TimestampWithTimeZone>),
+        ),
+    >,
+    ErrorMessage,
+> {
+    OrderAndRestaurantEventRepository::new()
+        .fetch_menu_history(restaurant_id)
+        .map(TableIterator::new)
+}
+
+/// Position extracted from the `event_id`, `offset` and `sequence_number` columns of the trigger tuple.
+fn to_event_position(
+    new: &PgHeapTuple<'_, impl WhoAllocated>,
+) -> Result<EventPosition, TriggerError> {
+    let event_id: Uuid = new
+        .get_by_name::<Uuid>("event_id")?
+        .ok_or(TriggerError::NullTriggerTuple)?;
+    let offset: i64 = new
+        .get_by_name::<i64>("offset")?
+        .ok_or(TriggerError::NullTriggerTuple)?;
+    let sequence_number: i64 = new
+        .get_by_name::<i64>("sequence_number")?
+        .ok_or(TriggerError::NullTriggerTuple)?;
+    Ok(EventPosition {
+        event_id: uuid::Uuid::from_bytes(*event_id.as_bytes()),
+        offset,
+        sequence_number,
+    })
+}
+
+/// Handles the event from the trigger tuple with the projection, if the projection is in the `sync` mode.
+/// The projections in the `statement` mode are handled by the statement-level trigger (see `handle_events_statement`).
+fn handle_projection_trigger(
+    projection: &str,
+    new: &PgHeapTuple<'_, impl WhoAllocated>,
+) -> Result<(), TriggerError> {
+    let projector = OrderAndRestaurantProjector::new(OrderAndRestaurantProjectionRepository::new());
+    // If the projection is updated asynchronously, the projector (background worker) will handle the event
+    if projector
+        .mode(projection)
+        .map_err(|err| TriggerError::EventHandlingError(err.message))?
+        != ProjectionMode::Sync
+    {
+        return Ok(());
+    }
+    let event: JsonB = new
+        .get_by_name::<JsonB>("data")?
+        .ok_or(TriggerError::NullTriggerTuple)?;
+    let position = to_event_position(new)?;
+    let store = new
+        .get_by_name::<String>("store_id")?
+        .ok_or(TriggerError::NullTriggerTuple)?;
+    let handler = projection_handler(projection)
+        .map_err(|err| TriggerError::EventHandlingError(err.message))?;
+    match to_trigger_event(event)? {
+        Some(event) => apply_trigger_event_in_store(handler, &event, &position, &store),
+        None => Ok(()),
+    }
+}
+
+/// Deserializes the event of the trigger. The event of unknown type is skipped (`None`) if `fmodel.skip_unknown_events` is enabled.
+fn to_trigger_event(event: JsonB) -> Result<Option<Event>, TriggerError> {
+    match to_event::<Event>(event)
+        .map_err(|err| TriggerError::DeserializationFailed(err.message))?
+    {
+        EventPayload::Known(e) => Ok(Some(e)),
+        // If the event is of unknown type (and `fmodel.skip_unknown_events` is enabled), we do nothing
+        EventPayload::Unknown(_) if SKIP_UNKNOWN_EVENTS.get() => Ok(None),
+        EventPayload::Unknown(e) => Err(TriggerError::UnknownEventType(
+            "unknown event type `".to_string()
+                + &e.r#type
+                + "`. Enable `fmodel.skip_unknown_events` to skip the events of unknown types",
+        )),
+    }
+}
+
+/// Applies the event of the trigger to the projection.
+fn apply_trigger_event(
+    handler: ProjectionHandler,
+    event: &Event,
+    position: &EventPosition,
+) -> Result<(), TriggerError> {
+    handler(event, position).map_err(|err| {
+        if err.message.starts_with(OUT_OF_ORDER_EVENT_ERROR) {
+            TriggerError::StaleEvent(err.message)
+        } else {
+            TriggerError::RepositorySaveFailed(err.message)
+        }
+    })
+}
+
+/// Applies the event of the trigger to the projection rows of the (logical) store of the event.
+fn apply_trigger_event_in_store(
+    handler: ProjectionHandler,
+    event: &Event,
+    position: &EventPosition,
+    store: &str,
+) -> Result<(), TriggerError> {
+    in_store(store, || Ok(apply_trigger_event(handler, event, position)))
+        .map_err(|err| TriggerError::EventHandlingError(err.message))?
+}
+
+/// Event handler for the projections in the `statement` mode / Statement-level trigger function that applies all the events inserted by the statement (the `new_events` transition table), in one pass per projection.
+/// The bulk inserts (e.g. importing the events with `INSERT ... SELECT`) avoid the per-row trigger overhead.
+#[pg_trigger]
+fn handle_events_statement<'a>(
+    trigger: &'a PgTrigger<'a>,
+) -> Result<Option<PgHeapTuple<'a, AllocatedByRust>>, ErrorReport> {
+    let projector = OrderAndRestaurantProjector::new(OrderAndRestaurantProjectionRepository::new());
+    let mut handlers = Vec::new();
+    for (projection, handler) in PROJECTIONS {
+        if projector
+            .mode(projection)
+            .map_err(|err| TriggerError::EventHandlingError(err.message))?
+            == ProjectionMode::Statement
+        {
+            handlers.push(handler);
+        }
+    }
+    if handlers.is_empty() {
+        return Ok(None);
+    }
+    let mut events = Vec::new();
+    for (event, position, store) in fetch_inserted_events(trigger)
+        .map_err(|err| TriggerError::EventHandlingError(err.message))?
+    {
+        if let Some(event) = to_trigger_event(event)? {
+            events.push((event, position, store));
+        }
+    }
+    for handler in handlers {
+        for (event, position, store) in &events {
+            apply_trigger_event_in_store(handler, event, position, store)?;
+        }
+    }
+    Ok(None)
+}
+
+/// Event handler for Restaurant events / Trigger function that handles restaurant related events and updates the materialized view/table.
+#[pg_trigger]
+fn handle_restaurant_events<'a>(
+    trigger: &'a PgTrigger<'a>,
+) -> Result<Option<PgHeapTuple<'a, impl WhoAllocated>>, ErrorReport> {
+    let new = trigger
+        .new()
+        .ok_or(TriggerError::NullTriggerTuple)?
+        .into_owned();
+    handle_projection_trigger(RESTAURANT_PROJECTION, &new)?;
+    Ok(Some(new))
+}
+
+// Materialized view / Table for the Restaurant query side model
+// This table is updated by the trigger function / event handler `handle_restaurant_events`, or by the projector if the projection is in the `async` mode
+extension_sql!(
+    r#"
+    CREATE TABLE IF NOT EXISTS restaurants (
+                                           store_id TEXT NOT NULL DEFAULT fmodel_store_id(),
+                                           id UUID NOT NULL,
+                                           data JSONB,
+                                           last_event_id UUID,
+                                           last_offset BIGINT,
+                                           owner TEXT,
+                                           PRIMARY KEY (store_id, id)
+    );
+
+    -- Restaurant search projection (name, cuisine and the menu item names), updated together with the `restaurants` materialized view
+    CREATE TABLE IF NOT EXISTS restaurants_search (
+                                           store_id TEXT NOT NULL DEFAULT fmodel_store_id(),
+                                           id UUID NOT NULL,
+                                           name TEXT NOT NULL,
+                                           cuisine TEXT NOT NULL,
+                                           item_names TEXT NOT NULL,
+                                           document TSVECTOR GENERATED ALWAYS AS (
+                                               setweight(to_tsvector('simple', name), 'A') ||
+                                               setweight(to_tsvector('simple', cuisine), 'B') ||
+                                               setweight(to_tsvector('simple', item_names), 'C')
+                                           ) STORED,
+                                           PRIMARY KEY (store_id, id)
+    );
+    CREATE INDEX IF NOT EXISTS restaurants_search_document_index ON restaurants_search USING GIN (document);
+
+    INSERT INTO projections (projection) VALUES ('restaurants') ON CONFLICT DO NOTHING;
+
+    CREATE TRIGGER restaurant_event_handler_trigger AFTER INSERT ON events FOR EACH ROW EXECUTE PROCEDURE handle_restaurant_events();
+    "#,
+    name = "restaurant_event_handler_trigger",
+    requires = [handle_restaurant_events]
+);
+
+/// Event handler for Order events / Trigger function that handles order related events and updates the materialized view/table.
+#[pg_trigger]
+fn handle_order_events<'a>(
+    trigger: &'a PgTrigger<'a>,
+) -> Result<Option<PgHeapTuple<'a, impl WhoAllocated>>, ErrorReport> {
+    let new = trigger
+        .new()
+        .ok_or(TriggerError::NullTriggerTuple)?
+        .into_owned();
+    handle_projection_trigger(ORDER_PROJECTION, &new)?;
+    Ok(Some(new))
+}
+
+// Materialized view / Table for the Order query side model
+// This table is updated by the trigger function / event handler `handle_order_events`, or by the projector if the projection is in the `async` mode
+extension_sql!(
+    r#"
+    CREATE TABLE IF NOT EXISTS orders (
+                                           store_id TEXT NOT NULL DEFAULT fmodel_store_id(),
+                                           id UUID NOT NULL,
+                                           data JSONB,
+                                           last_event_id UUID,
+                                           last_offset BIGINT,
+                                           owner TEXT,
+                                           PRIMARY KEY (store_id, id)
+    );
+
+    -- Archive of the orders of the final streams (see `set_projection_archival`)
+    CREATE TABLE IF NOT EXISTS orders_archive (
+                                           store_id TEXT NOT NULL DEFAULT fmodel_store_id(),
+                                           id UUID NOT NULL,
+                                           data JSONB,
+                                           last_event_id UUID,
+                                           last_offset BIGINT,
+                                           archived_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+                                           owner TEXT,
+                                           PRIMARY KEY (store_id, id)
+    );
+
+    INSERT INTO projections (projection) VALUES ('orders') ON CONFLICT DO NOTHING;
+
+    CREATE TRIGGER order_event_handler_trigger AFTER INSERT ON events FOR EACH ROW EXECUTE PROCEDURE handle_order_events();
+    "#,
+    name = "order_event_handler_trigger",
+    requires = [handle_order_events]
+);
+
+/// Event handler for Order events / Trigger function that counts the orders per restaurant per day (aggregating view).
+#[pg_trigger]
+fn handle_restaurant_daily_orders_events<'a>(
+    trigger: &'a PgTrigger<'a>,
+) -> Result<Option<PgHeapTuple<'a, impl WhoAllocated>>, ErrorReport> {
+    let new = trigger
+        .new()
+        .ok_or(TriggerError::NullTriggerTuple)?
+        .into_owned();
+    handle_projection_trigger(RESTAURANT_DAILY_ORDERS_PROJECTION, &new)?;
+    Ok(Some(new))
+}
+
+// Aggregating (analytic) view / Table of the order counters per restaurant per day (UTC)
+// This table is updated by the trigger function / event handler `handle_restaurant_daily_orders_events`, or by the projector if the projection is in the `async` mode
+extension_sql!(
+    r#"
+    CREATE TABLE IF NOT EXISTS restaurant_daily_orders (
+                                           store_id TEXT NOT NULL DEFAULT fmodel_store_id(),
+                                           restaurant_id UUID NOT NULL,
+                                           day DATE NOT NULL,
+                                           orders BIGINT NOT NULL DEFAULT 0,
+                                           items BIGINT NOT NULL DEFAULT 0,
+                                           revenue BIGINT NOT NULL DEFAULT 0,
+                                           last_offset BIGINT NOT NULL,
+                                           PRIMARY KEY (store_id, restaurant_id, day)
+    );
+
+    INSERT INTO projections (projection) VALUES ('restaurant_daily_orders') ON CONFLICT DO NOTHING;
+
+    CREATE TRIGGER restaurant_daily_orders_event_handler_trigger AFTER INSERT ON events FOR EACH ROW EXECUTE PROCEDURE handle_restaurant_daily_orders_events();
+    "#,
+    name = "restaurant_daily_orders_event_handler_trigger",
+    requires = [handle_restaurant_daily_orders_events]
+);
+
+/// Event handler for both Restaurant and Order events / Trigger function that updates the restaurant order board (cross-domain view).
+#[pg_trigger]
+fn handle_restaurant_order_board_events<'a>(
+    trigger: &'a PgTrigger<'a>,
+) -> Result<Option<PgHeapTuple<'a, impl WhoAllocated>>, ErrorReport> {
+    let new = trigger
+        .new()
+        .ok_or(TriggerError::NullTriggerTuple)?
+        .into_owned();
+    handle_projection_trigger(RESTAURANT_ORDER_BOARD_PROJECTION, &new)?;
+    Ok(Some(new))
+}
+
+// Cross-domain materialized view / Table of the restaurants with the status of their orders
+// This table is updated by the trigger function / event handler `handle_restaurant_order_board_events` (for the events of both deciders), or by the projector if the projection is in the `async` mode
+extension_sql!(
+    r#"
+    CREATE TABLE IF NOT EXISTS restaurant_order_board (
+                                           store_id TEXT NOT NULL DEFAULT fmodel_store_id(),
+                                           id UUID NOT NULL,
+                                           data JSONB,
+                                           last_event_id UUID,
+                                           last_offset BIGINT,
+                                           PRIMARY KEY (store_id, id)
+    );
+    -- The board of the order is looked up by the order id (the legacy `OrderPrepared` events do not carry the restaurant id)
+    CREATE INDEX IF NOT EXISTS restaurant_order_board_orders_index ON restaurant_order_board USING GIN ((data -> 'orders') jsonb_path_ops);
+
+    INSERT INTO projections (projection) VALUES ('restaurant_order_board') ON CONFLICT DO NOTHING;
+
+    CREATE TRIGGER restaurant_order_board_event_handler_trigger AFTER INSERT ON events FOR EACH ROW EXECUTE PROCEDURE handle_restaurant_order_board_events();
+    "#,
+    name = "restaurant_order_board_event_handler_trigger",
+    requires = [handle_restaurant_order_board_events]
+);
+
+/// Event handler for Restaurant events / Trigger function that sums the captured and refunded amounts per order (aggregating view).
+#[pg_trigger]
+fn handle_payments_events<'a>(
+    trigger: &'a PgTrigger<'a>,
+) -> Result<Option<PgHeapTuple<'a, impl WhoAllocated>>, ErrorReport> {
+    let new = trigger
+        .new()
+        .ok_or(TriggerError::NullTriggerTuple)?
+        .into_owned();
+    handle_projection_trigger(PAYMENTS_PROJECTION, &new)?;
+    Ok(Some(new))
+}
+
+// Aggregating view / Table of the captured and refunded amounts per order
+// This table is updated by the trigger function / event handler `handle_payments_events`, or by the projector if the projection is in the `async` mode
+extension_sql!(
+    r#"
+    CREATE TABLE IF NOT EXISTS payments (
+                                           store_id TEXT NOT NULL DEFAULT fmodel_store_id(),
+                                           order_id UUID NOT NULL,
+                                           restaurant_id UUID NOT NULL,
+                                           captured BIGINT NOT NULL DEFAULT 0,
+                                           refunded BIGINT NOT NULL DEFAULT 0,
+                                           last_offset BIGINT NOT NULL,
+                                           PRIMARY KEY (store_id, order_id)
+    );
+
+    INSERT INTO projections (projection) VALUES ('payments') ON CONFLICT DO NOTHING;
+
+    CREATE TRIGGER payments_event_handler_trigger AFTER INSERT ON events FOR EACH ROW EXECUTE PROCEDURE handle_payments_events();
+    "#,
+    name = "payments_event_handler_trigger",
+    requires = [handle_payments_events]
+);
+
+/// Event handler for Order events / Trigger function that updates the groups of the (sub) orders split across the restaurants.
+#[pg_trigger]
+fn handle_order_group_events<'a>(
+    trigger: &'a PgTrigger<'a>,
+) -> Result<Option<PgHeapTuple<'a, impl WhoAllocated>>, ErrorReport> {
+    let new = trigger
+        .new()
+        .ok_or(TriggerError::NullTriggerTuple)?
+        .into_owned();
+    handle_projection_trigger(ORDER_GROUPS_PROJECTION, &new)?;
+    Ok(Some(new))
+}
+
+// Materialized view / Table of the order groups: the (sub) orders split across the restaurants, with the combined status
+// This table is updated by the trigger function / event handler `handle_order_group_events`, or by the projector if the projection is in the `async` mode
+extension_sql!(
+    r#"
+    CREATE TABLE IF NOT EXISTS order_groups (
+                                           store_id TEXT NOT NULL DEFAULT fmodel_store_id(),
+                                           id UUID NOT NULL,
+                                           data JSONB,
+                                           last_event_id UUID,
+                                           last_offset BIGINT,
+                                           PRIMARY KEY (store_id, id)
+    );
+    -- The group of the order is looked up by the order id (only `OrderCreated` carries the group id)
+    CREATE INDEX IF NOT EXISTS order_groups_orders_index ON order_groups USING GIN ((data -> 'orders') jsonb_path_ops);
+
+    INSERT INTO projections (projection) VALUES ('order_groups') ON CONFLICT DO NOTHING;
+
+    CREATE TRIGGER order_groups_event_handler_trigger AFTER INSERT ON events FOR EACH ROW EXECUTE PROCEDURE handle_order_group_events();
+    "#,
+    name = "order_groups_event_handler_trigger",
+    requires = [handle_order_group_events]
+);
+
+/// Event handler for Order events / Trigger function that updates the queues of the open orders of the restaurant kitchens.
+#[pg_trigger]
+fn handle_kitchen_queue_events<'a>(
+    trigger: &'a PgTrigger<'a>,
+) -> Result<Option<PgHeapTuple<'a, impl WhoAllocated>>, ErrorReport> {
+    let new = trigger
+        .new()
+        .ok_or(TriggerError::NullTriggerTuple)?
+        .into_owned();
+    handle_projection_trigger(KITCHEN_QUEUE_PROJECTION, &new)?;
+    Ok(Some(new))
+}
+
+// Materialized view / Table of the open orders per restaurant (the kitchen queue), with the time they were placed
+// This table is updated by the trigger function / event handler `handle_kitchen_queue_events`, or by the projector if the projection is in the `async` mode
+extension_sql!(
+    r#"
+    CREATE TABLE IF NOT EXISTS kitchen_queue (
+                                           store_id TEXT NOT NULL DEFAULT fmodel_store_id(),
+                                           order_id UUID NOT NULL,
+                                           restaurant_id UUID NOT NULL,
+                                           data JSONB,
+                                           placed_at TIMESTAMPTZ NOT NULL,
+                                           last_event_id UUID,
+                                           last_offset BIGINT,
+                                           PRIMARY KEY (store_id, order_id)
+    );
+    CREATE INDEX IF NOT EXISTS kitchen_queue_restaurant_index ON kitchen_queue (store_id, restaurant_id, placed_at);
+
+    INSERT INTO projections (projection) VALUES ('kitchen_queue') ON CONFLICT DO NOTHING;
+
+    CREATE TRIGGER kitchen_queue_event_handler_trigger AFTER INSERT ON events FOR EACH ROW EXECUTE PROCEDURE handle_kitchen_queue_events();
+    "#,
+    name = "kitchen_queue_event_handler_trigger",
+    requires = [handle_kitchen_queue_events]
+);
+
+// Statement-level trigger for the projections in the `statement` mode (see `set_projection_mode`)
+extension_sql!(
+    r#"
+    CREATE TRIGGER events_statement_handler_trigger AFTER INSERT ON events REFERENCING NEW TABLE AS new_events FOR EACH STATEMENT EXECUTE PROCEDURE handle_events_statement();
+    "#,
+    name = "events_statement_handler_trigger",
+    requires = [handle_events_statement]
+);
+
+// Typed views over the JSONB projections, for the clients/BI tools that can not (or should not) use the JSONB operators.
+// They are (re)created with every (re)install/upgrade of the extension, so they follow the view state schema (`RestaurantViewState`, `OrderViewState`).
+extension_sql!(
+    r#"
+    CREATE OR REPLACE VIEW restaurants_typed AS
+    SELECT id,
+           data ->> 'name'                                 AS name,
+           data -> 'menu' ->> 'cuisine'                    AS cuisine,
+           (data -> 'menu' ->> 'menu_id')::UUID            AS menu_id,
+           jsonb_array_length(data -> 'menu' -> 'items')   AS item_count,
+           last_offset,
+           store_id
+    FROM restaurants;
+
+    CREATE OR REPLACE VIEW orders_typed AS
+    SELECT id,
+           (data ->> 'restaurant_identifier')::UUID        AS restaurant_id,
+           data ->> 'status'                               AS status,
+           jsonb_array_length(data -> 'line_items')        AS line_item_count,
+           (SELECT COALESCE(SUM((item ->> 'quantity')::INT), 0)
+            FROM jsonb_array_elements(data -> 'line_items') AS item)::INT AS item_quantity,
+           (data ->> 'total')::BIGINT                      AS total,
+           last_offset,
+           store_id
+    FROM orders;
+    "#,
+    name = "typed_views",
+    requires = [
+        "restaurant_event_handler_trigger",
+        "order_event_handler_trigger"
+    ]
+);
+
+/// Switches the mode in which the projection is updated: `sync` (trigger), `async` (projector / background worker) or `statement` (statement-level trigger, for the bulk inserts).
+/// Switching to the `sync` mode applies all the pending events first. Switch the modes while there are no concurrent writers.
+#[pg_extern]
+fn set_projection_mode(projection: &str, mode: &str) -> Result<(), ErrorMessage> {
+    let projector = OrderAndRestaurantProjector::new(OrderAndRestaurantProjectionRepository::new());
+    projector.switch_mode(
+        projection,
+        mode.parse()?,
+        PROJECTOR_BATCH_SIZE.get() as i64,
+        projection_handler(projection)?,
+    )
+}
+
+/// Returns the consistency token of the session: the highest offset written by the commands handled in the session (`fmodel.consistency_token`), or NULL if none.
+/// Pass it to `await_projection` to read your own writes from the `async` projections.
+#[pg_extern]
+fn consistency_token() -> Result<Option<i64>, ErrorMessage> {
+    Spi::get_one::<i64>(
+        "SELECT NULLIF(current_setting('fmodel.consistency_token', TRUE), '')::BIGINT",
+    )
+    .map_err(|err| ErrorMessage::spi("fetch the consistency token", None, &err))
+}
+
+/// Waits (on the latch of the backend, with the backoff) until the projection has applied the events up to the consistency token (see `consistency_token()`), or the timeout elapses.
+/// Returns `false` if the timeout elapsed. The `sync`/`statement` projections are always up to date.
+/// Await in a `READ COMMITTED` transaction (the default), separate from the one that handled the commands: the progress of the projector is not visible otherwise.
+#[pg_extern]
+fn await_projection(projection: &str, token: i64, timeout_ms: i32) -> Result<bool, ErrorMessage> {
+    projection_handler(projection)?;
+    OrderAndRestaurantProjector::new(OrderAndRestaurantProjectionRepository::new()).await_offset(
+        projection,
+        token,
+        Duration::from_millis(timeout_ms.max(0) as u64),
+    )
+}
+
+/// Enables/disables the archival of the projection rows of the final streams, per projection.
+/// When enabled, the row is moved to the `<projection>_archive` table (e.g. `orders_archive`) once the final event of the stream is applied.
+#[pg_extern]
+fn set_projection_archival(projection: &str, archive_final: bool) -> Result<(), ErrorMessage> {
+    projection_handler(projection)?;
+    OrderAndRestaurantProjectionRepository::new().save_archival(projection, archive_final)
+}
+
+/// Enables/disables the row-level security of the projection (e.g. `orders`), keyed by the owner of the events that created the rows (`fmodel.owner`).
+/// When enabled, the application roles see only the rows of their owner, so the projections can be exposed to the applications directly.
+#[pg_extern]
+fn set_projection_row_security(projection: &str, enabled: bool) -> Result<(), ErrorMessage> {
+    projection_handler(projection)?;
+    OrderAndRestaurantProjectionRepository::new().save_row_security(projection, enabled)
+}
+
+/// Rewrites the rows of the view (e.g. `restaurants`) eagerly, with the documents upcasted to the current schema of the view state (see `infrastructure/view_state_upcasters.rs`).
+/// The read model evolves without the rebuild from scratch. Returns the number of the rewritten rows.
+#[pg_extern]
+fn migrate_view_rows(view: &str, batch_size: default!(i64, 1000)) -> Result<i64, ErrorMessage> {
+    framework::infrastructure::view_state_repository::migrate_view_rows(
+        view,
+        view_state_upcaster(view)?,
+        batch_size,
+    )
+}
+
+/// Applies the next batch of events past the checkpoint to the projection (in the `async` mode), and returns the number of events applied.
+/// The projector background worker does the same periodically; this function is useful when the worker is not running (e.g. `pg_cron`).
+#[pg_extern]
+fn run_projector(projection: &str, batch_size: default!(i64, 1000)) -> Result<i64, ErrorMessage> {
+    let projector = OrderAndRestaurantProjector::new(OrderAndRestaurantProjectionRepository::new());
+    if projector.mode(projection)? != ProjectionMode::Async {
+        return Err(ErrorMessage {
+            message: "Projection `".to_string() + projection + "` is not in the `async` mode",
+            context: None,
+        });
+    }
+    projector.project(projection, batch_size, projection_handler(projection)?)
+}
+
+/// Applies the next batch of events to every projection that is being rebuilt, and returns the progress of the rebuild.
+/// The rebuild of all projections is started if there is no unfinished rebuild, otherwise the unfinished rebuild is resumed.
+/// Use the `rebuild_all_views` procedure to rebuild the projections completely, committing after every batch.
+#[pg_extern]
+fn rebuild_all_views_step(
+    batch_size: default!(i64, 1000),
+) -> Result<
+    TableIterator<
+        'static,
+        (
+            name!(projection, String),
+            name!(events_applied, i64),
+            name!(target_offset, i64),
+            name!(finished, bool),
+        ),
+    >,
+    ErrorMessage,
+> {
+    rebuild_projections(batch_size).map(|progress| {
+        TableIterator::new(progress.into_iter().filter_map(|(projection, rebuild)| {
+            rebuild.map(|r| {
+                (
+                    projection.to_string(),
+                    r.events_applied,
+                    r.target_offset,
+                    r.finished,
+                )
+            })
+        }))
+    })
+}
+
+/// Applies the next batch of the historical events to the (newly registered) projection only, and returns the progress of the backfill.
+/// The backfill is started if there is no unfinished backfill of the projection, otherwise it is resumed; once finished, the projection is switched back to its live mode.
+/// Use the `backfill_projection` procedure to backfill the projection completely, committing after every batch.
+#[pg_extern]
+fn backfill_projection_step(
+    projection: &str,
+    batch_size: default!(i64, 1000),
+) -> Result<
+    TableIterator<
+        'static,
+        (
+            name!(events_applied, i64),
+            name!(target_offset, i64),
+            name!(finished, bool),
+        ),
+    >,
+    ErrorMessage,
+> {
+    backfill_projection(projection, batch_size)
+        .map(|r| TableIterator::new(vec![(r.events_applied, r.target_offset, r.finished)]))
+}
+
+// Backfills the (newly registered) projection from the historical events, in batches, committing after every batch (the progress is stored in the `projection_rebuilds` table).
+// If interrupted, calling it again resumes the unfinished backfill.
+extension_sql!(
+    r#"
+    CREATE OR REPLACE PROCEDURE backfill_projection(name TEXT, batch_size INT DEFAULT 1000)
+        LANGUAGE plpgsql AS
+    '
+        DECLARE
+            unfinished BOOLEAN := TRUE;
+        BEGIN
+            WHILE unfinished LOOP
+                SELECT NOT finished INTO unfinished FROM backfill_projection_step(name, batch_size);
+                COMMIT;
+            END LOOP;
+        END;
+    ';
+    "#,
+    name = "backfill_projection",
+    requires = [backfill_projection_step]
+);
+
+// Rebuilds all the projections from scratch, in batches, committing after every batch (the progress is stored in the `projection_rebuilds` table).
+// If interrupted, calling it again resumes the unfinished rebuild.
+extension_sql!(
+    r#"
+    CREATE OR REPLACE PROCEDURE rebuild_all_views(batch_size INT DEFAULT 1000)
+        LANGUAGE plpgsql AS
+    '
+        DECLARE
+            unfinished BOOLEAN := TRUE;
+        BEGIN
+            WHILE unfinished LOOP
+                SELECT COALESCE(bool_or(NOT finished), FALSE) INTO unfinished FROM rebuild_all_views_step(batch_size);
+                COMMIT;
+            END LOOP;
+        END;
+    ';
+    "#,
+    name = "rebuild_all_views",
+    requires = [rebuild_all_views_step]
+);
+
+/// Applies the pending schema migrations (idempotently), and returns the schema version.
+/// The upgrade scripts (`ALTER EXTENSION fmodel_rust_postgres UPDATE`) call it to evolve the schema of the populated stores.
+#[pg_extern]
+fn fmodel_migrate() -> Result<i32, ErrorMessage> {
+    framework::infrastructure::migrations::migrate(&infrastructure::migrations::MIGRATIONS)
+}
+
+/// Provisions the event store into the schema (created if it does not exist), and into the tablespace (if given), and returns the schema version.
+/// Re-running it is idempotent: the event store already provisioned in the schema is only migrated (see `fmodel_migrate`).
+/// The projections of the extension are copied to the new event store; set the `search_path` to the schema to use it.
+/// Superuser only.
+#[pg_extern]
+fn fmodel_init(
+    schema: &str,
+    tablespace: default!(Option<&str>, "NULL"),
+) -> Result<i32, ErrorMessage> {
+    framework::infrastructure::require_superuser("provision the event store")?;
+    framework::infrastructure::provisioning::provision(
+        schema,
+        tablespace,
+        include_str!("../sql/event_sourcing.sql"),
+        &infrastructure::migrations::MIGRATIONS,
+        &infrastructure::schema::PROJECTION_TABLES,
+    )
+}
+
+/// Returns the schema version: the version of the latest applied migration.
+#[pg_extern(stable, parallel_safe)]
+fn fmodel_schema_version() -> Result<i32, ErrorMessage> {
+    framework::infrastructure::migrations::fetch_schema_version()
+}
+
+// Records the migrations as applied on the fresh install (the migrations are idempotent, the fresh install has the latest schema already).
+extension_sql!(
+    r#"
+    SELECT fmodel_migrate();
+    "#,
+    name = "migrate",
+    finalize
+);
+
+/// (Re)creates the `fmodel_event_stream` view: the events with the commonly filtered columns (restaurant id, order id, ...) decoded from the JSONB payload.
+/// Call it after upgrading the extension, to regenerate the view for the current events.
+#[pg_extern]
+fn fmodel_create_event_stream_view() -> Result<(), ErrorMessage> {
+    framework::infrastructure::event_stream::create_event_stream_view(
+        &infrastructure::event_stream::EVENT_STREAM_COLUMNS,
+    )
+}
+
+// The event stream view, for the analysts querying the events without knowing the serde layout of the payloads.
+extension_sql!(
+    r#"
+    SELECT fmodel_create_event_stream_view();
+    "#,
+    name = "event_stream_view",
+    requires = [fmodel_create_event_stream_view]
+);
+
+/// (Re)creates the check of the events inserted into the `events` table: the payload `type` must match the `event` column, and the payload must have the required fields of its event type (derived from the JSON Schema of the events, see `infrastructure/event_schema.rs`).
+/// The rows inserted by other tooling (e.g. the migrations and the manual fixes) can not violate what the deserializer of the events expects. Call it after upgrading the extension.
+#[pg_extern]
+fn fmodel_create_event_schema_check() -> Result<(), ErrorMessage> {
+    framework::infrastructure::event_schema::create_event_schema_check(
+        &infrastructure::event_schema::event_schemas(),
+    )
+}
+
+// The schema check of the inserted events, generated from the required fields of the events.
+extension_sql!(
+    r#"
+    SELECT fmodel_create_event_schema_check();
+    "#,
+    name = "event_schema_check",
+    requires = [fmodel_create_event_schema_check]
+);
+
+/// Checks the indexes recommended for the (large) event stores, and creates the missing ones (if `create_missing`).
+/// Returns the status of every recommended index: `present`, `created` or `missing`.
+#[pg_extern]
+fn fmodel_index_advisory(
+    create_missing: default!(bool, true),
+) -> Result<
+    TableIterator<
+        'static,
+        (
+            name!(index_name, String),
+            name!(definition, String),
+            name!(status, String),
+        ),
+    >,
+    ErrorMessage,
+> {
+    framework::infrastructure::indexes::advise_indexes(
+        &framework::infrastructure::indexes::RECOMMENDED_INDEXES,
+        create_missing,
+    )
+    .map(TableIterator::new)
+}
+
+/// Reports the storage of the extension tables (and their partitions, e.g. `events_restaurant`): the table, index and TOAST sizes (in bytes), the estimated live and dead tuples, the share of the partition in its partitioned table, and the recommendations (e.g. `45% bloat; consider pg_repack`).
+/// Ordered by the total size. The tuple counts are the estimates of the statistics collector: run ANALYZE for the fresh numbers.
+#[pg_extern]
+fn fmodel_storage_report() -> Result<
+    TableIterator<
+        'static,
+        (
+            name!(relation, String),
+            name!(parent, Option<String>),
+            name!(table_bytes, i64),
+            name!(index_bytes, i64),
+            name!(toast_bytes, i64),
+            name!(live_tuples, i64),
+            name!(dead_tuples, i64),
+            name!(dead_ratio, f64),
+            name!(partition_share, Option<f64>),
+            name!(recommendation, Option<String>),
+        ),
+    >,
+    ErrorMessage,
+> {
+    framework::infrastructure::storage_report::fetch_storage_stats().map(|stats| {
+        TableIterator::new(stats.into_iter().map(|stats| {
+            let recommendations = stats.recommendations();
+            (
+                stats.relation.clone(),
+                stats.parent.clone(),
+                stats.table_bytes,
+                stats.index_bytes,
+                stats.toast_bytes,
+                stats.live_tuples,
+                stats.dead_tuples,
+                stats.dead_ratio(),
+                stats.partition_share,
+                (!recommendations.is_empty()).then(|| recommendations.join("; ")),
+            )
+        }))
+    })
+}
+
+/// Full-text search over the restaurants (name, cuisine and the menu item names): every word of the query is matched as a prefix. Ordered by the rank of the match.
+#[pg_extern(stable, parallel_safe)]
+fn search_restaurants(
+    query: &str,
+    max_results: default!(i64, 20),
+) -> Result<
+    TableIterator<
+        'static,
+        (
+            name!(id, Uuid),
+            name!(name, String),
+            name!(cuisine, String),
+            name!(rank, f32),
+        ),
+    >,
+    ErrorMessage,
+> {
+    RestaurantSearchRepository::new()
+        .search(query, max_results)
+        .map(TableIterator::new)
+}
+
+/// Returns (up to `limit`) open orders of the restaurant from the kitchen queue, the longest waiting first: the line items, the time the order was placed, and the time elapsed since.
+#[pg_extern(stable, parallel_safe)]
+fn next_orders(
+    restaurant_id: Uuid,
+    limit: default!(i64, 10),
+) -> Result<
+    TableIterator<
+        'static,
+        (
+            name!(order_id, Uuid),
+            name!(line_items, JsonB),
+            name!(placed_at, TimestampWithTimeZone),
+            name!(elapsed, Interval),
+        ),
+    >,
+    ErrorMessage,
+> {
+    KitchenQueueRepository::new()
+        .fetch_next_orders(
+            &RestaurantId(uuid::Uuid::from_bytes(*restaurant_id.as_bytes())),
+            limit,
+        )
+        .map(TableIterator::new)
+}
+
+/// Claims (up to `limit`) customer notifications from the outbox, in the order they were written, for the relay of the push-notification service: the claimed notifications are marked as relayed.
+/// The concurrent relays claim the distinct notifications; the claim is undone if the transaction of the relay is rolled back (e.g. the push failed).
+#[pg_extern]
+fn claim_notifications(
+    limit: default!(i64, 100),
+) -> Result<
+    TableIterator<
+        'static,
+        (
+            name!(id, i64),
+            name!(customer_id, Uuid),
+            name!(template, String),
+            name!(payload, JsonB),
+            name!(created_at, TimestampWithTimeZone),
+        ),
+    >,
+    ErrorMessage,
+> {
+    NotificationOutboxRepository::new()
+        .claim(limit)
+        .map(TableIterator::new)
+}
+
+/// Returns (up to `limit`) last locations of the courier from the ring buffer (`fmodel.courier_location_policy` = `buffer`), the most recent first: the latitude and the longitude (in degrees), and the time it was reported.
+#[pg_extern(stable, parallel_safe)]
+fn courier_track(
+    courier_id: Uuid,
+    limit: default!(i64, 100),
+) -> Result<
+    TableIterator<
+        'static,
+        (
+            name!(latitude, f64),
+            name!(longitude, f64),
+            name!(recorded_at, TimestampWithTimeZone),
+        ),
+    >,
+    ErrorMessage,
+> {
+    CourierLocationRepository::new()
+        .fetch_track(
+            &CourierId(uuid::Uuid::from_bytes(*courier_id.as_bytes())),
+            limit,
+        )
+        .map(TableIterator::new)
+}
+
+/// Returns the offset of the last event applied to the restaurant view/projection, or NULL if the restaurant is not (yet) projected.
+#[pg_extern(stable, parallel_safe)]
+fn restaurant_view_version(id: Uuid) -> Result<Option<i64>, ErrorMessage> {
+    RestaurantViewStateRepository::new().fetch_version(&uuid::Uuid::from_bytes(*id.as_bytes()))
+}
+
+/// Returns the offset of the last event applied to the order view/projection, or NULL if the order is not (yet) projected.
+#[pg_extern(stable, parallel_safe)]
+fn order_view_version(id: Uuid) -> Result<Option<i64>, ErrorMessage> {
+    OrderViewStateRepository::new().fetch_version(&uuid::Uuid::from_bytes(*id.as_bytes()))
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pg_schema]
+mod tests {
+    // Test data: RestaurantCreated (`Pljeska`, the restaurant with the menu)
+    extension_sql!(
+        r#"
+    SELECT load_fixture('restaurant_with_menu');
+    "#,
+        name = "data_insert",
+        requires = [
+            load_fixture,
+            "restaurant_event_handler_trigger",
+            "order_event_handler_trigger",
+            "restaurant_order_board_event_handler_trigger"
+        ]
+    );
+    use crate::domain::api::{
+        AppliedDiscount, CreatePromotion, DiscountPercentage, ExpirePromotion, PromotionId,
+        PromotionName,
+    };
+    use crate::domain::api::{
+        CancelRestaurantOrder, CustomerId, InventoryId, RestockInventory, StockItem, StockQuantity,
+    };
+    use crate::domain::api::{
+        ChangeRestaurantMenu, ConvertedMoney, CreateOrder, CreateRestaurant, Currency,
+        ExchangeRate, MarkOrderAsPrepared, OpeningHours, OpeningPeriod, OrderCreated,
+        OrderLineItem, OrderPlaced, PlaceOrder, RestaurantCreated, RestaurantEvent,
+        RestaurantMenuChanged, RestaurantOpeningHoursChanged,
+    };
+    use crate::domain::api::{
+        MenuId, MenuItem, MenuItemId, MenuItemName, Money, OrderGroupId, OrderId, OrderLineItemId,
+        OrderLineItemQuantity, OrderStatus, RestaurantId, RestaurantMenu, RestaurantMenuCuisine,
+        RestaurantMenuVersion, RestaurantName,
+    };
+    use crate::domain::exchange_rates::{ExchangeRates, FixedExchangeRates};
+    use crate::domain::restaurant_decider::restaurant_decider;
+    use crate::domain::stock_levels::FixedStockLevels;
+    use crate::domain::{Command, Event};
+    use crate::framework::application::hooks::HookRegistry;
+    use crate::framework::domain::clock::{FixedClock, LocalTime};
+    use crate::framework::infrastructure::command_stats::CommandStats;
+    use crate::framework::infrastructure::errors::ErrorMessage;
+    use crate::framework::infrastructure::event_repository::EventPosition;
+    use crate::framework::infrastructure::rate_limiter::TokenBucket;
+    use crate::framework::infrastructure::storage_report::StorageStats;
+    use crate::framework::infrastructure::{
+        to_command_side_event, to_event, to_known_event, EventPayload, UnknownEvent,
+    };
+    use pgrx::prelude::*;
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    #[pg_test]
+    fn create_restaurant_test() {
+        let restaurant_identifier =
+            RestaurantId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708208").unwrap());
+        let restaurant_name = RestaurantName("Test Restaurant".to_string());
+        let menu_item_id =
+            MenuItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
+        let menu_id = MenuId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
+        let menu_items = vec![MenuItem {
+            id: menu_item_id,
+            name: MenuItemName("Item 1".to_string()),
+            price: Money(100u64),
+        }];
+
+        let create_restaurant_command = Command::CreateRestaurant(CreateRestaurant {
+            identifier: restaurant_identifier.clone(),
+            name: restaurant_name.clone(),
+            menu: RestaurantMenu {
+                menu_id: menu_id.clone(),
+                items: menu_items.clone(),
+                cuisine: RestaurantMenuCuisine::Vietnamese,
+                currency: None,
+            },
+        });
+
+        let restaurant_created_event = Event::RestaurantCreated(RestaurantCreated {
+            identifier: restaurant_identifier.clone(),
+            name: restaurant_name.clone(),
+            menu: RestaurantMenu {
+                menu_id: menu_id.clone(),
+                items: menu_items.clone(),
+                cuisine: RestaurantMenuCuisine::Vietnamese,
+                currency: None,
+            },
+            r#final: false,
+        });
+
+        assert_eq!(
+            Some(restaurant_created_event.clone()),
+            crate::handle(create_restaurant_command, None)
+                .unwrap()
+                .into_iter()
+                .next()
+        );
+    }
+
+    #[pg_test(error = "Failed to create the Restaurant. Restaurant already exists!")]
+    fn create_restaurant_error_test() {
+        let restaurant_identifier =
+            RestaurantId(Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap());
+        let restaurant_name = RestaurantName("Test Restaurant".to_string());
+        let menu_item_id =
+            MenuItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
+        let menu_id = MenuId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
+        let menu_items = vec![MenuItem {
+            id: menu_item_id,
+            name: MenuItemName("Item 1".to_string()),
+            price: Money(100u64),
+        }];
+
+        let create_restaurant_command = Command::CreateRestaurant(CreateRestaurant {
+            identifier: restaurant_identifier.clone(),
+            name: restaurant_name.clone(),
+            menu: RestaurantMenu {
+                menu_id: menu_id.clone(),
+                items: menu_items.clone(),
+                cuisine: RestaurantMenuCuisine::Vietnamese,
+                currency: None,
+            },
+        });
+
+        let _ = crate::handle(create_restaurant_command, None);
+    }
+
+    #[pg_test]
+    fn change_menu_test() {
+        let restaurant_identifier =
+            RestaurantId(Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap());
+        let menu_item_id =
+            MenuItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
+        let menu_id = MenuId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
+        let menu_items = vec![MenuItem {
+            id: menu_item_id,
+            name: MenuItemName("Item 1".to_string()),
+            price: Money(100u64),
+        }];
+
+        let change_restaurant_menu = Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
+            identifier: restaurant_identifier.clone(),
+            menu: RestaurantMenu {
+                menu_id: menu_id.clone(),
+                items: menu_items.clone(),
+                cuisine: RestaurantMenuCuisine::Vietnamese,
+                currency: None,
+            },
+        });
 
         let restaurant_menu_changed_event = Event::RestaurantMenuChanged(RestaurantMenuChanged {
             identifier: restaurant_identifier.clone(),
             menu: RestaurantMenu {
-                menu_id: menu_id.clone(),
-                items: menu_items.clone(),
+                menu_id: menu_id.clone(),
+                items: menu_items.clone(),
+                cuisine: RestaurantMenuCuisine::Vietnamese,
+                currency: None,
+            },
+            menu_version: RestaurantMenuVersion(2),
+            r#final: false,
+        });
+
+        assert_eq!(
+            Some(restaurant_menu_changed_event.clone()),
+            crate::handle(change_restaurant_menu, None)
+                .unwrap()
+                .into_iter()
+                .next()
+        );
+    }
+
+    #[pg_test(error = "Failed to change the menu. Restaurant does not exist!")]
+    fn change_menu_error_test() {
+        let restaurant_identifier =
+            RestaurantId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708208").unwrap());
+        let menu_item_id =
+            MenuItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
+        let menu_id = MenuId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
+        let menu_items = vec![MenuItem {
+            id: menu_item_id,
+            name: MenuItemName("Item 1".to_string()),
+            price: Money(100u64),
+        }];
+
+        let change_restaurant_menu = Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
+            identifier: restaurant_identifier.clone(),
+            menu: RestaurantMenu {
+                menu_id: menu_id.clone(),
+                items: menu_items.clone(),
+                cuisine: RestaurantMenuCuisine::Vietnamese,
+                currency: None,
+            },
+        });
+
+        let _ = crate::handle(change_restaurant_menu, None);
+    }
+
+    #[pg_test]
+    fn place_order_test() {
+        let restaurant_identifier =
+            RestaurantId(Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap());
+        let order_identifier =
+            OrderId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
+        let menu_item_id =
+            MenuItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
+        let line_items = vec![OrderLineItem {
+            id: OrderLineItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap()),
+            quantity: OrderLineItemQuantity(1),
+            menu_item_id: menu_item_id.clone(),
+            name: MenuItemName("Item 1".to_string()),
+            price: None,
+            restaurant_identifier: None,
+        }];
+
+        let place_order = Command::PlaceOrder(PlaceOrder {
+            identifier: restaurant_identifier.clone(),
+            order_identifier: order_identifier.clone(),
+            line_items: line_items.clone(),
+            group_identifier: None,
+            customer_identifier: None,
+        });
+
+        // The unit price is captured from the restaurant menu, at the time the order is placed
+        let line_items = vec![OrderLineItem {
+            price: Some(Money(10u64)),
+            ..line_items[0].clone()
+        }];
+        let order_placed_event = Event::OrderPlaced(OrderPlaced {
+            identifier: restaurant_identifier.clone(),
+            order_identifier: order_identifier.clone(),
+            line_items: line_items.clone(),
+            currency: None,
+            discounts: vec![],
+            group_identifier: None,
+            customer_identifier: None,
+            r#final: false,
+        });
+
+        let order_created_event = Event::OrderCreated(OrderCreated {
+            identifier: order_identifier.clone(),
+            restaurant_identifier: restaurant_identifier.clone(),
+            status: OrderStatus::Created,
+            line_items: line_items.clone(),
+            reference_total: None,
+            discounts: vec![],
+            group_identifier: None,
+            customer_identifier: None,
+            r#final: false,
+        });
+
+        let mut result = crate::handle(place_order, None).unwrap().into_iter();
+        assert_eq!(Some(order_placed_event), result.next(),);
+        assert_eq!(Some(order_created_event), result.next(),);
+    }
+
+    #[pg_test(error = "Failed to place the order. Restaurant does not exist!")]
+    fn place_order_error_test() {
+        let restaurant_identifier =
+            RestaurantId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708208").unwrap());
+        let order_identifier =
+            OrderId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
+        let menu_item_id =
+            MenuItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
+        let line_items = vec![OrderLineItem {
+            id: OrderLineItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap()),
+            quantity: OrderLineItemQuantity(1),
+            menu_item_id: menu_item_id.clone(),
+            name: MenuItemName("Item 1".to_string()),
+            price: None,
+            restaurant_identifier: None,
+        }];
+
+        let place_order = Command::PlaceOrder(PlaceOrder {
+            identifier: restaurant_identifier.clone(),
+            order_identifier: order_identifier.clone(),
+            line_items: line_items.clone(),
+            group_identifier: None,
+            customer_identifier: None,
+        });
+
+        let _ = crate::handle(place_order, None);
+    }
+
+    #[pg_test]
+    fn create_restaurant_and_place_order_test() {
+        let restaurant_identifier =
+            RestaurantId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708208").unwrap());
+        let order_identifier =
+            OrderId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
+        let restaurant_name = RestaurantName("Test Restaurant".to_string());
+        let menu_item_id =
+            MenuItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
+        let menu_id = MenuId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
+        let menu_items = vec![MenuItem {
+            id: menu_item_id.clone(),
+            name: MenuItemName("Item 1".to_string()),
+            price: Money(100u64),
+        }];
+        let line_items = vec![OrderLineItem {
+            id: OrderLineItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap()),
+            quantity: OrderLineItemQuantity(1),
+            menu_item_id: menu_item_id.clone(),
+            name: MenuItemName("Item 1".to_string()),
+            price: None,
+            restaurant_identifier: None,
+        }];
+
+        let create_restaurant_command = Command::CreateRestaurant(CreateRestaurant {
+            identifier: restaurant_identifier.clone(),
+            name: restaurant_name.clone(),
+            menu: RestaurantMenu {
+                menu_id: menu_id.clone(),
+                items: menu_items.clone(),
+                cuisine: RestaurantMenuCuisine::Vietnamese,
+                currency: None,
+            },
+        });
+
+        let place_order = Command::PlaceOrder(PlaceOrder {
+            identifier: restaurant_identifier.clone(),
+            order_identifier: order_identifier.clone(),
+            line_items: line_items.clone(),
+            group_identifier: None,
+            customer_identifier: None,
+        });
+
+        let restaurant_created_event = Event::RestaurantCreated(RestaurantCreated {
+            identifier: restaurant_identifier.clone(),
+            name: restaurant_name.clone(),
+            menu: RestaurantMenu {
+                menu_id: menu_id.clone(),
+                items: menu_items.clone(),
+                cuisine: RestaurantMenuCuisine::Vietnamese,
+                currency: None,
+            },
+            r#final: false,
+        });
+
+        // The unit price is captured from the restaurant menu, at the time the order is placed
+        let line_items = vec![OrderLineItem {
+            price: Some(Money(100u64)),
+            ..line_items[0].clone()
+        }];
+        let order_placed_event = Event::OrderPlaced(OrderPlaced {
+            identifier: restaurant_identifier.clone(),
+            order_identifier: order_identifier.clone(),
+            line_items: line_items.clone(),
+            currency: None,
+            discounts: vec![],
+            group_identifier: None,
+            customer_identifier: None,
+            r#final: false,
+        });
+
+        let order_created_event = Event::OrderCreated(OrderCreated {
+            identifier: order_identifier.clone(),
+            restaurant_identifier: restaurant_identifier.clone(),
+            status: OrderStatus::Created,
+            line_items: line_items.clone(),
+            reference_total: None,
+            discounts: vec![],
+            group_identifier: None,
+            customer_identifier: None,
+            r#final: false,
+        });
+
+        let mut result = crate::handle_all(vec![create_restaurant_command, place_order], None)
+            .unwrap()
+            .into_iter();
+        assert_eq!(Some(restaurant_created_event), result.next(),);
+        assert_eq!(Some(order_placed_event), result.next(),);
+        assert_eq!(Some(order_created_event), result.next(),);
+    }
+
+    #[pg_test]
+    fn handle_all_atomically_test() {
+        let menu = RestaurantMenu {
+            menu_id: MenuId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap()),
+            items: vec![MenuItem {
+                id: MenuItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap()),
+                name: MenuItemName("Item 1".to_string()),
+                price: Money(100u64),
+            }],
+            cuisine: RestaurantMenuCuisine::Vietnamese,
+            currency: None,
+        };
+        let restaurant_a =
+            RestaurantId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708208").unwrap());
+        let restaurant_b =
+            RestaurantId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708209").unwrap());
+
+        let result: Vec<_> = crate::handle_all_atomically(
+            vec![
+                Command::CreateRestaurant(CreateRestaurant {
+                    identifier: restaurant_a.clone(),
+                    name: RestaurantName("A".to_string()),
+                    menu: menu.clone(),
+                }),
+                Command::CreateRestaurant(CreateRestaurant {
+                    identifier: restaurant_b.clone(),
+                    name: RestaurantName("B".to_string()),
+                    menu: menu.clone(),
+                }),
+            ],
+            None,
+        )
+        .unwrap()
+        .collect();
+
+        assert_eq!(
+            vec![
+                pgrx::Uuid::from_bytes(restaurant_a.0.into_bytes()),
+                pgrx::Uuid::from_bytes(restaurant_b.0.into_bytes())
+            ],
+            result.iter().map(|(id, _)| *id).collect::<Vec<_>>()
+        );
+        assert!(result.iter().all(|(_, events)| events.len() == 1));
+        // The streams stay locked until the end of the transaction
+        assert_eq!(
+            Ok(Some(2)),
+            Spi::get_one::<i64>(
+                "SELECT count(*) FROM pg_locks WHERE locktype = 'advisory' AND pid = pg_backend_pid() AND classid::INT = hashtext('fmodel_streams') AND mode = 'ExclusiveLock'"
+            )
+        );
+    }
+
+    #[pg_test]
+    fn command_batch_test() {
+        let restaurant_identifier =
+            RestaurantId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708208").unwrap());
+        let order_identifier =
+            OrderId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
+        let menu_item_id =
+            MenuItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
+
+        crate::fmodel_begin_batch().unwrap();
+        crate::fmodel_queue(Command::CreateRestaurant(CreateRestaurant {
+            identifier: restaurant_identifier.clone(),
+            name: RestaurantName("Test Restaurant".to_string()),
+            menu: RestaurantMenu {
+                menu_id: MenuId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap()),
+                items: vec![MenuItem {
+                    id: menu_item_id.clone(),
+                    name: MenuItemName("Item 1".to_string()),
+                    price: Money(100u64),
+                }],
+                cuisine: RestaurantMenuCuisine::Vietnamese,
+                currency: None,
+            },
+        }))
+        .unwrap();
+        let queued = crate::fmodel_queue(Command::PlaceOrder(PlaceOrder {
+            identifier: restaurant_identifier,
+            order_identifier,
+            line_items: vec![OrderLineItem {
+                id: OrderLineItemId(
+                    Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                ),
+                quantity: OrderLineItemQuantity(1),
+                menu_item_id,
+                name: MenuItemName("Item 1".to_string()),
+                price: None,
+                restaurant_identifier: None,
+            }],
+            group_identifier: None,
+            customer_identifier: None,
+        }))
+        .unwrap();
+
+        assert_eq!(2, queued);
+        assert_eq!(3, crate::fmodel_execute_batch().unwrap().len());
+        assert!(crate::fmodel_execute_batch().is_err());
+    }
+
+    #[pg_test]
+    fn restaurant_view_version_test() {
+        let restaurant_identifier =
+            RestaurantId(Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap());
+        let menu_item_id =
+            MenuItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
+        let menu_id = MenuId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
+
+        let change_restaurant_menu = Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
+            identifier: restaurant_identifier.clone(),
+            menu: RestaurantMenu {
+                menu_id,
+                items: vec![MenuItem {
+                    id: menu_item_id,
+                    name: MenuItemName("Item 1".to_string()),
+                    price: Money(100u64),
+                }],
+                cuisine: RestaurantMenuCuisine::Vietnamese,
+                currency: None,
+            },
+        });
+
+        let (_, _, offset, _, _) = crate::handle_with_offsets(change_restaurant_menu, None)
+            .unwrap()
+            .last()
+            .unwrap();
+        assert_eq!(
+            Some(offset),
+            crate::restaurant_view_version(pgrx::Uuid::from_bytes(
+                restaurant_identifier.0.into_bytes()
+            ))
+            .unwrap()
+        );
+    }
+
+    #[pg_test]
+    fn view_state_patch_test() {
+        let restaurant_identifier =
+            RestaurantId(Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap());
+        let menu = RestaurantMenu {
+            menu_id: MenuId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap()),
+            items: vec![],
+            cuisine: RestaurantMenuCuisine::Greek,
+            currency: None,
+        };
+        crate::handle(
+            Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
+                identifier: restaurant_identifier,
+                menu: menu.clone(),
+            }),
+            None,
+        )
+        .unwrap();
+
+        // The menu is patched, the rest of the view state is kept
+        assert_eq!(
+            Some(serde_json::to_value(&menu).unwrap()),
+            Spi::get_one::<JsonB>(
+                "SELECT data->'menu' FROM restaurants WHERE id = 'e48d4d9e-403e-453f-b1ba-328e0ce23737'"
+            )
+            .unwrap()
+            .map(|data| data.0)
+        );
+        assert_eq!(
+            Some("Pljeska".to_string()),
+            Spi::get_one::<String>(
+                "SELECT data->>'name' FROM restaurants WHERE id = 'e48d4d9e-403e-453f-b1ba-328e0ce23737'"
+            )
+            .unwrap()
+        );
+    }
+
+    #[pg_test]
+    fn async_projection_test() {
+        let restaurant_identifier =
+            RestaurantId(Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap());
+        let menu_item_id =
+            MenuItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
+        let menu_id = MenuId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
+
+        let change_restaurant_menu = Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
+            identifier: restaurant_identifier.clone(),
+            menu: RestaurantMenu {
+                menu_id,
+                items: vec![MenuItem {
+                    id: menu_item_id,
+                    name: MenuItemName("Item 1".to_string()),
+                    price: Money(100u64),
+                }],
+                cuisine: RestaurantMenuCuisine::Vietnamese,
+                currency: None,
+            },
+        });
+
+        crate::set_projection_mode("restaurants", "async").unwrap();
+        let (_, _, offset, _, _) = crate::handle_with_offsets(change_restaurant_menu, None)
+            .unwrap()
+            .last()
+            .unwrap();
+        let restaurant_id = pgrx::Uuid::from_bytes(restaurant_identifier.0.into_bytes());
+        assert_ne!(
+            Some(offset),
+            crate::restaurant_view_version(restaurant_id).unwrap()
+        );
+        assert_eq!(1, crate::run_projector("restaurants", 1000).unwrap());
+        assert_eq!(
+            Some(offset),
+            crate::restaurant_view_version(restaurant_id).unwrap()
+        );
+    }
+
+    #[pg_test]
+    fn unknown_events_projection_test() {
+        Spi::run("SET fmodel.skip_unknown_events = on").unwrap();
+        crate::set_projection_mode("restaurants", "async").unwrap();
+        // The batch of the events of an unknown type only (e.g. appended by the newer version of the model)
+        Spi::run(
+            r#"INSERT INTO deciders (decider, event) VALUES ('Restaurant', 'RestaurantClosed');
+               INSERT INTO events (event, event_id, decider, decider_id, data, command_id, previous_id, final)
+               VALUES ('RestaurantClosed', 'd4b8fad7-bc4e-4031-8d85-6f7e8091a2b3', 'Restaurant', '3a6f5e4d-3c2b-4a1f-9e0d-9c8b7a6f5e4d',
+                       '{"type": "RestaurantClosed", "identifier": "3a6f5e4d-3c2b-4a1f-9e0d-9c8b7a6f5e4d", "final": false}',
+                       '3a6f5e4d-3c2b-4a1f-9e0d-9c8b7a6f5e4d', NULL, FALSE),
+                      ('RestaurantClosed', 'e5c9abe8-cd5f-4142-9e96-7a8f91a2b3c4', 'Restaurant', '4b7a6f5e-4d3c-4b2a-8f1e-0d9c8b7a6f5e',
+                       '{"type": "RestaurantClosed", "identifier": "4b7a6f5e-4d3c-4b2a-8f1e-0d9c8b7a6f5e", "final": false}',
+                       '4b7a6f5e-4d3c-4b2a-8f1e-0d9c8b7a6f5e', NULL, FALSE)"#,
+        )
+        .unwrap();
+        // Nothing is applied, but the checkpoint moves past the skipped events
+        assert_eq!(0, crate::run_projector("restaurants", 2).unwrap());
+        assert_eq!(
+            Spi::get_one::<i64>("SELECT MAX(\"offset\") FROM events").unwrap(),
+            Spi::get_one::<i64>(
+                "SELECT checkpoint FROM projections WHERE projection = 'restaurants'"
+            )
+            .unwrap()
+        );
+        assert_eq!(0, crate::run_projector("restaurants", 2).unwrap());
+    }
+
+    #[pg_test]
+    fn async_switch_horizon_test() {
+        // The event appended by the transaction still in progress (simulated by the transaction id past the current snapshot)
+        Spi::run(
+            r#"INSERT INTO events (event, event_id, decider, decider_id, data, command_id, previous_id, final, transaction_id)
+               VALUES ('RestaurantCreated', 'b8fcd1a2-3e4f-4a5b-8c6d-7e8f9a0b1c2d', 'Restaurant', '5c8b7a6f-5e4d-4c3b-9a2f-1e0d9c8b7a6f',
+                       '{"type": "RestaurantCreated", "identifier": "5c8b7a6f-5e4d-4c3b-9a2f-1e0d9c8b7a6f", "name": "In progress", "menu": {"menu_id": "02f09a3f-1624-3b1d-8409-44eff7708210", "items": [], "cuisine": "Greek"}, "final": false}',
+                       '5c8b7a6f-5e4d-4c3b-9a2f-1e0d9c8b7a6f', NULL, FALSE,
+                       (pg_snapshot_xmax(pg_current_snapshot())::TEXT::BIGINT + 1000)::TEXT::XID8)"#,
+        )
+        .unwrap();
+        crate::set_projection_mode("restaurants", "async").unwrap();
+        // The checkpoint stays behind the event of the unfinished transaction, so the projector reads it once it is committed
+        assert_eq!(
+            Spi::get_one::<i64>(
+                r#"SELECT MAX("offset") FROM events WHERE event_id <> 'b8fcd1a2-3e4f-4a5b-8c6d-7e8f9a0b1c2d'"#
+            )
+            .unwrap(),
+            Spi::get_one::<i64>(
+                "SELECT checkpoint FROM projections WHERE projection = 'restaurants'"
+            )
+            .unwrap()
+        );
+    }
+
+    #[pg_test]
+    fn statement_projection_test() {
+        crate::set_projection_mode("restaurants", "statement").unwrap();
+        // The bulk insert: the events inserted by the statement are applied in one pass
+        Spi::run(
+            r#"INSERT INTO events (event, event_id, decider, decider_id, data, command_id, previous_id, final)
+               VALUES ('RestaurantCreated', 'b2f6d8b5-9a2c-4e1f-8b63-4d5c6e7f8091', 'Restaurant', '1e4d3c2b-1a0f-4e9d-9c8b-7a6f5e4d3c2b',
+                       '{"type": "RestaurantCreated", "identifier": "1e4d3c2b-1a0f-4e9d-9c8b-7a6f5e4d3c2b", "name": "Bulk 1", "menu": {"menu_id": "02f09a3f-1624-3b1d-8409-44eff7708210", "items": [], "cuisine": "Greek"}, "final": false}',
+                       '1e4d3c2b-1a0f-4e9d-9c8b-7a6f5e4d3c2b', NULL, FALSE),
+                      ('RestaurantCreated', 'c3a7e9c6-ab3d-4f20-9c74-5e6d7f8091a2', 'Restaurant', '2f5e4d3c-2b1a-4f0e-8d9c-8b7a6f5e4d3c',
+                       '{"type": "RestaurantCreated", "identifier": "2f5e4d3c-2b1a-4f0e-8d9c-8b7a6f5e4d3c", "name": "Bulk 2", "menu": {"menu_id": "02f09a3f-1624-3b1d-8409-44eff7708210", "items": [], "cuisine": "Greek"}, "final": false}',
+                       '2f5e4d3c-2b1a-4f0e-8d9c-8b7a6f5e4d3c', NULL, FALSE)"#,
+        )
+        .unwrap();
+        assert_eq!(
+            Ok(Some(2)),
+            Spi::get_one::<i64>(
+                "SELECT count(*) FROM restaurants WHERE data ->> 'name' LIKE 'Bulk %'"
+            )
+        );
+
+        // The restaurant stream continues: the events of the command are applied by the statement-level trigger too
+        let (_, _, offset, _, _) = crate::handle_with_offsets(
+            Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
+                identifier: RestaurantId(
+                    Uuid::parse_str("1e4d3c2b-1a0f-4e9d-9c8b-7a6f5e4d3c2b").unwrap(),
+                ),
+                menu: RestaurantMenu {
+                    menu_id: MenuId(
+                        Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                    ),
+                    items: vec![],
+                    cuisine: RestaurantMenuCuisine::Thai,
+                    currency: None,
+                },
+            }),
+            None,
+        )
+        .unwrap()
+        .last()
+        .unwrap();
+        let restaurant_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("1e4d3c2b-1a0f-4e9d-9c8b-7a6f5e4d3c2b")
+                .unwrap()
+                .into_bytes(),
+        );
+        assert_eq!(
+            Some(offset),
+            crate::restaurant_view_version(restaurant_id).unwrap()
+        );
+
+        // Back to the row-level trigger
+        crate::set_projection_mode("restaurants", "sync").unwrap();
+        assert_eq!(
+            Ok(Some("sync".to_string())),
+            Spi::get_one::<String>("SELECT mode FROM projections WHERE projection = 'restaurants'")
+        );
+    }
+
+    #[pg_test]
+    fn await_projection_test() {
+        assert_eq!(None, crate::consistency_token().unwrap());
+        crate::set_projection_mode("restaurants", "async").unwrap();
+        let (_, _, offset, _, _) = crate::handle_with_offsets(
+            Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
+                identifier: RestaurantId(
+                    Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap(),
+                ),
+                menu: RestaurantMenu {
+                    menu_id: MenuId(
+                        Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                    ),
+                    items: vec![],
+                    cuisine: RestaurantMenuCuisine::Greek,
+                    currency: None,
+                },
+            }),
+            None,
+        )
+        .unwrap()
+        .last()
+        .unwrap();
+        let token = crate::consistency_token().unwrap().unwrap();
+        assert_eq!(offset, token);
+
+        // The projector is not running: the await times out, until the event is applied
+        assert!(!crate::await_projection("restaurants", token, 10).unwrap());
+        crate::run_projector("restaurants", 1000).unwrap();
+        assert!(crate::await_projection("restaurants", token, 10).unwrap());
+        // The `sync` projections are always up to date
+        assert!(crate::await_projection("orders", token, 0).unwrap());
+        assert!(crate::await_projection("unknown", token, 0).is_err());
+    }
+
+    #[pg_test]
+    fn backfill_projection_test() {
+        // The new projection starts empty
+        Spi::run("DELETE FROM restaurants").unwrap();
+        let step = || {
+            crate::backfill_projection_step("restaurants", 1)
+                .unwrap()
+                .next()
+                .unwrap()
+        };
+        let (events_applied, target_offset, finished) = step();
+        assert_eq!((1, false), (events_applied, finished));
+        assert_eq!(
+            Ok(Some("async".to_string())),
+            Spi::get_one::<String>("SELECT mode FROM projections WHERE projection = 'restaurants'")
+        );
+        // Resumed from the checkpoint, and switched back to the live mode once caught up
+        assert_eq!((1, target_offset, true), step());
+        assert_eq!(
+            Ok(Some("sync".to_string())),
+            Spi::get_one::<String>("SELECT mode FROM projections WHERE projection = 'restaurants'")
+        );
+        assert_eq!(
+            Ok(Some(1)),
+            Spi::get_one::<i64>("SELECT count(*) FROM restaurants")
+        );
+        // Other projections are not touched
+        assert_eq!(
+            Ok(Some(0)),
+            Spi::get_one::<i64>(
+                "SELECT count(*) FROM projection_rebuilds WHERE projection <> 'restaurants'"
+            )
+        );
+    }
+
+    #[pg_test]
+    fn commit_ordered_projection_test() {
+        crate::set_projection_mode("restaurants", "async").unwrap();
+        // The event appended by the transaction still in progress (simulated by the transaction id past the current snapshot), with the lower offset
+        Spi::run(
+            r#"INSERT INTO events (event, event_id, decider, decider_id, data, command_id, previous_id, final, transaction_id)
+               VALUES ('RestaurantCreated', 'a1e5c7a4-8f1b-4d0e-9a52-3c4b5d6e7f80', 'Restaurant', '9d3c2b1a-0f9e-4d8c-8b7a-6f5e4d3c2b1a',
+                       '{"type": "RestaurantCreated", "identifier": "9d3c2b1a-0f9e-4d8c-8b7a-6f5e4d3c2b1a", "name": "Late", "menu": {"menu_id": "02f09a3f-1624-3b1d-8409-44eff7708210", "items": [], "cuisine": "Greek"}, "final": false}',
+                       '9d3c2b1a-0f9e-4d8c-8b7a-6f5e4d3c2b1a', NULL, FALSE,
+                       (pg_snapshot_xmax(pg_current_snapshot())::TEXT::BIGINT + 1000)::TEXT::XID8)"#,
+        )
+        .unwrap();
+        let (_, _, offset, _, _) = crate::handle_with_offsets(
+            Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
+                identifier: RestaurantId(
+                    Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap(),
+                ),
+                menu: RestaurantMenu {
+                    menu_id: MenuId(
+                        Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                    ),
+                    items: vec![],
+                    cuisine: RestaurantMenuCuisine::Greek,
+                    currency: None,
+                },
+            }),
+            None,
+        )
+        .unwrap()
+        .last()
+        .unwrap();
+
+        // Only the events of the finished (and the current) transactions are applied; the checkpoint does not move past the unfinished transaction
+        assert_eq!(1, crate::run_projector("restaurants", 1000).unwrap());
+        assert_eq!(
+            Some(offset),
+            Spi::get_one::<i64>(
+                "SELECT checkpoint FROM projections WHERE projection = 'restaurants'"
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            None,
+            crate::restaurant_view_version(pgrx::Uuid::from_bytes(
+                Uuid::parse_str("9d3c2b1a-0f9e-4d8c-8b7a-6f5e4d3c2b1a")
+                    .unwrap()
+                    .into_bytes()
+            ))
+            .unwrap()
+        );
+    }
+
+    #[pg_test]
+    fn archive_final_order_test() {
+        let restaurant_identifier =
+            RestaurantId(Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap());
+        let order_identifier =
+            OrderId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
+        let line_items = vec![OrderLineItem {
+            id: OrderLineItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap()),
+            quantity: OrderLineItemQuantity(1),
+            menu_item_id: MenuItemId(
+                Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+            ),
+            name: MenuItemName("Item 1".to_string()),
+            price: None,
+            restaurant_identifier: None,
+        }];
+
+        crate::set_projection_archival("orders", true).unwrap();
+        crate::handle_all(
+            vec![
+                Command::PlaceOrder(PlaceOrder {
+                    identifier: restaurant_identifier,
+                    order_identifier: order_identifier.clone(),
+                    line_items,
+                    group_identifier: None,
+                    customer_identifier: None,
+                }),
+                Command::MarkOrderAsPrepared(MarkOrderAsPrepared {
+                    identifier: order_identifier,
+                }),
+            ],
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            Some(0),
+            Spi::get_one::<i64>("SELECT COUNT(*) FROM orders").unwrap()
+        );
+        assert_eq!(
+            Some(1),
+            Spi::get_one::<i64>("SELECT COUNT(*) FROM orders_archive").unwrap()
+        );
+    }
+
+    #[pg_test]
+    fn projection_row_security_test() {
+        Spi::run("SET LOCAL fmodel.owner = 'team_a'").unwrap();
+        crate::handle(
+            Command::PlaceOrder(PlaceOrder {
+                identifier: RestaurantId(
+                    Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap(),
+                ),
+                order_identifier: OrderId(
+                    Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                ),
+                line_items: vec![],
+                group_identifier: None,
+                customer_identifier: None,
+            }),
+            None,
+        )
+        .unwrap();
+        // The order is owned by the owner of the events, the restaurant is still owned by its creator
+        assert_eq!(
+            Some("team_a".to_string()),
+            Spi::get_one::<String>(
+                "SELECT owner FROM orders WHERE id = '02f09a3f-1624-3b1d-8409-44eff7708210'"
+            )
+            .unwrap()
+        );
+        assert_ne!(
+            Some("team_a".to_string()),
+            Spi::get_one::<String>(
+                "SELECT owner FROM restaurants WHERE id = 'e48d4d9e-403e-453f-b1ba-328e0ce23737'"
+            )
+            .unwrap()
+        );
+
+        crate::set_projection_row_security("orders", true).unwrap();
+        Spi::run("CREATE ROLE fmodel_test_reader; GRANT SELECT ON orders TO fmodel_test_reader")
+            .unwrap();
+        let count_orders = || {
+            Spi::run("SET LOCAL ROLE fmodel_test_reader").unwrap();
+            let count = Spi::get_one::<i64>("SELECT COUNT(*) FROM orders").unwrap();
+            Spi::run("RESET ROLE").unwrap();
+            count
+        };
+        assert_eq!(Some(1), count_orders());
+        Spi::run("RESET fmodel.owner").unwrap();
+        assert_eq!(Some(0), count_orders());
+
+        crate::set_projection_row_security("orders", false).unwrap();
+        assert_eq!(
+            Spi::get_one::<i64>("SELECT COUNT(*) FROM orders").unwrap(),
+            count_orders()
+        );
+        assert!(crate::set_projection_row_security("restaurant_order_board", true).is_err());
+    }
+
+    #[pg_test]
+    fn order_pricing_snapshot_test() {
+        let restaurant_identifier =
+            RestaurantId(Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap());
+        let menu_item_id =
+            MenuItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
+        crate::handle(
+            Command::PlaceOrder(PlaceOrder {
+                identifier: restaurant_identifier.clone(),
+                order_identifier: OrderId(
+                    Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                ),
+                line_items: vec![OrderLineItem {
+                    id: OrderLineItemId(
+                        Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                    ),
+                    quantity: OrderLineItemQuantity(2),
+                    menu_item_id: menu_item_id.clone(),
+                    name: MenuItemName("supa".to_string()),
+                    price: None,
+                    restaurant_identifier: None,
+                }],
+                group_identifier: None,
+                customer_identifier: None,
+            }),
+            None,
+        )
+        .unwrap();
+        // The later menu change does not alter the value of the order
+        crate::handle(
+            Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
+                identifier: restaurant_identifier,
+                menu: RestaurantMenu {
+                    menu_id: MenuId(
+                        Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                    ),
+                    items: vec![MenuItem {
+                        id: menu_item_id,
+                        name: MenuItemName("supa".to_string()),
+                        price: Money(50u64),
+                    }],
+                    cuisine: RestaurantMenuCuisine::Vietnamese,
+                    currency: None,
+                },
+            }),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            Some(20),
+            Spi::get_one::<i64>(
+                "SELECT total FROM orders_typed WHERE id = '02f09a3f-1624-3b1d-8409-44eff7708210'"
+            )
+            .unwrap()
+        );
+    }
+
+    #[pg_test]
+    fn modify_order_test() {
+        let restaurant_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737")
+                .unwrap()
+                .into_bytes(),
+        );
+        let order_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("6a1e3f0c-1b2d-4c3e-9f4a-5b6c7d8e9f01")
+                .unwrap()
+                .into_bytes(),
+        );
+        let line_items = |quantity: u32| {
+            pgrx::JsonB(
+                serde_json::json!([{"id": "02f09a3f-1624-3b1d-8409-44eff7708210", "quantity": quantity, "menu_item_id": "02f09a3f-1624-3b1d-8409-44eff7708210", "name": "supa"}]),
+            )
+        };
+        let total = || {
+            Spi::get_one::<i64>(
+                "SELECT total FROM orders_typed WHERE id = '6a1e3f0c-1b2d-4c3e-9f4a-5b6c7d8e9f01'",
+            )
+            .unwrap()
+        };
+        crate::place_order(restaurant_id, order_id, line_items(1), None).unwrap();
+        assert_eq!(Some(10), total());
+
+        // The modification is accepted by the restaurant, and the order is updated by the saga
+        let events = crate::modify_order(restaurant_id, order_id, line_items(3)).unwrap();
+        assert!(matches!(events[0], Event::OrderLineItemsModified(_)));
+        assert!(matches!(events[1], Event::OrderLineItemsUpdated(_)));
+        assert_eq!(Some(30), total());
+        assert_eq!(
+            Some(3),
+            Spi::get_one::<i64>(
+                "SELECT (data->'line_items'->0->>'quantity')::BIGINT FROM orders WHERE id = '6a1e3f0c-1b2d-4c3e-9f4a-5b6c7d8e9f01'",
+            )
+            .unwrap()
+        );
+    }
+
+    #[pg_test(
+        error = "Failed to modify the order. Order does not exist or is not in the correct state!"
+    )]
+    fn modify_prepared_order_error_test() {
+        let restaurant_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737")
+                .unwrap()
+                .into_bytes(),
+        );
+        let order_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("6a1e3f0c-1b2d-4c3e-9f4a-5b6c7d8e9f02")
+                .unwrap()
+                .into_bytes(),
+        );
+        let line_items = || {
+            pgrx::JsonB(
+                serde_json::json!([{"id": "02f09a3f-1624-3b1d-8409-44eff7708210", "quantity": 1, "menu_item_id": "02f09a3f-1624-3b1d-8409-44eff7708210", "name": "supa"}]),
+            )
+        };
+        crate::place_order(restaurant_id, order_id, line_items(), None).unwrap();
+        crate::mark_order_prepared(order_id).unwrap();
+        // The order is in the kitchen already
+        let _ = crate::modify_order(restaurant_id, order_id, line_items());
+    }
+
+    #[pg_test]
+    fn restaurant_capacity_test() {
+        let restaurant_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737")
+                .unwrap()
+                .into_bytes(),
+        );
+        let order_id = |id: &str| pgrx::Uuid::from_bytes(Uuid::parse_str(id).unwrap().into_bytes());
+        let line_items = || {
+            pgrx::JsonB(
+                serde_json::json!([{"id": "02f09a3f-1624-3b1d-8409-44eff7708210", "quantity": 1, "menu_item_id": "02f09a3f-1624-3b1d-8409-44eff7708210", "name": "supa"}]),
+            )
+        };
+        crate::change_restaurant_capacity(restaurant_id, Some(1)).unwrap();
+        crate::place_order(
+            restaurant_id,
+            order_id("8c3a5b2e-3d4f-4e6a-9b7c-8d9e0f1a2b01"),
+            line_items(),
+            None,
+        )
+        .unwrap();
+        // The order is prepared, and closed at the restaurant: the capacity is released
+        assert_eq!(
+            2,
+            crate::mark_order_prepared(order_id("8c3a5b2e-3d4f-4e6a-9b7c-8d9e0f1a2b01"))
+                .unwrap()
+                .len()
+        );
+        crate::place_order(
+            restaurant_id,
+            order_id("8c3a5b2e-3d4f-4e6a-9b7c-8d9e0f1a2b02"),
+            line_items(),
+            None,
+        )
+        .unwrap();
+        assert!(crate::change_restaurant_capacity(restaurant_id, Some(-1)).is_err());
+    }
+
+    #[pg_test(error = "Failed to place the order. Restaurant is at capacity!")]
+    fn restaurant_capacity_exceeded_test() {
+        let restaurant_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737")
+                .unwrap()
+                .into_bytes(),
+        );
+        let order_id = |id: &str| pgrx::Uuid::from_bytes(Uuid::parse_str(id).unwrap().into_bytes());
+        let line_items = || {
+            pgrx::JsonB(
+                serde_json::json!([{"id": "02f09a3f-1624-3b1d-8409-44eff7708210", "quantity": 1, "menu_item_id": "02f09a3f-1624-3b1d-8409-44eff7708210", "name": "supa"}]),
+            )
+        };
+        crate::change_restaurant_capacity(restaurant_id, Some(1)).unwrap();
+        crate::place_order(
+            restaurant_id,
+            order_id("8c3a5b2e-3d4f-4e6a-9b7c-8d9e0f1a2b03"),
+            line_items(),
+            None,
+        )
+        .unwrap();
+        let _ = crate::place_order(
+            restaurant_id,
+            order_id("8c3a5b2e-3d4f-4e6a-9b7c-8d9e0f1a2b04"),
+            line_items(),
+            None,
+        );
+    }
+
+    #[pg_test]
+    fn payments_test() {
+        let restaurant_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737")
+                .unwrap()
+                .into_bytes(),
+        );
+        let order_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("7b2f4a1d-2c3e-4d5f-8a6b-7c8d9e0f1a02")
+                .unwrap()
+                .into_bytes(),
+        );
+        let payments = || {
+            Spi::get_two::<i64, i64>(
+                "SELECT captured, refunded FROM payments WHERE order_id = '7b2f4a1d-2c3e-4d5f-8a6b-7c8d9e0f1a02'",
+            )
+            .unwrap()
+        };
+        // The total of the order is 30
+        crate::place_order(
+            restaurant_id,
+            order_id,
+            pgrx::JsonB(serde_json::json!([{"id": "02f09a3f-1624-3b1d-8409-44eff7708210", "quantity": 3, "menu_item_id": "02f09a3f-1624-3b1d-8409-44eff7708210", "name": "supa"}])),
+            None,
+        )
+        .unwrap();
+
+        // Split payment
+        crate::capture_payment(restaurant_id, order_id, 10).unwrap();
+        crate::capture_payment(restaurant_id, order_id, 20).unwrap();
+        // Partial refunds, after the order is prepared
+        crate::mark_order_prepared(order_id).unwrap();
+        crate::refund_payment(restaurant_id, order_id, 5).unwrap();
+        crate::refund_payment(restaurant_id, order_id, 5).unwrap();
+        assert_eq!((Some(30), Some(10)), payments());
+    }
+
+    #[pg_test(
+        error = "Failed to refund the payment. The amount exceeds the captured amount of the order!"
+    )]
+    fn payments_refund_exceeds_captured_test() {
+        let restaurant_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737")
+                .unwrap()
+                .into_bytes(),
+        );
+        let order_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("7b2f4a1d-2c3e-4d5f-8a6b-7c8d9e0f1a03")
+                .unwrap()
+                .into_bytes(),
+        );
+        crate::place_order(
+            restaurant_id,
+            order_id,
+            pgrx::JsonB(serde_json::json!([{"id": "02f09a3f-1624-3b1d-8409-44eff7708210", "quantity": 3, "menu_item_id": "02f09a3f-1624-3b1d-8409-44eff7708210", "name": "supa"}])),
+            None,
+        )
+        .unwrap();
+        crate::capture_payment(restaurant_id, order_id, 20).unwrap();
+        crate::refund_payment(restaurant_id, order_id, 15).unwrap();
+        // The cumulative refunds (15 + 10) exceed the captured amount (20)
+        let _ = crate::refund_payment(restaurant_id, order_id, 10);
+    }
+
+    #[pg_test]
+    fn search_restaurants_test() {
+        crate::handle(
+            Command::CreateRestaurant(CreateRestaurant {
+                identifier: RestaurantId(
+                    Uuid::parse_str("8f4b1a1e-2c3d-4e5f-8a9b-0c1d2e3f4a5b").unwrap(),
+                ),
+                name: RestaurantName("Trattoria Roma".to_string()),
+                menu: RestaurantMenu {
+                    menu_id: MenuId(
+                        Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                    ),
+                    items: vec![MenuItem {
+                        id: MenuItemId(
+                            Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                        ),
+                        name: MenuItemName("Carbonara".to_string()),
+                        price: Money(12u64),
+                    }],
+                    cuisine: RestaurantMenuCuisine::Italian,
+                    currency: None,
+                },
+            }),
+            None,
+        )
+        .unwrap();
+
+        let names = |query| {
+            crate::search_restaurants(query, 20)
+                .unwrap()
+                .map(|(_, name, _, _)| name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec!["Trattoria Roma".to_string()], names("tratt"));
+        assert_eq!(vec!["Trattoria Roma".to_string()], names("italian carbo"));
+        assert!(names("sushi").is_empty());
+        assert!(names("!&|").is_empty());
+    }
+
+    #[pg_test]
+    fn menu_item_availability_test() {
+        let restaurant_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737")
+                .unwrap()
+                .into_bytes(),
+        );
+        let menu_item_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210")
+                .unwrap()
+                .into_bytes(),
+        );
+        let unavailable_items = || {
+            Spi::get_one::<i64>(
+                "SELECT jsonb_array_length(data->'unavailable_items') FROM restaurants WHERE id = 'e48d4d9e-403e-453f-b1ba-328e0ce23737'",
+            )
+            .unwrap()
+        };
+        let found = || {
+            crate::search_restaurants("supa", 20)
+                .unwrap()
+                .any(|(_, name, _, _)| name == "Pljeska")
+        };
+
+        crate::mark_menu_item_unavailable(restaurant_id, menu_item_id).unwrap();
+        assert_eq!(Some(1), unavailable_items());
+        // The restaurant is not found by the dishes it can not serve
+        assert!(!found());
+
+        crate::mark_menu_item_available(restaurant_id, menu_item_id).unwrap();
+        assert_eq!(Some(0), unavailable_items());
+        assert!(found());
+        crate::place_order(
+            restaurant_id,
+            pgrx::Uuid::from_bytes(
+                Uuid::parse_str("9d4b6c3f-4e5a-4f7b-8c8d-9e0f1a2b3c01")
+                    .unwrap()
+                    .into_bytes(),
+            ),
+            pgrx::JsonB(
+                serde_json::json!([{"id": "02f09a3f-1624-3b1d-8409-44eff7708210", "quantity": 1, "menu_item_id": "02f09a3f-1624-3b1d-8409-44eff7708210", "name": "supa"}]),
+            ),
+            None,
+        )
+        .unwrap();
+    }
+
+    #[pg_test(error = "Failed to place the order. The menu item is unavailable!")]
+    fn menu_item_unavailable_test() {
+        let restaurant_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737")
+                .unwrap()
+                .into_bytes(),
+        );
+        crate::mark_menu_item_unavailable(
+            restaurant_id,
+            pgrx::Uuid::from_bytes(
+                Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210")
+                    .unwrap()
+                    .into_bytes(),
+            ),
+        )
+        .unwrap();
+        let _ = crate::place_order(
+            restaurant_id,
+            pgrx::Uuid::from_bytes(
+                Uuid::parse_str("9d4b6c3f-4e5a-4f7b-8c8d-9e0f1a2b3c02")
+                    .unwrap()
+                    .into_bytes(),
+            ),
+            pgrx::JsonB(
+                serde_json::json!([{"id": "02f09a3f-1624-3b1d-8409-44eff7708210", "quantity": 1, "menu_item_id": "02f09a3f-1624-3b1d-8409-44eff7708210", "name": "supa"}]),
+            ),
+            None,
+        );
+    }
+
+    /// Places the order at the restaurant open on Mondays from 09:00 to 17:00, and on Fridays from 18:00 to 02:00, with the Restaurant decider stopped at the local time.
+    fn place_order_at(time: LocalTime) -> Vec<RestaurantEvent> {
+        let decider = restaurant_decider(FixedClock(time), FixedStockLevels { stock: vec![] });
+        let restaurant_id =
+            RestaurantId(Uuid::parse_str("3c4d5e6f-7a8b-4c9d-8e0f-1a2b3c4d5e6f").unwrap());
+        let state = [
+            RestaurantEvent::Created(RestaurantCreated {
+                identifier: restaurant_id.to_owned(),
+                name: RestaurantName("Night Owl".to_string()),
+                menu: RestaurantMenu {
+                    menu_id: MenuId(
+                        Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                    ),
+                    items: vec![],
+                    cuisine: RestaurantMenuCuisine::Other,
+                    currency: None,
+                },
+                r#final: false,
+            }),
+            RestaurantEvent::OpeningHoursChanged(RestaurantOpeningHoursChanged {
+                identifier: restaurant_id.to_owned(),
+                opening_hours: Some(OpeningHours {
+                    time_zone: "Europe/Belgrade".to_string(),
+                    periods: vec![
+                        OpeningPeriod {
+                            day_of_week: 1,
+                            opens_at: 9 * 60,
+                            closes_at: 17 * 60,
+                        },
+                        OpeningPeriod {
+                            day_of_week: 5,
+                            opens_at: 18 * 60,
+                            closes_at: 2 * 60,
+                        },
+                    ],
+                }),
+                r#final: false,
+            }),
+        ]
+        .iter()
+        .fold(None, |state, event| (decider.evolve)(&state, event));
+        (decider.decide)(
+            &crate::domain::api::RestaurantCommand::PlaceOrder(PlaceOrder {
+                identifier: restaurant_id,
+                order_identifier: OrderId(
+                    Uuid::parse_str("4d5e6f7a-8b9c-4d0e-8f1a-2b3c4d5e6f7a").unwrap(),
+                ),
+                line_items: vec![],
+                group_identifier: None,
+                customer_identifier: None,
+            }),
+            &state,
+        )
+    }
+
+    #[pg_test]
+    fn restaurant_opening_hours_test() {
+        // Monday, 12:00
+        assert_eq!(
+            1,
+            place_order_at(LocalTime {
+                day_of_week: 1,
+                minute_of_day: 12 * 60
+            })
+            .len()
+        );
+        // Saturday, 01:00 (Friday night)
+        assert_eq!(
+            1,
+            place_order_at(LocalTime {
+                day_of_week: 6,
+                minute_of_day: 60
+            })
+            .len()
+        );
+
+        // Always open (every day from midnight to midnight), in the local time of the database clock
+        let restaurant_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737")
+                .unwrap()
+                .into_bytes(),
+        );
+        crate::change_restaurant_opening_hours(
+            restaurant_id,
+            Some(pgrx::JsonB(serde_json::json!({
+                "time_zone": "Europe/Belgrade",
+                "periods": (1..=7).map(|day_of_week| serde_json::json!({"day_of_week": day_of_week, "opens_at": 0, "closes_at": 0})).collect::<Vec<_>>()
+            }))),
+        )
+        .unwrap();
+        crate::place_order(
+            restaurant_id,
+            pgrx::Uuid::from_bytes(
+                Uuid::parse_str("5e6f7a8b-9c0d-4e1f-8a2b-3c4d5e6f7a8b")
+                    .unwrap()
+                    .into_bytes(),
+            ),
+            pgrx::JsonB(
+                serde_json::json!([{"id": "02f09a3f-1624-3b1d-8409-44eff7708210", "quantity": 1, "menu_item_id": "02f09a3f-1624-3b1d-8409-44eff7708210", "name": "supa"}]),
+            ),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            Some("Europe/Belgrade".to_string()),
+            Spi::get_one::<String>(
+                "SELECT data->'opening_hours'->>'time_zone' FROM restaurants WHERE id = 'e48d4d9e-403e-453f-b1ba-328e0ce23737'"
+            )
+            .unwrap()
+        );
+    }
+
+    #[pg_test(error = "Failed to place the order. Restaurant is closed!")]
+    fn restaurant_closed_test() {
+        // Saturday, 02:00 (Friday night, closed)
+        place_order_at(LocalTime {
+            day_of_week: 6,
+            minute_of_day: 2 * 60,
+        });
+    }
+
+    #[pg_test]
+    fn restaurant_daily_orders_test() {
+        let place_order = |order_id: &str, quantity| {
+            Command::PlaceOrder(PlaceOrder {
+                identifier: RestaurantId(
+                    Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap(),
+                ),
+                order_identifier: OrderId(Uuid::parse_str(order_id).unwrap()),
+                line_items: vec![OrderLineItem {
+                    id: OrderLineItemId(
+                        Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                    ),
+                    quantity: OrderLineItemQuantity(quantity),
+                    menu_item_id: MenuItemId(
+                        Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                    ),
+                    name: MenuItemName("supa".to_string()),
+                    price: None,
+                    restaurant_identifier: None,
+                }],
+                group_identifier: None,
+                customer_identifier: None,
+            })
+        };
+        crate::handle(place_order("3c1a7e0e-5b2d-4f6a-9c8b-7d6e5f4a3b21", 1), None).unwrap();
+        crate::handle(place_order("3c1a7e0e-5b2d-4f6a-9c8b-7d6e5f4a3b22", 2), None).unwrap();
+
+        assert_eq!(
+            Some((Some(2), Some(3), Some(30))),
+            Spi::get_three::<i64, i64, i64>(
+                "SELECT orders, items, revenue FROM restaurant_daily_orders WHERE restaurant_id = 'e48d4d9e-403e-453f-b1ba-328e0ce23737' AND day = (NOW() AT TIME ZONE 'UTC')::DATE"
+            )
+            .ok()
+        );
+    }
+
+    #[pg_test]
+    fn restaurant_order_board_test() {
+        crate::handle(
+            Command::PlaceOrder(PlaceOrder {
+                identifier: RestaurantId(
+                    Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap(),
+                ),
+                order_identifier: OrderId(
+                    Uuid::parse_str("6f1b2c3d-4e5f-4a6b-8c7d-9e0f1a2b3c4d").unwrap(),
+                ),
+                line_items: vec![],
+                group_identifier: None,
+                customer_identifier: None,
+            }),
+            None,
+        )
+        .unwrap();
+        let status = || {
+            Spi::get_one::<String>(
+                "SELECT data->'orders'->0->>'status' FROM restaurant_order_board WHERE id = 'e48d4d9e-403e-453f-b1ba-328e0ce23737' AND data->>'name' = 'Pljeska'",
+            )
+            .unwrap()
+        };
+        // `OrderCreated` (order event) is joined into the restaurant board (restaurant event)
+        assert_eq!(Some("Created".to_string()), status());
+
+        // `OrderPrepared` (order event) is joined into the restaurant board by the restaurant id
+        crate::handle(
+            Command::MarkOrderAsPrepared(MarkOrderAsPrepared {
+                identifier: OrderId(
+                    Uuid::parse_str("6f1b2c3d-4e5f-4a6b-8c7d-9e0f1a2b3c4d").unwrap(),
+                ),
+            }),
+            None,
+        )
+        .unwrap();
+        assert_eq!(Some("Prepared".to_string()), status());
+    }
+
+    #[pg_test]
+    fn migrate_view_rows_test() {
+        // The restaurant document stored before the cuisine was introduced
+        Spi::run(
+            "UPDATE restaurants SET data = data #- '{menu,cuisine}' WHERE id = 'e48d4d9e-403e-453f-b1ba-328e0ce23737'",
+        )
+        .unwrap();
+        let restaurant_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737")
+                .unwrap()
+                .into_bytes(),
+        );
+        // The document is upcasted when the state is fetched
+        crate::handle(
+            Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
+                identifier: RestaurantId(
+                    Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap(),
+                ),
+                menu: RestaurantMenu {
+                    menu_id: MenuId(
+                        Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                    ),
+                    items: vec![],
+                    cuisine: RestaurantMenuCuisine::Vietnamese,
+                    currency: None,
+                },
+            }),
+            None,
+        )
+        .unwrap();
+        assert!(crate::restaurant_view_version(restaurant_id)
+            .unwrap()
+            .is_some());
+
+        Spi::run(
+            "UPDATE restaurants SET data = data #- '{menu,cuisine}' WHERE id = 'e48d4d9e-403e-453f-b1ba-328e0ce23737'",
+        )
+        .unwrap();
+        assert_eq!(1, crate::migrate_view_rows("restaurants", 1).unwrap());
+        assert_eq!(
+            Some("Other".to_string()),
+            Spi::get_one::<String>(
+                "SELECT data->'menu'->>'cuisine' FROM restaurants WHERE id = 'e48d4d9e-403e-453f-b1ba-328e0ce23737'"
+            )
+            .unwrap()
+        );
+        // The migrated rows are not rewritten again
+        assert_eq!(0, crate::migrate_view_rows("restaurants", 1).unwrap());
+    }
+
+    #[pg_test]
+    fn load_fixture_test() {
+        // The restaurant, and 100 orders placed (restaurant stream) and created by the saga (order streams)
+        assert_eq!(201, crate::load_fixture("restaurant_with_orders").unwrap());
+        assert_eq!(
+            Some(100),
+            Spi::get_one::<i64>(
+                "SELECT COUNT(*) FROM orders WHERE data->>'restaurant_identifier' = '0b1c2d3e-4f5a-4b6c-8d7e-9f0a1b2c3d4e'"
+            )
+            .unwrap()
+        );
+
+        // The restaurant, the order placed, created, prepared (final) and closed at the restaurant
+        assert_eq!(5, crate::load_fixture("finalized_streams").unwrap());
+        assert_eq!(
+            Some(true),
+            Spi::get_one::<bool>(
+                "SELECT final FROM events WHERE decider_id = '9e8d7c6b-5a4f-4e3d-8c2b-1a0f9e8d7c6b' ORDER BY \"offset\" DESC LIMIT 1"
+            )
+            .unwrap()
+        );
+
+        assert!(crate::load_fixture("unknown").is_err());
+    }
+
+    #[pg_test]
+    fn restaurants_typed_view_test() {
+        assert_eq!(
+            Some(("Vietnamese".to_string(), 2)),
+            Spi::get_two::<String, i32>(
+                "SELECT cuisine, item_count FROM restaurants_typed WHERE name = 'Pljeska'"
+            )
+            .map(|(cuisine, item_count)| cuisine.zip(item_count))
+            .unwrap()
+        );
+    }
+
+    #[pg_test]
+    fn canonical_payload_test() {
+        assert_eq!(
+            r#"{"a":[1,{"c":null,"d":"x y"}],"bb":true}"#,
+            crate::fmodel_canonical_json(pgrx::JsonB(serde_json::json!({
+                "bb": true,
+                "a": [1, {"d": "x y", "c": null}]
+            })))
+        );
+
+        Spi::run("SET fmodel.deduplication = reject").unwrap();
+        crate::handle(
+            Command::PlaceOrder(PlaceOrder {
+                identifier: RestaurantId(
+                    Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap(),
+                ),
+                order_identifier: OrderId(
+                    Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                ),
+                line_items: vec![],
+                group_identifier: None,
+                customer_identifier: None,
+            }),
+            None,
+        )
+        .unwrap();
+        assert_eq!(0, crate::verify_canonical_payloads().unwrap().count());
+
+        // The historical event, hashed from the `jsonb` text representation
+        Spi::run("SET fmodel.deduplication = off").unwrap();
+        Spi::run(
+            r#"INSERT INTO events (event, event_id, decider, decider_id, data, command_id, previous_id, final, payload_hash)
+               VALUES ('RestaurantCreated', 'b2f6d8b5-9a2c-4e1f-8b63-4d5c6e7f8091', 'Restaurant', '1e4d3c2b-1a0f-4e9d-9c8b-7a6f5e4d3c2b',
+                       '{"type": "RestaurantCreated", "identifier": "1e4d3c2b-1a0f-4e9d-9c8b-7a6f5e4d3c2b", "name": "Legacy", "menu": {"menu_id": "02f09a3f-1624-3b1d-8409-44eff7708210", "items": [], "cuisine": "Greek"}, "final": false}',
+                       '1e4d3c2b-1a0f-4e9d-9c8b-7a6f5e4d3c2b', NULL, FALSE,
+                       sha256(convert_to('{"type": "RestaurantCreated", "identifier": "1e4d3c2b-1a0f-4e9d-9c8b-7a6f5e4d3c2b", "name": "Legacy", "menu": {"menu_id": "02f09a3f-1624-3b1d-8409-44eff7708210", "items": [], "cuisine": "Greek"}, "final": false}'::JSONB::TEXT, 'UTF8')))"#,
+        )
+        .unwrap();
+        let non_canonical: Vec<_> = crate::verify_canonical_payloads().unwrap().collect();
+        assert_eq!(1, non_canonical.len());
+        assert_eq!("1e4d3c2b-1a0f-4e9d-9c8b-7a6f5e4d3c2b", non_canonical[0].2);
+    }
+
+    #[pg_test]
+    fn deduplication_test() {
+        let change_restaurant_menu = || {
+            Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
+                identifier: RestaurantId(
+                    Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap(),
+                ),
+                menu: RestaurantMenu {
+                    menu_id: MenuId(
+                        Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                    ),
+                    items: vec![],
+                    cuisine: RestaurantMenuCuisine::Vietnamese,
+                    currency: None,
+                },
+            })
+        };
+
+        Spi::run("SET fmodel.deduplication = skip").unwrap();
+        assert_eq!(
+            1,
+            crate::handle(change_restaurant_menu(), None).unwrap().len()
+        );
+        assert_eq!(
+            0,
+            crate::handle(change_restaurant_menu(), None).unwrap().len()
+        );
+
+        // The event is saved with the id of the command it was decided by
+        let command_id = crate::framework::infrastructure::event_repository::command_id(
+            &change_restaurant_menu(),
+        )
+        .unwrap();
+        assert_eq!(
+            Ok(Some(1)),
+            Spi::get_one_with_args::<i64>(
+                "SELECT count(*) FROM events WHERE decider_id = 'e48d4d9e-403e-453f-b1ba-328e0ce23737' AND command_id = $1",
+                vec![(
+                    PgBuiltInOids::UUIDOID.oid(),
+                    pgrx::Uuid::from_bytes(command_id.into_bytes()).into_datum(),
+                )],
+            )
+        );
+
+        // The event with the same payload, decided by another command, is not a duplicate
+        Spi::run(
+            r#"INSERT INTO events (event, event_id, decider, decider_id, data, command_id, previous_id, final)
+               SELECT event, gen_random_uuid(), decider, decider_id, data, gen_random_uuid(), event_id, final
+               FROM events WHERE decider = 'Restaurant' AND decider_id = 'e48d4d9e-403e-453f-b1ba-328e0ce23737'
+               ORDER BY "offset" DESC LIMIT 1"#,
+        )
+        .unwrap();
+        assert_eq!(
+            Ok(Some(2)),
+            Spi::get_one_with_args::<i64>(
+                "SELECT count(*) FROM events WHERE decider_id = 'e48d4d9e-403e-453f-b1ba-328e0ce23737'
+                 AND payload_hash = (SELECT payload_hash FROM events WHERE command_id = $1)",
+                vec![(
+                    PgBuiltInOids::UUIDOID.oid(),
+                    pgrx::Uuid::from_bytes(command_id.into_bytes()).into_datum(),
+                )],
+            )
+        );
+    }
+
+    #[cfg(feature = "protobuf")]
+    #[pg_test]
+    fn protobuf_test() {
+        use crate::infrastructure::protobuf::{CommandMessage, EventMessage};
+        use prost::Message;
+
+        let change_restaurant_menu = Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
+            identifier: RestaurantId(
+                Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap(),
+            ),
+            menu: RestaurantMenu {
+                menu_id: MenuId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap()),
+                items: vec![],
+                cuisine: RestaurantMenuCuisine::Vietnamese,
+                currency: None,
+            },
+        });
+        let events: Vec<Event> =
+            crate::handle_protobuf(&CommandMessage::from(&change_restaurant_menu).encode_to_vec())
+                .unwrap()
+                .map(|bytes| {
+                    Event::try_from(EventMessage::decode(bytes.as_slice()).unwrap()).unwrap()
+                })
+                .collect();
+
+        assert_eq!(
+            vec![Event::RestaurantMenuChanged(RestaurantMenuChanged {
+                identifier: RestaurantId(
+                    Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap(),
+                ),
+                menu: RestaurantMenu {
+                    menu_id: MenuId(
+                        Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap()
+                    ),
+                    items: vec![],
+                    cuisine: RestaurantMenuCuisine::Vietnamese,
+                    currency: None,
+                },
+                menu_version: RestaurantMenuVersion(2),
+                r#final: false,
+            })],
+            events
+        );
+    }
+
+    #[pg_test]
+    fn generate_client_types_test() {
+        let typescript = crate::generate_client_types("typescript").unwrap();
+        assert!(typescript.contains(
+            "export type Command = CreateRestaurant | ChangeRestaurantMenu | PlaceOrder | "
+        ));
+        assert!(typescript.contains("export type RestaurantId = string;\n"));
+        assert!(typescript.contains("  type: \"RestaurantMenuChanged\";\n"));
+        assert!(typescript.contains("  menu_version?: RestaurantMenuVersion;\n"));
+        assert!(typescript.contains("  opening_hours: OpeningHours | null;\n"));
+
+        // The serialized command matches its JSON schema: the `type` tag, and every property is defined
+        let schema: serde_json::Value =
+            serde_json::from_str(&crate::generate_client_types("json_schema").unwrap()).unwrap();
+        let command = serde_json::to_value(Command::MarkOrderAsPrepared(MarkOrderAsPrepared {
+            identifier: OrderId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap()),
+        }))
+        .unwrap();
+        let command_schema = &schema["$defs"]["MarkOrderAsPrepared"];
+        assert_eq!(
+            command_schema["properties"]["type"]["const"],
+            command["type"]
+        );
+        assert!(command
+            .as_object()
+            .unwrap()
+            .keys()
+            .all(|key| command_schema["properties"].get(key).is_some()));
+        assert_eq!(
+            31,
+            schema["$defs"]["Event"]["oneOf"].as_array().unwrap().len()
+        );
+        assert!(crate::generate_client_types("flow").is_err());
+    }
+
+    #[pg_test]
+    fn event_avro_schemas_test() {
+        let schemas: Vec<_> = crate::event_avro_schemas().collect();
+
+        assert_eq!(
+            vec![
+                "RestaurantCreated",
+                "RestaurantMenuChanged",
+                "OrderPlaced",
+                "OrderCreated",
+                "OrderPrepared",
+                "OrderLineItemsModified",
+                "OrderLineItemsUpdated",
+                "PaymentCaptured",
+                "PaymentRefunded",
+                "RestaurantCapacityChanged",
+                "RestaurantOrderClosed",
+                "MenuItemMarkedUnavailable",
+                "MenuItemMarkedAvailable",
+                "RestaurantOpeningHoursChanged",
+                "RestaurantOrdersMigrated",
+                "RestaurantOrdersReceived",
+                "OrderMigrated",
+                "RestaurantPromotionApplied",
+                "RestaurantPromotionWithdrawn",
+                "PromotionCreated",
+                "PromotionExpired",
+                "RestaurantOrderCancelled",
+                "OrderCancelled",
+                "InventoryRestocked",
+                "StockReserved",
+                "StockReleased",
+                "RestaurantOrderSplit",
+                "GiftCardIssued",
+                "GiftCardRedeemed",
+                "GiftCardRefunded",
+                "CourierLocationUpdated"
+            ],
+            schemas
+                .iter()
+                .map(|(event_type, _)| event_type.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert!(schemas
+            .iter()
+            .all(|(event_type, schema)| schema.0["name"] == *event_type));
+    }
+
+    #[pg_test]
+    fn idempotency_middleware_test() {
+        let change_restaurant_menu = || {
+            Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
+                identifier: RestaurantId(
+                    Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap(),
+                ),
+                menu: RestaurantMenu {
+                    menu_id: MenuId(
+                        Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                    ),
+                    items: vec![],
+                    cuisine: RestaurantMenuCuisine::Vietnamese,
+                    currency: None,
+                },
+            })
+        };
+
+        // The key recorded by another (earlier) transaction
+        Spi::run("INSERT INTO command_idempotency_keys (idempotency_key, transaction_id) VALUES ('key-1', 0)").unwrap();
+        Spi::run("SET fmodel.idempotency_key = 'key-1'").unwrap();
+        assert!(crate::handle(change_restaurant_menu(), None).is_err());
+        Spi::run("SET fmodel.idempotency_key = 'key-2'").unwrap();
+        assert!(crate::handle(change_restaurant_menu(), None).is_ok());
+    }
+
+    #[pg_test]
+    fn wake_projector_test() {
+        // Without the shared memory (not loaded via `shared_preload_libraries`), there is no projector to wake
+        crate::application::order_restaurant_hooks::wake_projector(&[]).unwrap();
+        assert!(crate::handle(
+            Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
+                identifier: RestaurantId(
+                    Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap(),
+                ),
+                menu: RestaurantMenu {
+                    menu_id: MenuId(
+                        Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                    ),
+                    items: vec![],
+                    cuisine: RestaurantMenuCuisine::Vietnamese,
+                    currency: None,
+                },
+            }),
+            None,
+        )
+        .is_ok());
+    }
+
+    #[pg_test]
+    fn hooks_test() {
+        fn failing_hook(_: &[(Event, EventPosition)]) -> Result<(), ErrorMessage> {
+            Err(ErrorMessage {
+                message: "failing hook".to_string(),
+                context: None,
+            })
+        }
+        fn counting_hook(events: &[(Event, EventPosition)]) -> Result<(), ErrorMessage> {
+            Spi::run(&format!("SET fmodel.test_hook_events = {}", events.len())).map_err(|err| {
+                ErrorMessage {
+                    message: err.to_string(),
+                    context: None,
+                }
+            })
+        }
+
+        let aggregate =
+            crate::application::order_restaurant_aggregate::order_restaurant_aggregate()
+                .with_hooks(
+                    HookRegistry::new()
+                        .register("failing", failing_hook)
+                        .register("counting", counting_hook),
+                );
+        let result = aggregate.handle(&Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
+            identifier: RestaurantId(
+                Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap(),
+            ),
+            menu: RestaurantMenu {
+                menu_id: MenuId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap()),
+                items: vec![],
+                cuisine: RestaurantMenuCuisine::Vietnamese,
+                currency: None,
+            },
+        }));
+
+        // The failing hook does not fail the command handling, nor the hooks registered after it
+        assert!(result.is_ok());
+        assert_eq!(
+            Some("1".to_string()),
+            Spi::get_one::<String>("SELECT current_setting('fmodel.test_hook_events')").unwrap()
+        );
+    }
+
+    #[pg_test]
+    fn unknown_event_test() {
+        let payload = serde_json::json!({"type": "OrderDelivered", "identifier": "02f09a3f-1624-3b1d-8409-44eff7708210", "final": true});
+
+        assert_eq!(
+            Ok(EventPayload::Unknown(UnknownEvent {
+                r#type: "OrderDelivered".to_string(),
+                payload: payload.clone(),
+            })),
+            to_event::<Event>(pgrx::JsonB(payload.clone())).map_err(|err| err.message)
+        );
+        assert!(to_known_event::<Event>(pgrx::JsonB(payload.clone())).is_err());
+        Spi::run("SET fmodel.skip_unknown_events = on").unwrap();
+        assert_eq!(
+            None,
+            to_known_event::<Event>(pgrx::JsonB(payload.clone())).unwrap()
+        );
+        // The command side never skips the unknown events
+        assert!(to_command_side_event::<Event>(pgrx::JsonB(payload)).is_err());
+    }
+
+    #[pg_test]
+    fn correlation_test() {
+        let place_order = Command::PlaceOrder(PlaceOrder {
+            identifier: RestaurantId(
+                Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap(),
+            ),
+            order_identifier: OrderId(
+                Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+            ),
+            line_items: vec![],
+            group_identifier: None,
+            customer_identifier: None,
+        });
+
+        let results: Vec<_> = crate::handle_with_offsets(place_order, None)
+            .unwrap()
+            .collect();
+        let correlation_id = results[0].4;
+        // The order placed at the restaurant, and the order created by the saga
+        assert_eq!(2, results.len());
+        assert!(results.iter().all(|(_, _, _, _, id)| *id == correlation_id));
+        assert_eq!(
+            results
+                .into_iter()
+                .map(|(e, _, _, _, _)| e)
+                .collect::<Vec<_>>(),
+            crate::get_events_by_correlation(correlation_id).unwrap()
+        );
+    }
+
+    #[pg_test]
+    fn saga_trace_test() {
+        Spi::run("SET LOCAL fmodel.saga_traces = on").unwrap();
+        let place_order = Command::PlaceOrder(PlaceOrder {
+            identifier: RestaurantId(
+                Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap(),
+            ),
+            order_identifier: OrderId(
+                Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+            ),
+            line_items: vec![],
+            group_identifier: None,
+            customer_identifier: None,
+        });
+
+        let results: Vec<_> = crate::handle_with_offsets(place_order, None)
+            .unwrap()
+            .collect();
+        let trace: Vec<_> = crate::get_saga_trace(results[0].4).unwrap().collect();
+        // The saga reacted to the order placed at the restaurant, by creating the order
+        assert_eq!(1, trace.len());
+        let (depth, event, commands, events, _) = &trace[0];
+        assert_eq!(0, *depth);
+        assert_eq!(Some("OrderPlaced"), event.0["type"].as_str());
+        assert_eq!(Some("CreateOrder"), commands.0[0]["type"].as_str());
+        assert_eq!(Some("OrderCreated"), events.0[0]["type"].as_str());
+    }
+
+    #[pg_test]
+    fn analyze_event_streams_test() {
+        let place_order = |order_id: &str| {
+            Command::PlaceOrder(PlaceOrder {
+                identifier: RestaurantId(
+                    Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap(),
+                ),
+                order_identifier: OrderId(Uuid::parse_str(order_id).unwrap()),
+                line_items: vec![],
+                group_identifier: None,
+                customer_identifier: None,
+            })
+        };
+        crate::handle(place_order("02f09a3f-1624-3b1d-8409-44eff7708210"), None).unwrap();
+        crate::handle(place_order("6b5b1f4e-7e8a-4c1f-9f3e-2f0d6c1a9b21"), None).unwrap();
+
+        Spi::run("SET fmodel.long_stream_threshold = 1").unwrap();
+        let histogram: Vec<_> = crate::analyze_event_streams().unwrap().collect();
+        // The restaurant stream (created, and two orders placed), and the two order streams (created by the saga)
+        assert!(histogram.contains(&("Order".to_string(), 1, 9, 2, 1)));
+        assert!(histogram
+            .iter()
+            .any(
+                |(decider, from_length, to_length, streams, max_length)| decider == "Restaurant"
+                    && *from_length == 1
+                    && *to_length == 9
+                    && *streams == 1
+                    && *max_length >= 3
+            ));
+        assert_eq!(
+            Ok(Some(true)),
+            Spi::get_one::<bool>("SELECT count(*) > 0 FROM pg_stats WHERE tablename = 'events'")
+        );
+    }
+
+    #[pg_test]
+    fn pending_saga_work_test() {
+        let place_order = || {
+            Command::PlaceOrder(PlaceOrder {
+                identifier: RestaurantId(
+                    Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap(),
+                ),
+                order_identifier: OrderId(
+                    Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                ),
+                line_items: vec![],
+                group_identifier: None,
+                customer_identifier: None,
+            })
+        };
+        assert_eq!(2, crate::handle(place_order(), None).unwrap().len());
+        assert_eq!(0, crate::pending_saga_work().unwrap().count());
+        assert_eq!(
+            Ok(Some(1)),
+            Spi::get_one::<i64>(
+                "SELECT count(*) FROM saga_commands WHERE command_type = 'CreateOrder' AND decider_id = '02f09a3f-1624-3b1d-8409-44eff7708210' AND status = 'succeeded'"
+            )
+        );
+
+        // The order already exists: the saga command is rejected, but the order placed at the restaurant is not
+        assert_eq!(1, crate::handle(place_order(), None).unwrap().len());
+        let pending: Vec<_> = crate::pending_saga_work().unwrap().collect();
+        assert_eq!(1, pending.len());
+        let (_, correlation_id, depth, event, command, status, error, attempts, _) = &pending[0];
+        assert!(correlation_id.is_some());
+        assert_eq!(0, *depth);
+        assert_eq!(Some("OrderPlaced"), event.0["type"].as_str());
+        assert_eq!(Some("CreateOrder"), command.0["type"].as_str());
+        assert_eq!("failed", status);
+        assert_eq!(
+            Some("Failed to create the Order. Order already exists!"),
+            error.as_deref()
+        );
+        assert_eq!(1, *attempts);
+    }
+
+    #[pg_test]
+    fn workflow_status_test() {
+        let place_order = || {
+            Command::PlaceOrder(PlaceOrder {
+                identifier: RestaurantId(
+                    Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap(),
+                ),
+                order_identifier: OrderId(
+                    Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                ),
+                line_items: vec![],
+                group_identifier: None,
+                customer_identifier: None,
+            })
+        };
+        let correlation_id = || {
+            crate::handle_with_offsets(place_order(), None)
+                .unwrap()
+                .next()
+                .unwrap()
+                .4
+        };
+
+        // The saga created the order: the workflow waits for the next event (the order prepared)
+        let first = correlation_id();
+        let workflow: Vec<_> = crate::workflow_status(first).unwrap().collect();
+        assert_eq!(1, workflow.len());
+        let (status, steps) = &workflow[0];
+        assert_eq!("waiting", status);
+        assert_eq!(
+            Some("OrderPlaced"),
+            steps.0[0]["step"]["event_type"].as_str()
+        );
+        assert_eq!(
+            Some("CreateOrder"),
+            steps.0[0]["step"]["command_type"].as_str()
+        );
+        assert_eq!(Some("succeeded"), steps.0[0]["status"].as_str());
+
+        // The order already exists: the workflow is stuck, and it stays stuck when the command is rejected again
+        let second = correlation_id();
+        crate::retry_failed_saga_commands(10)
+            .unwrap()
+            .for_each(drop);
+        let (status, steps) = crate::workflow_status(second).unwrap().next().unwrap();
+        assert_eq!("stuck", status);
+        assert_eq!(Some(2), steps.0[0]["attempts"].as_i64());
+        assert_eq!(
+            Some("Failed to create the Order. Order already exists!"),
+            steps.0[0]["error"].as_str()
+        );
+        assert_eq!(
+            0,
+            crate::workflow_status(Uuid::from_bytes([0; 16]))
+                .unwrap()
+                .count()
+        );
+    }
+
+    #[pg_test]
+    fn retry_saga_commands_test() {
+        let order_id = Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap();
+        let place_order = || {
+            Command::PlaceOrder(PlaceOrder {
+                identifier: RestaurantId(
+                    Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap(),
+                ),
+                order_identifier: OrderId(order_id),
+                line_items: vec![],
+                group_identifier: None,
+                customer_identifier: None,
+            })
+        };
+        crate::handle(place_order(), None).unwrap();
+        // The order already exists: the saga command fails, and it is rejected again when re-driven
+        crate::handle(place_order(), None).unwrap();
+        let retried: Vec<_> = crate::retry_failed_saga_commands(10).unwrap().collect();
+        assert_eq!(1, retried.len());
+        let (id, status, error) = retried[0].clone();
+        assert_eq!("failed", status);
+        assert_eq!(
+            Some("Failed to create the Order. Order already exists!"),
+            error.as_deref()
+        );
+        assert_eq!(2, crate::pending_saga_work().unwrap().next().unwrap().7);
+
+        // The cause of the failure is resolved meanwhile (simulated: the command is re-addressed to the new order stream)
+        Spi::run_with_args(
+            "UPDATE saga_commands SET command = jsonb_set(command, '{identifier}', to_jsonb($2::TEXT)) WHERE id = $1",
+            Some(vec![
+                (PgBuiltInOids::INT8OID.oid(), id.into_datum()),
+                (
+                    PgBuiltInOids::TEXTOID.oid(),
+                    "7f4a1c2e-5b6d-4e8f-9a0b-1c2d3e4f5a6b".into_datum(),
+                ),
+            ]),
+        )
+        .unwrap();
+        let retried: Vec<_> = crate::retry_saga_command(id).unwrap().collect();
+        assert_eq!(vec![(id, "succeeded".to_string(), None)], retried);
+        assert_eq!(0, crate::pending_saga_work().unwrap().count());
+        // The order is created, within the correlation of the original business transaction
+        assert_eq!(
+            Ok(Some(1)),
+            Spi::get_one::<i64>(
+                "SELECT count(*) FROM events e JOIN saga_commands s ON s.correlation_id = e.correlation_id WHERE e.event = 'OrderCreated' AND e.decider_id = '7f4a1c2e-5b6d-4e8f-9a0b-1c2d3e4f5a6b'"
+            )
+        );
+        // Idempotency: the succeeded command is not handled again
+        assert!(crate::retry_saga_command(id).is_err());
+    }
+
+    #[pg_test]
+    fn handle_json_test() {
+        let events = crate::handle_json(
+            pgrx::JsonB(serde_json::json!({
+                "type": "ChangeRestaurantCapacity",
+                "identifier": "e48d4d9e-403e-453f-b1ba-328e0ce23737",
+                "max_concurrent_orders": 10
+            })),
+            None,
+        )
+        .unwrap();
+        assert_eq!(1, events.len());
+
+        // The command of the newer client
+        let err = crate::handle_json(
+            pgrx::JsonB(serde_json::json!({
+                "type": "ArchiveRestaurant",
+                "identifier": "e48d4d9e-403e-453f-b1ba-328e0ce23737"
+            })),
+            None,
+        )
+        .unwrap_err();
+        assert!(err
+            .message
+            .starts_with("Unsupported command type `ArchiveRestaurant` (extension version "));
+        assert!(err.message.contains("supported: [CreateRestaurant, "));
+
+        // Every supported command type is known to the `Command` enum
+        for command_type in crate::domain::COMMAND_TYPES {
+            let err =
+                serde_json::from_value::<Command>(serde_json::json!({ "type": command_type }))
+                    .unwrap_err();
+            assert!(!err.to_string().starts_with("unknown variant"));
+        }
+    }
+
+    #[pg_test]
+    fn saga_rules_test() {
+        Spi::run(
+            "INSERT INTO saga_rules (event, command, enabled, reason) VALUES ('OrderPlaced', 'CreateOrder', FALSE, 'incident')",
+        )
+        .unwrap();
+        let place_order = Command::PlaceOrder(PlaceOrder {
+            identifier: RestaurantId(
+                Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap(),
+            ),
+            order_identifier: OrderId(
+                Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+            ),
+            line_items: vec![],
+            group_identifier: None,
+            customer_identifier: None,
+        });
+
+        // The order placed at the restaurant only, the order is not created by the saga
+        let events = crate::handle(place_order, None).unwrap();
+        assert_eq!(1, events.len());
+        assert!(matches!(events[0], Event::OrderPlaced(_)));
+    }
+
+    #[pg_test]
+    fn store_id_test() {
+        let restaurant_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737")
+                .unwrap()
+                .into_bytes(),
+        );
+        let create_restaurant = Command::CreateRestaurant(CreateRestaurant {
+            identifier: RestaurantId(
+                Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap(),
+            ),
+            name: RestaurantName("Isolated".to_string()),
+            menu: RestaurantMenu {
+                menu_id: MenuId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap()),
+                items: vec![],
+                cuisine: RestaurantMenuCuisine::Vietnamese,
+                currency: None,
+            },
+        });
+        let default_events = crate::get_events(restaurant_id).unwrap();
+
+        // The restaurant exists in the `default` store only: it is created in the `catering` store
+        let events = crate::handle(create_restaurant, Some("catering")).unwrap();
+        assert_eq!(1, events.len());
+        assert!(matches!(events[0], Event::RestaurantCreated(_)));
+
+        // The `default` store and its projections are not affected
+        assert_eq!(default_events, crate::get_events(restaurant_id).unwrap());
+        assert_eq!(
+            Some("Pljeska".to_string()),
+            Spi::get_one::<String>(
+                "SELECT data->>'name' FROM restaurants WHERE store_id = 'default' AND id = 'e48d4d9e-403e-453f-b1ba-328e0ce23737'"
+            )
+            .unwrap()
+        );
+        // The event is projected into the rows of its own store
+        assert_eq!(
+            Some("Isolated".to_string()),
+            Spi::get_one::<String>(
+                "SELECT data->>'name' FROM restaurants WHERE store_id = 'catering' AND id = 'e48d4d9e-403e-453f-b1ba-328e0ce23737'"
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            Some(1),
+            Spi::get_one::<i64>("SELECT COUNT(*) FROM events WHERE store_id = 'catering'").unwrap()
+        );
+    }
+
+    #[pg_test]
+    fn payload_offload_test() {
+        Spi::run("SET LOCAL fmodel.payload_offload_threshold = 100").unwrap();
+        let restaurant_id = Uuid::parse_str("7a1c2d3e-4f50-4a6b-8c9d-0e1f2a3b4c5d").unwrap();
+        let create_restaurant = Command::CreateRestaurant(CreateRestaurant {
+            identifier: RestaurantId(restaurant_id),
+            name: RestaurantName("Offloaded".to_string()),
+            menu: RestaurantMenu {
+                menu_id: MenuId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap()),
+                items: vec![MenuItem {
+                    id: MenuItemId(
+                        Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                    ),
+                    name: MenuItemName("supa".to_string()),
+                    price: Money(10),
+                }],
+                cuisine: RestaurantMenuCuisine::Vietnamese,
+                currency: None,
+            },
+        });
+        let events = crate::handle(create_restaurant, None).unwrap();
+
+        // The `events` row keeps the reference envelope only
+        assert_eq!(
+            Some(1),
+            Spi::get_one::<i64>(
+                "SELECT COUNT(*) FROM events JOIN event_payloads USING (event_id) WHERE decider_id = '7a1c2d3e-4f50-4a6b-8c9d-0e1f2a3b4c5d' AND events.data ? 'payload_ref' AND NOT events.data ? 'menu'"
+            )
+            .unwrap()
+        );
+        // The payload is rehydrated transparently
+        assert_eq!(
+            events,
+            crate::get_events(pgrx::Uuid::from_bytes(restaurant_id.into_bytes())).unwrap()
+        );
+        assert_eq!(
+            Some("Offloaded".to_string()),
+            Spi::get_one::<String>(
+                "SELECT data->>'name' FROM restaurants WHERE id = '7a1c2d3e-4f50-4a6b-8c9d-0e1f2a3b4c5d'"
+            )
+            .unwrap()
+        );
+    }
+
+    #[pg_test]
+    fn get_events_test() {
+        let restaurant_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737")
+                .unwrap()
+                .into_bytes(),
+        );
+        let events = crate::get_events(restaurant_id).unwrap();
+        assert!(!events.is_empty());
+        assert_eq!(
+            Some(events.len() as i64),
+            crate::stream_version(restaurant_id).unwrap()
+        );
+    }
+
+    #[pg_test]
+    fn menu_history_test() {
+        let restaurant_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737")
+                .unwrap()
+                .into_bytes(),
+        );
+        let change_restaurant_menu = |price: u64| {
+            Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
+                identifier: RestaurantId(
+                    Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap(),
+                ),
+                menu: RestaurantMenu {
+                    menu_id: MenuId(
+                        Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                    ),
+                    items: vec![MenuItem {
+                        id: MenuItemId(
+                            Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                        ),
+                        name: MenuItemName("supa".to_string()),
+                        price: Money(price),
+                    }],
+                    cuisine: RestaurantMenuCuisine::Vietnamese,
+                    currency: None,
+                },
+            })
+        };
+        crate::handle(change_restaurant_menu(12), None).unwrap();
+        let events = crate::handle(change_restaurant_menu(14), None).unwrap();
+        assert!(matches!(
+            events.first(),
+            Some(Event::RestaurantMenuChanged(RestaurantMenuChanged {
+                menu_version: RestaurantMenuVersion(3),
+                ..
+            }))
+        ));
+
+        let history: Vec<_> = crate::menu_history(restaurant_id).unwrap().collect();
+        assert_eq!(
+            vec![1, 2, 3],
+            history
+                .iter()
+                .map(|(menu_version, _, _, _)| *menu_version)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(serde_json::json!(14), history[2].1 .0["items"][0]["price"]);
+        assert!(history[..2].iter().all(|(_, _, _, to)| to.is_some()));
+        assert!(history[2].3.is_none());
+    }
+
+    #[pg_test]
+    fn event_cursor_test() {
+        let cursor = crate::open_event_cursor(0).unwrap();
+        let first: Vec<_> = crate::fetch_event_batch(&cursor, 1).unwrap().collect();
+        assert_eq!(1, first.len());
+        // The next batch continues after the first one
+        let rest: Vec<_> = crate::fetch_event_batch(&cursor, 1000).unwrap().collect();
+        assert!(rest.iter().all(|(_, _, offset, _)| *offset > first[0].2));
+        assert_eq!(0, crate::fetch_event_batch(&cursor, 1000).unwrap().count());
+        crate::close_event_cursor(&cursor).unwrap();
+        assert!(crate::fetch_event_batch(&cursor, 1).is_err());
+    }
+
+    #[pg_test]
+    fn error_context_test() {
+        // The SPI error carries the operation, the stream and the SQLSTATE
+        let error = crate::framework::infrastructure::event_cursor::fetch_event_batch("missing", 1)
+            .unwrap_err();
+        let context = error.context.clone().unwrap();
+        assert_eq!("use the event cursor", context.operation);
+        assert_eq!(None, context.stream);
+        assert_eq!("34000", context.sql_state);
+        assert_eq!(
+            "Failed to use the event cursor: Cursor named missing not found (SQLSTATE: 34000)",
+            error.to_string()
+        );
+        let error = ErrorMessage::spi(
+            "fetch events",
+            Some("e48d4d9e-403e-453f-b1ba-328e0ce23737".to_string()),
+            &pgrx::spi::Error::CursorNotFound("missing".to_string()),
+        );
+        assert_eq!(
+            "Failed to fetch events: Cursor named missing not found (stream: e48d4d9e-403e-453f-b1ba-328e0ce23737, SQLSTATE: 34000)",
+            error.to_string()
+        );
+    }
+
+    #[pg_test]
+    fn event_validation_test() {
+        let menu_item = MenuItem {
+            id: MenuItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap()),
+            name: MenuItemName(" ".to_string()),
+            price: Money(100),
+        };
+        let change_restaurant_menu = Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
+            identifier: RestaurantId(
+                Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap(),
+            ),
+            menu: RestaurantMenu {
+                menu_id: MenuId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap()),
+                items: vec![menu_item.clone(), menu_item],
+                cuisine: RestaurantMenuCuisine::Other,
+                currency: None,
+            },
+        });
+        let count_events = || Spi::get_one::<i64>("SELECT COUNT(*) FROM events").unwrap();
+        let events = count_events();
+        // The decider does not check the menu items: the invalid event is rejected before it is saved, with all the violations listed
+        let error = crate::handle(change_restaurant_menu, None).unwrap_err();
+        assert_eq!(
+            "Failed to save the events. The events are invalid: \
+             RestaurantMenuChanged e48d4d9e-403e-453f-b1ba-328e0ce23737: the menu item `02f09a3f-1624-3b1d-8409-44eff7708210` has no name; \
+             RestaurantMenuChanged e48d4d9e-403e-453f-b1ba-328e0ce23737: the menu item `02f09a3f-1624-3b1d-8409-44eff7708210` has no name; \
+             RestaurantMenuChanged e48d4d9e-403e-453f-b1ba-328e0ce23737: the menu item `02f09a3f-1624-3b1d-8409-44eff7708210` is duplicated",
+            error.message
+        );
+        assert_eq!(events, count_events());
+    }
+
+    #[pg_test]
+    fn fork_stream_test() {
+        let restaurant_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737")
+                .unwrap()
+                .into_bytes(),
+        );
+        let fork_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("5b0a2b7e-6f6c-4d2c-9d55-5c1f3f0e7a01")
+                .unwrap()
+                .into_bytes(),
+        );
+        let forked = crate::fork_stream(restaurant_id, fork_id, i64::MAX).unwrap();
+        assert!(matches!(
+            forked.as_slice(),
+            [Event::RestaurantCreated(RestaurantCreated { identifier, .. })]
+                if identifier.0.as_bytes() == fork_id.as_bytes()
+        ));
+
+        // The hypothetical command is handled against the fork only
+        crate::handle(
+            Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
+                identifier: RestaurantId(Uuid::from_bytes(*fork_id.as_bytes())),
+                menu: RestaurantMenu {
+                    menu_id: MenuId(
+                        Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                    ),
+                    items: vec![],
+                    cuisine: RestaurantMenuCuisine::Vietnamese,
+                    currency: None,
+                },
+            }),
+            None,
+        )
+        .unwrap();
+        assert_eq!(Some(2), crate::stream_version(fork_id).unwrap());
+        assert_eq!(Some(1), crate::stream_version(restaurant_id).unwrap());
+        // The fork already exists
+        assert!(crate::fork_stream(restaurant_id, fork_id, i64::MAX).is_err());
+    }
+
+    #[pg_test]
+    fn export_import_stream_test() {
+        let restaurant_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737")
+                .unwrap()
+                .into_bytes(),
+        );
+        let document = crate::export_stream(restaurant_id).unwrap().0;
+        assert_eq!(1, document["events"].as_array().unwrap().len());
+        assert_eq!(Some(1), document["events"][0]["sequence_number"].as_i64());
+        assert_eq!("Pljeska", document["state"]["name"]);
+        // The stream already exists
+        assert!(crate::import_stream(pgrx::JsonB(document.clone()), None).is_err());
+
+        // Imported as the new stream (e.g. into the local development database), with the new identity
+        let import_id = "6c1b3c8f-7a7d-4e3d-8e66-6d2a4a1f8b02";
+        let identity_map = serde_json::json!({ "e48d4d9e-403e-453f-b1ba-328e0ce23737": import_id });
+        assert_eq!(
+            1,
+            crate::import_stream(pgrx::JsonB(document), Some(pgrx::JsonB(identity_map)))
+                .unwrap()
+                .len()
+        );
+        let import_id = pgrx::Uuid::from_bytes(Uuid::parse_str(import_id).unwrap().into_bytes());
+        assert_eq!(Some(1), crate::stream_version(import_id).unwrap());
+        assert_eq!(
+            "Pljeska",
+            crate::export_stream(import_id).unwrap().0["state"]["name"]
+        );
+    }
+
+    #[pg_test]
+    fn import_stream_identity_map_test() {
+        let restaurant_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737")
+                .unwrap()
+                .into_bytes(),
+        );
+        // The restaurant and its menu items get the new identities (the identifiers are matched case-insensitively)
+        let identity_map = serde_json::json!({
+            "E48D4D9E-403E-453F-B1BA-328E0CE23737": "7d2c4d9a-8b8e-4f4e-9f77-7e3b5b2a9c03",
+            "02f09a3f-1624-3b1d-8409-44eff7708210": "8e3d5eab-9c9f-4a5f-8a88-8f4c6c3bad04"
+        });
+        let imported = crate::import_stream(
+            crate::export_stream(restaurant_id).unwrap(),
+            Some(pgrx::JsonB(identity_map)),
+        )
+        .unwrap();
+        let data = serde_json::to_value(&imported).unwrap();
+        assert_eq!(
+            "7d2c4d9a-8b8e-4f4e-9f77-7e3b5b2a9c03",
+            data[0]["identifier"]
+        );
+        assert_eq!(
+            "8e3d5eab-9c9f-4a5f-8a88-8f4c6c3bad04",
+            data[0]["menu"]["items"][0]["id"]
+        );
+        // No real identifier is leaked
+        assert!(!data
+            .to_string()
+            .contains("e48d4d9e-403e-453f-b1ba-328e0ce23737"));
+        assert!(!data
+            .to_string()
+            .contains("02f09a3f-1624-3b1d-8409-44eff7708210"));
+        // The identity map must map the UUIDs to the UUIDs
+        let identity_map =
+            serde_json::json!({ "e48d4d9e-403e-453f-b1ba-328e0ce23737": "not-a-uuid" });
+        assert!(crate::import_stream(
+            crate::export_stream(restaurant_id).unwrap(),
+            Some(pgrx::JsonB(identity_map)),
+        )
+        .is_err());
+    }
+
+    #[pg_test]
+    fn function_volatility_test() {
+        let volatility = |function: &str| {
+            Spi::get_one_with_args::<String>(
+                "SELECT provolatile::TEXT || proparallel::TEXT FROM pg_proc WHERE proname = $1",
+                vec![(PgBuiltInOids::TEXTOID.oid(), function.into_datum())],
+            )
+            .unwrap()
+        };
+        // The functions replaying the stream through the aggregate of the backend are volatile (and parallel unsafe)
+        assert_eq!(Some("vu".to_string()), volatility("state_diff"));
+        assert_eq!(Some("vu".to_string()), volatility("export_stream"));
+        // The read-only functions are stable and parallel safe
+        assert_eq!(Some("ss".to_string()), volatility("get_events"));
+        assert_eq!(Some("ss".to_string()), volatility("stream_version"));
+    }
+
+    #[pg_test]
+    fn state_diff_test() {
+        let restaurant_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737")
+                .unwrap()
+                .into_bytes(),
+        );
+        // The restaurant is created: the restaurant state (the first in the tuple) is replaced
+        let pgrx::JsonB(patch) = crate::state_diff(restaurant_id, 0, i64::MAX).unwrap();
+        assert_eq!(
+            Some("/0"),
+            patch[0]["path"].as_str(),
+            "unexpected patch: {patch}"
+        );
+        let pgrx::JsonB(patch) = crate::state_diff(restaurant_id, i64::MAX, i64::MAX).unwrap();
+        assert_eq!(serde_json::json!([]), patch);
+    }
+
+    #[pg_test]
+    fn cached_aggregate_test() {
+        // The aggregate is constructed once per backend, and reused by the commands
+        assert!(std::ptr::eq(
+            crate::application::order_restaurant_aggregate::cached_order_restaurant_aggregate(),
+            crate::application::order_restaurant_aggregate::cached_order_restaurant_aggregate()
+        ));
+        let change_menu = || {
+            Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
+                identifier: RestaurantId(
+                    Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap(),
+                ),
+                menu: RestaurantMenu {
+                    menu_id: MenuId(
+                        Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                    ),
+                    items: vec![],
+                    cuisine: RestaurantMenuCuisine::Vietnamese,
+                    currency: None,
+                },
+            })
+        };
+        // The state is not cached: every command folds the current events of its stream
+        let versions: Vec<u64> = (0..2)
+            .map(|_| match &crate::handle(change_menu(), None).unwrap()[0] {
+                Event::RestaurantMenuChanged(event) => event.menu_version.0,
+                event => panic!("unexpected event: {event:?}"),
+            })
+            .collect();
+        assert_eq!(versions[0] + 1, versions[1]);
+    }
+
+    #[pg_test]
+    fn state_cache_test() {
+        let restaurant_id = Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap();
+        // The state is cached as JSON: the round trip keeps the state
+        let state = crate::application::order_restaurant_aggregate::order_restaurant_aggregate()
+            .state_at(&restaurant_id, i64::MAX)
+            .unwrap();
+        assert!(state.0.is_some());
+        let json = serde_json::to_vec(&state).unwrap();
+        assert_eq!(state, serde_json::from_slice(&json).unwrap());
+        // Without the shared memory (not loaded via `shared_preload_libraries`), the cache is disabled, and the stream is replayed
+        Spi::run("SET fmodel.state_cache = on").unwrap();
+        assert!(!crate::framework::infrastructure::state_cache::is_enabled());
+        let events = crate::handle(
+            Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
+                identifier: RestaurantId(restaurant_id),
+                menu: RestaurantMenu {
+                    menu_id: MenuId(
+                        Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                    ),
+                    items: vec![],
+                    cuisine: RestaurantMenuCuisine::Vietnamese,
+                    currency: None,
+                },
+            }),
+            None,
+        )
+        .unwrap();
+        assert_eq!(1, events.len());
+    }
+
+    #[pg_test]
+    fn handle_ids_test() {
+        let restaurant_identifier =
+            RestaurantId(Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap());
+        let change_menu = |price| {
+            Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
+                identifier: restaurant_identifier.clone(),
+                menu: RestaurantMenu {
+                    menu_id: MenuId(
+                        Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                    ),
+                    items: vec![MenuItem {
+                        id: MenuItemId(
+                            Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                        ),
+                        name: MenuItemName("Item 1".to_string()),
+                        price: Money(price),
+                    }],
+                    cuisine: RestaurantMenuCuisine::Vietnamese,
+                    currency: None,
+                },
+            })
+        };
+        let ids = crate::handle_ids(change_menu(100u64), None).unwrap();
+        assert_eq!(1, ids.len());
+        // The returned id is the id of the persisted event, and the view is projected
+        assert_eq!(
+            Some(100),
+            Spi::get_one_with_args::<i64>(
+                "SELECT (data->'menu'->'items'->0->>'price')::BIGINT FROM events WHERE event_id = $1",
+                vec![(PgBuiltInOids::UUIDOID.oid(), ids[0].into_datum())],
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            Some(100),
+            Spi::get_one::<i64>(
+                "SELECT (data->'menu'->'items'->0->>'price')::BIGINT FROM restaurants WHERE id = 'e48d4d9e-403e-453f-b1ba-328e0ce23737'"
+            )
+            .unwrap()
+        );
+        let ids =
+            crate::handle_all_ids(vec![change_menu(200u64), change_menu(300u64)], None).unwrap();
+        assert_eq!(2, ids.len());
+        assert_ne!(ids[0], ids[1]);
+    }
+
+    #[pg_test]
+    fn idempotent_projection_test() {
+        let restaurant_identifier =
+            RestaurantId(Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap());
+        let restaurant_id = pgrx::Uuid::from_bytes(restaurant_identifier.0.into_bytes());
+        let menu = |price| RestaurantMenu {
+            menu_id: MenuId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap()),
+            items: vec![MenuItem {
+                id: MenuItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap()),
+                name: MenuItemName("Item 1".to_string()),
+                price: Money(price),
+            }],
+            cuisine: RestaurantMenuCuisine::Vietnamese,
+            currency: None,
+        };
+        let (_, event_id, offset, sequence_number, _) = crate::handle_with_offsets(
+            Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
+                identifier: restaurant_identifier.clone(),
+                menu: menu(100u64),
+            }),
+            None,
+        )
+        .unwrap()
+        .last()
+        .unwrap();
+
+        // Replaying the (already applied) event does not change the view
+        crate::application::order_restaurant_projector::project_restaurant_event(
+            &Event::RestaurantMenuChanged(RestaurantMenuChanged {
+                identifier: restaurant_identifier,
+                menu: menu(200u64),
+                menu_version: RestaurantMenuVersion(2),
+                r#final: false,
+            }),
+            &EventPosition {
+                event_id: Uuid::from_bytes(*event_id.as_bytes()),
+                offset,
+                sequence_number,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            Some(offset),
+            crate::restaurant_view_version(restaurant_id).unwrap()
+        );
+        assert_eq!(
+            Some(100),
+            Spi::get_one::<i64>(
+                "SELECT (data->'menu'->'items'->0->>'price')::BIGINT FROM restaurants WHERE id = 'e48d4d9e-403e-453f-b1ba-328e0ce23737'"
+            )
+            .unwrap()
+        );
+    }
+
+    #[pg_test]
+    fn out_of_order_events_test() {
+        let restaurant_identifier =
+            RestaurantId(Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap());
+        let menu = |price| RestaurantMenu {
+            menu_id: MenuId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap()),
+            items: vec![MenuItem {
+                id: MenuItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap()),
+                name: MenuItemName("Item 1".to_string()),
+                price: Money(price),
+            }],
+            cuisine: RestaurantMenuCuisine::Vietnamese,
+            currency: None,
+        };
+        let (_, event_id, offset, sequence_number, _) = crate::handle_with_offsets(
+            Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
+                identifier: restaurant_identifier.clone(),
+                menu: menu(100u64),
+            }),
+            None,
+        )
+        .unwrap()
+        .last()
+        .unwrap();
+        let replay = || {
+            crate::application::order_restaurant_projector::project_restaurant_event(
+                &Event::RestaurantMenuChanged(RestaurantMenuChanged {
+                    identifier: restaurant_identifier.clone(),
+                    menu: menu(200u64),
+                    menu_version: RestaurantMenuVersion(2),
+                    r#final: false,
+                }),
+                &EventPosition {
+                    event_id: Uuid::from_bytes(*event_id.as_bytes()),
+                    offset: offset - 1,
+                    sequence_number,
+                },
+            )
+        };
+        let price = || {
+            Spi::get_one::<i64>(
+                "SELECT (data->'menu'->'items'->0->>'price')::BIGINT FROM restaurants WHERE id = 'e48d4d9e-403e-453f-b1ba-328e0ce23737'",
+            )
+            .unwrap()
+        };
+
+        Spi::run("SET LOCAL fmodel.out_of_order_events = 'reject'").unwrap();
+        assert!(replay().is_err());
+        assert_eq!(Some(100), price());
+
+        Spi::run("SET LOCAL fmodel.out_of_order_events = 'apply'").unwrap();
+        replay().unwrap();
+        assert_eq!(Some(200), price());
+    }
+
+    #[pg_test]
+    fn commit_ordered_view_test() {
+        crate::set_projection_mode("restaurants", "async").unwrap();
+        // Two interleaved transactions changing the menu of the restaurant (the same view row):
+        // the event with the lower offset is appended by the transaction that commits later (simulated by the higher transaction id)
+        Spi::run(
+            r#"INSERT INTO events (event, event_id, decider, decider_id, data, command_id, previous_id, final, transaction_id)
+               VALUES ('RestaurantMenuChanged', 'f6dabcf9-de60-4253-8fa7-8b9fa2b3c4d5', 'Restaurant', 'e48d4d9e-403e-453f-b1ba-328e0ce23737',
+                       '{"type": "RestaurantMenuChanged", "identifier": "e48d4d9e-403e-453f-b1ba-328e0ce23737", "menu": {"menu_id": "02f09a3f-1624-3b1d-8409-44eff7708210", "items": [], "cuisine": "Thai"}, "final": false}',
+                       NULL, (SELECT event_id FROM events WHERE decider_id = 'e48d4d9e-403e-453f-b1ba-328e0ce23737' ORDER BY "offset" DESC LIMIT 1), FALSE,
+                       (pg_current_xact_id()::TEXT::BIGINT + 2)::TEXT::XID8);
+               INSERT INTO events (event, event_id, decider, decider_id, data, command_id, previous_id, final, transaction_id)
+               VALUES ('RestaurantMenuChanged', '07ebcd0a-ef71-4364-9a08-9ca0b3c4d5e6', 'Restaurant', 'e48d4d9e-403e-453f-b1ba-328e0ce23737',
+                       '{"type": "RestaurantMenuChanged", "identifier": "e48d4d9e-403e-453f-b1ba-328e0ce23737", "menu": {"menu_id": "02f09a3f-1624-3b1d-8409-44eff7708210", "items": [], "cuisine": "Greek"}, "final": false}',
+                       NULL, 'f6dabcf9-de60-4253-8fa7-8b9fa2b3c4d5', FALSE,
+                       (pg_current_xact_id()::TEXT::BIGINT + 1)::TEXT::XID8)"#,
+        )
+        .unwrap();
+        let position = |event_id: &str| {
+            let offset = Spi::get_one_with_args::<i64>(
+                r#"SELECT "offset" FROM events WHERE event_id = $1::UUID"#,
+                vec![(PgBuiltInOids::TEXTOID.oid(), event_id.into_datum())],
+            )
+            .unwrap()
+            .unwrap();
+            EventPosition {
+                event_id: Uuid::parse_str(event_id).unwrap(),
+                offset,
+                sequence_number: 0,
+            }
+        };
+        let menu_changed = |cuisine| {
+            Event::RestaurantMenuChanged(RestaurantMenuChanged {
+                identifier: RestaurantId(
+                    Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap(),
+                ),
+                menu: RestaurantMenu {
+                    menu_id: MenuId(
+                        Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                    ),
+                    items: vec![],
+                    cuisine,
+                    currency: None,
+                },
+                menu_version: RestaurantMenuVersion(2),
+                r#final: false,
+            })
+        };
+        let apply = |cuisine, event_id| {
+            crate::application::order_restaurant_projector::project_restaurant_event(
+                &menu_changed(cuisine),
+                &position(event_id),
+            )
+        };
+        let cuisine = || {
+            Spi::get_one::<String>(
+                "SELECT data->'menu'->>'cuisine' FROM restaurants WHERE id = 'e48d4d9e-403e-453f-b1ba-328e0ce23737'",
+            )
+            .unwrap()
+        };
+
+        // Applied in the commit order: the event with the higher offset first, then the one with the lower offset, which is new (not out of order)
+        Spi::run("SET LOCAL fmodel.out_of_order_events = 'reject'").unwrap();
+        apply(
+            RestaurantMenuCuisine::Greek,
+            "07ebcd0a-ef71-4364-9a08-9ca0b3c4d5e6",
+        )
+        .unwrap();
+        apply(
+            RestaurantMenuCuisine::Thai,
+            "f6dabcf9-de60-4253-8fa7-8b9fa2b3c4d5",
+        )
+        .unwrap();
+        assert_eq!(Some("Thai".to_string()), cuisine());
+        // The event of the earlier transaction is already applied
+        assert!(apply(
+            RestaurantMenuCuisine::Greek,
+            "07ebcd0a-ef71-4364-9a08-9ca0b3c4d5e6"
+        )
+        .is_err());
+    }
+
+    #[pg_test]
+    fn trigger_error_sql_state_test() {
+        // The event of the type unknown to this version of the extension, e.g. appended by a newer version during a rolling upgrade
+        Spi::run(
+            r#"
+            INSERT INTO deciders (decider, event) VALUES ('Restaurant', 'RestaurantRenamed');
+            CREATE TEMP TABLE sql_states (sql_state TEXT);
+            DO $$
+            BEGIN
+                INSERT INTO events (event, event_id, decider, decider_id, data, command_id, previous_id, final)
+                VALUES ('RestaurantRenamed', gen_random_uuid(), 'Restaurant', 'e48d4d9e-403e-453f-b1ba-328e0ce23737',
+                        '{"type": "RestaurantRenamed", "identifier": "e48d4d9e-403e-453f-b1ba-328e0ce23737", "name": "Pljeska 2", "final": false}',
+                        NULL,
+                        (SELECT event_id FROM events WHERE decider_id = 'e48d4d9e-403e-453f-b1ba-328e0ce23737' ORDER BY "offset" DESC LIMIT 1),
+                        FALSE);
+            EXCEPTION WHEN OTHERS THEN
+                INSERT INTO sql_states VALUES (SQLSTATE);
+            END $$;
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            Some("0A000".to_string()),
+            Spi::get_one::<String>("SELECT sql_state FROM sql_states").unwrap()
+        );
+    }
+
+    #[pg_test]
+    fn token_bucket_test() {
+        let mut bucket = TokenBucket::new(1, 0, 2.0);
+        // Burst of 2 commands, then 1 command per second
+        assert_eq!(Ok(()), bucket.take(0, 1.0, 2.0));
+        assert_eq!(Ok(()), bucket.take(0, 1.0, 2.0));
+        assert_eq!(Err(Duration::from_secs(1)), bucket.take(0, 1.0, 2.0));
+        assert_eq!(Ok(()), bucket.take(1_000_000, 1.0, 2.0));
+    }
+
+    #[pg_test]
+    fn handle_with_retry_test() {
+        // The procedure commits the handled command: it can not be called in the transaction of the test
+        assert_eq!(
+            Ok(Some(true)),
+            Spi::get_one::<bool>(
+                "SELECT to_regprocedure('handle_with_retry(Command, Event[], TEXT)') IS NOT NULL"
+            )
+        );
+        assert_eq!(
+            Ok(Some("3".to_string())),
+            Spi::get_one::<String>("SELECT current_setting('fmodel.serialization_retries')")
+        );
+    }
+
+    // The procedure commits the handled command: it is called in a session of its own, outside of the transaction of the `#[pg_test]`
+    #[cfg(test)]
+    #[test]
+    fn handle_with_retry_serialization_failure_test() {
+        pgrx_tests::run_test(
+            "handle_with_retry_test",
+            None,
+            crate::pg_test::postgresql_conf_options(),
+        )
+        .unwrap();
+        let (mut client, _) = pgrx_tests::client().unwrap();
+
+        // The first two attempts fail with the (injected) serialization failure, the third one succeeds
+        // Set in its own statement, so the rollback of the failed attempt does not discard it
+        client
+            .batch_execute("SET fmodel.fail_serialization_n = 2")
+            .unwrap();
+        client
+            .batch_execute(
+                r#"CALL handle_with_retry('{"type": "IssueGiftCard", "identifier": "4d2f6b8a-0c1e-4a3b-9d5f-7e9a1b3c5d71", "amount": 50}', NULL, 'handle_with_retry_test');"#,
+            )
+            .unwrap();
+        let issued: i64 = client
+            .query_one(
+                "SELECT count(*) FROM events WHERE decider = 'GiftCard' AND decider_id = '4d2f6b8a-0c1e-4a3b-9d5f-7e9a1b3c5d71' AND store_id = 'handle_with_retry_test'",
+                &[],
+            )
+            .unwrap()
+            .get(0);
+        assert_eq!(1, issued);
+
+        // The serialization failure is raised once the retries are exhausted, and nothing is committed
+        client
+            .batch_execute(
+                "SET fmodel.serialization_retries = 1; SET fmodel.fail_serialization_n = 3",
+            )
+            .unwrap();
+        let error = client
+            .batch_execute(
+                r#"CALL handle_with_retry('{"type": "IssueGiftCard", "identifier": "8b4d0f2a-6c1e-4e3b-a5d7-9f1b3c5e7a82", "amount": 50}', NULL, 'handle_with_retry_test');"#,
+            )
+            .unwrap_err();
+        assert!(error
+            .as_db_error()
+            .is_some_and(|error| error.message().contains("injected serialization failure")));
+        let issued: i64 = client
+            .query_one(
+                "SELECT count(*) FROM events WHERE decider = 'GiftCard' AND decider_id = '8b4d0f2a-6c1e-4e3b-a5d7-9f1b3c5e7a82'",
+                &[],
+            )
+            .unwrap()
+            .get(0);
+        assert_eq!(0, issued);
+    }
+
+    // The procedure is the first call of the new backend: the library (and its settings) is not loaded yet
+    #[cfg(test)]
+    #[test]
+    fn handle_with_retry_new_backend_test() {
+        pgrx_tests::run_test(
+            "handle_with_retry_test",
+            None,
+            crate::pg_test::postgresql_conf_options(),
+        )
+        .unwrap();
+        let (mut client, _) = pgrx_tests::client().unwrap();
+
+        client
+            .batch_execute(
+                r#"CALL handle_with_retry('{"type": "IssueGiftCard", "identifier": "5e3a7c9b-1d2f-4b4c-8e6a-8fab2c4d6e93", "amount": 50}', NULL, 'handle_with_retry_test');"#,
+            )
+            .unwrap();
+        let issued: i64 = client
+            .query_one(
+                "SELECT count(*) FROM events WHERE decider = 'GiftCard' AND decider_id = '5e3a7c9b-1d2f-4b4c-8e6a-8fab2c4d6e93' AND store_id = 'handle_with_retry_test'",
+                &[],
+            )
+            .unwrap()
+            .get(0);
+        assert_eq!(1, issued);
+    }
+
+    #[pg_test]
+    fn command_stats_test() {
+        let mut stats = CommandStats::default();
+        stats.record(Duration::from_millis(10), Some(2));
+        stats.record(Duration::from_millis(30), None);
+        assert_eq!(2, stats.calls);
+        assert_eq!(0.5, stats.failure_rate());
+        assert_eq!(20.0, stats.mean_time_ms());
+        assert_eq!(30.0, stats.max_time_ms);
+        // Only the succeeded commands produce the events
+        assert_eq!(2.0, stats.mean_events());
+        // The shared memory is not initialized in the tests: nothing is tracked
+        crate::handle_ids(
+            Command::CreateRestaurant(CreateRestaurant {
+                identifier: RestaurantId(Uuid::new_v4()),
+                name: RestaurantName("Stats".to_string()),
+                menu: RestaurantMenu {
+                    menu_id: MenuId(Uuid::new_v4()),
+                    items: vec![],
+                    cuisine: RestaurantMenuCuisine::Greek,
+                    currency: None,
+                },
+            }),
+            None,
+        )
+        .unwrap();
+        assert_eq!(0, crate::fmodel_command_stats().count());
+        crate::fmodel_command_stats_reset();
+    }
+
+    #[pg_test]
+    fn spec_report_test() {
+        let report: Vec<_> = crate::spec_report().collect();
+        assert_eq!(11, report.len());
+        assert_eq!(
+            Vec::<&(String, bool, Option<String>)>::new(),
+            report
+                .iter()
+                .filter(|(_, passed, _)| !passed)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[pg_test]
+    fn fmodel_health_test() {
+        let pgrx::JsonB(health) = crate::fmodel_health().unwrap();
+        assert_eq!(Some(2), health["projections"].as_array().map(Vec::len));
+        assert_eq!(Some(false), health["projector_running"].as_bool());
+        assert!(health["latest_offset"]
+            .as_i64()
+            .is_some_and(|offset| offset > 0));
+    }
+
+    #[pg_test]
+    fn freeze_decider_test() {
+        let restaurant_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737")
+                .unwrap()
+                .into_bytes(),
+        );
+        let order_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("7c1e3a5b-9d2f-4b6a-8e0c-2f4a6b8c0d13")
+                .unwrap()
+                .into_bytes(),
+        );
+        let line_items = || {
+            pgrx::JsonB(
+                serde_json::json!([{"id": "02f09a3f-1624-3b1d-8409-44eff7708210", "quantity": 1, "menu_item_id": "02f09a3f-1624-3b1d-8409-44eff7708210", "name": "supa"}]),
+            )
+        };
+        assert!(crate::freeze_decider("Courier").unwrap());
+        assert!(!crate::freeze_decider("Courier").unwrap());
+        assert!(crate::freeze_decider("Kitchen").is_err());
+
+        // The commands of the frozen decider type are rejected
+        assert!(crate::freeze_decider("Restaurant").unwrap());
+        let pgrx::JsonB(health) = crate::fmodel_health().unwrap();
+        assert_eq!(
+            serde_json::json!(["Courier", "Restaurant"]),
+            health["frozen_deciders"]
+        );
+        assert_eq!(
+            "The command PlaceOrder is rejected: the decider `Restaurant` is frozen (see `unfreeze_decider`)",
+            crate::place_order(restaurant_id, order_id, line_items(), None)
+                .unwrap_err()
+                .message
+        );
+        assert!(crate::unfreeze_decider("Restaurant").unwrap());
+        assert!(!crate::unfreeze_decider("Restaurant").unwrap());
+
+        // The commands issued by the saga to the frozen decider type are recorded as failed
+        assert!(crate::freeze_decider("Order").unwrap());
+        let events = crate::place_order(restaurant_id, order_id, line_items(), None).unwrap();
+        assert_eq!(1, events.len());
+        assert!(matches!(events[0], Event::OrderPlaced(_)));
+        let pending: Vec<_> = crate::pending_saga_work().unwrap().collect();
+        assert_eq!(Some("CreateOrder"), pending[0].4 .0["type"].as_str());
+        assert_eq!("failed", pending[0].5);
+        assert!(crate::unfreeze_decider("Order").unwrap());
+        assert!(crate::unfreeze_decider("Courier").unwrap());
+        let pgrx::JsonB(health) = crate::fmodel_health().unwrap();
+        assert_eq!(serde_json::json!([]), health["frozen_deciders"]);
+    }
+
+    #[pg_test]
+    fn migrations_test() {
+        let version = crate::infrastructure::migrations::MIGRATIONS.len() as i32;
+        assert_eq!(version, crate::fmodel_schema_version().unwrap());
+        // Idempotent: nothing is applied twice
+        assert_eq!(version, crate::fmodel_migrate().unwrap());
+        assert_eq!(
+            Some(version as i64),
+            Spi::get_one::<i64>("SELECT COUNT(*) FROM fmodel_schema_version").unwrap()
+        );
+    }
+
+    #[pg_test]
+    fn fmodel_init_test() {
+        let version = crate::infrastructure::migrations::MIGRATIONS.len() as i32;
+        assert_eq!(version, crate::fmodel_init("fmodel_store", None).unwrap());
+        // Idempotent: the provisioned event store is only migrated
+        assert_eq!(version, crate::fmodel_init("fmodel_store", None).unwrap());
+        // The projection triggers are copied, and the new event store is in the search path
+        assert_eq!(
+            Some(true),
+            Spi::get_one::<bool>(
+                "SELECT (SELECT COUNT(*) FROM pg_trigger WHERE tgrelid = 'fmodel_store.events'::regclass AND NOT tgisinternal)
+                      = (SELECT COUNT(*) FROM pg_trigger WHERE tgrelid = 'public.events'::regclass AND NOT tgisinternal)"
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            Some(0),
+            Spi::get_one::<i64>("SELECT COUNT(*) FROM events").unwrap()
+        );
+        assert_eq!(
+            Some(version as i64),
+            Spi::get_one::<i64>("SELECT COUNT(*) FROM fmodel_store.fmodel_schema_version").unwrap()
+        );
+        // The projection tables are created in the new event store, and its events are projected into them
+        for table in crate::infrastructure::schema::PROJECTION_TABLES {
+            assert_eq!(
+                Some(true),
+                Spi::get_one_with_args::<bool>(
+                    "SELECT to_regclass('fmodel_store.' || quote_ident($1)) IS NOT NULL",
+                    vec![(PgBuiltInOids::TEXTOID.oid(), table.into_datum())],
+                )
+                .unwrap()
+            );
+        }
+        let create_restaurant = Command::CreateRestaurant(CreateRestaurant {
+            identifier: RestaurantId(
+                Uuid::parse_str("6d2b8f4a-0e1c-4a3b-9d5f-7e9a1b3c5d82").unwrap(),
+            ),
+            name: RestaurantName("Provisioned".to_string()),
+            menu: RestaurantMenu {
+                menu_id: MenuId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap()),
+                items: vec![],
                 cuisine: RestaurantMenuCuisine::Vietnamese,
+                currency: None,
             },
-            r#final: false,
         });
+        crate::handle(create_restaurant, None).unwrap();
+        assert_eq!(
+            Some(1),
+            Spi::get_one::<i64>(
+                "SELECT COUNT(*) FROM fmodel_store.restaurants WHERE id = '6d2b8f4a-0e1c-4a3b-9d5f-7e9a1b3c5d82'"
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            Some(0),
+            Spi::get_one::<i64>(
+                "SELECT COUNT(*) FROM public.restaurants WHERE id = '6d2b8f4a-0e1c-4a3b-9d5f-7e9a1b3c5d82'"
+            )
+            .unwrap()
+        );
+    }
+
+    #[pg_test]
+    fn fault_injection_test() {
+        let change_menu = |price| {
+            Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
+                identifier: RestaurantId(
+                    Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap(),
+                ),
+                menu: RestaurantMenu {
+                    menu_id: MenuId(
+                        Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                    ),
+                    items: vec![MenuItem {
+                        id: MenuItemId(
+                            Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                        ),
+                        name: MenuItemName("Item 1".to_string()),
+                        price: Money(price),
+                    }],
+                    cuisine: RestaurantMenuCuisine::Vietnamese,
+                    currency: None,
+                },
+            })
+        };
+        // The second event of the transaction fails to save
+        Spi::run("SET LOCAL fmodel.fail_save_after_n = 1").unwrap();
+        let err =
+            crate::handle_all(vec![change_menu(100u64), change_menu(200u64)], None).unwrap_err();
+        assert!(err
+            .message
+            .ends_with("injected fault, `fmodel.fail_save_after_n` = 1"));
+        Spi::run("SET LOCAL fmodel.fail_save_after_n = -1").unwrap();
+        // Every fetch fails with the probability `1`, and none with `0`
+        Spi::run("SET LOCAL fmodel.fail_fetch_probability = 1").unwrap();
+        let err = crate::handle(change_menu(300u64), None).unwrap_err();
+        assert!(err.message.starts_with("Failed to fetch events"));
+        Spi::run("SET LOCAL fmodel.fail_fetch_probability = 0").unwrap();
+        assert!(crate::handle(change_menu(300u64), None).is_ok());
+    }
+
+    #[pg_test]
+    fn schema_check_test() {
+        use crate::framework::infrastructure::schema_check::check_schema;
+        use crate::infrastructure::schema::{EXPECTED_INDEXES, EXPECTED_TABLES};
+
+        assert!(check_schema(&EXPECTED_TABLES, &EXPECTED_INDEXES).is_ok());
+        Spi::run(
+            "ALTER TABLE restaurants DROP COLUMN owner;
+             ALTER TABLE orders ALTER COLUMN owner TYPE VARCHAR(64);
+             DROP INDEX correlation_index;",
+        )
+        .unwrap();
+        assert_eq!(
+            "Extension schema mismatch, run fmodel_migrate(): column `restaurants.owner` is missing; column `orders.owner` is `character varying(64)`, expected `text`; index `correlation_index` is missing",
+            check_schema(&EXPECTED_TABLES, &EXPECTED_INDEXES)
+                .unwrap_err()
+                .message
+        );
+    }
+
+    #[pg_test]
+    fn events_partitioning_test() {
+        assert_eq!(
+            Some("p".to_string()),
+            Spi::get_one::<String>(
+                "SELECT relkind::TEXT FROM pg_class WHERE oid = 'events'::regclass"
+            )
+            .unwrap()
+        );
+        // The seed event is in the partition of its decider
+        assert_eq!(
+            Some(1),
+            Spi::get_one::<i64>("SELECT COUNT(*) FROM events_restaurant").unwrap()
+        );
+        assert_eq!(
+            Some(0),
+            Spi::get_one::<i64>("SELECT COUNT(*) FROM events_order").unwrap()
+        );
+        // The partition is created when the decider type first appears
+        Spi::run("INSERT INTO deciders (decider, event) VALUES ('Kitchen', 'KitchenOpened')")
+            .unwrap();
+        assert_eq!(
+            Some(true),
+            Spi::get_one::<bool>("SELECT to_regclass('events_kitchen') IS NOT NULL").unwrap()
+        );
+    }
+
+    #[pg_test]
+    fn index_advisory_test() {
+        let statuses = |create_missing: bool| {
+            crate::fmodel_index_advisory(create_missing)
+                .unwrap()
+                .map(|(_, _, status)| status)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec!["missing"; 4], statuses(false));
+        assert_eq!(vec!["created"; 4], statuses(true));
+        assert_eq!(vec!["present"; 4], statuses(true));
+    }
+
+    #[pg_test]
+    fn storage_report_test() {
+        let bloated = StorageStats {
+            relation: "orders".to_string(),
+            live_tuples: 6000,
+            dead_tuples: 4000,
+            analyzed: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            vec!["40% bloat; consider pg_repack (or VACUUM FULL, in the maintenance window) of `orders`"],
+            bloated.recommendations()
+        );
+        let relations = crate::fmodel_storage_report()
+            .unwrap()
+            .map(|(relation, parent, ..)| (relation, parent))
+            .collect::<Vec<_>>();
+        assert!(relations.contains(&("restaurants".to_string(), None)));
+        assert!(relations.contains(&("events".to_string(), None)));
+        assert!(relations
+            .iter()
+            .any(|(_, parent)| parent.as_deref() == Some("events")));
+    }
+
+    #[pg_test]
+    fn reference_total_test() {
+        let restaurant_identifier =
+            RestaurantId(Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap());
+        let menu_item_id =
+            MenuItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
+        crate::handle(
+            Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
+                identifier: restaurant_identifier.clone(),
+                menu: RestaurantMenu {
+                    menu_id: MenuId(menu_item_id.0),
+                    items: vec![MenuItem {
+                        id: menu_item_id.clone(),
+                        name: MenuItemName("Item 1".to_string()),
+                        price: Money(250),
+                    }],
+                    cuisine: RestaurantMenuCuisine::Vietnamese,
+                    currency: Some(Currency("EUR".to_string())),
+                },
+            }),
+            None,
+        )
+        .unwrap();
+        let place_order = |order_id: &str| {
+            Command::PlaceOrder(PlaceOrder {
+                identifier: restaurant_identifier.clone(),
+                order_identifier: OrderId(Uuid::parse_str(order_id).unwrap()),
+                line_items: vec![OrderLineItem {
+                    id: OrderLineItemId(menu_item_id.0),
+                    quantity: OrderLineItemQuantity(3),
+                    menu_item_id: menu_item_id.clone(),
+                    name: MenuItemName("Item 1".to_string()),
+                    price: None,
+                    restaurant_identifier: None,
+                }],
+                group_identifier: None,
+                customer_identifier: None,
+            })
+        };
+
+        // 3 x 2.50 EUR, converted to USD at 1.1
+        Spi::run("INSERT INTO exchange_rates (from_currency, to_currency, rate, valid_from) VALUES ('EUR', 'USD', 1.1, now() - INTERVAL '1 day')").unwrap();
+        let reference_total =
+            crate::handle(place_order("1c4b0a3e-8f2d-4e6a-9b7c-5d3e2f1a0b9c"), None)
+                .unwrap()
+                .into_iter()
+                .find_map(|event| match event {
+                    Event::OrderCreated(order_created) => Some(order_created.reference_total),
+                    _ => None,
+                });
+        assert_eq!(
+            Some(Some(ConvertedMoney {
+                amount: Money(825),
+                currency: Currency("USD".to_string()),
+                rate: ExchangeRate(1_100_000),
+            })),
+            reference_total
+        );
+
+        // The converted amount is rounded half up
+        let exchange_rates = FixedExchangeRates {
+            reference_currency: Currency("USD".to_string()),
+            rates: HashMap::from([(Currency("EUR".to_string()), ExchangeRate(1_085_000))]),
+        };
+        assert_eq!(
+            Some(Money(11)),
+            exchange_rates
+                .rate(&Currency("EUR".to_string()), &Currency("USD".to_string()))
+                .map(|rate| rate.convert(&Money(10)))
+        );
+    }
+
+    #[pg_test]
+    fn promotions_test() {
+        let restaurant_identifier =
+            RestaurantId(Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap());
+        let menu_item_id =
+            MenuItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
+        let promotion_id =
+            PromotionId(Uuid::parse_str("7d1c2b3a-4e5f-4a6b-8c7d-9e0f1a2b3c4d").unwrap());
+        crate::handle(
+            Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
+                identifier: restaurant_identifier.clone(),
+                menu: RestaurantMenu {
+                    menu_id: MenuId(menu_item_id.0),
+                    items: vec![MenuItem {
+                        id: menu_item_id.clone(),
+                        name: MenuItemName("Item 1".to_string()),
+                        price: Money(1000),
+                    }],
+                    cuisine: RestaurantMenuCuisine::Vietnamese,
+                    currency: None,
+                },
+            }),
+            None,
+        )
+        .unwrap();
+        let place_order = |order_id: &str| {
+            crate::handle(
+                Command::PlaceOrder(PlaceOrder {
+                    identifier: restaurant_identifier.clone(),
+                    order_identifier: OrderId(Uuid::parse_str(order_id).unwrap()),
+                    line_items: vec![OrderLineItem {
+                        id: OrderLineItemId(menu_item_id.0),
+                        quantity: OrderLineItemQuantity(2),
+                        menu_item_id: menu_item_id.clone(),
+                        name: MenuItemName("Item 1".to_string()),
+                        price: None,
+                        restaurant_identifier: None,
+                    }],
+                    group_identifier: None,
+                    customer_identifier: None,
+                }),
+                None,
+            )
+            .unwrap()
+            .into_iter()
+            .find_map(|event| match event {
+                Event::OrderCreated(order_created) => Some(order_created.discounts),
+                _ => None,
+            })
+        };
+
+        // The promotion is applied to the restaurant by the saga
+        let events = crate::handle(
+            Command::CreatePromotion(CreatePromotion {
+                identifier: promotion_id.clone(),
+                restaurant_identifier: restaurant_identifier.clone(),
+                name: PromotionName("Happy hour".to_string()),
+                percentage: DiscountPercentage(20),
+                menu_item_ids: vec![menu_item_id.clone()],
+            }),
+            None,
+        )
+        .unwrap();
+        assert!(matches!(events[0], Event::PromotionCreated(_)));
+        assert!(matches!(events[1], Event::RestaurantPromotionApplied(_)));
+
+        // 20% of 2 x 10.00
+        assert_eq!(
+            Some(vec![AppliedDiscount {
+                promotion_id: promotion_id.clone(),
+                line_item_id: OrderLineItemId(menu_item_id.0),
+                amount: Money(400),
+            }]),
+            place_order("3a9e5c1d-2b4f-4e8a-9c6d-7f1e0b2a3c4d")
+        );
+        assert_eq!(
+            Some(1600),
+            Spi::get_one::<i64>("SELECT (data->>'discounted_total')::BIGINT FROM orders WHERE id = '3a9e5c1d-2b4f-4e8a-9c6d-7f1e0b2a3c4d'")
+                .unwrap()
+        );
+
+        // The expired promotion no longer discounts the orders
+        crate::handle(
+            Command::ExpirePromotion(ExpirePromotion {
+                identifier: promotion_id,
+            }),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            Some(vec![]),
+            place_order("4b0f6d2e-3c5a-4f9b-8d7e-0a2f1c3b4d5e")
+        );
+    }
+
+    /// Places the order of the menu item (in the quantity) at the seeded restaurant, after restocking its inventory with the menu item (in the stock).
+    fn place_order_with_stock(order_id: &str, quantity: u32, stock: u32) -> Vec<Event> {
+        let restaurant_identifier =
+            RestaurantId(Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap());
+        let menu_item_id =
+            MenuItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
+        crate::handle(
+            Command::RestockInventory(RestockInventory {
+                identifier: InventoryId::of(&restaurant_identifier),
+                restaurant_identifier: restaurant_identifier.clone(),
+                items: vec![StockItem {
+                    menu_item_id: menu_item_id.clone(),
+                    quantity: StockQuantity(stock),
+                }],
+            }),
+            None,
+        )
+        .unwrap();
+        crate::handle(
+            Command::PlaceOrder(PlaceOrder {
+                identifier: restaurant_identifier,
+                order_identifier: OrderId(Uuid::parse_str(order_id).unwrap()),
+                line_items: vec![OrderLineItem {
+                    id: OrderLineItemId(menu_item_id.0),
+                    quantity: OrderLineItemQuantity(quantity),
+                    menu_item_id,
+                    name: MenuItemName("Item 1".to_string()),
+                    price: None,
+                    restaurant_identifier: None,
+                }],
+                group_identifier: None,
+                customer_identifier: None,
+            }),
+            None,
+        )
+        .unwrap()
+    }
+
+    #[pg_test]
+    fn inventory_test() {
+        let restaurant_identifier =
+            RestaurantId(Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap());
+        let order_id = "5c1a7e3f-4d6b-4a0c-9e8f-1b2c3d4e5f60";
+        let reserved = |events: &[Event]| {
+            events.iter().find_map(|event| match event {
+                Event::StockReserved(event) => Some(event.items[0].quantity.0),
+                _ => None,
+            })
+        };
+
+        // The stock of the order is reserved by the saga
+        let events = place_order_with_stock(order_id, 2, 2);
+        assert!(matches!(events[0], Event::OrderPlaced(_)));
+        assert!(matches!(events[1], Event::OrderCreated(_)));
+        assert_eq!(Some(2), reserved(&events));
+
+        // The cancelled order releases its stock
+        let events = crate::handle(
+            Command::CancelRestaurantOrder(CancelRestaurantOrder {
+                identifier: restaurant_identifier,
+                order_identifier: OrderId(Uuid::parse_str(order_id).unwrap()),
+                rejected: false,
+            }),
+            None,
+        )
+        .unwrap();
+        assert!(matches!(events[0], Event::RestaurantOrderCancelled(_)));
+        assert!(matches!(
+            &events[1],
+            Event::OrderCancelled(event) if event.status == OrderStatus::Cancelled
+        ));
+        assert!(matches!(events[2], Event::StockReleased(_)));
+        assert_eq!(
+            Some("Cancelled".to_string()),
+            Spi::get_one::<String>(
+                "SELECT data->>'status' FROM orders WHERE id = '5c1a7e3f-4d6b-4a0c-9e8f-1b2c3d4e5f60'"
+            )
+            .unwrap()
+        );
+
+        // The released stock (and the restocked one) is available to the next order
+        let events = place_order_with_stock("6d2b8f4a-5e7c-4b1d-8f9a-2c3d4e5f6a71", 3, 1);
+        assert_eq!(Some(3), reserved(&events));
+    }
+
+    #[pg_test(error = "Failed to place the order. Insufficient stock of the menu item!")]
+    fn inventory_insufficient_stock_test() {
+        place_order_with_stock("7e3c9a5b-6f8d-4c2e-9a0b-3d4e5f6a7b82", 2, 1);
+    }
+
+    #[pg_test]
+    fn gift_cards_test() {
+        let restaurant_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737")
+                .unwrap()
+                .into_bytes(),
+        );
+        let order_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("3f7a1c5e-8b2d-4e6f-9a0c-1d3e5f7a9b04")
+                .unwrap()
+                .into_bytes(),
+        );
+        let gift_card_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("9e1b5d3f-2a4c-4f6e-8b0d-3c5e7f9a1b26")
+                .unwrap()
+                .into_bytes(),
+        );
+        let payments = || {
+            Spi::get_two::<i64, i64>(
+                "SELECT captured, refunded FROM payments WHERE order_id = '3f7a1c5e-8b2d-4e6f-9a0c-1d3e5f7a9b04'",
+            )
+            .unwrap()
+        };
+        crate::issue_gift_card(gift_card_id, 50).unwrap();
+        // The total of the order is 30
+        crate::place_order(
+            restaurant_id,
+            order_id,
+            pgrx::JsonB(serde_json::json!([{"id": "02f09a3f-1624-3b1d-8409-44eff7708210", "quantity": 3, "menu_item_id": "02f09a3f-1624-3b1d-8409-44eff7708210", "name": "supa"}])),
+            None,
+        )
+        .unwrap();
+
+        // The redeemed amount is captured as the payment of the order, by the saga
+        let events = crate::redeem_gift_card(gift_card_id, restaurant_id, order_id, 30).unwrap();
+        assert!(matches!(events[0], Event::GiftCardRedeemed(_)));
+        assert!(matches!(events[1], Event::PaymentCaptured(_)));
+        assert_eq!((Some(30), Some(0)), payments());
+
+        // The refunded amount is refunded from the payments of the order, back to the gift card (the balance is 30)
+        crate::mark_order_prepared(order_id).unwrap();
+        let events = crate::refund_gift_card(gift_card_id, restaurant_id, order_id, 10).unwrap();
+        assert!(matches!(events[0], Event::GiftCardRefunded(_)));
+        assert!(matches!(events[1], Event::PaymentRefunded(_)));
+        assert_eq!((Some(30), Some(10)), payments());
+    }
+
+    #[pg_test(error = "Failed to redeem the gift card. Insufficient balance!")]
+    fn gift_cards_insufficient_balance_test() {
+        let restaurant_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737")
+                .unwrap()
+                .into_bytes(),
+        );
+        let order_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("3f7a1c5e-8b2d-4e6f-9a0c-1d3e5f7a9b05")
+                .unwrap()
+                .into_bytes(),
+        );
+        let gift_card_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("9e1b5d3f-2a4c-4f6e-8b0d-3c5e7f9a1b27")
+                .unwrap()
+                .into_bytes(),
+        );
+        crate::issue_gift_card(gift_card_id, 20).unwrap();
+        crate::place_order(
+            restaurant_id,
+            order_id,
+            pgrx::JsonB(serde_json::json!([{"id": "02f09a3f-1624-3b1d-8409-44eff7708210", "quantity": 3, "menu_item_id": "02f09a3f-1624-3b1d-8409-44eff7708210", "name": "supa"}])),
+            None,
+        )
+        .unwrap();
+        crate::redeem_gift_card(gift_card_id, restaurant_id, order_id, 15).unwrap();
+        let _ = crate::redeem_gift_card(gift_card_id, restaurant_id, order_id, 10);
+    }
+
+    #[pg_test]
+    fn courier_locations_test() {
+        let courier_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("5c2e8a4f-1d3b-4e6a-9f0c-7b1d3e5f7a92")
+                .unwrap()
+                .into_bytes(),
+        );
+        let in_event_log = || {
+            Spi::get_one::<i64>(
+                "SELECT count(*) FROM events WHERE decider = 'Courier' AND decider_id = '5c2e8a4f-1d3b-4e6a-9f0c-7b1d3e5f7a92'",
+            )
+            .unwrap()
+        };
+
+        // The locations are written to the ring buffer (by default), and not appended to the event log
+        Spi::run("SET fmodel.courier_location_buffer_size = 2").unwrap();
+        assert!(crate::update_courier_location(courier_id, 52.52, 13.405)
+            .unwrap()
+            .is_empty());
+        assert!(crate::update_courier_location(courier_id, 52.521, 13.406)
+            .unwrap()
+            .is_empty());
+        assert!(crate::update_courier_location(courier_id, 52.522, 13.407)
+            .unwrap()
+            .is_empty());
+        assert_eq!(Some(0), in_event_log());
+        let track: Vec<_> = crate::courier_track(courier_id, 100).unwrap().collect();
+        assert_eq!(2, track.len());
+        assert_eq!((52.522, 13.407), (track[0].0, track[0].1));
+        assert_eq!((52.521, 13.406), (track[1].0, track[1].1));
 
+        // The locations are downsampled: at most one location per interval is appended to the event log
+        Spi::run("SET fmodel.courier_location_policy = 'downsample'").unwrap();
+        let events = crate::update_courier_location(courier_id, 52.523, 13.408).unwrap();
+        assert!(matches!(events[0], Event::CourierLocationUpdated(_)));
+        assert!(crate::update_courier_location(courier_id, 52.524, 13.409)
+            .unwrap()
+            .is_empty());
+        assert_eq!(Some(1), in_event_log());
+        Spi::run("SET fmodel.courier_location_interval = 0").unwrap();
         assert_eq!(
-            Some(restaurant_menu_changed_event.clone()),
-            crate::handle(change_restaurant_menu)
+            1,
+            crate::update_courier_location(courier_id, 52.525, 13.41)
                 .unwrap()
-                .into_iter()
-                .next()
+                .len()
         );
+        assert_eq!(Some(2), in_event_log());
+        Spi::run("RESET fmodel.courier_location_policy").unwrap();
+        Spi::run("RESET fmodel.courier_location_interval").unwrap();
+        Spi::run("RESET fmodel.courier_location_buffer_size").unwrap();
+        assert!(crate::update_courier_location(courier_id, 181.0, 13.41).is_err());
     }
 
-    #[pg_test(error = "Failed to change the menu. Restaurant does not exist!")]
-    fn change_menu_error_test() {
+    #[pg_test]
+    fn order_groups_test() {
         let restaurant_identifier =
-            RestaurantId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708208").unwrap());
-        let menu_item_id =
-            MenuItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
-        let menu_id = MenuId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
-        let menu_items = vec![MenuItem {
-            id: menu_item_id,
+            RestaurantId(Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap());
+        let other_restaurant_identifier =
+            RestaurantId(Uuid::parse_str("9a5c3e1f-7b2d-4f6a-8c0e-1d2f3a4b5c6d").unwrap());
+        let group_identifier =
+            OrderGroupId(Uuid::parse_str("4b8e2d6a-1c3f-4a5b-9d7e-0f1a2b3c4d5e").unwrap());
+        crate::handle(
+            Command::CreateRestaurant(CreateRestaurant {
+                identifier: other_restaurant_identifier.clone(),
+                name: RestaurantName("Trattoria Roma".to_string()),
+                menu: RestaurantMenu {
+                    menu_id: MenuId(
+                        Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                    ),
+                    items: vec![],
+                    cuisine: RestaurantMenuCuisine::Italian,
+                    currency: None,
+                },
+            }),
+            None,
+        )
+        .unwrap();
+        let line_item = |id: &str, restaurant_identifier: Option<RestaurantId>| OrderLineItem {
+            id: OrderLineItemId(Uuid::parse_str(id).unwrap()),
+            quantity: OrderLineItemQuantity(1),
+            menu_item_id: MenuItemId(Uuid::parse_str(id).unwrap()),
             name: MenuItemName("Item 1".to_string()),
-            price: Money(100u64),
-        }];
+            price: None,
+            restaurant_identifier,
+        };
 
-        let change_restaurant_menu = Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
-            identifier: restaurant_identifier.clone(),
-            menu: RestaurantMenu {
-                menu_id: menu_id.clone(),
-                items: menu_items.clone(),
-                cuisine: RestaurantMenuCuisine::Vietnamese,
-            },
-        });
+        // The order spanning both restaurants is split into the sub orders, created with the group
+        let events = crate::handle(
+            Command::PlaceOrder(PlaceOrder {
+                identifier: restaurant_identifier.clone(),
+                order_identifier: OrderId(group_identifier.0),
+                line_items: vec![
+                    line_item("5d9f3e7b-2d4a-4b6c-8e8f-1a2b3c4d5e6f", None),
+                    line_item(
+                        "6e0a4f8c-3e5b-4c7d-9f9a-2b3c4d5e6f7a",
+                        Some(other_restaurant_identifier.clone()),
+                    ),
+                ],
+                group_identifier: None,
+                customer_identifier: None,
+            }),
+            None,
+        )
+        .unwrap();
+        assert!(matches!(
+            &events[0],
+            Event::RestaurantOrderSplit(event) if event.orders.len() == 2
+        ));
+        assert_eq!(
+            2,
+            events
+                .iter()
+                .filter(|event| matches!(
+                    event,
+                    Event::OrderCreated(event) if event.group_identifier == Some(group_identifier.clone())
+                ))
+                .count()
+        );
+        let group = || {
+            Spi::get_two::<String, i32>(
+                "SELECT data->>'status', jsonb_array_length(data->'orders')::INT FROM order_groups WHERE id = '4b8e2d6a-1c3f-4a5b-9d7e-0f1a2b3c4d5e'",
+            )
+            .unwrap()
+        };
+        assert_eq!((Some("Created".to_string()), Some(2)), group());
 
-        let _ = crate::handle(change_restaurant_menu);
+        // The group is created while any of its sub orders is created
+        crate::handle(
+            Command::MarkOrderAsPrepared(MarkOrderAsPrepared {
+                identifier: group_identifier.order_of(&restaurant_identifier),
+            }),
+            None,
+        )
+        .unwrap();
+        assert_eq!((Some("Created".to_string()), Some(2)), group());
+
+        // ... and prepared once all of them are closed, and any of them is prepared
+        crate::handle(
+            Command::CancelRestaurantOrder(CancelRestaurantOrder {
+                identifier: other_restaurant_identifier.clone(),
+                order_identifier: group_identifier.order_of(&other_restaurant_identifier),
+                rejected: true,
+            }),
+            None,
+        )
+        .unwrap();
+        assert_eq!((Some("Prepared".to_string()), Some(2)), group());
     }
 
     #[pg_test]
-    fn place_order_test() {
+    fn notifications_outbox_test() {
         let restaurant_identifier =
             RestaurantId(Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap());
-        let order_identifier =
-            OrderId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
         let menu_item_id =
             MenuItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
-        let line_items = vec![OrderLineItem {
-            id: OrderLineItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap()),
-            quantity: OrderLineItemQuantity(1),
-            menu_item_id: menu_item_id.clone(),
-            name: MenuItemName("Item 1".to_string()),
-        }];
-
-        let place_order = Command::PlaceOrder(PlaceOrder {
-            identifier: restaurant_identifier.clone(),
-            order_identifier: order_identifier.clone(),
-            line_items: line_items.clone(),
-        });
+        let place_order = |order_id: &str, customer_identifier: Option<CustomerId>| {
+            crate::handle(
+                Command::PlaceOrder(PlaceOrder {
+                    identifier: restaurant_identifier.clone(),
+                    order_identifier: OrderId(Uuid::parse_str(order_id).unwrap()),
+                    line_items: vec![OrderLineItem {
+                        id: OrderLineItemId(menu_item_id.0),
+                        quantity: OrderLineItemQuantity(1),
+                        menu_item_id: menu_item_id.clone(),
+                        name: MenuItemName("supa".to_string()),
+                        price: None,
+                        restaurant_identifier: None,
+                    }],
+                    group_identifier: None,
+                    customer_identifier,
+                }),
+                None,
+            )
+            .unwrap();
+        };
+        let templates = || {
+            Spi::get_one::<String>(
+                "SELECT string_agg(template, ',' ORDER BY id) FROM notifications_outbox WHERE customer_id = '8c2e4a6b-0d1f-4e3a-9b5c-7d9e1f3a5b7c' AND relayed_at IS NULL",
+            )
+            .unwrap()
+        };
 
-        let order_placed_event = Event::OrderPlaced(OrderPlaced {
-            identifier: restaurant_identifier.clone(),
-            order_identifier: order_identifier.clone(),
-            line_items: line_items.clone(),
-            r#final: false,
-        });
+        // The customer is notified that the order is accepted (created), and prepared
+        place_order(
+            "5c9e3a7d-1f2b-4d6c-8e0a-4b6c8d0e2f3a",
+            Some(CustomerId(
+                Uuid::parse_str("8c2e4a6b-0d1f-4e3a-9b5c-7d9e1f3a5b7c").unwrap(),
+            )),
+        );
+        assert_eq!(Some("OrderAccepted".to_string()), templates());
+        crate::handle(
+            Command::MarkOrderAsPrepared(MarkOrderAsPrepared {
+                identifier: OrderId(
+                    Uuid::parse_str("5c9e3a7d-1f2b-4d6c-8e0a-4b6c8d0e2f3a").unwrap(),
+                ),
+            }),
+            None,
+        )
+        .unwrap();
+        assert_eq!(Some("OrderAccepted,OrderPrepared".to_string()), templates());
 
-        let order_created_event = Event::OrderCreated(OrderCreated {
-            identifier: order_identifier.clone(),
-            restaurant_identifier: restaurant_identifier.clone(),
-            status: OrderStatus::Created,
-            line_items: line_items.clone(),
-            r#final: false,
-        });
+        // The anonymous orders are not notified
+        place_order("6d0f4b8e-2a3c-4e7d-9f1b-5c7d9e1f3a4b", None);
+        assert_eq!(
+            Some(2),
+            Spi::get_one::<i64>("SELECT count(*) FROM notifications_outbox").unwrap()
+        );
 
-        let mut result = crate::handle(place_order).unwrap().into_iter();
-        assert_eq!(Some(order_placed_event), result.next(),);
-        assert_eq!(Some(order_created_event), result.next(),);
+        // The relay claims the notifications once
+        assert_eq!(
+            Some(2),
+            Spi::get_one::<i64>("SELECT count(*) FROM claim_notifications(10)").unwrap()
+        );
+        assert_eq!(
+            Some(0),
+            Spi::get_one::<i64>("SELECT count(*) FROM claim_notifications(10)").unwrap()
+        );
     }
 
-    #[pg_test(error = "Failed to place the order. Restaurant does not exist!")]
-    fn place_order_error_test() {
+    #[pg_test]
+    fn kitchen_queue_test() {
         let restaurant_identifier =
-            RestaurantId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708208").unwrap());
-        let order_identifier =
-            OrderId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
+            RestaurantId(Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap());
         let menu_item_id =
             MenuItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
-        let line_items = vec![OrderLineItem {
-            id: OrderLineItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap()),
-            quantity: OrderLineItemQuantity(1),
-            menu_item_id: menu_item_id.clone(),
-            name: MenuItemName("Item 1".to_string()),
-        }];
+        let place_order = |order_id: &str| {
+            crate::handle(
+                Command::PlaceOrder(PlaceOrder {
+                    identifier: restaurant_identifier.clone(),
+                    order_identifier: OrderId(Uuid::parse_str(order_id).unwrap()),
+                    line_items: vec![OrderLineItem {
+                        id: OrderLineItemId(menu_item_id.0),
+                        quantity: OrderLineItemQuantity(1),
+                        menu_item_id: menu_item_id.clone(),
+                        name: MenuItemName("supa".to_string()),
+                        price: None,
+                        restaurant_identifier: None,
+                    }],
+                    group_identifier: None,
+                    customer_identifier: None,
+                }),
+                None,
+            )
+            .unwrap();
+        };
+        let next_orders = || {
+            Spi::get_two::<String, bool>(
+                "SELECT string_agg(order_id::TEXT, ',' ORDER BY placed_at), bool_and(elapsed >= INTERVAL '0') FROM next_orders('e48d4d9e-403e-453f-b1ba-328e0ce23737')",
+            )
+            .unwrap()
+        };
 
-        let place_order = Command::PlaceOrder(PlaceOrder {
-            identifier: restaurant_identifier.clone(),
-            order_identifier: order_identifier.clone(),
-            line_items: line_items.clone(),
+        // The open orders are queued in the order they were placed
+        place_order("3a7c1e5b-9d2f-4b6a-8c0e-2f4a6b8c0d1e");
+        place_order("4b8d2f6c-0e3a-4c7b-9d1f-3a5b7c9d1e2f");
+        assert_eq!(
+            (
+                Some(
+                    "3a7c1e5b-9d2f-4b6a-8c0e-2f4a6b8c0d1e,4b8d2f6c-0e3a-4c7b-9d1f-3a5b7c9d1e2f"
+                        .to_string()
+                ),
+                Some(true)
+            ),
+            next_orders()
+        );
+
+        // The prepared order leaves the queue
+        crate::handle(
+            Command::MarkOrderAsPrepared(MarkOrderAsPrepared {
+                identifier: OrderId(
+                    Uuid::parse_str("3a7c1e5b-9d2f-4b6a-8c0e-2f4a6b8c0d1e").unwrap(),
+                ),
+            }),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            (
+                Some("4b8d2f6c-0e3a-4c7b-9d1f-3a5b7c9d1e2f".to_string()),
+                Some(true)
+            ),
+            next_orders()
+        );
+    }
+
+    #[pg_test(error = "Failed to create the Order. No exchange rate to the reference currency!")]
+    fn reference_total_unknown_rate_test() {
+        // There is no RSD rate in the `exchange_rates` table
+        let menu_item_id =
+            MenuItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
+        let create_order = Command::CreateOrder(CreateOrder {
+            identifier: OrderId(Uuid::parse_str("1c4b0a3e-8f2d-4e6a-9b7c-5d3e2f1a0b9c").unwrap()),
+            restaurant_identifier: RestaurantId(
+                Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap(),
+            ),
+            line_items: vec![OrderLineItem {
+                id: OrderLineItemId(menu_item_id.0),
+                quantity: OrderLineItemQuantity(1),
+                menu_item_id,
+                name: MenuItemName("Item 1".to_string()),
+                price: Some(Money(250)),
+                restaurant_identifier: None,
+            }],
+            currency: Some(Currency("RSD".to_string())),
+            discounts: vec![],
+            group_identifier: None,
+            customer_identifier: None,
         });
+        crate::handle(create_order, None).unwrap();
+    }
+
+    #[pg_test]
+    fn event_stream_view_test() {
+        assert_eq!(
+            Some(("RestaurantCreated".to_string(), 1)),
+            Spi::get_two::<String, i64>(
+                "SELECT event_type, sequence_number FROM fmodel_event_stream WHERE restaurant_id = 'e48d4d9e-403e-453f-b1ba-328e0ce23737'"
+            )
+            .map(|(event_type, sequence_number)| event_type.zip(sequence_number))
+            .unwrap()
+        );
+    }
+
+    #[pg_test]
+    fn max_events_per_command_test() {
+        let place_order = || {
+            Command::PlaceOrder(PlaceOrder {
+                identifier: RestaurantId(
+                    Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap(),
+                ),
+                order_identifier: OrderId(
+                    Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                ),
+                line_items: vec![],
+                group_identifier: None,
+                customer_identifier: None,
+            })
+        };
 
-        let _ = crate::handle(place_order);
+        // The order placed at the restaurant, and the order created by the saga
+        Spi::run("SET fmodel.max_events_per_command = 1").unwrap();
+        assert!(crate::handle(place_order(), None).is_err());
+        Spi::run("SET fmodel.max_events_per_command = 2").unwrap();
+        assert_eq!(2, crate::handle(place_order(), None).unwrap().len());
     }
 
     #[pg_test]
-    fn create_restaurant_and_place_order_test() {
+    fn sql_friendly_commands_test() {
+        let restaurant_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("8c0d1f8e-96b6-4bd4-9b0f-0f6a3b1f6c01")
+                .unwrap()
+                .into_bytes(),
+        );
+        let order_id = pgrx::Uuid::from_bytes(
+            Uuid::parse_str("8c0d1f8e-96b6-4bd4-9b0f-0f6a3b1f6c02")
+                .unwrap()
+                .into_bytes(),
+        );
+        let menu = serde_json::json!({"menu_id": "02f09a3f-1624-3b1d-8409-44eff7708210", "items": [{"id": "02f09a3f-1624-3b1d-8409-44eff7708210", "name": "supa", "price": 10}], "cuisine": "Vietnamese"});
+        let line_items = serde_json::json!([{"id": "02f09a3f-1624-3b1d-8409-44eff7708210", "quantity": 1, "menu_item_id": "02f09a3f-1624-3b1d-8409-44eff7708210", "name": "supa"}]);
+
+        assert_eq!(
+            1,
+            crate::create_restaurant(restaurant_id, "Joe", pgrx::JsonB(menu))
+                .unwrap()
+                .len()
+        );
+        // The order is placed, and created by the saga
+        assert_eq!(
+            2,
+            crate::place_order(restaurant_id, order_id, pgrx::JsonB(line_items), None)
+                .unwrap()
+                .len()
+        );
+        // The order is prepared, and closed at the restaurant by the saga
+        assert_eq!(2, crate::mark_order_prepared(order_id).unwrap().len());
+        // Malformed menu
+        assert!(
+            crate::change_restaurant_menu(restaurant_id, pgrx::JsonB(serde_json::json!({})))
+                .is_err()
+        );
+    }
+
+    #[pg_test]
+    fn sequence_number_test() {
         let restaurant_identifier =
-            RestaurantId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708208").unwrap());
+            RestaurantId(Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap());
+        let change_restaurant_menu = || {
+            Command::ChangeRestaurantMenu(ChangeRestaurantMenu {
+                identifier: restaurant_identifier.clone(),
+                menu: RestaurantMenu {
+                    menu_id: MenuId(
+                        Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                    ),
+                    items: vec![],
+                    cuisine: RestaurantMenuCuisine::Vietnamese,
+                    currency: None,
+                },
+            })
+        };
+
+        let version =
+            crate::stream_version(pgrx::Uuid::from_bytes(restaurant_identifier.0.into_bytes()))
+                .unwrap()
+                .unwrap();
+        let (_, _, _, sequence_number, _) =
+            crate::handle_with_expected_version(change_restaurant_menu(), version, None)
+                .unwrap()
+                .last()
+                .unwrap();
+        assert_eq!(version + 1, sequence_number);
+        // The stream has moved on, the client's version is stale
+        assert!(
+            crate::handle_with_expected_version(change_restaurant_menu(), version, None).is_err()
+        );
+    }
+
+    #[pg_test]
+    fn merge_and_split_streams_test() {
+        let source = Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap();
+        let target = Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708208").unwrap();
         let order_identifier =
             OrderId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
-        let restaurant_name = RestaurantName("Test Restaurant".to_string());
-        let menu_item_id =
-            MenuItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
-        let menu_id = MenuId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap());
-        let menu_items = vec![MenuItem {
-            id: menu_item_id.clone(),
-            name: MenuItemName("Item 1".to_string()),
-            price: Money(100u64),
-        }];
-        let line_items = vec![OrderLineItem {
-            id: OrderLineItemId(Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap()),
-            quantity: OrderLineItemQuantity(1),
-            menu_item_id: menu_item_id.clone(),
-            name: MenuItemName("Item 1".to_string()),
-        }];
+        crate::handle(
+            Command::CreateRestaurant(CreateRestaurant {
+                identifier: RestaurantId(target),
+                name: RestaurantName("Pljeska (duplicate)".to_string()),
+                menu: RestaurantMenu {
+                    menu_id: MenuId(
+                        Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                    ),
+                    items: vec![],
+                    cuisine: RestaurantMenuCuisine::Other,
+                    currency: None,
+                },
+            }),
+            None,
+        )
+        .unwrap();
+        crate::handle(
+            Command::PlaceOrder(PlaceOrder {
+                identifier: RestaurantId(source),
+                order_identifier: order_identifier.clone(),
+                line_items: vec![],
+                group_identifier: None,
+                customer_identifier: None,
+            }),
+            None,
+        )
+        .unwrap();
 
-        let create_restaurant_command = Command::CreateRestaurant(CreateRestaurant {
-            identifier: restaurant_identifier.clone(),
-            name: restaurant_name.clone(),
-            menu: RestaurantMenu {
-                menu_id: menu_id.clone(),
-                items: menu_items.clone(),
-                cuisine: RestaurantMenuCuisine::Vietnamese,
-            },
-        });
+        // The order placed by mistake follows the target restaurant
+        crate::split_stream(
+            pgrx::Uuid::from_bytes(source.into_bytes()),
+            pgrx::Uuid::from_bytes(target.into_bytes()),
+            vec![pgrx::Uuid::from_bytes(order_identifier.0.into_bytes())],
+        )
+        .unwrap();
+        assert_eq!(
+            Some(3),
+            Spi::get_one::<i64>(
+                "SELECT COUNT(DISTINCT event) FROM events WHERE event IN ('RestaurantOrdersMigrated', 'RestaurantOrdersReceived', 'OrderMigrated')"
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            Some(target.to_string()),
+            Spi::get_one::<String>("SELECT data->>'restaurant_identifier' FROM orders").unwrap()
+        );
 
-        let place_order = Command::PlaceOrder(PlaceOrder {
-            identifier: restaurant_identifier.clone(),
-            order_identifier: order_identifier.clone(),
-            line_items: line_items.clone(),
-        });
+        // The source restaurant is retired
+        crate::merge_streams(
+            pgrx::Uuid::from_bytes(source.into_bytes()),
+            pgrx::Uuid::from_bytes(target.into_bytes()),
+        )
+        .unwrap();
+        assert_eq!(
+            Some(target.to_string()),
+            Spi::get_one::<String>(
+                "SELECT data->>'merged_into' FROM restaurants WHERE id = 'e48d4d9e-403e-453f-b1ba-328e0ce23737'"
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            Some(0),
+            Spi::get_one::<i64>(
+                "SELECT COUNT(*) FROM restaurants_search WHERE id = 'e48d4d9e-403e-453f-b1ba-328e0ce23737'"
+            )
+            .unwrap()
+        );
+        assert!(crate::handle(
+            Command::PlaceOrder(PlaceOrder {
+                identifier: RestaurantId(source),
+                order_identifier: OrderId(
+                    Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708211").unwrap()
+                ),
+                line_items: vec![],
+                group_identifier: None,
+                customer_identifier: None,
+            }),
+            None,
+        )
+        .is_err());
+    }
 
-        let restaurant_created_event = Event::RestaurantCreated(RestaurantCreated {
-            identifier: restaurant_identifier.clone(),
-            name: restaurant_name.clone(),
-            menu: RestaurantMenu {
-                menu_id: menu_id.clone(),
-                items: menu_items.clone(),
-                cuisine: RestaurantMenuCuisine::Vietnamese,
-            },
-            r#final: false,
-        });
+    #[pg_test]
+    fn event_schemas_test() {
+        let schemas = crate::infrastructure::event_schema::event_schemas();
+        assert_eq!(31, schemas.len());
+        let order_placed = schemas
+            .iter()
+            .find(|schema| schema.event == "OrderPlaced")
+            .unwrap();
+        // The `type` tag is checked separately, and the `currency` is optional (serde default)
+        assert_eq!(
+            vec!["identifier", "order_identifier", "line_items", "final"],
+            order_placed.required_fields
+        );
+    }
 
-        let order_placed_event = Event::OrderPlaced(OrderPlaced {
-            identifier: restaurant_identifier.clone(),
-            order_identifier: order_identifier.clone(),
-            line_items: line_items.clone(),
-            r#final: false,
-        });
+    #[pg_test(error = "The RestaurantCreated payload is missing the required fields: name, menu")]
+    fn event_schema_check_test() {
+        // The payload with all the required fields (the optional `menu_version` omitted)
+        Spi::run(
+            r#"INSERT INTO events (event, event_id, decider, decider_id, data, command_id, previous_id, final)
+               VALUES ('RestaurantMenuChanged', gen_random_uuid(), 'Restaurant', 'e48d4d9e-403e-453f-b1ba-328e0ce23737',
+                       '{"type": "RestaurantMenuChanged", "identifier": "e48d4d9e-403e-453f-b1ba-328e0ce23737", "menu": {"menu_id": "02f09a3f-1624-3b1d-8409-44eff7708210", "items": [], "cuisine": "Greek"}, "final": false}',
+                       NULL, (SELECT event_id FROM events WHERE decider_id = 'e48d4d9e-403e-453f-b1ba-328e0ce23737' ORDER BY "offset" DESC LIMIT 1), FALSE)"#,
+        )
+        .unwrap();
+        // The payload missing the required fields is rejected
+        Spi::run(
+            r#"INSERT INTO events (event, event_id, decider, decider_id, data, command_id, previous_id, final)
+               VALUES ('RestaurantCreated', gen_random_uuid(), 'Restaurant', '1e4d3c2b-1a0f-4e9d-9c8b-7a6f5e4d3c2b',
+                       '{"type": "RestaurantCreated", "identifier": "1e4d3c2b-1a0f-4e9d-9c8b-7a6f5e4d3c2b", "final": false}',
+                       NULL, NULL, FALSE)"#,
+        )
+        .unwrap();
+    }
 
-        let order_created_event = Event::OrderCreated(OrderCreated {
-            identifier: order_identifier.clone(),
-            restaurant_identifier: restaurant_identifier.clone(),
-            status: OrderStatus::Created,
-            line_items: line_items.clone(),
-            r#final: false,
-        });
+    #[pg_test]
+    fn decide_against_state_test() {
+        let place_order = || {
+            Command::PlaceOrder(PlaceOrder {
+                identifier: RestaurantId(
+                    Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap(),
+                ),
+                order_identifier: OrderId(
+                    Uuid::parse_str("02f09a3f-1624-3b1d-8409-44eff7708210").unwrap(),
+                ),
+                line_items: vec![],
+                group_identifier: None,
+                customer_identifier: None,
+            })
+        };
+        let pgrx::JsonB(exported) = crate::export_stream(pgrx::Uuid::from_bytes(
+            *Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737")
+                .unwrap()
+                .as_bytes(),
+        ))
+        .unwrap();
+        let count_events = || {
+            Spi::get_one::<i64>("SELECT count(*) FROM events")
+                .unwrap()
+                .unwrap()
+        };
+        let events_before = count_events();
+
+        let decide = |state: Option<serde_json::Value>| {
+            crate::decide_against_state(place_order(), state.map(pgrx::JsonB))
+                .unwrap()
+                .next()
+                .unwrap()
+        };
+
+        // The order can be placed at the (exported) restaurant
+        let (events, rejection) = decide(Some(exported["state"].clone()));
+        assert!(matches!(events.as_slice(), [Event::OrderPlaced(_)]));
+        assert_eq!(None, rejection);
+        // The restaurant does not exist (yet) in the hypothetical state
+        let (events, rejection) = decide(None);
+        assert!(events.is_empty());
+        assert_eq!(
+            Some("Failed to place the order. Restaurant does not exist!".to_string()),
+            rejection
+        );
+        // The state must be the state of the decider of the command
+        assert!(crate::decide_against_state(
+            place_order(),
+            Some(pgrx::JsonB(serde_json::json!({"unknown": true})))
+        )
+        .is_err());
+        // Nothing is persisted
+        assert_eq!(events_before, count_events());
+    }
+
+    #[pg_test]
+    fn append_correction_test() {
+        let restaurant_id = pgrx::Uuid::from_bytes(
+            *Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737")
+                .unwrap()
+                .as_bytes(),
+        );
+        let correction = || {
+            pgrx::JsonB(serde_json::json!({
+                "type": "RestaurantMenuChanged",
+                "identifier": "e48d4d9e-403e-453f-b1ba-328e0ce23737",
+                "menu": {"menu_id": "02f09a3f-1624-3b1d-8409-44eff7708210", "items": [], "cuisine": "Greek"},
+                "final": false
+            }))
+        };
+        // The payload is validated against the event schema, and must belong to the stream
+        assert!(crate::append_correction(
+            restaurant_id,
+            pgrx::JsonB(serde_json::json!({"type": "RestaurantMenuChanged"})),
+            "TICKET-42"
+        )
+        .is_err());
+        assert!(crate::append_correction(
+            pgrx::Uuid::from_bytes(*Uuid::new_v4().as_bytes()),
+            correction(),
+            "TICKET-42"
+        )
+        .is_err());
+        assert!(crate::append_correction(restaurant_id, correction(), " ").is_err());
+
+        let event = crate::append_correction(restaurant_id, correction(), "TICKET-42").unwrap();
+        assert!(matches!(event, Event::RestaurantMenuChanged(_)));
+        assert_eq!(
+            Ok(Some(true)),
+            Spi::get_one::<bool>(
+                "SELECT 'correction' = ANY(tags) FROM events ORDER BY \"offset\" DESC LIMIT 1"
+            )
+        );
+        assert_eq!(
+            Ok(Some("TICKET-42".to_string())),
+            Spi::get_one::<String>(
+                "SELECT reason FROM event_corrections JOIN events USING (event_id) WHERE corrected_by = session_user"
+            )
+        );
+        // The projection follows the correction
+        assert_eq!(
+            Ok(Some("Greek".to_string())),
+            Spi::get_one::<String>(
+                "SELECT data->'menu'->>'cuisine' FROM restaurants WHERE id = 'e48d4d9e-403e-453f-b1ba-328e0ce23737'"
+            )
+        );
+    }
 
-        let mut result = crate::handle_all(vec![create_restaurant_command, place_order])
+    #[pg_test]
+    fn get_events_by_tag_test() {
+        let restaurant_id = Uuid::parse_str("e48d4d9e-403e-453f-b1ba-328e0ce23737").unwrap();
+        // The tags of the session, and the tags attached by the domain (the cuisine)
+        Spi::run("SET LOCAL fmodel.event_tags = 'promo:summer24,vip-customer'").unwrap();
+        crate::change_restaurant_menu(
+            pgrx::Uuid::from_bytes(*restaurant_id.as_bytes()),
+            pgrx::JsonB(serde_json::json!({"menu_id": "02f09a3f-1624-3b1d-8409-44eff7708210", "items": [], "cuisine": "Vietnamese"})),
+        )
+        .unwrap();
+        assert_eq!(
+            Ok(Some(vec![
+                Some("cuisine:vietnamese".to_string()),
+                Some("promo:summer24".to_string()),
+                Some("vip-customer".to_string()),
+            ])),
+            Spi::get_one::<Vec<Option<String>>>(
+                "SELECT tags FROM events ORDER BY \"offset\" DESC LIMIT 1"
+            )
+        );
+        let tagged: Vec<_> = crate::get_events_by_tag("promo:summer24", 0, 100)
             .unwrap()
-            .into_iter();
-        assert_eq!(Some(restaurant_created_event), result.next(),);
-        assert_eq!(Some(order_placed_event), result.next(),);
-        assert_eq!(Some(order_created_event), result.next(),);
+            .collect();
+        assert_eq!(1, tagged.len());
+        assert_eq!(2, tagged[0].3);
+        assert!(matches!(tagged[0].0, Event::RestaurantMenuChanged(_)));
+        // Paged by the offset
+        assert_eq!(
+            0,
+            crate::get_events_by_tag("promo:summer24", tagged[0].2, 100)
+                .unwrap()
+                .count()
+        );
+        assert_eq!(
+            1,
+            crate::get_events_by_tag("cuisine:vietnamese", 0, 100)
+                .unwrap()
+                .count()
+        );
     }
 }
 